@@ -0,0 +1,80 @@
+// Copyright 2020 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Standard Exonum CLI command used to derive additional keypairs from the master key.
+
+use anyhow::{Context, Error};
+use exonum::keys::{read_derived_keys_from_file, DerivationPath};
+use serde_derive::{Deserialize, Serialize};
+use structopt::StructOpt;
+
+use std::{collections::BTreeMap, path::PathBuf};
+
+use crate::{
+    command::{ExonumCommand, StandardResult},
+    password::{PassInputMethod, PassphraseUsage},
+};
+
+/// Derive additional keypairs from the master key and print their public keys.
+///
+/// Keypairs are derived deterministically at the specified paths, so the same keypair
+/// can be recovered from the master key at any time. Public keys are printed to stdout
+/// as a TOML table mapping derivation paths to hex-encoded keys.
+#[derive(StructOpt, Debug, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct DeriveKeys {
+    /// Path to the master key file.
+    #[structopt(long, short = "k")]
+    pub master_key_path: PathBuf,
+    /// Passphrase entry method for master key.
+    ///
+    /// Possible values are: `stdin`, `env{:ENV_VAR_NAME}`, `pass:PASSWORD`.
+    /// Default Value is `stdin`.
+    /// If `ENV_VAR_NAME` is not specified `$EXONUM_MASTER_PASS` is used
+    /// by default.
+    #[structopt(long)]
+    pub master_key_pass: Option<PassInputMethod>,
+    /// Derivation paths of keypairs, e.g., `services/timestamping/operator`.
+    #[structopt(required = true)]
+    pub paths: Vec<DerivationPath>,
+}
+
+impl ExonumCommand for DeriveKeys {
+    fn execute(self) -> Result<StandardResult, Error> {
+        let master_passphrase = self
+            .master_key_pass
+            .unwrap_or_default()
+            .get_passphrase(PassphraseUsage::Using)?;
+        let keys = read_derived_keys_from_file(
+            &self.master_key_path,
+            master_passphrase.as_bytes(),
+            &self.paths,
+        )
+        .context("Could not read master key file")?;
+
+        let public_keys: Vec<_> = self
+            .paths
+            .into_iter()
+            .zip(keys)
+            .map(|(path, keypair)| (path, keypair.public_key()))
+            .collect();
+        let output: BTreeMap<_, _> = public_keys
+            .iter()
+            .map(|(path, public_key)| (path.to_string(), public_key.to_hex()))
+            .collect();
+        print!("{}", toml::to_string(&output)?);
+
+        Ok(StandardResult::DeriveKeys { public_keys })
+    }
+}
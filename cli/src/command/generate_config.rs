@@ -17,11 +17,13 @@
 
 use anyhow::{bail, Error};
 use exonum::{
-    blockchain::ValidatorKeys,
+    blockchain::{ValidatorBlsKey, ValidatorKeys},
     keys::{generate_keys, Keys},
-    merkledb::DbOptions,
+    merkledb::{DbOptions, EncryptionOptions},
+};
+use exonum_node::{
+    ConnectListConfig, MemoryPoolConfig, NetworkConfiguration, NodeApiConfig, RetentionPolicy,
 };
-use exonum_node::{ConnectListConfig, MemoryPoolConfig, NetworkConfiguration, NodeApiConfig};
 use serde_derive::{Deserialize, Serialize};
 use structopt::StructOpt;
 
@@ -92,6 +94,19 @@ pub struct GenerateConfig {
     /// Path to the master key file. If empty, file will be placed to <output_dir>.
     #[structopt(long)]
     pub master_key_path: Option<PathBuf>,
+
+    /// Include the BLS key of the node into the public configuration. The key is derived
+    /// from the master key and is used to sign commit certificates for blocks.
+    #[structopt(long)]
+    #[serde(default)]
+    pub bls_key: bool,
+
+    /// Encrypt the node database at rest with the key derived from the master key.
+    /// The master key passphrase is then required to run the node and to perform
+    /// maintenance actions.
+    #[structopt(long)]
+    #[serde(default)]
+    pub encrypt_storage: bool,
 }
 
 impl GenerateConfig {
@@ -150,7 +165,11 @@ impl ExonumCommand for GenerateConfig {
             )
         }?;
 
-        let validator_keys = ValidatorKeys::new(keys.consensus_pk(), keys.service_pk());
+        let mut validator_keys = ValidatorKeys::new(keys.consensus_pk(), keys.service_pk());
+        if self.bls_key {
+            let bls_key = ValidatorBlsKey::from_keypair(&keys.bls_keypair());
+            validator_keys = validator_keys.with_bls_key(bls_key);
+        }
         let public_config = NodePublicConfig {
             validator_keys: Some(validator_keys),
             address: Some(self.peer_address.to_string()),
@@ -159,15 +178,23 @@ impl ExonumCommand for GenerateConfig {
         // Save public config separately.
         save_config_file(&public_config, &public_config_path)?;
 
+        let mut database = DbOptions::default();
+        if self.encrypt_storage {
+            database.encryption = Some(EncryptionOptions::default());
+        }
         let private_config = NodePrivateConfig {
             listen_address,
             external_address: self.peer_address.to_string(),
             master_key_path: master_key_path.clone(),
+            pending_master_key_path: None,
             api: NodeApiConfig::default(),
             network: NetworkConfiguration::default(),
             mempool: MemoryPoolConfig::default(),
-            database: DbOptions::default(),
+            database,
             thread_pool_size: None,
+            retention: RetentionPolicy::default(),
+            state_sync: false,
+            remote_signer: None,
             connect_list: ConnectListConfig::default(),
             consensus_public_key: keys.consensus_pk(),
         };
@@ -187,7 +214,7 @@ fn get_master_key_path(path: Option<PathBuf>) -> Result<PathBuf, Error> {
     Ok(path.join(MASTER_KEY_FILE_NAME))
 }
 
-fn create_keys_and_files(
+pub(super) fn create_keys_and_files(
     secret_key_path: impl AsRef<Path>,
     passphrase: impl AsRef<[u8]>,
 ) -> anyhow::Result<Keys> {
@@ -14,19 +14,32 @@
 
 //! Standard Exonum CLI command used to perform different maintenance actions.
 
-use anyhow::Error;
-use exonum::merkledb::{migration::rollback_migration, Database, RocksDB};
-use exonum::runtime::remove_local_migration_result;
+use anyhow::{ensure, format_err, Error};
+use exonum::{
+    blockchain::{ApiSender, Blockchain, Schema},
+    crypto::{KeyPair, PublicKey},
+    helpers::Height,
+    merkledb::{migration::rollback_migration, Database, DatabaseExt, RocksDB},
+    runtime::remove_local_migration_result,
+};
 use exonum_node::helpers::clear_consensus_messages_cache;
+use log::warn;
 use serde_derive::{Deserialize, Serialize};
 use structopt::StructOpt;
 
-use std::path::{Path, PathBuf};
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use crate::{
     command::{ExonumCommand, StandardResult},
     config::NodeConfig,
+    database::{open_database, read_storage_key},
     io::load_config_file,
+    password::{PassInputMethod, PassphraseUsage},
 };
 
 /// Perform different maintenance actions.
@@ -41,6 +54,17 @@ pub struct Maintenance {
     #[structopt(long, short = "d")]
     pub db_path: PathBuf,
 
+    /// Passphrase entry method for master key. The passphrase is required only
+    /// if the database is encrypted.
+    ///
+    /// Possible values are: `stdin`, `env{:ENV_VAR_NAME}`, `pass:PASSWORD`.
+    /// Default Value is `stdin`.
+    /// If `ENV_VAR_NAME` is not specified `$EXONUM_MASTER_PASS` is used
+    /// by default.
+    #[structopt(long)]
+    #[serde(default)]
+    pub master_key_pass: Option<PassInputMethod>,
+
     /// Action to be performed.
     #[structopt(subcommand)]
     pub action: MaintenanceAction,
@@ -60,31 +84,56 @@ pub enum MaintenanceAction {
         /// Name of the service for migration restart, e.g. "explorer" or "my-service".
         service_name: String,
     },
+
+    /// Export blockchain state into a file.
+    #[structopt(name = "export-state")]
+    ExportState {
+        /// Path to the output file.
+        output: PathBuf,
+        /// Height of the block after which the state is exported. If not specified,
+        /// the state after the latest block is exported.
+        #[structopt(long)]
+        height: Option<u64>,
+    },
+
+    /// Import blockchain state exported with `export-state` into an empty database.
+    ///
+    /// The latest block in the imported state is checked against the consensus keys
+    /// of validators. If the import fails, the database must be discarded.
+    #[structopt(name = "import-state")]
+    ImportState {
+        /// Path to the file with the exported state.
+        input: PathBuf,
+        /// Hex-encoded consensus keys of validators that have committed the latest block
+        /// in the imported state, ordered by validator ID. If not specified, the keys from
+        /// the genesis consensus configuration in the node configuration are used.
+        #[structopt(long)]
+        consensus_keys: Vec<PublicKey>,
+    },
+
+    /// Check integrity of Merkelized indexes by recomputing their hashes from the stored data,
+    /// and check the state hash against the latest block.
+    ///
+    /// Like other maintenance actions, the check requires exclusive access to the database,
+    /// so the node must be stopped. The integrity of a running node can be checked
+    /// with the `v1/database_integrity` endpoint of the private system API.
+    #[structopt(name = "check-integrity")]
+    CheckIntegrity {
+        /// Perform full compaction of the database after a successful check.
+        #[structopt(long)]
+        compact: bool,
+    },
 }
 
 impl MaintenanceAction {
-    fn clear_cache(node_config: &Path, db_path: &Path) -> Result<(), Error> {
-        let node_config: NodeConfig = load_config_file(node_config)?;
-        let db: Box<dyn Database> = Box::new(RocksDB::open(
-            db_path,
-            &node_config.private_config.database,
-        )?);
+    fn clear_cache(db: &dyn Database) -> Result<(), Error> {
         let fork = db.fork();
         clear_consensus_messages_cache(&fork);
         db.merge_sync(fork.into_patch())?;
         Ok(())
     }
 
-    fn restart_migration(
-        node_config: &Path,
-        db_path: &Path,
-        service_name: &str,
-    ) -> Result<(), Error> {
-        let node_config: NodeConfig = load_config_file(node_config)?;
-        let db: Box<dyn Database> = Box::new(RocksDB::open(
-            db_path,
-            &node_config.private_config.database,
-        )?);
+    fn restart_migration(db: &dyn Database, service_name: &str) -> Result<(), Error> {
         let mut fork = db.fork();
         rollback_migration(&mut fork, service_name);
         remove_local_migration_result(&fork, service_name);
@@ -92,20 +141,192 @@ impl MaintenanceAction {
 
         Ok(())
     }
+
+    fn export_state(
+        db: Arc<dyn Database>,
+        output: &Path,
+        height: Option<u64>,
+    ) -> Result<(), Error> {
+        let blockchain = Blockchain::new(db, KeyPair::random(), ApiSender::closed());
+        let height = if let Some(height) = height {
+            Height(height)
+        } else {
+            let blocks_count = Schema::new(&blockchain.snapshot())
+                .block_hashes_by_height()
+                .len();
+            let last_height = blocks_count
+                .checked_sub(1)
+                .ok_or_else(|| format_err!("Database does not contain blocks"))?;
+            Height(last_height)
+        };
+
+        let writer = BufWriter::new(File::create(output)?);
+        blockchain.export_state(height, writer)?;
+        Ok(())
+    }
+
+    fn import_state(
+        db: &dyn Database,
+        node_config: &NodeConfig,
+        db_path: &Path,
+        input: &Path,
+        consensus_keys: &[PublicKey],
+    ) -> Result<(), Error> {
+        let reader = BufReader::new(File::open(input)?);
+        let (block_proof, info) = Blockchain::import_state(db, reader)?;
+
+        let genesis_config = &node_config.public_config.consensus;
+        if block_proof.block.height == Height(0) {
+            // The genesis block is not endorsed by validators; instead, we check that
+            // the imported state was created with the expected consensus configuration.
+            let snapshot = db.snapshot();
+            let consensus_config = Schema::new(&snapshot).consensus_config();
+            ensure!(
+                consensus_config == *genesis_config,
+                "Consensus configuration in the imported genesis block does not match \
+                 the node configuration; the database at {} must be discarded",
+                db_path.display()
+            );
+        } else {
+            let consensus_keys = if consensus_keys.is_empty() {
+                genesis_config
+                    .validator_keys
+                    .iter()
+                    .map(|keys| keys.consensus_key)
+                    .collect()
+            } else {
+                consensus_keys.to_vec()
+            };
+            block_proof.verify(&consensus_keys).map_err(|e| {
+                format_err!(
+                    "Cannot verify the block at height {} in the imported state: {}; \
+                     the database at {} must be discarded",
+                    block_proof.block.height,
+                    e,
+                    db_path.display()
+                )
+            })?;
+        }
+
+        if !info.unauthenticated_indexes.is_empty() {
+            warn!(
+                "The following indexes in the imported state are not authenticated \
+                 by the state hash and are trusted as provided by the source of the state: {}",
+                info.unauthenticated_indexes.join(", ")
+            );
+        }
+        Ok(())
+    }
+
+    fn check_integrity(
+        db: Arc<dyn Database>,
+        node_config: &NodeConfig,
+        db_path: &Path,
+        compact: bool,
+    ) -> Result<(), Error> {
+        let report = db.check_integrity();
+        let mut errors: Vec<_> = report
+            .corrupted_indexes
+            .iter()
+            .map(|index| {
+                let group_key = index
+                    .group_key
+                    .as_ref()
+                    .map_or_else(String::new, |key| format!(" (group key: {})", key));
+                let migration = if index.in_migration {
+                    " (in migration)"
+                } else {
+                    ""
+                };
+                format!(
+                    "Index `{}`{}{}: {}",
+                    index.name, group_key, migration, index.reason
+                )
+            })
+            .collect();
+
+        let snapshot = db.snapshot();
+        let schema = Schema::new(&snapshot);
+        if schema.block_hashes_by_height().is_empty() {
+            return Err(format_err!("Database does not contain blocks"));
+        }
+        let last_block = schema.last_block();
+        if last_block.state_hash != report.state_hash {
+            errors.push(format!(
+                "State hash of the database ({:?}) does not match the state hash \
+                 in the block at height {} ({:?})",
+                report.state_hash, last_block.height, last_block.state_hash
+            ));
+        }
+
+        if !errors.is_empty() {
+            return Err(format_err!(
+                "Database integrity check failed:\n{}",
+                errors.join("\n")
+            ));
+        }
+        if compact {
+            // Compaction is performed on the underlying database, which needs to be reopened.
+            drop(snapshot);
+            drop(db);
+            RocksDB::open(db_path, &node_config.private_config.database)?.compact()?;
+        }
+        Ok(())
+    }
+}
+
+impl Maintenance {
+    /// Opens the node database. If the database is encrypted, the storage key is derived
+    /// from the master key of the node.
+    fn open_database(&mut self, node_config: &NodeConfig) -> Result<Arc<dyn Database>, Error> {
+        let private_config = &node_config.private_config;
+        let storage_key = if private_config.database.encryption.is_some() {
+            let passphrase = self
+                .master_key_pass
+                .take()
+                .unwrap_or_default()
+                .get_passphrase(PassphraseUsage::Using)?;
+            let key = read_storage_key(
+                &self.node_config,
+                &private_config.master_key_path,
+                passphrase.as_bytes(),
+            )?;
+            Some(key)
+        } else {
+            None
+        };
+        open_database(
+            &self.db_path,
+            &private_config.database,
+            storage_key.as_ref(),
+        )
+    }
 }
 
 impl ExonumCommand for Maintenance {
-    fn execute(self) -> Result<StandardResult, Error> {
+    fn execute(mut self) -> Result<StandardResult, Error> {
+        let node_config: NodeConfig = load_config_file(&self.node_config)?;
+        let db = self.open_database(&node_config)?;
         match self.action {
-            MaintenanceAction::ClearCache => {
-                MaintenanceAction::clear_cache(&self.node_config, &self.db_path)?
-            }
+            MaintenanceAction::ClearCache => MaintenanceAction::clear_cache(db.as_ref())?,
             MaintenanceAction::RestartMigration { ref service_name } => {
-                MaintenanceAction::restart_migration(
-                    &self.node_config,
-                    &self.db_path,
-                    service_name,
-                )?
+                MaintenanceAction::restart_migration(db.as_ref(), service_name)?
+            }
+            MaintenanceAction::ExportState { ref output, height } => {
+                MaintenanceAction::export_state(db, output, height)?
+            }
+            MaintenanceAction::ImportState {
+                ref input,
+                ref consensus_keys,
+            } => MaintenanceAction::import_state(
+                db.as_ref(),
+                &node_config,
+                &self.db_path,
+                input,
+                consensus_keys,
+            )?,
+            MaintenanceAction::CheckIntegrity { compact } => {
+                MaintenanceAction::check_integrity(db, &node_config, &self.db_path, compact)?
             }
         }
 
@@ -15,6 +15,7 @@
 //! Standard Exonum CLI node configuration commands.
 
 pub use self::{
+    derive_keys::DeriveKeys,
     finalize::Finalize,
     generate_config::{
         GenerateConfig, DEFAULT_EXONUM_LISTEN_PORT, MASTER_KEY_FILE_NAME, PRIVATE_CONFIG_FILE_NAME,
@@ -24,16 +25,23 @@ pub use self::{
     maintenance::{Maintenance, MaintenanceAction},
     run::{NodeRunConfig, Run},
     run_dev::RunDev,
+    run_signer::RunSigner,
+    stage_keys::{StageKeys, PENDING_MASTER_KEY_FILE_NAME},
 };
 
+mod derive_keys;
 mod finalize;
 mod generate_config;
 mod generate_template;
 mod maintenance;
 mod run;
 mod run_dev;
+mod run_signer;
+mod stage_keys;
 
 use anyhow::Error;
+use exonum::{blockchain::ValidatorKeys, crypto::PublicKey, keys::DerivationPath};
+use exonum_node::signer::SignerAddress;
 use serde_derive::{Deserialize, Serialize};
 use structopt::StructOpt;
 
@@ -73,6 +81,18 @@ pub enum Command {
     /// Perform different maintenance actions.
     #[structopt(name = "maintenance")]
     Maintenance(Maintenance),
+
+    /// Run the remote signer for consensus messages.
+    #[structopt(name = "run-signer")]
+    RunSigner(RunSigner),
+
+    /// Generate new keys of the node and stage them for key rotation.
+    #[structopt(name = "stage-keys")]
+    StageKeys(StageKeys),
+
+    /// Derive additional keypairs from the master key and print their public keys.
+    #[structopt(name = "derive-keys")]
+    DeriveKeys(DeriveKeys),
 }
 
 impl Command {
@@ -91,6 +111,9 @@ impl ExonumCommand for Command {
             Self::Run(command) => command.execute(),
             Self::RunDev(command) => command.execute(),
             Self::Maintenance(command) => command.execute(),
+            Self::RunSigner(command) => command.execute(),
+            Self::StageKeys(command) => command.execute(),
+            Self::DeriveKeys(command) => command.execute(),
         }
     }
 }
@@ -133,4 +156,25 @@ pub enum StandardResult {
         /// Performed action.
         performed_action: MaintenanceAction,
     },
+
+    /// `run-signer` command output. The output is produced once the signer is stopped.
+    RunSigner {
+        /// Address the signer has listened to.
+        address: SignerAddress,
+    },
+
+    /// `stage-keys` command output.
+    StageKeys {
+        /// Path to the master key file with the new keys of the node.
+        master_key_path: PathBuf,
+        /// Public keys of the node to use in the consensus configuration. All keys, including
+        /// the BLS key (if any), must be set in the same configuration change.
+        validator_keys: ValidatorKeys,
+    },
+
+    /// `derive-keys` command output.
+    DeriveKeys {
+        /// Public keys of the derived keypairs together with their derivation paths.
+        public_keys: Vec<(DerivationPath, PublicKey)>,
+    },
 }
@@ -16,7 +16,10 @@
 //! configuration file.
 
 use anyhow::Error;
-use exonum::keys::{read_keys_from_file, Keys};
+use exonum::{
+    crypto::aead,
+    keys::{read_keys_from_file, Keys},
+};
 use serde_derive::{Deserialize, Serialize};
 use structopt::StructOpt;
 
@@ -28,6 +31,7 @@ use std::{
 use crate::{
     command::{ExonumCommand, StandardResult},
     config::NodeConfig,
+    database::read_storage_key,
     io::load_config_file,
     password::{PassInputMethod, PassphraseUsage},
 };
@@ -40,6 +44,11 @@ pub struct NodeRunConfig {
     pub node_config: NodeConfig,
     /// Node keys.
     pub node_keys: Keys,
+    /// Pending keys of the node used for key rotation.
+    pub pending_keys: Option<Keys>,
+    /// Key used to encrypt the node database, if the encryption is enabled
+    /// in the database options.
+    pub storage_key: Option<aead::Key>,
     /// Path to a directory containing database files, provided by user.
     pub db_path: PathBuf,
     /// User-provided path to the node configuration file.
@@ -72,8 +81,17 @@ pub struct Run {
     /// Default Value is `stdin`.
     /// If `ENV_VAR_NAME` is not specified `$EXONUM_MASTER_PASS` is used
     /// by default.
+    /// The master key is also used to derive the key for the database encryption.
     #[structopt(long)]
     pub master_key_pass: Option<PassInputMethod>,
+    /// Passphrase entry method for the pending master key used for key rotation.
+    /// The passphrase is only requested if the pending master key is specified
+    /// in the node configuration.
+    ///
+    /// Possible values are the same as for `master-key-pass`.
+    /// Default Value is `stdin`.
+    #[structopt(long)]
+    pub pending_key_pass: Option<PassInputMethod>,
 }
 
 impl ExonumCommand for Run {
@@ -101,10 +119,31 @@ impl ExonumCommand for Run {
             &config.private_config.master_key_path,
             master_passphrase.as_bytes(),
         );
+        let pending_keys = match config.private_config.pending_master_key_path {
+            Some(ref path) => {
+                let passphrase = self
+                    .pending_key_pass
+                    .unwrap_or_default()
+                    .get_pending_passphrase(PassphraseUsage::Using)?;
+                Some(read_secret_keys(config_path, path, passphrase.as_bytes()))
+            }
+            None => None,
+        };
+        let storage_key = if config.private_config.database.encryption.is_some() {
+            Some(read_storage_key(
+                config_path,
+                &config.private_config.master_key_path,
+                master_passphrase.as_bytes(),
+            )?)
+        } else {
+            None
+        };
 
         let run_config = NodeRunConfig {
             node_config: config,
             node_keys,
+            pending_keys,
+            storage_key,
             db_path: self.db_path,
             node_config_path: self.node_config,
         };
@@ -97,6 +97,8 @@ impl ExonumCommand for RunDev {
                 no_password: true,
                 master_key_pass: None,
                 master_key_path: None,
+                bls_key: false,
+                encrypt_storage: false,
             };
             generate_config.execute()?;
 
@@ -118,6 +120,7 @@ impl ExonumCommand for RunDev {
             public_api_address: None,
             private_api_address: None,
             master_key_pass: Some(FromStr::from_str("pass:").unwrap()),
+            pending_key_pass: Some(FromStr::from_str("pass:").unwrap()),
         };
         run.execute()
     }
@@ -0,0 +1,78 @@
+// Copyright 2020 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Standard Exonum CLI command used to run a remote signer of consensus messages.
+
+use anyhow::{Context, Error};
+use exonum::{crypto::PublicKey, keys::read_keys_from_file};
+use exonum_node::signer::{DoubleSignGuard, SignerAddress, SignerServer};
+use serde_derive::{Deserialize, Serialize};
+use structopt::StructOpt;
+
+use std::path::PathBuf;
+
+use crate::{
+    command::{ExonumCommand, StandardResult},
+    password::{PassInputMethod, PassphraseUsage},
+};
+
+/// Run the remote signer for consensus messages of a validator node.
+///
+/// The signer uses the consensus key from the master key file and refuses to sign
+/// conflicting consensus messages. Only nodes with the specified network keys may connect
+/// to the signer. The command blocks until the signer is stopped.
+#[derive(StructOpt, Debug, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct RunSigner {
+    /// Path to the master key file with the consensus key of the validator.
+    #[structopt(long, short = "k")]
+    pub master_key_path: PathBuf,
+    /// Address to listen to, either `tcp://$host:$port` or `unix://$path`.
+    #[structopt(long, short = "a")]
+    pub address: SignerAddress,
+    /// Path to the file persisting the state of double-signing protection.
+    /// The file is created if it does not exist.
+    #[structopt(long, short = "s")]
+    pub state_path: PathBuf,
+    /// Passphrase entry method for master key.
+    ///
+    /// Possible values are: `stdin`, `env{:ENV_VAR_NAME}`, `pass:PASSWORD`.
+    /// Default Value is `stdin`.
+    /// If `ENV_VAR_NAME` is not specified `$EXONUM_MASTER_PASS` is used
+    /// by default.
+    #[structopt(long)]
+    pub master_key_pass: Option<PassInputMethod>,
+    /// Network public key of a node allowed to connect to the signer. The key is stored
+    /// in the master key file of the node as the consensus key. The option may be repeated.
+    #[structopt(long = "allowed-key", required = true, number_of_values = 1)]
+    pub allowed_keys: Vec<PublicKey>,
+}
+
+impl ExonumCommand for RunSigner {
+    fn execute(self) -> Result<StandardResult, Error> {
+        let master_passphrase = self
+            .master_key_pass
+            .unwrap_or_default()
+            .get_passphrase(PassphraseUsage::Using)?;
+        let keys = read_keys_from_file(&self.master_key_path, master_passphrase.as_bytes())
+            .context("Could not read master key file")?;
+        let guard = DoubleSignGuard::with_state_file(&self.state_path)
+            .context("Could not load the state of the signer")?;
+
+        SignerServer::new(keys.consensus, guard, self.allowed_keys).run(&self.address)?;
+        Ok(StandardResult::RunSigner {
+            address: self.address,
+        })
+    }
+}
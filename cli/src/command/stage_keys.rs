@@ -0,0 +1,114 @@
+// Copyright 2020 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Standard Exonum CLI command used to generate new keys of the node
+//! for key rotation.
+
+use anyhow::Error;
+use exonum::blockchain::{ValidatorBlsKey, ValidatorKeys};
+use log::warn;
+use serde_derive::{Deserialize, Serialize};
+use structopt::StructOpt;
+
+use std::{env, path::PathBuf};
+
+use crate::{
+    command::{generate_config::create_keys_and_files, ExonumCommand, StandardResult},
+    config::NodeConfig,
+    io::{load_config_file, save_config_file},
+    password::{PassInputMethod, Passphrase, PassphraseUsage},
+};
+
+/// Name for a encrypted file containing the pending master key of the node.
+pub const PENDING_MASTER_KEY_FILE_NAME: &str = "pending_master.key.toml";
+
+/// Generate new keys of the node and stage them for key rotation.
+///
+/// The new keys are saved into a separate master key file, the path to which is recorded
+/// in the node configuration. The node switches to the new keys once the consensus
+/// configuration containing them is activated; the public keys output by the command
+/// should be used in the corresponding configuration change. If the node uses a BLS key,
+/// a new BLS key is generated as well; it must be set in the same configuration change
+/// as the new consensus key. The new master key file is encrypted with its own passphrase,
+/// which is then required to run the node.
+#[derive(StructOpt, Debug, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct StageKeys {
+    /// Path to a node configuration file.
+    #[structopt(long, short = "c")]
+    pub node_config: PathBuf,
+    /// Path to the new master key file. If not specified, the file is placed
+    /// to the directory of the node configuration file.
+    #[structopt(long)]
+    pub master_key_path: Option<PathBuf>,
+    /// Don't prompt for a password when generating the new master key.
+    #[structopt(long, short = "n")]
+    pub no_password: bool,
+    /// Passphrase entry method for the new master key.
+    ///
+    /// Possible values are: `stdin`, `env{:ENV_VAR_NAME}`, `pass:PASSWORD`.
+    /// Default Value is `stdin`.
+    /// If `ENV_VAR_NAME` is not specified `$EXONUM_MASTER_PASS` is used
+    /// by default.
+    #[structopt(long)]
+    pub pending_key_pass: Option<PassInputMethod>,
+}
+
+impl ExonumCommand for StageKeys {
+    fn execute(self) -> Result<StandardResult, Error> {
+        let mut config: NodeConfig = load_config_file(&self.node_config)?;
+        let config_folder = self.node_config.parent().unwrap();
+
+        let passphrase = if self.no_password {
+            Passphrase::default()
+        } else {
+            self.pending_key_pass
+                .unwrap_or_default()
+                .get_pending_passphrase(PassphraseUsage::SettingUp)?
+        };
+
+        let master_key_path = match self.master_key_path {
+            Some(path) if path.is_relative() => env::current_dir()?.join(path),
+            Some(path) => path,
+            None => PathBuf::from(PENDING_MASTER_KEY_FILE_NAME),
+        };
+        let keys =
+            create_keys_and_files(config_folder.join(&master_key_path), passphrase.as_bytes())?;
+
+        let mut validator_keys = ValidatorKeys::new(keys.consensus_pk(), keys.service_pk());
+        // The BLS key is derived from the new master key, thus, it changes together with
+        // the consensus key. It is output only if the node has used a BLS key before.
+        let uses_bls_key = config
+            .public_config
+            .validator_keys
+            .as_ref()
+            .map_or(false, |keys| keys.bls_key.is_some());
+        if uses_bls_key {
+            let bls_key = ValidatorBlsKey::from_keypair(&keys.bls_keypair());
+            validator_keys = validator_keys.with_bls_key(bls_key);
+            warn!(
+                "The new BLS key of the node must be set in the same configuration change \
+                 as the new consensus key; otherwise, the node cannot sign commit certificates"
+            );
+        }
+
+        config.private_config.pending_master_key_path = Some(master_key_path.clone());
+        save_config_file(&config, &self.node_config)?;
+
+        Ok(StandardResult::StageKeys {
+            master_key_path,
+            validator_keys,
+        })
+    }
+}
@@ -20,8 +20,8 @@ use exonum::{
     merkledb::DbOptions,
 };
 use exonum_node::{
-    ConnectListConfig, MemoryPoolConfig, NetworkConfiguration, NodeApiConfig,
-    NodeConfig as CoreNodeConfig,
+    signer::RemoteSignerConfig, ConnectListConfig, MemoryPoolConfig, NetworkConfiguration,
+    NodeApiConfig, NodeConfig as CoreNodeConfig, RetentionPolicy,
 };
 use exonum_supervisor::mode::Mode as SupervisorMode;
 use serde_derive::{Deserialize, Serialize};
@@ -63,6 +63,10 @@ pub struct NodePrivateConfig {
     pub external_address: String,
     /// Path to the master key file.
     pub master_key_path: PathBuf,
+    /// Path to the master key file with the pending keys of the node, which the node
+    /// switches to once they are activated by the consensus configuration.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pending_master_key_path: Option<PathBuf>,
     /// API configuration.
     pub api: NodeApiConfig,
     /// Network configuration.
@@ -74,6 +78,15 @@ pub struct NodePrivateConfig {
     pub database: DbOptions,
     /// Amount of threads used for transactions verification.
     pub thread_pool_size: Option<u8>,
+    /// Retention policy for the historical blockchain data.
+    #[serde(default)]
+    pub retention: RetentionPolicy,
+    /// Can the node synchronize the blockchain state with other nodes on startup?
+    #[serde(default)]
+    pub state_sync: bool,
+    /// Remote signer for consensus messages.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remote_signer: Option<RemoteSignerConfig>,
     /// Information about peers within network.
     pub connect_list: ConnectListConfig,
     /// Consensus public key of the node.
@@ -100,6 +113,9 @@ impl Into<CoreNodeConfig> for NodeConfig {
             mempool: self.private_config.mempool,
             connect_list: self.private_config.connect_list,
             thread_pool_size: self.private_config.thread_pool_size,
+            retention: self.private_config.retention,
+            state_sync: self.private_config.state_sync,
+            remote_signer: self.private_config.remote_signer,
         }
     }
 }
@@ -83,6 +83,7 @@ mod tests {
     use exonum::{blockchain::ConsensusConfig, crypto::KeyPair, merkledb::DbOptions};
     use exonum_node::{
         ConnectInfo, ConnectListConfig, MemoryPoolConfig, NetworkConfiguration, NodeApiConfig,
+        RetentionPolicy,
     };
     use exonum_supervisor::mode::Mode;
     use tempfile::tempdir;
@@ -100,11 +101,15 @@ mod tests {
                 listen_address: "127.0.0.1:5400".parse().unwrap(),
                 external_address: "127.0.0.1:5400".to_string(),
                 master_key_path: PathBuf::default(),
+                pending_master_key_path: None,
                 api: NodeApiConfig::default(),
                 network: NetworkConfiguration::default(),
                 mempool: MemoryPoolConfig::default(),
                 database: DbOptions::default(),
                 thread_pool_size: None,
+                retention: RetentionPolicy::default(),
+                state_sync: false,
+                remote_signer: None,
                 connect_list: ConnectListConfig::default(),
                 consensus_public_key: KeyPair::random().public_key(),
             },
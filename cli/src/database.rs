@@ -0,0 +1,73 @@
+// Copyright 2020 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Opening the node database according to the node configuration.
+
+use anyhow::{bail, format_err, Context, Error};
+use exonum::{
+    crypto::aead,
+    keys::read_storage_key_from_file,
+    merkledb::{Database, DbOptions, EncryptedDB, RocksDB},
+};
+
+use std::{path::Path, sync::Arc};
+
+/// Reads the key used to encrypt the node database from the master key file.
+/// A relative `master_key_path` is resolved relative to the directory
+/// of the node configuration file.
+pub(crate) fn read_storage_key(
+    config_file_path: &Path,
+    master_key_path: &Path,
+    master_key_passphrase: &[u8],
+) -> Result<aead::Key, Error> {
+    let master_key_path = if master_key_path.is_absolute() {
+        master_key_path.to_owned()
+    } else {
+        let config_folder = config_file_path.parent().unwrap_or_else(|| Path::new(""));
+        config_folder.join(master_key_path)
+    };
+    read_storage_key_from_file(&master_key_path, master_key_passphrase).with_context(|| {
+        format!(
+            "reading storage key from master key file {}",
+            master_key_path.display()
+        )
+    })
+}
+
+/// Opens the node database at `db_path` with the specified options. If the options enable
+/// the database encryption, the database is wrapped into `EncryptedDB` with the `storage_key`.
+///
+/// Returns an error if the options do not match the existing database, i.e., the database
+/// is encrypted, but the encryption is disabled in the options, or vice versa.
+pub(crate) fn open_database(
+    db_path: &Path,
+    options: &DbOptions,
+    storage_key: Option<&aead::Key>,
+) -> Result<Arc<dyn Database>, Error> {
+    let database = RocksDB::open(db_path, options)?;
+    if EncryptedDB::is_encrypted(&database) && options.encryption.is_none() {
+        bail!(
+            "Database at {} is encrypted, but the encryption is disabled in the node configuration",
+            db_path.display()
+        );
+    }
+    Ok(if let Some(encryption) = options.encryption {
+        let storage_key = storage_key.ok_or_else(|| {
+            format_err!("Database is encrypted, but the storage key is not provided")
+        })?;
+        EncryptedDB::new(database, storage_key, encryption)?.into()
+    } else {
+        database.into()
+    })
+}
@@ -139,7 +139,6 @@ pub use structopt;
 
 use exonum::{
     blockchain::config::{GenesisConfig, GenesisConfigBuilder},
-    merkledb::RocksDB,
     runtime::{RuntimeInstance, WellKnownRuntime},
 };
 use exonum_explorer_service::ExplorerFactory;
@@ -152,10 +151,14 @@ use tempfile::TempDir;
 
 use std::{env, ffi::OsString, iter, path::PathBuf};
 
-use crate::command::{Command, ExonumCommand, NodeRunConfig, StandardResult};
+use crate::{
+    command::{Command, ExonumCommand, NodeRunConfig, StandardResult},
+    database::open_database,
+};
 
 pub mod command;
 pub mod config;
+mod database;
 mod io;
 pub mod password;
 
@@ -266,7 +269,11 @@ impl NodeBuilder {
 
             let genesis_config = Self::genesis_config(&run_config, self.genesis_config);
             let db_options = &run_config.node_config.private_config.database;
-            let database = RocksDB::open(run_config.db_path, db_options)?;
+            let database = open_database(
+                &run_config.db_path,
+                db_options,
+                run_config.storage_key.as_ref(),
+            )?;
 
             let node_config_path = run_config.node_config_path.to_string_lossy();
             let config_manager = DefaultConfigManager::new(node_config_path.into_owned());
@@ -280,6 +287,9 @@ impl NodeBuilder {
                 .with_config_manager(config_manager)
                 .with_plugin(SystemApiPlugin)
                 .with_runtime_fn(|channel| rust_runtime.build(channel.endpoints_sender()));
+            if let Some(pending_keys) = run_config.pending_keys {
+                node_builder = node_builder.with_pending_keys(pending_keys);
+            }
             for runtime in self.external_runtimes {
                 node_builder = node_builder.with_runtime(runtime);
             }
@@ -94,14 +94,25 @@ impl PassInputMethod {
     /// Details of this process differs for different secret key types and whether we run node
     /// or generate config files.
     pub fn get_passphrase(self, usage: PassphraseUsage) -> Result<Passphrase, Error> {
+        self.get_passphrase_with_prompt("Enter master key passphrase: ", usage)
+    }
+
+    /// Get passphrase for the pending master key of the node, which is used for key rotation,
+    /// using selected method.
+    pub fn get_pending_passphrase(self, usage: PassphraseUsage) -> Result<Passphrase, Error> {
+        self.get_passphrase_with_prompt("Enter pending master key passphrase: ", usage)
+    }
+
+    fn get_passphrase_with_prompt(
+        self,
+        prompt: &str,
+        usage: PassphraseUsage,
+    ) -> Result<Passphrase, Error> {
         match self {
-            Self::Terminal => {
-                let prompt = "Enter master key passphrase: ";
-                match usage {
-                    PassphraseUsage::SettingUp => prompt_passphrase(prompt),
-                    PassphraseUsage::Using => Passphrase::read_from_tty(prompt),
-                }
-            }
+            Self::Terminal => match usage {
+                PassphraseUsage::SettingUp => prompt_passphrase(prompt),
+                PassphraseUsage::Using => Passphrase::read_from_tty(prompt),
+            },
             Self::EnvVariable(name) => {
                 let variable_name = name.unwrap_or_else(|| DEFAULT_MASTER_PASS_ENV_VAR.to_string());
                 let passphrase = env::var(&variable_name).with_context(|| {
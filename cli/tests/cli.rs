@@ -14,7 +14,18 @@
 
 // This is a regression test for exonum configuration.
 
-use exonum::{blockchain::ValidatorKeys, crypto::KeyPair};
+use exonum::{
+    blockchain::{
+        config::GenesisConfigBuilder, ApiSender, Blockchain, BlockchainBuilder, ValidatorKeys,
+    },
+    crypto::{aead, KeyPair},
+    helpers::ValidateInput,
+    keys::{read_derived_keys_from_file, read_keys_from_file, read_storage_key_from_file},
+    merkledb::{
+        access::CopyAccessExt, Database, EncryptedDB, EncryptionOptions, RocksDB, WalSyncMode,
+    },
+};
+use exonum_node::signer::{RemoteSigner, RemoteSignerConfig, Signer, SignerAddress, SignerError};
 use exonum_supervisor::mode::Mode as SupervisorMode;
 use pretty_assertions::assert_eq;
 use structopt::StructOpt;
@@ -26,15 +37,18 @@ use std::{
     env,
     ffi::OsString,
     fs::{self, OpenOptions},
+    net::TcpListener,
     panic,
     path::{Path, PathBuf},
+    thread,
+    time::Duration,
 };
 
 use exonum_cli::{
     command::{
         Command, ExonumCommand, Finalize, GenerateConfig, GenerateTemplate, Run, StandardResult,
     },
-    config::{GeneralConfig, NodePrivateConfig, NodePublicConfig},
+    config::{GeneralConfig, NodeConfig, NodePrivateConfig, NodePublicConfig},
     load_config_file,
     password::DEFAULT_MASTER_PASS_ENV_VAR,
     save_config_file,
@@ -74,6 +88,11 @@ impl ConfigSpec {
         Self::new(root_dir, 4)
     }
 
+    fn new_tuned_database() -> Self {
+        let root_dir = PathBuf::from(Self::CONFIG_TESTDATA_FOLDER).join("tuned_database");
+        Self::new(root_dir, 1)
+    }
+
     fn command(&self, name: &str) -> ArgsBuilder {
         ArgsBuilder {
             args: vec!["exonum-config-test".into(), name.into()],
@@ -291,6 +310,25 @@ fn test_generate_config_key_files() {
     );
 }
 
+#[test]
+fn test_generate_config_with_bls_key() {
+    let env = ConfigSpec::new_without_pass();
+
+    env.command("generate-config")
+        .with_arg(&env.expected_template_file(SupervisorMode::Simple))
+        .with_arg(&env.output_node_config_dir(0))
+        .with_named_arg("-a", "0.0.0.0:8000")
+        .with_arg("--no-password")
+        .with_arg("--bls-key")
+        .run()
+        .unwrap();
+
+    let public_cfg: NodePublicConfig = load_config_file(&env.output_public_config(0)).unwrap();
+    let validator_keys = public_cfg.validator_keys.unwrap();
+    validator_keys.validate().unwrap();
+    assert!(validator_keys.bls_key.is_some());
+}
+
 #[test]
 fn master_key_path_current_dir() {
     let env = ConfigSpec::new_without_pass();
@@ -536,6 +574,378 @@ fn test_restart_migration() {
         .unwrap();
 }
 
+#[test]
+fn test_export_and_import_state() {
+    let env = ConfigSpec::new_without_pass();
+    let node_config_path = env.expected_node_config_file(0);
+    let node_config: NodeConfig = load_config_file(&node_config_path).unwrap();
+    let db_path = env.output_dir().join("db0");
+    let imported_db_path = env.output_dir().join("db1");
+    let state_path = env.output_dir().join("state.dump");
+
+    // Create a database containing the genesis block.
+    let db = RocksDB::open(&db_path, &node_config.private_config.database).unwrap();
+    let blockchain = Blockchain::new(db, KeyPair::random(), ApiSender::closed());
+    let consensus_config = node_config.public_config.consensus.clone();
+    let genesis_config = GenesisConfigBuilder::with_consensus_config(consensus_config).build();
+    let genesis_block = BlockchainBuilder::new(blockchain)
+        .with_genesis_config(genesis_config)
+        .build()
+        .as_ref()
+        .last_block();
+
+    env.command("maintenance")
+        .with_named_arg("--node-config", &node_config_path)
+        .with_named_arg("--db-path", &db_path)
+        .with_arg("export-state")
+        .with_arg(&state_path)
+        .run()
+        .unwrap();
+    env.command("maintenance")
+        .with_named_arg("--node-config", &node_config_path)
+        .with_named_arg("--db-path", &imported_db_path)
+        .with_arg("import-state")
+        .with_arg(&state_path)
+        .run()
+        .unwrap();
+
+    let db = RocksDB::open(&imported_db_path, &node_config.private_config.database).unwrap();
+    let blockchain = Blockchain::new(db, KeyPair::random(), ApiSender::closed());
+    assert_eq!(blockchain.last_block(), genesis_block);
+    drop(blockchain);
+
+    // The genesis block is checked against the consensus configuration of the node.
+    let mut other_node_config = node_config.clone();
+    other_node_config
+        .public_config
+        .consensus
+        .first_round_timeout += 1;
+    let other_node_config_path = env.output_dir().join("other_node.toml");
+    save_config_file(&other_node_config, &other_node_config_path).unwrap();
+    let err = env
+        .command("maintenance")
+        .with_named_arg("--node-config", &other_node_config_path)
+        .with_named_arg("--db-path", env.output_dir().join("db2"))
+        .with_arg("import-state")
+        .with_arg(&state_path)
+        .run()
+        .unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("does not match the node configuration"),
+        "{}",
+        err
+    );
+
+    // The state cannot be imported into a non-empty database.
+    let err = env
+        .command("maintenance")
+        .with_named_arg("--node-config", &node_config_path)
+        .with_named_arg("--db-path", &imported_db_path)
+        .with_arg("import-state")
+        .with_arg(&state_path)
+        .run()
+        .unwrap_err();
+    assert!(err.to_string().contains("empty database"), "{}", err);
+}
+
+#[test]
+fn test_check_integrity() {
+    let env = ConfigSpec::new_without_pass();
+    let node_config_path = env.expected_node_config_file(0);
+    let node_config: NodeConfig = load_config_file(&node_config_path).unwrap();
+    let db_path = env.output_dir().join("db0");
+
+    // Create a database containing the genesis block.
+    let db = RocksDB::open(&db_path, &node_config.private_config.database).unwrap();
+    let blockchain = Blockchain::new(db, KeyPair::random(), ApiSender::closed());
+    let consensus_config = node_config.public_config.consensus.clone();
+    let genesis_config = GenesisConfigBuilder::with_consensus_config(consensus_config).build();
+    BlockchainBuilder::new(blockchain)
+        .with_genesis_config(genesis_config)
+        .build();
+
+    env.command("maintenance")
+        .with_named_arg("--node-config", &node_config_path)
+        .with_named_arg("--db-path", &db_path)
+        .with_arg("check-integrity")
+        .with_arg("--compact")
+        .run()
+        .unwrap();
+
+    // Change the state bypassing block creation.
+    let db = RocksDB::open(&db_path, &node_config.private_config.database).unwrap();
+    let fork = db.fork();
+    fork.get_proof_entry("test.entry").set(1_u64);
+    db.merge(fork.into_patch()).unwrap();
+    drop(db);
+
+    let err = env
+        .command("maintenance")
+        .with_named_arg("--node-config", &node_config_path)
+        .with_named_arg("--db-path", &db_path)
+        .with_arg("check-integrity")
+        .run()
+        .unwrap_err();
+    assert!(err.to_string().contains("State hash"), "{}", err);
+}
+
+#[test]
+fn test_encrypted_storage() {
+    let env = ConfigSpec::new_without_pass();
+    env.copy_node_config_to_output(0);
+    let mut node_config: NodeConfig = load_config_file(env.expected_node_config_file(0)).unwrap();
+    let encryption = EncryptionOptions::default();
+    node_config.private_config.database.encryption = Some(encryption);
+    let node_config_path = env.output_node_config(0);
+    save_config_file(&node_config, &node_config_path).unwrap();
+    let db_path = env.output_dir().join("db0");
+
+    let run_config = match env
+        .command("run")
+        .with_named_arg("--node-config", &node_config_path)
+        .with_named_arg("--db-path", &db_path)
+        .with_named_arg("--master-key-pass", "pass:")
+        .run()
+        .unwrap()
+    {
+        StandardResult::Run(run_config) => run_config,
+        _ => unreachable!("Invalid result of run"),
+    };
+    let master_key_path = env.output_node_config_dir(0).join("master.key.toml");
+    let storage_key = read_storage_key_from_file(&master_key_path, b"").unwrap();
+    assert_eq!(run_config.storage_key, Some(storage_key.clone()));
+
+    // Maintenance actions open the database with the encryption.
+    env.command("maintenance")
+        .with_named_arg("--node-config", &node_config_path)
+        .with_named_arg("--db-path", &db_path)
+        .with_named_arg("--master-key-pass", "pass:")
+        .with_arg("clear-cache")
+        .run()
+        .unwrap();
+
+    let db = RocksDB::open(&db_path, &node_config.private_config.database).unwrap();
+    let err = EncryptedDB::new(db, &aead::gen_key(), encryption).unwrap_err();
+    assert!(err.to_string().contains("another key"), "{}", err);
+    let db = RocksDB::open(&db_path, &node_config.private_config.database).unwrap();
+    EncryptedDB::new(db, &storage_key, encryption).unwrap();
+}
+
+#[test]
+fn test_database_encryption_mismatch() {
+    let env = ConfigSpec::new_without_pass();
+    env.copy_node_config_to_output(0);
+    let mut node_config: NodeConfig = load_config_file(env.expected_node_config_file(0)).unwrap();
+    let node_config_path = env.output_node_config(0);
+    let clear_cache = |db_path: &Path| {
+        env.command("maintenance")
+            .with_named_arg("--node-config", &node_config_path)
+            .with_named_arg("--db-path", db_path)
+            .with_named_arg("--master-key-pass", "pass:")
+            .with_arg("clear-cache")
+            .run()
+    };
+
+    // The plaintext database with data cannot be opened with the encryption enabled.
+    let db_path = env.output_dir().join("db0");
+    let db = RocksDB::open(&db_path, &node_config.private_config.database).unwrap();
+    let fork = db.fork();
+    fork.get_entry("test.entry").set(1_u64);
+    db.merge(fork.into_patch()).unwrap();
+    drop(db);
+    node_config.private_config.database.encryption = Some(EncryptionOptions::default());
+    save_config_file(&node_config, &node_config_path).unwrap();
+    let err = clear_cache(&db_path).unwrap_err();
+    assert!(err.to_string().contains("not encrypted"), "{}", err);
+
+    // The encrypted database cannot be opened with the encryption disabled.
+    let db_path = env.output_dir().join("db1");
+    clear_cache(&db_path).unwrap();
+    node_config.private_config.database.encryption = None;
+    save_config_file(&node_config, &node_config_path).unwrap();
+    let err = clear_cache(&db_path).unwrap_err();
+    assert!(err.to_string().contains("is encrypted"), "{}", err);
+}
+
+#[test]
+fn test_run_with_tuned_database() {
+    let env = ConfigSpec::new_tuned_database();
+    let src = env.expected_node_config_dir(0);
+    let dest = env.output_node_config_dir(0);
+    fs::create_dir_all(&dest).unwrap();
+    ["node.toml", "master.key.toml"]
+        .iter()
+        .try_for_each(|file| copy_secured(src.join(file), dest.join(file)))
+        .expect("Can't copy file");
+    let db_path = env.output_dir().join("db0");
+
+    let run_config = match env
+        .command("run")
+        .with_named_arg("--node-config", env.output_node_config(0))
+        .with_named_arg("--db-path", &db_path)
+        .with_named_arg("--master-key-pass", "pass:")
+        .run()
+        .unwrap()
+    {
+        StandardResult::Run(run_config) => run_config,
+        _ => unreachable!("Invalid result of run"),
+    };
+    let database = run_config.node_config.private_config.database;
+    assert_eq!(database.wal_sync_mode, WalSyncMode::Always);
+    assert_eq!(database.block_cache_size, Some(16 << 20));
+    let tx_options = database.column_family_options("core.transactions");
+    assert_eq!(tx_options.write_buffer_size, Some(32 << 20));
+    assert_eq!(tx_options.max_write_buffer_number, Some(4));
+
+    // Tuning options survive serialization.
+    let node_config: NodeConfig = load_config_file(env.output_node_config(0)).unwrap();
+    let saved_config_path = env.output_node_config_dir(0).join("saved.toml");
+    save_config_file(&node_config, &saved_config_path).unwrap();
+    assert_config_files_eq(&saved_config_path, env.expected_node_config_file(0));
+
+    let db = RocksDB::open(&db_path, &database).unwrap();
+    let fork = db.fork();
+    fork.get_list("core.transactions").push(1_u64);
+    db.merge(fork.into_patch()).unwrap();
+}
+
+#[test]
+fn test_run_signer() {
+    let env = ConfigSpec::new_without_pass();
+    env.copy_node_config_to_output(0);
+    let master_key_path = env.output_node_config_dir(0).join("master.key.toml");
+    let keys = read_keys_from_file(&master_key_path, b"").unwrap();
+    let state_path = env.output_dir().join("signer_state.json");
+    let address = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let address = SignerAddress::Tcp(address);
+
+    let command = env
+        .command("run-signer")
+        .with_named_arg("--master-key-path", &master_key_path)
+        .with_named_arg("--address", address.to_string())
+        .with_named_arg("--state-path", &state_path)
+        .with_named_arg("--master-key-pass", "pass:")
+        .with_named_arg("--allowed-key", keys.consensus_pk().to_hex());
+    thread::spawn(|| command.run());
+
+    let config = RemoteSignerConfig::new(address, keys.consensus_pk());
+    let signer = RemoteSigner::new(config, keys.consensus.clone());
+    let mut attempts = 0;
+    let err = loop {
+        match signer.sign(b"not a consensus message") {
+            Err(SignerError::Io(_)) if attempts < 50 => {
+                attempts += 1;
+                thread::sleep(Duration::from_millis(100));
+            }
+            res => break res.unwrap_err(),
+        }
+    };
+    match err {
+        SignerError::Rejected(reason) => assert!(reason.contains("Cannot decode")),
+        other => panic!("Unexpected error: {}", other),
+    }
+}
+
+#[test]
+fn test_derive_keys() {
+    let env = ConfigSpec::new_without_pass();
+    env.copy_node_config_to_output(0);
+    let master_key_path = env.output_node_config_dir(0).join("master.key.toml");
+
+    let feedback = env
+        .command("derive-keys")
+        .with_named_arg("--master-key-path", &master_key_path)
+        .with_named_arg("--master-key-pass", "pass:")
+        .with_arg("services/timestamping/operator")
+        .with_arg("clients/1")
+        .run()
+        .unwrap();
+    let public_keys = match feedback {
+        StandardResult::DeriveKeys { public_keys } => public_keys,
+        other => panic!("Unexpected command output: {:?}", other),
+    };
+    let paths: Vec<_> = public_keys.iter().map(|(path, _)| path.clone()).collect();
+    assert_eq!(paths[0].to_string(), "services/timestamping/operator");
+    assert_eq!(paths[1].to_string(), "clients/1");
+    let keys = read_derived_keys_from_file(&master_key_path, b"", &paths).unwrap();
+    assert_eq!(public_keys[0].1, keys[0].public_key());
+    assert_eq!(public_keys[1].1, keys[1].public_key());
+
+    // The master key file should be decrypted with the correct passphrase.
+    let err = env
+        .command("derive-keys")
+        .with_named_arg("--master-key-path", &master_key_path)
+        .with_named_arg("--master-key-pass", "pass:wrong")
+        .with_arg("clients/1")
+        .run()
+        .unwrap_err();
+    assert!(err.to_string().contains("Could not read master key file"));
+}
+
+#[test]
+fn test_stage_keys() {
+    let env = ConfigSpec::new_without_pass();
+    env.copy_node_config_to_output(0);
+    let node_config = env.output_node_config(0);
+    env.command("finalize")
+        .with_arg(env.output_private_config(0))
+        .with_arg(&node_config)
+        .with_arg("--public-configs")
+        .with_args(env.expected_pub_configs())
+        .run()
+        .unwrap();
+
+    let feedback = env
+        .command("stage-keys")
+        .with_named_arg("-c", &node_config)
+        .with_named_arg("--pending-key-pass", "pass:pending")
+        .run()
+        .unwrap();
+    let validator_keys = match feedback {
+        StandardResult::StageKeys {
+            master_key_path,
+            validator_keys,
+        } => {
+            assert_eq!(master_key_path, Path::new("pending_master.key.toml"));
+            validator_keys
+        }
+        other => panic!("Unexpected command output: {:?}", other),
+    };
+    let config: NodeConfig = load_config_file(&node_config).unwrap();
+    assert_eq!(
+        config.private_config.pending_master_key_path,
+        Some(PathBuf::from("pending_master.key.toml"))
+    );
+
+    // Staging keys again should fail, since the pending key file already exists.
+    let err = env
+        .command("stage-keys")
+        .with_named_arg("-c", &node_config)
+        .with_arg("--no-password")
+        .run()
+        .unwrap_err();
+    assert!(err.to_string().contains("File exists"));
+
+    let feedback = env
+        .command("run")
+        .with_named_arg("-c", &node_config)
+        .with_named_arg("-d", env.output_dir().join("foo"))
+        .with_named_arg("--master-key-pass", "pass:")
+        .with_named_arg("--pending-key-pass", "pass:pending")
+        .run()
+        .unwrap();
+    let pending_keys = match feedback {
+        StandardResult::Run(run_config) => run_config.pending_keys.unwrap(),
+        other => panic!("Unexpected command output: {:?}", other),
+    };
+    assert_eq!(pending_keys.consensus_pk(), validator_keys.consensus_key);
+    assert_eq!(pending_keys.service_pk(), validator_keys.service_key);
+}
+
 #[test]
 fn run_node_with_simple_supervisor() {
     run_node_with_supervisor(&SupervisorMode::Simple).unwrap();
@@ -554,11 +964,15 @@ fn different_supervisor_modes_in_public_configs() -> anyhow::Result<()> {
         listen_address: "127.0.0.1:5400".parse().unwrap(),
         external_address: "127.0.0.1:5400".to_string(),
         master_key_path: Default::default(),
+        pending_master_key_path: None,
         api: Default::default(),
         network: Default::default(),
         mempool: Default::default(),
         database: Default::default(),
         thread_pool_size: None,
+        retention: Default::default(),
+        state_sync: false,
+        remote_signer: None,
         connect_list: Default::default(),
         consensus_public_key: KeyPair::random().public_key(),
     };
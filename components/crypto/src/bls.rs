@@ -0,0 +1,436 @@
+// Copyright 2020 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! BLS signatures over the BLS12-381 curve, which can be aggregated into a single
+//! constant-size signature.
+//!
+//! The module follows the "minimal public key size" variant of the [IETF draft] with
+//! the proof of possession scheme: public keys are points on G1 (48 bytes compressed),
+//! and signatures are points on G2 (96 bytes compressed). Signatures of several keys
+//! over the same message can be aggregated and verified with a single pairing check.
+//!
+//! Since aggregate verification is susceptible to rogue key attacks, a public key must only
+//! be trusted together with a proof of possession of the corresponding secret key
+//! (see [`BlsKeyPair::prove_possession`] and [`BlsPublicKey::verify_possession`]).
+//!
+//! The module is available if the `bls` crate feature is enabled.
+//!
+//! [IETF draft]: https://tools.ietf.org/html/draft-irtf-cfrg-bls-signature-04
+//! [`BlsKeyPair::prove_possession`]: struct.BlsKeyPair.html#method.prove_possession
+//! [`BlsPublicKey::verify_possession`]: struct.BlsPublicKey.html#method.verify_possession
+//!
+//! # Examples
+//!
+//! ```
+//! use exonum_crypto::bls::{self, BlsKeyPair};
+//!
+//! let keys: Vec<_> = (0..3).map(|_| BlsKeyPair::random()).collect();
+//! let signatures: Vec<_> = keys.iter().map(|keys| keys.sign(b"block")).collect();
+//! let aggregated = bls::aggregate_signatures(&signatures);
+//! let public_keys = keys.iter().map(BlsKeyPair::public_key);
+//! assert!(bls::verify_aggregate(b"block", public_keys, &aggregated));
+//! ```
+
+use bls12_381::{
+    hash_to_curve::{ExpandMsgXmd, HashToCurve},
+    multi_miller_loop, G1Affine, G1Projective, G2Affine, G2Prepared, G2Projective, Gt, Scalar,
+};
+use hex::FromHex;
+use serde::{
+    de::{self, Deserialize, Deserializer},
+    Serialize, Serializer,
+};
+
+use std::{fmt, str::FromStr};
+
+use crate::{crypto_impl, hash};
+
+/// Length of a BLS public key in bytes.
+pub const BLS_PUBLIC_KEY_LENGTH: usize = 48;
+/// Length of a BLS signature in bytes.
+pub const BLS_SIGNATURE_LENGTH: usize = 96;
+
+/// Domain separation tag for signatures.
+const SIGNATURE_DST: &[u8] = b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_POP_";
+/// Domain separation tag for proofs of possession.
+const POSSESSION_DST: &[u8] = b"BLS_POP_BLS12381G2_XMD:SHA-256_SSWU_RO_POP_";
+/// Domain separation prefix for deterministic derivation of secret keys.
+const KEYGEN_PREFIX: &[u8] = b"exonum-bls-keygen";
+
+fn hash_to_g2(message: &[u8], dst: &[u8]) -> G2Affine {
+    let point =
+        <G2Projective as HashToCurve<ExpandMsgXmd<sha2::Sha256>>>::hash_to_curve(message, dst);
+    G2Affine::from(point)
+}
+
+/// Checks the pairing equation `e(public_key, H(message)) == e(G1, signature)`.
+fn check_pairing(public_key: &G1Affine, message: &[u8], dst: &[u8], signature: &G2Affine) -> bool {
+    let message_point = G2Prepared::from(hash_to_g2(message, dst));
+    let signature = G2Prepared::from(*signature);
+    let generator = -G1Affine::generator();
+    let result = multi_miller_loop(&[(public_key, &message_point), (&generator, &signature)]);
+    result.final_exponentiation() == Gt::identity()
+}
+
+/// BLS public key.
+///
+/// Public keys are checked to be valid non-identity points when constructed.
+#[derive(Clone, Copy)]
+pub struct BlsPublicKey([u8; BLS_PUBLIC_KEY_LENGTH]);
+
+impl BlsPublicKey {
+    /// Creates a public key from its compressed representation. Returns `None`
+    /// if the bytes do not represent a valid public key.
+    pub fn from_slice(bytes: &[u8]) -> Option<Self> {
+        Self::decode(bytes).map(|_| {
+            let mut buffer = [0; BLS_PUBLIC_KEY_LENGTH];
+            buffer.copy_from_slice(bytes);
+            Self(buffer)
+        })
+    }
+
+    fn decode(bytes: &[u8]) -> Option<G1Affine> {
+        if bytes.len() != BLS_PUBLIC_KEY_LENGTH {
+            return None;
+        }
+        let mut buffer = [0; BLS_PUBLIC_KEY_LENGTH];
+        buffer.copy_from_slice(bytes);
+        let point: Option<G1Affine> = G1Affine::from_compressed(&buffer).into();
+        point.filter(|point| !bool::from(point.is_identity()))
+    }
+
+    fn point(&self) -> G1Affine {
+        Self::decode(&self.0).expect("Public key is checked on construction")
+    }
+
+    /// Verifies a signature of the message created with the corresponding secret key.
+    pub fn verify(&self, message: &[u8], signature: &BlsSignature) -> bool {
+        check_pairing(&self.point(), message, SIGNATURE_DST, &signature.point())
+    }
+
+    /// Verifies a proof of possession of the secret key corresponding to this public key.
+    pub fn verify_possession(&self, proof: &BlsSignature) -> bool {
+        check_pairing(&self.point(), &self.0, POSSESSION_DST, &proof.point())
+    }
+}
+
+/// BLS signature. Signatures can be aggregated with [`aggregate_signatures`].
+///
+/// Signatures are checked to be valid points when constructed.
+///
+/// [`aggregate_signatures`]: fn.aggregate_signatures.html
+#[derive(Clone, Copy)]
+pub struct BlsSignature([u8; BLS_SIGNATURE_LENGTH]);
+
+impl BlsSignature {
+    /// Creates a signature from its compressed representation. Returns `None`
+    /// if the bytes do not represent a valid signature.
+    pub fn from_slice(bytes: &[u8]) -> Option<Self> {
+        Self::decode(bytes).map(|_| {
+            let mut buffer = [0; BLS_SIGNATURE_LENGTH];
+            buffer.copy_from_slice(bytes);
+            Self(buffer)
+        })
+    }
+
+    fn decode(bytes: &[u8]) -> Option<G2Affine> {
+        if bytes.len() != BLS_SIGNATURE_LENGTH {
+            return None;
+        }
+        let mut buffer = [0; BLS_SIGNATURE_LENGTH];
+        buffer.copy_from_slice(bytes);
+        G2Affine::from_compressed(&buffer).into()
+    }
+
+    fn from_point(point: G2Affine) -> Self {
+        Self(point.to_compressed())
+    }
+
+    fn point(&self) -> G2Affine {
+        Self::decode(&self.0).expect("Signature is checked on construction")
+    }
+}
+
+macro_rules! implement_bls_wrapper {
+    ($name:ident) => {
+        impl $name {
+            /// Returns the compressed binary representation.
+            pub fn as_bytes(&self) -> &[u8] {
+                &self.0
+            }
+
+            /// Returns a hex representation of binary data.
+            pub fn to_hex(&self) -> String {
+                hex::encode(&self.0[..])
+            }
+        }
+
+        impl PartialEq for $name {
+            fn eq(&self, other: &Self) -> bool {
+                self.0[..] == other.0[..]
+            }
+        }
+
+        impl Eq for $name {}
+
+        impl PartialOrd for $name {
+            fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        impl Ord for $name {
+            fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+                self.0[..].cmp(&other.0[..])
+            }
+        }
+
+        impl std::hash::Hash for $name {
+            fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+                self.0[..].hash(state);
+            }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                let mut hex =
+                    String::with_capacity(crate::BYTES_IN_DEBUG * 2 + crate::BYTES_IN_ELLIPSIS);
+                crate::write_short_hex(&mut hex, &self.0[..])?;
+                f.debug_tuple(stringify!($name)).field(&hex).finish()
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(&self.to_hex())
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = anyhow::Error;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                let bytes = Vec::<u8>::from_hex(s).map_err(|e| anyhow::format_err!("{}", e))?;
+                Self::from_slice(&bytes)
+                    .ok_or_else(|| anyhow::format_err!(concat!("Invalid ", stringify!($name))))
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: Serializer,
+            {
+                serializer.serialize_str(&self.to_hex())
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                let s = String::deserialize(deserializer)?;
+                s.parse().map_err(de::Error::custom)
+            }
+        }
+    };
+}
+
+implement_bls_wrapper!(BlsPublicKey);
+implement_bls_wrapper!(BlsSignature);
+
+/// Pair of BLS secret and public keys.
+#[derive(Clone)]
+pub struct BlsKeyPair {
+    public_key: BlsPublicKey,
+    secret_key: Scalar,
+}
+
+impl fmt::Debug for BlsKeyPair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BlsKeyPair")
+            .field("public_key", &self.public_key)
+            .finish()
+    }
+}
+
+impl BlsKeyPair {
+    fn from_scalar(secret_key: Scalar) -> Self {
+        let point = G1Affine::from(G1Affine::generator() * secret_key);
+        let public_key = BlsPublicKey(point.to_compressed());
+        Self {
+            public_key,
+            secret_key,
+        }
+    }
+
+    /// Generates a random keypair.
+    pub fn random() -> Self {
+        let mut bytes = [0; 64];
+        crypto_impl::fill_random_bytes(&mut bytes);
+        Self::from_scalar(Scalar::from_bytes_wide(&bytes))
+    }
+
+    /// Deterministically derives a keypair from the provided secret seed.
+    /// The seed should have at least 32 bytes of entropy.
+    pub fn from_seed(seed: &[u8]) -> Self {
+        let mut bytes = [0; 64];
+        for (i, chunk) in bytes.chunks_mut(32).enumerate() {
+            let mut input = KEYGEN_PREFIX.to_vec();
+            input.push(i as u8);
+            input.extend_from_slice(seed);
+            chunk.copy_from_slice(hash(&input).as_ref());
+        }
+        Self::from_scalar(Scalar::from_bytes_wide(&bytes))
+    }
+
+    /// Returns the public key.
+    pub fn public_key(&self) -> &BlsPublicKey {
+        &self.public_key
+    }
+
+    /// Signs the message.
+    pub fn sign(&self, message: &[u8]) -> BlsSignature {
+        let point = hash_to_g2(message, SIGNATURE_DST) * self.secret_key;
+        BlsSignature::from_point(G2Affine::from(point))
+    }
+
+    /// Creates a proof of possession of the secret key.
+    pub fn prove_possession(&self) -> BlsSignature {
+        let point = hash_to_g2(&self.public_key.0, POSSESSION_DST) * self.secret_key;
+        BlsSignature::from_point(G2Affine::from(point))
+    }
+}
+
+/// Aggregates several signatures into a single one.
+///
+/// # Panics
+///
+/// Panics if `signatures` is empty.
+pub fn aggregate_signatures<'a>(
+    signatures: impl IntoIterator<Item = &'a BlsSignature>,
+) -> BlsSignature {
+    let mut signatures = signatures.into_iter().peekable();
+    assert!(
+        signatures.peek().is_some(),
+        "Cannot aggregate an empty set of signatures"
+    );
+    let sum = signatures.fold(G2Projective::identity(), |acc, signature| {
+        acc + signature.point()
+    });
+    BlsSignature::from_point(G2Affine::from(sum))
+}
+
+/// Verifies an aggregated signature of the message created by the owners of the specified
+/// public keys. Returns `false` if `public_keys` is empty.
+///
+/// The caller is responsible for checking proofs of possession for the keys.
+pub fn verify_aggregate<'a>(
+    message: &[u8],
+    public_keys: impl IntoIterator<Item = &'a BlsPublicKey>,
+    signature: &BlsSignature,
+) -> bool {
+    let mut public_keys = public_keys.into_iter().peekable();
+    if public_keys.peek().is_none() {
+        return false;
+    }
+    let sum = public_keys.fold(G1Projective::identity(), |acc, key| acc + key.point());
+    check_pairing(
+        &G1Affine::from(sum),
+        message,
+        SIGNATURE_DST,
+        &signature.point(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sign_and_verify() {
+        let keys = BlsKeyPair::random();
+        let signature = keys.sign(b"message");
+        assert!(keys.public_key().verify(b"message", &signature));
+        assert!(!keys.public_key().verify(b"other message", &signature));
+        assert!(!BlsKeyPair::random()
+            .public_key()
+            .verify(b"message", &signature));
+    }
+
+    #[test]
+    fn proof_of_possession() {
+        let keys = BlsKeyPair::random();
+        let proof = keys.prove_possession();
+        assert!(keys.public_key().verify_possession(&proof));
+        assert!(!BlsKeyPair::random().public_key().verify_possession(&proof));
+
+        // A signature of the key bytes is not a proof of possession because of domain separation.
+        let signature = keys.sign(keys.public_key().as_bytes());
+        assert!(!keys.public_key().verify_possession(&signature));
+    }
+
+    #[test]
+    fn deterministic_keys() {
+        let keys = BlsKeyPair::from_seed(&[1; 32]);
+        assert_eq!(
+            keys.public_key(),
+            BlsKeyPair::from_seed(&[1; 32]).public_key()
+        );
+        assert_ne!(
+            keys.public_key(),
+            BlsKeyPair::from_seed(&[2; 32]).public_key()
+        );
+    }
+
+    #[test]
+    fn aggregation() {
+        let keys: Vec<_> = (0..5).map(|_| BlsKeyPair::random()).collect();
+        let signatures: Vec<_> = keys.iter().map(|keys| keys.sign(b"block")).collect();
+        let public_keys: Vec<_> = keys.iter().map(|keys| *keys.public_key()).collect();
+
+        let aggregated = aggregate_signatures(&signatures);
+        assert!(verify_aggregate(b"block", &public_keys, &aggregated));
+        assert!(!verify_aggregate(b"other block", &public_keys, &aggregated));
+        assert!(!verify_aggregate(b"block", &public_keys[1..], &aggregated));
+        assert!(!verify_aggregate(b"block", &[], &aggregated));
+
+        let partial = aggregate_signatures(&signatures[..3]);
+        assert!(verify_aggregate(b"block", &public_keys[..3], &partial));
+        assert!(!verify_aggregate(b"block", &public_keys, &partial));
+    }
+
+    #[test]
+    fn string_conversions() {
+        let keys = BlsKeyPair::random();
+        let public_key = *keys.public_key();
+        let json = serde_json::to_string(&public_key).unwrap();
+        assert_eq!(
+            serde_json::from_str::<BlsPublicKey>(&json).unwrap(),
+            public_key
+        );
+
+        let signature = keys.sign(b"message");
+        let restored: BlsSignature = signature.to_string().parse().unwrap();
+        assert_eq!(restored, signature);
+
+        // Invalid points are rejected.
+        assert!(BlsPublicKey::from_slice(&[0; BLS_PUBLIC_KEY_LENGTH]).is_none());
+        assert!(BlsSignature::from_slice(&[1; BLS_SIGNATURE_LENGTH]).is_none());
+        // The identity point is not a valid public key.
+        let identity = G1Affine::identity().to_compressed();
+        assert!(BlsPublicKey::from_slice(&identity).is_none());
+    }
+}
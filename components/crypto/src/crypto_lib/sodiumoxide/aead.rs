@@ -0,0 +1,320 @@
+// Copyright 2020 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Symmetric authenticated encryption with additional data (AEAD) based on
+//! the XChaCha20-Poly1305 construction.
+//!
+//! XChaCha20-Poly1305 uses 192-bit nonces, which are long enough to be generated randomly
+//! without the risk of a nonce collision. The construction derives a subkey from the key
+//! and the first 128 bits of the nonce with `HChaCha20`, and encrypts data with the IETF variant
+//! of ChaCha20-Poly1305 using the subkey and the remaining 64 bits of the nonce.
+
+// spell-checker:ignore hchacha, KEYBYTES, NONCEBYTES, TAGBYTES
+
+use std::{error::Error as StdError, fmt};
+
+use super::sodiumoxide::{
+    crypto::{
+        aead::chacha20poly1305_ietf::{
+            self as sodium_aead, Key as SodiumKey, Nonce as IetfNonce, KEYBYTES, NONCEBYTES,
+            TAGBYTES,
+        },
+        auth::hmacsha256,
+    },
+    randombytes::randombytes_into,
+};
+
+/// Length of the secret key.
+pub const KEY_LENGTH: usize = KEYBYTES;
+/// Length of the nonce.
+pub const NONCE_LENGTH: usize = 24;
+/// Length of the authentication tag appended to ciphertexts.
+pub const TAG_LENGTH: usize = TAGBYTES;
+
+/// Length of the nonce prefix used to derive a subkey with `HChaCha20`.
+const SUBKEY_NONCE_LENGTH: usize = 16;
+
+/// Secret key for authenticated encryption. The contents of the key are zeroed out
+/// when the key goes out of scope.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Key(SodiumKey);
+
+impl Key {
+    /// Creates a new key from bytes array.
+    pub fn new(bytes_array: [u8; KEY_LENGTH]) -> Self {
+        Self(SodiumKey(bytes_array))
+    }
+
+    /// Creates a new key from bytes slice.
+    pub fn from_slice(bytes_slice: &[u8]) -> Option<Self> {
+        SodiumKey::from_slice(bytes_slice).map(Self)
+    }
+
+    /// Derives a subkey for the specified `context` from this key. Keys derived
+    /// for different contexts are independent.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # exonum_crypto::init();
+    /// use exonum_crypto::aead;
+    ///
+    /// let key = aead::gen_key();
+    /// let values_key = key.derive_subkey("values");
+    /// assert_ne!(values_key, key.derive_subkey("keys"));
+    /// ```
+    #[must_use]
+    pub fn derive_subkey(&self, context: &str) -> Self {
+        let tag = hmacsha256::authenticate(context.as_bytes(), &self.hmac_key());
+        Self::new(tag.0)
+    }
+
+    /// Derives a nonce from `data`. The nonce is a pseudo-random function of the key
+    /// and `data`, which allows to use it for deterministic encryption, with equal
+    /// plaintexts encrypted into equal ciphertexts.
+    pub fn derive_nonce(&self, data: &[u8]) -> Nonce {
+        let tag = hmacsha256::authenticate(data, &self.hmac_key());
+        let mut nonce = [0; NONCE_LENGTH];
+        nonce.copy_from_slice(&tag.0[..NONCE_LENGTH]);
+        Nonce::new(nonce)
+    }
+
+    fn hmac_key(&self) -> hmacsha256::Key {
+        hmacsha256::Key((self.0).0)
+    }
+}
+
+impl AsRef<[u8]> for Key {
+    fn as_ref(&self) -> &[u8] {
+        &(self.0).0[..]
+    }
+}
+
+impl fmt::Debug for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Secret keys are not printed.
+        f.write_str("Key(..)")
+    }
+}
+
+/// Nonce used in authenticated encryption. A nonce must never be reused with the same key
+/// for different plaintexts.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Nonce([u8; NONCE_LENGTH]);
+
+impl Nonce {
+    /// Creates a new nonce from bytes array.
+    pub fn new(bytes_array: [u8; NONCE_LENGTH]) -> Self {
+        Self(bytes_array)
+    }
+
+    /// Creates a new nonce from bytes slice.
+    pub fn from_slice(bytes_slice: &[u8]) -> Option<Self> {
+        if bytes_slice.len() == NONCE_LENGTH {
+            let mut bytes_array = [0; NONCE_LENGTH];
+            bytes_array.copy_from_slice(bytes_slice);
+            Some(Self(bytes_array))
+        } else {
+            None
+        }
+    }
+
+    /// Derives the subkey and the nonce for the IETF variant of ChaCha20-Poly1305.
+    fn ietf_params(&self, key: &Key) -> (SodiumKey, IetfNonce) {
+        let mut subkey_input = [0; SUBKEY_NONCE_LENGTH];
+        subkey_input.copy_from_slice(&self.0[..SUBKEY_NONCE_LENGTH]);
+        let subkey = hchacha20(&(key.0).0, &subkey_input);
+
+        let mut nonce = [0; NONCEBYTES];
+        nonce[NONCEBYTES - (NONCE_LENGTH - SUBKEY_NONCE_LENGTH)..]
+            .copy_from_slice(&self.0[SUBKEY_NONCE_LENGTH..]);
+        (SodiumKey(subkey), IetfNonce(nonce))
+    }
+}
+
+impl AsRef<[u8]> for Nonce {
+    fn as_ref(&self) -> &[u8] {
+        &self.0[..]
+    }
+}
+
+impl fmt::Debug for Nonce {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Nonce(")?;
+        crate::write_short_hex(f, self.as_ref())?;
+        f.write_str(")")
+    }
+}
+
+/// Generates a random secret key.
+pub fn gen_key() -> Key {
+    Key(sodium_aead::gen_key())
+}
+
+/// Generates a random nonce.
+pub fn gen_nonce() -> Nonce {
+    let mut nonce = [0; NONCE_LENGTH];
+    randombytes_into(&mut nonce);
+    Nonce(nonce)
+}
+
+/// Error returned if a ciphertext cannot be decrypted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Error(());
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(
+            "ciphertext cannot be decrypted with the provided key, nonce and additional data",
+        )
+    }
+}
+
+impl StdError for Error {}
+
+/// Encrypts and authenticates `plaintext` together with optional additional data `ad`,
+/// which is authenticated, but not encrypted. The returned ciphertext is `TAG_LENGTH`
+/// bytes longer than the plaintext.
+///
+/// # Examples
+///
+/// ```
+/// # exonum_crypto::init();
+/// use exonum_crypto::aead;
+///
+/// let key = aead::gen_key();
+/// let nonce = aead::gen_nonce();
+/// let ciphertext = aead::seal(b"secret", Some(b"context"), &nonce, &key);
+/// let plaintext = aead::open(&ciphertext, Some(b"context"), &nonce, &key).unwrap();
+/// assert_eq!(plaintext, b"secret");
+/// assert!(aead::open(&ciphertext, Some(b"other"), &nonce, &key).is_err());
+/// ```
+pub fn seal(plaintext: &[u8], ad: Option<&[u8]>, nonce: &Nonce, key: &Key) -> Vec<u8> {
+    let (subkey, ietf_nonce) = nonce.ietf_params(key);
+    sodium_aead::seal(plaintext, ad, &ietf_nonce, &subkey)
+}
+
+/// Verifies and decrypts a ciphertext produced by [`seal()`](fn.seal.html).
+///
+/// Returns an error if the ciphertext or additional data `ad` were tampered with,
+/// or if the ciphertext was produced with a different key or nonce.
+pub fn open(
+    ciphertext: &[u8],
+    ad: Option<&[u8]>,
+    nonce: &Nonce,
+    key: &Key,
+) -> Result<Vec<u8>, Error> {
+    let (subkey, ietf_nonce) = nonce.ietf_params(key);
+    sodium_aead::open(ciphertext, ad, &ietf_nonce, &subkey).map_err(|()| Error(()))
+}
+
+/// Computes the `HChaCha20` function, which derives a subkey from the `key`
+/// and a 128-bit `input`.
+fn hchacha20(key: &[u8; KEY_LENGTH], input: &[u8; SUBKEY_NONCE_LENGTH]) -> [u8; KEY_LENGTH] {
+    fn quarter_round(state: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+        state[a] = state[a].wrapping_add(state[b]);
+        state[d] = (state[d] ^ state[a]).rotate_left(16);
+        state[c] = state[c].wrapping_add(state[d]);
+        state[b] = (state[b] ^ state[c]).rotate_left(12);
+        state[a] = state[a].wrapping_add(state[b]);
+        state[d] = (state[d] ^ state[a]).rotate_left(8);
+        state[c] = state[c].wrapping_add(state[d]);
+        state[b] = (state[b] ^ state[c]).rotate_left(7);
+    }
+
+    let read_word = |bytes: &[u8]| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    let mut state = [0_u32; 16];
+    state[..4].copy_from_slice(&[0x6170_7865, 0x3320_646e, 0x7962_2d32, 0x6b20_6574]);
+    for (i, chunk) in key.chunks(4).enumerate() {
+        state[4 + i] = read_word(chunk);
+    }
+    for (i, chunk) in input.chunks(4).enumerate() {
+        state[12 + i] = read_word(chunk);
+    }
+
+    for _ in 0..10 {
+        quarter_round(&mut state, 0, 4, 8, 12);
+        quarter_round(&mut state, 1, 5, 9, 13);
+        quarter_round(&mut state, 2, 6, 10, 14);
+        quarter_round(&mut state, 3, 7, 11, 15);
+        quarter_round(&mut state, 0, 5, 10, 15);
+        quarter_round(&mut state, 1, 6, 11, 12);
+        quarter_round(&mut state, 2, 7, 8, 13);
+        quarter_round(&mut state, 3, 4, 9, 14);
+    }
+
+    let mut output = [0; KEY_LENGTH];
+    let words = state[..4].iter().chain(&state[12..]);
+    for (chunk, word) in output.chunks_mut(4).zip(words) {
+        chunk.copy_from_slice(&word.to_le_bytes());
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use hex::FromHex;
+
+    use super::*;
+
+    // Test vectors are taken from the XChaCha20-Poly1305 specification draft
+    // (draft-irtf-cfrg-xchacha-03).
+
+    #[test]
+    fn hchacha20_test_vector() {
+        let mut key = [0; KEY_LENGTH];
+        for (i, byte) in key.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        let input = <[u8; 16]>::from_hex("000000090000004a0000000031415927").unwrap();
+        let subkey = hchacha20(&key, &input);
+        assert_eq!(
+            hex::encode(subkey),
+            "82413b4227b27bfed30e42508a877d73a0f9e4d58a74a853c12ec41326d3ecdc"
+        );
+    }
+
+    #[test]
+    fn xchacha20_poly1305_test_vector() {
+        crate::init();
+        let mut key = [0; KEY_LENGTH];
+        for (i, byte) in key.iter_mut().enumerate() {
+            *byte = 0x80 + i as u8;
+        }
+        let key = Key::new(key);
+        let nonce = Nonce::from_slice(
+            &Vec::from_hex("404142434445464748494a4b4c4d4e4f5051525354555657").unwrap(),
+        )
+        .unwrap();
+        let ad = Vec::from_hex("50515253c0c1c2c3c4c5c6c7").unwrap();
+        let plaintext = b"Ladies and Gentlemen of the class of '99: If I could offer you only \
+            one tip for the future, sunscreen would be it.";
+
+        let ciphertext = seal(plaintext, Some(&ad), &nonce, &key);
+        assert_eq!(ciphertext.len(), plaintext.len() + TAG_LENGTH);
+        assert_eq!(
+            hex::encode(&ciphertext[..32]),
+            "bd6d179d3e83d43b9576579493c0e939572a1700252bfaccbed2902c21396cbb"
+        );
+        assert_eq!(
+            hex::encode(&ciphertext[plaintext.len()..]),
+            "c0875924c1c7987947deafd8780acf49"
+        );
+        assert_eq!(
+            open(&ciphertext, Some(&ad), &nonce, &key).unwrap(),
+            &plaintext[..]
+        );
+        assert_eq!(open(&ciphertext, None, &nonce, &key), Err(Error(())));
+    }
+}
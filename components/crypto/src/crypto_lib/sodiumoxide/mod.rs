@@ -59,6 +59,7 @@ use self::sodiumoxide::crypto::{
     sign::{convert_sk_to_pk, ed25519},
 };
 
+pub mod aead;
 pub mod x25519;
 
 /// Number of bytes in a `Hash`.
@@ -110,6 +111,11 @@ pub fn hash(data: &[u8]) -> Hash {
     sha256::hash(data)
 }
 
+/// Fills the buffer with cryptographically secure random bytes.
+pub(crate) fn fill_random_bytes(buffer: &mut [u8]) {
+    sodiumoxide::randombytes::randombytes_into(buffer);
+}
+
 /// Verifies that public key matches provided secret key.
 pub(crate) fn verify_keys_match(public_key: &PublicKey, secret_key: &SecretKey) -> bool {
     convert_sk_to_pk(secret_key) == *public_key
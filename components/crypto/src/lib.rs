@@ -47,11 +47,14 @@ pub use self::crypto_impl::{
     HASH_SIZE, PUBLIC_KEY_LENGTH, SECRET_KEY_LENGTH, SEED_LENGTH, SIGNATURE_LENGTH,
 };
 #[cfg(feature = "sodiumoxide-crypto")]
-pub use self::crypto_lib::sodiumoxide::x25519;
+pub use self::crypto_lib::sodiumoxide::{aead, x25519};
 
+#[cfg(feature = "bls")]
+pub mod bls;
 #[cfg(feature = "with-protobuf")]
 #[doc(hidden)]
 pub mod proto;
+pub mod schemes;
 
 use hex::{encode as encode_hex, FromHex, FromHexError, ToHex};
 use serde::{
@@ -20,7 +20,10 @@ pub use crate::proto::schema::*;
 use anyhow::{ensure, format_err, Error};
 use exonum_proto::ProtobufConvert;
 
-use crate::{HASH_SIZE, PUBLIC_KEY_LENGTH, SIGNATURE_LENGTH};
+use crate::{
+    schemes::{AnyPublicKey, AnySignature},
+    HASH_SIZE, PUBLIC_KEY_LENGTH, SIGNATURE_LENGTH,
+};
 
 mod schema;
 #[cfg(test)]
@@ -54,6 +57,10 @@ impl ProtobufConvert for crate::PublicKey {
     }
 
     fn from_pb(pb: schema::PublicKey) -> Result<Self, Error> {
+        ensure!(
+            pb.get_scheme() == schema::SignatureScheme::ED25519,
+            "Expected Ed25519 public key"
+        );
         let data = pb.get_data();
         ensure!(data.len() == PUBLIC_KEY_LENGTH, "Wrong PublicKey size");
         Self::from_slice(data).ok_or_else(|| format_err!("Cannot convert PublicKey from bytes"))
@@ -70,8 +77,98 @@ impl ProtobufConvert for crate::Signature {
     }
 
     fn from_pb(pb: schema::Signature) -> Result<Self, Error> {
+        ensure!(
+            pb.get_scheme() == schema::SignatureScheme::ED25519,
+            "Expected Ed25519 signature"
+        );
         let data = pb.get_data();
         ensure!(data.len() == SIGNATURE_LENGTH, "Wrong Signature size");
         Self::from_slice(data).ok_or_else(|| format_err!("Cannot convert Signature from bytes"))
     }
 }
+
+impl ProtobufConvert for crate::schemes::SignatureScheme {
+    type ProtoStruct = schema::SignatureScheme;
+
+    fn to_pb(&self) -> schema::SignatureScheme {
+        match self {
+            Self::Ed25519 => schema::SignatureScheme::ED25519,
+            Self::Secp256k1 => schema::SignatureScheme::SECP256K1,
+            Self::P256 => schema::SignatureScheme::P256,
+            Self::Ethereum => schema::SignatureScheme::ETHEREUM,
+            Self::WebAuthn => schema::SignatureScheme::WEBAUTHN,
+        }
+    }
+
+    fn from_pb(pb: schema::SignatureScheme) -> Result<Self, Error> {
+        Ok(match pb {
+            schema::SignatureScheme::ED25519 => Self::Ed25519,
+            schema::SignatureScheme::SECP256K1 => Self::Secp256k1,
+            schema::SignatureScheme::P256 => Self::P256,
+            schema::SignatureScheme::ETHEREUM => Self::Ethereum,
+            schema::SignatureScheme::WEBAUTHN => Self::WebAuthn,
+        })
+    }
+}
+
+impl ProtobufConvert for AnyPublicKey {
+    type ProtoStruct = schema::PublicKey;
+
+    fn to_pb(&self) -> schema::PublicKey {
+        let mut key = schema::PublicKey::new();
+        key.set_data(self.as_bytes().to_vec());
+        key.set_scheme(self.scheme().to_pb());
+        key
+    }
+
+    fn from_pb(pb: schema::PublicKey) -> Result<Self, Error> {
+        let scheme = crate::schemes::SignatureScheme::from_pb(pb.get_scheme())?;
+        Self::new(scheme, pb.get_data())
+    }
+}
+
+impl ProtobufConvert for AnySignature {
+    type ProtoStruct = schema::Signature;
+
+    fn to_pb(&self) -> schema::Signature {
+        let mut sign = schema::Signature::new();
+        sign.set_data(self.as_bytes().to_vec());
+        sign.set_scheme(self.scheme().to_pb());
+        sign
+    }
+
+    fn from_pb(pb: schema::Signature) -> Result<Self, Error> {
+        let scheme = crate::schemes::SignatureScheme::from_pb(pb.get_scheme())?;
+        Self::new(scheme, pb.get_data())
+    }
+}
+
+#[cfg(feature = "bls")]
+impl ProtobufConvert for crate::bls::BlsPublicKey {
+    type ProtoStruct = schema::BlsPublicKey;
+
+    fn to_pb(&self) -> schema::BlsPublicKey {
+        let mut key = schema::BlsPublicKey::new();
+        key.set_data(self.as_bytes().to_vec());
+        key
+    }
+
+    fn from_pb(pb: schema::BlsPublicKey) -> Result<Self, Error> {
+        Self::from_slice(pb.get_data()).ok_or_else(|| format_err!("Invalid BLS public key"))
+    }
+}
+
+#[cfg(feature = "bls")]
+impl ProtobufConvert for crate::bls::BlsSignature {
+    type ProtoStruct = schema::BlsSignature;
+
+    fn to_pb(&self) -> schema::BlsSignature {
+        let mut sign = schema::BlsSignature::new();
+        sign.set_data(self.as_bytes().to_vec());
+        sign
+    }
+
+    fn from_pb(pb: schema::BlsSignature) -> Result<Self, Error> {
+        Self::from_slice(pb.get_data()).ok_or_else(|| format_err!("Invalid BLS signature"))
+    }
+}
@@ -13,7 +13,10 @@
 // limitations under the License.
 
 use super::{schema::types, ProtobufConvert};
-use crate::{Hash, PublicKey, Signature, HASH_SIZE, PUBLIC_KEY_LENGTH, SIGNATURE_LENGTH};
+use crate::{
+    schemes::{AnyKeyPair, AnyPublicKey, AnySignature},
+    Hash, KeyPair, PublicKey, Signature, HASH_SIZE, PUBLIC_KEY_LENGTH, SIGNATURE_LENGTH,
+};
 
 #[test]
 fn test_hash_pb_convert() {
@@ -92,3 +95,60 @@ fn test_signature_wrong_pb_convert() {
     pb_sign.set_data([8; SIGNATURE_LENGTH - 1].to_vec());
     assert!(<Signature as ProtobufConvert>::from_pb(pb_sign).is_err());
 }
+
+#[test]
+fn test_any_keys_pb_convert() {
+    let keypair = KeyPair::random();
+    let any_keypair = AnyKeyPair::from(keypair.clone());
+
+    // Ed25519 keys and signatures have the same Protobuf representation in both forms.
+    let pb_key = any_keypair.public_key().to_pb();
+    assert_eq!(pb_key, keypair.public_key().to_pb());
+    let key: PublicKey = ProtobufConvert::from_pb(pb_key.clone()).unwrap();
+    assert_eq!(key, keypair.public_key());
+    let any_key: AnyPublicKey = ProtobufConvert::from_pb(pb_key).unwrap();
+    assert_eq!(any_key, *any_keypair.public_key());
+
+    let signature = any_keypair.sign(b"data");
+    let pb_sign = signature.to_pb();
+    assert_eq!(pb_sign, signature.as_ed25519().unwrap().to_pb());
+    let any_signature: AnySignature = ProtobufConvert::from_pb(pb_sign).unwrap();
+    assert_eq!(any_signature, signature);
+}
+
+#[test]
+#[cfg(feature = "ecdsa-secp256k1")]
+fn test_ecdsa_keys_pb_convert() {
+    use crate::schemes::SignatureScheme;
+
+    let keypair = AnyKeyPair::random(SignatureScheme::Secp256k1);
+    let pb_key = keypair.public_key().to_pb();
+    assert_eq!(pb_key.get_scheme(), types::SignatureScheme::SECP256K1);
+    let key: AnyPublicKey = ProtobufConvert::from_pb(pb_key.clone()).unwrap();
+    assert_eq!(key, *keypair.public_key());
+    // ECDSA keys cannot be parsed as Ed25519 ones.
+    assert!(<PublicKey as ProtobufConvert>::from_pb(pb_key).is_err());
+
+    let pb_sign = keypair.sign(b"data").to_pb();
+    let signature: AnySignature = ProtobufConvert::from_pb(pb_sign.clone()).unwrap();
+    assert!(keypair.public_key().verify(b"data", &signature));
+    assert!(<Signature as ProtobufConvert>::from_pb(pb_sign).is_err());
+}
+
+#[test]
+#[cfg(feature = "bls")]
+fn test_bls_pb_convert() {
+    use crate::bls::{BlsKeyPair, BlsPublicKey, BlsSignature};
+
+    let keys = BlsKeyPair::random();
+    let pb_key = keys.public_key().to_pb();
+    let key: BlsPublicKey = ProtobufConvert::from_pb(pb_key).unwrap();
+    assert_eq!(key, *keys.public_key());
+    let signature = keys.sign(b"data");
+    let restored: BlsSignature = ProtobufConvert::from_pb(signature.to_pb()).unwrap();
+    assert_eq!(restored, signature);
+
+    let mut pb_key = types::BlsPublicKey::new();
+    pb_key.set_data(vec![0; 48]);
+    assert!(<BlsPublicKey as ProtobufConvert>::from_pb(pb_key).is_err());
+}
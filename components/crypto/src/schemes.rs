@@ -0,0 +1,1092 @@
+// Copyright 2020 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Keys and signatures for several digital signature schemes.
+//!
+//! Ed25519 remains the main signature scheme in Exonum; for example, consensus messages
+//! must always be signed with Ed25519 keys. The types in this module allow to use
+//! other schemes where this is explicitly supported (e.g., to sign transactions):
+//!
+//! - ECDSA over secp256k1, the curve used in Bitcoin and Ethereum. Enabled with
+//!   the `ecdsa-secp256k1` crate feature.
+//! - ECDSA over NIST P-256 (aka secp256r1), the curve used by hardware authenticators.
+//!   Enabled with the `ecdsa-p256` crate feature.
+//! - Ethereum signatures, i.e., ECDSA signatures over secp256k1 created by Ethereum wallets
+//!   with `personal_sign`. Enabled with the `ethereum` crate feature.
+//! - WebAuthn assertions, i.e., ECDSA signatures over P-256 created by WebAuthn authenticators.
+//!   Enabled with the `webauthn` crate feature.
+//!
+//! ECDSA signatures are created over the SHA-256 digest of the signed data with deterministic
+//! nonces as per RFC 6979. A signature is encoded as 64 bytes (`r` followed by `s`)
+//! and must have the low-`s` form; otherwise, the signature would be malleable.
+//! ECDSA public keys (including Ethereum and WebAuthn ones) are encoded in the compressed
+//! SEC1 form (33 bytes).
+//!
+//! Ethereum signatures are created over the Keccak-256 digest of the signed data prefixed
+//! with `"\x19Ethereum Signed Message:\n"` and the decimal length of the data, as specified
+//! in [EIP-191]. A signature is encoded as 65 bytes: `r`, `s` and the recovery byte `v`,
+//! which must be equal to 27 or 28. As with other ECDSA schemes, `s` must be low.
+//!
+//! A WebAuthn assertion signs the authenticator data concatenated with the SHA-256 digest
+//! of the client data JSON. The client data must have the `webauthn.get` type, and its challenge
+//! must be equal to the SHA-256 digest of the signed data encoded with the URL-safe Base64
+//! alphabet without padding. The authenticator must confirm the user presence; the relying party
+//! ID is not checked. Assertions are converted into signatures with [`AnySignature::webauthn()`].
+//!
+//! [EIP-191]: https://eips.ethereum.org/EIPS/eip-191
+//! [`AnySignature::webauthn()`]: struct.AnySignature.html#method.webauthn
+//!
+//! # Examples
+//!
+//! ```
+//! # use exonum_crypto::{schemes::{AnyKeyPair, SignatureScheme}, KeyPair};
+//! # #[cfg(feature = "ecdsa-secp256k1")] {
+//! let keys = AnyKeyPair::random(SignatureScheme::Secp256k1);
+//! let signature = keys.sign(b"Hello, world!");
+//! assert!(keys.public_key().verify(b"Hello, world!", &signature));
+//! # }
+//!
+//! // Ed25519 keys can be converted to the generic representation.
+//! let keys = AnyKeyPair::from(KeyPair::random());
+//! assert_eq!(keys.public_key().scheme(), SignatureScheme::Ed25519);
+//! ```
+
+use anyhow::{bail, ensure, format_err};
+use hex::FromHex;
+use serde::{
+    de::{self, Deserialize, Deserializer},
+    Serialize, Serializer,
+};
+
+use std::{fmt, str::FromStr};
+
+use crate::{
+    crypto_impl, hash, KeyPair, PublicKey, SecretKey, Seed, Signature, PUBLIC_KEY_LENGTH,
+    SEED_LENGTH, SIGNATURE_LENGTH,
+};
+
+/// Length of an ECDSA public key in the compressed SEC1 form.
+pub const ECDSA_PUBLIC_KEY_LENGTH: usize = 33;
+/// Length of an ECDSA signature.
+pub const ECDSA_SIGNATURE_LENGTH: usize = 64;
+/// Length of an ECDSA secret key.
+pub const ECDSA_SECRET_KEY_LENGTH: usize = 32;
+/// Length of an Ethereum signature.
+pub const ETHEREUM_SIGNATURE_LENGTH: usize = 65;
+
+/// Digital signature scheme.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum SignatureScheme {
+    /// Ed25519 signatures.
+    Ed25519 = 0,
+    /// ECDSA signatures over the secp256k1 curve.
+    Secp256k1 = 1,
+    /// ECDSA signatures over the NIST P-256 curve.
+    P256 = 2,
+    /// ECDSA signatures over the secp256k1 curve created by Ethereum wallets
+    /// with `personal_sign`.
+    Ethereum = 3,
+    /// ECDSA signatures over the NIST P-256 curve created by WebAuthn authenticators.
+    WebAuthn = 4,
+}
+
+impl SignatureScheme {
+    /// Checks if the scheme is supported with the current set of crate features.
+    pub fn is_supported(self) -> bool {
+        match self {
+            Self::Ed25519 => true,
+            Self::Secp256k1 => cfg!(feature = "ecdsa-secp256k1"),
+            Self::P256 => cfg!(feature = "ecdsa-p256"),
+            Self::Ethereum => cfg!(feature = "ethereum"),
+            Self::WebAuthn => cfg!(feature = "webauthn"),
+        }
+    }
+
+    /// Returns the name of the scheme.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Ed25519 => "ed25519",
+            Self::Secp256k1 => "secp256k1",
+            Self::P256 => "p256",
+            Self::Ethereum => "ethereum",
+            Self::WebAuthn => "webauthn",
+        }
+    }
+
+    fn public_key_len(self) -> usize {
+        match self {
+            Self::Ed25519 => PUBLIC_KEY_LENGTH,
+            Self::Secp256k1 | Self::P256 | Self::Ethereum | Self::WebAuthn => {
+                ECDSA_PUBLIC_KEY_LENGTH
+            }
+        }
+    }
+
+    /// Returns the signature length, or `None` if signatures have variable length.
+    fn signature_len(self) -> Option<usize> {
+        match self {
+            Self::Ed25519 => Some(SIGNATURE_LENGTH),
+            Self::Secp256k1 | Self::P256 => Some(ECDSA_SIGNATURE_LENGTH),
+            Self::Ethereum => Some(ETHEREUM_SIGNATURE_LENGTH),
+            Self::WebAuthn => None,
+        }
+    }
+
+    fn ensure_supported(self) -> anyhow::Result<()> {
+        ensure!(
+            self.is_supported(),
+            "Signature scheme `{}` is not supported; enable the corresponding crate feature",
+            self
+        );
+        Ok(())
+    }
+}
+
+impl fmt::Display for SignatureScheme {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(self.name())
+    }
+}
+
+impl FromStr for SignatureScheme {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ed25519" => Ok(Self::Ed25519),
+            "secp256k1" => Ok(Self::Secp256k1),
+            "p256" => Ok(Self::P256),
+            "ethereum" => Ok(Self::Ethereum),
+            "webauthn" => Ok(Self::WebAuthn),
+            _ => bail!("Unknown signature scheme: {}", s),
+        }
+    }
+}
+
+/// Implements ECDSA operations for a curve from the `RustCrypto` collection.
+#[allow(unused_macros)] // The macro is unused if all ECDSA features are disabled.
+macro_rules! impl_ecdsa {
+    ($name:ident, $curve_crate:ident) => {
+        mod $name {
+            use $curve_crate::ecdsa::{
+                signature::{Signer, Verifier},
+                Signature, SigningKey, VerifyingKey,
+            };
+
+            pub(super) use $curve_crate::ecdsa::SigningKey as SecretKey;
+
+            pub(super) fn check_public_key(bytes: &[u8]) -> anyhow::Result<()> {
+                VerifyingKey::from_sec1_bytes(bytes)
+                    .map(drop)
+                    .map_err(|_| anyhow::format_err!("Invalid ECDSA public key"))
+            }
+
+            pub(super) fn secret_key(bytes: &[u8]) -> anyhow::Result<SigningKey> {
+                SigningKey::from_slice(bytes)
+                    .map_err(|_| anyhow::format_err!("Invalid ECDSA secret key"))
+            }
+
+            pub(super) fn public_key(secret_key: &SigningKey) -> Vec<u8> {
+                let point = secret_key.verifying_key().to_encoded_point(true);
+                point.as_bytes().to_vec()
+            }
+
+            pub(super) fn sign(secret_key: &SigningKey, data: &[u8]) -> Vec<u8> {
+                let signature: Signature = secret_key.sign(data);
+                let signature = signature.normalize_s().unwrap_or(signature);
+                signature.to_bytes().to_vec()
+            }
+
+            pub(super) fn verify(public_key: &[u8], data: &[u8], signature: &[u8]) -> bool {
+                let public_key = match VerifyingKey::from_sec1_bytes(public_key) {
+                    Ok(key) => key,
+                    Err(_) => return false,
+                };
+                let signature = match Signature::from_slice(signature) {
+                    Ok(signature) => signature,
+                    Err(_) => return false,
+                };
+                // Signatures with high `s` are malleable and are not accepted.
+                if signature.normalize_s().is_some() {
+                    return false;
+                }
+                public_key.verify(data, &signature).is_ok()
+            }
+        }
+    };
+}
+
+#[cfg(feature = "ecdsa-secp256k1")]
+impl_ecdsa!(secp256k1, k256);
+#[cfg(feature = "ecdsa-p256")]
+impl_ecdsa!(p256, p256);
+
+/// Ethereum signatures created with `personal_sign`.
+#[cfg(feature = "ethereum")]
+mod ethereum {
+    use k256::ecdsa::{RecoveryId, Signature, SigningKey, VerifyingKey};
+    use sha3::{Digest, Keccak256};
+
+    /// Offset of the recovery byte `v` in Ethereum signatures.
+    const RECOVERY_BYTE_OFFSET: u8 = 27;
+
+    /// Computes the digest of the data signed with `personal_sign` (EIP-191).
+    fn message_digest(data: &[u8]) -> [u8; 32] {
+        let mut hasher = Keccak256::new();
+        hasher.update(format!("\x19Ethereum Signed Message:\n{}", data.len()));
+        hasher.update(data);
+        hasher.finalize().into()
+    }
+
+    pub(super) fn sign(secret_key: &SigningKey, data: &[u8]) -> Vec<u8> {
+        // `k256` always produces signatures with low `s`.
+        let (signature, recovery_id) = secret_key
+            .sign_prehash_recoverable(&message_digest(data))
+            .expect("Cannot sign digest");
+        let mut bytes = signature.to_bytes().to_vec();
+        bytes.push(RECOVERY_BYTE_OFFSET + recovery_id.to_byte());
+        bytes
+    }
+
+    pub(super) fn verify(public_key: &[u8], data: &[u8], signature: &[u8]) -> bool {
+        let (signature, recovery_byte) = signature.split_at(signature.len() - 1);
+        // Only 27 and 28 are valid values of `v`; otherwise, the signature would be malleable.
+        let recovery_id = match recovery_byte[0].checked_sub(RECOVERY_BYTE_OFFSET) {
+            Some(byte @ 0..=1) => RecoveryId::from_byte(byte).unwrap(),
+            _ => return false,
+        };
+        let signature = match Signature::from_slice(signature) {
+            Ok(signature) => signature,
+            Err(_) => return false,
+        };
+        // Signatures with high `s` are malleable and are not accepted.
+        if signature.normalize_s().is_some() {
+            return false;
+        }
+
+        let digest = message_digest(data);
+        VerifyingKey::recover_from_prehash(&digest, &signature, recovery_id).map_or(false, |key| {
+            key.to_encoded_point(true).as_bytes() == public_key
+        })
+    }
+}
+
+/// WebAuthn assertions.
+///
+/// An assertion is encoded as the big-endian 2-byte length of the authenticator data,
+/// the authenticator data, the client data JSON and the ECDSA signature (64 bytes).
+#[cfg(feature = "webauthn")]
+mod webauthn {
+    use anyhow::{ensure, format_err};
+    use p256::ecdsa::{signature::Verifier, Signature, VerifyingKey};
+    use serde_derive::Deserialize;
+
+    use std::convert::TryFrom;
+
+    use super::ECDSA_SIGNATURE_LENGTH;
+    use crate::hash;
+
+    /// Minimum length of the authenticator data: the relying party ID hash (32 bytes),
+    /// flags (1 byte) and the signature counter (4 bytes).
+    const MIN_AUTHENTICATOR_DATA_LENGTH: usize = 37;
+    /// Offset of flags in the authenticator data.
+    const FLAGS_OFFSET: usize = 32;
+    /// Flag signalling that the user was present during the assertion.
+    const USER_PRESENT: u8 = 0x01;
+    /// Type of client data for assertions.
+    const ASSERTION_TYPE: &str = "webauthn.get";
+
+    #[derive(Debug, Deserialize)]
+    struct ClientData {
+        #[serde(rename = "type")]
+        ty: String,
+        challenge: String,
+    }
+
+    /// Parts of an encoded assertion.
+    struct Assertion<'a> {
+        authenticator_data: &'a [u8],
+        client_data_json: &'a [u8],
+        signature: &'a [u8],
+    }
+
+    impl<'a> Assertion<'a> {
+        fn parse(bytes: &'a [u8]) -> anyhow::Result<Self> {
+            ensure!(bytes.len() >= 2, "WebAuthn signature is too short");
+            let (len, rest) = bytes.split_at(2);
+            let authenticator_data_len = usize::from(u16::from_be_bytes([len[0], len[1]]));
+            ensure!(
+                authenticator_data_len >= MIN_AUTHENTICATOR_DATA_LENGTH,
+                "WebAuthn authenticator data is too short"
+            );
+            ensure!(
+                rest.len() >= authenticator_data_len + ECDSA_SIGNATURE_LENGTH,
+                "WebAuthn signature is too short"
+            );
+            let (authenticator_data, rest) = rest.split_at(authenticator_data_len);
+            let (client_data_json, signature) = rest.split_at(rest.len() - ECDSA_SIGNATURE_LENGTH);
+            Ok(Self {
+                authenticator_data,
+                client_data_json,
+                signature,
+            })
+        }
+
+        fn signed_message(&self) -> Vec<u8> {
+            let mut message = self.authenticator_data.to_vec();
+            message.extend_from_slice(hash(self.client_data_json).as_ref());
+            message
+        }
+    }
+
+    pub(super) fn check_signature(bytes: &[u8]) -> anyhow::Result<()> {
+        Assertion::parse(bytes).map(drop)
+    }
+
+    pub(super) fn encode(
+        authenticator_data: &[u8],
+        client_data_json: &[u8],
+        der_signature: &[u8],
+    ) -> anyhow::Result<Vec<u8>> {
+        let authenticator_data_len = u16::try_from(authenticator_data.len())
+            .map_err(|_| format_err!("WebAuthn authenticator data is too long"))?;
+        ensure!(
+            authenticator_data.len() >= MIN_AUTHENTICATOR_DATA_LENGTH,
+            "WebAuthn authenticator data is too short"
+        );
+        let signature = Signature::from_der(der_signature)
+            .map_err(|_| format_err!("Invalid DER encoding of WebAuthn signature"))?;
+        // Authenticators do not necessarily produce signatures with low `s`.
+        let signature = signature.normalize_s().unwrap_or(signature);
+
+        let mut bytes = authenticator_data_len.to_be_bytes().to_vec();
+        bytes.extend_from_slice(authenticator_data);
+        bytes.extend_from_slice(client_data_json);
+        bytes.extend_from_slice(&signature.to_bytes());
+        Ok(bytes)
+    }
+
+    /// Encodes the challenge expected in the client data for the signed `data`.
+    pub(super) fn challenge(data: &[u8]) -> String {
+        base64::encode_config(hash(data).as_ref(), base64::URL_SAFE_NO_PAD)
+    }
+
+    pub(super) fn verify(public_key: &[u8], data: &[u8], signature: &[u8]) -> bool {
+        let assertion = match Assertion::parse(signature) {
+            Ok(assertion) => assertion,
+            Err(_) => return false,
+        };
+        if assertion.authenticator_data[FLAGS_OFFSET] & USER_PRESENT == 0 {
+            return false;
+        }
+        let client_data: ClientData = match serde_json::from_slice(assertion.client_data_json) {
+            Ok(client_data) => client_data,
+            Err(_) => return false,
+        };
+        if client_data.ty != ASSERTION_TYPE || client_data.challenge != challenge(data) {
+            return false;
+        }
+
+        let public_key = match VerifyingKey::from_sec1_bytes(public_key) {
+            Ok(key) => key,
+            Err(_) => return false,
+        };
+        let signature = match Signature::from_slice(assertion.signature) {
+            Ok(signature) => signature,
+            Err(_) => return false,
+        };
+        // Signatures with high `s` are malleable and are not accepted.
+        if signature.normalize_s().is_some() {
+            return false;
+        }
+        public_key
+            .verify(&assertion.signed_message(), &signature)
+            .is_ok()
+    }
+}
+
+/// Public key of any supported signature scheme.
+///
+/// # JSON serialization
+///
+/// Ed25519 keys are serialized as a hex string, like `PublicKey`s. Keys of other schemes
+/// are serialized as the scheme name and the hex-encoded key separated by a colon,
+/// e.g., `secp256k1:02f9...`.
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AnyPublicKey {
+    scheme: SignatureScheme,
+    bytes: Vec<u8>,
+}
+
+impl AnyPublicKey {
+    /// Creates a public key from the provided bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the scheme is not supported or the bytes do not represent
+    /// a valid public key. ECDSA keys must be in the compressed form.
+    pub fn new(scheme: SignatureScheme, bytes: &[u8]) -> anyhow::Result<Self> {
+        scheme.ensure_supported()?;
+        ensure!(
+            bytes.len() == scheme.public_key_len(),
+            "Invalid length of `{}` public key: expected {} bytes, got {}",
+            scheme,
+            scheme.public_key_len(),
+            bytes.len()
+        );
+        match scheme {
+            SignatureScheme::Ed25519 => {}
+            #[cfg(feature = "ecdsa-secp256k1")]
+            SignatureScheme::Secp256k1 => secp256k1::check_public_key(bytes)?,
+            #[cfg(feature = "ecdsa-p256")]
+            SignatureScheme::P256 => p256::check_public_key(bytes)?,
+            #[cfg(feature = "ethereum")]
+            SignatureScheme::Ethereum => secp256k1::check_public_key(bytes)?,
+            #[cfg(feature = "webauthn")]
+            SignatureScheme::WebAuthn => p256::check_public_key(bytes)?,
+            #[allow(unreachable_patterns)]
+            _ => unreachable!("Unsupported schemes are filtered above"),
+        }
+
+        Ok(Self {
+            scheme,
+            bytes: bytes.to_vec(),
+        })
+    }
+
+    /// Returns the signature scheme of the key.
+    pub fn scheme(&self) -> SignatureScheme {
+        self.scheme
+    }
+
+    /// Returns the binary representation of the key.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Returns the Ed25519 key if the key belongs to this scheme.
+    pub fn as_ed25519(&self) -> Option<PublicKey> {
+        if self.scheme == SignatureScheme::Ed25519 {
+            PublicKey::from_slice(&self.bytes)
+        } else {
+            None
+        }
+    }
+
+    /// Returns a 32-byte identifier of the key, which can be used in place of `PublicKey`s,
+    /// for example, to identify transaction authors.
+    ///
+    /// The identifier of an Ed25519 key is the key itself. For other schemes, the identifier
+    /// is the hash of the scheme tag and the key bytes; thus, it *cannot* be used to verify
+    /// signatures.
+    pub fn key_id(&self) -> PublicKey {
+        if let Some(key) = self.as_ed25519() {
+            return key;
+        }
+
+        let mut buffer = Vec::with_capacity(1 + self.bytes.len());
+        buffer.push(self.scheme as u8);
+        buffer.extend_from_slice(&self.bytes);
+        PublicKey::new(hash(&buffer).as_bytes())
+    }
+
+    /// Verifies that `data` is signed with a secret key corresponding to this public key.
+    /// Signatures of other schemes are never valid.
+    pub fn verify(&self, data: &[u8], signature: &AnySignature) -> bool {
+        if signature.scheme != self.scheme {
+            return false;
+        }
+        match self.scheme {
+            SignatureScheme::Ed25519 => match (self.as_ed25519(), signature.as_ed25519()) {
+                (Some(public_key), Some(signature)) => crate::verify(&signature, data, &public_key),
+                _ => false,
+            },
+            #[cfg(feature = "ecdsa-secp256k1")]
+            SignatureScheme::Secp256k1 => secp256k1::verify(&self.bytes, data, &signature.bytes),
+            #[cfg(feature = "ecdsa-p256")]
+            SignatureScheme::P256 => p256::verify(&self.bytes, data, &signature.bytes),
+            #[cfg(feature = "ethereum")]
+            SignatureScheme::Ethereum => ethereum::verify(&self.bytes, data, &signature.bytes),
+            #[cfg(feature = "webauthn")]
+            SignatureScheme::WebAuthn => webauthn::verify(&self.bytes, data, &signature.bytes),
+            #[allow(unreachable_patterns)]
+            _ => false,
+        }
+    }
+}
+
+impl From<PublicKey> for AnyPublicKey {
+    fn from(key: PublicKey) -> Self {
+        Self {
+            scheme: SignatureScheme::Ed25519,
+            bytes: key.as_ref().to_vec(),
+        }
+    }
+}
+
+/// Digital signature of any supported signature scheme.
+///
+/// Signatures are serialized to JSON in the same way as [`AnyPublicKey`]s.
+///
+/// [`AnyPublicKey`]: struct.AnyPublicKey.html
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AnySignature {
+    scheme: SignatureScheme,
+    bytes: Vec<u8>,
+}
+
+impl AnySignature {
+    /// Creates a signature from the provided bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the scheme is not supported or the signature has an invalid length.
+    pub fn new(scheme: SignatureScheme, bytes: &[u8]) -> anyhow::Result<Self> {
+        scheme.ensure_supported()?;
+        if let Some(signature_len) = scheme.signature_len() {
+            ensure!(
+                bytes.len() == signature_len,
+                "Invalid length of `{}` signature: expected {} bytes, got {}",
+                scheme,
+                signature_len,
+                bytes.len()
+            );
+        }
+        #[cfg(feature = "webauthn")]
+        {
+            if scheme == SignatureScheme::WebAuthn {
+                webauthn::check_signature(bytes)?;
+            }
+        }
+
+        Ok(Self {
+            scheme,
+            bytes: bytes.to_vec(),
+        })
+    }
+
+    /// Creates a signature from a WebAuthn assertion: the authenticator data, the client
+    /// data JSON and the DER-encoded ECDSA signature returned by the authenticator.
+    /// The challenge for the assertion is returned by [`webauthn_challenge()`].
+    ///
+    /// The assertion is not verified by this method.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the authenticator data or the signature are malformed.
+    ///
+    /// [`webauthn_challenge()`]: #method.webauthn_challenge
+    #[cfg(feature = "webauthn")]
+    pub fn webauthn(
+        authenticator_data: &[u8],
+        client_data_json: &[u8],
+        der_signature: &[u8],
+    ) -> anyhow::Result<Self> {
+        let bytes = webauthn::encode(authenticator_data, client_data_json, der_signature)?;
+        Ok(Self {
+            scheme: SignatureScheme::WebAuthn,
+            bytes,
+        })
+    }
+
+    /// Returns the challenge for a WebAuthn assertion signing `data`, which should be passed
+    /// to the authenticator. The client data JSON of the assertion contains this challenge
+    /// encoded with the URL-safe Base64 alphabet without padding.
+    #[cfg(feature = "webauthn")]
+    pub fn webauthn_challenge(data: &[u8]) -> crate::Hash {
+        hash(data)
+    }
+
+    /// Returns the signature scheme of the signature.
+    pub fn scheme(&self) -> SignatureScheme {
+        self.scheme
+    }
+
+    /// Returns the binary representation of the signature.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Returns the Ed25519 signature if the signature belongs to this scheme.
+    pub fn as_ed25519(&self) -> Option<Signature> {
+        if self.scheme == SignatureScheme::Ed25519 {
+            Signature::from_slice(&self.bytes)
+        } else {
+            None
+        }
+    }
+}
+
+impl From<Signature> for AnySignature {
+    fn from(signature: Signature) -> Self {
+        Self {
+            scheme: SignatureScheme::Ed25519,
+            bytes: signature.as_ref().to_vec(),
+        }
+    }
+}
+
+macro_rules! implement_any_serde {
+    ($name:ident) => {
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                let mut hex = String::with_capacity(
+                    self.scheme.name().len()
+                        + 1
+                        + crate::BYTES_IN_DEBUG * 2
+                        + crate::BYTES_IN_ELLIPSIS,
+                );
+                hex.push_str(self.scheme.name());
+                hex.push(':');
+                crate::write_short_hex(&mut hex, &self.bytes)?;
+                f.debug_tuple(stringify!($name)).field(&hex).finish()
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                if self.scheme != SignatureScheme::Ed25519 {
+                    write!(f, "{}:", self.scheme)?;
+                }
+                f.write_str(&hex::encode(&self.bytes))
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = anyhow::Error;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                let mut parts = s.splitn(2, ':');
+                let first_part = parts.next().unwrap();
+                let (scheme, hex_bytes) = if let Some(hex_bytes) = parts.next() {
+                    (first_part.parse()?, hex_bytes)
+                } else {
+                    (SignatureScheme::Ed25519, first_part)
+                };
+                let bytes = Vec::<u8>::from_hex(hex_bytes).map_err(|e| format_err!("{}", e))?;
+                Self::new(scheme, &bytes)
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: Serializer,
+            {
+                serializer.serialize_str(&self.to_string())
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                let s = String::deserialize(deserializer)?;
+                s.parse().map_err(de::Error::custom)
+            }
+        }
+    };
+}
+
+implement_any_serde!(AnyPublicKey);
+implement_any_serde!(AnySignature);
+
+const WEBAUTHN_KEYS_ERROR: &str =
+    "WebAuthn secret keys are held by authenticators; use `AnySignature::webauthn()` \
+     to convert assertions into signatures";
+
+#[derive(Clone)]
+enum AnySecretKey {
+    Ed25519(SecretKey),
+    #[cfg(feature = "ecdsa-secp256k1")]
+    Secp256k1(secp256k1::SecretKey),
+    #[cfg(feature = "ecdsa-p256")]
+    P256(p256::SecretKey),
+    #[cfg(feature = "ethereum")]
+    Ethereum(secp256k1::SecretKey),
+}
+
+/// Pair of matching secret and public keys of any supported signature scheme.
+#[derive(Clone)]
+pub struct AnyKeyPair {
+    public_key: AnyPublicKey,
+    secret_key: AnySecretKey,
+}
+
+impl fmt::Debug for AnyKeyPair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AnyKeyPair")
+            .field("public_key", &self.public_key)
+            .finish()
+    }
+}
+
+impl AnyKeyPair {
+    /// Generates a random keypair for the specified scheme.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the scheme is not supported, or if it is `WebAuthn`, the secret keys
+    /// of which are held by authenticators.
+    pub fn random(scheme: SignatureScheme) -> Self {
+        if let Err(e) = scheme.ensure_supported() {
+            panic!("{}", e);
+        }
+        match scheme {
+            SignatureScheme::Ed25519 => return KeyPair::random().into(),
+            SignatureScheme::WebAuthn => panic!("{}", WEBAUTHN_KEYS_ERROR),
+            _ => {}
+        }
+
+        loop {
+            let mut bytes = [0_u8; ECDSA_SECRET_KEY_LENGTH];
+            crypto_impl::fill_random_bytes(&mut bytes);
+            // The conversion fails with negligible probability if the bytes are out of range
+            // for the curve order.
+            if let Ok(keypair) = Self::from_secret_bytes(scheme, &bytes) {
+                break keypair;
+            }
+        }
+    }
+
+    /// Creates a keypair from the binary representation of a secret key. For Ed25519,
+    /// the secret key is represented by a 32-byte seed; for ECDSA schemes, it is a 32-byte
+    /// big-endian scalar (e.g., as used for private keys in Ethereum).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the scheme is not supported or the bytes do not represent
+    /// a valid secret key. Keypairs cannot be created for the `WebAuthn` scheme, since
+    /// its secret keys are held by authenticators.
+    pub fn from_secret_bytes(scheme: SignatureScheme, bytes: &[u8]) -> anyhow::Result<Self> {
+        scheme.ensure_supported()?;
+        match scheme {
+            SignatureScheme::Ed25519 => {
+                let seed = Seed::from_slice(bytes).ok_or_else(|| {
+                    format_err!("Invalid Ed25519 seed length; expected {}", SEED_LENGTH)
+                })?;
+                Ok(KeyPair::from_seed(&seed).into())
+            }
+            #[cfg(feature = "ecdsa-secp256k1")]
+            SignatureScheme::Secp256k1 => {
+                let secret_key = secp256k1::secret_key(bytes)?;
+                Ok(Self {
+                    public_key: AnyPublicKey {
+                        scheme,
+                        bytes: secp256k1::public_key(&secret_key),
+                    },
+                    secret_key: AnySecretKey::Secp256k1(secret_key),
+                })
+            }
+            #[cfg(feature = "ecdsa-p256")]
+            SignatureScheme::P256 => {
+                let secret_key = p256::secret_key(bytes)?;
+                Ok(Self {
+                    public_key: AnyPublicKey {
+                        scheme,
+                        bytes: p256::public_key(&secret_key),
+                    },
+                    secret_key: AnySecretKey::P256(secret_key),
+                })
+            }
+            #[cfg(feature = "ethereum")]
+            SignatureScheme::Ethereum => {
+                let secret_key = secp256k1::secret_key(bytes)?;
+                Ok(Self {
+                    public_key: AnyPublicKey {
+                        scheme,
+                        bytes: secp256k1::public_key(&secret_key),
+                    },
+                    secret_key: AnySecretKey::Ethereum(secret_key),
+                })
+            }
+            SignatureScheme::WebAuthn => bail!(WEBAUTHN_KEYS_ERROR),
+            #[allow(unreachable_patterns)]
+            _ => unreachable!("Unsupported schemes are filtered above"),
+        }
+    }
+
+    /// Returns the public key.
+    pub fn public_key(&self) -> &AnyPublicKey {
+        &self.public_key
+    }
+
+    /// Signs the provided data.
+    pub fn sign(&self, data: &[u8]) -> AnySignature {
+        let scheme = self.public_key.scheme;
+        let bytes = match &self.secret_key {
+            AnySecretKey::Ed25519(secret_key) => crate::sign(data, secret_key).as_ref().to_vec(),
+            #[cfg(feature = "ecdsa-secp256k1")]
+            AnySecretKey::Secp256k1(secret_key) => secp256k1::sign(secret_key, data),
+            #[cfg(feature = "ecdsa-p256")]
+            AnySecretKey::P256(secret_key) => p256::sign(secret_key, data),
+            #[cfg(feature = "ethereum")]
+            AnySecretKey::Ethereum(secret_key) => ethereum::sign(secret_key, data),
+        };
+        AnySignature { scheme, bytes }
+    }
+}
+
+impl From<KeyPair> for AnyKeyPair {
+    fn from(keypair: KeyPair) -> Self {
+        Self {
+            public_key: keypair.public_key().into(),
+            secret_key: AnySecretKey::Ed25519(keypair.secret_key().clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns supported schemes for which keypairs can be created.
+    fn supported_schemes() -> Vec<SignatureScheme> {
+        vec![
+            SignatureScheme::Ed25519,
+            SignatureScheme::Secp256k1,
+            SignatureScheme::P256,
+            SignatureScheme::Ethereum,
+        ]
+        .into_iter()
+        .filter(|scheme| scheme.is_supported())
+        .collect()
+    }
+
+    #[test]
+    fn sign_and_verify() {
+        for scheme in supported_schemes() {
+            let keys = AnyKeyPair::random(scheme);
+            let public_key = keys.public_key();
+            assert_eq!(public_key.scheme(), scheme);
+            assert_eq!(public_key.as_bytes().len(), scheme.public_key_len());
+
+            let signature = keys.sign(b"data");
+            assert_eq!(signature.scheme(), scheme);
+            assert!(public_key.verify(b"data", &signature));
+            assert!(!public_key.verify(b"other data", &signature));
+            let other_keys = AnyKeyPair::random(scheme);
+            assert!(!other_keys.public_key().verify(b"data", &signature));
+        }
+    }
+
+    #[test]
+    fn signatures_of_other_schemes_are_invalid() {
+        let ed25519_keys = AnyKeyPair::random(SignatureScheme::Ed25519);
+        let signature = ed25519_keys.sign(b"data");
+        for scheme in supported_schemes() {
+            if scheme == SignatureScheme::Ed25519 {
+                continue;
+            }
+            let keys = AnyKeyPair::random(scheme);
+            let forged_signature = AnySignature {
+                scheme,
+                bytes: signature.bytes.clone(),
+            };
+            assert!(!keys.public_key().verify(b"data", &forged_signature));
+            assert!(!keys.public_key().verify(b"data", &signature));
+            assert!(!ed25519_keys
+                .public_key()
+                .verify(b"data", &keys.sign(b"data")));
+        }
+    }
+
+    #[test]
+    fn ed25519_compatibility() {
+        let keypair = KeyPair::random();
+        let any_keypair = AnyKeyPair::from(keypair.clone());
+        assert_eq!(
+            any_keypair.public_key().as_ed25519(),
+            Some(keypair.public_key())
+        );
+        assert_eq!(any_keypair.public_key().key_id(), keypair.public_key());
+
+        let signature = any_keypair.sign(b"data");
+        assert_eq!(
+            signature.as_ed25519(),
+            Some(crate::sign(b"data", keypair.secret_key()))
+        );
+        assert_eq!(
+            AnyPublicKey::from(keypair.public_key()).to_string(),
+            keypair.public_key().to_hex()
+        );
+    }
+
+    #[test]
+    fn string_conversions() {
+        for scheme in supported_schemes() {
+            let keys = AnyKeyPair::random(scheme);
+            let public_key = keys.public_key();
+            let key_string = public_key.to_string();
+            assert_eq!(key_string.parse::<AnyPublicKey>().unwrap(), *public_key);
+            let json = serde_json::to_value(public_key).unwrap();
+            assert_eq!(json, key_string);
+            let restored: AnyPublicKey = serde_json::from_value(json).unwrap();
+            assert_eq!(restored, *public_key);
+
+            let signature = keys.sign(b"data");
+            let restored: AnySignature = signature.to_string().parse().unwrap();
+            assert_eq!(restored, signature);
+        }
+
+        let err = "foo:00".parse::<AnyPublicKey>().unwrap_err();
+        assert!(err.to_string().contains("Unknown signature scheme"));
+        let err = "ed25519:00".parse::<AnyPublicKey>().unwrap_err();
+        assert!(err.to_string().contains("Invalid length"));
+    }
+
+    #[test]
+    #[cfg(feature = "ecdsa-secp256k1")]
+    fn secp256k1_known_key() {
+        // Private key with value 1 corresponds to the generator of the curve.
+        let mut secret = [0_u8; 32];
+        secret[31] = 1;
+        let keys = AnyKeyPair::from_secret_bytes(SignatureScheme::Secp256k1, &secret).unwrap();
+        assert_eq!(
+            hex::encode(keys.public_key().as_bytes()),
+            "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798"
+        );
+        assert_ne!(
+            keys.public_key().key_id().as_ref(),
+            &keys.public_key().as_bytes()[1..]
+        );
+
+        // Uncompressed keys are not accepted.
+        let mut uncompressed = vec![4];
+        uncompressed.extend_from_slice(&[0; 64]);
+        assert!(AnyPublicKey::new(SignatureScheme::Secp256k1, &uncompressed).is_err());
+        // Invalid points are not accepted.
+        let mut invalid_point = [0xff; ECDSA_PUBLIC_KEY_LENGTH];
+        invalid_point[0] = 2;
+        assert!(AnyPublicKey::new(SignatureScheme::Secp256k1, &invalid_point).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "ecdsa-p256")]
+    fn high_s_signatures_are_rejected() {
+        use ::p256::{ecdsa::Signature as EcdsaSignature, elliptic_curve::ops::Neg};
+
+        let keys = AnyKeyPair::random(SignatureScheme::P256);
+        let signature = keys.sign(b"data");
+        let ecdsa_signature = EcdsaSignature::from_slice(signature.as_bytes()).unwrap();
+        let (r, s) = ecdsa_signature.split_scalars();
+        let high_s_signature = EcdsaSignature::from_scalars(r, s.neg()).unwrap();
+        let malleated =
+            AnySignature::new(SignatureScheme::P256, high_s_signature.to_bytes().as_ref()).unwrap();
+        assert!(!keys.public_key().verify(b"data", &malleated));
+    }
+
+    #[test]
+    #[cfg(feature = "ethereum")]
+    fn ethereum_known_signature() {
+        // Test vector from the `web3.eth.accounts.sign()` documentation.
+        let secret =
+            Vec::<u8>::from_hex("4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318")
+                .unwrap();
+        let keys = AnyKeyPair::from_secret_bytes(SignatureScheme::Ethereum, &secret).unwrap();
+        let signature = keys.sign(b"Some data");
+        assert_eq!(
+            hex::encode(signature.as_bytes()),
+            "b91467e570a6466aa9e9876cbcd013baba02900b8979d43fe208a4a4f339f5fd\
+             6007e74cd82e037b800186422fc2da167c747ef045e5d18a5f5d4300f8e1a029\
+             1c"
+        );
+        assert!(keys.public_key().verify(b"Some data", &signature));
+
+        // Signatures with a non-canonical recovery byte are rejected.
+        for &recovery_byte in &[0, 1, 27, 29] {
+            let mut bytes = signature.as_bytes().to_vec();
+            bytes[ETHEREUM_SIGNATURE_LENGTH - 1] = recovery_byte;
+            let signature = AnySignature::new(SignatureScheme::Ethereum, &bytes).unwrap();
+            assert!(!keys.public_key().verify(b"Some data", &signature));
+        }
+
+        // Ethereum signatures are not valid plain secp256k1 signatures, and vice versa.
+        let secp256k1_keys =
+            AnyKeyPair::from_secret_bytes(SignatureScheme::Secp256k1, &secret).unwrap();
+        assert_eq!(
+            secp256k1_keys.public_key().as_bytes(),
+            keys.public_key().as_bytes()
+        );
+        let secp256k1_signature = secp256k1_keys.sign(b"Some data");
+        assert!(!keys.public_key().verify(b"Some data", &secp256k1_signature));
+        assert!(!secp256k1_keys.public_key().verify(b"Some data", &signature));
+    }
+
+    #[cfg(feature = "webauthn")]
+    fn webauthn_assertion(
+        signing_key: &::p256::ecdsa::SigningKey,
+        flags: u8,
+        client_data_json: &str,
+    ) -> AnySignature {
+        use ::p256::ecdsa::{signature::Signer, Signature as EcdsaSignature};
+
+        let mut authenticator_data = vec![0xaa; 32];
+        authenticator_data.push(flags);
+        authenticator_data.extend_from_slice(&[0, 0, 0, 1]);
+        let mut message = authenticator_data.clone();
+        message.extend_from_slice(hash(client_data_json.as_bytes()).as_ref());
+        let signature: EcdsaSignature = signing_key.sign(&message);
+        AnySignature::webauthn(
+            &authenticator_data,
+            client_data_json.as_bytes(),
+            signature.to_der().as_bytes(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    #[cfg(feature = "webauthn")]
+    fn webauthn_signatures() {
+        use ::p256::ecdsa::SigningKey;
+
+        let signing_key = SigningKey::from_slice(&[1; 32]).unwrap();
+        let point = signing_key.verifying_key().to_encoded_point(true);
+        let public_key = AnyPublicKey::new(SignatureScheme::WebAuthn, point.as_bytes()).unwrap();
+
+        let challenge = base64::encode_config(
+            AnySignature::webauthn_challenge(b"data").as_ref(),
+            base64::URL_SAFE_NO_PAD,
+        );
+        let client_data = format!(
+            r#"{{"type":"webauthn.get","challenge":"{}","origin":"https://example.com"}}"#,
+            challenge
+        );
+        let signature = webauthn_assertion(&signing_key, 0x05, &client_data);
+        assert_eq!(signature.scheme(), SignatureScheme::WebAuthn);
+        assert!(public_key.verify(b"data", &signature));
+        assert!(!public_key.verify(b"other data", &signature));
+        let restored: AnySignature = signature.to_string().parse().unwrap();
+        assert_eq!(restored, signature);
+
+        // The user must be present.
+        let signature = webauthn_assertion(&signing_key, 0x04, &client_data);
+        assert!(!public_key.verify(b"data", &signature));
+        // Registration client data is not accepted.
+        let registration_data = client_data.replace("webauthn.get", "webauthn.create");
+        let signature = webauthn_assertion(&signing_key, 0x05, &registration_data);
+        assert!(!public_key.verify(b"data", &signature));
+
+        // Malformed assertions are rejected.
+        assert!(AnySignature::new(SignatureScheme::WebAuthn, &[0; 64]).is_err());
+        let err = AnySignature::webauthn(&[0; 32], b"{}", &[0; 8]).unwrap_err();
+        assert!(err.to_string().contains("authenticator data is too short"));
+        let err = AnyKeyPair::from_secret_bytes(SignatureScheme::WebAuthn, &[1; 32]).unwrap_err();
+        assert!(err.to_string().contains("held by authenticators"));
+    }
+}
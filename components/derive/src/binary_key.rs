@@ -0,0 +1,272 @@
+// Copyright 2020 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use proc_macro::TokenStream;
+use proc_macro2::{Ident, Span};
+use quote::{quote, ToTokens};
+use syn::{spanned::Spanned, Data, DeriveInput, Fields, Generics};
+
+/// Maximum number of enum variants; the variant tag is encoded as a single byte.
+const MAX_VARIANTS: usize = 256;
+
+#[derive(Debug)]
+struct BinaryKey<'a> {
+    ident: &'a Ident,
+    generics: &'a Generics,
+    kind: KeyKind<'a>,
+}
+
+#[derive(Debug)]
+enum KeyKind<'a> {
+    Struct(KeyFields<'a>),
+    Enum(Vec<KeyFields<'a>>),
+}
+
+/// Fields of a struct or an enum variant.
+#[derive(Debug)]
+struct KeyFields<'a> {
+    path: proc_macro2::TokenStream,
+    fields: &'a Fields,
+}
+
+impl<'a> KeyFields<'a> {
+    fn new(path: proc_macro2::TokenStream, fields: &'a Fields) -> Self {
+        Self { path, fields }
+    }
+
+    fn bindings(&self) -> Vec<Ident> {
+        (0..self.fields.len())
+            .map(|i| Ident::new(&format!("__field{}", i), Span::call_site()))
+            .collect()
+    }
+
+    /// Pattern or expression to destructure or construct the fields from the bindings.
+    fn pattern(&self) -> proc_macro2::TokenStream {
+        let path = &self.path;
+        let bindings = self.bindings();
+        match self.fields {
+            Fields::Named(fields) => {
+                let names = fields.named.iter().map(|field| &field.ident);
+                quote!(#path { #(#names: #bindings,)* })
+            }
+            Fields::Unnamed(_) => quote!(#path(#(#bindings,)*)),
+            Fields::Unit => quote!(#path),
+        }
+    }
+
+    /// Splits bindings and field types into non-last and last ones.
+    fn split_last(&self) -> (Vec<(Ident, &syn::Type)>, Option<Ident>) {
+        let mut fields: Vec<_> = self
+            .bindings()
+            .into_iter()
+            .zip(self.fields.iter().map(|field| &field.ty))
+            .collect();
+        let last = fields.pop().map(|(binding, _)| binding);
+        (fields, last)
+    }
+
+    fn size(&self) -> impl ToTokens {
+        let (fields, last) = self.split_last();
+        let sizes = fields.iter().map(
+            |(binding, _)| quote!(exonum_merkledb::_reexports::composite::field_size(#binding)),
+        );
+        let last_size = last.map(|binding| quote!(+ exonum_merkledb::BinaryKey::size(#binding)));
+        quote!(0 #(+ #sizes)* #last_size)
+    }
+
+    fn write(&self) -> impl ToTokens {
+        let (fields, last) = self.split_last();
+        let writes = fields.iter().map(|(binding, _)| {
+            quote! {
+                pos += exonum_merkledb::_reexports::composite::write_field(
+                    #binding,
+                    &mut buffer[pos..],
+                );
+            }
+        });
+        let last_write = last.map(|binding| {
+            quote!(pos += exonum_merkledb::BinaryKey::write(#binding, &mut buffer[pos..]);)
+        });
+        quote!(#(#writes)* #last_write)
+    }
+
+    fn read(&self) -> impl ToTokens {
+        let (fields, last) = self.split_last();
+        let reads = fields.iter().map(|(binding, ty)| {
+            quote! {
+                let (#binding, len) =
+                    exonum_merkledb::_reexports::composite::read_field::<#ty>(&buffer[pos..]);
+                pos += len;
+            }
+        });
+        let last_read = last.map(|binding| {
+            let ty = &self.fields.iter().last().unwrap().ty;
+            quote!(let #binding = <#ty as exonum_merkledb::BinaryKey>::read(&buffer[pos..]);)
+        });
+        let pattern = self.pattern();
+        quote! {
+            #(#reads)*
+            #last_read
+            #pattern
+        }
+    }
+
+    fn fixed_size(&self) -> impl ToTokens {
+        let sizes = self
+            .fields
+            .iter()
+            .map(|field| &field.ty)
+            .map(|ty| quote!(<#ty as exonum_merkledb::BinaryKey>::fixed_size()));
+        quote!(exonum_merkledb::_reexports::composite::fields_size(&[#(#sizes,)*]))
+    }
+}
+
+impl<'a> BinaryKey<'a> {
+    fn from_derive_input(input: &'a DeriveInput) -> Result<Self, syn::Error> {
+        let kind = match &input.data {
+            Data::Struct(data) => KeyKind::Struct(KeyFields::new(quote!(Self), &data.fields)),
+            Data::Enum(data) => {
+                if data.variants.is_empty() {
+                    let msg = "`BinaryKey` cannot be derived for enums without variants";
+                    return Err(syn::Error::new(input.span(), msg));
+                }
+                if data.variants.len() > MAX_VARIANTS {
+                    let msg = format!(
+                        "`BinaryKey` can be derived for enums with at most {} variants",
+                        MAX_VARIANTS
+                    );
+                    return Err(syn::Error::new(input.span(), msg));
+                }
+                let variants = data
+                    .variants
+                    .iter()
+                    .map(|variant| {
+                        let ident = &variant.ident;
+                        KeyFields::new(quote!(Self::#ident), &variant.fields)
+                    })
+                    .collect();
+                KeyKind::Enum(variants)
+            }
+            Data::Union(_) => {
+                let msg = "`BinaryKey` cannot be derived for unions";
+                return Err(syn::Error::new(input.span(), msg));
+            }
+        };
+
+        Ok(Self {
+            ident: &input.ident,
+            generics: &input.generics,
+            kind,
+        })
+    }
+
+    fn implement_struct(fields: &KeyFields<'_>) -> proc_macro2::TokenStream {
+        let pattern = fields.pattern();
+        let size = fields.size();
+        let write = fields.write();
+        let read = fields.read();
+        let fixed_size = fields.fixed_size();
+
+        quote! {
+            fn size(&self) -> usize {
+                let #pattern = self;
+                #size
+            }
+
+            fn write(&self, buffer: &mut [u8]) -> usize {
+                let #pattern = self;
+                let mut pos = 0;
+                #write
+                pos
+            }
+
+            fn read(buffer: &[u8]) -> Self {
+                let mut pos = 0;
+                #read
+            }
+
+            fn fixed_size() -> Option<usize> {
+                #fixed_size
+            }
+        }
+    }
+
+    fn implement_enum(variants: &[KeyFields<'_>]) -> proc_macro2::TokenStream {
+        let patterns: Vec<_> = variants.iter().map(KeyFields::pattern).collect();
+        let tags: Vec<_> = (0..variants.len()).map(|tag| tag as u8).collect();
+        let sizes = variants.iter().map(KeyFields::size);
+        let writes = variants.iter().map(KeyFields::write);
+        let reads = variants.iter().map(KeyFields::read);
+        let fixed_sizes = variants.iter().map(KeyFields::fixed_size);
+
+        quote! {
+            fn size(&self) -> usize {
+                1 + match self {
+                    #(#patterns => #sizes,)*
+                }
+            }
+
+            fn write(&self, buffer: &mut [u8]) -> usize {
+                let mut pos = 1;
+                match self {
+                    #(#patterns => {
+                        buffer[0] = #tags;
+                        #writes
+                    })*
+                }
+                pos
+            }
+
+            fn read(buffer: &[u8]) -> Self {
+                let mut pos = 1;
+                match buffer[0] {
+                    #(#tags => { #reads })*
+                    tag => panic!("Invalid variant tag in `BinaryKey`: {}", tag),
+                }
+            }
+
+            fn fixed_size() -> Option<usize> {
+                exonum_merkledb::_reexports::composite::variants_size(&[#(#fixed_sizes,)*])
+            }
+        }
+    }
+}
+
+impl ToTokens for BinaryKey<'_> {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        let name = self.ident;
+        let (impl_generics, ty_generics, where_clause) = self.generics.split_for_impl();
+        let methods = match &self.kind {
+            KeyKind::Struct(fields) => Self::implement_struct(fields),
+            KeyKind::Enum(variants) => Self::implement_enum(variants),
+        };
+
+        let expanded = quote! {
+            impl #impl_generics exonum_merkledb::BinaryKey for #name #ty_generics #where_clause {
+                #methods
+            }
+        };
+        tokens.extend(expanded);
+    }
+}
+
+pub fn impl_binary_key(input: TokenStream) -> TokenStream {
+    let input: DeriveInput = syn::parse(input).unwrap();
+    let binary_key = match BinaryKey::from_derive_input(&input) {
+        Ok(binary_key) => binary_key,
+        Err(e) => return e.to_compile_error().into(),
+    };
+    let tokens = quote!(#binary_key);
+    tokens.into()
+}
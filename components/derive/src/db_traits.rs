@@ -45,6 +45,7 @@ impl FromDeriveInput for BinaryValueStruct {
 enum Codec {
     Protobuf,
     Bincode,
+    Cbor,
 }
 
 impl Default for Codec {
@@ -58,9 +59,10 @@ impl FromMeta for Codec {
         match value {
             "protobuf" => Ok(Codec::Protobuf),
             "bincode" => Ok(Codec::Bincode),
+            "cbor" => Ok(Codec::Cbor),
             _ => {
                 let msg = format!(
-                    "Unknown codec ({}). Use one of `protobuf` or `bincode`",
+                    "Unknown codec ({}). Use one of `protobuf`, `bincode` or `cbor`",
                     value
                 );
                 Err(darling::Error::custom(msg))
@@ -142,10 +144,43 @@ impl BinaryValueStruct {
         }
     }
 
+    fn implement_binary_value_from_cbor(&self) -> proc_macro2::TokenStream {
+        let name = &self.ident;
+
+        quote! {
+            impl exonum_merkledb::BinaryValue for #name {
+                fn to_bytes(&self) -> std::vec::Vec<u8> {
+                    let mut bytes = std::vec::Vec::new();
+                    ciborium::ser::into_writer(self, &mut bytes).expect(
+                        concat!("Failed to serialize `BinaryValue` for ", stringify!(#name))
+                    );
+                    bytes
+                }
+
+                fn from_bytes(
+                    value: std::borrow::Cow<[u8]>,
+                ) -> std::result::Result<Self, exonum_merkledb::_reexports::Error> {
+                    let mut bytes = value.as_ref();
+                    let value = ciborium::de::from_reader(&mut bytes)?;
+                    // Unlike other codecs, `ciborium` does not check that the input is
+                    // fully consumed.
+                    if bytes.is_empty() {
+                        Ok(value)
+                    } else {
+                        Err(exonum_merkledb::_reexports::Error::msg(
+                            "Trailing bytes after the CBOR-encoded value",
+                        ))
+                    }
+                }
+            }
+        }
+    }
+
     fn implement_binary_value(&self) -> impl ToTokens {
         match self.attrs.codec {
             Codec::Protobuf => self.implement_binary_value_from_pb(),
             Codec::Bincode => self.implement_binary_value_from_bincode(),
+            Codec::Cbor => self.implement_binary_value_from_cbor(),
         }
     }
 }
@@ -243,6 +278,15 @@ struct FromAccessFieldAttrs {
     rename: Option<String>,
     #[darling(default)]
     flatten: bool,
+    #[darling(default)]
+    secondary_index: Option<SecondaryIndexAttrs>,
+}
+
+#[derive(Debug, FromMeta)]
+struct SecondaryIndexAttrs {
+    field: String,
+    #[darling(default)]
+    rename: Option<String>,
 }
 
 impl FromAccess {
@@ -309,18 +353,38 @@ impl FromDeriveInput for FromAccess {
                         );
                         return Err(e);
                     }
+                    if this.fields[0].secondary_index.is_some() {
+                        let msg = "Secondary indexes are not supported in transparent structs";
+                        let e = darling::Error::custom(msg).with_span(&this.fields[0].span);
+                        return Err(e);
+                    }
                 } else {
                     let mut field_names = HashSet::new();
 
                     for field in &this.fields {
+                        if field.flatten && field.secondary_index.is_some() {
+                            let msg = "Secondary indexes cannot be used with flattened fields";
+                            return Err(darling::Error::custom(msg).with_span(&field.span));
+                        }
+
                         if let Some(ref name) = field.name_suffix {
                             validate_address_component(name).map_err(|msg| {
                                 darling::Error::custom(msg).with_span(&field.span)
                             })?;
-                            if !field_names.insert(name) {
+                            if !field_names.insert(name.to_owned()) {
                                 let e = "Duplicate field name";
                                 return Err(darling::Error::custom(e).with_span(&field.span));
                             }
+
+                            if let Some(secondary_name) = field.secondary_name() {
+                                validate_address_component(&secondary_name).map_err(|msg| {
+                                    darling::Error::custom(msg).with_span(&field.span)
+                                })?;
+                                if !field_names.insert(secondary_name) {
+                                    let e = "Duplicate secondary index name";
+                                    return Err(darling::Error::custom(e).with_span(&field.span));
+                                }
+                            }
                         } else if !field.flatten {
                             let msg = if this.fields.len() == 1 {
                                 "Unnamed fields necessitate #[from_access(rename = ...)]. \
@@ -346,8 +410,19 @@ impl FromDeriveInput for FromAccess {
 struct AccessField {
     span: Span,
     ident: Option<Ident>,
+    ty: syn::Type,
     name_suffix: Option<String>,
     flatten: bool,
+    secondary_index: Option<SecondaryIndex>,
+}
+
+/// Secondary index over the values of the field.
+#[derive(Debug)]
+struct SecondaryIndex {
+    /// Field of the values used as the secondary key.
+    field: syn::Member,
+    /// Name of the secondary index override.
+    rename: Option<String>,
 }
 
 impl FromField for AccessField {
@@ -361,11 +436,27 @@ impl FromField for AccessField {
         let name_suffix = attrs
             .rename
             .or_else(|| ident.as_ref().map(ToString::to_string));
+        let secondary_index = attrs
+            .secondary_index
+            .map(|secondary_index| {
+                let value_field = syn::parse_str(&secondary_index.field).map_err(|_| {
+                    let msg = format!("Invalid value field `{}`", secondary_index.field);
+                    darling::Error::custom(msg).with_span(&field.span())
+                })?;
+                Ok(SecondaryIndex {
+                    field: value_field,
+                    rename: secondary_index.rename,
+                })
+            })
+            .transpose()?;
+
         Ok(Self {
             ident,
+            ty: field.ty.clone(),
             name_suffix,
             span: field.span(),
             flatten: attrs.flatten,
+            secondary_index,
         })
     }
 }
@@ -380,10 +471,50 @@ impl AccessField {
         }
     }
 
+    /// Returns the name of the secondary index for the field, if any.
+    fn secondary_name(&self) -> Option<String> {
+        let secondary_index = self.secondary_index.as_ref()?;
+        let name = if let Some(ref rename) = secondary_index.rename {
+            rename.to_owned()
+        } else {
+            let value_field = &secondary_index.field;
+            let value_field = quote!(#value_field);
+            format!("{}_by_{}", self.name_suffix.as_ref()?, value_field)
+        };
+        Some(name)
+    }
+
+    fn secondary_index_constructor(
+        &self,
+        field_index: usize,
+        addr: impl ToTokens,
+        secondary_addr: impl ToTokens,
+    ) -> impl ToTokens {
+        let ident = self.ident(field_index);
+        let ty = &self.ty;
+        let value_field = &self.secondary_index.as_ref().unwrap().field;
+        quote! {
+            #ident: <#ty>::new(
+                access.clone(),
+                #addr,
+                #secondary_addr,
+                |value| std::clone::Clone::clone(&value.#value_field),
+            )?
+        }
+    }
+
     fn constructor(&self, field_index: usize) -> impl ToTokens {
         let from_access = quote!(exonum_merkledb::access::FromAccess);
         let ident = self.ident(field_index);
-        if self.flatten {
+        if let Some(secondary_name) = self.secondary_name() {
+            let name = self.name_suffix.as_ref().unwrap();
+            let tokens = self.secondary_index_constructor(
+                field_index,
+                quote!(addr.clone().append_name(#name)),
+                quote!(addr.clone().append_name(#secondary_name)),
+            );
+            quote!(#tokens)
+        } else if self.flatten {
             quote!(#ident: #from_access::from_access(access.clone(), addr.clone())?)
         } else {
             let name = self.name_suffix.as_ref().unwrap();
@@ -394,7 +525,15 @@ impl AccessField {
     fn root_constructor(&self, field_index: usize) -> impl ToTokens {
         let from_access = quote!(exonum_merkledb::access::FromAccess);
         let ident = self.ident(field_index);
-        if self.flatten {
+        if let Some(secondary_name) = self.secondary_name() {
+            let name = &self.name_suffix;
+            let tokens = self.secondary_index_constructor(
+                field_index,
+                quote!(#name.into()),
+                quote!(#secondary_name.into()),
+            );
+            quote!(#tokens)
+        } else if self.flatten {
             quote!(#ident: #from_access::from_root(access.clone())?)
         } else {
             let name = &self.name_suffix;
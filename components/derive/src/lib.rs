@@ -20,6 +20,7 @@
 
 extern crate proc_macro;
 
+mod binary_key;
 mod db_traits;
 mod execution_fail;
 mod exonum_interface;
@@ -35,21 +36,32 @@ use syn::{Attribute, NestedMeta};
 /// Derives `BinaryValue` trait. The target type must implement (de)serialization logic,
 /// which should be provided externally.
 ///
-/// The trait currently supports two codecs:
+/// The trait currently supports three codecs:
 ///
 /// - Protobuf serialization (used by default) via `exonum-proto` crate and its `ProtobufConvert`
 ///   trait.
 /// - `bincode` serialization via the eponymous crate. Switched on by the
 ///   `#[binary_value(codec = "bincode")]` attribute. Beware that `bincode` format is not as
 ///   forward / backward compatible as Protobuf; hence, this codec is better suited for tests
-///   than for production code.
+///   and internal data than for data exchanged with other parties.
+/// - CBOR serialization via the `ciborium` crate. Switched on by the
+///   `#[binary_value(codec = "cbor")]` attribute. Struct fields are encoded together with
+///   their names, so the format tolerates adding optional fields (e.g., ones marked with
+///   `#[serde(default)]`).
+///
+/// `bincode` and CBOR codecs require the target type to implement `Serialize` and `Deserialize`
+/// from `serde`, and the crate with the type to depend on `bincode` or `ciborium`,
+/// respectively. Both codecs produce deterministic encodings as long as the type
+/// serialization is deterministic, which is important for types implementing `ObjectHash`.
+/// In particular, the type should not contain `HashMap`s or `HashSet`s, since their iteration
+/// order is randomized; use `BTreeMap` and `BTreeSet` instead.
 ///
 /// # Container Attributes
 ///
 /// ## `codec`
 ///
-/// Selects the serialization codec to use. Allowed values are `protobuf` (used by default)
-/// and `bincode`.
+/// Selects the serialization codec to use. Allowed values are `protobuf` (used by default),
+/// `bincode` and `cbor`.
 ///
 /// # Examples
 ///
@@ -86,6 +98,18 @@ use syn::{Attribute, NestedMeta};
 /// };
 /// let bytes = wallet.to_bytes();
 /// ```
+///
+/// With CBOR serialization:
+///
+/// ```ignore
+/// #[derive(Clone, Debug, Serialize, Deserialize, BinaryValue, ObjectHash)]
+/// #[binary_value(codec = "cbor")]
+/// pub struct PeerState {
+///     pub address: String,
+///     pub failed_attempts: u32,
+///     pub tags: BTreeSet<String>,
+/// }
+/// ```
 #[proc_macro_derive(BinaryValue, attributes(binary_value))]
 pub fn binary_value(input: TokenStream) -> TokenStream {
     db_traits::impl_binary_value(input)
@@ -116,6 +140,53 @@ pub fn object_hash(input: TokenStream) -> TokenStream {
     db_traits::impl_object_hash(input)
 }
 
+/// Derives `BinaryKey` trait for structs and enums, all fields of which implement `BinaryKey`.
+///
+/// The derived encoding preserves the lexicographic order of fields: keys are compared by
+/// the first field, then by the second one, and so on. For enums, keys are compared by
+/// the variant index (i.e., the order of variants in the enum declaration) and then by the
+/// variant fields. This makes derived keys suitable for range queries in maps,
+/// e.g., via `MapIndex::iter_from`.
+///
+/// Fields are encoded as follows:
+///
+/// - Fields with fixed-size encoding (e.g., integers, hashes and public keys) are written as is.
+/// - Other fields (e.g., strings and byte vectors) are escaped and terminated, so that
+///   a shorter field value always sorts before its extensions. As an exception, the last field
+///   of a struct or an enum variant is always written as is.
+/// - Enum variants are prefixed by a single byte with the variant index; hence, an enum
+///   may have at most 256 variants.
+///
+/// To sort keys by a certain field in the descending order, wrap the field
+/// in `std::cmp::Reverse`.
+///
+/// The target type must implement `Clone`. If the type is generic, its type params used
+/// as fields should be bound by `BinaryKey<Owned = T>`.
+///
+/// # Examples
+///
+/// ```ignore
+/// #[derive(Debug, Clone, PartialEq, BinaryKey)]
+/// pub struct TransferKey {
+///     /// Sender of the transfer.
+///     pub sender: PublicKey,
+///     /// Height of the transfer; newer transfers go first.
+///     pub height: Reverse<u64>,
+///     /// Transfer comment.
+///     pub comment: String,
+/// }
+///
+/// #[derive(Debug, Clone, PartialEq, BinaryKey)]
+/// pub enum Account {
+///     Wallet(PublicKey),
+///     Alias { name: String, index: u32 },
+/// }
+/// ```
+#[proc_macro_derive(BinaryKey)]
+pub fn binary_key(input: TokenStream) -> TokenStream {
+    binary_key::impl_binary_key(input)
+}
+
 /// Derives `FromAccess` trait.
 ///
 /// This macro can be applied only to `struct`s, each field of which implements `FromAccess`
@@ -152,6 +223,18 @@ pub fn object_hash(input: TokenStream) -> TokenStream {
 ///
 /// Changes the suffix appended to the address when creating a field. The name should follow
 /// conventions for index names.
+///
+/// ## `secondary_index`
+///
+/// ```text
+/// #[from_access(secondary_index(field = "value_field", rename = "name"))]
+/// ```
+///
+/// Instantiates an `IndexedMapIndex` field, which keeps a secondary index over the specified
+/// field of the map values. The secondary index is created at the address with the suffix
+/// `{name}_by_{value_field}`, where `{name}` is the suffix of the field itself; the suffix
+/// may be overridden with the optional `rename` parameter. The value field must implement
+/// `Clone` and `BinaryKey`.
 #[proc_macro_derive(FromAccess, attributes(from_access))]
 pub fn from_access(input: TokenStream) -> TokenStream {
     db_traits::impl_from_access(input)
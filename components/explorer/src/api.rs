@@ -19,7 +19,7 @@
 
 use chrono::{DateTime, Utc};
 use exonum::{
-    blockchain::{Block, CallProof},
+    blockchain::{Block, CallProof, CommitCertificate, CompactCallProof},
     crypto::Hash,
     helpers::Height,
     merkledb::BinaryValue,
@@ -78,6 +78,10 @@ pub struct BlockInfo {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub precommits: Option<Vec<Verified<Precommit>>>,
 
+    /// Commit certificate of the block. Present only if validators have BLS keys.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit_certificate: Option<CommitCertificate>,
+
     /// Info of transactions in the block.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub txs: Option<Vec<TxInfo>>,
@@ -92,6 +96,7 @@ impl From<crate::BlockInfo<'_>> for BlockInfo {
         Self {
             block: inner.header().clone(),
             precommits: Some(inner.precommits().to_vec()),
+            commit_certificate: inner.commit_certificate(),
             txs: Some(
                 inner
                     .transaction_hashes()
@@ -133,6 +138,12 @@ impl BlockInfo {
                 None
             },
 
+            commit_certificate: if query.add_precommits {
+                block.commit_certificate()
+            } else {
+                None
+            },
+
             block: block.into_header(),
         }
     }
@@ -165,7 +176,8 @@ pub struct BlocksQuery {
     #[serde(default)]
     pub add_blocks_time: bool,
     /// If true, then the `precommits` field in each returned block will contain precommits for the
-    /// block stored by the node.
+    /// block stored by the node. The `commit_certificate` field will contain the commit certificate
+    /// for the block, if any.
     #[serde(default)]
     pub add_precommits: bool,
 }
@@ -232,6 +244,23 @@ impl TransactionQuery {
     }
 }
 
+/// Format of cryptographic proofs returned by the explorer API.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProofFormat {
+    /// Proofs are serialized in JSON in the same way as other data.
+    Json,
+    /// Merkle proofs are serialized in the compact binary format and encoded as hex strings.
+    /// This format is more succinct and is recommended for light clients.
+    Compact,
+}
+
+impl Default for ProofFormat {
+    fn default() -> Self {
+        Self::Json
+    }
+}
+
 /// Query parameters to check the execution status of a transaction.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[non_exhaustive]
@@ -241,6 +270,9 @@ pub struct TransactionStatusQuery {
     /// Whether to return the status with a cryptographic proof of authenticity.
     #[serde(default)]
     pub with_proof: bool,
+    /// Format of the proof if it is requested.
+    #[serde(default)]
+    pub proof_format: ProofFormat,
 }
 
 impl TransactionStatusQuery {
@@ -249,6 +281,7 @@ impl TransactionStatusQuery {
         Self {
             hash,
             with_proof: false,
+            proof_format: ProofFormat::Json,
         }
     }
 
@@ -257,6 +290,13 @@ impl TransactionStatusQuery {
         self.with_proof = true;
         self
     }
+
+    /// Requests to return a call status with a proof in the compact binary format.
+    pub fn with_compact_proof(mut self) -> Self {
+        self.with_proof = true;
+        self.proof_format = ProofFormat::Compact;
+        self
+    }
 }
 
 /// Query parameters to check the execution status of a `before_transactions` or
@@ -271,6 +311,9 @@ pub struct CallStatusQuery {
     /// Whether to return the status with a cryptographic proof of authenticity.
     #[serde(default)]
     pub with_proof: bool,
+    /// Format of the proof if it is requested.
+    #[serde(default)]
+    pub proof_format: ProofFormat,
 }
 
 impl CallStatusQuery {
@@ -280,6 +323,7 @@ impl CallStatusQuery {
             height,
             service_id,
             with_proof: false,
+            proof_format: ProofFormat::Json,
         }
     }
 
@@ -288,12 +332,19 @@ impl CallStatusQuery {
         self.with_proof = true;
         self
     }
+
+    /// Requests to return a call status with a proof in the compact binary format.
+    pub fn with_compact_proof(mut self) -> Self {
+        self.with_proof = true;
+        self.proof_format = ProofFormat::Compact;
+        self
+    }
 }
 
 /// Call status response.
 ///
 /// This enum is serialized in JSON untagged. Hence, if the consumer knows the type of the response,
-/// she may parse it directly to `ExecutionStatus` or `CallProof`.
+/// she may parse it directly to `ExecutionStatus`, `CallProof` or `CompactCallProof`.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(untagged)]
 pub enum CallStatusResponse {
@@ -301,4 +352,6 @@ pub enum CallStatusResponse {
     Simple(ExecutionStatus),
     /// Response with a cryptographic proof of authenticity.
     Proof(CallProof),
+    /// Response with a cryptographic proof of authenticity in the compact binary format.
+    CompactProof(CompactCallProof),
 }
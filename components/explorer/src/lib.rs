@@ -52,7 +52,7 @@
 
 use chrono::{DateTime, Utc};
 use exonum::{
-    blockchain::{Block, CallInBlock, CallProof, Schema, TxLocation},
+    blockchain::{Block, CallInBlock, CallProof, CommitCertificate, Schema, TxLocation},
     crypto::Hash,
     helpers::Height,
     merkledb::{ListProof, ObjectHash, Snapshot},
@@ -96,10 +96,14 @@ fn end_height(bound: Bound<&Height>, max: Height) -> Height {
 /// |------|-------|--------|
 /// | `block` | [`Block`] | Block header as recorded in the blockchain |
 /// | `precommits` | `Vec<`[`Precommit`]`>` | Precommits authorizing the block |
+/// | `commit_certificate` | [`CommitCertificate`] | Commit certificate of the block (if any) |
 /// | `txs` | `Vec<`[`Hash`]`>` | Hashes of transactions in the block |
 ///
+/// The `commit_certificate` field is omitted if the block has no certificate.
+///
 /// [`Block`]: https://docs.rs/exonum/latest/exonum/blockchain/struct.Block.html
 /// [`Precommit`]: https://docs.rs/exonum/latest/exonum/messages/struct.Precommit.html
+/// [`CommitCertificate`]: https://docs.rs/exonum/latest/exonum/blockchain/struct.CommitCertificate.html
 /// [`Hash`]: https://docs.rs/exonum-crypto/latest/exonum_crypto/struct.Hash.html
 #[derive(Debug)]
 pub struct BlockInfo<'a> {
@@ -169,6 +173,15 @@ impl<'a> BlockInfo<'a> {
         })
     }
 
+    /// Returns the commit certificate for this block, or `None` if the block has no certificate.
+    /// Certificates are only created if validators have BLS keys.
+    pub fn commit_certificate(&self) -> Option<CommitCertificate> {
+        self.explorer
+            .schema
+            .commit_certificates()
+            .get(&self.header.object_hash())
+    }
+
     /// Lists hashes of transactions included in this block.
     pub fn transaction_hashes(&self) -> Ref<'_, [Hash]> {
         if self.txs.borrow().is_none() {
@@ -232,10 +245,15 @@ impl<'a> BlockInfo<'a> {
             .errors()
             .map(|(location, error)| ErrorWithLocation { location, error })
             .collect();
+        let commit_certificate = explorer
+            .schema
+            .commit_certificates()
+            .get(&header.object_hash());
 
         BlockWithTransactions {
             header,
             precommits,
+            commit_certificate,
             transactions,
             errors,
         }
@@ -246,9 +264,14 @@ impl<'a> Serialize for BlockInfo<'a> {
     fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
         use serde::ser::SerializeStruct;
 
-        let mut s = serializer.serialize_struct("BlockInfo", 3)?;
+        let mut s = serializer.serialize_struct("BlockInfo", 4)?;
         s.serialize_field("block", &self.header)?;
         s.serialize_field("precommits", &*self.precommits())?;
+        if let Some(certificate) = self.commit_certificate() {
+            s.serialize_field("commit_certificate", &certificate)?;
+        } else {
+            s.skip_field("commit_certificate")?;
+        }
         s.serialize_field("txs", &*self.transaction_hashes())?;
         s.end()
     }
@@ -294,6 +317,9 @@ pub struct BlockWithTransactions {
     pub header: Block,
     /// Precommits.
     pub precommits: Vec<Verified<Precommit>>,
+    /// Commit certificate of the block, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit_certificate: Option<CommitCertificate>,
     /// Transactions in the order they appear in the block.
     pub transactions: Vec<CommittedTransaction>,
     /// Errors that have occurred within the block.
@@ -736,6 +762,7 @@ impl<'a> BlockchainExplorer<'a> {
         Some(BlockWithTransactions {
             header: block_proof.block,
             precommits: block_proof.precommits,
+            commit_certificate: block_proof.commit_certificate,
             transactions: txs_table
                 .iter()
                 .map(|tx_hash| self.committed_transaction(&tx_hash, None))
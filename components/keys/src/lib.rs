@@ -34,6 +34,11 @@
 //! # Ok(())
 //! # }
 //! ```
+//!
+//! Besides validator keys, additional keypairs may be derived from the master key
+//! at named paths, see [`DerivationPath`].
+//!
+//! [`DerivationPath`]: struct.DerivationPath.html
 
 #![warn(
     missing_debug_implementations,
@@ -54,19 +59,23 @@
     clippy::missing_errors_doc, clippy::missing_const_for_fn
 )]
 
-use anyhow::format_err;
-use exonum_crypto::{KeyPair, PublicKey, SecretKey, Seed, SEED_LENGTH};
+use anyhow::{ensure, format_err};
+use exonum_crypto::{
+    aead, bls::BlsKeyPair, hash, KeyPair, PublicKey, SecretKey, Seed, SEED_LENGTH,
+};
 use pwbox::{sodium::Sodium, ErasedPwBox, Eraser, SensitiveData, Suite};
 use rand::thread_rng;
-use secret_tree::{Name, SecretTree};
+use secret_tree::{Name, SecretTree, MAX_NAME_LEN};
 use serde_derive::{Deserialize, Serialize};
 
 #[cfg(unix)]
 use std::os::unix::fs::{MetadataExt, OpenOptionsExt};
 use std::{
+    fmt,
     fs::{File, OpenOptions},
     io::{Error, ErrorKind, Read, Write},
     path::Path,
+    str::FromStr,
 };
 
 #[cfg(unix)]
@@ -138,6 +147,147 @@ impl Keys {
     pub fn service_sk(&self) -> &SecretKey {
         self.service.secret_key()
     }
+
+    /// BLS keypair used to sign commit certificates. The keypair is deterministically
+    /// derived from the consensus secret key.
+    pub fn bls_keypair(&self) -> BlsKeyPair {
+        BlsKeyPair::from_seed(&self.consensus_sk()[..SEED_LENGTH])
+    }
+}
+
+/// Path at which a keypair is derived from the master key.
+///
+/// A path consists of one or more segments separated by `/`, for example,
+/// `services/timestamping/operator` or `api-clients/3`. A segment consisting of digits
+/// is interpreted as a numeric index, which must not have leading zeros; other segments
+/// are names, which must be non-empty and must not contain zero chars. Names longer
+/// than 16 bytes are hashed before being used for derivation. Derivation is deterministic:
+/// the same master key and path always produce the same keypair. Derived keypairs
+/// are independent from each other and from the validator keys of the node.
+///
+/// The path is (de)serialized as a string.
+///
+/// # Examples
+///
+/// ```
+/// use exonum_keys::{generate_keys, read_derived_keys_from_file, DerivationPath};
+/// use tempdir::TempDir;
+///
+/// # fn main() -> anyhow::Result<()> {
+/// let dir = TempDir::new("test_keys")?;
+/// let file_path = dir.path().join("private_key.toml");
+/// let pass_phrase = b"super_secret_passphrase";
+/// generate_keys(file_path.as_path(), pass_phrase)?;
+///
+/// let paths = [
+///     "services/timestamping/operator".parse::<DerivationPath>()?,
+///     "api-clients/3".parse()?,
+/// ];
+/// let keys = read_derived_keys_from_file(file_path.as_path(), pass_phrase, &paths)?;
+/// assert_ne!(keys[0], keys[1]);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DerivationPath {
+    segments: Vec<PathSegment>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum PathSegment {
+    Name(String),
+    Index(u64),
+}
+
+impl DerivationPath {
+    fn derive_keypair(&self, tree: &SecretTree) -> KeyPair {
+        // Derived keys are placed into a separate subtree, so that they cannot
+        // coincide with the validator or storage keys.
+        let mut tree = tree.child(Name::new("derived"));
+        for segment in &self.segments {
+            tree = match segment {
+                PathSegment::Name(name) if name.len() <= MAX_NAME_LEN => {
+                    tree.child(Name::new(name))
+                }
+                PathSegment::Name(name) => {
+                    // `secret_tree` limits the length of names, so long names are replaced
+                    // with their hash, which is split into indexes of a dedicated subtree.
+                    let name_hash = hash(name.as_bytes());
+                    name_hash.as_ref().chunks(8).fold(
+                        tree.child(Name::new("long-name")),
+                        |tree, chunk| {
+                            let mut index = [0_u8; 8];
+                            index.copy_from_slice(chunk);
+                            tree.index(u64::from_le_bytes(index))
+                        },
+                    )
+                }
+                PathSegment::Index(index) => tree.index(*index),
+            };
+        }
+
+        let mut buffer = [0_u8; 32];
+        tree.fill(&mut buffer);
+        KeyPair::from_seed(&Seed::new(buffer))
+    }
+}
+
+impl FromStr for DerivationPath {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let segments = s
+            .split('/')
+            .map(|segment| {
+                ensure!(
+                    !segment.is_empty(),
+                    "Derivation path contains an empty segment"
+                );
+                if segment.bytes().all(|byte| byte.is_ascii_digit()) {
+                    ensure!(
+                        segment == "0" || !segment.starts_with('0'),
+                        "Index `{}` of derivation path has leading zeros",
+                        segment
+                    );
+                    return Ok(PathSegment::Index(segment.parse()?));
+                }
+                ensure!(
+                    !segment.contains('\0'),
+                    "Segment of derivation path contains zero chars"
+                );
+                Ok(PathSegment::Name(segment.to_owned()))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self { segments })
+    }
+}
+
+impl serde::Serialize for DerivationPath {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for DerivationPath {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = <String as serde::Deserialize>::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl fmt::Display for DerivationPath {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, segment) in self.segments.iter().enumerate() {
+            if i > 0 {
+                formatter.write_str("/")?;
+            }
+            match segment {
+                PathSegment::Name(name) => formatter.write_str(name)?,
+                PathSegment::Index(index) => write!(formatter, "{}", index)?,
+            }
+        }
+        Ok(())
+    }
 }
 
 fn save_master_key<P: AsRef<Path>>(
@@ -228,11 +378,16 @@ fn generate_keys_from_master_password(tree: &SecretTree) -> Keys {
     Keys::from_keys(consensus_keys, service_keys)
 }
 
-/// Reads encrypted master key from file and generate validator keys from it.
-pub fn read_keys_from_file<P: AsRef<Path>, W: AsRef<[u8]>>(
+fn generate_storage_key_from_master_password(tree: &SecretTree) -> aead::Key {
+    let mut buffer = [0_u8; aead::KEY_LENGTH];
+    tree.child(Name::new("storage")).fill(&mut buffer);
+    aead::Key::new(buffer)
+}
+
+fn read_master_key_from_file<P: AsRef<Path>, W: AsRef<[u8]>>(
     path: P,
     pass_phrase: W,
-) -> anyhow::Result<Keys> {
+) -> anyhow::Result<SecretTree> {
     let mut key_file = File::open(path)?;
 
     #[cfg(unix)]
@@ -243,11 +398,45 @@ pub fn read_keys_from_file<P: AsRef<Path>, W: AsRef<[u8]>>(
     let keys: EncryptedMasterKey =
         toml::from_slice(file_content.as_slice()).map_err(|e| Error::new(ErrorKind::Other, e))?;
     let seed = keys.decrypt(pass_phrase)?;
-    let tree = SecretTree::from_seed(&seed).expect("Error creating secret tree from seed.");
+    Ok(SecretTree::from_seed(&seed).expect("Error creating secret tree from seed."))
+}
 
+/// Reads encrypted master key from file and generate validator keys from it.
+pub fn read_keys_from_file<P: AsRef<Path>, W: AsRef<[u8]>>(
+    path: P,
+    pass_phrase: W,
+) -> anyhow::Result<Keys> {
+    let tree = read_master_key_from_file(path, pass_phrase)?;
     Ok(generate_keys_from_master_password(&tree))
 }
 
+/// Reads encrypted master key from file and derives the key for storage encryption from it.
+///
+/// The storage key is derived from the same master key as validator keys, but is independent
+/// from them. It can be used to encrypt the node database at rest.
+pub fn read_storage_key_from_file<P: AsRef<Path>, W: AsRef<[u8]>>(
+    path: P,
+    pass_phrase: W,
+) -> anyhow::Result<aead::Key> {
+    let tree = read_master_key_from_file(path, pass_phrase)?;
+    Ok(generate_storage_key_from_master_password(&tree))
+}
+
+/// Reads encrypted master key from file and derives keypairs at the specified paths from it.
+///
+/// Keypairs are returned in the same order as the paths.
+pub fn read_derived_keys_from_file<P: AsRef<Path>, W: AsRef<[u8]>>(
+    path: P,
+    pass_phrase: W,
+    derivation_paths: &[DerivationPath],
+) -> anyhow::Result<Vec<KeyPair>> {
+    let tree = read_master_key_from_file(path, pass_phrase)?;
+    Ok(derivation_paths
+        .iter()
+        .map(|derivation_path| derivation_path.derive_keypair(&tree))
+        .collect())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -263,6 +452,88 @@ mod tests {
         assert_eq!(pk1, pk2);
     }
 
+    #[test]
+    fn storage_key_is_deterministic() {
+        let dir = TempDir::new("test_utils").expect("Couldn't create TempDir");
+        let file_path = dir.path().join("private_key.toml");
+        let pass_phrase = b"passphrase";
+        let keys = generate_keys(file_path.as_path(), pass_phrase).unwrap();
+        let storage_key = read_storage_key_from_file(file_path.as_path(), pass_phrase).unwrap();
+        let other_key = read_storage_key_from_file(file_path.as_path(), pass_phrase).unwrap();
+        assert_eq!(storage_key, other_key);
+        assert_ne!(storage_key.as_ref(), &keys.consensus_sk()[..32]);
+        assert_ne!(storage_key.as_ref(), &keys.service_sk()[..32]);
+    }
+
+    #[test]
+    fn derived_keys_are_deterministic() {
+        let dir = TempDir::new("test_utils").expect("Couldn't create TempDir");
+        let file_path = dir.path().join("private_key.toml");
+        let pass_phrase = b"passphrase";
+        let keys = generate_keys(file_path.as_path(), pass_phrase).unwrap();
+
+        let paths: Vec<DerivationPath> = [
+            "consensus",
+            "services/foo/operator",
+            "clients/0",
+            "clients/1",
+            "services/a_very_long_service_name/operator",
+            "services/a_very_long_service_name_2/operator",
+        ]
+        .iter()
+        .map(|path| path.parse().unwrap())
+        .collect();
+        let derived =
+            read_derived_keys_from_file(file_path.as_path(), pass_phrase, &paths).unwrap();
+        assert_eq!(derived.len(), paths.len());
+        // Derived keys do not coincide with validator keys.
+        assert_ne!(derived[0], keys.consensus);
+        assert_ne!(derived[0], keys.service);
+        assert_ne!(derived[1], derived[2]);
+        assert_ne!(derived[2], derived[3]);
+        // Long names are distinguished even if they have a common prefix.
+        assert_ne!(derived[4], derived[5]);
+
+        let same =
+            read_derived_keys_from_file(file_path.as_path(), pass_phrase, &paths[1..2]).unwrap();
+        assert_eq!(same[0], derived[1]);
+    }
+
+    #[test]
+    fn derivation_path_parsing() {
+        let path: DerivationPath = "services/timestamping/operator".parse().unwrap();
+        assert_eq!(path.to_string(), "services/timestamping/operator");
+        let path: DerivationPath = "clients/0".parse().unwrap();
+        assert_eq!(path.segments[1], PathSegment::Index(0));
+        let path: DerivationPath = "services/a_very_long_service_name".parse().unwrap();
+        assert_eq!(path.to_string(), "services/a_very_long_service_name");
+
+        let err = "services//operator".parse::<DerivationPath>().unwrap_err();
+        assert!(err.to_string().contains("empty segment"));
+        let err = "".parse::<DerivationPath>().unwrap_err();
+        assert!(err.to_string().contains("empty segment"));
+        let err = "clients/007".parse::<DerivationPath>().unwrap_err();
+        assert!(err.to_string().contains("leading zeros"));
+        let err = "clients/99999999999999999999"
+            .parse::<DerivationPath>()
+            .unwrap_err();
+        assert!(err.to_string().contains("too large"));
+    }
+
+    #[test]
+    fn bls_keypair_is_deterministic() {
+        let keys = Keys::random();
+        let same_keys = Keys::from_keys(keys.consensus.clone(), keys.service.clone());
+        assert_eq!(
+            keys.bls_keypair().public_key(),
+            same_keys.bls_keypair().public_key()
+        );
+        assert_ne!(
+            keys.bls_keypair().public_key(),
+            Keys::random().bls_keypair().public_key()
+        );
+    }
+
     #[test]
     fn encrypt_decrypt() {
         let pass_phrase = b"passphrase";
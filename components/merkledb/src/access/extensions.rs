@@ -19,7 +19,8 @@ use crate::{
     indexes::proof_map::{Raw, ToProofPath},
     views::IndexType,
     BinaryKey, BinaryValue, Entry, Group, IndexAddress, KeySetIndex, ListIndex, MapIndex,
-    ObjectHash, ProofEntry, ProofListIndex, ProofMapIndex, SparseListIndex, ValueSetIndex,
+    ObjectHash, ProofEntry, ProofKeySetIndex, ProofListIndex, ProofMapIndex, SparseListIndex,
+    ValueSetIndex,
 };
 
 /// Extension trait allowing for easy access to indexes from any type implementing
@@ -219,6 +220,35 @@ pub trait CopyAccessExt: Access + Copy {
             .unwrap_or_else(|e| panic!("MerkleDB error: {}", e))
     }
 
+    /// Gets a Merkelized key set index with the specified address.
+    ///
+    /// # Panics
+    ///
+    /// If the index exists, but is not a Merkelized key set.
+    fn get_proof_key_set<I, K>(self, addr: I) -> ProofKeySetIndex<Self::Base, K>
+    where
+        I: Into<IndexAddress>,
+        K: BinaryKey + ObjectHash + ?Sized,
+    {
+        ProofKeySetIndex::from_access(self, addr.into())
+            .unwrap_or_else(|e| panic!("MerkleDB error: {}", e))
+    }
+
+    /// Variant of the proof key set with elements that can be mapped directly to `ProofPath`.
+    ///
+    /// # Panics
+    ///
+    /// If the index exists, but is not a Merkelized key set.
+    fn get_raw_proof_key_set<I, K>(self, addr: I) -> ProofKeySetIndex<Self::Base, K, Raw>
+    where
+        I: Into<IndexAddress>,
+        K: BinaryKey + ?Sized,
+        Raw: ToProofPath<K>,
+    {
+        ProofKeySetIndex::<_, _, Raw>::from_access(self, addr.into())
+            .unwrap_or_else(|e| panic!("MerkleDB error: {}", e))
+    }
+
     /// Gets a sparse list index with the specified address.
     ///
     /// # Panics
@@ -442,6 +472,35 @@ pub trait AccessExt: Access {
             .unwrap_or_else(|e| panic!("MerkleDB error: {}", e))
     }
 
+    /// Gets a Merkelized key set index with the specified address.
+    ///
+    /// # Panics
+    ///
+    /// If the index exists, but is not a Merkelized key set.
+    fn get_proof_key_set<I, K>(&self, addr: I) -> ProofKeySetIndex<Self::Base, K>
+    where
+        I: Into<IndexAddress>,
+        K: BinaryKey + ObjectHash + ?Sized,
+    {
+        ProofKeySetIndex::from_access(self.clone(), addr.into())
+            .unwrap_or_else(|e| panic!("MerkleDB error: {}", e))
+    }
+
+    /// Variant of the proof key set with elements that can be mapped directly to `ProofPath`.
+    ///
+    /// # Panics
+    ///
+    /// If the index exists, but is not a Merkelized key set.
+    fn get_raw_proof_key_set<I, K>(&self, addr: I) -> ProofKeySetIndex<Self::Base, K, Raw>
+    where
+        I: Into<IndexAddress>,
+        K: BinaryKey + ?Sized,
+        Raw: ToProofPath<K>,
+    {
+        ProofKeySetIndex::<_, _, Raw>::from_access(self.clone(), addr.into())
+            .unwrap_or_else(|e| panic!("MerkleDB error: {}", e))
+    }
+
     /// Gets a sparse list index with the specified address.
     ///
     /// # Panics
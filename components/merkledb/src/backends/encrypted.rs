@@ -0,0 +1,689 @@
+// Copyright 2020 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! An implementation of a database wrapper encrypting data at rest.
+
+use exonum_crypto::aead::{self, Key, Nonce, NONCE_LENGTH};
+
+use std::{
+    collections::HashMap,
+    mem,
+    sync::{Arc, RwLock},
+};
+
+use crate::{
+    db::{Change, ViewChanges, DB_METADATA},
+    views::{IndexAddress, IndexesPool, View, INDEXES_POOL_NAME, STATE_AGGREGATOR},
+    ColumnFamilyStats, Database, DatabaseExt, EncryptionOptions, Error, Iter, Iterator, Patch,
+    ResolvedAddress, Result, Snapshot,
+};
+
+/// Name of the database metadata entry used to check the encryption key.
+const ENCRYPTION_MARKER: &str = "encryption";
+
+/// A wrapper over a database which encrypts data before it is written to the underlying
+/// database, and decrypts it on reads.
+///
+/// Values are encrypted with XChaCha20-Poly1305, an authenticated cipher, using random nonces.
+/// The address and the key of each value are authenticated together with the value,
+/// so that encrypted values cannot be swapped with each other. If [`EncryptionOptions`]
+/// specify so, keys are encrypted as well. Key encryption is deterministic (that is, a key
+/// is always encrypted into the same ciphertext), which allows looking up values by keys.
+/// Index identifiers are not encrypted.
+///
+/// Database metadata (such as the data layout version) and index metadata (names, types
+/// and identifiers of indexes) are not encrypted. The database metadata contains an encrypted
+/// marker, which is used to check that the database is opened with the same key and options
+/// as it was created with. Wrapping a database which already contains unencrypted data
+/// in indexes is not supported.
+///
+/// The encryption key is usually derived from the master key of the node
+/// with the `read_storage_key_from_file` function from the `exonum-keys` crate.
+///
+/// # Performance notes
+///
+/// Encryption increases the size of each value by 40 bytes (a 24-byte nonce
+/// and a 16-byte authentication tag). If keys are encrypted, each key is increased
+/// by the same amount.
+///
+/// Encrypted keys do not preserve the key order. Hence, the first iteration over an index
+/// with encrypted keys in a snapshot decrypts and sorts all keys of the index; the sorted keys
+/// are cached in the snapshot and reused by subsequent iterations. Values are fetched
+/// and decrypted lazily as the iterator advances. Removing a range of keys requires
+/// iterating over the range.
+///
+/// # Examples
+///
+/// ```
+/// use exonum_crypto::aead;
+/// use exonum_merkledb::{
+///     access::CopyAccessExt, Database, EncryptedDB, EncryptionOptions, TemporaryDB,
+/// };
+///
+/// # exonum_crypto::init();
+/// let key = aead::gen_key();
+/// let db = EncryptedDB::new(TemporaryDB::new(), &key, EncryptionOptions::default()).unwrap();
+/// let fork = db.fork();
+/// fork.get_list("list").extend(vec![1_u32, 2, 3]);
+/// db.merge(fork.into_patch()).unwrap();
+///
+/// let snapshot = db.snapshot();
+/// let list = snapshot.get_list::<_, u32>("list");
+/// assert_eq!(list.iter().collect::<Vec<_>>(), vec![1, 2, 3]);
+/// ```
+///
+/// [`EncryptionOptions`]: struct.EncryptionOptions.html
+#[derive(Debug)]
+pub struct EncryptedDB<T> {
+    inner: T,
+    cipher: Arc<Cipher>,
+}
+
+/// Keys and options used to encrypt and decrypt data.
+#[derive(Debug)]
+struct Cipher {
+    values_key: Key,
+    keys_key: Key,
+    key_nonces_key: Key,
+    encrypt_keys: bool,
+}
+
+impl Cipher {
+    fn new(key: &Key, options: EncryptionOptions) -> Self {
+        Self {
+            values_key: key.derive_subkey("exonum-merkledb/values"),
+            keys_key: key.derive_subkey("exonum-merkledb/keys"),
+            key_nonces_key: key.derive_subkey("exonum-merkledb/key-nonces"),
+            encrypt_keys: options.encrypt_keys,
+        }
+    }
+
+    /// Checks whether data at the address is stored in plaintext.
+    fn is_plaintext(address: &ResolvedAddress) -> bool {
+        address.id.is_none() && (address.name == DB_METADATA || address.name == INDEXES_POOL_NAME)
+    }
+
+    /// Serializes the address unambiguously to use it as additional authenticated data.
+    fn address_context(address: &ResolvedAddress) -> Vec<u8> {
+        let mut context = Vec::with_capacity(address.name.len() + 10);
+        context.extend_from_slice(address.name.as_bytes());
+        context.push(0);
+        if let Some(id_bytes) = address.id_to_bytes() {
+            context.push(1);
+            context.extend_from_slice(&id_bytes);
+        } else {
+            context.push(0);
+        }
+        context
+    }
+
+    /// Returns the key under which the value is stored in the underlying database.
+    fn encrypt_key(&self, address: &ResolvedAddress, key: &[u8]) -> Vec<u8> {
+        if !self.encrypt_keys || Self::is_plaintext(address) {
+            return key.to_vec();
+        }
+
+        let context = Self::address_context(address);
+        let mut nonce_data = context.clone();
+        nonce_data.extend_from_slice(key);
+        let nonce = self.key_nonces_key.derive_nonce(&nonce_data);
+        Self::seal(key, &context, nonce, &self.keys_key)
+    }
+
+    fn decrypt_key(&self, address: &ResolvedAddress, stored_key: &[u8]) -> Vec<u8> {
+        let context = Self::address_context(address);
+        Self::open(stored_key, &context, &self.keys_key).unwrap_or_else(|e| {
+            panic!(
+                "Cannot decrypt a key at address {:?}: {}; the database is corrupted \
+                 or was encrypted with another key",
+                address, e
+            )
+        })
+    }
+
+    fn encrypt_value(&self, address: &ResolvedAddress, key: &[u8], value: &[u8]) -> Vec<u8> {
+        if Self::is_plaintext(address) {
+            return value.to_vec();
+        }
+        let mut context = Self::address_context(address);
+        context.extend_from_slice(key);
+        Self::seal(value, &context, aead::gen_nonce(), &self.values_key)
+    }
+
+    fn try_decrypt_value(
+        &self,
+        address: &ResolvedAddress,
+        key: &[u8],
+        stored_value: &[u8],
+    ) -> Result<Vec<u8>> {
+        let mut context = Self::address_context(address);
+        context.extend_from_slice(key);
+        Self::open(stored_value, &context, &self.values_key)
+    }
+
+    fn decrypt_value(&self, address: &ResolvedAddress, key: &[u8], stored_value: &[u8]) -> Vec<u8> {
+        if Self::is_plaintext(address) {
+            return stored_value.to_vec();
+        }
+        self.try_decrypt_value(address, key, stored_value)
+            .unwrap_or_else(|e| {
+                panic!(
+                    "Cannot decrypt a value at address {:?}: {}; the database is corrupted \
+                     or was encrypted with another key",
+                    address, e
+                )
+            })
+    }
+
+    /// Encrypts data, prepending the nonce to the ciphertext.
+    fn seal(plaintext: &[u8], context: &[u8], nonce: Nonce, key: &Key) -> Vec<u8> {
+        let mut output = nonce.as_ref().to_vec();
+        output.extend_from_slice(&aead::seal(plaintext, Some(context), &nonce, key));
+        output
+    }
+
+    fn open(ciphertext: &[u8], context: &[u8], key: &Key) -> Result<Vec<u8>> {
+        if ciphertext.len() < NONCE_LENGTH {
+            return Err(Error::new("encrypted data is too short"));
+        }
+        let (nonce, ciphertext) = ciphertext.split_at(NONCE_LENGTH);
+        let nonce = Nonce::from_slice(nonce).expect("nonce has the correct length");
+        aead::open(ciphertext, Some(context), &nonce, key).map_err(|e| Error::new(e.to_string()))
+    }
+}
+
+/// Returns `true` if the database was encrypted with `EncryptedDB`.
+pub(crate) fn is_encrypted(snapshot: &dyn Snapshot) -> bool {
+    let marker_address = ResolvedAddress::system(DB_METADATA);
+    snapshot.contains(&marker_address, ENCRYPTION_MARKER.as_bytes())
+}
+
+impl<T: Database> EncryptedDB<T> {
+    /// Wraps the `inner` database, encrypting its data with the specified key.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database was encrypted with another key or other
+    /// encryption options, if the database is not encrypted but already contains indexes,
+    /// if the encryption marker cannot be written to the database, or if the unfinished
+    /// [journal] in the database cannot be recovered.
+    ///
+    /// [journal]: ../struct.Journal.html
+    pub fn new(inner: T, key: &Key, options: EncryptionOptions) -> Result<Self> {
+        let cipher = Cipher::new(key, options);
+        let marker_address = ResolvedAddress::system(DB_METADATA);
+        let marker_key = ENCRYPTION_MARKER.as_bytes();
+        let marker_value = vec![u8::from(options.encrypt_keys)];
+
+        let snapshot = inner.snapshot();
+        if let Some(marker) = snapshot.get(&marker_address, marker_key) {
+            let decrypted_marker = cipher
+                .try_decrypt_value(&marker_address, marker_key, &marker)
+                .map_err(|_| Error::new("Database was encrypted with another key"))?;
+            if decrypted_marker != marker_value {
+                return Err(Error::new(
+                    "Database was encrypted with other encryption options",
+                ));
+            }
+            let db = Self {
+                inner,
+                cipher: Arc::new(cipher),
+            };
+            // The journal is encrypted, thus, it can only be recovered via the wrapper.
+            db.recover_journal()?;
+            Ok(db)
+        } else {
+            // Existing plaintext data cannot be read via the wrapper. The state aggregator
+            // is created when the database is opened, so it is not considered data.
+            let has_data = IndexesPool::new(&snapshot).indexes().any(|(full_name, _)| {
+                IndexAddress::from_fully_qualified_name(&full_name).name() != STATE_AGGREGATOR
+            });
+            if has_data {
+                return Err(Error::new("Database is not encrypted, but contains data"));
+            }
+
+            let mut context = Cipher::address_context(&marker_address);
+            context.extend_from_slice(marker_key);
+            let marker = Cipher::seal(
+                &marker_value,
+                &context,
+                aead::gen_nonce(),
+                &cipher.values_key,
+            );
+
+            // The marker is written via the wrapper, so that any auxiliary data written
+            // by the fork is encrypted as well.
+            let db = Self {
+                inner,
+                cipher: Arc::new(cipher),
+            };
+            let fork = db.fork();
+            View::new(&fork, marker_address).put(ENCRYPTION_MARKER, marker);
+            db.merge(fork.into_patch())?;
+            Ok(db)
+        }
+    }
+
+    /// Returns `true` if the database was encrypted with `EncryptedDB`. The encryption key
+    /// and options are not checked.
+    pub fn is_encrypted(db: &T) -> bool {
+        is_encrypted(db.snapshot().as_ref())
+    }
+
+    /// Returns a reference to the underlying database.
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Converts this wrapper into the underlying database.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    fn encrypt_patch(&self, patch: Patch) -> Patch {
+        let snapshot = self.snapshot();
+        patch.map_changes(|address, changes| self.encrypt_changes(&*snapshot, address, changes))
+    }
+
+    fn encrypt_changes(
+        &self,
+        snapshot: &dyn Snapshot,
+        address: &ResolvedAddress,
+        mut changes: ViewChanges,
+    ) -> ViewChanges {
+        if Cipher::is_plaintext(address) {
+            return changes;
+        }
+
+        if self.cipher.encrypt_keys {
+            // Ranges of encrypted keys cannot be removed from the underlying database directly,
+            // so we convert them into removals of separate keys.
+            for (start, end) in mem::take(&mut changes.removed_ranges) {
+                let mut iter = snapshot.iter(address, &start);
+                while let Some((key, _)) = iter.next() {
+                    if end.as_ref().map_or(false, |end| key >= end.as_slice()) {
+                        break;
+                    }
+                    changes.data.entry(key.to_vec()).or_insert(Change::Delete);
+                }
+            }
+        }
+
+        changes.data = mem::take(&mut changes.data)
+            .into_iter()
+            .map(|(key, change)| {
+                let change = match change {
+                    Change::Put(value) => {
+                        Change::Put(self.cipher.encrypt_value(address, &key, &value))
+                    }
+                    Change::Delete => Change::Delete,
+                };
+                (self.cipher.encrypt_key(address, &key), change)
+            })
+            .collect();
+        changes
+    }
+}
+
+impl<T: Database> Database for EncryptedDB<T> {
+    fn snapshot(&self) -> Box<dyn Snapshot> {
+        Box::new(EncryptedSnapshot::new(
+            self.inner.snapshot(),
+            Arc::clone(&self.cipher),
+        ))
+    }
+
+    fn try_snapshot(&self) -> Result<Box<dyn Snapshot>> {
+        Ok(Box::new(EncryptedSnapshot::new(
+            self.inner.try_snapshot()?,
+            Arc::clone(&self.cipher),
+        )))
+    }
+
+    fn merge(&self, patch: Patch) -> Result<()> {
+        self.inner.merge(self.encrypt_patch(patch))
+    }
+
+    fn merge_sync(&self, patch: Patch) -> Result<()> {
+        self.inner.merge_sync(self.encrypt_patch(patch))
+    }
+
+    fn column_family_stats(&self) -> Vec<ColumnFamilyStats> {
+        self.inner.column_family_stats()
+    }
+}
+
+impl<T: Database> From<EncryptedDB<T>> for Arc<dyn Database> {
+    fn from(db: EncryptedDB<T>) -> Self {
+        Arc::new(db)
+    }
+}
+
+/// Decrypted keys of an index sorted in ascending order, together with the corresponding
+/// keys in the underlying database.
+type SortedKeys = Arc<Vec<(Vec<u8>, Vec<u8>)>>;
+
+/// A snapshot of an `EncryptedDB`, which decrypts data from the underlying snapshot.
+struct EncryptedSnapshot {
+    inner: Box<dyn Snapshot>,
+    cipher: Arc<Cipher>,
+    /// Sorted keys of indexes with encrypted keys, which were iterated over in this snapshot.
+    sorted_keys: RwLock<HashMap<ResolvedAddress, SortedKeys>>,
+}
+
+impl EncryptedSnapshot {
+    fn new(inner: Box<dyn Snapshot>, cipher: Arc<Cipher>) -> Self {
+        Self {
+            inner,
+            cipher,
+            sorted_keys: RwLock::default(),
+        }
+    }
+
+    /// Returns sorted keys of the index at the specified address. The keys are decrypted
+    /// and sorted on the first call for the address; since the snapshot is immutable,
+    /// the result is cached and reused by subsequent calls.
+    fn sorted_keys(&self, address: &ResolvedAddress) -> SortedKeys {
+        let cached_keys = self.sorted_keys.read().unwrap().get(address).cloned();
+        if let Some(keys) = cached_keys {
+            return keys;
+        }
+
+        let mut keys = vec![];
+        let mut iter = self.inner.iter(address, &[]);
+        while let Some((stored_key, _)) = iter.next() {
+            let key = self.cipher.decrypt_key(address, stored_key);
+            keys.push((key, stored_key.to_vec()));
+        }
+        keys.sort_unstable();
+        let keys = Arc::new(keys);
+        self.sorted_keys
+            .write()
+            .unwrap()
+            .insert(address.to_owned(), Arc::clone(&keys));
+        keys
+    }
+}
+
+impl Snapshot for EncryptedSnapshot {
+    fn get(&self, name: &ResolvedAddress, key: &[u8]) -> Option<Vec<u8>> {
+        let stored_key = self.cipher.encrypt_key(name, key);
+        self.inner
+            .get(name, &stored_key)
+            .map(|value| self.cipher.decrypt_value(name, key, &value))
+    }
+
+    fn contains(&self, name: &ResolvedAddress, key: &[u8]) -> bool {
+        let stored_key = self.cipher.encrypt_key(name, key);
+        self.inner.contains(name, &stored_key)
+    }
+
+    fn iter(&self, name: &ResolvedAddress, from: &[u8]) -> Iter<'_> {
+        if Cipher::is_plaintext(name) {
+            self.inner.iter(name, from)
+        } else if self.cipher.encrypt_keys {
+            let keys = self.sorted_keys(name);
+            let position = keys
+                .binary_search_by(|(key, _)| key.as_slice().cmp(from))
+                .unwrap_or_else(|position| position);
+            Box::new(SortedIter {
+                snapshot: self,
+                address: name.to_owned(),
+                keys,
+                position,
+                current: None,
+            })
+        } else {
+            Box::new(DecryptingIter {
+                inner: self.inner.iter(name, from),
+                address: name.to_owned(),
+                cipher: &self.cipher,
+                value: None,
+            })
+        }
+    }
+}
+
+/// Iterator over an index with plaintext keys and encrypted values.
+struct DecryptingIter<'a> {
+    inner: Iter<'a>,
+    address: ResolvedAddress,
+    cipher: &'a Cipher,
+    /// Decrypted value for the current key.
+    value: Option<(Vec<u8>, Vec<u8>)>,
+}
+
+impl DecryptingIter<'_> {
+    /// Decrypts the value at the current position of the iterator, unless it is already
+    /// decrypted. Returns `None` if the iterator has ended.
+    fn decrypt_current(&mut self) -> Option<()> {
+        let (key, value) = self.inner.peek()?;
+        let is_decrypted = self
+            .value
+            .as_ref()
+            .map_or(false, |(decrypted_key, _)| decrypted_key.as_slice() == key);
+        if !is_decrypted {
+            let value = self.cipher.decrypt_value(&self.address, key, value);
+            self.value = Some((key.to_vec(), value));
+        }
+        Some(())
+    }
+
+    fn current(&self) -> Option<(&[u8], &[u8])> {
+        self.value
+            .as_ref()
+            .map(|(key, value)| (key.as_slice(), value.as_slice()))
+    }
+}
+
+impl Iterator for DecryptingIter<'_> {
+    fn next(&mut self) -> Option<(&[u8], &[u8])> {
+        self.decrypt_current()?;
+        self.inner.next();
+        self.current()
+    }
+
+    fn peek(&mut self) -> Option<(&[u8], &[u8])> {
+        self.decrypt_current()?;
+        self.current()
+    }
+}
+
+/// Iterator over an index with encrypted keys. The iterator goes over the sorted keys
+/// of the index and decrypts values on demand.
+struct SortedIter<'a> {
+    snapshot: &'a EncryptedSnapshot,
+    address: ResolvedAddress,
+    keys: SortedKeys,
+    /// Position of the next entry in `keys`.
+    position: usize,
+    /// Decrypted entry at the position of the iterator.
+    current: Option<(usize, Vec<u8>)>,
+}
+
+impl SortedIter<'_> {
+    /// Decrypts the value at the current position of the iterator, unless it is already
+    /// decrypted. Returns `None` if the iterator has ended.
+    fn decrypt_current(&mut self) -> Option<()> {
+        let (key, stored_key) = self.keys.get(self.position)?;
+        let is_decrypted = self
+            .current
+            .as_ref()
+            .map_or(false, |(position, _)| *position == self.position);
+        if !is_decrypted {
+            let stored_value = self
+                .snapshot
+                .inner
+                .get(&self.address, stored_key)
+                .expect("Value is missing for a key in the snapshot");
+            let value = self
+                .snapshot
+                .cipher
+                .decrypt_value(&self.address, key, &stored_value);
+            self.current = Some((self.position, value));
+        }
+        Some(())
+    }
+
+    fn current(&self) -> Option<(&[u8], &[u8])> {
+        let (position, value) = self.current.as_ref()?;
+        Some((self.keys[*position].0.as_slice(), value.as_slice()))
+    }
+}
+
+impl Iterator for SortedIter<'_> {
+    fn next(&mut self) -> Option<(&[u8], &[u8])> {
+        self.decrypt_current()?;
+        self.position += 1;
+        self.current()
+    }
+
+    fn peek(&mut self) -> Option<(&[u8], &[u8])> {
+        self.decrypt_current()?;
+        self.current()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{access::CopyAccessExt, TemporaryDB};
+
+    fn create_db(key: &Key, encrypt_keys: bool) -> EncryptedDB<TemporaryDB> {
+        let options = EncryptionOptions::new(encrypt_keys);
+        EncryptedDB::new(TemporaryDB::new(), key, options).unwrap()
+    }
+
+    fn check_basic_operations(encrypt_keys: bool) {
+        exonum_crypto::init();
+        let db = create_db(&aead::gen_key(), encrypt_keys);
+        let fork = db.fork();
+        {
+            let mut map = fork.get_map("map");
+            for i in 0_u32..10 {
+                map.put(&i, format!("value #{}", i));
+            }
+        }
+        db.merge(fork.into_patch()).unwrap();
+
+        let snapshot = db.snapshot();
+        let map = snapshot.get_map::<_, u32, String>("map");
+        assert_eq!(map.get(&3), Some("value #3".to_owned()));
+        assert!(map.contains(&9));
+        assert!(!map.contains(&10));
+        assert_eq!(map.keys().collect::<Vec<_>>(), (0..10).collect::<Vec<_>>());
+        assert_eq!(map.iter_from(&7).count(), 3);
+
+        let fork = db.fork();
+        {
+            let mut map = fork.get_map::<_, u32, String>("map");
+            map.remove_range(2..5);
+            map.put(&3, "new value".to_owned());
+            map.remove(&8);
+        }
+        db.merge(fork.into_patch()).unwrap();
+
+        let snapshot = db.snapshot();
+        let map = snapshot.get_map::<_, u32, String>("map");
+        assert_eq!(map.keys().collect::<Vec<_>>(), vec![0, 1, 3, 5, 6, 7, 9]);
+        assert_eq!(map.get(&3), Some("new value".to_owned()));
+
+        // The underlying database does not contain plaintext values.
+        let inner_snapshot = db.inner().snapshot();
+        let metadata = IndexesPool::new(&inner_snapshot)
+            .index_metadata(b"map")
+            .unwrap();
+        let resolved = ResolvedAddress::new("map", Some(metadata.identifier()));
+        let mut iter = inner_snapshot.iter(&resolved, &[]);
+        let mut count = 0;
+        while let Some((key, value)) = iter.next() {
+            count += 1;
+            assert!(!value.windows(5).any(|window| window == b"value"));
+            if encrypt_keys {
+                assert!(key.len() > 4);
+            } else {
+                assert_eq!(key.len(), 4);
+            }
+        }
+        assert_eq!(count, 7);
+    }
+
+    #[test]
+    fn encrypted_values() {
+        check_basic_operations(false);
+    }
+
+    #[test]
+    fn encrypted_keys_and_values() {
+        check_basic_operations(true);
+    }
+
+    #[test]
+    fn clearing_index_with_encrypted_keys() {
+        exonum_crypto::init();
+        let db = create_db(&aead::gen_key(), true);
+        let fork = db.fork();
+        fork.get_list("list").extend(vec![1_u64, 2, 3]);
+        db.merge(fork.into_patch()).unwrap();
+
+        let fork = db.fork();
+        {
+            let mut list = fork.get_list::<_, u64>("list");
+            list.clear();
+            list.push(4);
+        }
+        db.merge(fork.into_patch()).unwrap();
+        let snapshot = db.snapshot();
+        let list = snapshot.get_list::<_, u64>("list");
+        assert_eq!(list.iter().collect::<Vec<_>>(), vec![4]);
+    }
+
+    #[test]
+    fn reopening_database() {
+        exonum_crypto::init();
+        let key = aead::gen_key();
+        let db = create_db(&key, true);
+        let fork = db.fork();
+        fork.get_entry("entry").set(42_u32);
+        db.merge(fork.into_patch()).unwrap();
+
+        let inner = db.into_inner();
+        let err =
+            EncryptedDB::new(inner, &aead::gen_key(), EncryptionOptions::new(true)).unwrap_err();
+        assert!(err.to_string().contains("another key"));
+
+        let db = create_db(&key, true).into_inner();
+        let db = EncryptedDB::new(db, &key, EncryptionOptions::new(true)).unwrap();
+        let err =
+            EncryptedDB::new(db.into_inner(), &key, EncryptionOptions::new(false)).unwrap_err();
+        assert!(err.to_string().contains("other encryption options"));
+    }
+
+    #[test]
+    fn encrypting_database_with_data() {
+        exonum_crypto::init();
+        let db = TemporaryDB::new();
+        let fork = db.fork();
+        fork.get_entry("entry").set(42_u32);
+        db.merge(fork.into_patch()).unwrap();
+        assert!(!EncryptedDB::is_encrypted(&db));
+
+        let err = EncryptedDB::new(db, &aead::gen_key(), EncryptionOptions::new(true)).unwrap_err();
+        assert!(err.to_string().contains("not encrypted"), "{}", err);
+
+        // An empty database can be encrypted.
+        let db = create_db(&aead::gen_key(), false).into_inner();
+        assert!(EncryptedDB::is_encrypted(&db));
+    }
+}
@@ -12,5 +12,47 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+pub mod encrypted;
+#[cfg(feature = "redb")]
+pub mod redb;
 pub mod rocksdb;
 pub mod temporarydb;
+
+/// Generates the sequence of bytes lexicographically following the provided one. Assumes that
+/// the provided sequence is less than `[u8::max_value(); 8]`.
+fn next_id_bytes(id_bytes: [u8; 8]) -> [u8; 8] {
+    let mut next_id_bytes = id_bytes;
+    for byte in next_id_bytes.iter_mut().rev() {
+        if *byte == u8::max_value() {
+            *byte = 0;
+        } else {
+            *byte += 1;
+            break;
+        }
+    }
+    next_id_bytes
+}
+
+#[test]
+fn test_next_id_bytes() {
+    assert_eq!(
+        next_id_bytes([1, 0, 0, 0, 0, 0, 0, 0]),
+        [1, 0, 0, 0, 0, 0, 0, 1]
+    );
+    assert_eq!(
+        next_id_bytes([1, 2, 3, 4, 5, 6, 7, 8]),
+        [1, 2, 3, 4, 5, 6, 7, 9]
+    );
+    assert_eq!(
+        next_id_bytes([1, 0, 0, 0, 0, 0, 0, 254]),
+        [1, 0, 0, 0, 0, 0, 0, 255]
+    );
+    assert_eq!(
+        next_id_bytes([1, 0, 0, 0, 0, 0, 41, 255]),
+        [1, 0, 0, 0, 0, 0, 42, 0]
+    );
+    assert_eq!(
+        next_id_bytes([1, 2, 3, 4, 5, 255, 255, 255]),
+        [1, 2, 3, 4, 6, 0, 0, 0]
+    );
+}
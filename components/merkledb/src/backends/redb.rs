@@ -0,0 +1,419 @@
+// Copyright 2020 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! An implementation of the database backed by `redb`.
+
+use redb::{Database as InnerDB, Durability, ReadTransaction, Table, TableDefinition, TableError};
+use smallvec::SmallVec;
+
+use std::{fmt, fs, iter::Peekable, mem, path::Path, sync::Arc};
+
+use crate::{
+    db::{check_database, Change},
+    journal, Database, DbOptions, Iter, Iterator, Patch, ResolvedAddress, Snapshot,
+};
+
+/// Size of a byte representation of an index ID, which is used to prefix index keys
+/// in a table.
+const ID_SIZE: usize = mem::size_of::<u64>();
+
+/// Name of the file with the database contents within the database directory.
+const DB_FILE_NAME: &str = "merkledb.redb";
+
+/// Type of `redb` tables used to store data. A table corresponds to a column family
+/// in the `RocksDB` backend.
+type RawTable<'a> = TableDefinition<'a, &'static [u8], &'static [u8]>;
+
+/// Database implementation on top of [`redb`](https://github.com/cberner/redb),
+/// an embedded key-value store written in pure Rust.
+///
+/// Unlike [`RocksDB`], this backend does not require a C++ toolchain to build.
+/// It is available if the `redb` crate feature is enabled.
+///
+/// The database is stored in a single file inside the directory passed to [`open`].
+/// Of all [`DbOptions`], only `create_if_missing` is taken into account;
+/// `redb` has no limit on open files and does not support compression.
+///
+/// [`RocksDB`]: struct.RocksDB.html
+/// [`open`]: #method.open
+/// [`DbOptions`]: struct.DbOptions.html
+pub struct RedbDB {
+    db: Arc<InnerDB>,
+}
+
+/// A snapshot of a `RedbDB`.
+///
+/// The snapshot is a read transaction, which provides a consistent view of the database
+/// regardless of the subsequent writes.
+pub struct RedbDBSnapshot {
+    txn: ReadTransaction,
+}
+
+/// Iterator over owned key-value pairs read from a `redb` table.
+type RawIter = Box<dyn std::iter::Iterator<Item = (Vec<u8>, Vec<u8>)>>;
+
+/// An iterator over the entries of a `RedbDB`.
+struct RedbDBIterator {
+    iter: Peekable<RawIter>,
+    key: Option<Vec<u8>>,
+    value: Option<Vec<u8>>,
+    prefix: Option<[u8; ID_SIZE]>,
+    ended: bool,
+}
+
+fn table_definition(name: &str) -> RawTable<'_> {
+    TableDefinition::new(name)
+}
+
+impl RedbDB {
+    /// Opens a database stored at the specified path with the specified options.
+    ///
+    /// If the database does not exist at the indicated path and the option
+    /// `create_if_missing` is switched on in `DbOptions`, a new database will
+    /// be created at the indicated path.
+    pub fn open<P: AsRef<Path>>(path: P, options: &DbOptions) -> crate::Result<Self> {
+        let path = path.as_ref();
+        let inner = if options.create_if_missing {
+            fs::create_dir_all(path)
+                .map_err(|e| crate::Error::new(format!("Cannot create DB directory: {}", e)))?;
+            InnerDB::create(path.join(DB_FILE_NAME)).map_err(redb::Error::from)?
+        } else {
+            InnerDB::open(path.join(DB_FILE_NAME)).map_err(redb::Error::from)?
+        };
+
+        let mut db = Self {
+            db: Arc::new(inner),
+        };
+        // Check that read transactions can be started, so that errors are reported on opening
+        // the database rather than on its first use.
+        db.redb_snapshot()?;
+        check_database(&mut db)?;
+        journal::recover_on_open(&db)?;
+        Ok(db)
+    }
+
+    fn redb_snapshot(&self) -> crate::Result<RedbDBSnapshot> {
+        let txn = self.db.begin_read().map_err(redb::Error::from)?;
+        Ok(RedbDBSnapshot { txn })
+    }
+
+    fn do_merge(&self, patch: Patch, durability: Durability) -> crate::Result<()> {
+        let mut txn = self.db.begin_write().map_err(redb::Error::from)?;
+        txn.set_durability(durability);
+
+        for (resolved, changes) in patch.into_changes() {
+            let mut table = txn
+                .open_table(table_definition(&resolved.name))
+                .map_err(redb::Error::from)?;
+
+            if changes.is_cleared() {
+                clear_prefix(&mut table, &resolved)?;
+            }
+            for (start, end) in changes.removed_ranges() {
+                remove_range(&mut table, &resolved, start, end)?;
+            }
+
+            if let Some(id_bytes) = resolved.id_to_bytes() {
+                // Write changes to the table with each key prefixed by the ID of the
+                // resolved address.
+                let mut buffer: SmallVec<[u8; 1_024]> = SmallVec::new();
+                buffer.extend_from_slice(&id_bytes);
+
+                for (key, change) in changes.into_data() {
+                    buffer.truncate(ID_SIZE);
+                    buffer.extend_from_slice(&key);
+                    apply_change(&mut table, &buffer, &change)?;
+                }
+            } else {
+                // Write changes to the table as-is.
+                for (key, change) in changes.into_data() {
+                    apply_change(&mut table, &key, &change)?;
+                }
+            }
+        }
+
+        txn.commit().map_err(redb::Error::from)?;
+        Ok(())
+    }
+}
+
+fn apply_change(
+    table: &mut Table<'_, &[u8], &[u8]>,
+    key: &[u8],
+    change: &Change,
+) -> crate::Result<()> {
+    match change {
+        Change::Put(value) => {
+            table
+                .insert(key, value.as_slice())
+                .map_err(redb::Error::from)?;
+        }
+        Change::Delete => {
+            table.remove(key).map_err(redb::Error::from)?;
+        }
+    }
+    Ok(())
+}
+
+/// Removes all keys belonging to the resolved address from a table.
+fn clear_prefix(
+    table: &mut Table<'_, &[u8], &[u8]>,
+    resolved: &ResolvedAddress,
+) -> crate::Result<()> {
+    if let Some(id_bytes) = resolved.id_to_bytes() {
+        let next_bytes = super::next_id_bytes(id_bytes);
+        table
+            .retain_in(&id_bytes[..]..&next_bytes[..], |_, _| false)
+            .map_err(redb::Error::from)?;
+    } else {
+        table.retain(|_, _| false).map_err(redb::Error::from)?;
+    }
+    Ok(())
+}
+
+/// Removes keys in the `start..end` range of the resolved address from a table.
+/// `end == None` means that the range is unbounded from above.
+fn remove_range(
+    table: &mut Table<'_, &[u8], &[u8]>,
+    resolved: &ResolvedAddress,
+    start: &[u8],
+    end: Option<&[u8]>,
+) -> crate::Result<()> {
+    let start = resolved.keyed(start);
+    let end = match (end, resolved.id_to_bytes()) {
+        (Some(end), _) => Some(resolved.keyed(end).into_owned()),
+        (None, Some(id_bytes)) => Some(super::next_id_bytes(id_bytes).to_vec()),
+        (None, None) => None,
+    };
+
+    if let Some(end) = end {
+        table
+            .retain_in(start.as_ref()..end.as_slice(), |_, _| false)
+            .map_err(redb::Error::from)?;
+    } else {
+        table
+            .retain_in(start.as_ref().., |_, _| false)
+            .map_err(redb::Error::from)?;
+    }
+    Ok(())
+}
+
+impl RedbDBSnapshot {
+    fn redb_iter(&self, name: &ResolvedAddress, from: &[u8]) -> RedbDBIterator {
+        let from = name.keyed(from);
+        let range = match self.txn.open_table(table_definition(&name.name)) {
+            Ok(table) => Some(table.range(from.as_ref()..).expect("Cannot read from DB")),
+            Err(TableError::TableDoesNotExist(_)) => None,
+            Err(e) => panic!("{}", e),
+        };
+
+        let iter: RawIter = match range {
+            Some(range) => Box::new(range.map(|entry| {
+                let (key, value) = entry.expect("Cannot read from DB");
+                (key.value().to_vec(), value.value().to_vec())
+            })),
+            None => Box::new(std::iter::empty()),
+        };
+
+        RedbDBIterator {
+            iter: iter.peekable(),
+            prefix: name.id_to_bytes(),
+            key: None,
+            value: None,
+            ended: false,
+        }
+    }
+}
+
+impl Database for RedbDB {
+    fn snapshot(&self) -> Box<dyn Snapshot> {
+        self.try_snapshot()
+            .unwrap_or_else(|e| panic!("Cannot start read transaction: {}", e))
+    }
+
+    fn try_snapshot(&self) -> crate::Result<Box<dyn Snapshot>> {
+        Ok(Box::new(self.redb_snapshot()?))
+    }
+
+    fn merge(&self, patch: Patch) -> crate::Result<()> {
+        self.do_merge(patch, Durability::Eventual)
+    }
+
+    fn merge_sync(&self, patch: Patch) -> crate::Result<()> {
+        self.do_merge(patch, Durability::Immediate)
+    }
+}
+
+impl Snapshot for RedbDBSnapshot {
+    fn get(&self, resolved_addr: &ResolvedAddress, key: &[u8]) -> Option<Vec<u8>> {
+        match self.txn.open_table(table_definition(&resolved_addr.name)) {
+            Ok(table) => table
+                .get(resolved_addr.keyed(key).as_ref())
+                .unwrap_or_else(|e| panic!("{}", e))
+                .map(|value| value.value().to_vec()),
+            Err(TableError::TableDoesNotExist(_)) => None,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    fn iter(&self, name: &ResolvedAddress, from: &[u8]) -> Iter<'_> {
+        Box::new(self.redb_iter(name, from))
+    }
+}
+
+impl Iterator for RedbDBIterator {
+    fn next(&mut self) -> Option<(&[u8], &[u8])> {
+        if self.ended {
+            return None;
+        }
+
+        let (key, value) = self.iter.next()?;
+        if let Some(ref prefix) = self.prefix {
+            if &key[..ID_SIZE] != prefix {
+                self.ended = true;
+                return None;
+            }
+        }
+
+        self.key = Some(key);
+        let key = if self.prefix.is_some() {
+            &self.key.as_ref()?[ID_SIZE..]
+        } else {
+            &self.key.as_ref()?[..]
+        };
+        self.value = Some(value);
+        Some((key, self.value.as_ref()?))
+    }
+
+    fn peek(&mut self) -> Option<(&[u8], &[u8])> {
+        if self.ended {
+            return None;
+        }
+
+        let (key, value) = self.iter.peek()?;
+        let key = if let Some(prefix) = self.prefix {
+            if key[..ID_SIZE] != prefix {
+                self.ended = true;
+                return None;
+            }
+            &key[ID_SIZE..]
+        } else {
+            &key[..]
+        };
+        Some((key, &value[..]))
+    }
+}
+
+impl From<RedbDB> for Arc<dyn Database> {
+    fn from(db: RedbDB) -> Self {
+        Self::from(Box::new(db) as Box<dyn Database>)
+    }
+}
+
+impl fmt::Debug for RedbDB {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RedbDB").finish()
+    }
+}
+
+impl fmt::Debug for RedbDBSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RedbDBSnapshot").finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::access::CopyAccessExt;
+
+    #[test]
+    fn snapshot_isolation() {
+        let dir = TempDir::new().unwrap();
+        let db = RedbDB::open(dir.path(), &DbOptions::default()).unwrap();
+
+        let fork = db.fork();
+        fork.get_list("list").extend(vec![1_u32, 2, 3]);
+        db.merge(fork.into_patch()).unwrap();
+        let snapshot = db.snapshot();
+
+        let fork = db.fork();
+        fork.get_list("list").push(4_u32);
+        fork.get_proof_map("map").put(&1_u32, "!".to_owned());
+        db.merge_sync(fork.into_patch()).unwrap();
+
+        let list = snapshot.get_list::<_, u32>("list");
+        assert_eq!(list.iter().collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert_eq!(
+            snapshot.get_proof_map::<_, u32, String>("map").get(&1),
+            None
+        );
+
+        let snapshot = db.snapshot();
+        let list = snapshot.get_list::<_, u32>("list");
+        assert_eq!(list.iter().collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+        let map = snapshot.get_proof_map::<_, u32, String>("map");
+        assert_eq!(map.get(&1), Some("!".to_owned()));
+    }
+
+    #[test]
+    fn clearing_indexes_in_group() {
+        let dir = TempDir::new().unwrap();
+        let db = RedbDB::open(dir.path(), &DbOptions::default()).unwrap();
+
+        let fork = db.fork();
+        fork.get_list(("group", &1_u8)).extend(vec![1_u32, 2]);
+        fork.get_list(("group", &2_u8)).extend(vec![3_u32, 4]);
+        fork.get_list("list").extend(vec![5_u32, 6]);
+        db.merge(fork.into_patch()).unwrap();
+
+        let fork = db.fork();
+        fork.get_list::<_, u32>(("group", &1_u8)).clear();
+        fork.get_list::<_, u32>("list").clear();
+        db.merge(fork.into_patch()).unwrap();
+
+        let snapshot = db.snapshot();
+        assert!(snapshot.get_list::<_, u32>(("group", &1_u8)).is_empty());
+        assert!(snapshot.get_list::<_, u32>("list").is_empty());
+        let list = snapshot.get_list::<_, u32>(("group", &2_u8));
+        assert_eq!(list.iter().collect::<Vec<_>>(), vec![3, 4]);
+    }
+
+    #[test]
+    fn reopening_database() {
+        let dir = TempDir::new().unwrap();
+        {
+            let db = RedbDB::open(dir.path(), &DbOptions::default()).unwrap();
+            let fork = db.fork();
+            fork.get_proof_entry("entry").set(42_u64);
+            db.merge_sync(fork.into_patch()).unwrap();
+        }
+
+        let mut options = DbOptions::default();
+        options.create_if_missing = false;
+        let db = RedbDB::open(dir.path(), &options).unwrap();
+        let snapshot = db.snapshot();
+        assert_eq!(snapshot.get_proof_entry::<_, u64>("entry").get(), Some(42));
+    }
+
+    #[test]
+    fn opening_missing_database() {
+        let dir = TempDir::new().unwrap();
+        let mut options = DbOptions::default();
+        options.create_if_missing = false;
+        assert!(RedbDB::open(dir.path().join("missing"), &options).is_err());
+    }
+}
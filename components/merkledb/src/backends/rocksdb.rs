@@ -19,12 +19,13 @@ pub use rocksdb::{BlockBasedOptions as RocksBlockOptions, WriteOptions as RocksD
 use crossbeam::sync::{ShardedLock, ShardedLockReadGuard};
 use ctor::{ctor, dtor};
 use rocksdb::{
-    self, checkpoint::Checkpoint, ColumnFamily, DBIterator, Options as RocksDbOptions, WriteBatch,
+    self, checkpoint::Checkpoint, ColumnFamily, ColumnFamilyDescriptor, DBIterator,
+    Options as RocksDbOptions, WriteBatch,
 };
 use smallvec::SmallVec;
 
 use std::{
-    fmt,
+    cmp, fmt,
     iter::Peekable,
     mem, ops,
     path::Path,
@@ -34,15 +35,21 @@ use std::{
     },
 };
 
+use super::next_id_bytes;
 use crate::{
     db::{check_database, Change},
-    Database, DbOptions, Iter, Iterator, Patch, ResolvedAddress, Snapshot,
+    journal,
+    options::{ColumnFamilyOptions, WalSyncMode},
+    ColumnFamilyStats, Database, DbOptions, Iter, Iterator, Patch, ResolvedAddress, Snapshot,
 };
 
 /// Size of a byte representation of an index ID, which is used to prefix index keys
 /// in a column family.
 const ID_SIZE: usize = mem::size_of::<u64>();
 
+/// Name of the default column family.
+pub(super) const DEFAULT_CF: &str = "default";
+
 /// Flag indicating that the main thread has exited.
 #[ctor]
 static FINISHED: AtomicBool = AtomicBool::new(false);
@@ -103,6 +110,9 @@ type InnerDB = NoDropOnShutdown<rocksdb::DB>;
 pub struct RocksDB {
     db: Arc<ShardedLock<InnerDB>>,
     options: DbOptions,
+    /// Block-based table options shared by all column families. The options hold
+    /// a reference to the block cache, so the cache is shared among column families as well.
+    block_options: Option<RocksBlockOptions>,
 }
 
 impl From<DbOptions> for RocksDbOptions {
@@ -113,11 +123,63 @@ impl From<DbOptions> for RocksDbOptions {
 
 impl From<&DbOptions> for RocksDbOptions {
     fn from(opts: &DbOptions) -> Self {
-        let mut defaults = Self::default();
-        defaults.create_if_missing(opts.create_if_missing);
-        defaults.set_compression_type(opts.compression_type.into());
-        defaults.set_max_open_files(opts.max_open_files.unwrap_or(-1));
-        defaults
+        cf_options(opts, block_options(opts).as_ref(), DEFAULT_CF)
+    }
+}
+
+/// Creates block-based table options from the database options, or returns `None`
+/// if the default table options should be used.
+fn block_options(opts: &DbOptions) -> Option<RocksBlockOptions> {
+    if opts.block_cache_size.is_none() && opts.bloom_filter_bits.is_none() {
+        return None;
+    }
+
+    let mut block_opts = RocksBlockOptions::default();
+    if let Some(size) = opts.block_cache_size {
+        block_opts.set_lru_cache(size);
+    }
+    if let Some(bits) = opts.bloom_filter_bits {
+        block_opts.set_bloom_filter(bits, false);
+    }
+    Some(block_opts)
+}
+
+/// Creates `RocksDB` options for the column family with the specified name.
+fn cf_options(
+    opts: &DbOptions,
+    block_opts: Option<&RocksBlockOptions>,
+    cf_name: &str,
+) -> RocksDbOptions {
+    let mut defaults = RocksDbOptions::default();
+    defaults.create_if_missing(opts.create_if_missing);
+    defaults.set_compression_type(opts.compression_type.into());
+    defaults.set_max_open_files(opts.max_open_files.unwrap_or(-1));
+    if let Some(jobs) = opts.max_background_jobs {
+        // Split jobs among flushes and compactions similar to `max_background_jobs`
+        // in newer `RocksDB` versions.
+        let flushes = cmp::max(jobs / 4, 1);
+        defaults.increase_parallelism(jobs);
+        defaults.set_max_background_flushes(flushes);
+        defaults.set_max_background_compactions(cmp::max(jobs - flushes, 1));
+    }
+    if let Some(block_opts) = block_opts {
+        // Table options are copied into the column family options together with
+        // the reference to the block cache.
+        defaults.set_block_based_table_factory(block_opts);
+    }
+    apply_cf_options(&mut defaults, opts.column_family_options(cf_name));
+    defaults
+}
+
+fn apply_cf_options(target: &mut RocksDbOptions, opts: ColumnFamilyOptions) {
+    if let Some(size) = opts.write_buffer_size {
+        target.set_write_buffer_size(size);
+    }
+    if let Some(number) = opts.max_write_buffer_number {
+        target.set_max_write_buffer_number(number);
+    }
+    if let Some(compression_type) = opts.compression_type {
+        target.set_compression_type(compression_type.into());
     }
 }
 
@@ -143,19 +205,26 @@ impl RocksDB {
     /// `create_if_missing` is switched on in `DbOptions`, a new database will
     /// be created at the indicated path.
     pub fn open<P: AsRef<Path>>(path: P, options: &DbOptions) -> crate::Result<Self> {
+        let block_options = block_options(options);
+        let db_options = cf_options(options, block_options.as_ref(), DEFAULT_CF);
         let inner = {
             if let Ok(names) = rocksdb::DB::list_cf(&RocksDbOptions::default(), &path) {
-                let cf_names = names.iter().map(String::as_str).collect::<Vec<_>>();
-                rocksdb::DB::open_cf(&options.into(), path, cf_names)?
+                let cf_descriptors = names.iter().map(|name| {
+                    let cf_options = cf_options(options, block_options.as_ref(), name);
+                    ColumnFamilyDescriptor::new(name, cf_options)
+                });
+                rocksdb::DB::open_cf_descriptors(&db_options, path, cf_descriptors)?
             } else {
-                rocksdb::DB::open(&options.into(), path)?
+                rocksdb::DB::open(&db_options, path)?
             }
         };
         let mut db = Self {
             db: Arc::new(ShardedLock::new(NoDropOnShutdown::new(inner))),
-            options: *options,
+            options: options.clone(),
+            block_options,
         };
         check_database(&mut db)?;
+        journal::recover_on_open(&db)?;
         Ok(db)
     }
 
@@ -171,6 +240,22 @@ impl RocksDB {
         Ok(())
     }
 
+    /// Performs full compaction of all column families in the database. Compaction discards
+    /// deleted and overwritten entries, which reduces the database size on the disk.
+    ///
+    /// The database remains available for reads and writes during compaction; however,
+    /// compaction is I/O intensive and may take a long time for large databases.
+    pub fn compact(&self) -> crate::Result<()> {
+        let db_reader = self.get_lock_guard();
+        let names = rocksdb::DB::list_cf(&RocksDbOptions::default(), db_reader.path())?;
+        for name in names {
+            if let Some(cf) = db_reader.cf_handle(&name) {
+                db_reader.compact_range_cf(cf, None::<&[u8]>, None::<&[u8]>);
+            }
+        }
+        Ok(())
+    }
+
     fn cf_exists(&self, cf_name: &str) -> bool {
         self.get_lock_guard().cf_handle(cf_name).is_some()
     }
@@ -179,10 +264,24 @@ impl RocksDB {
         self.db
             .write()
             .expect("Couldn't get write lock to DB")
-            .create_cf(cf_name, &self.options.into())
+            .create_cf(
+                cf_name,
+                &cf_options(&self.options, self.block_options.as_ref(), cf_name),
+            )
             .map_err(Into::into)
     }
 
+    /// Creates write options according to the WAL sync mode of the database.
+    fn write_options(&self, sync: bool) -> RocksDBWriteOptions {
+        let mut w_opts = RocksDBWriteOptions::default();
+        match self.options.wal_sync_mode {
+            WalSyncMode::Manual => w_opts.set_sync(sync),
+            WalSyncMode::Always => w_opts.set_sync(true),
+            WalSyncMode::Disabled => w_opts.disable_wal(true),
+        }
+        w_opts
+    }
+
     pub(super) fn get_lock_guard(&self) -> ShardedLockReadGuard<'_, InnerDB> {
         self.db.read().expect("Couldn't get read lock to DB")
     }
@@ -230,6 +329,9 @@ impl RocksDB {
             if changes.is_cleared() {
                 self.clear_prefix(&mut batch, cf, &resolved)?;
             }
+            for (start, end) in changes.removed_ranges() {
+                self.remove_range(&mut batch, cf, &resolved, start, end)?;
+            }
 
             if let Some(id_bytes) = resolved.id_to_bytes() {
                 // Write changes to the column family with each key prefixed by the ID of the
@@ -281,6 +383,43 @@ impl RocksDB {
         }
     }
 
+    /// Removes keys in the `start..end` range of the resolved address from a column family.
+    /// `end == None` means that the range is unbounded from above.
+    fn remove_range(
+        &self,
+        batch: &mut WriteBatch,
+        cf: &ColumnFamily,
+        resolved: &ResolvedAddress,
+        start: &[u8],
+        end: Option<&[u8]>,
+    ) -> crate::Result<()> {
+        if let Some(id_bytes) = resolved.id_to_bytes() {
+            let end = end.map_or_else(
+                || next_id_bytes(id_bytes).to_vec(),
+                |end| resolved.keyed(end).into_owned(),
+            );
+            batch
+                .delete_range_cf(cf, resolved.keyed(start).as_ref(), end.as_slice())
+                .map_err(Into::into)
+        } else if let Some(end) = end {
+            batch.delete_range_cf(cf, start, end).map_err(Into::into)
+        } else {
+            let db_reader = self.get_lock_guard();
+            let mut iter = db_reader.raw_iterator_cf(cf)?;
+            iter.seek_to_last();
+            if iter.valid() {
+                if let Some(last_key) = iter.key() {
+                    if last_key >= start {
+                        // `delete_range_cf` does not remove the end of the range.
+                        batch.delete_range_cf(cf, start, last_key)?;
+                        batch.delete_cf(cf, last_key)?;
+                    }
+                }
+            }
+            Ok(())
+        }
+    }
+
     #[allow(unsafe_code)]
     pub(super) fn rocksdb_snapshot(&self) -> RocksDBSnapshot {
         RocksDBSnapshot {
@@ -329,14 +468,40 @@ impl Database for RocksDB {
     }
 
     fn merge(&self, patch: Patch) -> crate::Result<()> {
-        let w_opts = RocksDBWriteOptions::default();
-        self.do_merge(patch, &w_opts)
+        self.do_merge(patch, &self.write_options(false))
     }
 
     fn merge_sync(&self, patch: Patch) -> crate::Result<()> {
-        let mut w_opts = RocksDBWriteOptions::default();
-        w_opts.set_sync(true);
-        self.do_merge(patch, &w_opts)
+        self.do_merge(patch, &self.write_options(true))
+    }
+
+    fn column_family_stats(&self) -> Vec<ColumnFamilyStats> {
+        /// Integer properties of column families included into statistics.
+        const PROPERTIES: &[&str] = &[
+            "rocksdb.estimate-num-keys",
+            "rocksdb.estimate-live-data-size",
+            "rocksdb.total-sst-files-size",
+            "rocksdb.size-all-mem-tables",
+            "rocksdb.estimate-pending-compaction-bytes",
+        ];
+
+        let db_reader = self.get_lock_guard();
+        let names =
+            rocksdb::DB::list_cf(&RocksDbOptions::default(), db_reader.path()).unwrap_or_default();
+        names
+            .into_iter()
+            .filter_map(|name| {
+                let cf = db_reader.cf_handle(&name)?;
+                let properties = PROPERTIES
+                    .iter()
+                    .filter_map(|&property| {
+                        let value = db_reader.property_int_value_cf(cf, property).ok()??;
+                        Some((property.to_owned(), value))
+                    })
+                    .collect();
+                Some(ColumnFamilyStats::new(name, properties))
+            })
+            .collect()
     }
 }
 
@@ -418,45 +583,6 @@ impl fmt::Debug for RocksDBSnapshot {
     }
 }
 
-/// Generates the sequence of bytes lexicographically following the provided one. Assumes that
-/// the provided sequence is less than `[u8::max_value(); ID_SIZE]`.
-fn next_id_bytes(id_bytes: [u8; ID_SIZE]) -> [u8; ID_SIZE] {
-    let mut next_id_bytes = id_bytes;
-    for byte in next_id_bytes.iter_mut().rev() {
-        if *byte == u8::max_value() {
-            *byte = 0;
-        } else {
-            *byte += 1;
-            break;
-        }
-    }
-    next_id_bytes
-}
-
-#[test]
-fn test_next_id_bytes() {
-    assert_eq!(
-        next_id_bytes([1, 0, 0, 0, 0, 0, 0, 0]),
-        [1, 0, 0, 0, 0, 0, 0, 1]
-    );
-    assert_eq!(
-        next_id_bytes([1, 2, 3, 4, 5, 6, 7, 8]),
-        [1, 2, 3, 4, 5, 6, 7, 9]
-    );
-    assert_eq!(
-        next_id_bytes([1, 0, 0, 0, 0, 0, 0, 254]),
-        [1, 0, 0, 0, 0, 0, 0, 255]
-    );
-    assert_eq!(
-        next_id_bytes([1, 0, 0, 0, 0, 0, 41, 255]),
-        [1, 0, 0, 0, 0, 0, 42, 0]
-    );
-    assert_eq!(
-        next_id_bytes([1, 2, 3, 4, 5, 255, 255, 255]),
-        [1, 2, 3, 4, 6, 0, 0, 0]
-    );
-}
-
 /// To reproduce UB in the test, it should run in isolation. The UB may vanish on consecutive
 /// launches of the test.
 ///
@@ -491,3 +617,73 @@ fn concurrency_is_hard() {
     thread::sleep(Duration::from_millis(20));
     signal.store(false, Ordering::Release);
 }
+
+#[test]
+fn database_with_tuned_options() {
+    use crate::{access::CopyAccessExt, options::ColumnFamilyOptions};
+    use tempfile::TempDir;
+
+    let dir = TempDir::new().unwrap();
+    let mut options = DbOptions {
+        max_background_jobs: Some(4),
+        wal_sync_mode: WalSyncMode::Always,
+        block_cache_size: Some(1 << 20),
+        bloom_filter_bits: Some(10),
+        column_family: ColumnFamilyOptions {
+            max_write_buffer_number: Some(3),
+            ..ColumnFamilyOptions::default()
+        },
+        ..DbOptions::default()
+    };
+    let tx_options = ColumnFamilyOptions {
+        write_buffer_size: Some(1 << 22),
+        ..ColumnFamilyOptions::default()
+    };
+    options
+        .column_family_overrides
+        .insert("transactions".to_owned(), tx_options);
+
+    let tx_options = options.column_family_options("transactions");
+    assert_eq!(tx_options.write_buffer_size, Some(1 << 22));
+    assert_eq!(tx_options.max_write_buffer_number, Some(3));
+    assert_eq!(
+        options.column_family_options("other"),
+        options.column_family
+    );
+
+    {
+        let db = RocksDB::open(dir.path(), &options).unwrap();
+        let fork = db.fork();
+        fork.get_list("transactions").push(1_u64);
+        fork.get_list("other").push(2_u64);
+        db.merge(fork.into_patch()).unwrap();
+    }
+
+    // Column families are reopened with the same options.
+    options.wal_sync_mode = WalSyncMode::Disabled;
+    let db = RocksDB::open(dir.path(), &options).unwrap();
+    let snapshot = db.snapshot();
+    assert_eq!(snapshot.get_list::<_, u64>("transactions").get(0), Some(1));
+    assert_eq!(snapshot.get_list::<_, u64>("other").get(0), Some(2));
+}
+
+#[test]
+fn database_compaction() {
+    use crate::access::CopyAccessExt;
+    use tempfile::TempDir;
+
+    let dir = TempDir::new().unwrap();
+    let db = RocksDB::open(dir.path(), &DbOptions::default()).unwrap();
+    let fork = db.fork();
+    fork.get_list("list").extend(0_u64..100);
+    fork.get_map("map").put(&1_u8, 2_u64);
+    db.merge(fork.into_patch()).unwrap();
+    let fork = db.fork();
+    fork.get_list::<_, u64>("list").clear();
+    db.merge(fork.into_patch()).unwrap();
+
+    db.compact().unwrap();
+    let snapshot = db.snapshot();
+    assert!(snapshot.get_list::<_, u64>("list").is_empty());
+    assert_eq!(snapshot.get_map::<_, u8, u64>("map").get(&1), Some(2));
+}
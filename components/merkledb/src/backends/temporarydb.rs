@@ -19,8 +19,11 @@ use tempfile::TempDir;
 
 use std::sync::Arc;
 
-use crate::backends::rocksdb::{RocksDB, RocksDBSnapshot};
-use crate::{db::DB_METADATA, Database, DbOptions, Iter, Patch, ResolvedAddress, Result, Snapshot};
+use crate::backends::rocksdb::{RocksDB, RocksDBSnapshot, DEFAULT_CF};
+use crate::{
+    db::DB_METADATA, ColumnFamilyStats, Database, DbOptions, Iter, Patch, ResolvedAddress, Result,
+    Snapshot,
+};
 
 /// A wrapper over the `RocksDB` backend which stores data in the temporary directory
 /// using the `tempfile` crate.
@@ -51,9 +54,6 @@ impl TemporaryDB {
 
     /// Clears the contents of the database.
     pub fn clear(&self) -> crate::Result<()> {
-        /// Name of the default column family.
-        const DEFAULT_CF: &str = "default";
-
         let opts = rocksdb::Options::default();
         let names = rocksdb::DB::list_cf(&opts, self.dir.path())?;
 
@@ -97,6 +97,10 @@ impl Database for TemporaryDB {
     fn merge_sync(&self, patch: Patch) -> Result<()> {
         self.inner.merge_sync(patch)
     }
+
+    fn column_family_stats(&self) -> Vec<ColumnFamilyStats> {
+        self.inner.column_family_stats()
+    }
 }
 
 impl Snapshot for TemporarySnapshot {
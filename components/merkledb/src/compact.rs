@@ -0,0 +1,706 @@
+// Copyright 2020 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Compact binary format for Merkle proofs.
+//!
+//! The compact format is intended for bandwidth-constrained clients (e.g., mobile light
+//! clients). Compared to the JSON and Protobuf representations of proofs, it removes
+//! redundancy in the proof structure:
+//!
+//! - Paths in a [`MapProof`] are sorted, so each path is encoded as the length of
+//!   the prefix shared with the previous path and the remaining bits only.
+//! - Positions of hashes in a [`ListProof`] are mostly determined by the indexes
+//!   of the proven elements. The format encodes positions as a bitmap over the possible
+//!   sibling positions, falling back to explicit positions for proofs that cannot
+//!   be represented this way.
+//!
+//! Hashes, keys and values are written as is. A decoded proof is *unchecked*; it must be
+//! verified with `check()` or `check_against_hash()` as a proof in any other format.
+//!
+//! # Examples
+//!
+//! ```
+//! # use exonum_merkledb::{
+//! #     access::CopyAccessExt, compact::CompactProof, Database, MapProof, ObjectHash, TemporaryDB,
+//! # };
+//! # use exonum_crypto::{hash, Hash};
+//! # fn main() -> anyhow::Result<()> {
+//! let fork = { let db = TemporaryDB::new(); db.fork() };
+//! let mut map = fork.get_proof_map("index");
+//! for i in 0_u8..100 {
+//!     map.put(&hash(&[i]), u64::from(i));
+//! }
+//! let proof = map.get_multiproof(vec![hash(&[1]), hash(&[2]), hash(&[255])]);
+//!
+//! let bytes = proof.to_compact_bytes();
+//! assert!(bytes.len() < serde_json::to_vec(&proof)?.len());
+//! let restored = MapProof::<Hash, u64>::from_compact_bytes(&bytes)?;
+//! assert_eq!(restored, proof);
+//! restored.check_against_hash(map.object_hash())?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`MapProof`]: ../indexes/proof_map/struct.MapProof.html
+//! [`ListProof`]: ../indexes/proof_list/struct.ListProof.html
+
+use anyhow::{bail, ensure, format_err};
+use exonum_crypto::{Hash, HASH_SIZE};
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+
+use std::{borrow::Cow, collections::BTreeSet, convert::TryFrom};
+
+use crate::{
+    indexes::{
+        proof_list::{tree_height_by_length, HashedEntry, ProofListKey, HEIGHT_SHIFT, MAX_INDEX},
+        proof_map::{BitsRange, ChildKind, ProofPath, PROOF_MAP_KEY_SIZE},
+    },
+    BinaryValue, ListProof, MapProof,
+};
+
+/// Tag of a key missing from the map.
+const TAG_MISSING: u8 = 0;
+/// Tag of a key-value pair present in the map.
+const TAG_PRESENT: u8 = 1;
+/// Positions of list proof hashes are encoded as a bitmap over possible sibling positions.
+const POSITIONS_BITMAP: u8 = 0;
+/// Positions of list proof hashes are encoded explicitly.
+const POSITIONS_EXPLICIT: u8 = 1;
+
+/// Proofs that can be converted to and from the compact binary format.
+///
+/// See the [module docs](index.html) for the description of the format.
+pub trait CompactProof: Sized {
+    /// Serializes the proof into the compact binary format.
+    fn to_compact_bytes(&self) -> Vec<u8>;
+
+    /// Deserializes the proof from the compact binary format.
+    ///
+    /// The returned proof is not checked; it should be verified as a proof obtained
+    /// from any other untrusted source.
+    fn from_compact_bytes(bytes: &[u8]) -> anyhow::Result<Self>;
+}
+
+/// Wrapper for a proof serialized with `serde` as a hex string with the compact
+/// binary representation of the proof.
+///
+/// # Examples
+///
+/// ```
+/// # use exonum_merkledb::{
+/// #     access::CopyAccessExt, compact::Compact, Database, ListProof, TemporaryDB,
+/// # };
+/// # use serde_json::json;
+/// let fork = { let db = TemporaryDB::new(); db.fork() };
+/// let mut list = fork.get_proof_list("index");
+/// list.extend(vec![1_u32, 2, 3]);
+///
+/// let proof = Compact(list.get_proof(1));
+/// let json = serde_json::to_value(&proof).unwrap();
+/// assert!(json.is_string());
+/// let restored: Compact<ListProof<u32>> = serde_json::from_value(json).unwrap();
+/// assert_eq!(restored, proof);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Compact<P>(pub P);
+
+impl<P: CompactProof> Serialize for Compact<P> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&hex::encode(self.0.to_compact_bytes()))
+    }
+}
+
+impl<'de, P: CompactProof> Deserialize<'de> for Compact<P> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let hex_string = <Cow<'_, str>>::deserialize(deserializer)?;
+        let bytes = hex::decode(hex_string.as_ref()).map_err(D::Error::custom)?;
+        P::from_compact_bytes(&bytes)
+            .map(Self)
+            .map_err(D::Error::custom)
+    }
+}
+
+fn write_len(buffer: &mut Vec<u8>, len: u64) {
+    leb128::write::unsigned(buffer, len).unwrap();
+}
+
+fn write_bytes(buffer: &mut Vec<u8>, bytes: &[u8]) {
+    write_len(buffer, bytes.len() as u64);
+    buffer.extend_from_slice(bytes);
+}
+
+/// Reader of the compact binary format.
+#[derive(Debug)]
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    fn read_u64(&mut self) -> anyhow::Result<u64> {
+        leb128::read::unsigned(&mut self.bytes).map_err(|e| format_err!("{}", e))
+    }
+
+    fn read_len(&mut self) -> anyhow::Result<usize> {
+        usize::try_from(self.read_u64()?).map_err(From::from)
+    }
+
+    fn read_slice(&mut self, len: usize) -> anyhow::Result<&'a [u8]> {
+        ensure!(self.bytes.len() >= len, "Unexpected end of compact proof");
+        let (slice, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(slice)
+    }
+
+    fn read_u8(&mut self) -> anyhow::Result<u8> {
+        self.read_slice(1).map(|slice| slice[0])
+    }
+
+    fn read_bytes(&mut self) -> anyhow::Result<&'a [u8]> {
+        let len = self.read_len()?;
+        self.read_slice(len)
+    }
+
+    fn read_value<V: BinaryValue>(&mut self) -> anyhow::Result<V> {
+        self.read_bytes()
+            .and_then(|bytes| V::from_bytes(Cow::Borrowed(bytes)))
+    }
+
+    fn read_hash(&mut self) -> anyhow::Result<Hash> {
+        self.read_slice(HASH_SIZE)
+            .map(|bytes| Hash::from_slice(bytes).unwrap())
+    }
+
+    fn finish(&self) -> anyhow::Result<()> {
+        ensure!(self.bytes.is_empty(), "Trailing bytes in compact proof");
+        Ok(())
+    }
+}
+
+/// Bits packed into bytes starting from the least significant bit, as in `ProofPath`.
+#[derive(Debug, Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    len: usize,
+}
+
+impl BitWriter {
+    fn push(&mut self, bit: bool) {
+        if self.len % 8 == 0 {
+            self.bytes.push(0);
+        }
+        if bit {
+            *self.bytes.last_mut().unwrap() |= 1 << (self.len % 8);
+        }
+        self.len += 1;
+    }
+}
+
+#[derive(Debug)]
+struct BitReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    fn read(&mut self) -> anyhow::Result<bool> {
+        let byte = self
+            .bytes
+            .get(self.pos / 8)
+            .ok_or_else(|| format_err!("Unexpected end of bitmap in compact proof"))?;
+        let bit = byte & (1 << (self.pos % 8)) != 0;
+        self.pos += 1;
+        Ok(bit)
+    }
+
+    /// Checks that all bits have been read, and the padding bits are zero.
+    fn finish(&self) -> anyhow::Result<()> {
+        ensure!(
+            self.bytes.len() == (self.pos + 7) / 8,
+            "Trailing bytes in bitmap in compact proof"
+        );
+        let tail = self.pos % 8;
+        if tail != 0 {
+            let last_byte = self.bytes[self.bytes.len() - 1];
+            ensure!(last_byte >> tail == 0, "Non-zero padding in compact proof");
+        }
+        Ok(())
+    }
+}
+
+/// Writes bits of the `path` starting from `from` position.
+fn write_path_bits(buffer: &mut Vec<u8>, path: &ProofPath, from: u16) {
+    let mut bits = BitWriter::default();
+    for i in from..path.len() {
+        bits.push(path.bit(i) == ChildKind::Right);
+    }
+    buffer.extend_from_slice(&bits.bytes);
+}
+
+impl<K, V, KeyMode> CompactProof for MapProof<K, V, KeyMode>
+where
+    K: BinaryValue,
+    V: BinaryValue,
+{
+    /// Serializes the proof into the compact binary format:
+    ///
+    /// - Number of entries, followed by the entries. Each entry is encoded as a tag (`0`
+    ///   for missing keys, `1` for key-value pairs), the key and, for existing entries,
+    ///   the value. Keys and values are encoded as `BinaryValue`s prefixed by their length.
+    /// - Number of proof entries, followed by the proof entries. Each entry is encoded
+    ///   as the length of the prefix shared with the previous path in the proof, the total
+    ///   length of the path in bits, the remaining path bits packed into bytes, and the hash.
+    ///
+    /// All numbers are encoded with `leb128`.
+    fn to_compact_bytes(&self) -> Vec<u8> {
+        let mut buffer = vec![];
+
+        let entries: Vec<_> = self.all_entries_unchecked().collect();
+        write_len(&mut buffer, entries.len() as u64);
+        for (key, value) in entries {
+            buffer.push(if value.is_some() {
+                TAG_PRESENT
+            } else {
+                TAG_MISSING
+            });
+            write_bytes(&mut buffer, &key.to_bytes());
+            if let Some(value) = value {
+                write_bytes(&mut buffer, &value.to_bytes());
+            }
+        }
+
+        let proof = self.proof_unchecked();
+        write_len(&mut buffer, proof.len() as u64);
+        let mut prev_path: Option<ProofPath> = None;
+        for (path, hash) in proof {
+            let shared_len = prev_path.map_or(0, |prev| prev.common_prefix_len(&path));
+            write_len(&mut buffer, u64::from(shared_len));
+            write_len(&mut buffer, u64::from(path.len()));
+            write_path_bits(&mut buffer, &path, shared_len);
+            buffer.extend_from_slice(hash.as_ref());
+            prev_path = Some(path);
+        }
+        buffer
+    }
+
+    fn from_compact_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+        let mut reader = Reader::new(bytes);
+        let mut proof = Self::new();
+
+        let entries_count = reader.read_len()?;
+        for _ in 0..entries_count {
+            let tag = reader.read_u8()?;
+            let key = reader.read_value()?;
+            proof = match tag {
+                TAG_MISSING => proof.add_missing(key),
+                TAG_PRESENT => proof.add_entry(key, reader.read_value()?),
+                _ => bail!("Unknown entry tag in compact proof: {}", tag),
+            };
+        }
+
+        let max_len = PROOF_MAP_KEY_SIZE as u16 * 8;
+        let proof_count = reader.read_len()?;
+        let mut paths = vec![];
+        let mut prev_bytes = [0_u8; PROOF_MAP_KEY_SIZE];
+        let mut prev_len = 0;
+        for _ in 0..proof_count {
+            let shared_len = u16::try_from(reader.read_u64()?)?;
+            let len = u16::try_from(reader.read_u64()?)?;
+            ensure!(
+                len > 0 && len <= max_len,
+                "Invalid path length in compact proof"
+            );
+            ensure!(
+                shared_len <= len && shared_len <= prev_len,
+                "Invalid shared prefix length in compact proof"
+            );
+
+            let mut path_bytes = prev_bytes;
+            for i in shared_len..max_len {
+                path_bytes[usize::from(i / 8)] &= !(1 << (i % 8));
+            }
+            let suffix_len = usize::from(len - shared_len);
+            let mut bits = BitReader::new(reader.read_slice((suffix_len + 7) / 8)?);
+            for i in shared_len..len {
+                if bits.read()? {
+                    path_bytes[usize::from(i / 8)] |= 1 << (i % 8);
+                }
+            }
+            bits.finish()?;
+
+            let mut path = ProofPath::from_bytes(path_bytes);
+            if len < max_len {
+                path = path.prefix(len);
+            }
+            if let Some((prev_path, _)) = paths.last() {
+                ensure!(
+                    *prev_path < path,
+                    "Paths in compact proof are not ordered by increasing value"
+                );
+            }
+            paths.push((path, reader.read_hash()?));
+            prev_bytes = path_bytes;
+            prev_len = len;
+        }
+        reader.finish()?;
+
+        Ok(proof.add_proof_entries(paths))
+    }
+}
+
+/// Walks over possible positions of hashes in a list proof with the given entries,
+/// yielding proof positions selected by `take`.
+///
+/// Possible positions on each height of the Merkle tree are the siblings of the nodes
+/// which hashes are known from the lower heights (i.e., can be restored from the entries
+/// and hashes previously selected by `take`).
+fn walk_positions(
+    indexes: impl Iterator<Item = u64>,
+    length: u64,
+    mut take: impl FnMut(ProofListKey) -> anyhow::Result<bool>,
+) -> anyhow::Result<Vec<ProofListKey>> {
+    ensure!(length <= MAX_INDEX + 1, "List length is too large");
+    let mut known: BTreeSet<u64> = indexes.collect();
+    let mut positions = vec![];
+
+    for height in 1..=tree_height_by_length(length) {
+        let shift = height - 1;
+        let width = (length + (1 << shift) - 1) >> shift;
+        let candidates: Vec<_> = known
+            .iter()
+            .map(|index| index ^ 1)
+            .filter(|index| *index < width && !known.contains(index))
+            .collect();
+
+        for index in candidates {
+            let key = ProofListKey::new(height, index);
+            if take(key)? {
+                known.insert(index);
+                positions.push(key);
+            }
+        }
+        known = known.into_iter().map(|index| index / 2).collect();
+    }
+    Ok(positions)
+}
+
+impl<V: BinaryValue> CompactProof for ListProof<V> {
+    /// Serializes the proof into the compact binary format:
+    ///
+    /// - Length of the list.
+    /// - Number of entries, followed by the entries. Each entry is encoded as its index
+    ///   and the value prefixed by its length.
+    /// - Positions of the hashes in the proof. The positions are encoded as a flag byte
+    ///   followed either by a bitmap over possible sibling positions (flag `0`), or by the number
+    ///   of hashes and the `(height, index)` pair for each hash (flag `1`). The bitmap
+    ///   is used whenever possible; it cannot represent positions not adjacent to the entries
+    ///   (e.g., a root hash in the proof for an empty range).
+    /// - Hashes in the proof.
+    ///
+    /// All numbers are encoded with `leb128`.
+    fn to_compact_bytes(&self) -> Vec<u8> {
+        let mut buffer = vec![];
+        write_len(&mut buffer, self.list_len());
+
+        let entries = self.entries_unchecked();
+        write_len(&mut buffer, entries.len() as u64);
+        for (index, value) in entries {
+            write_len(&mut buffer, *index);
+            write_bytes(&mut buffer, &value.to_bytes());
+        }
+
+        let proof = self.proof_unchecked();
+        let keys: Vec<_> = proof.iter().map(|entry| entry.key).collect();
+        let key_set: BTreeSet<_> = keys.iter().copied().collect();
+        let mut bitmap = BitWriter::default();
+        let positions = walk_positions(self.indexes_unchecked(), self.list_len(), |key| {
+            let is_present = key_set.contains(&key);
+            bitmap.push(is_present);
+            Ok(is_present)
+        });
+
+        if positions.ok().as_ref() == Some(&keys) {
+            buffer.push(POSITIONS_BITMAP);
+            write_bytes(&mut buffer, &bitmap.bytes);
+        } else {
+            buffer.push(POSITIONS_EXPLICIT);
+            write_len(&mut buffer, keys.len() as u64);
+            for key in keys {
+                buffer.push(key.height());
+                write_len(&mut buffer, key.index());
+            }
+        }
+
+        for entry in proof {
+            buffer.extend_from_slice(entry.hash.as_ref());
+        }
+        buffer
+    }
+
+    fn from_compact_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+        let mut reader = Reader::new(bytes);
+        let length = reader.read_u64()?;
+
+        let entries_count = reader.read_len()?;
+        let mut entries = vec![];
+        for _ in 0..entries_count {
+            let index = reader.read_u64()?;
+            entries.push((index, reader.read_value()?));
+        }
+
+        let keys = match reader.read_u8()? {
+            POSITIONS_BITMAP => {
+                let mut bitmap = BitReader::new(reader.read_bytes()?);
+                let indexes = entries.iter().map(|(index, _)| *index);
+                let keys = walk_positions(indexes, length, |_| bitmap.read())?;
+                bitmap.finish()?;
+                keys
+            }
+            POSITIONS_EXPLICIT => {
+                let count = reader.read_len()?;
+                let mut keys = vec![];
+                for _ in 0..count {
+                    let height = reader.read_u8()?;
+                    let index = reader.read_u64()?;
+                    ensure!(
+                        u64::from(height) <= HEIGHT_SHIFT && index <= MAX_INDEX,
+                        "Invalid hash position in compact proof"
+                    );
+                    keys.push(ProofListKey::new(height, index));
+                }
+                keys
+            }
+            flag => bail!("Unknown positions encoding in compact proof: {}", flag),
+        };
+
+        let mut proof = vec![];
+        for key in keys {
+            proof.push(HashedEntry::new(key, reader.read_hash()?));
+        }
+        reader.finish()?;
+        Ok(Self::from_raw_parts(proof, entries, length))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use exonum_crypto::hash;
+    use proptest::{collection::vec, prelude::*, test_runner::Config};
+
+    use std::fmt::Debug;
+
+    use super::*;
+    use crate::{access::CopyAccessExt, Database, ObjectHash, TemporaryDB};
+
+    fn check_roundtrip<P>(proof: &P) -> Vec<u8>
+    where
+        P: CompactProof + PartialEq + Debug,
+    {
+        let bytes = proof.to_compact_bytes();
+        let restored = P::from_compact_bytes(&bytes).unwrap();
+        assert_eq!(restored, *proof);
+        bytes
+    }
+
+    #[test]
+    fn map_proof_roundtrip() {
+        let db = TemporaryDB::new();
+        let fork = db.fork();
+        let mut map = fork.get_proof_map("map");
+
+        let proof = map.get_proof(hash(&[1]));
+        check_roundtrip(&proof);
+
+        for i in 0_u16..1_000 {
+            map.put(&hash(&i.to_le_bytes()), i);
+        }
+        let keys: Vec<_> = (990_u16..1_010).map(|i| hash(&i.to_le_bytes())).collect();
+        let proof = map.get_multiproof(keys);
+        let bytes = check_roundtrip(&proof);
+
+        let restored = MapProof::<Hash, u16>::from_compact_bytes(&bytes).unwrap();
+        let checked = restored.check_against_hash(map.object_hash()).unwrap();
+        assert_eq!(checked.entries().count(), 10);
+        assert_eq!(checked.missing_keys().count(), 10);
+
+        // The compact format is more succinct than the Protobuf one.
+        #[cfg(feature = "with-protobuf")]
+        {
+            use exonum_proto::ProtobufConvert;
+            use protobuf::Message;
+
+            let pb_len = proof.to_pb().write_to_bytes().unwrap().len();
+            assert!(bytes.len() < pb_len, "{} >= {}", bytes.len(), pb_len);
+        }
+    }
+
+    #[test]
+    fn raw_map_proof_roundtrip() {
+        let db = TemporaryDB::new();
+        let fork = db.fork();
+        let mut map = fork.get_raw_proof_map("map");
+        for i in 0_u8..50 {
+            map.put(&[i; 32], u32::from(i));
+        }
+        let proof = map.get_multiproof(vec![[0; 32], [1; 32], [100; 32]]);
+        let bytes = check_roundtrip(&proof);
+
+        let restored =
+            MapProof::<[u8; 32], u32, crate::proof_map::Raw>::from_compact_bytes(&bytes).unwrap();
+        restored.check_against_hash(map.object_hash()).unwrap();
+    }
+
+    #[test]
+    fn list_proof_roundtrip() {
+        let db = TemporaryDB::new();
+        let fork = db.fork();
+        let mut list = fork.get_proof_list("list");
+        check_roundtrip(&list.get_proof(0));
+
+        list.extend(0_u32..1_000);
+        for proof in &[
+            list.get_proof(0),
+            list.get_proof(999),
+            list.get_proof(500),
+            list.get_range_proof(100..200),
+            list.get_range_proof(..),
+        ] {
+            let bytes = check_roundtrip(proof);
+            let restored = ListProof::<u32>::from_compact_bytes(&bytes).unwrap();
+            restored.check_against_hash(list.object_hash()).unwrap();
+        }
+
+        // Proofs of ranges outside the list contain the root hash only.
+        let proof = list.get_range_proof(2_000..3_000);
+        let bytes = check_roundtrip(&proof);
+        let restored = ListProof::<u32>::from_compact_bytes(&bytes).unwrap();
+        restored.check_against_hash(list.object_hash()).unwrap();
+    }
+
+    #[test]
+    fn list_proof_bitmap_size() {
+        let db = TemporaryDB::new();
+        let fork = db.fork();
+        let mut list = fork.get_proof_list("list");
+        list.extend(0_u8..=255);
+
+        let proof = list.get_proof(100);
+        let bytes = check_roundtrip(&proof);
+        // Length (2 bytes), entries (4 bytes), positions (3 bytes), hashes.
+        assert_eq!(bytes.len(), 9 + 8 * HASH_SIZE);
+    }
+
+    #[test]
+    fn malformed_map_proofs() {
+        let db = TemporaryDB::new();
+        let fork = db.fork();
+        let mut map = fork.get_proof_map("map");
+        for i in 0_u8..10 {
+            map.put(&hash(&[i]), i);
+        }
+        let proof = map.get_multiproof(vec![hash(&[1]), hash(&[2])]);
+        let bytes = proof.to_compact_bytes();
+
+        for len in 0..bytes.len() {
+            assert!(MapProof::<Hash, u8>::from_compact_bytes(&bytes[..len]).is_err());
+        }
+        let mut extended_bytes = bytes.clone();
+        extended_bytes.push(0);
+        let err = MapProof::<Hash, u8>::from_compact_bytes(&extended_bytes).unwrap_err();
+        assert!(err.to_string().contains("Trailing bytes"));
+
+        let mut bytes_with_invalid_tag = bytes;
+        bytes_with_invalid_tag[1] = 2;
+        let err = MapProof::<Hash, u8>::from_compact_bytes(&bytes_with_invalid_tag).unwrap_err();
+        assert!(err.to_string().contains("Unknown entry tag"));
+    }
+
+    #[test]
+    fn malformed_list_proofs() {
+        let db = TemporaryDB::new();
+        let fork = db.fork();
+        let mut list = fork.get_proof_list("list");
+        list.extend(0_u8..10);
+        let bytes = list.get_range_proof(3..5).to_compact_bytes();
+
+        for len in 0..bytes.len() {
+            assert!(ListProof::<u8>::from_compact_bytes(&bytes[..len]).is_err());
+        }
+
+        // Huge list length.
+        let mut buffer = vec![];
+        write_len(&mut buffer, u64::max_value());
+        buffer.extend_from_slice(&[0, POSITIONS_BITMAP, 0]);
+        let err = ListProof::<u8>::from_compact_bytes(&buffer).unwrap_err();
+        assert!(err.to_string().contains("too large"));
+
+        // Invalid explicit position.
+        let mut buffer = vec![10, 0, POSITIONS_EXPLICIT, 1, 100];
+        write_len(&mut buffer, 0);
+        buffer.extend_from_slice(&[0; HASH_SIZE]);
+        let err = ListProof::<u8>::from_compact_bytes(&buffer).unwrap_err();
+        assert!(err.to_string().contains("Invalid hash position"));
+    }
+
+    proptest! {
+        #![proptest_config(Config::with_cases(32))]
+
+        #[test]
+        fn map_multiproofs(
+            keys in vec(any::<u16>(), 1..50),
+            proof_keys in vec(any::<u16>(), 1..20),
+        ) {
+            let db = TemporaryDB::new();
+            let fork = db.fork();
+            let mut map = fork.get_proof_map("map");
+            for key in keys {
+                map.put(&hash(&key.to_le_bytes()), key);
+            }
+            let proof_keys = proof_keys.into_iter().map(|key| hash(&key.to_le_bytes()));
+            let proof = map.get_multiproof(proof_keys);
+
+            let bytes = proof.to_compact_bytes();
+            let restored = MapProof::<Hash, u16>::from_compact_bytes(&bytes).unwrap();
+            prop_assert_eq!(&restored, &proof);
+            prop_assert!(restored.check_against_hash(map.object_hash()).is_ok());
+        }
+
+        #[test]
+        fn list_range_proofs(len in 1_u64..300, start in 0_u64..310, range_len in 0_u64..50) {
+            let db = TemporaryDB::new();
+            let fork = db.fork();
+            let mut list = fork.get_proof_list("list");
+            list.extend(0..len);
+            let proof = list.get_range_proof(start..start + range_len);
+
+            let bytes = proof.to_compact_bytes();
+            let restored = ListProof::<u64>::from_compact_bytes(&bytes).unwrap();
+            prop_assert_eq!(&restored, &proof);
+            prop_assert!(restored.check_against_hash(list.object_hash()).is_ok());
+        }
+    }
+}
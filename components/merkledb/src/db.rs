@@ -14,6 +14,7 @@
 
 use std::{
     cell::RefCell,
+    cmp,
     collections::{BTreeMap, HashMap, HashSet},
     fmt,
     iter::{Iterator as StdIterator, Peekable},
@@ -25,11 +26,14 @@ use std::{
 };
 
 use crate::{
+    history, integrity,
+    journal::{self, Journal},
+    stats,
     validation::assert_valid_name_component,
     views::{
         get_object_hash, AsReadonly, ChangesIter, IndexesPool, RawAccess, ResolvedAddress, View,
     },
-    Error, Result, SystemSchema,
+    ColumnFamilyStats, Error, IndexStats, IntegrityReport, Result, SystemSchema,
 };
 
 /// Changes related to a specific `View`.
@@ -39,6 +43,10 @@ pub struct ViewChanges {
     pub(super) data: BTreeMap<Vec<u8>, Change>,
     /// Was the view cleared as a part of changes?
     is_cleared: bool,
+    /// Ranges of keys removed from the view, keyed by the inclusive start of the range.
+    /// The values are exclusive ends of the ranges, or `None` for ranges unbounded
+    /// from above. The ranges are disjoint. Changes in `data` take precedence over the ranges.
+    pub(super) removed_ranges: BTreeMap<Vec<u8>, Option<Vec<u8>>>,
     /// Is the view aggregated into `state_hash` of the database?
     /// Storing this information directly in the changes allows to avoid relatively expensive
     /// metadata lookups during state aggregator update in `Fork::into_patch()`.
@@ -50,6 +58,14 @@ impl ViewChanges {
         Self::default()
     }
 
+    /// Creates changes consisting of the specified puts and deletions.
+    pub(crate) fn from_data(data: BTreeMap<Vec<u8>, Change>) -> Self {
+        Self {
+            data,
+            ..Self::default()
+        }
+    }
+
     pub fn is_cleared(&self) -> bool {
         self.is_cleared
     }
@@ -61,9 +77,72 @@ impl ViewChanges {
 
     pub fn clear(&mut self) {
         self.data.clear();
+        self.removed_ranges.clear();
         self.is_cleared = true;
     }
 
+    /// Removes keys in the `start..end` range, where `end == None` means that the range
+    /// is unbounded from above. Unlike removing keys one by one, the removal is recorded
+    /// as a single range tombstone, the size of which does not depend on the number of keys
+    /// in the range.
+    pub fn remove_range(&mut self, start: Vec<u8>, end: Option<Vec<u8>>) {
+        if let Some(ref end) = end {
+            if *end <= start {
+                return;
+            }
+        }
+
+        let mut removed_data = self.data.split_off(&start);
+        if let Some(ref end) = end {
+            let mut retained_data = removed_data.split_off(end);
+            self.data.append(&mut retained_data);
+        }
+        if self.is_cleared {
+            // The snapshot is ignored anyway, so there is no need to record the range.
+            return;
+        }
+
+        // Merge the range with overlapping or adjacent ranges. Since the ranges are disjoint,
+        // their ends are ordered in the same way as their starts.
+        let (mut start, mut end) = (start, end);
+        let overlapping_starts: Vec<_> = self
+            .removed_ranges
+            .iter()
+            .rev()
+            .skip_while(|(range_start, _)| end.as_ref().map_or(false, |end| *range_start > end))
+            .take_while(|(_, range_end)| range_end.as_ref().map_or(true, |end| *end >= start))
+            .map(|(range_start, _)| range_start.to_owned())
+            .collect();
+
+        for range_start in overlapping_starts {
+            let range_end = self.removed_ranges.remove(&range_start).unwrap();
+            if range_start < start {
+                start = range_start;
+            }
+            end = match (end, range_end) {
+                (Some(end), Some(range_end)) => Some(end.max(range_end)),
+                _ => None,
+            };
+        }
+        self.removed_ranges.insert(start, end);
+    }
+
+    /// Returns the removed range containing the specified key, if any.
+    fn removed_range(&self, key: &[u8]) -> Option<(&[u8], Option<&[u8]>)> {
+        self.removed_ranges
+            .range::<[u8], _>((Bound::Unbounded, Bound::Included(key)))
+            .next_back()
+            .map(|(start, end)| (start.as_slice(), end.as_deref()))
+            .filter(|(_, end)| end.map_or(true, |end| key < end))
+    }
+
+    /// Returns an iterator over the removed ranges of keys in the ascending order.
+    pub(crate) fn removed_ranges(&self) -> impl StdIterator<Item = (&[u8], Option<&[u8]>)> {
+        self.removed_ranges
+            .iter()
+            .map(|(start, end)| (start.as_slice(), end.as_deref()))
+    }
+
     pub fn set_aggregation(&mut self, namespace: Option<String>) {
         self.namespace = namespace;
     }
@@ -81,7 +160,7 @@ impl ViewChanges {
                 Change::Delete => None,
             });
         }
-        if self.is_cleared() {
+        if self.is_cleared() || self.removed_range(key).is_some() {
             return Ok(None);
         }
         Err(())
@@ -97,7 +176,7 @@ impl ViewChanges {
             });
         }
 
-        if self.is_cleared() {
+        if self.is_cleared() || self.removed_range(key).is_some() {
             return Ok(false);
         }
         Err(())
@@ -288,6 +367,10 @@ impl WorkingPatch {
             if changes.is_cleared() {
                 *patch_changes = changes;
             } else {
+                // Removed ranges are older than the changes in `data`, so they are applied first.
+                for (start, end) in changes.removed_ranges {
+                    patch_changes.remove_range(start, end);
+                }
                 patch_changes.data.extend(changes.data);
             }
         }
@@ -434,6 +517,36 @@ pub(super) struct ForkIter<'a, T: StdIterator> {
     changes: Option<Peekable<T>>,
 }
 
+/// Iterator over a snapshot skipping the keys in the ranges removed by the overlying changes.
+struct RemovedRangesIter<'a> {
+    snapshot: &'a dyn Snapshot,
+    name: ResolvedAddress,
+    changes: &'a ViewChanges,
+    inner: Iter<'a>,
+    ended: bool,
+}
+
+/// Returns an iterator over the entries of the `snapshot` not covered by ranges removed
+/// in `changes`.
+pub(super) fn snapshot_iter<'a>(
+    snapshot: &'a dyn Snapshot,
+    name: &ResolvedAddress,
+    from: &[u8],
+    changes: Option<&'a ViewChanges>,
+) -> Iter<'a> {
+    let inner = snapshot.iter(name, from);
+    match changes {
+        Some(changes) if !changes.removed_ranges.is_empty() => Box::new(RemovedRangesIter {
+            snapshot,
+            name: name.to_owned(),
+            changes,
+            inner,
+            ended: false,
+        }),
+        _ => inner,
+    }
+}
+
 #[derive(Debug, PartialEq, Eq)]
 enum NextIterValue {
     Stored,
@@ -478,6 +591,17 @@ pub trait Database: Send + Sync + 'static {
     /// Creates a new snapshot of the database from its current state.
     fn snapshot(&self) -> Box<dyn Snapshot>;
 
+    /// Creates a new snapshot of the database from its current state, returning an error
+    /// if the snapshot cannot be created. The default implementation never fails.
+    ///
+    /// Backends for which snapshot creation may fail (e.g., because it involves I/O)
+    /// should override this method; in this case, [`snapshot`] panics on such errors.
+    ///
+    /// [`snapshot`]: #tymethod.snapshot
+    fn try_snapshot(&self) -> Result<Box<dyn Snapshot>> {
+        Ok(self.snapshot())
+    }
+
     /// Creates a new fork of the database from its current state.
     fn fork(&self) -> Fork {
         Fork {
@@ -514,6 +638,12 @@ pub trait Database: Send + Sync + 'static {
     /// will be returned. In case of an error, the method guarantees no changes are applied to
     /// the database.
     fn merge_sync(&self, patch: Patch) -> Result<()>;
+
+    /// Returns backend-specific statistics for column families of the database, such as
+    /// internal `RocksDB` properties. The default implementation returns an empty list.
+    fn column_family_stats(&self) -> Vec<ColumnFamilyStats> {
+        vec![]
+    }
 }
 
 /// Extension trait for `Database`.
@@ -573,47 +703,328 @@ pub trait DatabaseExt: Database {
 
         let snapshot = self.snapshot();
         let changed_aggregated_addrs = patch.changed_aggregated_addrs.clone();
-        let mut rev_changes = HashMap::with_capacity(patch.changes.len());
+        let rev_changes = reverse_changes(snapshot.as_ref(), &patch);
 
-        for (name, changes) in &patch.changes {
-            let mut view_changes = changes.data.clone();
-            for (key, change) in &mut view_changes {
-                *change = if let Some(value) = snapshot.get(name, key) {
-                    Change::Put(value)
-                } else {
-                    Change::Delete
-                };
+        self.merge(patch)?;
+        Ok(Patch {
+            snapshot: self.snapshot(),
+            changes: rev_changes,
+            changed_aggregated_addrs,
+            removed_aggregated_addrs: HashSet::new(),
+        })
+    }
+
+    /// Merges a patch into the database and records changes reverting it in the persistent
+    /// database history. Returns the version of the database state after the merge.
+    ///
+    /// Versions are assigned sequentially starting from zero. The database state at any recorded
+    /// version can be accessed with [`snapshot_at`](#method.snapshot_at). Only patches merged
+    /// with this method are versioned; the history does not track patches merged via
+    /// `Database::merge()` or `Database::merge_sync()`, and such patches will be visible
+    /// in all historical snapshots.
+    ///
+    /// The history grows with each versioned patch; use [`prune_history`](#method.prune_history)
+    /// to bound its size.
+    ///
+    /// # Notes
+    ///
+    /// This method must not be called concurrently with other merges to the same database:
+    /// the reverse changes and the version are computed based on the database snapshot taken
+    /// before the merge.
+    ///
+    /// # Performance notes
+    ///
+    /// The history entry is computed in the same way as the backup patch in
+    /// [`merge_with_backup`](#method.merge_with_backup) and has the same complexity.
+    /// Besides, the history entry is stored in the database and increases its size
+    /// proportionally to the patch size.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error in the same situations as `Database::merge()`.
+    fn merge_with_history(&self, mut patch: Patch) -> Result<u64> {
+        let snapshot = self.snapshot();
+        let version = history::latest_version(snapshot.as_ref()).map_or(0, |version| version + 1);
+        let rev_changes = reverse_changes(snapshot.as_ref(), &patch);
+        let entry = history::encode(
+            rev_changes
+                .iter()
+                .map(|(address, changes)| (address, &changes.data)),
+        );
+
+        patch
+            .changes
+            .entry(history::history_address())
+            .or_insert_with(ViewChanges::new)
+            .data
+            .insert(history::version_key(version).to_vec(), Change::Put(entry));
+        patch
+            .changes
+            .entry(history::metadata_address())
+            .or_insert_with(ViewChanges::new)
+            .data
+            .insert(
+                history::LATEST_VERSION_NAME.as_bytes().to_vec(),
+                Change::Put(history::version_key(version).to_vec()),
+            );
+
+        self.merge(patch)?;
+        Ok(version)
+    }
+
+    /// Returns statistics for all indexes in the database, including indexes in migration.
+    ///
+    /// ```
+    /// # use exonum_merkledb::{access::CopyAccessExt, Database, DatabaseExt, IndexType, TemporaryDB};
+    /// let db = TemporaryDB::new();
+    /// let fork = db.fork();
+    /// fork.get_list("list").extend(vec![1_u32, 2, 3]);
+    /// fork.get_map(("group", &1_u8)).put(&1_u32, 2_u64);
+    /// db.merge(fork.into_patch()).unwrap();
+    ///
+    /// let stats = db.index_stats();
+    /// let list_stats = stats.iter().find(|stats| stats.name == "list").unwrap();
+    /// assert_eq!(list_stats.index_type, IndexType::List);
+    /// assert!(list_stats.key_count >= 3);
+    /// let map_stats = stats.iter().find(|stats| stats.name == "group").unwrap();
+    /// assert_eq!(map_stats.group_key.as_deref(), Some("01"));
+    /// assert_eq!(map_stats.key_count, 1);
+    /// ```
+    ///
+    /// # Performance notes
+    ///
+    /// The method iterates over all entries in the database, thus, it is linear w.r.t.
+    /// the database size.
+    fn index_stats(&self) -> Vec<IndexStats> {
+        stats::index_stats(self.snapshot().as_ref())
+    }
+
+    /// Checks integrity of the Merkelized indexes in the database. For each proof list,
+    /// proof map and proof key set, the hashes of the Merkle tree nodes are recomputed from
+    /// the index contents and compared with the stored ones. Additionally, the hashes
+    /// of the aggregated indexes are compared with the state aggregators: each aggregated index
+    /// must have an up-to-date entry in the aggregator, and each aggregator entry must correspond
+    /// to an aggregated index.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use exonum_merkledb::{
+    /// #     access::CopyAccessExt, Database, DatabaseExt, SystemSchema, TemporaryDB,
+    /// # };
+    /// let db = TemporaryDB::new();
+    /// let fork = db.fork();
+    /// fork.get_proof_list("list").extend(vec![1_u32, 2, 3]);
+    /// fork.get_proof_map("map").put(&1_u64, "!".to_owned());
+    /// db.merge(fork.into_patch()).unwrap();
+    ///
+    /// let report = db.check_integrity();
+    /// assert!(report.is_ok());
+    /// assert_eq!(report.state_hash, SystemSchema::new(&db.snapshot()).state_hash());
+    /// ```
+    ///
+    /// # Performance notes
+    ///
+    /// The method reads all Merkelized indexes in the database, thus, it is linear w.r.t.
+    /// the database size.
+    fn check_integrity(&self) -> IntegrityReport {
+        integrity::check_integrity(self.snapshot().as_ref())
+    }
+
+    /// Returns the latest version recorded in the database history, or `None` if no patches
+    /// were merged with [`merge_with_history`](#method.merge_with_history).
+    fn latest_version(&self) -> Option<u64> {
+        history::latest_version(self.snapshot().as_ref())
+    }
+
+    /// Removes history entries necessary to access versions preceding `version`,
+    /// so that `version` becomes the earliest version accessible
+    /// with [`snapshot_at`](#method.snapshot_at). If `version` exceeds the latest recorded
+    /// version, the latest version is retained. The method does nothing if the history is empty.
+    ///
+    /// ```
+    /// # use exonum_merkledb::{access::CopyAccessExt, Database, DatabaseExt, TemporaryDB};
+    /// let db = TemporaryDB::new();
+    /// for i in 0_u32..5 {
+    ///     let fork = db.fork();
+    ///     fork.get_proof_entry("entry").set(i);
+    ///     db.merge_with_history(fork.into_patch()).unwrap();
+    /// }
+    ///
+    /// db.prune_history(3).unwrap();
+    /// assert!(db.snapshot_at(2).is_err());
+    /// let snapshot = db.snapshot_at(3).unwrap();
+    /// assert_eq!(snapshot.get_proof_entry::<_, u32>("entry").get(), Some(3));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error in the same situations as `Database::merge()`.
+    fn prune_history(&self, version: u64) -> Result<()> {
+        let snapshot = self.snapshot();
+        let latest_version = match history::latest_version(snapshot.as_ref()) {
+            Some(latest_version) => latest_version,
+            None => return Ok(()),
+        };
+        let version = cmp::min(version, latest_version);
+
+        let mut history_changes = ViewChanges::new();
+        history_changes.remove_range(vec![], Some(history::version_key(version).to_vec()));
+        let mut changes = HashMap::new();
+        changes.insert(history::history_address(), history_changes);
+        self.merge(Patch {
+            snapshot,
+            changes,
+            changed_aggregated_addrs: HashMap::new(),
+            removed_aggregated_addrs: HashSet::new(),
+        })
+    }
+
+    /// Returns a read-only snapshot of the database state as of the specified `version`,
+    /// i.e., right after the patch with this version was merged
+    /// with [`merge_with_history`](#method.merge_with_history).
+    ///
+    /// The returned snapshot is consistent: its aggregated state (including the state hash
+    /// returned by `SystemSchema::state_hash()`) corresponds to the requested version, so proofs
+    /// built from the snapshot can be checked against the state hash recorded at that version.
+    ///
+    /// ```
+    /// # use exonum_merkledb::{access::CopyAccessExt, Database, DatabaseExt, TemporaryDB};
+    /// let db = TemporaryDB::new();
+    /// let fork = db.fork();
+    /// fork.get_proof_map("map").put(&1_u32, "foo".to_owned());
+    /// let version = db.merge_with_history(fork.into_patch()).unwrap();
+    /// let fork = db.fork();
+    /// fork.get_proof_map("map").put(&1_u32, "bar".to_owned());
+    /// db.merge_with_history(fork.into_patch()).unwrap();
+    ///
+    /// let old_snapshot = db.snapshot_at(version).unwrap();
+    /// let map = old_snapshot.get_proof_map::<_, u32, String>("map");
+    /// assert_eq!(map.get(&1).unwrap(), "foo");
+    /// ```
+    ///
+    /// # Performance notes
+    ///
+    /// The snapshot is constructed by loading all changes recorded after `version`
+    /// into memory. Thus, the method may be inappropriate for accessing very old versions
+    /// of frequently changing databases.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the `version` was not recorded in the database history,
+    /// or if the history is corrupted.
+    fn snapshot_at(&self, version: u64) -> Result<Box<dyn Snapshot>> {
+        let snapshot = self.snapshot();
+        let latest_version = history::latest_version(snapshot.as_ref())
+            .ok_or_else(|| Error::new("Database history is empty"))?;
+        // The reverse changes for the earliest version lead to the state before it,
+        // which is not considered versioned.
+        let earliest_version = history::earliest_version(snapshot.as_ref()).unwrap_or(0);
+        if version > latest_version || version < earliest_version {
+            return Err(Error::new(format!(
+                "Version {} is not available in the database history (available versions: {}..={})",
+                version, earliest_version, latest_version
+            )));
+        }
+
+        let mut changes: HashMap<ResolvedAddress, ViewChanges> = HashMap::new();
+        // Entries are loaded in the ascending order, so the change from the entry closest
+        // to `version` wins.
+        for entry in history::load_changes(snapshot.as_ref(), version, latest_version)? {
+            for (address, data) in entry {
+                let view_changes = changes.entry(address).or_insert_with(ViewChanges::new);
+                for (key, change) in data {
+                    view_changes.data.entry(key).or_insert(change);
+                }
             }
+        }
 
-            // Remember all elements that will be deleted.
-            if changes.is_cleared() {
-                let mut iter = snapshot.iter(name, &[]);
+        Ok(Box::new(Patch {
+            snapshot,
+            changes,
+            changed_aggregated_addrs: HashMap::new(),
+            removed_aggregated_addrs: HashSet::new(),
+        }))
+    }
+
+    /// Starts a journal, which groups several patches merged into the database into a single
+    /// durable unit. See [`Journal`] for more details.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database contains an unfinished journal. Such a journal
+    /// should be rolled back with [`recover_journal`](#method.recover_journal) first.
+    ///
+    /// [`Journal`]: struct.Journal.html
+    fn begin_journal(&self) -> Result<Journal<'_, Self>> {
+        Journal::new(self)
+    }
+
+    /// Rolls back the unfinished journal left in the database, e.g., because of a crash
+    /// of the process that has started the journal. Returns the number of reverted patches.
+    /// If there is no unfinished journal, the method does nothing and returns zero.
+    ///
+    /// The journal is recovered automatically when the database is opened
+    /// with `RocksDB::open()`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the journal is corrupted, or in the same situations
+    /// as `Database::merge()`. The recovery may be safely retried after an error.
+    fn recover_journal(&self) -> Result<usize> {
+        journal::rollback(self)
+    }
+}
+
+/// Computes changes reverting the `patch` if it is merged on top of the `snapshot`.
+pub(crate) fn reverse_changes(
+    snapshot: &dyn Snapshot,
+    patch: &Patch,
+) -> HashMap<ResolvedAddress, ViewChanges> {
+    let mut rev_changes = HashMap::with_capacity(patch.changes.len());
+
+    for (name, changes) in &patch.changes {
+        let mut view_changes = changes.data.clone();
+        for (key, change) in &mut view_changes {
+            *change = if let Some(value) = snapshot.get(name, key) {
+                Change::Put(value)
+            } else {
+                Change::Delete
+            };
+        }
+
+        // Remember all elements that will be deleted.
+        if changes.is_cleared() {
+            let mut iter = snapshot.iter(name, &[]);
+            while let Some((key, value)) = iter.next() {
+                view_changes.insert(key.to_vec(), Change::Put(value.to_vec()));
+            }
+        } else {
+            for (start, end) in changes.removed_ranges() {
+                let mut iter = snapshot.iter(name, start);
                 while let Some((key, value)) = iter.next() {
+                    if end.map_or(false, |end| key >= end) {
+                        break;
+                    }
                     view_changes.insert(key.to_vec(), Change::Put(value.to_vec()));
                 }
             }
-
-            rev_changes.insert(
-                name.to_owned(),
-                ViewChanges {
-                    data: view_changes,
-                    is_cleared: false,
-                    namespace: changes.namespace.clone(),
-                },
-            );
         }
 
-        self.merge(patch)?;
-        Ok(Patch {
-            snapshot: self.snapshot(),
-            changes: rev_changes,
-            changed_aggregated_addrs,
-            removed_aggregated_addrs: HashSet::new(),
-        })
+        rev_changes.insert(
+            name.to_owned(),
+            ViewChanges {
+                data: view_changes,
+                is_cleared: false,
+                removed_ranges: BTreeMap::new(),
+                namespace: changes.namespace.clone(),
+            },
+        );
     }
+    rev_changes
 }
 
-impl<T: Database> DatabaseExt for T {}
+impl<T: Database + ?Sized> DatabaseExt for T {}
 
 /// A read-only snapshot of a storage backend.
 ///
@@ -649,10 +1060,56 @@ pub trait Iterator {
 }
 
 impl Patch {
+    /// Creates a patch with the specified changes on top of the `snapshot`.
+    pub(crate) fn from_changes(
+        snapshot: Box<dyn Snapshot>,
+        changes: HashMap<ResolvedAddress, ViewChanges>,
+    ) -> Self {
+        Self {
+            snapshot,
+            changes,
+            changed_aggregated_addrs: HashMap::new(),
+            removed_aggregated_addrs: HashSet::new(),
+        }
+    }
+
+    /// Adds a change to the patch, replacing the existing change for the same key, if any.
+    pub(crate) fn put_change(&mut self, address: ResolvedAddress, key: Vec<u8>, change: Change) {
+        self.changes
+            .entry(address)
+            .or_insert_with(ViewChanges::new)
+            .data
+            .insert(key, change);
+    }
+
+    /// Clears the view at the specified address within the patch.
+    pub(crate) fn clear_view(&mut self, address: ResolvedAddress) {
+        self.changes
+            .entry(address)
+            .or_insert_with(ViewChanges::new)
+            .clear();
+    }
+
     /// Iterates over changes in this patch.
     pub(crate) fn into_changes(self) -> HashMap<ResolvedAddress, ViewChanges> {
         self.changes
     }
+
+    /// Transforms changes in this patch with the provided closure.
+    pub(crate) fn map_changes<F>(self, mut map_fn: F) -> Self
+    where
+        F: FnMut(&ResolvedAddress, ViewChanges) -> ViewChanges,
+    {
+        let changes = self
+            .changes
+            .into_iter()
+            .map(|(address, changes)| {
+                let changes = map_fn(&address, changes);
+                (address, changes)
+            })
+            .collect();
+        Self { changes, ..self }
+    }
 }
 
 impl Snapshot for Patch {
@@ -685,7 +1142,8 @@ impl Snapshot for Patch {
             // Ignore all changes from the snapshot.
             Box::new(ChangesIter::new(changes_iter.unwrap()))
         } else {
-            Box::new(ForkIter::new(self.snapshot.iter(name, from), changes_iter))
+            let snapshot_iter = snapshot_iter(self.snapshot.as_ref(), name, from, maybe_changes);
+            Box::new(ForkIter::new(snapshot_iter, changes_iter))
         }
     }
 }
@@ -1114,6 +1572,48 @@ where
     }
 }
 
+impl RemovedRangesIter<'_> {
+    /// Advances the inner iterator past the removed ranges.
+    fn skip_removed(&mut self) {
+        while !self.ended {
+            let range_end = match self.inner.peek() {
+                Some((key, _)) => match self.changes.removed_range(key) {
+                    Some((_, range_end)) => range_end,
+                    None => return,
+                },
+                None => return,
+            };
+
+            if let Some(range_end) = range_end {
+                // Seek to the end of the range instead of skipping keys one by one.
+                self.inner = self.snapshot.iter(&self.name, range_end);
+            } else {
+                self.ended = true;
+            }
+        }
+    }
+}
+
+impl Iterator for RemovedRangesIter<'_> {
+    fn next(&mut self) -> Option<(&[u8], &[u8])> {
+        self.skip_removed();
+        if self.ended {
+            None
+        } else {
+            self.inner.next()
+        }
+    }
+
+    fn peek(&mut self) -> Option<(&[u8], &[u8])> {
+        self.skip_removed();
+        if self.ended {
+            None
+        } else {
+            self.inner.peek()
+        }
+    }
+}
+
 impl fmt::Debug for dyn Database {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Database").finish()
@@ -1347,6 +1847,156 @@ mod tests {
         assert!(backup.get_list::<_, u32>(("foo", &1_u8)).is_empty());
     }
 
+    #[test]
+    fn snapshots_at_past_versions() {
+        let db = TemporaryDB::new();
+        assert_eq!(db.latest_version(), None);
+        assert!(db.snapshot_at(0).is_err());
+
+        let mut state_hashes = vec![];
+        for i in 0_u32..5 {
+            let fork = db.fork();
+            fork.get_proof_list("list").push(i);
+            let mut map = fork.get_proof_map("map");
+            if i == 3 {
+                map.clear();
+                fork.get_entry(("entry", &i)).set(i);
+            }
+            map.put(&i, i.to_string());
+            drop(map);
+            let version = db.merge_with_history(fork.into_patch()).unwrap();
+            assert_eq!(version, u64::from(i));
+            state_hashes.push(SystemSchema::new(&db.snapshot()).state_hash());
+        }
+        assert_eq!(db.latest_version(), Some(4));
+
+        for i in 0_u32..5 {
+            let snapshot = db.snapshot_at(u64::from(i)).unwrap();
+            let list = snapshot.get_proof_list::<_, u32>("list");
+            assert_eq!(list.iter().collect::<Vec<_>>(), (0..=i).collect::<Vec<_>>());
+            let map = snapshot.get_proof_map::<_, u32, String>("map");
+            let expected_keys = if i < 3 { 0..=i } else { 3..=i };
+            assert_eq!(
+                map.keys().collect::<Vec<_>>(),
+                expected_keys.collect::<Vec<_>>()
+            );
+            assert_eq!(snapshot.index_type(("entry", &3_u32)).is_some(), i >= 3);
+            assert_eq!(
+                SystemSchema::new(&snapshot).state_hash(),
+                state_hashes[i as usize]
+            );
+        }
+        assert!(db.snapshot_at(5).is_err());
+    }
+
+    #[test]
+    fn rollback_of_removed_ranges() {
+        let db = TemporaryDB::new();
+        let fork = db.fork();
+        let mut map = fork.get_map("map");
+        for i in 0_u32..10 {
+            map.put(&i, i.to_string());
+        }
+        drop(map);
+        db.merge(fork.into_patch()).unwrap();
+
+        let fork = db.fork();
+        {
+            let mut map = fork.get_map::<_, u32, String>("map");
+            map.remove_range(3..7);
+            map.put(&5, "five".to_owned());
+        }
+        let backup = db.merge_with_backup(fork.into_patch()).unwrap();
+        let snapshot = db.snapshot();
+        let map = snapshot.get_map::<_, u32, String>("map");
+        assert_eq!(map.keys().collect::<Vec<_>>(), vec![0, 1, 2, 5, 7, 8, 9]);
+        assert_eq!(map.get(&5).unwrap(), "five");
+
+        db.merge(backup).unwrap();
+        let snapshot = db.snapshot();
+        let map = snapshot.get_map::<_, u32, String>("map");
+        assert_eq!(map.keys().collect::<Vec<_>>(), (0..10).collect::<Vec<_>>());
+        assert_eq!(map.get(&5).unwrap(), "5");
+    }
+
+    #[test]
+    fn history_with_removed_ranges() {
+        let db = TemporaryDB::new();
+        let fork = db.fork();
+        fork.get_list("list").extend(0_u32..10);
+        let version = db.merge_with_history(fork.into_patch()).unwrap();
+
+        let fork = db.fork();
+        fork.get_list::<_, u32>("list").truncate(3);
+        db.merge_with_history(fork.into_patch()).unwrap();
+        let snapshot = db.snapshot();
+        assert_eq!(
+            snapshot
+                .get_list::<_, u32>("list")
+                .iter()
+                .collect::<Vec<_>>(),
+            vec![0, 1, 2]
+        );
+
+        let snapshot = db.snapshot_at(version).unwrap();
+        let list = snapshot.get_list::<_, u32>("list");
+        assert_eq!(list.iter().collect::<Vec<_>>(), (0..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn history_with_unversioned_merges() {
+        let db = TemporaryDB::new();
+        let fork = db.fork();
+        fork.get_entry("entry").set(1_u32);
+        let version = db.merge_with_history(fork.into_patch()).unwrap();
+
+        // Changes merged without history are visible in historical snapshots.
+        let fork = db.fork();
+        fork.get_entry("other").set(2_u32);
+        db.merge(fork.into_patch()).unwrap();
+        let fork = db.fork();
+        fork.get_entry("entry").set(3_u32);
+        db.merge_with_history(fork.into_patch()).unwrap();
+
+        let snapshot = db.snapshot_at(version).unwrap();
+        assert_eq!(snapshot.get_entry::<_, u32>("entry").get(), Some(1));
+        assert_eq!(snapshot.get_entry::<_, u32>("other").get(), Some(2));
+    }
+
+    #[test]
+    fn pruning_history() {
+        let db = TemporaryDB::new();
+        // Pruning an empty history is a no-op.
+        db.prune_history(10).unwrap();
+        assert_eq!(db.latest_version(), None);
+
+        for i in 0_u32..5 {
+            let fork = db.fork();
+            fork.get_proof_list("list").push(i);
+            db.merge_with_history(fork.into_patch()).unwrap();
+        }
+
+        db.prune_history(2).unwrap();
+        assert!(db.snapshot_at(1).is_err());
+        for i in 2_u32..5 {
+            let snapshot = db.snapshot_at(u64::from(i)).unwrap();
+            let list = snapshot.get_proof_list::<_, u32>("list");
+            assert_eq!(list.iter().collect::<Vec<_>>(), (0..=i).collect::<Vec<_>>());
+        }
+
+        // The latest version is always retained.
+        db.prune_history(100).unwrap();
+        assert!(db.snapshot_at(3).is_err());
+        let snapshot = db.snapshot_at(4).unwrap();
+        assert_eq!(snapshot.get_proof_list::<_, u32>("list").len(), 5);
+
+        let fork = db.fork();
+        fork.get_proof_list("list").push(5_u32);
+        assert_eq!(db.merge_with_history(fork.into_patch()).unwrap(), 5);
+        let snapshot = db.snapshot_at(4).unwrap();
+        assert_eq!(snapshot.get_proof_list::<_, u32>("list").len(), 5);
+    }
+
     #[test]
     fn updated_addrs_are_efficiently_updated() {
         let db = TemporaryDB::new();
@@ -0,0 +1,896 @@
+// Copyright 2020 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Streaming export and import of the database state.
+//!
+//! A dump contains all indexes of a [`Snapshot`] together with their metadata, so that
+//! the snapshot can be recreated in another database without replaying the changes
+//! that led to it. Auxiliary database data, such as the [history of versions], is not dumped.
+//!
+//! # Dump Format
+//!
+//! A dump starts with a header consisting of a magic byte string, the format version
+//! and the state hash of the dumped snapshot. The header is followed by a sequence of chunks
+//! and a trailer.
+//!
+//! Each chunk contains raw key-value pairs for one or more views, and is limited in size,
+//! so that neither export nor import needs to keep the entire state in memory. Chunks form
+//! a hash chain: a chunk is followed by the hash of the concatenation of the previous hash
+//! and the chunk contents; the state hash from the header is used as the starting point.
+//! The trailer records the total number of chunks and entries, and the final hash of the chain.
+//! Thus, any corruption, reordering or truncation of the dump is detected during import.
+//!
+//! After all chunks are imported, the hashes of Merkelized indexes are recomputed from
+//! the imported data, and the hashes of aggregated indexes are compared with
+//! the [state aggregator], the root hash of which must match the state hash from the header.
+//!
+//! The state hash only authenticates indexes participating in the state aggregation.
+//! Other indexes (e.g., non-Merkelized ones) are imported as is; their names are reported
+//! in [`DumpInfo`], so that the caller can decide whether the source of the dump is trusted
+//! enough to use them.
+//!
+//! # State Chunks
+//!
+//! Besides whole dumps, the state can be transferred in separate chunks with [`export_chunk`]
+//! and [`import_chunk`], e.g., over the network. Each exported chunk comes with a cursor
+//! of the next chunk, so the chunks may be requested one by one, possibly from different
+//! sources. Separate chunks are not authenticated; after all chunks are imported,
+//! the state should be checked with [`verify_aggregation`].
+//!
+//! [`DumpInfo`]: struct.DumpInfo.html
+//! [`export_chunk`]: fn.export_chunk.html
+//! [`import_chunk`]: fn.import_chunk.html
+//! [`verify_aggregation`]: fn.verify_aggregation.html
+//! [`Snapshot`]: ../trait.Snapshot.html
+//! [history of versions]: ../trait.DatabaseExt.html#method.merge_with_history
+//! [state aggregator]: ../struct.SystemSchema.html#method.state_aggregator
+//!
+//! # Examples
+//!
+//! ```
+//! # use exonum_merkledb::{access::CopyAccessExt, dump, Database, SystemSchema, TemporaryDB};
+//! let db = TemporaryDB::new();
+//! let fork = db.fork();
+//! fork.get_proof_list("list").extend(vec![1_u32, 2, 3]);
+//! fork.get_map("map").put(&1_u8, "foo".to_owned());
+//! db.merge(fork.into_patch()).unwrap();
+//!
+//! let mut buffer = vec![];
+//! let exported = dump::export(db.snapshot().as_ref(), &mut buffer).unwrap();
+//!
+//! let other_db = TemporaryDB::new();
+//! let imported = dump::import(&other_db, buffer.as_slice()).unwrap();
+//! assert_eq!(exported, imported);
+//! let snapshot = other_db.snapshot();
+//! assert_eq!(SystemSchema::new(&snapshot).state_hash(), imported.state_hash);
+//! assert_eq!(snapshot.get_map::<_, u8, String>("map").get(&1).unwrap(), "foo");
+//! ```
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use exonum_crypto::{Hash, HashStream, HASH_SIZE};
+
+use std::{
+    collections::BTreeSet,
+    convert::TryFrom,
+    io::{Read, Write},
+    num::NonZeroU64,
+};
+
+use crate::{
+    history::{read_bytes, read_len, write_bytes},
+    integrity::check_integrity,
+    views::{check_proof_entry, IndexAddress, IndexType, IndexesPool, View, STATE_AGGREGATOR},
+    Database, DatabaseExt, Error, Fork, ResolvedAddress, Result, Snapshot, SystemSchema,
+};
+
+/// Magic bytes at the start of each dump.
+const MAGIC: &[u8; 8] = b"EXNMDUMP";
+/// Version of the dump format.
+const FORMAT_VERSION: u8 = 1;
+/// Size of chunk contents after which the chunk is flushed during export.
+const CHUNK_SIZE: usize = 1 << 20;
+/// Maximum size of chunk contents accepted during import.
+const MAX_CHUNK_SIZE: usize = 1 << 30;
+
+const TAG_TRAILER: u8 = 0;
+const TAG_CHUNK: u8 = 1;
+
+/// Summary information about an exported or imported dump.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct DumpInfo {
+    /// State hash of the dumped snapshot.
+    pub state_hash: Hash,
+    /// Number of chunks in the dump.
+    pub chunks: u64,
+    /// Total number of key-value pairs in the dump.
+    pub entries: u64,
+    /// Names of indexes in the dump that are not authenticated by the state hash.
+    /// See [`unauthenticated_indexes`] for details.
+    ///
+    /// [`unauthenticated_indexes`]: fn.unauthenticated_indexes.html
+    pub unauthenticated_indexes: Vec<String>,
+}
+
+/// Key-value pairs of a single view.
+type ViewEntries = Vec<(Vec<u8>, Vec<u8>)>;
+
+/// Writer of dump chunks used during export.
+#[derive(Debug)]
+struct ChunkWriter<W> {
+    writer: W,
+    last_hash: Hash,
+    /// Entries of the current chunk grouped by view.
+    views: Vec<(ResolvedAddress, ViewEntries)>,
+    /// Approximate size of the current chunk.
+    size: usize,
+    chunks: u64,
+    entries: u64,
+}
+
+impl<W: Write> ChunkWriter<W> {
+    fn new(writer: W, state_hash: Hash) -> Self {
+        Self {
+            writer,
+            last_hash: state_hash,
+            views: Vec::new(),
+            size: 0,
+            chunks: 0,
+            entries: 0,
+        }
+    }
+
+    fn push(&mut self, address: &ResolvedAddress, key: &[u8], value: &[u8]) -> Result<()> {
+        match self.views.last_mut() {
+            Some((last_address, entries)) if last_address == address => {
+                entries.push((key.to_vec(), value.to_vec()));
+            }
+            _ => {
+                let entries = vec![(key.to_vec(), value.to_vec())];
+                self.views.push((address.to_owned(), entries));
+                self.size += address.name.len();
+            }
+        }
+        self.size += key.len() + value.len();
+        self.entries += 1;
+
+        if self.size >= CHUNK_SIZE {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        if self.views.is_empty() {
+            return Ok(());
+        }
+
+        let payload = encode_chunk(self.views.drain(..), self.size);
+        self.size = 0;
+
+        self.last_hash = chain_hash(self.last_hash, &payload);
+        self.writer.write_u8(TAG_CHUNK)?;
+        self.writer
+            .write_u32::<LittleEndian>(payload.len() as u32)?;
+        self.writer.write_all(&payload)?;
+        self.writer.write_all(self.last_hash.as_ref())?;
+        self.chunks += 1;
+        Ok(())
+    }
+
+    fn finish(
+        mut self,
+        state_hash: Hash,
+        unauthenticated_indexes: Vec<String>,
+    ) -> Result<DumpInfo> {
+        self.flush()?;
+        self.writer.write_u8(TAG_TRAILER)?;
+        self.writer.write_u64::<LittleEndian>(self.chunks)?;
+        self.writer.write_u64::<LittleEndian>(self.entries)?;
+        self.writer.write_all(self.last_hash.as_ref())?;
+        self.writer.flush()?;
+
+        Ok(DumpInfo {
+            state_hash,
+            chunks: self.chunks,
+            entries: self.entries,
+            unauthenticated_indexes,
+        })
+    }
+}
+
+/// Encodes key-value pairs grouped by view into chunk contents.
+fn encode_chunk(
+    views: impl ExactSizeIterator<Item = (ResolvedAddress, ViewEntries)>,
+    size: usize,
+) -> Vec<u8> {
+    let mut payload = Vec::with_capacity(size * 11 / 10);
+    leb128::write::unsigned(&mut payload, views.len() as u64).unwrap();
+    for (address, entries) in views {
+        write_bytes(&mut payload, address.name.as_bytes());
+        let id = address.id.map_or(0, NonZeroU64::get);
+        leb128::write::unsigned(&mut payload, id).unwrap();
+        leb128::write::unsigned(&mut payload, entries.len() as u64).unwrap();
+        for (key, value) in entries {
+            write_bytes(&mut payload, &key);
+            write_bytes(&mut payload, &value);
+        }
+    }
+    payload
+}
+
+fn chain_hash(last_hash: Hash, payload: &[u8]) -> Hash {
+    HashStream::new()
+        .update(last_hash.as_ref())
+        .update(payload)
+        .hash()
+}
+
+/// Exports all indexes from the `snapshot` into the `writer`.
+///
+/// The snapshot is read in a streaming fashion, and the data is written to the `writer`
+/// in chunks, so the memory consumption does not depend on the size of the snapshot.
+pub fn export<W: Write>(snapshot: &dyn Snapshot, writer: W) -> Result<DumpInfo> {
+    let state_hash = SystemSchema::new(snapshot).state_hash();
+
+    let mut writer = writer;
+    writer.write_all(MAGIC)?;
+    writer.write_u8(FORMAT_VERSION)?;
+    writer.write_all(state_hash.as_ref())?;
+
+    let mut chunk_writer = ChunkWriter::new(writer, state_hash);
+    for address in IndexesPool::new(snapshot).views() {
+        let mut iter = snapshot.iter(&address, &[]);
+        while let Some((key, value)) = iter.next() {
+            chunk_writer.push(&address, key, value)?;
+        }
+    }
+    chunk_writer.finish(state_hash, unauthenticated_indexes(snapshot))
+}
+
+/// Position in the database state, from which a state chunk is exported.
+///
+/// Cursors are only meaningful for a fixed snapshot; the cursor pointing to the start
+/// of the state is created with `ChunkCursor::default()`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ChunkCursor {
+    /// Ordinal number of the view in the snapshot.
+    pub view: u64,
+    /// Key in the view.
+    pub key: Vec<u8>,
+}
+
+/// Exports a single chunk of the `snapshot` state starting from the specified `cursor`.
+///
+/// The chunk contents are encoded in the same way as in the dump created by [`export`].
+/// The export stops as soon as the chunk size reaches `max_size` bytes (the size of the encoded
+/// chunk is slightly larger); each chunk contains at least one key-value pair, unless
+/// the state is exhausted. Returns the chunk contents and the cursor of the next chunk,
+/// or `None` if the chunk is the last one.
+///
+/// Chunks can be imported with [`import_chunk`]. Unlike a dump, separate chunks are not
+/// authenticated; the imported state should be checked with [`verify_aggregation`]
+/// after all chunks are imported.
+///
+/// [`export`]: fn.export.html
+/// [`import_chunk`]: fn.import_chunk.html
+/// [`verify_aggregation`]: fn.verify_aggregation.html
+pub fn export_chunk(
+    snapshot: &dyn Snapshot,
+    cursor: &ChunkCursor,
+    max_size: usize,
+) -> Result<(Vec<u8>, Option<ChunkCursor>)> {
+    let pool = IndexesPool::new(snapshot);
+    if cursor.view > pool.len() {
+        return Err(Error::new(format!(
+            "Invalid chunk cursor: view #{} does not exist",
+            cursor.view
+        )));
+    }
+
+    let mut views = vec![];
+    let mut size = 0;
+    let mut is_empty = true;
+    let views_iter = pool.views().skip(cursor.view as usize);
+    for (view_number, address) in (cursor.view..).zip(views_iter) {
+        let from = if view_number == cursor.view {
+            cursor.key.as_slice()
+        } else {
+            &[]
+        };
+        let mut entries = vec![];
+        let mut iter = snapshot.iter(&address, from);
+        while let Some((key, value)) = iter.next() {
+            if size >= max_size && !is_empty {
+                let next_cursor = ChunkCursor {
+                    view: view_number,
+                    key: key.to_vec(),
+                };
+                if !entries.is_empty() {
+                    views.push((address, entries));
+                }
+                return Ok((encode_chunk(views.into_iter(), size), Some(next_cursor)));
+            }
+            entries.push((key.to_vec(), value.to_vec()));
+            size += key.len() + value.len();
+            is_empty = false;
+        }
+
+        if !entries.is_empty() {
+            size += address.name.len();
+            views.push((address, entries));
+        }
+    }
+    Ok((encode_chunk(views.into_iter(), size), None))
+}
+
+fn read_hash<R: Read>(reader: &mut R) -> Result<Hash> {
+    let mut bytes = [0; HASH_SIZE];
+    reader.read_exact(&mut bytes)?;
+    Ok(Hash::new(bytes))
+}
+
+/// Decodes chunk contents and merges them into the `database`. Returns the number
+/// of imported entries.
+///
+/// The chunk contents are not authenticated; the imported state should be checked with
+/// [`verify_aggregation`] after all chunks are imported.
+///
+/// [`verify_aggregation`]: fn.verify_aggregation.html
+pub fn import_chunk(database: &dyn Database, mut payload: &[u8]) -> Result<u64> {
+    let reader = &mut payload;
+    let fork = database.fork();
+    let mut entries = 0;
+
+    let views_count = read_len(reader)?;
+    for _ in 0..views_count {
+        let name = String::from_utf8(read_bytes(reader)?).map_err(|e| Error::new(e.to_string()))?;
+        let id = leb128::read::unsigned(reader).map_err(|e| Error::new(e.to_string()))?;
+        let mut view = View::new(&fork, ResolvedAddress::new(name, NonZeroU64::new(id)));
+
+        let entries_count = read_len(reader)?;
+        for _ in 0..entries_count {
+            let key = read_bytes(reader)?;
+            let value = read_bytes(reader)?;
+            view.put(key.as_slice(), value);
+        }
+        entries += entries_count as u64;
+    }
+
+    if !reader.is_empty() {
+        return Err(Error::new("Trailing bytes in dump chunk"));
+    }
+    database.merge(fork.into_patch())?;
+    Ok(entries)
+}
+
+/// Checks that the database contains no indexes except for the state aggregator, which is
+/// created on database initialization.
+fn is_empty(snapshot: &dyn Snapshot) -> bool {
+    IndexesPool::new(snapshot).len() <= 1
+        && SystemSchema::new(snapshot)
+            .state_aggregator()
+            .keys()
+            .next()
+            .is_none()
+}
+
+/// Removes all indexes in the `fork`.
+fn clear_fork(fork: &Fork) {
+    let addresses: Vec<_> = IndexesPool::new(fork).views().collect();
+    for address in addresses {
+        View::new(fork, address).clear();
+    }
+}
+
+/// Removes all indexes from the `database`, so that it can be used to import a dump
+/// or state chunks. Auxiliary database data, such as the history of versions, is retained.
+pub fn clear(database: &dyn Database) -> Result<()> {
+    let fork = database.fork();
+    clear_fork(&fork);
+    database.merge(fork.into_patch())
+}
+
+/// Replaces all indexes in the `database` with the indexes from the `source` snapshot.
+///
+/// The source is usually the state imported and verified in a staging database.
+/// Auxiliary database data, such as the history of versions, is retained.
+///
+/// The data is copied within a [journal]; the staged changes are flushed in size-limited
+/// patches, so the memory consumption does not depend on the size of the copied state
+/// (the reverse changes for the removed indexes are kept in memory, though). The replacement
+/// is atomic: if the process crashes while copying, the original state is restored
+/// by [`DatabaseExt::recover_journal()`].
+///
+/// [journal]: ../struct.Journal.html
+/// [`DatabaseExt::recover_journal()`]: ../trait.DatabaseExt.html#method.recover_journal
+pub fn replace_state(database: &dyn Database, source: &dyn Snapshot) -> Result<()> {
+    let mut journal = database.begin_journal()?;
+    clear_fork(journal.fork());
+
+    let mut size = 0;
+    for address in IndexesPool::new(source).views() {
+        let mut view = View::new(journal.fork(), address.clone());
+        let mut iter = source.iter(&address, &[]);
+        while let Some((key, value)) = iter.next() {
+            view.put(key, value.to_vec());
+            size += key.len() + value.len();
+            if size >= CHUNK_SIZE {
+                drop(view);
+                journal.flush()?;
+                view = View::new(journal.fork(), address.clone());
+                size = 0;
+            }
+        }
+    }
+    journal.commit()
+}
+
+/// Verifies the state of the `snapshot` and returns its state hash.
+///
+/// The hashes of all Merkelized indexes are recomputed from the stored data, and the hashes
+/// of aggregated indexes are checked against the state aggregator. Hashes of `ProofEntry`
+/// values are assumed to be computed with the default `ObjectHash` implementation.
+///
+/// Only the indexes participating in the state aggregation are authenticated by
+/// the returned hash; other indexes can be listed with [`unauthenticated_indexes`].
+///
+/// [`unauthenticated_indexes`]: fn.unauthenticated_indexes.html
+pub fn verify_aggregation(snapshot: &dyn Snapshot) -> Result<Hash> {
+    let report = check_integrity(snapshot);
+    if let Some(index) = report.corrupted_indexes.first() {
+        return Err(Error::new(format!(
+            "Index `{}` is corrupted: {}",
+            index.name, index.reason
+        )));
+    }
+
+    for (full_name, metadata) in IndexesPool::new(snapshot).indexes() {
+        if metadata.index_type() == IndexType::ProofEntry {
+            check_proof_entry(snapshot, &full_name, metadata).map_err(|reason| {
+                let address = IndexAddress::from_fully_qualified_name(&full_name);
+                Error::new(format!(
+                    "Index `{}` is corrupted: {}",
+                    address.name(),
+                    reason
+                ))
+            })?;
+        }
+    }
+    Ok(report.state_hash)
+}
+
+/// Returns the names of indexes in the `snapshot` that are not authenticated by the state hash.
+///
+/// These are non-Merkelized indexes and Merkelized indexes that do not participate
+/// in the state aggregation (such as indexes in groups or in migration). For indexes in groups,
+/// the name of the group is returned. The names are sorted and deduplicated.
+///
+/// The contents of such indexes cannot be checked when importing a dump or state chunks,
+/// and should be trusted only to the extent the source of the state is trusted.
+pub fn unauthenticated_indexes(snapshot: &dyn Snapshot) -> Vec<String> {
+    let aggregator = SystemSchema::new(snapshot).state_aggregator();
+    let names: BTreeSet<_> = IndexesPool::new(snapshot)
+        .indexes()
+        .filter_map(|(full_name, _)| {
+            let address = IndexAddress::from_fully_qualified_name(&full_name);
+            let is_aggregated = if address.is_in_migration() {
+                false
+            } else if address.name() == STATE_AGGREGATOR {
+                // Only the aggregator for the default namespace is covered by the state hash.
+                address.id_in_group() == Some(&[])
+            } else {
+                address.id_in_group().is_none() && aggregator.contains(address.name())
+            };
+            if is_aggregated {
+                None
+            } else {
+                Some(address.name().to_owned())
+            }
+        })
+        .collect();
+    names.into_iter().collect()
+}
+
+/// Imports a dump created with [`export`] from the `reader` into the `database`.
+///
+/// The database must not contain any indexes. Chunks are merged into the database as soon as
+/// they are read. If the import fails (e.g., because the dump is corrupted), the database
+/// may contain a part of the imported data, and should be discarded.
+///
+/// [`export`]: fn.export.html
+pub fn import<R: Read>(database: &dyn Database, reader: R) -> Result<DumpInfo> {
+    if !is_empty(database.snapshot().as_ref()) {
+        return Err(Error::new(
+            "Dump can only be imported into an empty database",
+        ));
+    }
+
+    let mut reader = reader;
+    let mut magic = [0; 8];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(Error::new("Input is not a database dump"));
+    }
+    let format_version = reader.read_u8()?;
+    if format_version != FORMAT_VERSION {
+        return Err(Error::new(format!(
+            "Unsupported dump format version: {}",
+            format_version
+        )));
+    }
+    let state_hash = read_hash(&mut reader)?;
+
+    let mut last_hash = state_hash;
+    let mut chunks = 0_u64;
+    let mut entries = 0_u64;
+    loop {
+        match reader.read_u8()? {
+            TAG_CHUNK => {
+                let len = reader.read_u32::<LittleEndian>()?;
+                let len = usize::try_from(len).map_err(|e| Error::new(e.to_string()))?;
+                if len > MAX_CHUNK_SIZE {
+                    return Err(Error::new(format!(
+                        "Dump chunk is too large: {} bytes",
+                        len
+                    )));
+                }
+                let mut payload = vec![0; len];
+                reader.read_exact(&mut payload)?;
+
+                last_hash = chain_hash(last_hash, &payload);
+                if read_hash(&mut reader)? != last_hash {
+                    return Err(Error::new(format!(
+                        "Hash mismatch in dump chunk #{}",
+                        chunks
+                    )));
+                }
+                entries += import_chunk(database, &payload)?;
+                chunks += 1;
+            }
+            TAG_TRAILER => break,
+            other => return Err(Error::new(format!("Unknown tag in dump: {}", other))),
+        }
+    }
+
+    let expected_chunks = reader.read_u64::<LittleEndian>()?;
+    let expected_entries = reader.read_u64::<LittleEndian>()?;
+    if expected_chunks != chunks
+        || expected_entries != entries
+        || read_hash(&mut reader)? != last_hash
+    {
+        return Err(Error::new("Dump trailer does not match imported data"));
+    }
+
+    let snapshot = database.snapshot();
+    let actual_state_hash = verify_aggregation(snapshot.as_ref())?;
+    if actual_state_hash != state_hash {
+        return Err(Error::new(format!(
+            "State hash of imported data ({:?}) does not match the dump header ({:?})",
+            actual_state_hash, state_hash
+        )));
+    }
+
+    Ok(DumpInfo {
+        state_hash,
+        chunks,
+        entries,
+        unauthenticated_indexes: unauthenticated_indexes(snapshot.as_ref()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        access::{AccessExt, CopyAccessExt},
+        migration::Migration,
+        ObjectHash, TemporaryDB,
+    };
+
+    fn create_database() -> TemporaryDB {
+        let db = TemporaryDB::new();
+        let fork = db.fork();
+        fork.get_proof_list("list").extend(0_u64..1_000);
+        fork.get_proof_map::<_, u64, String>("map")
+            .put(&1, "foo".to_owned());
+        fork.get_entry(("group", &1_u8)).set(42_u32);
+        fork.get_proof_entry(("group", &2_u8)).set(23_u32);
+        fork.get_value_set("set").insert("bar".to_owned());
+        Migration::new("migrated", &fork)
+            .get_proof_map::<_, u64, u64>("map")
+            .put(&1, 2);
+        db.merge_with_history(fork.into_patch()).unwrap();
+
+        let fork = db.fork();
+        fork.get_proof_list::<_, u64>("list").push(1_000);
+        db.merge_with_history(fork.into_patch()).unwrap();
+        db
+    }
+
+    fn export_to_vec(snapshot: &dyn Snapshot) -> (Vec<u8>, DumpInfo) {
+        let mut buffer = vec![];
+        let info = export(snapshot, &mut buffer).unwrap();
+        (buffer, info)
+    }
+
+    #[test]
+    fn dump_roundtrip() {
+        let db = create_database();
+        let snapshot = db.snapshot();
+        let (buffer, info) = export_to_vec(snapshot.as_ref());
+        assert_eq!(info.state_hash, SystemSchema::new(&snapshot).state_hash());
+        assert_eq!(info.chunks, 1);
+
+        let other_db = TemporaryDB::new();
+        assert_eq!(import(&other_db, buffer.as_slice()).unwrap(), info);
+        let other_snapshot = other_db.snapshot();
+        assert_eq!(
+            other_snapshot
+                .get_proof_list::<_, u64>("list")
+                .object_hash(),
+            snapshot.get_proof_list::<_, u64>("list").object_hash()
+        );
+        assert_eq!(
+            other_snapshot.get_entry::<_, u32>(("group", &1_u8)).get(),
+            Some(42)
+        );
+        let migration = Migration::new("migrated", &other_snapshot);
+        assert_eq!(
+            migration.get_proof_map::<_, u64, u64>("map").get(&1),
+            Some(2)
+        );
+
+        // History is not a part of the dump.
+        assert_eq!(other_db.latest_version(), None);
+
+        // New indexes can be created in the imported database without clashing
+        // with the existing ones.
+        let fork = other_db.fork();
+        fork.get_list("new_list").push(1_u8);
+        assert_eq!(fork.get_proof_list::<_, u64>("list").len(), 1_001);
+        other_db.merge(fork.into_patch()).unwrap();
+        let snapshot = other_db.snapshot();
+        assert_eq!(snapshot.get_list::<_, u8>("new_list").len(), 1);
+        assert_eq!(snapshot.get_proof_list::<_, u64>("list").len(), 1_001);
+    }
+
+    #[test]
+    fn dump_of_past_version() {
+        let db = create_database();
+        let snapshot = db.snapshot_at(0).unwrap();
+        assert_eq!(snapshot.get_proof_list::<_, u64>("list").len(), 1_000);
+        let (buffer, info) = export_to_vec(snapshot.as_ref());
+
+        let other_db = TemporaryDB::new();
+        let imported = import(&other_db, buffer.as_slice()).unwrap();
+        assert_eq!(imported.state_hash, info.state_hash);
+        let other_snapshot = other_db.snapshot();
+        assert_eq!(other_snapshot.get_proof_list::<_, u64>("list").len(), 1_000);
+    }
+
+    #[test]
+    fn large_dump_is_split_into_chunks() {
+        let db = TemporaryDB::new();
+        let fork = db.fork();
+        let mut map = fork.get_proof_map::<_, u64, Vec<u8>>("map");
+        for i in 0_u64..3_000 {
+            map.put(&i, vec![1; 1_024]);
+        }
+        drop(map);
+        db.merge(fork.into_patch()).unwrap();
+
+        let (buffer, info) = export_to_vec(db.snapshot().as_ref());
+        assert!(info.chunks > 2, "{:?}", info);
+        let other_db = TemporaryDB::new();
+        assert_eq!(import(&other_db, buffer.as_slice()).unwrap(), info);
+    }
+
+    #[test]
+    fn state_transfer_in_chunks() {
+        let db = create_database();
+        let snapshot = db.snapshot();
+        let other_db = TemporaryDB::new();
+        let fork = other_db.fork();
+        fork.get_list("garbage").extend(vec![1_u8, 2, 3]);
+        other_db.merge(fork.into_patch()).unwrap();
+        clear(&other_db).unwrap();
+        assert!(is_empty(other_db.snapshot().as_ref()));
+
+        let mut cursor = Some(ChunkCursor::default());
+        let mut chunks = 0;
+        while let Some(current) = cursor {
+            let (chunk, next) = export_chunk(snapshot.as_ref(), &current, 1_000).unwrap();
+            import_chunk(&other_db, &chunk).unwrap();
+            chunks += 1;
+            cursor = next;
+        }
+        assert!(chunks > 10, "{}", chunks);
+
+        let other_snapshot = other_db.snapshot();
+        assert_eq!(
+            verify_aggregation(other_snapshot.as_ref()).unwrap(),
+            SystemSchema::new(&snapshot).state_hash()
+        );
+        assert_eq!(other_snapshot.get_proof_list::<_, u64>("list").len(), 1_001);
+        assert!(other_snapshot.get_list::<_, u8>("garbage").is_empty());
+
+        let cursor = ChunkCursor {
+            view: 1_000,
+            key: vec![],
+        };
+        assert!(export_chunk(snapshot.as_ref(), &cursor, 1_000).is_err());
+    }
+
+    #[test]
+    fn replacing_state() {
+        let db = create_database();
+        let staging_db = TemporaryDB::new();
+        let fork = staging_db.fork();
+        let mut map = fork.get_proof_map::<_, u64, Vec<u8>>("map");
+        for i in 0_u64..3_000 {
+            map.put(&i, vec![1; 1_024]);
+        }
+        drop(map);
+        staging_db.merge(fork.into_patch()).unwrap();
+        let staging_snapshot = staging_db.snapshot();
+
+        replace_state(&db, staging_snapshot.as_ref()).unwrap();
+        let snapshot = db.snapshot();
+        assert_eq!(
+            verify_aggregation(snapshot.as_ref()).unwrap(),
+            SystemSchema::new(&staging_snapshot).state_hash()
+        );
+        let map = snapshot.get_proof_map::<_, u64, Vec<u8>>("map");
+        assert_eq!(map.iter().count(), 3_000);
+        assert!(snapshot.get_proof_list::<_, u64>("list").is_empty());
+        // History is retained.
+        assert_eq!(db.latest_version(), Some(1));
+
+        // New indexes can be created without clashing with the copied ones.
+        let fork = db.fork();
+        fork.get_list("new_list").push(1_u8);
+        db.merge(fork.into_patch()).unwrap();
+        let snapshot = db.snapshot();
+        assert_eq!(snapshot.get_list::<_, u8>("new_list").len(), 1);
+        assert_eq!(
+            snapshot
+                .get_proof_map::<_, u64, Vec<u8>>("map")
+                .iter()
+                .count(),
+            3_000
+        );
+    }
+
+    #[test]
+    fn corrupted_dumps_are_rejected() {
+        let db = create_database();
+        let (buffer, _) = export_to_vec(db.snapshot().as_ref());
+
+        // Truncated dump.
+        let err = import(&TemporaryDB::new(), &buffer[..buffer.len() - 1]).unwrap_err();
+        assert!(err.to_string().contains("fill whole buffer"), "{}", err);
+
+        // Modified chunk contents.
+        let mut modified = buffer.clone();
+        let chunk_start = MAGIC.len() + 1 + HASH_SIZE + 5;
+        modified[chunk_start + 20] ^= 1;
+        let err = import(&TemporaryDB::new(), modified.as_slice()).unwrap_err();
+        assert!(err.to_string().contains("Hash mismatch"), "{}", err);
+
+        // Modified state hash.
+        let mut modified = buffer.clone();
+        modified[MAGIC.len() + 1] ^= 1;
+        let err = import(&TemporaryDB::new(), modified.as_slice()).unwrap_err();
+        assert!(err.to_string().contains("Hash mismatch"), "{}", err);
+
+        // Non-empty database.
+        let err = import(&db, buffer.as_slice()).unwrap_err();
+        assert!(err.to_string().contains("empty database"), "{}", err);
+    }
+
+    #[test]
+    fn forged_aggregated_index_is_rejected() {
+        let db = TemporaryDB::new();
+        let fork = db.fork();
+        fork.get_proof_entry("entry").set(0x_dead_beef_u32);
+        db.merge(fork.into_patch()).unwrap();
+        let (mut buffer, _) = export_to_vec(db.snapshot().as_ref());
+
+        // Replace the hash of the entry stored in its metadata, and fix the hash chain
+        // so that the dump itself looks valid.
+        let state_hash_start = MAGIC.len() + 1;
+        let state_hash = Hash::from_slice(&buffer[state_hash_start..][..HASH_SIZE]).unwrap();
+        let payload_start = state_hash_start + HASH_SIZE + 5;
+        let payload_len = (&buffer[payload_start - 4..])
+            .read_u32::<LittleEndian>()
+            .unwrap();
+        let payload_end = payload_start + payload_len as usize;
+
+        let entry_hash = 0x_dead_beef_u32.object_hash();
+        let payload = &mut buffer[payload_start..payload_end];
+        let pos = payload
+            .windows(HASH_SIZE)
+            .position(|window| window == entry_hash.as_ref())
+            .unwrap();
+        payload[pos] ^= 1;
+        let chunk_hash = chain_hash(state_hash, payload);
+        buffer[payload_end..][..HASH_SIZE].copy_from_slice(chunk_hash.as_ref());
+        let trailer_hash_start = buffer.len() - HASH_SIZE;
+        buffer[trailer_hash_start..].copy_from_slice(chunk_hash.as_ref());
+
+        let err = import(&TemporaryDB::new(), buffer.as_slice()).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("does not match the state aggregator"),
+            "{}",
+            err
+        );
+    }
+
+    fn raw_view<'a>(fork: &'a Fork, name: &str) -> View<&'a Fork> {
+        let id = IndexesPool::new(fork)
+            .index_metadata(name.as_bytes())
+            .unwrap()
+            .identifier();
+        View::new(fork, ResolvedAddress::new(name, Some(id)))
+    }
+
+    #[test]
+    fn forged_index_data_is_rejected() {
+        fn create_forged_dump(forge: impl FnOnce(&Fork)) -> Vec<u8> {
+            let db = TemporaryDB::new();
+            let fork = db.fork();
+            fork.get_proof_list("list").extend(vec![1_u32, 2, 3, 4, 5]);
+            fork.get_proof_entry("entry").set(42_u64);
+            db.merge(fork.into_patch()).unwrap();
+
+            // Change the data bypassing the index, so that the stored hashes remain intact.
+            let fork = db.fork();
+            forge(&fork);
+            db.merge(fork.into_patch()).unwrap();
+            export_to_vec(db.snapshot().as_ref()).0
+        }
+
+        let buffer = create_forged_dump(|fork| raw_view(fork, "list").put(&3_u64, 100_u32));
+        let err = import(&TemporaryDB::new(), buffer.as_slice()).unwrap_err();
+        assert!(
+            err.to_string().contains("Index `list` is corrupted"),
+            "{}",
+            err
+        );
+
+        let buffer = create_forged_dump(|fork| raw_view(fork, "entry").put(&(), 23_u64));
+        let err = import(&TemporaryDB::new(), buffer.as_slice()).unwrap_err();
+        assert!(
+            err.to_string().contains("Index `entry` is corrupted"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn unauthenticated_indexes_are_reported() {
+        let db = create_database();
+        let (buffer, info) = export_to_vec(db.snapshot().as_ref());
+        // The state aggregator for the migration namespace is not covered by the state hash.
+        let expected_names = vec!["__STATE_AGGREGATOR__", "group", "migrated.map", "set"];
+        assert_eq!(info.unauthenticated_indexes, expected_names);
+        let imported = import(&TemporaryDB::new(), buffer.as_slice()).unwrap();
+        assert_eq!(
+            imported.unauthenticated_indexes,
+            info.unauthenticated_indexes
+        );
+    }
+}
@@ -36,8 +36,21 @@ impl Error {
     }
 }
 
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Self::new(err.to_string())
+    }
+}
+
 impl From<rocksdb::Error> for Error {
     fn from(err: rocksdb::Error) -> Self {
         Self::new(err.to_string())
     }
 }
+
+#[cfg(feature = "redb")]
+impl From<redb::Error> for Error {
+    fn from(err: redb::Error) -> Self {
+        Self::new(err.to_string())
+    }
+}
@@ -0,0 +1,198 @@
+// Copyright 2020 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Persistent history of database changes.
+//!
+//! The history is a system view mapping each version of the database (encoded as a big-endian
+//! `u64`) to the changes reverting the database state to the previous version. The reverse changes
+//! are computed in the same way as in `DatabaseExt::merge_with_backup()` and are stored atomically
+//! together with the changes that they revert.
+
+use byteorder::{BigEndian, ByteOrder};
+
+use std::{collections::BTreeMap, convert::TryFrom, num::NonZeroU64};
+
+use crate::{
+    db::{Change, DB_METADATA},
+    views::ResolvedAddress,
+    Error, Result, Snapshot,
+};
+
+/// Name of the system view with reverse changes for each database version.
+const HISTORY_NAME: &str = "__HISTORY__";
+/// Name of the metadata attribute holding the latest recorded version.
+pub(crate) const LATEST_VERSION_NAME: &str = "history_version";
+
+/// Changes to a single view reverting a single database version.
+pub(crate) type ReverseChanges = Vec<(ResolvedAddress, BTreeMap<Vec<u8>, Change>)>;
+
+const TAG_DELETE: u8 = 0;
+const TAG_PUT: u8 = 1;
+
+pub(crate) fn history_address() -> ResolvedAddress {
+    ResolvedAddress::system(HISTORY_NAME)
+}
+
+pub(crate) fn metadata_address() -> ResolvedAddress {
+    ResolvedAddress::system(DB_METADATA)
+}
+
+pub(crate) fn version_key(version: u64) -> [u8; 8] {
+    let mut key = [0; 8];
+    BigEndian::write_u64(&mut key, version);
+    key
+}
+
+/// Returns the latest version recorded in the history, or `None` if the history is empty.
+pub(crate) fn latest_version(snapshot: &dyn Snapshot) -> Option<u64> {
+    snapshot
+        .get(&metadata_address(), LATEST_VERSION_NAME.as_bytes())
+        .map(|bytes| BigEndian::read_u64(&bytes))
+}
+
+/// Returns the earliest version with reverse changes available in the history.
+pub(crate) fn earliest_version(snapshot: &dyn Snapshot) -> Option<u64> {
+    let address = history_address();
+    let mut iter = snapshot.iter(&address, &[]);
+    iter.next().map(|(key, _)| BigEndian::read_u64(key))
+}
+
+/// Loads reverse changes for all versions in the `(from, to]` interval, in the ascending
+/// version order.
+pub(crate) fn load_changes(
+    snapshot: &dyn Snapshot,
+    from: u64,
+    to: u64,
+) -> Result<Vec<ReverseChanges>> {
+    let address = history_address();
+    let mut iter = snapshot.iter(&address, &version_key(from + 1));
+    let mut changes = Vec::new();
+    while let Some((key, value)) = iter.next() {
+        if BigEndian::read_u64(key) > to {
+            break;
+        }
+        changes.push(decode(value)?);
+    }
+    Ok(changes)
+}
+
+pub(crate) fn write_bytes(buffer: &mut Vec<u8>, bytes: &[u8]) {
+    leb128::write::unsigned(buffer, bytes.len() as u64).unwrap();
+    buffer.extend_from_slice(bytes);
+}
+
+pub(crate) fn read_len(reader: &mut &[u8]) -> Result<usize> {
+    let len = leb128::read::unsigned(reader).map_err(|e| Error::new(e.to_string()))?;
+    usize::try_from(len).map_err(|e| Error::new(e.to_string()))
+}
+
+fn read_slice<'a>(reader: &mut &'a [u8], len: usize) -> Result<&'a [u8]> {
+    if reader.len() < len {
+        return Err(Error::new("Unexpected end of history entry"));
+    }
+    let (bytes, rest) = reader.split_at(len);
+    *reader = rest;
+    Ok(bytes)
+}
+
+pub(crate) fn read_bytes(reader: &mut &[u8]) -> Result<Vec<u8>> {
+    let len = read_len(reader)?;
+    read_slice(reader, len).map(<[u8]>::to_vec)
+}
+
+/// Encodes reverse changes into the binary format used in the history view.
+pub(crate) fn encode<'a>(
+    changes: impl ExactSizeIterator<Item = (&'a ResolvedAddress, &'a BTreeMap<Vec<u8>, Change>)>,
+) -> Vec<u8> {
+    let mut buffer = Vec::new();
+    leb128::write::unsigned(&mut buffer, changes.len() as u64).unwrap();
+    for (address, data) in changes {
+        write_bytes(&mut buffer, address.name.as_bytes());
+        let id = address.id.map_or(0, NonZeroU64::get);
+        leb128::write::unsigned(&mut buffer, id).unwrap();
+        leb128::write::unsigned(&mut buffer, data.len() as u64).unwrap();
+        for (key, change) in data {
+            write_bytes(&mut buffer, key);
+            match change {
+                Change::Put(value) => {
+                    buffer.push(TAG_PUT);
+                    write_bytes(&mut buffer, value);
+                }
+                Change::Delete => buffer.push(TAG_DELETE),
+            }
+        }
+    }
+    buffer
+}
+
+/// Decodes reverse changes previously encoded with `encode`.
+pub(crate) fn decode(mut reader: &[u8]) -> Result<ReverseChanges> {
+    let reader = &mut reader;
+    let views_count = read_len(reader)?;
+    let mut changes = Vec::with_capacity(views_count);
+    for _ in 0..views_count {
+        let name = String::from_utf8(read_bytes(reader)?).map_err(|e| Error::new(e.to_string()))?;
+        let id = leb128::read::unsigned(reader).map_err(|e| Error::new(e.to_string()))?;
+        let address = ResolvedAddress::new(name, NonZeroU64::new(id));
+
+        let changes_count = read_len(reader)?;
+        let mut data = BTreeMap::new();
+        for _ in 0..changes_count {
+            let key = read_bytes(reader)?;
+            let change = match read_slice(reader, 1)?[0] {
+                TAG_PUT => Change::Put(read_bytes(reader)?),
+                TAG_DELETE => Change::Delete,
+                other => {
+                    return Err(Error::new(format!(
+                        "Unknown change tag in history entry: {}",
+                        other
+                    )))
+                }
+            };
+            data.insert(key, change);
+        }
+        changes.push((address, data));
+    }
+
+    if reader.is_empty() {
+        Ok(changes)
+    } else {
+        Err(Error::new("Trailing bytes in history entry"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reverse_changes_roundtrip() {
+        let mut first = BTreeMap::new();
+        first.insert(vec![1, 2], Change::Put(vec![3; 200]));
+        first.insert(vec![], Change::Delete);
+        let mut second = BTreeMap::new();
+        second.insert(vec![0; 300], Change::Put(vec![]));
+
+        let first_addr = ResolvedAddress::system("first");
+        let second_addr = ResolvedAddress::new("second", NonZeroU64::new(42));
+        let changes = vec![(first_addr, first), (second_addr, second)];
+        let bytes = encode(changes.iter().map(|(addr, data)| (addr, data)));
+        assert_eq!(decode(&bytes).unwrap(), changes);
+
+        assert!(decode(&bytes[..bytes.len() - 1]).is_err());
+        let mut extended = bytes;
+        extended.push(0);
+        assert!(decode(&extended).is_err());
+    }
+}
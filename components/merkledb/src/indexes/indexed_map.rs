@@ -0,0 +1,270 @@
+// Copyright 2020 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! An implementation of a key-value map with a secondary index over its values.
+//!
+//! `IndexedMapIndex` keeps a secondary index consistent with the map on each modification.
+
+use exonum_crypto::{hash, Hash};
+
+use crate::{
+    access::{Access, AccessError, FromAccess},
+    indexes::{
+        iter::{Entries, Keys, Values},
+        KeySetIndex, MapIndex,
+    },
+    views::{IndexAddress, RawAccessMut},
+    BinaryKey, BinaryValue,
+};
+
+/// A map of keys and values with a secondary index over the map values.
+///
+/// The secondary index maps a *secondary key* computed from a value to the set of keys
+/// of the map entries with the values having this secondary key. The secondary index
+/// is stored as a single [`KeySetIndex`], elements of which are the hash of the secondary key
+/// concatenated with the key of the map entry. The index is updated automatically
+/// on each modification of the map, and is not accessible directly.
+///
+/// `IndexedMapIndex` is usually created within a component with the help of the
+/// `secondary_index` field attribute of the `FromAccess` derive macro, which specifies
+/// a field of the values used as the secondary key.
+///
+/// # Examples
+///
+/// ```
+/// # use serde_derive::{Serialize, Deserialize};
+/// use exonum_derive::{BinaryValue, FromAccess};
+/// use exonum_merkledb::{
+///     access::{Access, FromAccess},
+///     Database, IndexedMapIndex, TemporaryDB,
+/// };
+///
+/// #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, BinaryValue)]
+/// #[binary_value(codec = "bincode")]
+/// struct Wallet {
+///     owner: String,
+///     balance: u64,
+/// }
+///
+/// #[derive(FromAccess)]
+/// struct Schema<T: Access> {
+///     #[from_access(secondary_index(field = "owner"))]
+///     wallets: IndexedMapIndex<T, u64, Wallet, String>,
+/// }
+///
+/// # fn main() {
+/// let db = TemporaryDB::new();
+/// let fork = db.fork();
+/// let mut schema = Schema::from_root(&fork).unwrap();
+/// let wallet = Wallet { owner: "Alice".to_owned(), balance: 100 };
+/// schema.wallets.put(&1, wallet.clone());
+/// schema.wallets.put(&2, wallet);
+///
+/// let alice_wallets = schema.wallets.keys_by_secondary(&"Alice".to_owned());
+/// assert_eq!(alice_wallets.collect::<Vec<_>>(), vec![1, 2]);
+/// # }
+/// ```
+///
+/// [`KeySetIndex`]: struct.KeySetIndex.html
+#[derive(Debug)]
+pub struct IndexedMapIndex<T: Access, K: ?Sized, V, S> {
+    primary: MapIndex<T::Base, K, V>,
+    secondary: KeySetIndex<T::Base, [u8]>,
+    secondary_key: fn(&V) -> S,
+}
+
+/// Computes the prefix of the secondary index elements for the specified secondary key.
+/// The secondary key is hashed, so that elements for different secondary keys
+/// cannot be confused with each other if the keys have variable length.
+fn secondary_prefix<S: BinaryKey>(secondary_key: &S) -> Hash {
+    hash(&concat_keys!(secondary_key))
+}
+
+impl<T, K, V, S> IndexedMapIndex<T, K, V, S>
+where
+    T: Access,
+    K: BinaryKey + ?Sized,
+    V: BinaryValue,
+    S: BinaryKey,
+{
+    /// Creates a map at the `addr` address with the secondary index at the `secondary_addr`
+    /// address. The secondary key for map values is computed with the `secondary_key` function.
+    pub fn new(
+        access: T,
+        addr: IndexAddress,
+        secondary_addr: IndexAddress,
+        secondary_key: fn(&V) -> S,
+    ) -> Result<Self, AccessError> {
+        Ok(Self {
+            primary: FromAccess::from_access(access.clone(), addr)?,
+            secondary: FromAccess::from_access(access, secondary_addr)?,
+            secondary_key,
+        })
+    }
+
+    /// Returns a value corresponding to the key.
+    pub fn get(&self, key: &K) -> Option<V> {
+        self.primary.get(key)
+    }
+
+    /// Returns `true` if the map contains a value corresponding to the specified key.
+    pub fn contains(&self, key: &K) -> bool {
+        self.primary.contains(key)
+    }
+
+    /// Returns an iterator over the entries of the map in ascending order.
+    pub fn iter(&self) -> Entries<'_, K, V> {
+        self.primary.iter()
+    }
+
+    /// Returns an iterator over the keys of the map in ascending order.
+    pub fn keys(&self) -> Keys<'_, K> {
+        self.primary.keys()
+    }
+
+    /// Returns an iterator over the values of the map in ascending order of keys.
+    pub fn values(&self) -> Values<'_, V> {
+        self.primary.values()
+    }
+
+    /// Returns an iterator over the entries of the map in ascending order starting from
+    /// the specified key.
+    pub fn iter_from(&self, from: &K) -> Entries<'_, K, V> {
+        self.primary.iter_from(from)
+    }
+
+    /// Returns an iterator over the keys of the map entries, values of which have
+    /// the specified secondary key. The keys are returned in ascending order.
+    pub fn keys_by_secondary(&self, secondary_key: &S) -> Keys<'_, K> {
+        self.secondary
+            .iter_detached(&secondary_prefix(secondary_key))
+    }
+}
+
+impl<T, K, V, S> IndexedMapIndex<T, K, V, S>
+where
+    T: Access,
+    T::Base: RawAccessMut,
+    K: BinaryKey + ?Sized,
+    V: BinaryValue,
+    S: BinaryKey,
+{
+    /// Inserts a key-value pair into the map and updates the secondary index.
+    pub fn put(&mut self, key: &K, value: V) {
+        let new_secondary_key = (self.secondary_key)(&value);
+        if let Some(old_value) = self.primary.get(key) {
+            let old_secondary_key = (self.secondary_key)(&old_value);
+            self.remove_secondary(&old_secondary_key, key);
+        }
+        let prefix = secondary_prefix(&new_secondary_key);
+        self.secondary.insert(&concat_keys!(&prefix, key));
+        self.primary.put(key, value);
+    }
+
+    /// Removes a key from the map and updates the secondary index.
+    pub fn remove(&mut self, key: &K) {
+        if let Some(old_value) = self.primary.get(key) {
+            let old_secondary_key = (self.secondary_key)(&old_value);
+            self.remove_secondary(&old_secondary_key, key);
+            self.primary.remove(key);
+        }
+    }
+
+    /// Clears the map and the secondary index, removing all entries.
+    pub fn clear(&mut self) {
+        self.secondary.clear();
+        self.primary.clear();
+    }
+
+    fn remove_secondary(&mut self, secondary_key: &S, key: &K) {
+        let prefix = secondary_prefix(secondary_key);
+        self.secondary.remove(&concat_keys!(&prefix, key));
+    }
+}
+
+impl<'a, T, K, V, S> IntoIterator for &'a IndexedMapIndex<T, K, V, S>
+where
+    T: Access,
+    K: BinaryKey + ?Sized,
+    V: BinaryValue,
+    S: BinaryKey,
+{
+    type Item = (K::Owned, V);
+    type IntoIter = Entries<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{access::CopyAccessExt, Database, TemporaryDB};
+
+    fn create_index<T: Access>(access: T) -> IndexedMapIndex<T, u32, String, u8> {
+        IndexedMapIndex::new(
+            access,
+            "map".into(),
+            "map_by_len".into(),
+            |value: &String| value.len() as u8,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn secondary_index_is_updated() {
+        let db = TemporaryDB::new();
+        let fork = db.fork();
+        let mut index = create_index(&fork);
+
+        index.put(&1, "foo".to_owned());
+        index.put(&2, "bar".to_owned());
+        index.put(&3, "quux".to_owned());
+        assert_eq!(index.get(&1), Some("foo".to_owned()));
+        assert_eq!(index.keys_by_secondary(&3).collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(index.keys_by_secondary(&4).collect::<Vec<_>>(), vec![3]);
+
+        // Changing the secondary key of a value.
+        index.put(&2, "barbaz".to_owned());
+        assert_eq!(index.keys_by_secondary(&3).collect::<Vec<_>>(), vec![1]);
+        assert_eq!(index.keys_by_secondary(&6).collect::<Vec<_>>(), vec![2]);
+
+        index.remove(&1);
+        index.remove(&100);
+        assert!(!index.contains(&1));
+        assert_eq!(index.keys_by_secondary(&3).count(), 0);
+
+        // The secondary index is stored in a single key set.
+        drop(index);
+        let by_len = fork.get_key_set::<_, [u8]>("map_by_len");
+        assert_eq!(by_len.iter().count(), 2);
+    }
+
+    #[test]
+    fn clearing_indexed_map() {
+        let db = TemporaryDB::new();
+        let fork = db.fork();
+        let mut index = create_index(&fork);
+        index.put(&1, "foo".to_owned());
+        index.put(&2, "quux".to_owned());
+        index.clear();
+
+        assert_eq!(index.iter().count(), 0);
+        assert_eq!(index.keys_by_secondary(&3).count(), 0);
+        assert_eq!(index.keys_by_secondary(&4).count(), 0);
+        drop(index);
+        assert_eq!(fork.get_key_set::<_, [u8]>("map_by_len").iter().count(), 0);
+    }
+}
@@ -14,11 +14,16 @@
 
 //! Generic iterator types used by all indexes.
 
+use std::{borrow::Borrow, fmt, vec};
+
 use crate::{
     views::{Iter, RawAccess, View},
     BinaryKey, BinaryValue,
 };
 
+/// Type of owned keys of an index.
+type OwnedKey<I> = <<I as IndexIterator>::Key as ToOwned>::Owned;
+
 /// Iterator over key-value pairs of an index.
 ///
 /// This structure is returned by the [`IndexIterator`] trait and by inherent methods
@@ -155,4 +160,229 @@ pub trait IndexIterator {
     /// Continues iteration from the specified position. If `from` is `None`, starts the iteration
     /// from scratch.
     fn index_iter(&self, from: Option<&Self::Key>) -> Entries<'_, Self::Key, Self::Value>;
+
+    /// Converts the index into an owned iterator over its entries, starting from the specified
+    /// position. See [`OwnedEntries`] for details.
+    ///
+    /// [`OwnedEntries`]: struct.OwnedEntries.html
+    fn into_owned_iter(self, from: Option<&Self::Key>) -> OwnedEntries<Self>
+    where
+        Self: Sized,
+    {
+        OwnedEntries::new(self, from)
+    }
+}
+
+/// Owned iterator over key-value pairs of an index.
+///
+/// Unlike [`Entries`], this iterator does not borrow the index, but rather takes ownership of it.
+/// If the index is based on an owned access, such as `Arc<dyn Snapshot>`, the iterator
+/// is `Send + 'static`, and can be moved to another thread or held across `await` points.
+///
+/// Entries are read from the database in chunks of the specified size; thus, the iterator
+/// uses bounded memory regardless of the index size. If the `with-futures` crate feature
+/// is enabled, the iterator can be converted into a `futures::Stream` with [`into_stream`].
+/// Note that reading a chunk is a blocking operation.
+///
+/// [`Entries`]: struct.Entries.html
+/// [`into_stream`]: #method.into_stream
+///
+/// # Examples
+///
+/// ```
+/// # use exonum_merkledb::{
+/// #     access::{AccessExt, CopyAccessExt}, indexes::IndexIterator, Database, Snapshot,
+/// #     TemporaryDB,
+/// # };
+/// # use std::sync::Arc;
+/// let db = TemporaryDB::new();
+/// let fork = db.fork();
+/// fork.get_list("list").extend(vec![1_u32, 2, 3]);
+/// db.merge(fork.into_patch()).unwrap();
+///
+/// let snapshot: Arc<dyn Snapshot> = db.snapshot().into();
+/// let list = snapshot.get_list::<_, u32>("list");
+/// let iter = list.into_owned_iter(Some(&1));
+/// let handle = std::thread::spawn(move || iter.map(|(_, value)| value).collect::<Vec<_>>());
+/// assert_eq!(handle.join().unwrap(), vec![2, 3]);
+/// ```
+pub struct OwnedEntries<I: IndexIterator> {
+    index: I,
+    next_key: Option<OwnedKey<I>>,
+    buffered_entries: vec::IntoIter<(OwnedKey<I>, I::Value)>,
+    buffer_size: usize,
+}
+
+impl<I: IndexIterator> OwnedEntries<I> {
+    /// Default number of entries read from the database at once.
+    pub const DEFAULT_BUFFER_SIZE: usize = 1_000;
+
+    /// Creates an iterator over the `index` starting from the specified position.
+    pub fn new(index: I, from: Option<&I::Key>) -> Self {
+        Self::with_buffer_size(index, from, Self::DEFAULT_BUFFER_SIZE)
+    }
+
+    /// Creates an iterator over the `index` starting from the specified position, which reads
+    /// `buffer_size` entries from the database at once.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `buffer_size` is zero.
+    pub fn with_buffer_size(index: I, from: Option<&I::Key>, buffer_size: usize) -> Self {
+        assert!(buffer_size > 0, "Buffer size must be positive");
+
+        let mut this = Self {
+            index,
+            next_key: None,
+            buffered_entries: Vec::new().into_iter(),
+            buffer_size,
+        };
+        this.buffer_entries(from);
+        this
+    }
+
+    /// Returns a reference to the underlying index.
+    pub fn index(&self) -> &I {
+        &self.index
+    }
+
+    /// Converts this iterator into a `futures::Stream`.
+    #[cfg(feature = "with-futures")]
+    pub fn into_stream(self) -> futures::stream::Iter<Self> {
+        futures::stream::iter(self)
+    }
+
+    fn buffer_entries(&mut self, from: Option<&I::Key>) {
+        let mut iter = self.index.index_iter(from);
+        let buffer: Vec<_> = iter.by_ref().take(self.buffer_size).collect();
+        // Store the key of the next entry, if any, to continue iteration from it.
+        self.next_key = iter.next().map(|(key, _)| key);
+        self.buffered_entries = buffer.into_iter();
+    }
+}
+
+impl<I: IndexIterator> Iterator for OwnedEntries<I> {
+    type Item = (OwnedKey<I>, I::Value);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.buffered_entries.next().or_else(|| {
+            let next_key = self.next_key.take()?;
+            // Buffer more entries.
+            self.buffer_entries(Some(next_key.borrow()));
+            self.buffered_entries.next()
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let buffered = self.buffered_entries.len();
+        if self.next_key.is_some() {
+            (buffered + 1, None)
+        } else {
+            (buffered, Some(buffered))
+        }
+    }
+}
+
+impl<I> fmt::Debug for OwnedEntries<I>
+where
+    I: IndexIterator + fmt::Debug,
+{
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("OwnedEntries")
+            .field("index", &self.index)
+            .field("buffer_size", &self.buffer_size)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        access::{AccessExt, CopyAccessExt},
+        Database, Snapshot, TemporaryDB,
+    };
+
+    fn create_snapshot() -> Arc<dyn Snapshot> {
+        let db = TemporaryDB::new();
+        let fork = db.fork();
+        fork.get_list("list").extend(0_u64..25);
+        fork.get_proof_list("proof_list").extend(0_u64..25);
+        let mut map = fork.get_map("map");
+        for i in 0_u8..25 {
+            map.put(&i, i.to_string());
+        }
+        drop(map);
+        db.merge(fork.into_patch()).unwrap();
+        db.snapshot().into()
+    }
+
+    fn assert_send_static<T: Send + 'static>(_: &T) {}
+
+    #[test]
+    fn owned_iter_for_map() {
+        let snapshot = create_snapshot();
+        let map = snapshot.get_map::<_, u8, String>("map");
+        let iter = OwnedEntries::with_buffer_size(map, None, 7);
+        assert_send_static(&iter);
+        let entries: Vec<_> = iter.collect();
+        let expected: Vec<_> = (0_u8..25).map(|i| (i, i.to_string())).collect();
+        assert_eq!(entries, expected);
+
+        let map = snapshot.get_map::<_, u8, String>("map");
+        let keys: Vec<_> = OwnedEntries::with_buffer_size(map, Some(&20), 2)
+            .map(|(key, _)| key)
+            .collect();
+        assert_eq!(keys, vec![20, 21, 22, 23, 24]);
+    }
+
+    #[test]
+    fn owned_iter_for_lists() {
+        let snapshot = create_snapshot();
+        for &buffer_size in &[1, 5, 24, 25, 100] {
+            let list = snapshot.get_list::<_, u64>("list");
+            let values: Vec<_> = OwnedEntries::with_buffer_size(list, Some(&3), buffer_size)
+                .map(|(_, value)| value)
+                .collect();
+            assert_eq!(values, (3..25).collect::<Vec<_>>());
+
+            let list = snapshot.get_proof_list::<_, u64>("proof_list");
+            let iter = OwnedEntries::with_buffer_size(list, None, buffer_size);
+            assert_send_static(&iter);
+            let values: Vec<_> = iter.map(|(_, value)| value).collect();
+            assert_eq!(values, (0..25).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn owned_iter_size_hint() {
+        let snapshot = create_snapshot();
+        let list = snapshot.get_list::<_, u64>("list");
+        let mut iter = OwnedEntries::with_buffer_size(list, None, 10);
+        assert_eq!(iter.size_hint(), (11, None));
+        assert_eq!(iter.nth(20), Some((20, 20)));
+        assert_eq!(iter.size_hint(), (4, Some(4)));
+    }
+
+    #[test]
+    fn owned_iter_for_empty_index() {
+        let snapshot = create_snapshot();
+        let list = snapshot.get_list::<_, u64>("empty");
+        assert_eq!(list.into_owned_iter(None).count(), 0);
+    }
+
+    #[cfg(feature = "with-futures")]
+    #[test]
+    fn owned_iter_as_stream() {
+        use futures::{executor::block_on, StreamExt};
+
+        let snapshot = create_snapshot();
+        let list = snapshot.get_proof_list::<_, u64>("proof_list");
+        let stream = OwnedEntries::with_buffer_size(list, Some(&10), 4).into_stream();
+        let values: Vec<_> = block_on(stream.map(|(_, value)| value).collect());
+        assert_eq!(values, (10..25).collect::<Vec<_>>());
+    }
 }
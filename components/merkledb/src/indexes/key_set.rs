@@ -119,6 +119,17 @@ where
     pub fn iter_from(&self, from: &K) -> Keys<'_, K> {
         self.index_iter(Some(from)).skip_values()
     }
+
+    /// Returns an iterator over set elements starting with the specified prefix.
+    /// The prefix is detached from the elements, the remaining part of which is parsed
+    /// as a key of type `R`.
+    pub(crate) fn iter_detached<P, R>(&self, prefix: &P) -> Keys<'_, R>
+    where
+        P: BinaryKey,
+        R: BinaryKey + ?Sized,
+    {
+        Entries::<R, ()>::with_detached_prefix(&self.base, prefix, None).skip_values()
+    }
 }
 
 impl<T, K> KeySetIndex<T, K>
@@ -17,7 +17,10 @@
 //! The given section contains methods related to `ListIndex` and the iterator
 //! over the items of this list.
 
-use std::marker::PhantomData;
+use std::{
+    marker::PhantomData,
+    ops::{Bound, RangeBounds},
+};
 
 use crate::{
     access::{Access, AccessError, FromAccess},
@@ -293,10 +296,75 @@ where
     /// assert_eq!(3, index.len());
     /// ```
     pub fn truncate(&mut self, len: u64) {
-        // TODO: Optimize this. (ECR-175)
-        while self.len() > len {
-            self.pop();
+        if self.len() > len {
+            self.base.remove_range(len..);
+            self.set_len(len);
+        }
+    }
+
+    /// Removes the specified range of elements from the list, shifting all elements
+    /// after the range to the left.
+    ///
+    /// # Notes
+    ///
+    /// The elements in the range are removed without iterating over them; the removal
+    /// is stored as a single range tombstone in the fork. However, the elements following
+    /// the range need to be moved, so the method is most efficient when removing elements
+    /// close to the end of the list.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the start of the range is greater than its end, or if the end
+    /// of the range is greater than the length of the list.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum_merkledb::{access::CopyAccessExt, TemporaryDB, Database, ListIndex};
+    ///
+    /// let db = TemporaryDB::new();
+    /// let fork = db.fork();
+    /// let mut index = fork.get_list("name");
+    ///
+    /// index.extend([1, 2, 3, 4, 5].iter().cloned());
+    /// index.remove_range(1..3);
+    /// assert_eq!(index.iter().collect::<Vec<_>>(), vec![1, 4, 5]);
+    /// index.remove_range(2..);
+    /// assert_eq!(index.iter().collect::<Vec<_>>(), vec![1, 4]);
+    /// ```
+    pub fn remove_range<R>(&mut self, range: R)
+    where
+        R: RangeBounds<u64>,
+    {
+        let len = self.len();
+        let start = match range.start_bound() {
+            Bound::Included(&index) => index,
+            Bound::Excluded(&index) => index + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&index) => index + 1,
+            Bound::Excluded(&index) => index,
+            Bound::Unbounded => len,
+        };
+        assert!(
+            start <= end && end <= len,
+            "range out of bounds: the len is {} but the range is {}..{}",
+            len,
+            start,
+            end
+        );
+        if start == end {
+            return;
         }
+
+        for index in end..len {
+            let value: V = self.base.get(&index).expect("List element is missing");
+            self.base.put(&(index - end + start), value);
+        }
+        let new_len = len - (end - start);
+        self.base.remove_range(new_len..);
+        self.set_len(new_len);
     }
 
     /// Changes a value at the specified position.
@@ -557,6 +625,50 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_list_index_remove_range() {
+        let db = TemporaryDB::new();
+        let fork = db.fork();
+        fork.get_list(IDX_NAME).extend(0_u32..10);
+        db.merge(fork.into_patch()).unwrap();
+
+        let fork = db.fork();
+        let mut list_index = fork.get_list::<_, u32>(IDX_NAME);
+        list_index.remove_range(2..5);
+        assert_eq!(list_index.len(), 7);
+        assert_eq!(
+            list_index.iter().collect::<Vec<_>>(),
+            vec![0, 1, 5, 6, 7, 8, 9]
+        );
+        list_index.remove_range(5..=6);
+        list_index.remove_range(1..1);
+        assert_eq!(list_index.iter().collect::<Vec<_>>(), vec![0, 1, 5, 6, 7]);
+        assert_eq!(list_index.get(5), None);
+
+        list_index.push(10);
+        list_index.truncate(2);
+        list_index.push(11);
+        assert_eq!(list_index.iter().collect::<Vec<_>>(), vec![0, 1, 11]);
+        drop(list_index);
+        db.merge(fork.into_patch()).unwrap();
+
+        let snapshot = db.snapshot();
+        let list_index = snapshot.get_list::<_, u32>(IDX_NAME);
+        assert_eq!(list_index.len(), 3);
+        assert_eq!(list_index.iter().collect::<Vec<_>>(), vec![0, 1, 11]);
+        assert_eq!(list_index.get(3), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "range out of bounds")]
+    fn test_list_index_remove_range_out_of_bounds() {
+        let db = TemporaryDB::new();
+        let fork = db.fork();
+        let mut list_index = fork.get_list::<_, u32>(IDX_NAME);
+        list_index.extend(0_u32..3);
+        list_index.remove_range(2..4);
+    }
+
     #[test]
     fn restore_after_no_op_initialization() {
         let db = TemporaryDB::new();
@@ -18,7 +18,7 @@
 //! the [`BinaryValue`] trait. The given section contains methods related to
 //! `MapIndex` and iterators over the items of this map.
 
-use std::{borrow::Borrow, marker::PhantomData};
+use std::{borrow::Borrow, marker::PhantomData, ops::RangeBounds};
 
 use crate::{
     access::{Access, AccessError, FromAccess},
@@ -272,6 +272,38 @@ where
         self.base.remove(key);
     }
 
+    /// Removes all entries with the keys in the specified range from a map.
+    ///
+    /// # Notes
+    ///
+    /// Unlike removing keys one by one, this method does not iterate over the removed entries.
+    /// The removal is stored as a single range tombstone in the fork, and is applied
+    /// to the database with a single range deletion when the fork is merged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum_merkledb::{access::CopyAccessExt, TemporaryDB, Database, MapIndex};
+    ///
+    /// let db = TemporaryDB::default();
+    /// let fork = db.fork();
+    /// let mut index = fork.get_map("name");
+    ///
+    /// for i in 0_u32..10 {
+    ///     index.put(&i, i.to_string());
+    /// }
+    /// index.remove_range(2..8);
+    /// assert_eq!(index.keys().collect::<Vec<_>>(), vec![0, 1, 8, 9]);
+    /// index.remove_range(5..);
+    /// assert_eq!(index.keys().collect::<Vec<_>>(), vec![0, 1]);
+    /// ```
+    pub fn remove_range<R>(&mut self, range: R)
+    where
+        R: RangeBounds<K>,
+    {
+        self.base.remove_range(range);
+    }
+
     /// Clears a map, removing all entries.
     ///
     /// # Notes
@@ -388,6 +420,37 @@ mod tests {
         assert!(!map_index.contains(&3_u8));
     }
 
+    #[test]
+    fn test_remove_range() {
+        use std::ops::Bound;
+
+        let db = TemporaryDB::default();
+        let fork = db.fork();
+        let mut map_index = fork.get_map(IDX_NAME);
+        for key in &["a", "ab", "abc", "b", "ba", "c"] {
+            map_index.put(*key, key.len() as u8);
+        }
+        drop(map_index);
+        db.merge(fork.into_patch()).unwrap();
+
+        let fork = db.fork();
+        let mut map_index = fork.get_map::<_, str, u8>(IDX_NAME);
+        map_index.remove_range((Bound::Excluded("a"), Bound::Included("b")));
+        assert_eq!(map_index.keys().collect::<Vec<_>>(), vec!["a", "ba", "c"]);
+        map_index.put("abc", 0);
+        map_index.remove_range((Bound::Included("b"), Bound::Unbounded));
+        assert_eq!(map_index.keys().collect::<Vec<_>>(), vec!["a", "abc"]);
+        drop(map_index);
+        db.merge(fork.into_patch()).unwrap();
+
+        let snapshot = db.snapshot();
+        let map_index = snapshot.get_map::<_, str, u8>(IDX_NAME);
+        assert_eq!(
+            map_index.iter().collect::<Vec<_>>(),
+            vec![("a".to_owned(), 1), ("abc".to_owned(), 0)]
+        );
+    }
+
     #[test]
     fn test_iter() {
         let db = TemporaryDB::default();
@@ -17,22 +17,26 @@
 pub use self::{
     entry::Entry,
     group::Group,
-    iter::{Entries, IndexIterator, Keys, Values},
+    indexed_map::IndexedMapIndex,
+    iter::{Entries, IndexIterator, Keys, OwnedEntries, Values},
     key_set::KeySetIndex,
     list::ListIndex,
     map::MapIndex,
     proof_entry::ProofEntry,
+    proof_key_set::{ProofKeySetIndex, RawProofKeySetIndex},
     sparse_list::SparseListIndex,
     value_set::ValueSetIndex,
 };
 
 mod entry;
 mod group;
+mod indexed_map;
 mod iter;
 mod key_set;
 mod list;
 mod map;
 mod proof_entry;
+mod proof_key_set;
 pub mod proof_list;
 pub mod proof_map;
 mod sparse_list;
@@ -0,0 +1,401 @@
+// Copyright 2020 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! An implementation of a Merkelized set for items that utilize the `BinaryKey` trait.
+//!
+//! `ProofKeySetIndex` implements a set that is backed by a Merkle Patricia tree in the same way
+//! as `ProofMapIndex`, with set elements being the map keys with empty values.
+
+use exonum_crypto::Hash;
+
+use std::fmt;
+
+use crate::{
+    access::{Access, AccessError, FromAccess},
+    indexes::{
+        iter::Keys,
+        proof_map::{Hashed, MapProof, ProofMapIndex, Raw, ToProofPath},
+    },
+    views::{IndexAddress, IndexType, RawAccess, RawAccessMut},
+    BinaryKey, ObjectHash,
+};
+
+/// A Merkelized set of key items that provides proofs of membership or non-membership
+/// for the set elements.
+///
+/// `ProofKeySetIndex` is stored as a Merkle Patricia tree, elements of the set being the keys
+/// of the tree with empty values. Consequently, proofs for the set are [`MapProof`]s
+/// with the unit type as the value type: an element is proven to be present in the set
+/// if it is returned among the proof entries, and absent if it is returned among
+/// the missing keys.
+///
+/// `ProofKeySetIndex` requires that elements implement the [`BinaryKey`] trait. Like
+/// `ProofMapIndex`, the set can either hash its elements to obtain paths in the tree
+/// (the default), or use [`Raw`] elements, which can be mapped to paths directly.
+///
+/// [`MapProof`]: proof_map/struct.MapProof.html
+/// [`BinaryKey`]: ../trait.BinaryKey.html
+/// [`Raw`]: proof_map/enum.Raw.html
+pub struct ProofKeySetIndex<T: RawAccess, K: ?Sized, KeyMode: ToProofPath<K> = Hashed> {
+    base: ProofMapIndex<T, K, (), KeyMode>,
+}
+
+/// Raw variant of the `ProofKeySetIndex`, useful for elements that are mapped directly to
+/// `ProofPath` without hashing, for example `Hash` and `PublicKey`.
+pub type RawProofKeySetIndex<T, K> = ProofKeySetIndex<T, K, Raw>;
+
+impl<T, K, KeyMode> FromAccess<T> for ProofKeySetIndex<T::Base, K, KeyMode>
+where
+    T: Access,
+    K: BinaryKey + ?Sized,
+    KeyMode: ToProofPath<K>,
+{
+    fn from_access(access: T, addr: IndexAddress) -> Result<Self, AccessError> {
+        let view = access.get_or_create_view(addr, IndexType::ProofKeySet)?;
+        Ok(Self {
+            base: ProofMapIndex::new(view),
+        })
+    }
+}
+
+impl<T, K, KeyMode> ProofKeySetIndex<T, K, KeyMode>
+where
+    T: RawAccess,
+    K: BinaryKey + ?Sized,
+    KeyMode: ToProofPath<K>,
+{
+    /// Returns `true` if the set contains the indicated element.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum_merkledb::{access::CopyAccessExt, TemporaryDB, Database};
+    ///
+    /// let db = TemporaryDB::new();
+    /// let fork = db.fork();
+    /// let mut index = fork.get_proof_key_set("name");
+    /// assert!(!index.contains(&1));
+    ///
+    /// index.insert(&1);
+    /// assert!(index.contains(&1));
+    /// ```
+    pub fn contains(&self, item: &K) -> bool {
+        self.base.contains(item)
+    }
+
+    /// Returns the proof of membership or non-membership for the specified element.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum_merkledb::{access::CopyAccessExt, TemporaryDB, Database, ObjectHash};
+    ///
+    /// let db = TemporaryDB::new();
+    /// let fork = db.fork();
+    /// let mut index = fork.get_proof_key_set("name");
+    /// index.insert(&1_u32);
+    ///
+    /// let proof = index.get_proof(1);
+    /// let checked_proof = proof.check_against_hash(index.object_hash()).unwrap();
+    /// assert_eq!(checked_proof.entries().count(), 1);
+    ///
+    /// let proof = index.get_proof(2);
+    /// let checked_proof = proof.check_against_hash(index.object_hash()).unwrap();
+    /// assert_eq!(checked_proof.missing_keys().collect::<Vec<_>>(), vec![&2]);
+    /// ```
+    pub fn get_proof(&self, item: K::Owned) -> MapProof<K::Owned, (), KeyMode> {
+        self.base.get_proof(item)
+    }
+
+    /// Returns the combined proof of membership or non-membership for the multiple
+    /// specified elements.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum_merkledb::{access::CopyAccessExt, TemporaryDB, Database};
+    ///
+    /// let db = TemporaryDB::new();
+    /// let fork = db.fork();
+    /// let index = fork.get_proof_key_set::<_, u32>("name");
+    /// let proof = index.get_multiproof(vec![1, 2, 3]);
+    /// ```
+    pub fn get_multiproof<KI>(&self, items: KI) -> MapProof<K::Owned, (), KeyMode>
+    where
+        KI: IntoIterator<Item = K::Owned>,
+    {
+        self.base.get_multiproof(items)
+    }
+
+    /// Returns an iterator over set elements. Unlike `KeySetIndex`, elements are ordered
+    /// by their `BinaryKey` serialization rather than by their paths in the Merkle tree.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum_merkledb::{access::CopyAccessExt, TemporaryDB, Database};
+    ///
+    /// let db = TemporaryDB::new();
+    /// let fork = db.fork();
+    /// let index = fork.get_proof_key_set::<_, u8>("name");
+    ///
+    /// for val in index.iter() {
+    ///     println!("{}", val);
+    /// }
+    /// ```
+    pub fn iter(&self) -> Keys<'_, K> {
+        self.base.keys()
+    }
+
+    /// Returns an iterator over set elements starting from the specified element.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum_merkledb::{access::CopyAccessExt, TemporaryDB, Database};
+    ///
+    /// let db = TemporaryDB::new();
+    /// let fork = db.fork();
+    /// let index = fork.get_proof_key_set::<_, u8>("name");
+    ///
+    /// for val in index.iter_from(&2) {
+    ///     println!("{}", val);
+    /// }
+    /// ```
+    pub fn iter_from(&self, from: &K) -> Keys<'_, K> {
+        self.base.keys_from(from)
+    }
+}
+
+impl<T, K, KeyMode> ProofKeySetIndex<T, K, KeyMode>
+where
+    T: RawAccessMut,
+    K: BinaryKey + ?Sized,
+    KeyMode: ToProofPath<K>,
+{
+    /// Adds an element to the set.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum_merkledb::{access::CopyAccessExt, TemporaryDB, Database};
+    ///
+    /// let db = TemporaryDB::new();
+    /// let fork = db.fork();
+    /// let mut index = fork.get_proof_key_set("name");
+    ///
+    /// index.insert(&1);
+    /// assert!(index.contains(&1));
+    /// ```
+    pub fn insert(&mut self, item: &K) {
+        self.base.put(item, ())
+    }
+
+    /// Removes an element from the set.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum_merkledb::{access::CopyAccessExt, TemporaryDB, Database};
+    ///
+    /// let db = TemporaryDB::new();
+    /// let fork = db.fork();
+    /// let mut index = fork.get_proof_key_set("name");
+    ///
+    /// index.insert(&1);
+    /// index.remove(&1);
+    /// assert!(!index.contains(&1));
+    /// ```
+    pub fn remove(&mut self, item: &K) {
+        self.base.remove(item)
+    }
+
+    /// Clears the set, removing all elements.
+    ///
+    /// # Notes
+    ///
+    /// Currently, this method is not optimized to delete a large set of data. During the execution of
+    /// this method, the amount of allocated memory is linearly dependent on the number of elements
+    /// in the index.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum_merkledb::{access::CopyAccessExt, TemporaryDB, Database};
+    ///
+    /// let db = TemporaryDB::new();
+    /// let fork = db.fork();
+    /// let mut index = fork.get_proof_key_set("name");
+    ///
+    /// index.insert(&1);
+    /// index.clear();
+    /// assert!(!index.contains(&1));
+    /// ```
+    pub fn clear(&mut self) {
+        self.base.clear()
+    }
+}
+
+/// `object_hash()` of a proof key set is defined in the same way as for a [`ProofMapIndex`]
+/// with the set elements as keys and empty values.
+///
+/// [`ProofMapIndex`]: proof_map/struct.ProofMapIndex.html#impl-ObjectHash
+impl<T, K, KeyMode> ObjectHash for ProofKeySetIndex<T, K, KeyMode>
+where
+    T: RawAccess,
+    K: BinaryKey + ?Sized,
+    KeyMode: ToProofPath<K>,
+{
+    fn object_hash(&self) -> Hash {
+        self.base.object_hash()
+    }
+}
+
+impl<'a, T, K, KeyMode> IntoIterator for &'a ProofKeySetIndex<T, K, KeyMode>
+where
+    T: RawAccess,
+    K: BinaryKey + ?Sized,
+    KeyMode: ToProofPath<K>,
+{
+    type Item = K::Owned;
+    type IntoIter = Keys<'a, K>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T, K, KeyMode> fmt::Debug for ProofKeySetIndex<T, K, KeyMode>
+where
+    T: RawAccess,
+    K: BinaryKey + ?Sized,
+    KeyMode: ToProofPath<K>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProofKeySetIndex")
+            .field("tree", &self.base)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        access::{AccessErrorKind, CopyAccessExt},
+        Database, HashTag, SystemSchema, TemporaryDB,
+    };
+
+    use exonum_crypto::PublicKey;
+
+    const INDEX_NAME: &str = "test_index_name";
+
+    #[test]
+    fn proof_key_set_methods() {
+        let db = TemporaryDB::new();
+        let fork = db.fork();
+
+        let mut index = fork.get_proof_key_set(INDEX_NAME);
+        assert_eq!(index.object_hash(), HashTag::empty_map_hash());
+        assert!(!index.contains(&1_u8));
+        index.insert(&1_u8);
+        assert!(index.contains(&1_u8));
+        index.insert(&2_u8);
+        index.insert(&3_u8);
+        assert_eq!(index.iter().collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert_eq!(index.iter_from(&2).collect::<Vec<_>>(), vec![2, 3]);
+
+        index.remove(&1_u8);
+        assert!(!index.contains(&1_u8));
+        index.clear();
+        assert!(!index.contains(&2_u8));
+        assert_eq!(index.object_hash(), HashTag::empty_map_hash());
+    }
+
+    #[test]
+    fn set_hash_matches_map_hash() {
+        let db = TemporaryDB::new();
+        let fork = db.fork();
+
+        let mut set = fork.get_proof_key_set("set");
+        let mut map = fork.get_proof_map("map");
+        for i in 0_u32..10 {
+            set.insert(&i);
+            map.put(&i, ());
+        }
+        assert_eq!(set.object_hash(), map.object_hash());
+    }
+
+    #[test]
+    fn membership_proofs() {
+        let db = TemporaryDB::new();
+        let fork = db.fork();
+
+        let mut index = fork.get_raw_proof_key_set(INDEX_NAME);
+        let keys: Vec<_> = (0_u8..5).map(|i| PublicKey::new([i; 32])).collect();
+        for key in &keys[..3] {
+            index.insert(key);
+        }
+
+        let proof = index.get_multiproof(keys.clone());
+        let checked_proof = proof.check_against_hash(index.object_hash()).unwrap();
+        let mut present: Vec<_> = checked_proof.entries().map(|(key, _)| *key).collect();
+        present.sort();
+        assert_eq!(present, &keys[..3]);
+        let mut missing: Vec<_> = checked_proof.missing_keys().copied().collect();
+        missing.sort();
+        assert_eq!(missing, &keys[3..]);
+
+        let proof = index.get_proof(keys[4]);
+        let checked_proof = proof.check_against_hash(index.object_hash()).unwrap();
+        assert_eq!(checked_proof.entries().count(), 0);
+    }
+
+    #[test]
+    fn proof_key_set_is_aggregated() {
+        let db = TemporaryDB::new();
+        let fork = db.fork();
+        fork.get_proof_key_set(INDEX_NAME)
+            .insert(&"nonce".to_owned());
+        db.merge(fork.into_patch()).unwrap();
+
+        let snapshot = db.snapshot();
+        let index = snapshot.get_proof_key_set::<_, String>(INDEX_NAME);
+        let aggregator = SystemSchema::new(&snapshot).state_aggregator();
+        assert_eq!(
+            aggregator.get(&INDEX_NAME.to_owned()),
+            Some(index.object_hash())
+        );
+        assert_eq!(
+            aggregator.object_hash(),
+            SystemSchema::new(&snapshot).state_hash()
+        );
+    }
+
+    #[test]
+    fn proof_key_set_has_distinct_type() {
+        let db = TemporaryDB::new();
+        let fork = db.fork();
+        fork.get_proof_key_set(INDEX_NAME).insert(&1_u8);
+
+        let err = ProofMapIndex::<_, u8, ()>::from_access(&fork, INDEX_NAME.into()).unwrap_err();
+        match err.kind {
+            AccessErrorKind::WrongIndexType { expected, actual } => {
+                assert_eq!(expected, IndexType::ProofMap);
+                assert_eq!(actual, IndexType::ProofKeySet);
+            }
+            other => panic!("Unexpected error: {:?}", other),
+        }
+    }
+}
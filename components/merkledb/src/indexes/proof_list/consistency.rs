@@ -0,0 +1,307 @@
+// Copyright 2020 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Consistency proofs between two versions of an append-only `ProofListIndex`.
+
+use exonum_crypto::Hash;
+use serde_derive::*;
+
+use super::{
+    key::{ProofListKey, MAX_INDEX},
+    proof::{hash_layer, merge, HashedEntry},
+    tree_height_by_length, ListProofError, ValidationError,
+};
+use crate::HashTag;
+
+/// Proof that a `ProofListIndex` of length `old_length` is a prefix of the list
+/// of length `new_length`, i.e., that the list was only appended to between these versions.
+///
+/// The proof consists of hashes of Merkle tree nodes. Nodes corresponding to full subtrees
+/// in the old list are shared by both versions of the list, which allows to restore
+/// both the old and the new list hash from the same proof. The set of nodes in the proof
+/// is fully determined by the lengths of the lists.
+///
+/// # Workflow
+///
+/// Consistency proofs are created with the [`get_consistency_proof()`] method
+/// of `ProofListIndex`. A client which knows the trusted hashes of both versions of the list
+/// verifies the proof with [`check_against_hashes()`].
+///
+/// ```
+/// # use exonum_merkledb::{access::CopyAccessExt, Database, TemporaryDB, ObjectHash};
+/// # fn main() -> anyhow::Result<()> {
+/// let fork = { let db = TemporaryDB::new(); db.fork() };
+/// let mut list = fork.get_proof_list("index");
+/// list.extend(vec![1_u32, 2, 3]);
+/// let old_hash = list.object_hash();
+/// list.extend(vec![4_u32, 5]);
+///
+/// let proof = list.get_consistency_proof(3, 5);
+/// let checked_proof = proof.check_against_hashes(old_hash, list.object_hash())?;
+/// assert_eq!(checked_proof.old_length(), 3);
+/// assert_eq!(checked_proof.new_length(), 5);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # JSON serialization
+///
+/// `ListConsistencyProof` is serialized to JSON as an object with the following fields:
+///
+/// - `proof` is an array of `{ height: number, index: number, hash: Hash }` objects.
+/// - `old_length` is the length of the old version of the list.
+/// - `new_length` is the length of the new version of the list.
+///
+/// [`get_consistency_proof()`]: struct.ProofListIndex.html#method.get_consistency_proof
+/// [`check_against_hashes()`]: #method.check_against_hashes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ListConsistencyProof {
+    proof: Vec<HashedEntry>,
+    old_length: u64,
+    new_length: u64,
+}
+
+/// Positions of the Merkle tree nodes included into a consistency proof.
+#[derive(Debug)]
+pub(super) struct ConsistencyNodes {
+    /// Full subtrees covering the old list, which are shared by both versions of the list.
+    pub old: Vec<ProofListKey>,
+    /// Nodes necessary to restore the Merkle root of the new list in addition to `old` ones.
+    pub new: Vec<ProofListKey>,
+}
+
+impl ConsistencyNodes {
+    /// Computes node positions for lists with the specified lengths. The caller must ensure
+    /// that `old_length <= new_length`.
+    pub fn new(old_length: u64, new_length: u64) -> Self {
+        debug_assert!(old_length <= new_length);
+
+        // The old list is decomposed into full subtrees according to the binary
+        // representation of its length, from the largest subtree to the smallest one.
+        let mut old = vec![];
+        let mut offset = 0;
+        for bit in (0..64).rev() {
+            let size = 1_u64 << bit;
+            if old_length & size != 0 {
+                old.push(ProofListKey::new(bit as u8 + 1, offset >> bit));
+                offset += size;
+            }
+        }
+
+        let new_height = tree_height_by_length(new_length);
+        let mut new = vec![];
+        if old_length == 0 {
+            if new_length > 0 {
+                new.push(ProofListKey::new(new_height, 0));
+            }
+        } else {
+            // Similar to range proofs, we need the hashes to the right of the old list
+            // on each height, provided that they exist in the new list.
+            let mut right = old_length - 1;
+            let mut last_index_on_level = new_length - 1;
+            for height in 1..new_height {
+                if right % 2 == 0 && right < last_index_on_level {
+                    new.push(ProofListKey::new(height, right + 1));
+                }
+                right /= 2;
+                last_index_on_level /= 2;
+            }
+        }
+
+        Self { old, new }
+    }
+
+    /// Returns all node positions ordered by increasing key.
+    pub fn all(&self) -> Vec<ProofListKey> {
+        let mut keys: Vec<_> = self.old.iter().chain(&self.new).copied().collect();
+        keys.sort();
+        keys
+    }
+}
+
+/// Restores the Merkle root of the list with the specified length from the hashes of its nodes,
+/// which need to be ordered by increasing key.
+fn restore_root(mut hashes: Vec<HashedEntry>, length: u64) -> Result<Hash, ListProofError> {
+    let tree_height = tree_height_by_length(length);
+    if tree_height == 0 {
+        return if hashes.is_empty() {
+            Ok(Hash::zero())
+        } else {
+            Err(ListProofError::NonEmptyProof)
+        };
+    }
+
+    let mut layer = vec![];
+    let mut last_index = length - 1;
+    for height in 1..=tree_height {
+        let split_key = ProofListKey::new(height + 1, 0);
+        let split_index = hashes
+            .binary_search_by(|entry| entry.key.cmp(&split_key))
+            .unwrap_or_else(|i| i);
+        let remaining_hashes = hashes.split_off(split_index);
+
+        layer = merge(layer.into_iter(), hashes.into_iter())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| ListProofError::RedundantHash)?;
+        if height < tree_height {
+            hash_layer(&mut layer, last_index)?;
+            last_index /= 2;
+        }
+        hashes = remaining_hashes;
+    }
+
+    match layer[..] {
+        [HashedEntry { key, hash }] if key == ProofListKey::new(tree_height, 0) => Ok(hash),
+        _ => Err(ListProofError::MissingHash),
+    }
+}
+
+impl ListConsistencyProof {
+    pub(super) fn new(proof: Vec<HashedEntry>, old_length: u64, new_length: u64) -> Self {
+        Self {
+            proof,
+            old_length,
+            new_length,
+        }
+    }
+
+    /// Returns the length of the old version of the list.
+    pub fn old_length(&self) -> u64 {
+        self.old_length
+    }
+
+    /// Returns the length of the new version of the list.
+    pub fn new_length(&self) -> u64 {
+        self.new_length
+    }
+
+    /// Provides access to the proof part of the view. Used in serialization.
+    pub(crate) fn proof_unchecked(&self) -> &[HashedEntry] {
+        &self.proof
+    }
+
+    /// Verifies the correctness of the proof.
+    ///
+    /// If the proof is valid, a checked proof is returned, which contains the hashes
+    /// of both versions of the list.
+    ///
+    /// ## Errors
+    ///
+    /// An error is returned if proof is malformed. The following checks are performed:
+    ///
+    /// - The old length does not exceed the new length.
+    /// - `proof` field is ordered by increasing `(height, index)` tuple.
+    /// - `proof` contains exactly the nodes necessary to restore Merkle roots of both
+    ///   versions of the list.
+    pub fn check(&self) -> Result<CheckedListConsistencyProof, ListProofError> {
+        if self.old_length > self.new_length || self.new_length > MAX_INDEX + 1 {
+            return Err(ListProofError::OutOfBounds);
+        }
+        if !self.proof.iter().all(|entry| entry.key.is_valid()) {
+            return Err(ListProofError::OutOfBounds);
+        }
+        let hashes_ordered = self
+            .proof
+            .windows(2)
+            .all(|window| window[0].key < window[1].key);
+        if !hashes_ordered {
+            return Err(ListProofError::Unordered);
+        }
+
+        let nodes = ConsistencyNodes::new(self.old_length, self.new_length);
+        let expected_keys = nodes.all();
+        if self.proof.len() < expected_keys.len() {
+            return Err(ListProofError::MissingHash);
+        } else if self.proof.len() > expected_keys.len() {
+            return Err(ListProofError::RedundantHash);
+        }
+        let keys_match = self
+            .proof
+            .iter()
+            .zip(&expected_keys)
+            .all(|(entry, key)| entry.key == *key);
+        if !keys_match {
+            return Err(ListProofError::UnexpectedBranch);
+        }
+
+        let old_hashes = self
+            .proof
+            .iter()
+            .filter(|entry| nodes.old.contains(&entry.key))
+            .copied()
+            .collect();
+        let old_root = restore_root(old_hashes, self.old_length)?;
+        let new_root = restore_root(self.proof.clone(), self.new_length)?;
+
+        Ok(CheckedListConsistencyProof {
+            old_length: self.old_length,
+            new_length: self.new_length,
+            old_hash: HashTag::hash_list_node(self.old_length, old_root),
+            new_hash: HashTag::hash_list_node(self.new_length, new_root),
+        })
+    }
+
+    /// Verifies the correctness of the proof according to the trusted hashes of the old
+    /// and the new version of the list.
+    ///
+    /// The method is essentially a convenience wrapper around `check()`.
+    pub fn check_against_hashes(
+        &self,
+        expected_old_hash: Hash,
+        expected_new_hash: Hash,
+    ) -> Result<CheckedListConsistencyProof, ValidationError<ListProofError>> {
+        self.check()
+            .map_err(ValidationError::Malformed)
+            .and_then(|checked_proof| {
+                if checked_proof.old_hash() == expected_old_hash
+                    && checked_proof.new_hash() == expected_new_hash
+                {
+                    Ok(checked_proof)
+                } else {
+                    Err(ValidationError::UnmatchedRootHash)
+                }
+            })
+    }
+}
+
+/// Version of `ListConsistencyProof` obtained after verification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CheckedListConsistencyProof {
+    old_length: u64,
+    new_length: u64,
+    old_hash: Hash,
+    new_hash: Hash,
+}
+
+impl CheckedListConsistencyProof {
+    /// Returns the length of the old version of the list.
+    pub fn old_length(&self) -> u64 {
+        self.old_length
+    }
+
+    /// Returns the length of the new version of the list.
+    pub fn new_length(&self) -> u64 {
+        self.new_length
+    }
+
+    /// Returns the `object_hash()` of the old version of the list.
+    pub fn old_hash(&self) -> Hash {
+        self.old_hash
+    }
+
+    /// Returns the `object_hash()` of the new version of the list.
+    pub fn new_hash(&self) -> Hash {
+        self.new_hash
+    }
+}
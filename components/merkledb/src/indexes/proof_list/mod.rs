@@ -14,17 +14,21 @@
 
 //! An implementation of a Merkelized version of an array list (Merkle tree).
 
-pub use self::proof::{CheckedListProof, ListProof, ListProofError, ValidationError};
+pub use self::{
+    consistency::{CheckedListConsistencyProof, ListConsistencyProof},
+    proof::{CheckedListProof, ListProof, ListProofError, ValidationError},
+};
 
 use exonum_crypto::Hash;
 
 use std::{cmp, iter, marker::PhantomData, ops::RangeBounds};
 
-use self::{
-    key::{ProofListKey, MAX_INDEX},
+pub(crate) use self::{
+    key::{ProofListKey, HEIGHT_SHIFT, MAX_INDEX},
     proof::HashedEntry,
-    proof_builder::{BuildProof, MerkleTree},
 };
+
+use self::proof_builder::{BuildProof, MerkleTree};
 use crate::{
     access::{Access, AccessError, FromAccess},
     hash::HashTag,
@@ -33,13 +37,14 @@ use crate::{
     BinaryValue, IndexAddress, ObjectHash,
 };
 
+mod consistency;
 mod key;
 mod proof;
 mod proof_builder;
 #[cfg(test)]
 mod tests;
 
-fn tree_height_by_length(len: u64) -> u8 {
+pub(crate) fn tree_height_by_length(len: u64) -> u8 {
     if len == 0 {
         0
     } else {
@@ -150,6 +155,61 @@ where
         ProofListKey::new(self.height(), 0)
     }
 
+    fn get_stored_branch(&self, key: ProofListKey) -> Result<Hash, String> {
+        let bytes: Vec<u8> = self.base.get(&key).ok_or_else(|| {
+            format!(
+                "Tree node at height {}, index {} is missing",
+                key.height(),
+                key.index()
+            )
+        })?;
+        Hash::from_bytes(bytes.into()).map_err(|e| format!("Cannot decode tree node: {}", e))
+    }
+
+    /// Checks that the tree nodes stored in the database match the hashes recomputed
+    /// from the list elements. Returns the description of the first detected mismatch.
+    pub(crate) fn check_integrity(&self) -> Result<(), String> {
+        let len = self.len();
+        for index in 0..len {
+            let value: Vec<u8> = self
+                .base
+                .get(&ProofListKey::leaf(index))
+                .ok_or_else(|| format!("Element #{} is missing", index))?;
+            let stored_hash = self.get_stored_branch(ProofListKey::new(1, index))?;
+            if HashTag::hash_leaf(&value) != stored_hash {
+                return Err(format!(
+                    "Hash of element #{} does not match the stored hash",
+                    index
+                ));
+            }
+        }
+
+        let mut len_on_height = len;
+        for height in 1..self.height() {
+            for index in (0..len_on_height).step_by(2) {
+                let key = ProofListKey::new(height, index);
+                let left_hash = self.get_stored_branch(key)?;
+                let expected_hash = if index + 1 < len_on_height {
+                    let right_hash = self.get_stored_branch(key.as_right())?;
+                    HashTag::hash_node(&left_hash, &right_hash)
+                } else {
+                    HashTag::hash_single_node(&left_hash)
+                };
+
+                let parent = key.parent();
+                if self.get_stored_branch(parent)? != expected_hash {
+                    return Err(format!(
+                        "Tree node at height {}, index {} does not match its children",
+                        parent.height(),
+                        parent.index()
+                    ));
+                }
+            }
+            len_on_height = (len_on_height + 1) / 2;
+        }
+        Ok(())
+    }
+
     /// Returns the element at the indicated position or `None` if the indicated position
     /// is out of bounds.
     ///
@@ -309,6 +369,38 @@ where
         self.create_range_proof(range)
     }
 
+    /// Returns the proof that the first `old_len` elements of the list form a prefix
+    /// of its first `new_len` elements, i.e., that the list with `old_len` elements
+    /// was only appended to in order to obtain the list with `new_len` elements.
+    ///
+    /// The proof allows to restore `object_hash()` of both versions of the list; see
+    /// [`ListConsistencyProof`] for details.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `old_len` exceeds `new_len`, or if `new_len` exceeds the list length.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum_merkledb::{access::CopyAccessExt, TemporaryDB, Database, ObjectHash};
+    ///
+    /// let db = TemporaryDB::new();
+    /// let fork = db.fork();
+    /// let mut index = fork.get_proof_list("name");
+    /// index.extend(vec![1, 2, 3]);
+    /// let old_hash = index.object_hash();
+    /// index.extend(vec![4, 5, 6, 7]);
+    ///
+    /// let proof = index.get_consistency_proof(3, 7);
+    /// assert!(proof.check_against_hashes(old_hash, index.object_hash()).is_ok());
+    /// ```
+    ///
+    /// [`ListConsistencyProof`]: struct.ListConsistencyProof.html
+    pub fn get_consistency_proof(&self, old_len: u64, new_len: u64) -> ListConsistencyProof {
+        self.create_consistency_proof(old_len, new_len)
+    }
+
     /// Returns an iterator over the list values.
     ///
     /// # Examples
@@ -787,7 +879,7 @@ mod proto {
 
     use super::{
         key::{HEIGHT_SHIFT, MAX_INDEX},
-        HashedEntry, ListProof, ProofListKey,
+        HashedEntry, ListConsistencyProof, ListProof, ProofListKey,
     };
     use crate::{
         proto::{self, *},
@@ -884,4 +976,30 @@ mod proto {
             Ok(Self::from_raw_parts(proof, entries, pb.get_length()))
         }
     }
+
+    impl ProtobufConvert for ListConsistencyProof {
+        type ProtoStruct = proto::ListConsistencyProof;
+
+        fn to_pb(&self) -> Self::ProtoStruct {
+            let mut consistency_proof = proto::ListConsistencyProof::new();
+            let proof = self
+                .proof_unchecked()
+                .iter()
+                .map(HashedEntry::to_pb)
+                .collect();
+            consistency_proof.set_proof(RepeatedField::from_vec(proof));
+            consistency_proof.set_old_length(self.old_length());
+            consistency_proof.set_new_length(self.new_length());
+            consistency_proof
+        }
+
+        fn from_pb(mut pb: Self::ProtoStruct) -> anyhow::Result<Self> {
+            let proof = pb
+                .take_proof()
+                .into_iter()
+                .map(HashedEntry::from_pb)
+                .collect::<anyhow::Result<_>>()?;
+            Ok(Self::new(proof, pb.get_old_length(), pb.get_new_length()))
+        }
+    }
 }
@@ -37,8 +37,8 @@ use crate::{proto, ProtobufConvert};
 )]
 pub struct HashedEntry {
     #[serde(flatten)]
-    key: ProofListKey,
-    hash: Hash,
+    pub(crate) key: ProofListKey,
+    pub(crate) hash: Hash,
 }
 
 impl HashedEntry {
@@ -147,7 +147,7 @@ pub struct ListProof<V> {
 ///
 /// Iterator will yield an error if there is an equal `HashedEntry.key` present in both
 /// input iterators.
-fn merge(
+pub(super) fn merge(
     first: impl Iterator<Item = HashedEntry>,
     second: impl Iterator<Item = HashedEntry>,
 ) -> impl Iterator<Item = Result<HashedEntry, ()>> {
@@ -228,7 +228,10 @@ fn merge(
 /// # Examples
 ///
 /// See unit tests at the end of this file.
-fn hash_layer(layer: &mut Vec<HashedEntry>, last_index: u64) -> Result<(), ListProofError> {
+pub(super) fn hash_layer(
+    layer: &mut Vec<HashedEntry>,
+    last_index: u64,
+) -> Result<(), ListProofError> {
     let new_len = (layer.len() + 1) / 2;
     for i in 0..new_len {
         let x = &layer[2 * i];
@@ -18,8 +18,13 @@ use exonum_crypto::Hash;
 
 use std::ops::{Bound, RangeBounds};
 
-use super::{key::ProofListKey, tree_height_by_length, ListProof};
-use crate::BinaryValue;
+use super::{
+    consistency::{ConsistencyNodes, ListConsistencyProof},
+    key::ProofListKey,
+    proof::HashedEntry,
+    tree_height_by_length, ListProof,
+};
+use crate::{BinaryValue, HashTag};
 
 /// Encapsulation of a binary Merkle tree allowing to access its terminal and intermediate
 /// nodes.
@@ -46,6 +51,7 @@ pub trait MerkleTree<V> {
 pub trait BuildProof<V> {
     fn create_proof(&self, index: u64) -> ListProof<V>;
     fn create_range_proof(&self, indexes: impl RangeBounds<u64>) -> ListProof<V>;
+    fn create_consistency_proof(&self, old_length: u64, new_length: u64) -> ListConsistencyProof;
 }
 
 impl<V, T> BuildProof<V> for T
@@ -87,6 +93,47 @@ where
         );
         create_proof(self, from, to - 1)
     }
+
+    fn create_consistency_proof(&self, old_length: u64, new_length: u64) -> ListConsistencyProof {
+        assert!(
+            old_length <= new_length && new_length <= self.len(),
+            "Illegal list lengths: the old length is {}, the new length is {}, \
+             while the list length is {}",
+            old_length,
+            new_length,
+            self.len()
+        );
+
+        let proof = ConsistencyNodes::new(old_length, new_length)
+            .all()
+            .into_iter()
+            .map(|key| HashedEntry::new(key, prefix_node(self, key, new_length)))
+            .collect();
+        ListConsistencyProof::new(proof, old_length, new_length)
+    }
+}
+
+/// Gets the node at the specified `position` in the Merkle tree built over the first
+/// `prefix_len` elements of the `tree`.
+///
+/// Nodes corresponding to full subtrees in the prefix are the same as in the `tree`;
+/// other nodes are recomputed from their children.
+fn prefix_node<V>(tree: &impl MerkleTree<V>, position: ProofListKey, prefix_len: u64) -> Hash {
+    let height = position.height();
+    let subtree_len = if height < 2 { 1 } else { 1_u64 << (height - 1) };
+    if prefix_len == tree.len() || position.first_left_leaf_index() + subtree_len <= prefix_len {
+        return tree.node(position);
+    }
+
+    let left = ProofListKey::new(height - 1, position.index() * 2);
+    let right = ProofListKey::new(height - 1, position.index() * 2 + 1);
+    let left_hash = prefix_node(tree, left, prefix_len);
+    if right.first_left_leaf_index() < prefix_len {
+        let right_hash = prefix_node(tree, right, prefix_len);
+        HashTag::hash_node(&left_hash, &right_hash)
+    } else {
+        HashTag::hash_single_node(&left_hash)
+    }
 }
 
 /// Creates a `ListProof` for a contiguous closed range of indexes `[from, inclusive_to]`.
@@ -22,8 +22,13 @@ use serde_json::{self, json};
 
 use std::cmp;
 
-use super::{key::ProofListKey, tree_height_by_length, ListProof, ListProofError, ProofListIndex};
-use crate::{access::CopyAccessExt, BinaryValue, Database, HashTag, ObjectHash, TemporaryDB};
+use super::{
+    key::ProofListKey, tree_height_by_length, ListConsistencyProof, ListProof, ListProofError,
+    ProofListIndex,
+};
+use crate::{
+    access::CopyAccessExt, BinaryValue, Database, HashTag, ObjectHash, TemporaryDB, ValidationError,
+};
 
 const IDX_NAME: &str = "idx_name";
 
@@ -1145,6 +1150,116 @@ fn proof_with_out_of_bound_elements() {
     assert_eq!(proof.hash_ops().unwrap_err(), ListProofError::OutOfBounds);
 }
 
+#[test]
+fn consistency_proofs_for_list_prefixes() {
+    let db = TemporaryDB::new();
+    let fork = db.fork();
+    let mut index = fork.get_proof_list(IDX_NAME);
+
+    let mut hashes = vec![index.object_hash()];
+    for i in 0_u32..33 {
+        index.push(i);
+        hashes.push(index.object_hash());
+    }
+
+    for new_len in 0..hashes.len() {
+        for old_len in 0..=new_len {
+            let proof = index.get_consistency_proof(old_len as u64, new_len as u64);
+            let checked_proof = proof
+                .check_against_hashes(hashes[old_len], hashes[new_len])
+                .unwrap_or_else(|e| panic!("{} -> {}: {}", old_len, new_len, e));
+            assert_eq!(checked_proof.old_length(), old_len as u64);
+            assert_eq!(checked_proof.new_length(), new_len as u64);
+        }
+    }
+}
+
+#[test]
+fn consistency_proof_for_rewritten_list() {
+    let db = TemporaryDB::new();
+    let fork = db.fork();
+    let mut index = fork.get_proof_list(IDX_NAME);
+    index.extend(0_u32..5);
+    let old_hash = index.object_hash();
+
+    index.set(3, 100);
+    index.extend(5_u32..10);
+    let proof = index.get_consistency_proof(5, 10);
+    let checked_proof = proof.check().unwrap();
+    assert_ne!(checked_proof.old_hash(), old_hash);
+    assert_eq!(checked_proof.new_hash(), index.object_hash());
+    let err = proof
+        .check_against_hashes(old_hash, index.object_hash())
+        .unwrap_err();
+    assert!(matches!(err, ValidationError::UnmatchedRootHash));
+}
+
+#[test]
+#[should_panic(expected = "the old length is 3, the new length is 2")]
+fn consistency_proof_illegal_lengths() {
+    let db = TemporaryDB::new();
+    let fork = db.fork();
+    let mut index = fork.get_proof_list(IDX_NAME);
+    index.extend(0_u32..5);
+    index.get_consistency_proof(3, 2);
+}
+
+#[test]
+#[should_panic(expected = "while the list length is 5")]
+fn consistency_proof_exceeding_list_length() {
+    let db = TemporaryDB::new();
+    let fork = db.fork();
+    let mut index = fork.get_proof_list(IDX_NAME);
+    index.extend(0_u32..5);
+    index.get_consistency_proof(3, 6);
+}
+
+#[test]
+fn malformed_consistency_proofs() {
+    let db = TemporaryDB::new();
+    let fork = db.fork();
+    let mut index = fork.get_proof_list(IDX_NAME);
+    index.extend(0_u32..7);
+    let proof = index.get_consistency_proof(3, 7);
+
+    let mut json = serde_json::to_value(&proof).unwrap();
+    assert_eq!(json["old_length"], 3);
+    assert_eq!(json["new_length"], 7);
+    let proof_from_json: ListConsistencyProof = serde_json::from_value(json.clone()).unwrap();
+    assert_eq!(proof_from_json, proof);
+
+    json["new_length"] = 2.into();
+    let bogus_proof: ListConsistencyProof = serde_json::from_value(json.clone()).unwrap();
+    assert_eq!(
+        bogus_proof.check().unwrap_err(),
+        ListProofError::OutOfBounds
+    );
+
+    json["new_length"] = 7.into();
+    let entries = json["proof"].as_array_mut().unwrap();
+    let first_entry = entries.remove(0);
+    let bogus_proof: ListConsistencyProof = serde_json::from_value(json.clone()).unwrap();
+    assert_eq!(
+        bogus_proof.check().unwrap_err(),
+        ListProofError::MissingHash
+    );
+
+    let entries = json["proof"].as_array_mut().unwrap();
+    entries.insert(0, first_entry.clone());
+    entries.insert(0, first_entry);
+    let bogus_proof: ListConsistencyProof = serde_json::from_value(json.clone()).unwrap();
+    assert_eq!(bogus_proof.check().unwrap_err(), ListProofError::Unordered);
+
+    let entries = json["proof"].as_array_mut().unwrap();
+    entries.remove(0);
+    entries.push(json!({ "height": 4, "index": 0, "hash": Hash::zero() }));
+    let bogus_proof: ListConsistencyProof = serde_json::from_value(json.clone()).unwrap();
+    assert_eq!(
+        bogus_proof.check().unwrap_err(),
+        ListProofError::RedundantHash
+    );
+}
+
 mod root_hash {
     use crate::{
         access::CopyAccessExt, hash::HashTag, BinaryValue, Database, ObjectHash, TemporaryDB,
@@ -14,7 +14,7 @@
 
 //! An implementation of a Merkelized version of a map (Merkle Patricia tree).
 
-pub(crate) use self::key::{BitsRange, ProofPath};
+pub(crate) use self::key::{BitsRange, ChildKind, ProofPath};
 pub use self::{
     key::{Hashed, Raw, RawKey, ToProofPath, KEY_SIZE as PROOF_MAP_KEY_SIZE, PROOF_PATH_SIZE},
     proof::{CheckedMapProof, MapProof, MapProofError, ValidationError},
@@ -25,7 +25,7 @@ use exonum_crypto::Hash;
 use std::{fmt, io, marker::PhantomData};
 
 use self::{
-    key::{ChildKind, VALUE_KEY_PREFIX},
+    key::{BRANCH_KEY_PREFIX, LEAF_KEY_PREFIX, VALUE_KEY_PREFIX},
     node::{BranchNode, Node},
     proof_builder::{BuildProof, MerklePatriciaTree},
 };
@@ -202,6 +202,72 @@ where
         }
     }
 
+    fn get_stored_node<N: BinaryValue>(&self, path: &ProofPath) -> Result<N, String> {
+        let bytes: Vec<u8> = self
+            .base
+            .get(path)
+            .ok_or_else(|| "Tree node referenced by its parent is missing".to_owned())?;
+        N::from_bytes(bytes.into()).map_err(|e| format!("Cannot decode tree node: {}", e))
+    }
+
+    /// Checks that the tree nodes stored in the database match the hashes recomputed
+    /// from the map values. Returns the description of the first detected mismatch.
+    ///
+    /// Since the key transform is not known for untyped data, leaves are matched with values
+    /// by their hashes rather than by keys.
+    pub(crate) fn check_integrity(&self) -> Result<(), String> {
+        let mut leaf_hashes = vec![];
+        let mut branch_count = 0_usize;
+        let mut stack: Vec<(ProofPath, Option<Hash>)> = self
+            .get_root_path()
+            .map(|path| (path, None))
+            .into_iter()
+            .collect();
+
+        while let Some((path, expected_hash)) = stack.pop() {
+            let actual_hash = if path.is_leaf() {
+                let hash: Hash = self.get_stored_node(&path)?;
+                leaf_hashes.push(hash);
+                hash
+            } else {
+                let branch: BranchNode = self.get_stored_node(&path)?;
+                branch_count += 1;
+                for &kind in &[ChildKind::Left, ChildKind::Right] {
+                    stack.push((branch.child_path(kind), Some(branch.child_hash(kind))));
+                }
+                branch.object_hash()
+            };
+
+            if expected_hash.map_or(false, |hash| hash != actual_hash) {
+                return Err("Tree node does not match the hash stored in its parent".to_owned());
+            }
+        }
+
+        let stored_branch_count = self
+            .base
+            .iter::<_, Vec<u8>, Vec<u8>>(&BRANCH_KEY_PREFIX)
+            .count();
+        let stored_leaf_count = self
+            .base
+            .iter::<_, Vec<u8>, Vec<u8>>(&LEAF_KEY_PREFIX)
+            .count();
+        if stored_branch_count != branch_count || stored_leaf_count != leaf_hashes.len() {
+            return Err("Storage contains tree nodes unreachable from the root".to_owned());
+        }
+
+        let mut value_hashes: Vec<_> = self
+            .base
+            .iter::<_, Vec<u8>, Vec<u8>>(&VALUE_KEY_PREFIX)
+            .map(|(_, value)| HashTag::hash_leaf(&value))
+            .collect();
+        leaf_hashes.sort_unstable();
+        value_hashes.sort_unstable();
+        if leaf_hashes != value_hashes {
+            return Err("Leaf hashes do not match the stored values".to_owned());
+        }
+        Ok(())
+    }
+
     /// Returns a value corresponding to the key.
     ///
     /// # Examples
@@ -17,7 +17,11 @@
 //! The given section contains methods related to `SparseListIndex` and iterators
 //! over the items of this index.
 
-use std::{io::Error, marker::PhantomData};
+use std::{
+    io::Error,
+    marker::PhantomData,
+    ops::{Bound, RangeBounds},
+};
 
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 
@@ -354,6 +358,63 @@ where
         v
     }
 
+    /// Removes elements with the indexes in the specified range from the list. The capacity
+    /// of the list is not changed.
+    ///
+    /// # Notes
+    ///
+    /// The elements are removed with a single range tombstone in the fork, without writing
+    /// a separate change for each element. The indexes of the removed elements are still
+    /// iterated over to update the length of the list.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use exonum_merkledb::{access::CopyAccessExt, TemporaryDB, Database, SparseListIndex};
+    ///
+    /// let db = TemporaryDB::new();
+    /// let fork = db.fork();
+    /// let mut index = fork.get_sparse_list("name");
+    ///
+    /// index.extend([1, 2, 3, 4, 5].iter().cloned());
+    /// index.remove(2);
+    /// index.remove_range(1..4);
+    /// assert_eq!(index.iter().collect::<Vec<_>>(), vec![(0, 1), (4, 5)]);
+    /// assert_eq!(2, index.len());
+    /// assert_eq!(5, index.capacity());
+    /// ```
+    pub fn remove_range<R>(&mut self, range: R)
+    where
+        R: RangeBounds<u64>,
+    {
+        let mut size = self.size();
+        let start = match range.start_bound() {
+            Bound::Included(&index) => index,
+            Bound::Excluded(&index) => index + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&index) => index + 1,
+            Bound::Excluded(&index) => index,
+            Bound::Unbounded => size.capacity,
+        };
+        let end = end.min(size.capacity);
+        if start >= end {
+            return;
+        }
+
+        let removed_count = self
+            .iter_from(start)
+            .skip_values()
+            .take_while(|&index| index < end)
+            .count() as u64;
+        if removed_count > 0 {
+            self.base.remove_range(start..end);
+            size.length -= removed_count;
+            self.set_size(size);
+        }
+    }
+
     /// Extends the list with the contents of an iterator.
     ///
     /// # Examples
@@ -623,6 +684,42 @@ mod tests {
         assert_eq!(list_index.values().collect::<Vec<_>>(), vec![1_u8, 2, 3]);
     }
 
+    #[test]
+    fn test_list_index_remove_range() {
+        let db = TemporaryDB::default();
+        let fork = db.fork();
+        let mut list_index = fork.get_sparse_list(IDX_NAME);
+        list_index.extend(0_u8..10);
+        list_index.remove(3);
+        drop(list_index);
+        db.merge(fork.into_patch()).unwrap();
+
+        let fork = db.fork();
+        let mut list_index = fork.get_sparse_list::<_, u8>(IDX_NAME);
+        list_index.remove_range(2..5);
+        assert_eq!(list_index.len(), 7);
+        assert_eq!(list_index.capacity(), 10);
+        assert_eq!(
+            list_index.indexes().collect::<Vec<_>>(),
+            vec![0, 1, 5, 6, 7, 8, 9]
+        );
+
+        list_index.set(3, 33);
+        list_index.remove_range(8..);
+        list_index.remove_range(20..30);
+        assert_eq!(list_index.len(), 6);
+        assert_eq!(list_index.capacity(), 10);
+        drop(list_index);
+        db.merge(fork.into_patch()).unwrap();
+
+        let snapshot = db.snapshot();
+        let list_index = snapshot.get_sparse_list::<_, u8>(IDX_NAME);
+        assert_eq!(
+            list_index.iter().collect::<Vec<_>>(),
+            vec![(0, 0), (1, 1), (3, 33), (5, 5), (6, 6), (7, 7)]
+        );
+    }
+
     #[test]
     fn restore_after_no_op_initialization() {
         let db = TemporaryDB::new();
@@ -0,0 +1,358 @@
+// Copyright 2020 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Integrity checks of the database contents.
+
+use exonum_crypto::Hash;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    views::{
+        check_index_integrity, get_object_hash, get_state_aggregator, IndexAddress, IndexType,
+        IndexesPool, SystemSchema, STATE_AGGREGATOR,
+    },
+    ResolvedAddress, Snapshot,
+};
+
+/// Report produced by an integrity check of the database.
+///
+/// Reports are obtained with [`DatabaseExt::check_integrity()`].
+///
+/// [`DatabaseExt::check_integrity()`]: trait.DatabaseExt.html#method.check_integrity
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct IntegrityReport {
+    /// Number of checked Merkelized indexes.
+    pub checked_indexes: usize,
+    /// Indexes for which the check has failed.
+    pub corrupted_indexes: Vec<CorruptedIndex>,
+    /// State hash of the database as per [`SystemSchema::state_hash()`]. The hash can only
+    /// be trusted if the state aggregator is not corrupted.
+    ///
+    /// [`SystemSchema::state_hash()`]: struct.SystemSchema.html#method.state_hash
+    pub state_hash: Hash,
+}
+
+impl IntegrityReport {
+    /// Returns `true` if no corrupted indexes were found.
+    pub fn is_ok(&self) -> bool {
+        self.corrupted_indexes.is_empty()
+    }
+}
+
+/// Information about an index that has failed the integrity check.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct CorruptedIndex {
+    /// Name of the index. For indexes in a group, this is the name of the group.
+    pub name: String,
+    /// Hex-encoded key of the index in the group, or `None` if the index does not belong
+    /// to a group.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group_key: Option<String>,
+    /// Is the index in migration?
+    pub in_migration: bool,
+    /// Type of the index, or `None` if the index metadata is missing.
+    pub index_type: Option<IndexType>,
+    /// Human-readable description of the detected corruption.
+    pub reason: String,
+}
+
+impl CorruptedIndex {
+    fn new(qualified_name: &[u8], index_type: Option<IndexType>, reason: String) -> Self {
+        let addr = IndexAddress::from_fully_qualified_name(qualified_name);
+        Self {
+            name: addr.name().to_owned(),
+            group_key: addr.id_in_group().map(hex::encode),
+            in_migration: addr.is_in_migration(),
+            index_type,
+            reason,
+        }
+    }
+}
+
+/// Checks Merkle trees of all Merkelized indexes in the snapshot and aggregation
+/// of the index hashes into the state hash.
+pub(crate) fn check_integrity(snapshot: &dyn Snapshot) -> IntegrityReport {
+    let pool = IndexesPool::new(snapshot);
+    let mut checked_indexes = 0;
+    let mut corrupted_indexes = vec![];
+
+    for (qualified_name, metadata) in pool.indexes() {
+        let index_type = metadata.index_type();
+        if !index_type.is_merkelized() {
+            continue;
+        }
+        checked_indexes += 1;
+        if let Err(reason) = check_index_integrity(snapshot, qualified_name.clone(), metadata) {
+            corrupted_indexes.push(CorruptedIndex::new(
+                &qualified_name,
+                Some(index_type),
+                reason,
+            ));
+        }
+    }
+
+    // Hashes of corrupted indexes cannot be computed reliably, thus, such indexes
+    // are skipped when checking aggregation. If a state aggregator itself is corrupted,
+    // the aggregation check is skipped for its namespace.
+    let is_aggregator_corrupted = |corrupted: &[CorruptedIndex], namespace: &str| {
+        let group_key = hex::encode(namespace);
+        corrupted.iter().any(|index| {
+            index.name == STATE_AGGREGATOR && index.group_key.as_ref() == Some(&group_key)
+        })
+    };
+
+    // Each aggregated index should have an up-to-date entry in the aggregator
+    // of its namespace.
+    let merkelized_indexes = pool
+        .indexes()
+        .filter(|(_, metadata)| metadata.index_type().is_merkelized());
+    for (qualified_name, metadata) in merkelized_indexes {
+        let addr = IndexAddress::from_fully_qualified_name(&qualified_name);
+        // Indexes in groups (including aggregators themselves) are not aggregated.
+        if addr.id_in_group().is_some()
+            || is_aggregator_corrupted(&corrupted_indexes, addr.namespace())
+        {
+            continue;
+        }
+        let is_index_corrupted = corrupted_indexes.iter().any(|index| {
+            index.name == addr.name()
+                && index.group_key.is_none()
+                && index.in_migration == addr.is_in_migration()
+        });
+        if is_index_corrupted {
+            continue;
+        }
+
+        let aggregator = get_state_aggregator(snapshot, addr.namespace());
+        let reason = match aggregator.get(addr.name()) {
+            Some(hash) => {
+                let address = ResolvedAddress::new(addr.name(), Some(metadata.identifier()));
+                if get_object_hash(snapshot, address, addr.is_in_migration()) == hash {
+                    continue;
+                }
+                "Index hash does not match the state aggregator"
+            }
+            None => "Index is missing from the state aggregator",
+        };
+        corrupted_indexes.push(CorruptedIndex::new(
+            &qualified_name,
+            Some(metadata.index_type()),
+            reason.to_owned(),
+        ));
+    }
+
+    // Conversely, each entry in an aggregator should correspond to an existing index.
+    let namespaces = pool.indexes().filter_map(|(qualified_name, _)| {
+        let addr = IndexAddress::from_fully_qualified_name(&qualified_name);
+        if addr.name() == STATE_AGGREGATOR && !addr.is_in_migration() {
+            addr.id_in_group()
+                .map(|namespace| String::from_utf8_lossy(namespace).into_owned())
+        } else {
+            None
+        }
+    });
+    let namespaces: Vec<_> = namespaces.collect();
+    for namespace in namespaces {
+        if is_aggregator_corrupted(&corrupted_indexes, &namespace) {
+            continue;
+        }
+        let aggregator = get_state_aggregator(snapshot, &namespace);
+        for name in aggregator.keys() {
+            let mut addr = IndexAddress::from_root(&name);
+            if !namespace.is_empty() {
+                addr.set_in_migration();
+            }
+            let qualified_name = addr.fully_qualified_name();
+            let is_aggregated = pool
+                .index_metadata(&qualified_name)
+                .map_or(false, |metadata| metadata.index_type().is_merkelized());
+            if !is_aggregated {
+                corrupted_indexes.push(CorruptedIndex::new(
+                    &qualified_name,
+                    None,
+                    "Aggregated index is missing or is not Merkelized".to_owned(),
+                ));
+            }
+        }
+    }
+
+    IntegrityReport {
+        checked_indexes,
+        corrupted_indexes,
+        state_hash: SystemSchema::new(snapshot).state_hash(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use exonum_crypto::Hash;
+
+    use crate::{
+        access::{AccessExt, CopyAccessExt},
+        migration::Migration,
+        views::{get_state_aggregator, IndexesPool, View},
+        Database, DatabaseExt, Fork, IndexType, ResolvedAddress, TemporaryDB,
+    };
+
+    fn raw_view<'a>(fork: &'a Fork, name: &str) -> View<&'a Fork> {
+        let id = IndexesPool::new(fork)
+            .index_metadata(name.as_bytes())
+            .unwrap()
+            .identifier();
+        View::new(fork, ResolvedAddress::new(name, Some(id)))
+    }
+
+    fn create_db() -> TemporaryDB {
+        let db = TemporaryDB::new();
+        let fork = db.fork();
+        fork.get_proof_list("list").extend(vec![1_u32, 2, 3, 4, 5]);
+        {
+            let mut map = fork.get_proof_map("map");
+            for i in 0_u64..10 {
+                map.put(&i, i.to_string());
+            }
+        }
+        fork.get_proof_entry("entry").set(42_u64);
+        fork.get_list("not_merkelized").push(1_u8);
+        db.merge(fork.into_patch()).unwrap();
+        db
+    }
+
+    #[test]
+    fn integrity_check_for_intact_database() {
+        let db = create_db();
+        let report = db.check_integrity();
+        assert!(report.is_ok(), "{:?}", report);
+        // The state aggregator is checked together with user indexes.
+        assert_eq!(report.checked_indexes, 4);
+    }
+
+    #[test]
+    fn integrity_check_with_corrupted_list() {
+        let db = create_db();
+        let fork = db.fork();
+        // Overwrite the element #3 of the list bypassing the index.
+        raw_view(&fork, "list").put(&3_u64, 100_u32);
+        db.merge(fork.into_patch()).unwrap();
+
+        let report = db.check_integrity();
+        assert_eq!(report.corrupted_indexes.len(), 1);
+        let corrupted = &report.corrupted_indexes[0];
+        assert_eq!(corrupted.name, "list");
+        assert_eq!(corrupted.index_type, Some(IndexType::ProofList));
+        assert!(
+            corrupted.reason.contains("element #3"),
+            "{}",
+            corrupted.reason
+        );
+    }
+
+    #[test]
+    fn integrity_check_with_corrupted_list_branch() {
+        let db = create_db();
+        let fork = db.fork();
+        // Overwrite the tree node at height 2, index 1 (i.e., the hash of elements #2 and #3).
+        let key = (2_u64 << 56) + 1;
+        raw_view(&fork, "list").put(&key, Hash::zero());
+        db.merge(fork.into_patch()).unwrap();
+
+        let report = db.check_integrity();
+        assert_eq!(report.corrupted_indexes.len(), 1);
+        let reason = &report.corrupted_indexes[0].reason;
+        assert!(reason.contains("height 2, index 1"), "{}", reason);
+    }
+
+    #[test]
+    fn integrity_check_with_corrupted_map() {
+        let db = create_db();
+        let fork = db.fork();
+        // Overwrite the value for key 5 bypassing the index.
+        let mut value_path = vec![2_u8];
+        value_path.extend_from_slice(&5_u64.to_be_bytes());
+        raw_view(&fork, "map").put(&value_path, "!".to_owned());
+        db.merge(fork.into_patch()).unwrap();
+
+        let report = db.check_integrity();
+        assert_eq!(report.corrupted_indexes.len(), 1);
+        let corrupted = &report.corrupted_indexes[0];
+        assert_eq!(corrupted.name, "map");
+        assert_eq!(corrupted.index_type, Some(IndexType::ProofMap));
+    }
+
+    #[test]
+    fn integrity_check_with_removed_map_value() {
+        let db = create_db();
+        let fork = db.fork();
+        let mut value_path = vec![2_u8];
+        value_path.extend_from_slice(&0_u64.to_be_bytes());
+        raw_view(&fork, "map").remove(&value_path);
+        db.merge(fork.into_patch()).unwrap();
+
+        let report = db.check_integrity();
+        assert_eq!(report.corrupted_indexes.len(), 1);
+        assert_eq!(report.corrupted_indexes[0].name, "map");
+    }
+
+    #[test]
+    fn integrity_check_with_index_missing_from_aggregator() {
+        let db = create_db();
+        let fork = db.fork();
+        get_state_aggregator(&fork, "").remove("entry");
+        db.merge(fork.into_patch()).unwrap();
+
+        let report = db.check_integrity();
+        assert_eq!(report.corrupted_indexes.len(), 1);
+        let corrupted = &report.corrupted_indexes[0];
+        assert_eq!(corrupted.name, "entry");
+        assert_eq!(corrupted.index_type, Some(IndexType::ProofEntry));
+        assert!(corrupted.reason.contains("missing"), "{}", corrupted.reason);
+    }
+
+    #[test]
+    fn integrity_check_with_stale_aggregator_entry() {
+        let db = create_db();
+        let fork = db.fork();
+        get_state_aggregator(&fork, "").put("removed", Hash::zero());
+        db.merge(fork.into_patch()).unwrap();
+
+        let report = db.check_integrity();
+        assert_eq!(report.corrupted_indexes.len(), 1);
+        let corrupted = &report.corrupted_indexes[0];
+        assert_eq!(corrupted.name, "removed");
+        assert_eq!(corrupted.index_type, None);
+    }
+
+    #[test]
+    fn integrity_check_with_migration() {
+        let db = create_db();
+        let fork = db.fork();
+        Migration::new("test", &fork)
+            .get_proof_list("list")
+            .extend(vec![1_u32, 2]);
+        db.merge(fork.into_patch()).unwrap();
+        let report = db.check_integrity();
+        assert!(report.is_ok(), "{:?}", report);
+
+        let fork = db.fork();
+        get_state_aggregator(&fork, "test").remove("test.list");
+        db.merge(fork.into_patch()).unwrap();
+        let report = db.check_integrity();
+        assert_eq!(report.corrupted_indexes.len(), 1);
+        let corrupted = &report.corrupted_indexes[0];
+        assert_eq!(corrupted.name, "test.list");
+        assert!(corrupted.in_migration);
+    }
+}
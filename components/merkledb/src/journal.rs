@@ -0,0 +1,432 @@
+// Copyright 2020 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Write-ahead journal grouping several patches into a single durable unit.
+//!
+//! Changes made within the journal are staged in memory until the journal is committed
+//! or explicitly flushed. Flushed changes are written to the database together with
+//! the changes reverting them; the reverse changes are stored in a system view mapping
+//! the sequence number of each flushed patch (encoded as a big-endian `u64`) to these changes,
+//! using the same format as entries in the database history. Committing the journal writes
+//! the remaining staged changes and clears the view in a single atomic merge; rolling it back
+//! applies reverse changes from the newest flushed patch to the oldest one, removing each
+//! applied entry atomically with its changes. Thus, the rollback may be safely interrupted
+//! and resumed.
+
+use byteorder::{BigEndian, ByteOrder};
+
+use std::fmt;
+
+use crate::{
+    backends::encrypted::is_encrypted,
+    db::{reverse_changes, Change, ViewChanges},
+    history,
+    views::ResolvedAddress,
+    Database, Error, Fork, Patch, Result,
+};
+
+/// Name of the system view with reverse changes for patches flushed within the journal.
+const JOURNAL_NAME: &str = "__JOURNAL__";
+
+fn journal_address() -> ResolvedAddress {
+    ResolvedAddress::system(JOURNAL_NAME)
+}
+
+/// Returns `true` if the database contains an unfinished journal.
+fn has_entries<D: Database + ?Sized>(db: &D) -> bool {
+    let snapshot = db.snapshot();
+    let mut iter = snapshot.iter(&journal_address(), &[]);
+    iter.next().is_some()
+}
+
+/// Journal grouping several patches into a single durable unit.
+///
+/// Changes are made in the [fork] of the journal and are not visible in the database
+/// until the journal is [committed]. The commit is atomic: concurrent readers observe either
+/// the state before the journal or the state with all its changes.
+///
+/// If the staged changes are too large to be kept in memory, they may be [flushed]
+/// to the database. Flushed changes become visible to database readers immediately,
+/// but are still reverted if the journal is not committed: either when the journal
+/// is [rolled back] or dropped, or by [`DatabaseExt::recover_journal()`] if the process
+/// has crashed before the journal was finished. [`RocksDB::open()`] recovers unfinished
+/// journals automatically.
+///
+/// Journals are created with [`DatabaseExt::begin_journal()`].
+///
+/// # Safety
+///
+/// It is logically unsound to merge patches to the database bypassing the journal while
+/// the journal has flushed changes, or to have several active journals for the same database.
+/// Such patches will be partially reverted if the journal is rolled back.
+///
+/// # Examples
+///
+/// ```
+/// # use exonum_merkledb::{access::CopyAccessExt, Database, DatabaseExt, TemporaryDB};
+/// let db = TemporaryDB::new();
+/// let mut journal = db.begin_journal().unwrap();
+/// journal.fork().get_list("list").push(1_u32);
+/// // Flushed changes are visible in the database...
+/// journal.flush().unwrap();
+/// assert_eq!(db.snapshot().get_list::<_, u32>("list").len(), 1);
+/// // ...but are reverted together with the rest of the journal.
+/// journal.fork().get_list("list").push(2_u32);
+/// journal.rollback().unwrap();
+/// assert!(db.snapshot().get_list::<_, u32>("list").is_empty());
+///
+/// let journal = db.begin_journal().unwrap();
+/// journal.fork().get_list("list").push(3_u32);
+/// // Staged changes are not visible until the journal is committed.
+/// assert!(db.snapshot().get_list::<_, u32>("list").is_empty());
+/// journal.commit().unwrap();
+/// assert_eq!(db.snapshot().get_list::<_, u32>("list").len(), 1);
+/// ```
+///
+/// [fork]: #method.fork
+/// [committed]: #method.commit
+/// [flushed]: #method.flush
+/// [rolled back]: #method.rollback
+/// [`DatabaseExt::recover_journal()`]: trait.DatabaseExt.html#method.recover_journal
+/// [`DatabaseExt::begin_journal()`]: trait.DatabaseExt.html#method.begin_journal
+/// [`RocksDB::open()`]: struct.RocksDB.html#method.open
+pub struct Journal<'a, D: Database + ?Sized> {
+    db: &'a D,
+    // Only equals `None` during flushes and after the journal is finished.
+    fork: Option<Fork>,
+    flushed_patches: u64,
+}
+
+impl<D: Database + ?Sized> fmt::Debug for Journal<'_, D> {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("Journal")
+            .field("flushed_patches", &self.flushed_patches)
+            .finish()
+    }
+}
+
+impl<'a, D: Database + ?Sized> Journal<'a, D> {
+    pub(crate) fn new(db: &'a D) -> Result<Self> {
+        if has_entries(db) {
+            return Err(Error::new(
+                "Database contains an unfinished journal; it should be recovered first",
+            ));
+        }
+        Ok(Self {
+            db,
+            fork: Some(db.fork()),
+            flushed_patches: 0,
+        })
+    }
+
+    /// Returns the fork with the changes staged in the journal. The fork is based
+    /// on the database state including the flushed changes.
+    pub fn fork(&self) -> &Fork {
+        // `unwrap` is safe due to the way we define `fork`
+        self.fork.as_ref().unwrap()
+    }
+
+    /// Returns the number of patches flushed within the journal.
+    pub fn flushed_patches(&self) -> u64 {
+        self.flushed_patches
+    }
+
+    /// Writes the staged changes to the database. The changes reverting them are written
+    /// atomically together with the changes. After the flush, the changes are visible
+    /// to database readers.
+    ///
+    /// # Performance notes
+    ///
+    /// Reverse changes are computed in the same way as the backup patch
+    /// in [`DatabaseExt::merge_with_backup()`] and have the same complexity.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error in the same situations as `Database::merge()`. In this case,
+    /// the staged changes are lost, and the journal should be rolled back.
+    ///
+    /// [`DatabaseExt::merge_with_backup()`]: trait.DatabaseExt.html#method.merge_with_backup
+    pub fn flush(&mut self) -> Result<()> {
+        let mut patch = self.fork.take().unwrap().into_patch();
+        let snapshot = self.db.snapshot();
+        let rev_changes: Vec<_> = reverse_changes(snapshot.as_ref(), &patch)
+            .into_iter()
+            .map(|(address, changes)| (address, changes.into_data()))
+            .collect();
+        let entry = history::encode(rev_changes.iter().map(|(address, data)| (address, data)));
+        patch.put_change(
+            journal_address(),
+            history::version_key(self.flushed_patches).to_vec(),
+            Change::Put(entry),
+        );
+
+        let res = self.db.merge(patch);
+        self.fork = Some(self.db.fork());
+        res?;
+        self.flushed_patches += 1;
+        Ok(())
+    }
+
+    /// Commits the journal, making all its changes permanent. The staged changes are written
+    /// atomically together with finishing the journal; the commit is synced to the disk.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error in the same situations as `Database::merge_sync()`. In this case,
+    /// the journal remains unfinished and its flushed changes will be rolled back on recovery.
+    pub fn commit(mut self) -> Result<()> {
+        let mut patch = self.fork.take().unwrap().into_patch();
+        patch.clear_view(journal_address());
+        self.db.merge_sync(patch)
+    }
+
+    /// Rolls back the journal, discarding the staged changes and reverting all flushed ones.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the journal entries cannot be decoded, or in the same situations
+    /// as `Database::merge()`. In this case, the journal remains unfinished, and the rollback
+    /// may be resumed with [`DatabaseExt::recover_journal()`].
+    ///
+    /// [`DatabaseExt::recover_journal()`]: trait.DatabaseExt.html#method.recover_journal
+    pub fn rollback(mut self) -> Result<()> {
+        self.fork = None;
+        rollback(self.db).map(drop)
+    }
+}
+
+impl<D: Database + ?Sized> Drop for Journal<'_, D> {
+    fn drop(&mut self) {
+        if self.fork.take().is_none() || self.flushed_patches == 0 {
+            return;
+        }
+        // If the rollback fails, the journal remains in the database and will be
+        // rolled back on recovery.
+        if let Err(err) = rollback(self.db) {
+            log::error!(
+                "Failed to roll back the dropped journal: {}. The journal will be rolled back \
+                 on the database recovery",
+                err
+            );
+        }
+    }
+}
+
+/// Recovers the unfinished journal when the database is opened. The recovery is skipped
+/// for encrypted databases; it is performed by the `EncryptedDB` wrapper instead.
+pub(crate) fn recover_on_open<D: Database + ?Sized>(db: &D) -> Result<()> {
+    if !is_encrypted(db.snapshot().as_ref()) {
+        rollback(db)?;
+    }
+    Ok(())
+}
+
+/// Reverts all patches recorded in the journal. Returns the number of reverted patches.
+pub(crate) fn rollback<D: Database + ?Sized>(db: &D) -> Result<usize> {
+    let address = journal_address();
+    let entries = {
+        let snapshot = db.snapshot();
+        let mut iter = snapshot.iter(&address, &[]);
+        let mut entries = vec![];
+        while let Some((key, value)) = iter.next() {
+            entries.push((BigEndian::read_u64(key), history::decode(value)?));
+        }
+        entries
+    };
+
+    let reverted_patches = entries.len();
+    for (index, entry) in entries.into_iter().rev() {
+        let changes = entry
+            .into_iter()
+            .map(|(address, data)| (address, ViewChanges::from_data(data)))
+            .collect();
+        let mut patch = Patch::from_changes(db.snapshot(), changes);
+        patch.put_change(
+            address.clone(),
+            history::version_key(index).to_vec(),
+            Change::Delete,
+        );
+        db.merge(patch)?;
+    }
+    Ok(reverted_patches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use exonum_crypto::aead;
+    use tempfile::TempDir;
+
+    use crate::{
+        access::CopyAccessExt, DatabaseExt, DbOptions, EncryptedDB, EncryptionOptions, ObjectHash,
+        RocksDB, SystemSchema, TemporaryDB,
+    };
+
+    fn list_contents<D: Database + ?Sized>(db: &D) -> Vec<u32> {
+        db.snapshot().get_proof_list("list").iter().collect()
+    }
+
+    fn stage_values<D: Database + ?Sized>(journal: &Journal<'_, D>, values: &[u32]) {
+        let fork = journal.fork();
+        fork.get_proof_list("list").extend(values.iter().copied());
+        fork.get_map("map").put(&values[0], values.len() as u64);
+    }
+
+    fn flush_values<D: Database + ?Sized>(journal: &mut Journal<'_, D>, values: &[u32]) {
+        stage_values(journal, values);
+        journal.flush().unwrap();
+    }
+
+    #[test]
+    fn journal_commit() {
+        let db = TemporaryDB::new();
+        let mut journal = db.begin_journal().unwrap();
+        flush_values(&mut journal, &[1, 2]);
+        flush_values(&mut journal, &[3]);
+        stage_values(&journal, &[4]);
+        assert_eq!(journal.flushed_patches(), 2);
+        assert_eq!(list_contents(&db), vec![1, 2, 3]);
+        journal.commit().unwrap();
+
+        assert_eq!(list_contents(&db), vec![1, 2, 3, 4]);
+        assert!(!has_entries(&db));
+        assert_eq!(db.recover_journal().unwrap(), 0);
+        assert_eq!(list_contents(&db), vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn staged_changes_are_committed_atomically() {
+        let db = TemporaryDB::new();
+        let journal = db.begin_journal().unwrap();
+        stage_values(&journal, &[1, 2]);
+        stage_values(&journal, &[3]);
+        // Staged changes are invisible to database readers.
+        assert!(list_contents(&db).is_empty());
+        assert!(!has_entries(&db));
+        journal.commit().unwrap();
+
+        assert_eq!(list_contents(&db), vec![1, 2, 3]);
+        assert!(!has_entries(&db));
+        assert_eq!(db.recover_journal().unwrap(), 0);
+        assert_eq!(list_contents(&db), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn journal_rollback() {
+        let db = TemporaryDB::new();
+        let fork = db.fork();
+        fork.get_proof_list("list").push(0_u32);
+        fork.get_map("map").put(&1_u32, 100_u64);
+        db.merge(fork.into_patch()).unwrap();
+        let state_hash = SystemSchema::new(&db.snapshot()).state_hash();
+
+        let mut journal = db.begin_journal().unwrap();
+        flush_values(&mut journal, &[1, 2]);
+        let fork = journal.fork();
+        fork.get_proof_list::<_, u32>("list").clear();
+        fork.get_map::<_, u32, u64>("map").remove(&1);
+        journal.flush().unwrap();
+        flush_values(&mut journal, &[5]);
+        stage_values(&journal, &[6]);
+        assert_eq!(list_contents(&db), vec![5]);
+        journal.rollback().unwrap();
+
+        let snapshot = db.snapshot();
+        assert_eq!(list_contents(&db), vec![0]);
+        assert_eq!(snapshot.get_map::<_, u32, u64>("map").get(&1), Some(100));
+        assert_eq!(snapshot.get_map::<_, u32, u64>("map").get(&5), None);
+        assert_eq!(SystemSchema::new(&snapshot).state_hash(), state_hash);
+        assert!(!has_entries(&db));
+    }
+
+    #[test]
+    fn dropped_journal_is_rolled_back() {
+        let db = TemporaryDB::new();
+        {
+            let mut journal = db.begin_journal().unwrap();
+            flush_values(&mut journal, &[1, 2]);
+            stage_values(&journal, &[3]);
+        }
+        assert!(list_contents(&db).is_empty());
+        assert!(!has_entries(&db));
+    }
+
+    #[test]
+    fn unfinished_journal_prevents_new_journal() {
+        let db = TemporaryDB::new();
+        let mut journal = db.begin_journal().unwrap();
+        flush_values(&mut journal, &[1, 2]);
+        // Emulate a crash: the journal is not finished and its destructor is not run.
+        std::mem::forget(journal);
+
+        let err = db.begin_journal().unwrap_err();
+        assert!(err.to_string().contains("unfinished journal"));
+        assert_eq!(db.recover_journal().unwrap(), 1);
+        assert!(list_contents(&db).is_empty());
+        db.begin_journal().unwrap().commit().unwrap();
+    }
+
+    #[test]
+    fn journal_is_recovered_on_open() {
+        let dir = TempDir::new().unwrap();
+        let options = DbOptions::default();
+        let list_hash = {
+            let db = RocksDB::open(&dir, &options).unwrap();
+            let fork = db.fork();
+            fork.get_proof_list("list").push(0_u32);
+            db.merge(fork.into_patch()).unwrap();
+            let list_hash = db.snapshot().get_proof_list::<_, u32>("list").object_hash();
+
+            let mut journal = db.begin_journal().unwrap();
+            flush_values(&mut journal, &[1, 2]);
+            flush_values(&mut journal, &[3]);
+            std::mem::forget(journal);
+            assert_eq!(list_contents(&db), vec![0, 1, 2, 3]);
+            list_hash
+        };
+
+        let db = RocksDB::open(&dir, &options).unwrap();
+        assert_eq!(list_contents(&db), vec![0]);
+        let snapshot = db.snapshot();
+        assert_eq!(
+            SystemSchema::new(&snapshot).state_aggregator().get("list"),
+            Some(list_hash)
+        );
+        assert!(!has_entries(&db));
+    }
+
+    #[test]
+    fn encrypted_journal_is_recovered_by_wrapper() {
+        exonum_crypto::init();
+        let dir = TempDir::new().unwrap();
+        let options = DbOptions::default();
+        let key = aead::gen_key();
+        let encryption = EncryptionOptions::new(true);
+        {
+            let db = RocksDB::open(&dir, &options).unwrap();
+            let db = EncryptedDB::new(db, &key, encryption).unwrap();
+            let mut journal = db.begin_journal().unwrap();
+            flush_values(&mut journal, &[1, 2]);
+            std::mem::forget(journal);
+        }
+
+        // The raw database does not touch the encrypted journal.
+        let db = RocksDB::open(&dir, &options).unwrap();
+        assert!(has_entries(&db));
+        let db = EncryptedDB::new(db, &key, encryption).unwrap();
+        assert!(list_contents(&db).is_empty());
+        assert!(!has_entries(&db));
+    }
+}
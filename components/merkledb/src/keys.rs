@@ -19,6 +19,8 @@ use chrono::{DateTime, NaiveDateTime, Utc};
 use rust_decimal::Decimal;
 use uuid::Uuid;
 
+use std::cmp::Reverse;
+
 use exonum_crypto::{Hash, PublicKey, Signature, HASH_SIZE, PUBLIC_KEY_LENGTH, SIGNATURE_LENGTH};
 
 /// A type that can be (de)serialized as a key in the blockchain storage.
@@ -82,6 +84,17 @@ pub trait BinaryKey: ToOwned {
     /// Deserializes the key from the specified buffer of bytes.
     // TODO: Should be unsafe? (ECR-174)
     fn read(buffer: &[u8]) -> Self::Owned;
+
+    /// Returns the size of the serialized key if it is the same for all keys of this type,
+    /// or `None` otherwise.
+    ///
+    /// The size is used to lay out composite keys, such as ones produced by the `BinaryKey`
+    /// derive macro: fixed-size keys are written as is, while other keys are written
+    /// in a self-delimiting form. The default implementation returns `None`, which is
+    /// always correct, but may lead to less compact composite keys.
+    fn fixed_size() -> Option<usize> {
+        None
+    }
 }
 
 /// No-op implementation.
@@ -96,6 +109,10 @@ impl BinaryKey for () {
     }
 
     fn read(_buffer: &[u8]) -> Self::Owned {}
+
+    fn fixed_size() -> Option<usize> {
+        Some(0)
+    }
 }
 
 impl BinaryKey for u8 {
@@ -111,6 +128,10 @@ impl BinaryKey for u8 {
     fn read(buffer: &[u8]) -> Self::Owned {
         buffer[0]
     }
+
+    fn fixed_size() -> Option<usize> {
+        Some(1)
+    }
 }
 
 /// Uses encoding with the values mapped to `u8`
@@ -128,6 +149,10 @@ impl BinaryKey for i8 {
     fn read(buffer: &[u8]) -> Self::Owned {
         buffer[0].wrapping_sub(Self::min_value() as u8) as Self
     }
+
+    fn fixed_size() -> Option<usize> {
+        Some(1)
+    }
 }
 
 // spell-checker:ignore utype, itype, vals, ints
@@ -148,6 +173,10 @@ macro_rules! storage_key_for_ints {
             fn read(buffer: &[u8]) -> Self {
                 BigEndian::$read_method(buffer)
             }
+
+            fn fixed_size() -> Option<usize> {
+                Some($size)
+            }
         }
 
         /// Uses big-endian encoding with the values mapped to the unsigned format
@@ -165,6 +194,10 @@ macro_rules! storage_key_for_ints {
             fn read(buffer: &[u8]) -> Self {
                 BigEndian::$read_method(buffer).wrapping_sub(Self::min_value() as $utype) as Self
             }
+
+            fn fixed_size() -> Option<usize> {
+                Some($size)
+            }
         }
     };
 }
@@ -189,6 +222,10 @@ macro_rules! storage_key_for_crypto_types {
             fn read(buffer: &[u8]) -> Self {
                 Self::from_slice(buffer).unwrap()
             }
+
+            fn fixed_size() -> Option<usize> {
+                Some($size)
+            }
         }
     };
 }
@@ -242,6 +279,10 @@ impl BinaryKey for [u8; 32] {
         value.copy_from_slice(buffer);
         value
     }
+
+    fn fixed_size() -> Option<usize> {
+        Some(32)
+    }
 }
 
 /// Uses UTF-8 string serialization.
@@ -310,6 +351,10 @@ impl BinaryKey for DateTime<Utc> {
         let nanos = u32::read(&buffer[8..12]);
         Self::from_utc(NaiveDateTime::from_timestamp(secs, nanos), Utc)
     }
+
+    fn fixed_size() -> Option<usize> {
+        Some(12)
+    }
 }
 
 impl BinaryKey for Uuid {
@@ -325,6 +370,10 @@ impl BinaryKey for Uuid {
     fn read(buffer: &[u8]) -> Self::Owned {
         Self::from_slice(buffer).unwrap()
     }
+
+    fn fixed_size() -> Option<usize> {
+        Some(16)
+    }
 }
 
 impl BinaryKey for Decimal {
@@ -342,6 +391,154 @@ impl BinaryKey for Decimal {
         bytes.copy_from_slice(buffer);
         Self::deserialize(bytes)
     }
+
+    fn fixed_size() -> Option<usize> {
+        Some(16)
+    }
+}
+
+/// Reverses the order of keys. Fixed-size keys are encoded as the bitwise negation
+/// of the wrapped key; other keys are negated after being converted into a self-delimiting form.
+///
+/// The wrapper is useful for composite keys, e.g., ones produced by the `BinaryKey` derive
+/// macro, to iterate over some key components in the descending order.
+///
+/// # Examples
+///
+/// ```
+/// # use exonum_merkledb::{access::CopyAccessExt, Database, TemporaryDB};
+/// use std::cmp::Reverse;
+///
+/// let db = TemporaryDB::new();
+/// let fork = db.fork();
+/// let mut map = fork.get_map("map");
+/// map.put(&Reverse(1_u64), "one".to_owned());
+/// map.put(&Reverse(2_u64), "two".to_owned());
+/// let keys: Vec<_> = map.keys().map(|Reverse(key)| key).collect();
+/// assert_eq!(keys, vec![2, 1]);
+/// ```
+impl<T> BinaryKey for Reverse<T>
+where
+    T: BinaryKey<Owned = T> + Clone,
+{
+    fn size(&self) -> usize {
+        composite::field_size(&self.0)
+    }
+
+    fn write(&self, buffer: &mut [u8]) -> usize {
+        let size = composite::write_field(&self.0, buffer);
+        for byte in &mut buffer[..size] {
+            *byte = !*byte;
+        }
+        size
+    }
+
+    fn read(buffer: &[u8]) -> Self::Owned {
+        let negated: Vec<u8> = buffer.iter().map(|&byte| !byte).collect();
+        Self(composite::read_field::<T>(&negated).0)
+    }
+
+    fn fixed_size() -> Option<usize> {
+        T::fixed_size()
+    }
+}
+
+/// Helpers for composite keys used by the `BinaryKey` derive macro.
+///
+/// Components of a composite key are written one after another. A component with
+/// a [fixed size] is written as is. Other components, except for the last one, are written
+/// in the self-delimiting form: each zero byte is escaped as `[0, 0xff]`, and the component
+/// is terminated by `[0, 1]`. This encoding preserves the lexicographic order of components.
+///
+/// [fixed size]: ../trait.BinaryKey.html#method.fixed_size
+#[doc(hidden)]
+pub mod composite {
+    use super::BinaryKey;
+
+    const ESCAPE: u8 = 0;
+    const ESCAPED_ZERO: u8 = 0xff;
+    const TERMINATOR: u8 = 1;
+
+    /// Returns the size of the non-last component of a composite key.
+    pub fn field_size<K: BinaryKey + ?Sized>(key: &K) -> usize {
+        if K::fixed_size().is_some() {
+            key.size()
+        } else {
+            let mut buffer = vec![0; key.size()];
+            key.write(&mut buffer);
+            let escaped_len: usize = buffer
+                .iter()
+                .map(|&byte| if byte == ESCAPE { 2 } else { 1 })
+                .sum();
+            escaped_len + 2
+        }
+    }
+
+    /// Writes the non-last component of a composite key. Returns the number of written bytes.
+    pub fn write_field<K: BinaryKey + ?Sized>(key: &K, buffer: &mut [u8]) -> usize {
+        if let Some(size) = K::fixed_size() {
+            return key.write(&mut buffer[..size]);
+        }
+
+        let mut raw = vec![0; key.size()];
+        key.write(&mut raw);
+        let mut pos = 0;
+        for byte in raw {
+            buffer[pos] = byte;
+            pos += 1;
+            if byte == ESCAPE {
+                buffer[pos] = ESCAPED_ZERO;
+                pos += 1;
+            }
+        }
+        buffer[pos] = ESCAPE;
+        buffer[pos + 1] = TERMINATOR;
+        pos + 2
+    }
+
+    /// Reads the non-last component of a composite key from the beginning of the `buffer`.
+    /// Returns the component and the number of read bytes.
+    pub fn read_field<K: BinaryKey + ?Sized>(buffer: &[u8]) -> (K::Owned, usize) {
+        if let Some(size) = K::fixed_size() {
+            return (K::read(&buffer[..size]), size);
+        }
+
+        let mut raw = vec![];
+        let mut pos = 0;
+        loop {
+            let byte = buffer[pos];
+            if byte == ESCAPE {
+                match buffer[pos + 1] {
+                    ESCAPED_ZERO => raw.push(ESCAPE),
+                    TERMINATOR => break,
+                    other => panic!("Invalid escaped byte in composite key: {}", other),
+                }
+                pos += 2;
+            } else {
+                raw.push(byte);
+                pos += 1;
+            }
+        }
+        (K::read(&raw), pos + 2)
+    }
+
+    /// Returns the fixed size of a composite key with the specified component sizes.
+    pub fn fields_size(sizes: &[Option<usize>]) -> Option<usize> {
+        sizes
+            .iter()
+            .try_fold(0, |acc, size| size.map(|size| acc + size))
+    }
+
+    /// Returns the fixed size of an enum with the specified variant sizes. The size includes
+    /// a single byte for the variant tag.
+    pub fn variants_size(sizes: &[Option<usize>]) -> Option<usize> {
+        let first = *sizes.first()?;
+        if sizes.iter().all(|&size| size == first) {
+            first.map(|size| size + 1)
+        } else {
+            None
+        }
+    }
 }
 
 #[cfg(test)]
@@ -679,6 +876,43 @@ mod tests {
         assert_round_trip_eq(&decimals);
     }
 
+    #[test]
+    fn test_composite_field_encoding() {
+        let field = vec![1_u8, 0, 2];
+        let mut buffer = vec![0; composite::field_size(&field)];
+        assert_eq!(composite::write_field(&field, &mut buffer), buffer.len());
+        assert_eq!(buffer, [1, 0, 0xff, 2, 0, 1]);
+
+        buffer.extend_from_slice(&[5, 6]);
+        let (read_field, len) = composite::read_field::<Vec<u8>>(&buffer);
+        assert_eq!(read_field, field);
+        assert_eq!(len, 6);
+
+        // Fixed-size fields are written as is.
+        let mut buffer = vec![0; composite::field_size(&0x_0100_u16)];
+        composite::write_field(&0x_0100_u16, &mut buffer);
+        assert_eq!(buffer, [1, 0]);
+        assert_eq!(composite::read_field::<u16>(&buffer), (0x_0100, 2));
+    }
+
+    #[test]
+    fn test_reverse_order() {
+        let strings = ["", "a", "a\0", "a\0b", "ab", "b"];
+        let keys: Vec<_> = strings
+            .iter()
+            .map(|&s| {
+                let key = Reverse(s.to_owned());
+                let mut buffer = get_buffer(&key);
+                key.write(&mut buffer);
+                assert_eq!(<Reverse<String>>::read(&buffer), key);
+                buffer
+            })
+            .collect();
+        for window in keys.windows(2) {
+            assert!(window[0] > window[1]);
+        }
+    }
+
     fn assert_round_trip_eq<T>(values: &[T])
     where
         T: BinaryKey + PartialEq<<T as ToOwned>::Owned> + Debug,
@@ -27,6 +27,10 @@
 //! You can interact with the `Database` from multiple threads by cloning its instance.
 //!
 //! This crate provides two database types: [`RocksDB`] and [`TemporaryDB`].
+//! If the `redb` crate feature is enabled, a third type, [`RedbDB`], is available;
+//! it is a persistent database written in pure Rust, which does not require a C++
+//! toolchain to build. Any of these databases can be wrapped into [`EncryptedDB`],
+//! which encrypts data at rest.
 //!
 //! # Snapshot and Fork
 //!
@@ -74,6 +78,8 @@
 //! - [`SparseListIndex`] is a list of items stored in a sequential order. Similar to `ListIndex`,
 //!   but may contain indexes without elements.
 //! - [`MapIndex`] is a map of keys and values. Similar to [`BTreeMap`].
+//! - [`IndexedMapIndex`] is a `MapIndex` with a secondary index over its values, which
+//!   is kept in sync with the map automatically.
 //! - [`ProofEntry`] is a Merkelized version of `Entry`.
 //! - [`ProofListIndex`] is a Merkelized version of `ListIndex` that supports cryptographic
 //!   proofs of existence and is implemented as a Merkle tree.
@@ -81,6 +87,9 @@
 //!   proofs of existence and is implemented as a binary Merkle Patricia tree.
 //! - [`KeySetIndex`] and [`ValueSetIndex`] are sets of items, similar to [`BTreeSet`] and
 //!   [`HashSet`] accordingly.
+//! - [`ProofKeySetIndex`] is a Merkelized version of `KeySetIndex` that supports cryptographic
+//!   proofs of membership and non-membership. Like `ProofMapIndex`, it is implemented
+//!   as a binary Merkle Patricia tree.
 //!
 //! # State aggregation
 //!
@@ -93,7 +102,8 @@
 //! as per [`ObjectHash`] implementation. An index is aggregated if and only if it satisfies
 //! the following constraints:
 //!
-//! - Index has a matching type (`ProofListIndex`, `ProofMapIndex`, or `ProofEntry`)
+//! - Index has a matching type (`ProofListIndex`, `ProofMapIndex`, `ProofKeySetIndex`,
+//!   or `ProofEntry`)
 //! - Index is not a part of a group, i.e., its address does not contain the `bytes` part
 //!
 //! The aggregation is automatically updated when a `Fork` is converted into a `Patch`.
@@ -110,6 +120,8 @@
 //! [`Database`]: trait.Database.html
 //! [`RocksDB`]: struct.RocksDB.html
 //! [`TemporaryDB`]: struct.TemporaryDB.html
+//! [`RedbDB`]: struct.RedbDB.html
+//! [`EncryptedDB`]: struct.EncryptedDB.html
 //! [`Snapshot`]: trait.Snapshot.html
 //! [`Fork`]: struct.Fork.html
 //! [`Patch`]: struct.Patch.html
@@ -123,9 +135,11 @@
 //! [`ListIndex`]: indexes/struct.ListIndex.html
 //! [`SparseListIndex`]: indexes/struct.SparseListIndex.html
 //! [`MapIndex`]: indexes/struct.MapIndex.html
+//! [`IndexedMapIndex`]: indexes/struct.IndexedMapIndex.html
 //! [`ProofListIndex`]: indexes/proof_list/struct.ProofListIndex.html
 //! [`ProofMapIndex`]: indexes/proof_map/struct.ProofMapIndex.html
 //! [`KeySetIndex`]: indexes/struct.KeySetIndex.html
+//! [`ProofKeySetIndex`]: indexes/struct.ProofKeySetIndex.html
 //! [`ValueSetIndex`]: indexes/struct.ValueSetIndex.html
 //! [`ObjectHash`]: trait.ObjectHash.html
 //! [doc:storage]: https://exonum.com/doc/architecture/storage
@@ -162,49 +176,62 @@
 #[macro_use] // Code generated by Protobuf requires `serde_derive` macros to be globally available.
 extern crate serde_derive;
 
-// Re-export the crypto crate for use in the `ObjectHash` derive macro.
+// Re-exports for use in the `ObjectHash` and `BinaryKey` derive macros.
 #[doc(hidden)]
 pub mod _reexports {
+    pub use crate::keys::composite;
     pub use anyhow::Error;
     pub use exonum_crypto::{hash, Hash};
 }
 
 pub use self::{
-    backends::{rocksdb::RocksDB, temporarydb::TemporaryDB},
+    backends::{encrypted::EncryptedDB, rocksdb::RocksDB, temporarydb::TemporaryDB},
     db::{
         Database, DatabaseExt, Fork, Iter, Iterator, OwnedReadonlyFork, Patch, ReadonlyFork,
         Snapshot,
     },
     error::Error,
     hash::{root_hash, HashTag, ObjectHash, ValidationError},
+    integrity::{CorruptedIndex, IntegrityReport},
+    journal::Journal,
     keys::BinaryKey,
     lazy::Lazy,
-    options::DbOptions,
+    options::{ColumnFamilyOptions, DbOptions, EncryptionOptions, WalSyncMode},
+    stats::{ColumnFamilyStats, IndexStats},
     values::BinaryValue,
     views::{AsReadonly, IndexAddress, IndexType, ResolvedAddress, SystemSchema},
 };
+#[cfg(feature = "redb")]
+pub use self::backends::redb::RedbDB;
 // Workaround for 'Linked file at path {exonum_merkledb_path}/struct.ProofMapIndex.html
 // does not exist!'
 #[doc(no_inline)]
 pub use self::indexes::{
-    proof_list::{self, ListProof, ProofListIndex},
+    proof_list::{self, ListConsistencyProof, ListProof, ProofListIndex},
     proof_map::{self, MapProof, ProofMapIndex, RawProofMapIndex},
-    Entry, Group, KeySetIndex, ListIndex, MapIndex, ProofEntry, SparseListIndex, ValueSetIndex,
+    Entry, Group, IndexedMapIndex, KeySetIndex, ListIndex, MapIndex, ProofEntry, ProofKeySetIndex,
+    RawProofKeySetIndex, SparseListIndex, ValueSetIndex,
 };
 
 #[macro_use]
 mod macros;
 pub mod access;
 mod backends;
+pub mod compact;
 mod db;
+pub mod dump;
 mod error;
 pub mod generic;
 mod hash;
+mod history;
 pub mod indexes;
+mod integrity;
+mod journal;
 mod keys;
 mod lazy;
 pub mod migration;
 mod options;
+mod stats;
 pub mod validation;
 mod values;
 mod views;
@@ -17,10 +17,47 @@
 use rocksdb::DBCompressionType;
 use serde_derive::{Deserialize, Serialize};
 
+use std::collections::BTreeMap;
+
 /// Options for the database.
 ///
 /// These parameters apply to the underlying database of Exonum, currently `RocksDB`.
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+///
+/// # Examples
+///
+/// Options can be read from TOML, e.g., as a part of the node configuration:
+///
+/// ```
+/// # use exonum_merkledb::{ColumnFamilyOptions, DbOptions, WalSyncMode};
+/// let options: DbOptions = toml::from_str(r#"
+///     create_if_missing = true
+///     compression_type = "lz4"
+///     max_background_jobs = 4
+///     wal_sync_mode = "always"
+///
+///     block_cache_size = 134217728
+///     bloom_filter_bits = 10
+///
+///     [column_family]
+///     write_buffer_size = 67108864
+///
+///     [column_family_overrides."core.transactions"]
+///     write_buffer_size = 268435456
+///     max_write_buffer_number = 4
+///
+///     [encryption]
+///     encrypt_keys = false
+/// "#).unwrap();
+///
+/// assert_eq!(options.wal_sync_mode, WalSyncMode::Always);
+/// assert!(options.encryption.is_some());
+/// let tx_options = options.column_family_options("core.transactions");
+/// assert_eq!(tx_options.write_buffer_size, Some(256 << 20));
+/// assert_eq!(tx_options.max_write_buffer_number, Some(4));
+/// let other_options = options.column_family_options("core.call_records");
+/// assert_eq!(other_options.write_buffer_size, Some(64 << 20));
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[non_exhaustive]
 pub struct DbOptions {
     /// Number of open files that can be used by the database.
@@ -46,6 +83,58 @@ pub struct DbOptions {
     ///
     /// Defaults to `CompressionType::None`, meaning there is no compression.
     pub compression_type: CompressionType,
+    /// Maximum number of concurrent background jobs (compactions and flushes).
+    ///
+    /// A quarter of the jobs (but at least one) is reserved for flushes, and the rest
+    /// is used for compactions. Increasing the number of jobs may help to avoid write stalls
+    /// under heavy load.
+    ///
+    /// Defaults to `None`, meaning that the default `RocksDB` settings are used.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_background_jobs: Option<i32>,
+    /// Mode of synchronizing the write-ahead log (WAL) to the disk.
+    ///
+    /// Defaults to `WalSyncMode::Manual`.
+    #[serde(default, skip_serializing_if = "WalSyncMode::is_default")]
+    pub wal_sync_mode: WalSyncMode,
+    /// Size of the LRU cache for uncompressed data blocks, in bytes. A single cache
+    /// is shared by all column families.
+    ///
+    /// Defaults to `None`, meaning that the default `RocksDB` settings are used
+    /// (a separate 8 MB cache for each column family).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_cache_size: Option<usize>,
+    /// Number of bits per key in the bloom filter, which speeds up lookups of absent keys.
+    /// `10` bits per key yield approximately 1% false positive rate. The filter
+    /// is used for all column families.
+    ///
+    /// Defaults to `None`, meaning that bloom filters are not used.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bloom_filter_bits: Option<i32>,
+    /// Options applied to all column families.
+    ///
+    /// Defaults to empty options, meaning that the default `RocksDB` settings are used.
+    #[serde(default, skip_serializing_if = "ColumnFamilyOptions::is_empty")]
+    pub column_family: ColumnFamilyOptions,
+    /// Options for specific column families, keyed by the column family name.
+    /// Options specified here take precedence over `column_family` ones.
+    ///
+    /// A column family is named after the index stored in it, e.g., `core.transactions`.
+    /// Indexes in a group share the column family named after the group.
+    ///
+    /// Defaults to an empty map.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub column_family_overrides: BTreeMap<String, ColumnFamilyOptions>,
+    /// Options of the data encryption at rest. If set, the database should be wrapped
+    /// into [`EncryptedDB`] with these options; e.g., the node launched via `exonum-cli`
+    /// encrypts the database with the key derived from the master key of the node.
+    /// The setting cannot be changed for an existing database.
+    ///
+    /// Defaults to `None`, meaning that the data is not encrypted.
+    ///
+    /// [`EncryptedDB`]: struct.EncryptedDB.html
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encryption: Option<EncryptionOptions>,
 }
 
 impl DbOptions {
@@ -59,10 +148,111 @@ impl DbOptions {
             max_open_files,
             create_if_missing,
             compression_type,
+            max_background_jobs: None,
+            wal_sync_mode: WalSyncMode::default(),
+            block_cache_size: None,
+            bloom_filter_bits: None,
+            column_family: ColumnFamilyOptions::default(),
+            column_family_overrides: BTreeMap::new(),
+            encryption: None,
+        }
+    }
+
+    /// Returns options for the column family with the specified name, taking
+    /// `column_family_overrides` into account.
+    pub fn column_family_options(&self, cf_name: &str) -> ColumnFamilyOptions {
+        self.column_family_overrides
+            .get(cf_name)
+            .map_or(self.column_family, |overrides| {
+                overrides.or(self.column_family)
+            })
+    }
+}
+
+/// Tuning options for a column family.
+///
+/// All options are optional; if an option is not set, the default `RocksDB` setting is used.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[non_exhaustive]
+pub struct ColumnFamilyOptions {
+    /// Size of a single memtable, in bytes. Once a memtable exceeds this size,
+    /// it is flushed to the disk.
+    pub write_buffer_size: Option<usize>,
+    /// Maximum number of memtables, both active and immutable. If the active memtable
+    /// is full and the number of memtables is at the limit, writes are stalled.
+    pub max_write_buffer_number: Option<i32>,
+    /// An algorithm used for compression of the column family. Overrides
+    /// `DbOptions.compression_type`.
+    pub compression_type: Option<CompressionType>,
+}
+
+impl ColumnFamilyOptions {
+    /// Checks whether none of the options is set.
+    fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Returns options with the unset fields taken from `defaults`.
+    fn or(self, defaults: Self) -> Self {
+        Self {
+            write_buffer_size: self.write_buffer_size.or(defaults.write_buffer_size),
+            max_write_buffer_number: self
+                .max_write_buffer_number
+                .or(defaults.max_write_buffer_number),
+            compression_type: self.compression_type.or(defaults.compression_type),
         }
     }
 }
 
+/// Mode of synchronizing the write-ahead log (WAL) of the database to the disk.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum WalSyncMode {
+    /// The WAL is synchronized only on `Database::merge_sync()`, and is left to the OS
+    /// on `Database::merge()`.
+    Manual,
+    /// The WAL is synchronized on each write. This mode is the safest, but the slowest one.
+    Always,
+    /// The WAL is disabled. Writes are faster, but the latest changes may be lost
+    /// if the process crashes.
+    Disabled,
+}
+
+impl Default for WalSyncMode {
+    fn default() -> Self {
+        Self::Manual
+    }
+}
+
+impl WalSyncMode {
+    #[allow(clippy::trivially_copy_pass_by_ref)] // required by `serde`
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Options for the data encryption in [`EncryptedDB`].
+///
+/// [`EncryptedDB`]: struct.EncryptedDB.html
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[non_exhaustive]
+pub struct EncryptionOptions {
+    /// Whether keys are encrypted in addition to values.
+    ///
+    /// Encrypted keys do not preserve the order of keys, which makes iteration over indexes
+    /// and removing ranges of keys significantly slower.
+    ///
+    /// Defaults to `false`, meaning that only values are encrypted.
+    pub encrypt_keys: bool,
+}
+
+impl EncryptionOptions {
+    /// Creates a new `EncryptionOptions` object.
+    pub fn new(encrypt_keys: bool) -> Self {
+        Self { encrypt_keys }
+    }
+}
+
 /// Algorithms of compression for the database.
 ///
 /// Database contents are stored in a set of blocks, each of which holds a
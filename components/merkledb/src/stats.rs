@@ -0,0 +1,155 @@
+// Copyright 2020 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Statistics of the database contents.
+
+use serde_derive::{Deserialize, Serialize};
+
+use std::collections::BTreeMap;
+
+use crate::{
+    views::{IndexAddress, IndexType, IndexesPool},
+    ResolvedAddress, Snapshot,
+};
+
+/// Statistics of a single index in the database.
+///
+/// Statistics are obtained with [`DatabaseExt::index_stats()`].
+///
+/// [`DatabaseExt::index_stats()`]: trait.DatabaseExt.html#method.index_stats
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct IndexStats {
+    /// Name of the index. For indexes in a group, this is the name of the group.
+    pub name: String,
+    /// Hex-encoded key of the index in the group, or `None` if the index does not belong
+    /// to a group.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group_key: Option<String>,
+    /// Is the index in migration?
+    pub in_migration: bool,
+    /// Type of the index.
+    pub index_type: IndexType,
+    /// Number of entries in the underlying key-value storage. This number may differ
+    /// from the number of elements of the index; e.g., Merkelized indexes store hashes
+    /// of the Merkle tree nodes alongside elements.
+    pub key_count: u64,
+    /// Total size of keys and values of the entries in bytes. The size on the disk may differ
+    /// because of compression and the storage overhead.
+    pub size: u64,
+}
+
+/// Backend-specific statistics of a column family.
+///
+/// Statistics are obtained with [`Database::column_family_stats()`].
+///
+/// [`Database::column_family_stats()`]: trait.Database.html#method.column_family_stats
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct ColumnFamilyStats {
+    /// Name of the column family.
+    pub name: String,
+    /// Internal properties of the column family, such as the estimated number of keys.
+    /// The set of properties depends on the database backend.
+    pub properties: BTreeMap<String, u64>,
+}
+
+impl ColumnFamilyStats {
+    /// Creates statistics for a column family.
+    pub fn new(name: impl Into<String>, properties: BTreeMap<String, u64>) -> Self {
+        Self {
+            name: name.into(),
+            properties,
+        }
+    }
+}
+
+/// Collects statistics for all indexes in the snapshot.
+pub(crate) fn index_stats(snapshot: &dyn Snapshot) -> Vec<IndexStats> {
+    let pool = IndexesPool::new(snapshot);
+    pool.indexes()
+        .map(|(qualified_name, metadata)| {
+            let addr = IndexAddress::from_fully_qualified_name(&qualified_name);
+            let resolved = ResolvedAddress::new(addr.name(), Some(metadata.identifier()));
+
+            let (mut key_count, mut size) = (0, 0);
+            let mut iter = snapshot.iter(&resolved, &[]);
+            while let Some((key, value)) = iter.next() {
+                key_count += 1;
+                size += (key.len() + value.len()) as u64;
+            }
+
+            IndexStats {
+                name: addr.name().to_owned(),
+                group_key: addr.id_in_group().map(hex::encode),
+                in_migration: addr.is_in_migration(),
+                index_type: metadata.index_type(),
+                key_count,
+                size,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        access::{AccessExt, CopyAccessExt},
+        migration::Migration,
+        Database, DatabaseExt, TemporaryDB,
+    };
+
+    #[test]
+    fn index_stats_with_migration() {
+        let db = TemporaryDB::new();
+        let fork = db.fork();
+        fork.get_entry("test.entry").set(1_u64);
+        Migration::new("test", &fork)
+            .get_proof_list("list")
+            .extend(vec![1_u32, 2]);
+        db.merge(fork.into_patch()).unwrap();
+
+        let stats = db.index_stats();
+        let entry_stats = stats
+            .iter()
+            .find(|stats| stats.name == "test.entry")
+            .unwrap();
+        assert!(!entry_stats.in_migration);
+        assert_eq!(entry_stats.key_count, 1);
+        assert_eq!(entry_stats.size, 8);
+
+        let list_stats = stats
+            .iter()
+            .find(|stats| stats.name == "test.list")
+            .unwrap();
+        assert!(list_stats.in_migration);
+        assert_eq!(list_stats.group_key, None);
+        // The list stores hashes of the Merkle tree nodes in addition to the elements.
+        assert!(list_stats.key_count > 2);
+    }
+
+    #[test]
+    fn column_family_stats() {
+        let db = TemporaryDB::new();
+        let fork = db.fork();
+        fork.get_list("list").extend(vec![1_u32, 2, 3]);
+        db.merge(fork.into_patch()).unwrap();
+
+        let stats = db.column_family_stats();
+        let list_stats = stats.iter().find(|stats| stats.name == "list").unwrap();
+        assert!(list_stats
+            .properties
+            .contains_key("rocksdb.estimate-num-keys"));
+    }
+}
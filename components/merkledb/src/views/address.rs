@@ -83,7 +83,7 @@ impl IndexAddress {
     /// is defined as the component of the address name up (but not including) the first dot `'.'`
     /// char in the name (e.g., `foo` for address `^foo.bar`). For non-migrated indexes,
     /// the namespace is the empty string.
-    pub(super) fn namespace(&self) -> &str {
+    pub(crate) fn namespace(&self) -> &str {
         if self.in_migration {
             let dot_position = self.name.find('.');
             if let Some(pos) = dot_position {
@@ -159,6 +159,10 @@ impl IndexAddress {
         }
     }
 
+    pub(crate) fn is_in_migration(&self) -> bool {
+        self.in_migration
+    }
+
     pub(crate) fn set_in_migration(&mut self) {
         self.in_migration = true;
     }
@@ -188,6 +192,21 @@ impl IndexAddress {
         prefix
     }
 
+    /// Restores an address from the fully qualified name obtained with `fully_qualified_name`.
+    pub(crate) fn from_fully_qualified_name(qualified_name: &[u8]) -> Self {
+        let (in_migration, qualified_name) = match qualified_name.split_first() {
+            Some((&MIGRATION_CHAR, rest)) => (true, rest),
+            _ => (false, qualified_name),
+        };
+        let mut parts = qualified_name.splitn(2, |&byte| byte == SEPARATOR_CHAR);
+        let name = parts.next().unwrap_or_default();
+        Self {
+            name: String::from_utf8_lossy(name).into_owned(),
+            id_in_group: parts.next().map(<[u8]>::to_vec),
+            in_migration,
+        }
+    }
+
     /// Infers the name part of the fully qualified name that was obtained with
     /// `fully_qualified_name`. This is the part corresponding to `ResolvedAddress.name`.
     /// `min_name_len` specifies the minimum known length of the name part.
@@ -330,6 +349,28 @@ impl From<&str> for ResolvedAddress {
     }
 }
 
+#[test]
+fn restoring_address_from_qualified_name() {
+    let addresses = vec![
+        IndexAddress::from_root("some.list"),
+        IndexAddress::from_root("some.list").append_key(&[1_u8, 0, 2][..]),
+        IndexAddress::from_root("some.list").append_key(&[][..]),
+    ];
+    for mut addr in addresses {
+        let qualified_name = addr.fully_qualified_name();
+        assert_eq!(
+            IndexAddress::from_fully_qualified_name(&qualified_name),
+            addr
+        );
+        addr.set_in_migration();
+        let qualified_name = addr.fully_qualified_name();
+        assert_eq!(
+            IndexAddress::from_fully_qualified_name(&qualified_name),
+            addr
+        );
+    }
+}
+
 #[test]
 fn address_resolution() {
     {
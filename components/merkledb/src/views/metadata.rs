@@ -19,7 +19,7 @@ use exonum_crypto::Hash;
 use num_traits::FromPrimitive;
 use serde_derive::{Deserialize, Serialize};
 
-use std::{borrow::Cow, io::Error, mem, num::NonZeroU64, vec};
+use std::{borrow::Cow, io::Error, iter, mem, num::NonZeroU64, vec};
 
 use super::{IndexAddress, RawAccess, RawAccessMut, ResolvedAddress, View};
 use crate::{
@@ -29,7 +29,7 @@ use crate::{
 };
 
 /// Name of the column family used to store `IndexesPool`.
-const INDEXES_POOL_NAME: &str = "__INDEXES_POOL__";
+pub(crate) const INDEXES_POOL_NAME: &str = "__INDEXES_POOL__";
 
 /// Type of an index supported by Exonum.
 ///
@@ -55,6 +55,8 @@ pub enum IndexType {
     ProofMap = 8,
     /// Merkelized entry.
     ProofEntry = 9,
+    /// Merkelized set index with elements stored as keys in a Merkle Patricia tree.
+    ProofKeySet = 10,
 
     /// Tombstone indicating necessity to remove an index after migration is completed.
     Tombstone = 254,
@@ -67,7 +69,7 @@ impl IndexType {
     /// Checks if the index of this type is Merkelized.
     pub fn is_merkelized(self) -> bool {
         match self {
-            Self::ProofList | Self::ProofMap | Self::ProofEntry => true,
+            Self::ProofList | Self::ProofMap | Self::ProofEntry | Self::ProofKeySet => true,
             _ => false,
         }
     }
@@ -323,14 +325,32 @@ impl<T: RawAccess> IndexesPool<T> {
         Self(view)
     }
 
-    pub(super) fn len(&self) -> u64 {
+    pub(crate) fn len(&self) -> u64 {
         self.0.get(&()).unwrap_or_default()
     }
 
-    fn index_metadata(&self, index_name: &[u8]) -> Option<IndexMetadata> {
+    pub(crate) fn index_metadata(&self, index_name: &[u8]) -> Option<IndexMetadata> {
         self.0.get(index_name)
     }
 
+    /// Returns fully qualified names and metadata of all indexes in the pool, including
+    /// indexes in migration.
+    pub(crate) fn indexes(&self) -> impl Iterator<Item = (Vec<u8>, IndexMetadata)> + '_ {
+        // The empty key is occupied by the pool length; index names are never empty.
+        self.0
+            .iter_from::<_, _, Vec<u8>, IndexMetadata>(&(), &[0_u8][..])
+    }
+
+    /// Returns resolved addresses of all views managed by the pool: the pool itself
+    /// and all indexes, including indexes in migration.
+    pub(crate) fn views(&self) -> impl Iterator<Item = ResolvedAddress> + '_ {
+        let indexes = self.indexes().map(|(full_name, metadata)| {
+            let (name, _) = IndexAddress::parse_fully_qualified_name(&full_name, 0);
+            ResolvedAddress::new(name, Some(metadata.identifier))
+        });
+        iter::once(ResolvedAddress::system(INDEXES_POOL_NAME)).chain(indexes)
+    }
+
     fn set_len(&mut self, len: u64) {
         self.0.put_or_forget(&(), len);
     }
@@ -552,7 +572,7 @@ pub fn get_object_hash<T: RawAccess>(
             // Hash is stored directly in the metadata.
             metadata.convert::<Hash>().state.unwrap_or_default()
         }
-        IndexType::ProofList | IndexType::ProofMap => {
+        IndexType::ProofList | IndexType::ProofMap | IndexType::ProofKeySet => {
             let view_with_metadata = ViewWithMetadata {
                 view: View::new(access, addr),
                 metadata,
@@ -566,6 +586,7 @@ pub fn get_object_hash<T: RawAccess>(
                 list.object_hash()
             } else {
                 // We don't access map elements, so the key / value types don't matter.
+                // Proof key sets are stored in the same way as maps with empty values.
                 let map = ProofMapIndex::<_, (), ()>::new(view_with_metadata);
                 map.object_hash()
             }
@@ -574,6 +595,63 @@ pub fn get_object_hash<T: RawAccess>(
     }
 }
 
+/// Checks that the Merkle tree nodes of an index match the hashes recomputed from
+/// the index contents. Indexes that do not store Merkle trees are always considered intact.
+pub(crate) fn check_index_integrity<T: RawAccess>(
+    access: T,
+    index_full_name: Vec<u8>,
+    metadata: IndexMetadata,
+) -> Result<(), String> {
+    use crate::{ProofListIndex, ProofMapIndex};
+
+    let index_type = metadata.index_type;
+    let (name, _) = IndexAddress::parse_fully_qualified_name(&index_full_name, 0);
+    let addr = ResolvedAddress::new(name, Some(metadata.identifier));
+    let view_with_metadata = ViewWithMetadata {
+        view: View::new(access, addr),
+        metadata,
+        index_full_name,
+        is_phantom: false,
+    };
+
+    match index_type {
+        // We don't access elements directly, so the element types don't matter.
+        IndexType::ProofList => ProofListIndex::<_, ()>::new(view_with_metadata).check_integrity(),
+        IndexType::ProofMap | IndexType::ProofKeySet => {
+            ProofMapIndex::<_, (), ()>::new(view_with_metadata).check_integrity()
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Checks that the hash of a `ProofEntry` stored in its metadata matches the entry value.
+///
+/// Since the value type is not known, the check assumes that the value hash is either
+/// the hash of the serialized value (as per the default `ObjectHash` implementation),
+/// or the value itself (as for `Hash` values).
+pub(crate) fn check_proof_entry<T: RawAccess>(
+    access: T,
+    index_full_name: &[u8],
+    metadata: IndexMetadata,
+) -> Result<(), String> {
+    debug_assert_eq!(metadata.index_type, IndexType::ProofEntry);
+    let (name, _) = IndexAddress::parse_fully_qualified_name(index_full_name, 0);
+    let view = View::new(
+        access,
+        ResolvedAddress::new(name, Some(metadata.identifier)),
+    );
+    let value: Option<Vec<u8>> = view.get(&());
+    let stored_hash = metadata.convert::<Hash>().state.unwrap_or_default();
+
+    match value {
+        None if stored_hash == Hash::default() => Ok(()),
+        None => Err("Entry hash is set, but the entry is empty".to_owned()),
+        Some(ref value) if exonum_crypto::hash(value) == stored_hash => Ok(()),
+        Some(ref value) if value.as_slice() == stored_hash.as_ref() => Ok(()),
+        Some(_) => Err("Entry hash does not match the entry value".to_owned()),
+    }
+}
+
 /// Wrapper struct to manipulate `IndexMetadata` for an index with provided `index_name`.
 #[derive(Debug)]
 pub struct ViewWithMetadata<T: RawAccess> {
@@ -20,12 +20,22 @@ pub use self::{
     },
     system_schema::{get_state_aggregator, SystemSchema},
 };
+pub(crate) use self::{
+    metadata::{check_index_integrity, check_proof_entry, INDEXES_POOL_NAME},
+    system_schema::STATE_AGGREGATOR,
+};
 
-use std::{borrow::Cow, fmt, iter::Peekable, marker::PhantomData};
+use std::{
+    borrow::Cow,
+    fmt,
+    iter::Peekable,
+    marker::PhantomData,
+    ops::{Bound, RangeBounds},
+};
 
 use self::address::key_bytes;
 use super::{
-    db::{Change, ChangesMut, ChangesRef, ForkIter, ViewChanges},
+    db::{snapshot_iter, Change, ChangesMut, ChangesRef, ForkIter, ViewChanges},
     BinaryKey, BinaryValue, Iter as BytesIter, Iterator as BytesIterator, Snapshot,
 };
 
@@ -214,10 +224,9 @@ impl<T: RawAccess> ViewInner<T> {
             // Ignore all changes from the snapshot.
             Box::new(ChangesIter::new(changes_iter.unwrap()))
         } else {
-            Box::new(ForkIter::new(
-                self.snapshot().iter(&self.address, from),
-                changes_iter,
-            ))
+            let snapshot_iter =
+                snapshot_iter(self.snapshot(), &self.address, from, self.changes.as_ref());
+            Box::new(ForkIter::new(snapshot_iter, changes_iter))
         }
     }
 }
@@ -424,6 +433,34 @@ impl<T: RawAccessMut> View<T> {
             .insert(concat_keys!(key), Change::Delete);
     }
 
+    /// Removes keys within the specified range from the view.
+    ///
+    /// The removal is recorded as a single range tombstone, the size of which does not depend
+    /// on the number of removed keys.
+    pub fn remove_range<K, R>(&mut self, range: R)
+    where
+        K: BinaryKey + ?Sized,
+        R: RangeBounds<K>,
+    {
+        // The lexicographically smallest key following `key`.
+        fn successor(mut key: Vec<u8>) -> Vec<u8> {
+            key.push(0);
+            key
+        }
+
+        let start = match range.start_bound() {
+            Bound::Included(key) => key_bytes(key),
+            Bound::Excluded(key) => successor(key_bytes(key)),
+            Bound::Unbounded => vec![],
+        };
+        let end = match range.end_bound() {
+            Bound::Included(key) => Some(successor(key_bytes(key))),
+            Bound::Excluded(key) => Some(key_bytes(key)),
+            Bound::Unbounded => None,
+        };
+        self.changes_mut().remove_range(start, end);
+    }
+
     /// Clears the view removing all its elements.
     pub fn clear(&mut self) {
         self.changes_mut().clear();
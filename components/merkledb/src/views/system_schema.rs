@@ -15,10 +15,10 @@
 use exonum_crypto::Hash;
 
 use super::{AsReadonly, IndexType, RawAccess, ViewWithMetadata};
-use crate::{Fork, ObjectHash, ProofMapIndex};
+use crate::{integrity, Fork, IntegrityReport, ObjectHash, ProofMapIndex, Snapshot};
 
 /// Name of the state aggregator proof map.
-const STATE_AGGREGATOR: &str = "__STATE_AGGREGATOR__";
+pub(crate) const STATE_AGGREGATOR: &str = "__STATE_AGGREGATOR__";
 
 pub fn get_state_aggregator<T: RawAccess>(
     access: T,
@@ -98,6 +98,17 @@ impl<T: RawAccess + AsReadonly> SystemSchema<T> {
     }
 }
 
+impl SystemSchema<&dyn Snapshot> {
+    /// Checks integrity of the Merkelized indexes in the snapshot. Unlike
+    /// [`DatabaseExt::check_integrity()`], this method allows to relate the check results
+    /// to other data in the same snapshot, such as the latest committed block.
+    ///
+    /// [`DatabaseExt::check_integrity()`]: trait.DatabaseExt.html#method.check_integrity
+    pub fn check_integrity(&self) -> IntegrityReport {
+        integrity::check_integrity(self.0)
+    }
+}
+
 impl SystemSchema<&Fork> {
     /// Updates state hash of the database.
     pub(crate) fn update_state_aggregators(
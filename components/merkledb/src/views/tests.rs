@@ -266,6 +266,63 @@ where
     assert_iter(&view, 4, &[(4, 0)]);
 }
 
+fn test_remove_range<T, I>(db: &T, address: I)
+where
+    T: Database,
+    I: Into<ResolvedAddress> + Copy,
+{
+    let fork = db.fork();
+    {
+        let mut view = View::new(&fork, address);
+        for i in 1_u8..=6 {
+            view.put(&vec![i], vec![i]);
+        }
+    }
+    db.merge(fork.into_patch()).unwrap();
+
+    let mut fork = db.fork();
+    {
+        let mut view = View::new(&fork, address);
+        view.put(&vec![7], vec![7]);
+        view.remove_range(vec![2]..vec![5]);
+
+        assert_eq!(view.get_bytes(&[2]), None);
+        assert!(!view.contains_raw_key(&[4]));
+        assert_eq!(view.get_bytes(&[5]), Some(vec![5]));
+        assert_iter(&view, 0, &[(1, 1), (5, 5), (6, 6), (7, 7)]);
+        assert_iter(&view, 3, &[(5, 5), (6, 6), (7, 7)]);
+
+        view.put(&vec![3], vec![30]);
+        assert_eq!(view.get_bytes(&[3]), Some(vec![30]));
+        assert_iter(&view, 0, &[(1, 1), (3, 30), (5, 5), (6, 6), (7, 7)]);
+    }
+    fork.flush();
+    {
+        let mut view = View::new(&fork, address);
+        assert_iter(&view, 0, &[(1, 1), (3, 30), (5, 5), (6, 6), (7, 7)]);
+
+        view.remove_range(vec![6]..);
+        assert_eq!(view.get_bytes(&[7]), None);
+        assert_iter(&view, 0, &[(1, 1), (3, 30), (5, 5)]);
+        view.remove_range(vec![0]..=vec![1]);
+        assert_iter(&view, 0, &[(3, 30), (5, 5)]);
+    }
+    fork.flush();
+    {
+        let view = View::new(fork.readonly(), address);
+        assert_eq!(view.get_bytes(&[1]), None);
+        assert_iter(&view, 0, &[(3, 30), (5, 5)]);
+        assert_iter(&view, 4, &[(5, 5)]);
+    }
+
+    db.merge(fork.into_patch()).unwrap();
+    let snapshot = db.snapshot();
+    let view = View::new(&snapshot, address);
+    assert_eq!(view.get_bytes(&[2]), None);
+    assert_eq!(view.get_bytes(&[3]), Some(vec![30]));
+    assert_iter(&view, 0, &[(3, 30), (5, 5)]);
+}
+
 fn test_fork_iter<T, I>(db: &T, address: I)
 where
     T: Database,
@@ -610,6 +667,39 @@ fn clear_prefixed_view() {
     test_clear_view(&TemporaryDB::new(), PREFIXED_IDX);
 }
 
+#[test]
+fn remove_range() {
+    test_remove_range(&TemporaryDB::new(), IDX_NAME);
+}
+
+#[test]
+fn remove_range_in_prefixed_view() {
+    test_remove_range(&TemporaryDB::new(), PREFIXED_IDX);
+}
+
+#[test]
+fn remove_range_in_sibling_views() {
+    const IDX_1: (&str, u64) = ("foo", 23);
+    const IDX_2: (&str, u64) = ("foo", 24);
+
+    let db = TemporaryDB::new();
+    let fork = db.fork();
+    for &address in &[IDX_1, IDX_2] {
+        let mut view = View::new(&fork, address);
+        view.put(&vec![1], vec![1]);
+        view.put(&vec![2], vec![2]);
+    }
+    db.merge(fork.into_patch()).unwrap();
+
+    let fork = db.fork();
+    View::new(&fork, IDX_1).remove_range::<Vec<u8>, _>(..);
+    db.merge(fork.into_patch()).unwrap();
+
+    let snapshot = db.snapshot();
+    assert_iter(&View::new(&snapshot, IDX_1), 0, &[]);
+    assert_iter(&View::new(&snapshot, IDX_2), 0, &[(1, 1), (2, 2)]);
+}
+
 #[test]
 fn clear_sibling_views() {
     const IDX_1: (&str, u64) = ("foo", 23);
@@ -1125,3 +1215,103 @@ fn fork_from_patch() {
     db.merge(fork.into_patch())
         .expect("Fork created from patch should be merged successfully");
 }
+
+#[cfg(feature = "redb")]
+mod redb {
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::RedbDB;
+
+    fn create_db() -> (RedbDB, TempDir) {
+        let dir = TempDir::new().unwrap();
+        let db = RedbDB::open(dir.path(), &DbOptions::default()).unwrap();
+        (db, dir)
+    }
+
+    #[test]
+    fn database_check_correct_version() {
+        let (db, _dir) = create_db();
+        let snapshot = db.snapshot();
+
+        let view = View::new(&snapshot, ResolvedAddress::system(db::DB_METADATA));
+        let version: u8 = view.get(db::VERSION_NAME).unwrap();
+        assert_eq!(version, db::DB_VERSION);
+    }
+
+    #[test]
+    #[should_panic(expected = "actual 2, expected 0")]
+    fn database_check_incorrect_version() {
+        let dir = TempDir::new().unwrap();
+        let opts = DbOptions::default();
+        // Writes different version to metadata.
+        {
+            let db = RedbDB::open(&dir, &opts).unwrap();
+            let fork = db.fork();
+            {
+                let mut view = View::new(&fork, ResolvedAddress::system(db::DB_METADATA));
+                view.put(db::VERSION_NAME, 2_u8);
+            }
+            db.merge(fork.into_patch()).unwrap();
+        }
+        // Tries to open modified database.
+        RedbDB::open(&dir, &opts).unwrap();
+    }
+
+    #[test]
+    fn fork_iter() {
+        test_fork_iter(&create_db().0, IDX_NAME);
+    }
+
+    #[test]
+    fn fork_iter_prefixed() {
+        test_fork_iter(&create_db().0, PREFIXED_IDX);
+    }
+
+    #[test]
+    fn changelog() {
+        test_changelog(&create_db().0, IDX_NAME);
+    }
+
+    #[test]
+    fn changelog_prefixed() {
+        test_changelog(&create_db().0, PREFIXED_IDX);
+    }
+
+    #[test]
+    fn views_in_same_family() {
+        _views_in_same_family(&create_db().0);
+    }
+
+    #[test]
+    fn clear_view() {
+        test_clear_view(&create_db().0, IDX_NAME);
+    }
+
+    #[test]
+    fn clear_prefixed_view() {
+        test_clear_view(&create_db().0, PREFIXED_IDX);
+    }
+
+    #[test]
+    fn remove_range() {
+        test_remove_range(&create_db().0, IDX_NAME);
+    }
+
+    #[test]
+    fn remove_range_in_prefixed_view() {
+        test_remove_range(&create_db().0, PREFIXED_IDX);
+    }
+
+    #[test]
+    #[should_panic]
+    fn two_mutable_borrows() {
+        test_two_mutable_borrows(&create_db().0, IDX_NAME);
+    }
+
+    #[test]
+    #[should_panic]
+    fn mutable_and_immutable_borrows() {
+        test_mutable_and_immutable_borrows(&create_db().0, IDX_NAME);
+    }
+}
@@ -0,0 +1,264 @@
+// Copyright 2020 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tests for `BinaryKey` derivation.
+
+use exonum_crypto::{Hash, PublicKey};
+use exonum_derive::BinaryKey;
+use proptest::{prelude::*, test_runner::Config};
+
+use std::{cmp::Reverse, fmt::Debug};
+
+use exonum_merkledb::{access::CopyAccessExt, BinaryKey, Database, TemporaryDB};
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, BinaryKey)]
+struct Fixed {
+    a: u32,
+    b: i16,
+    c: Hash,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, BinaryKey)]
+struct Composite {
+    name: String,
+    bytes: Vec<u8>,
+    index: u64,
+    comment: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, BinaryKey)]
+struct Tuple(u8, String);
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, BinaryKey)]
+struct Unit;
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, BinaryKey)]
+enum Account {
+    Anonymous,
+    Wallet(PublicKey),
+    Alias { name: String, index: u32 },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, BinaryKey)]
+struct Nested {
+    account: Account,
+    height: Reverse<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, BinaryKey)]
+struct Generic<T: BinaryKey<Owned = T> + Clone> {
+    inner: T,
+    tag: u8,
+}
+
+fn to_bytes<K: BinaryKey + ?Sized>(key: &K) -> Vec<u8> {
+    let mut buffer = vec![0; key.size()];
+    assert_eq!(key.write(&mut buffer), buffer.len());
+    buffer
+}
+
+fn check_roundtrip<K>(key: &K)
+where
+    K: BinaryKey + ToOwned<Owned = K> + PartialEq + Debug,
+{
+    let bytes = to_bytes(key);
+    assert_eq!(K::read(&bytes), *key);
+    if let Some(size) = K::fixed_size() {
+        assert_eq!(bytes.len(), size);
+    }
+}
+
+/// Checks that the order of serialized keys is the same as the order of keys themselves.
+fn check_order<K: BinaryKey + Ord + Debug>(keys: &[K]) {
+    for x in keys {
+        for y in keys {
+            assert_eq!(
+                to_bytes(x).cmp(&to_bytes(y)),
+                x.cmp(y),
+                "Order mismatch for {:?} and {:?}",
+                x,
+                y
+            );
+        }
+    }
+}
+
+fn str_with_zeros() -> impl Strategy<Value = String> {
+    // Include zero chars and chars with the highest bytes to test escaping.
+    "[\0\x01a\u{ff}]{0,5}"
+}
+
+fn bytes_with_zeros() -> impl Strategy<Value = Vec<u8>> {
+    prop::collection::vec(
+        prop_oneof![Just(0_u8), Just(1), Just(0xff), any::<u8>()],
+        0..5,
+    )
+}
+
+fn composite() -> impl Strategy<Value = Composite> {
+    (
+        str_with_zeros(),
+        bytes_with_zeros(),
+        0_u64..3,
+        str_with_zeros(),
+    )
+        .prop_map(|(name, bytes, index, comment)| Composite {
+            name,
+            bytes,
+            index,
+            comment,
+        })
+}
+
+fn account() -> impl Strategy<Value = Account> {
+    prop_oneof![
+        Just(Account::Anonymous),
+        any::<[u8; 32]>().prop_map(|bytes| Account::Wallet(PublicKey::new(bytes))),
+        (str_with_zeros(), 0_u32..3).prop_map(|(name, index)| Account::Alias { name, index }),
+    ]
+}
+
+#[test]
+fn fixed_size_keys() {
+    assert_eq!(Fixed::fixed_size(), Some(4 + 2 + 32));
+    assert_eq!(Unit::fixed_size(), Some(0));
+    assert_eq!(Composite::fixed_size(), None);
+    assert_eq!(Tuple::fixed_size(), None);
+    assert_eq!(Account::fixed_size(), None);
+    assert_eq!(Generic::<u64>::fixed_size(), Some(9));
+    assert_eq!(Generic::<String>::fixed_size(), None);
+    assert_eq!(<Reverse<u32>>::fixed_size(), Some(4));
+
+    let key = Fixed {
+        a: 1,
+        b: -1,
+        c: Hash::zero(),
+    };
+    check_roundtrip(&key);
+    check_roundtrip(&Unit);
+    assert!(to_bytes(&Unit).is_empty());
+}
+
+#[test]
+fn single_field_is_encoded_transparently() {
+    #[derive(Debug, Clone, PartialEq, BinaryKey)]
+    struct Wrapper(String);
+
+    let wrapper = Wrapper("foo".to_owned());
+    assert_eq!(to_bytes(&wrapper), b"foo");
+    check_roundtrip(&wrapper);
+}
+
+#[test]
+fn variant_tags() {
+    assert_eq!(to_bytes(&Account::Anonymous), [0]);
+    let wallet = Account::Wallet(PublicKey::new([1; 32]));
+    assert_eq!(to_bytes(&wallet)[0], 1);
+    let alias = Account::Alias {
+        name: "foo".to_owned(),
+        index: 0,
+    };
+    assert_eq!(to_bytes(&alias)[0], 2);
+
+    for account in &[Account::Anonymous, wallet, alias] {
+        check_roundtrip(account);
+    }
+}
+
+#[test]
+#[should_panic(expected = "Invalid variant tag")]
+fn invalid_variant_tag() {
+    Account::read(&[3]);
+}
+
+#[test]
+fn reverse_fields_in_map() {
+    let db = TemporaryDB::new();
+    let fork = db.fork();
+    let mut map = fork.get_map("map");
+    let accounts = [
+        Account::Anonymous,
+        Account::Alias {
+            name: "alice".to_owned(),
+            index: 0,
+        },
+    ];
+    for account in &accounts {
+        for height in 0_u64..5 {
+            let key = Nested {
+                account: account.clone(),
+                height: Reverse(height),
+            };
+            map.put(&key, height);
+        }
+    }
+
+    // Iterate over the alias entries starting from the latest height.
+    let start = Nested {
+        account: accounts[1].clone(),
+        height: Reverse(u64::max_value()),
+    };
+    let heights: Vec<_> = map
+        .iter_from(&start)
+        .map(|(key, height)| {
+            assert_eq!(key.account, accounts[1]);
+            height
+        })
+        .collect();
+    assert_eq!(heights, vec![4, 3, 2, 1, 0]);
+}
+
+proptest! {
+    #![proptest_config(Config::with_cases(64))]
+
+    #[test]
+    fn composite_roundtrip(key in composite()) {
+        check_roundtrip(&key);
+    }
+
+    #[test]
+    fn composite_order(keys in prop::collection::vec(composite(), 2..10)) {
+        check_order(&keys);
+    }
+
+    #[test]
+    fn tuple_order(keys in prop::collection::vec((any::<u8>(), str_with_zeros()), 2..10)) {
+        let keys: Vec<_> = keys.into_iter().map(|(a, b)| Tuple(a, b)).collect();
+        check_order(&keys);
+    }
+
+    #[test]
+    fn nested_roundtrip(account in account(), height: u64) {
+        let key = Nested { account, height: Reverse(height) };
+        check_roundtrip(&key);
+    }
+
+    #[test]
+    fn nested_order(keys in prop::collection::vec((account(), 0_u64..3), 2..10)) {
+        let keys: Vec<_> = keys
+            .into_iter()
+            .map(|(account, height)| Nested { account, height: Reverse(height) })
+            .collect();
+        check_order(&keys);
+    }
+
+    #[test]
+    fn generic_order(keys in prop::collection::vec((str_with_zeros(), any::<u8>()), 2..10)) {
+        let keys: Vec<_> = keys
+            .into_iter()
+            .map(|(inner, tag)| Generic { inner, tag })
+            .collect();
+        check_order(&keys);
+    }
+}
@@ -0,0 +1,176 @@
+// Copyright 2020 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tests for `BinaryValue` derivation with `serde`-based codecs.
+
+use exonum_crypto::{Hash, PublicKey};
+use exonum_derive::{BinaryValue, ObjectHash};
+use serde_derive::{Deserialize, Serialize};
+
+use std::{borrow::Cow, collections::BTreeMap};
+
+use exonum_merkledb::{access::CopyAccessExt, BinaryValue, Database, ObjectHash, TemporaryDB};
+
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    Serialize,
+    Deserialize,
+    BinaryValue,
+    ObjectHash
+)]
+#[binary_value(codec = "bincode")]
+struct BincodeWallet {
+    owner: PublicKey,
+    balance: u64,
+    history: BTreeMap<u64, Hash>,
+}
+
+#[derive(
+    Debug,
+    Clone,
+    PartialEq,
+    Serialize,
+    Deserialize,
+    BinaryValue,
+    ObjectHash
+)]
+#[binary_value(codec = "cbor")]
+struct CborWallet {
+    owner: PublicKey,
+    balance: u64,
+    history: BTreeMap<u64, Hash>,
+}
+
+/// Newer version of `CborWallet` with an additional optional field.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, BinaryValue)]
+#[binary_value(codec = "cbor")]
+struct CborWalletV2 {
+    owner: PublicKey,
+    balance: u64,
+    history: BTreeMap<u64, Hash>,
+    #[serde(default)]
+    name: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, BinaryValue)]
+#[binary_value(codec = "cbor")]
+enum Status {
+    Active,
+    Frozen { reason: String },
+}
+
+fn history() -> BTreeMap<u64, Hash> {
+    (0_u64..10)
+        .rev()
+        .map(|i| (i, exonum_crypto::hash(&i.to_le_bytes())))
+        .collect()
+}
+
+fn check_roundtrip<T: BinaryValue + PartialEq + std::fmt::Debug>(value: &T) {
+    let bytes = value.to_bytes();
+    assert_eq!(T::from_bytes(Cow::Borrowed(&bytes)).unwrap(), *value);
+    // Encoding is deterministic.
+    assert_eq!(value.to_bytes(), bytes);
+}
+
+#[test]
+fn bincode_codec() {
+    let wallet = BincodeWallet {
+        owner: PublicKey::new([1; 32]),
+        balance: 100,
+        history: history(),
+    };
+    check_roundtrip(&wallet);
+    assert_eq!(wallet.to_bytes(), bincode::serialize(&wallet).unwrap());
+    assert_eq!(
+        wallet.object_hash(),
+        exonum_crypto::hash(&wallet.to_bytes())
+    );
+}
+
+#[test]
+fn cbor_codec() {
+    let wallet = CborWallet {
+        owner: PublicKey::new([1; 32]),
+        balance: 100,
+        history: history(),
+    };
+    check_roundtrip(&wallet);
+    let mut bytes = vec![];
+    ciborium::ser::into_writer(&wallet, &mut bytes).unwrap();
+    assert_eq!(wallet.to_bytes(), bytes);
+    assert_eq!(
+        wallet.object_hash(),
+        exonum_crypto::hash(&wallet.to_bytes())
+    );
+
+    check_roundtrip(&Status::Active);
+    check_roundtrip(&Status::Frozen {
+        reason: "audit".to_owned(),
+    });
+}
+
+#[test]
+fn cbor_codec_errors() {
+    let wallet = CborWallet {
+        owner: PublicKey::new([1; 32]),
+        balance: 100,
+        history: BTreeMap::new(),
+    };
+    let mut bytes = wallet.to_bytes();
+    bytes.push(0);
+    assert!(CborWallet::from_bytes(bytes.into()).is_err());
+
+    let bytes = Status::Active.to_bytes();
+    assert!(CborWallet::from_bytes(bytes.into()).is_err());
+}
+
+#[test]
+fn cbor_codec_with_added_field() {
+    let wallet = CborWallet {
+        owner: PublicKey::new([2; 32]),
+        balance: 5,
+        history: history(),
+    };
+    let upgraded = CborWalletV2::from_bytes(wallet.to_bytes().into()).unwrap();
+    assert_eq!(upgraded.balance, 5);
+    assert_eq!(upgraded.history, wallet.history);
+    assert_eq!(upgraded.name, None);
+}
+
+#[test]
+fn cbor_values_in_indexes() {
+    let db = TemporaryDB::new();
+    let fork = db.fork();
+    {
+        let mut map = fork.get_proof_map("wallets");
+        for i in 0_u8..5 {
+            let wallet = CborWallet {
+                owner: PublicKey::new([i; 32]),
+                balance: u64::from(i),
+                history: history(),
+            };
+            map.put(&wallet.owner, wallet.clone());
+        }
+    }
+    db.merge(fork.into_patch()).unwrap();
+
+    let snapshot = db.snapshot();
+    let map = snapshot.get_proof_map::<_, PublicKey, CborWallet>("wallets");
+    let wallet = map.get(&PublicKey::new([3; 32])).unwrap();
+    assert_eq!(wallet.balance, 3);
+    assert_eq!(wallet.history, history());
+}
@@ -14,14 +14,23 @@
 
 //! Tests related to components and `FromAccess` derivation.
 
-use exonum_derive::FromAccess;
+use exonum_derive::{BinaryValue, FromAccess};
+use serde_derive::{Deserialize, Serialize};
 
 use exonum_merkledb::{
     access::{Access, CopyAccessExt, FromAccess, RawAccessMut},
-    BinaryKey, Database, Entry, Group, Lazy, ListIndex, ObjectHash, ProofEntry, ProofMapIndex,
-    TemporaryDB,
+    BinaryKey, Database, Entry, Group, IndexedMapIndex, Lazy, ListIndex, ObjectHash, ProofEntry,
+    ProofMapIndex, TemporaryDB,
 };
 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, BinaryValue)]
+#[binary_value(codec = "bincode")]
+struct Wallet {
+    owner: String,
+    currency: u8,
+    balance: u64,
+}
+
 #[derive(FromAccess)]
 struct Simple<Acc: Access> {
     map: ProofMapIndex<Acc::Base, u64, String>,
@@ -295,3 +304,87 @@ fn multiple_flattened_fields() {
         Some(1_u64)
     );
 }
+
+#[test]
+fn secondary_indexes() {
+    #[derive(FromAccess)]
+    struct Schema<T: Access> {
+        #[from_access(secondary_index(field = "owner"))]
+        wallets: IndexedMapIndex<T, u64, Wallet, String>,
+        #[from_access(
+            rename = "other",
+            secondary_index(field = "currency", rename = "currencies")
+        )]
+        other_wallets: IndexedMapIndex<T, u64, Wallet, u8>,
+    }
+
+    let wallet = |owner: &str, currency| Wallet {
+        owner: owner.to_owned(),
+        currency,
+        balance: 100,
+    };
+
+    let db = TemporaryDB::new();
+    let fork = db.fork();
+    {
+        let mut schema = Schema::from_access(&fork, "schema".into()).unwrap();
+        schema.wallets.put(&1, wallet("Alice", 0));
+        schema.wallets.put(&2, wallet("Bob", 1));
+        schema.wallets.put(&3, wallet("Alice", 1));
+        schema.wallets.put(&2, wallet("Carol", 1));
+        schema.wallets.remove(&1);
+
+        schema.other_wallets.put(&1, wallet("Alice", 0));
+        schema.other_wallets.put(&2, wallet("Alice", 1));
+    }
+
+    let schema = Schema::from_access(fork.readonly(), "schema".into()).unwrap();
+    assert_eq!(
+        schema
+            .wallets
+            .keys_by_secondary(&"Alice".to_owned())
+            .collect::<Vec<_>>(),
+        vec![3]
+    );
+    assert_eq!(
+        schema.wallets.keys_by_secondary(&"Bob".to_owned()).count(),
+        0
+    );
+    assert_eq!(
+        schema
+            .wallets
+            .keys_by_secondary(&"Carol".to_owned())
+            .collect::<Vec<_>>(),
+        vec![2]
+    );
+    assert_eq!(
+        schema
+            .other_wallets
+            .keys_by_secondary(&1)
+            .collect::<Vec<_>>(),
+        vec![2]
+    );
+    // Secondary indexes are stored at the addresses derived from the field names.
+    let snapshot = fork.readonly();
+    assert_eq!(
+        snapshot
+            .get_key_set::<_, [u8]>("schema.wallets_by_owner")
+            .iter()
+            .count(),
+        2
+    );
+    assert_eq!(
+        snapshot
+            .get_key_set::<_, [u8]>("schema.currencies")
+            .iter()
+            .count(),
+        2
+    );
+    assert_eq!(
+        fork.readonly()
+            .get_map::<_, u64, Wallet>("schema.other")
+            .iter()
+            .count(),
+        2
+    );
+}
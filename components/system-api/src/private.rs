@@ -21,6 +21,8 @@
 //!
 //! - [Get node info](#get-node-info)
 //! - [Get node statistics](#get-node-statistics)
+//! - [Get database statistics](#get-database-statistics)
+//! - [Check database integrity](#check-database-integrity)
 //! - [Add peer](#add-peer)
 //! - [Change consensus status](#change-consensus-status)
 //! - [Node shutdown](#node-shutdown)
@@ -81,6 +83,75 @@
 //! # }
 //! ```
 //!
+//! # Get Database Statistics
+//!
+//! | Property    | Value |
+//! |-------------|-------|
+//! | Path        | `/api/system/v1/database_stats` |
+//! | Method      | GET   |
+//! | Query type  | - |
+//! | Return type | [`DatabaseStats`] |
+//!
+//! Returns statistics of the node database: the number of entries and the size of each index,
+//! the same values aggregated for each service, and backend-specific properties of the database
+//! column families. Collecting statistics requires iterating over the entire database, so it is
+//! performed on a separate thread pool, and the collected statistics are cached for a minute.
+//!
+//! [`DatabaseStats`]: struct.DatabaseStats.html
+//!
+//! ```
+//! use exonum_system_api::{private::DatabaseStats, SystemApiPlugin};
+//! use exonum_testkit::{ApiKind, TestKitBuilder};
+//!
+//! # #[tokio::main]
+//! # async fn main() -> anyhow::Result<()> {
+//! let mut testkit = TestKitBuilder::validator()
+//!     .with_plugin(SystemApiPlugin)
+//!     .build();
+//! let api = testkit.api();
+//! let stats: DatabaseStats = api.private(ApiKind::System).get("v1/database_stats").await?;
+//! let total_size: u64 = stats.indexes.iter().map(|index| index.size).sum();
+//! let services_size: u64 = stats.services.iter().map(|service| service.size).sum();
+//! assert!(services_size <= total_size);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! # Check Database Integrity
+//!
+//! | Property    | Value |
+//! |-------------|-------|
+//! | Path        | `/api/system/v1/database_integrity` |
+//! | Method      | GET   |
+//! | Query type  | - |
+//! | Return type | [`DatabaseIntegrity`] |
+//!
+//! Checks integrity of the Merkelized indexes in the node database and compares the state hash
+//! of the database with the state hash in the latest block. The check reads all Merkelized
+//! indexes, so it is performed on a separate thread pool; concurrent checks are executed
+//! one at a time.
+//!
+//! [`DatabaseIntegrity`]: struct.DatabaseIntegrity.html
+//!
+//! ```
+//! use exonum_system_api::{private::DatabaseIntegrity, SystemApiPlugin};
+//! use exonum_testkit::{ApiKind, TestKitBuilder};
+//!
+//! # #[tokio::main]
+//! # async fn main() -> anyhow::Result<()> {
+//! let mut testkit = TestKitBuilder::validator()
+//!     .with_plugin(SystemApiPlugin)
+//!     .build();
+//! let api = testkit.api();
+//! let integrity: DatabaseIntegrity = api
+//!     .private(ApiKind::System)
+//!     .get("v1/database_integrity")
+//!     .await?;
+//! assert!(integrity.is_ok());
+//! # Ok(())
+//! # }
+//! ```
+//!
 //! # Add Peer
 //!
 //! | Property    | Value |
@@ -184,17 +255,27 @@
 //! ```
 // limitations under the License.
 
+use actix_web::web;
 use exonum::{
     blockchain::{ApiSender, Blockchain, Schema},
-    crypto::PublicKey,
+    crypto::{Hash, PublicKey},
     helpers::{exonum_version, os_info, rust_version},
+    merkledb::{ColumnFamilyStats, IndexStats, IntegrityReport, SystemSchema},
+    runtime::{InstanceId, SnapshotExt},
 };
 use exonum_api::{self as api, ApiBackend, ApiScope};
 use exonum_node::{ConnectInfo, ExternalMessage, SharedNodeState};
 use futures::{future, prelude::*};
 use semver::Version;
 use serde::{Deserialize, Serialize};
-use std::{sync::Arc, time::SystemTime};
+use std::{
+    convert::Infallible,
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant, SystemTime},
+};
+
+/// Time during which the collected database statistics are returned without recomputation.
+const DATABASE_STATS_TTL: Duration = Duration::from_secs(60);
 
 /// Information about the current state of the node.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -212,6 +293,122 @@ pub struct NodeStats {
     pub uptime: u64,
 }
 
+/// Statistics of the node database.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct DatabaseStats {
+    /// Height of the blockchain at which the statistics were collected.
+    pub height: u64,
+    /// Statistics of all indexes in the database.
+    pub indexes: Vec<IndexStats>,
+    /// Statistics of indexes aggregated for each service instance.
+    pub services: Vec<ServiceStats>,
+    /// Backend-specific statistics of the database column families.
+    pub column_families: Vec<ColumnFamilyStats>,
+}
+
+impl DatabaseStats {
+    fn collect(blockchain: &Blockchain) -> Self {
+        let snapshot = blockchain.snapshot();
+        let height = snapshot.for_core().height().into();
+        let indexes = blockchain.index_stats();
+
+        let services = snapshot
+            .for_dispatcher()
+            .service_instances()
+            .values()
+            .map(|state| {
+                let mut stats = ServiceStats::new(state.spec.id, state.spec.name);
+                // Service indexes reside in the namespace named after the service;
+                // this includes the indexes being migrated.
+                let prefix = format!("{}.", stats.name);
+                for index in indexes
+                    .iter()
+                    .filter(|index| index.name.starts_with(&prefix))
+                {
+                    stats.index_count += 1;
+                    stats.key_count += index.key_count;
+                    stats.size += index.size;
+                }
+                stats
+            })
+            .collect();
+
+        Self {
+            height,
+            indexes,
+            services,
+            column_families: blockchain.column_family_stats(),
+        }
+    }
+}
+
+/// Statistics of indexes belonging to a service instance.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct ServiceStats {
+    /// Numeric identifier of the service instance.
+    pub id: InstanceId,
+    /// Name of the service instance.
+    pub name: String,
+    /// Number of indexes of the service, including the indexes in migration.
+    pub index_count: u64,
+    /// Total number of entries in the service indexes.
+    pub key_count: u64,
+    /// Total size of keys and values of the entries in the service indexes in bytes.
+    pub size: u64,
+}
+
+impl ServiceStats {
+    fn new(id: InstanceId, name: String) -> Self {
+        Self {
+            id,
+            name,
+            index_count: 0,
+            key_count: 0,
+            size: 0,
+        }
+    }
+}
+
+/// Results of the database integrity check.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct DatabaseIntegrity {
+    /// Height of the latest block at the time of the check.
+    pub height: u64,
+    /// State hash recorded in the latest block.
+    pub block_state_hash: Hash,
+    /// Report on the integrity of Merkelized indexes in the database.
+    pub report: IntegrityReport,
+}
+
+impl DatabaseIntegrity {
+    fn check(blockchain: &Blockchain) -> Self {
+        let snapshot = blockchain.snapshot();
+        let report = SystemSchema::new(snapshot.as_ref()).check_integrity();
+        let last_block = snapshot.for_core().last_block();
+        Self {
+            height: last_block.height.into(),
+            block_state_hash: last_block.state_hash,
+            report,
+        }
+    }
+
+    /// Returns `true` if no corrupted indexes were found, and the state hash
+    /// of the database matches the latest block.
+    pub fn is_ok(&self) -> bool {
+        self.report.is_ok() && self.report.state_hash == self.block_state_hash
+    }
+}
+
+/// Database statistics together with the time of their collection.
+#[derive(Debug)]
+struct CachedDatabaseStats {
+    stats: DatabaseStats,
+    collected_at: Instant,
+}
+
 /// Consensus status of the current node.
 #[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
 #[serde(rename_all = "snake_case")]
@@ -296,6 +493,8 @@ pub(super) struct SystemApi {
     shared_api_state: SharedNodeState,
     sender: ApiSender<ExternalMessage>,
     start_time: SystemTime,
+    database_stats: Arc<Mutex<Option<CachedDatabaseStats>>>,
+    integrity_check_lock: Arc<Mutex<()>>,
 }
 
 impl SystemApi {
@@ -310,6 +509,8 @@ impl SystemApi {
             sender,
             shared_api_state,
             start_time: SystemTime::now(),
+            database_stats: Arc::default(),
+            integrity_check_lock: Arc::default(),
         }
     }
 
@@ -317,6 +518,8 @@ impl SystemApi {
     pub fn wire(self, api_scope: &mut ApiScope) -> &mut ApiScope {
         self.handle_info("v1/info", api_scope)
             .handle_stats("v1/stats", api_scope)
+            .handle_database_stats("v1/database_stats", api_scope)
+            .handle_database_integrity("v1/database_integrity", api_scope)
             .handle_peers("v1/peers", api_scope)
             .handle_consensus_status("v1/consensus_status", api_scope)
             .handle_shutdown("v1/shutdown", api_scope);
@@ -377,6 +580,57 @@ impl SystemApi {
         self
     }
 
+    fn handle_database_stats(self, name: &'static str, api_scope: &mut ApiScope) -> Self {
+        let blockchain = self.blockchain.clone();
+        let cache = Arc::clone(&self.database_stats);
+        api_scope.endpoint(name, move |_query: ()| {
+            let blockchain = blockchain.clone();
+            let cache = Arc::clone(&cache);
+            // Collecting statistics iterates over the entire database, so it is performed
+            // on the blocking thread pool in order not to stall the API server.
+            web::block(move || Ok::<_, Infallible>(Self::database_stats(&blockchain, &cache)))
+                .map_err(|e| api::Error::internal(e).title("Failed to collect database stats"))
+        });
+        self
+    }
+
+    fn handle_database_integrity(self, name: &'static str, api_scope: &mut ApiScope) -> Self {
+        let blockchain = self.blockchain.clone();
+        let lock = Arc::clone(&self.integrity_check_lock);
+        api_scope.endpoint(name, move |_query: ()| {
+            let blockchain = blockchain.clone();
+            let lock = Arc::clone(&lock);
+            web::block(move || {
+                let _guard = lock.lock().unwrap_or_else(PoisonError::into_inner);
+                Ok::<_, Infallible>(DatabaseIntegrity::check(&blockchain))
+            })
+            .map_err(|e| api::Error::internal(e).title("Failed to check database integrity"))
+        });
+        self
+    }
+
+    /// Returns cached database statistics, or collects them if the cached ones are outdated.
+    /// Concurrent requests wait for a single collection to complete.
+    fn database_stats(
+        blockchain: &Blockchain,
+        cache: &Mutex<Option<CachedDatabaseStats>>,
+    ) -> DatabaseStats {
+        let mut cache = cache.lock().unwrap_or_else(PoisonError::into_inner);
+        match &*cache {
+            Some(cached) if cached.collected_at.elapsed() < DATABASE_STATS_TTL => {
+                cached.stats.clone()
+            }
+            _ => {
+                let stats = DatabaseStats::collect(blockchain);
+                *cache = Some(CachedDatabaseStats {
+                    stats: stats.clone(),
+                    collected_at: Instant::now(),
+                });
+                stats
+            }
+        }
+    }
+
     fn handle_peers(self, name: &'static str, api_scope: &mut ApiScope) -> Self {
         let sender = self.sender.clone();
         api_scope.endpoint_mut(name, move |connect_info: ConnectInfo| {
@@ -12,18 +12,49 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use exonum::{
+    helpers::Height,
+    merkledb::access::AccessExt,
+    runtime::{ExecutionContext, ExecutionError, InstanceId},
+};
+use exonum_derive::{ServiceDispatcher, ServiceFactory};
 use exonum_node::ExternalMessage;
-use exonum_testkit::{ApiKind, TestKit, TestKitBuilder};
+use exonum_rust_runtime::{DefaultInstance, Service};
+use exonum_testkit::{ApiKind, Spec, TestKit, TestKitBuilder};
 use pretty_assertions::assert_eq;
 
 use exonum_system_api::{
-    private::{ConsensusStatus, NodeInfo, NodeStats},
+    private::{ConsensusStatus, DatabaseIntegrity, DatabaseStats, NodeInfo, NodeStats},
     SystemApiPlugin,
 };
 
+/// Service storing a list of values on initialization.
+#[derive(Debug, ServiceDispatcher, ServiceFactory)]
+#[service_factory(artifact_name = "list-service")]
+struct ListService;
+
+impl Service for ListService {
+    fn initialize(
+        &self,
+        context: ExecutionContext<'_>,
+        _params: Vec<u8>,
+    ) -> Result<(), ExecutionError> {
+        let mut list = context.service_data().get_proof_list("list");
+        list.extend(vec![1_u64, 2, 3]);
+        context.service_data().get_entry("entry").set(4_u64);
+        Ok(())
+    }
+}
+
+impl DefaultInstance for ListService {
+    const INSTANCE_ID: InstanceId = 100;
+    const INSTANCE_NAME: &'static str = "list";
+}
+
 fn create_testkit() -> TestKit {
     TestKitBuilder::validator()
         .with_validators(2)
+        .with(Spec::new(ListService).with_default_instance())
         .with_plugin(SystemApiPlugin)
         .build()
 }
@@ -53,6 +84,69 @@ async fn stats() {
     assert_eq!(info.tx_cache_size, 0);
 }
 
+#[tokio::test]
+async fn database_stats() {
+    let mut testkit = create_testkit();
+    let api = testkit.api();
+    let stats: DatabaseStats = api
+        .private(ApiKind::System)
+        .get("v1/database_stats")
+        .await
+        .unwrap();
+
+    let block_hashes = stats
+        .indexes
+        .iter()
+        .find(|index| index.name == "core.block_hashes_by_height")
+        .unwrap();
+    assert_eq!(block_hashes.key_count, 1);
+    assert!(block_hashes.size > 0);
+    assert!(stats
+        .column_families
+        .iter()
+        .any(|cf| cf.name == "core.block_hashes_by_height"));
+
+    assert_eq!(stats.height, 0);
+    assert_eq!(stats.services.len(), 1);
+    let service_stats = &stats.services[0];
+    assert_eq!(service_stats.id, ListService::INSTANCE_ID);
+    assert_eq!(service_stats.name, ListService::INSTANCE_NAME);
+    assert_eq!(service_stats.index_count, 2);
+    let service_indexes = stats
+        .indexes
+        .iter()
+        .filter(|index| index.name == "list.list" || index.name == "list.entry");
+    let service_size: u64 = service_indexes.map(|index| index.size).sum();
+    assert_eq!(service_stats.size, service_size);
+
+    // Statistics are cached.
+    testkit.create_block();
+    let stats: DatabaseStats = api
+        .private(ApiKind::System)
+        .get("v1/database_stats")
+        .await
+        .unwrap();
+    assert_eq!(stats.height, 0);
+}
+
+#[tokio::test]
+async fn database_integrity() {
+    let mut testkit = create_testkit();
+    testkit.create_blocks_until(Height(2));
+    let api = testkit.api();
+    let integrity: DatabaseIntegrity = api
+        .private(ApiKind::System)
+        .get("v1/database_integrity")
+        .await
+        .unwrap();
+
+    assert!(integrity.is_ok(), "{:?}", integrity);
+    assert_eq!(integrity.height, 2);
+    assert_eq!(integrity.block_state_hash, integrity.report.state_hash);
+    // Proof list of the service is checked together with core indexes.
+    assert!(integrity.report.checked_indexes > 1);
+}
+
 #[tokio::test]
 async fn shutdown() {
     let mut testkit = create_testkit();
@@ -261,6 +261,9 @@ fn node_config() -> (NodeConfig, Keys) {
         api: api_cfg,
         mempool: Default::default(),
         thread_pool_size: Default::default(),
+        retention: Default::default(),
+        state_sync: false,
+        remote_signer: None,
     };
     (node_config, keys)
 }
@@ -47,6 +47,9 @@ impl NodeHandler {
             Message::Responses(Responses::BlockResponse(msg)) => {
                 self.handle_block(msg);
             }
+            Message::Responses(Responses::StateChunkResponse(msg)) => {
+                self.handle_state_chunk(msg);
+            }
             Message::Responses(Responses::TransactionsResponse(msg)) => {
                 if let Err(e) = self.handle_txs_batch(&msg) {
                     log::warn!(
@@ -90,6 +93,8 @@ impl NodeHandler {
     /// Removes peer from the state and from the cache. Node will try to connect to that address
     /// again if it was in the validators list. Returns true if the peer has been connected.
     fn remove_peer_with_addr(&mut self, key: PublicKey) -> bool {
+        // The consensus key of the peer is known only while its `Connect` message is stored.
+        let is_validator = self.state.peer_is_validator(&key);
         let was_connected = self.state.remove_peer_with_pubkey(&key).is_some();
 
         let fork = self.blockchain.fork();
@@ -98,7 +103,6 @@ impl NodeHandler {
             .merge(fork.into_patch())
             .expect("Cannot remove peer from peer cache");
 
-        let is_validator = self.state.peer_is_validator(&key);
         let in_connect_list = self.state.peer_in_connect_list(&key);
         if is_validator && in_connect_list {
             self.connect(key);
@@ -189,7 +193,8 @@ impl NodeHandler {
             msg.payload().blockchain_height
         );
 
-        if !self.state.connect_list().is_peer_allowed(&msg.author()) {
+        let network_key = self.state.network_key_of(&msg.author());
+        if !self.state.connect_list().is_peer_allowed(&network_key) {
             error!(
                 "Received status message from peer = `{}` which not in ConnectList.",
                 msg.author()
@@ -201,6 +206,10 @@ impl NodeHandler {
         let peer_state = PeerState::new(msg.payload());
         self.state.update_peer_state(peer, peer_state);
 
+        if self.sync_state_with_peer(peer, peer_state.blockchain_height) {
+            return;
+        }
+
         // Handle message from future epoch / height.
         if peer_state.blockchain_height > block_height {
             // Request a block with the next height.
@@ -288,7 +297,8 @@ impl NodeHandler {
         };
         trace!("Broadcast status: {:?}", status);
 
-        let message = self.sign_message(status);
-        self.broadcast(message);
+        if let Some(message) = self.sign_consensus_message(status) {
+            self.broadcast(message);
+        }
     }
 }
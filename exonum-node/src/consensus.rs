@@ -12,13 +12,13 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use anyhow::{bail, format_err};
+use anyhow::{bail, ensure, format_err};
 use exonum::{
     blockchain::{
         BlockContents, BlockKind, BlockParams, BlockPatch, Blockchain, BlockchainMut,
-        PersistentPool, ProposerId, Schema,
+        CommitCertificate, EquivocationKind, PersistentPool, ProposerId, Schema,
     },
-    crypto::{Hash, PublicKey},
+    crypto::{bls::BlsSignature, schemes::SignatureScheme, Hash, PublicKey},
     helpers::{Height, Round, ValidatorId},
     merkledb::{BinaryValue, Fork, ObjectHash},
     messages::{AnyTx, Precommit, SignedMessage, Verified},
@@ -31,9 +31,9 @@ use std::{collections::HashSet, convert::TryFrom, fmt};
 use crate::{
     events::InternalRequest,
     messages::{
-        BlockRequest, BlockResponse, Consensus as ConsensusMessage, PoolTransactionsRequest,
-        Prevote, PrevotesRequest, Propose, ProposeRequest, TransactionsRequest,
-        TransactionsResponse,
+        BlockRequest, BlockResponse, Consensus as ConsensusMessage, ExonumMessage, Message,
+        PoolTransactionsRequest, Prevote, PrevotesRequest, Propose, ProposeRequest, Service,
+        StateChunkRequest, TransactionsRequest, TransactionsResponse,
     },
     pool::{ProposeParams, ProposeTemplate},
     schema::NodeSchema,
@@ -41,11 +41,19 @@ use crate::{
     NodeHandler,
 };
 
-/// Shortcut to get verified messages from bytes.
-fn into_verified<T: TryFrom<SignedMessage>>(raw: &[Vec<u8>]) -> anyhow::Result<Vec<Verified<T>>> {
+/// Shortcut to get verified consensus messages from bytes. The messages must be signed
+/// with Ed25519 keys.
+pub(crate) fn into_verified<T: TryFrom<SignedMessage>>(
+    raw: &[Vec<u8>],
+) -> anyhow::Result<Vec<Verified<T>>> {
     let mut items = Vec::with_capacity(raw.len());
     for bytes in raw {
-        let verified = SignedMessage::from_bytes(bytes.into())?.into_verified()?;
+        let signed = SignedMessage::from_bytes(bytes.into())?;
+        ensure!(
+            signed.signature_scheme() == SignatureScheme::Ed25519,
+            "Consensus messages must be signed with Ed25519 keys."
+        );
+        let verified = signed.into_verified()?;
         items.push(verified);
     }
     Ok(items)
@@ -86,6 +94,8 @@ pub(crate) enum HandleTxError {
     AlreadyProcessed,
     /// Transaction is invalid according to `Blockchain::check_tx`.
     Invalid(ExecutionError),
+    /// The node is synchronizing the blockchain state and cannot check transactions.
+    StateSync,
 }
 
 impl fmt::Display for HandleTxError {
@@ -93,6 +103,7 @@ impl fmt::Display for HandleTxError {
         match self {
             Self::AlreadyProcessed => formatter.write_str("Transaction is already processed"),
             Self::Invalid(e) => write!(formatter, "Transaction failed preliminary checks: {}", e),
+            Self::StateSync => formatter.write_str("Node is synchronizing blockchain state"),
         }
     }
 }
@@ -108,6 +119,13 @@ impl NodeHandler {
             );
             return;
         }
+        if self.state_sync.is_some() {
+            trace!(
+                "Ignoring a consensus message {:?} during state synchronization",
+                msg
+            );
+            return;
+        }
 
         // Warning for messages from previous and future height
         if msg.epoch() < self.state.epoch().previous() || msg.epoch() > self.state.epoch().next() {
@@ -162,6 +180,12 @@ impl NodeHandler {
             Some(from),
             self.state.consensus_public_key_of(msg.payload().validator)
         );
+        self.detect_equivocation(
+            EquivocationKind::Propose,
+            msg.payload().validator,
+            msg.payload().round,
+            msg.as_raw(),
+        );
 
         if msg.payload().skip && !msg.payload().transactions.is_empty() {
             error!(
@@ -197,6 +221,7 @@ impl NodeHandler {
         let has_unknown_txs = match self.state.add_propose(
             msg.clone(),
             &schema.transactions(),
+            &schema.transactions_locations(),
             &schema.transactions_pool(),
         ) {
             Ok(state) => state.has_unknown_txs(),
@@ -241,6 +266,10 @@ impl NodeHandler {
             );
         }
 
+        if self.state_sync.is_some() {
+            bail!("Received block during state synchronization");
+        }
+
         let block = &msg.payload().block;
         let block_hash = block.object_hash();
         let epoch = block
@@ -500,6 +529,56 @@ impl NodeHandler {
         self.request_next_block();
     }
 
+    /// Checks whether a consensus message conflicts with a message of the same kind
+    /// received earlier from the same validator, and saves the evidence of equivocation
+    /// to the blockchain storage if it does.
+    fn detect_equivocation(
+        &mut self,
+        kind: EquivocationKind,
+        validator: ValidatorId,
+        round: Round,
+        message: &SignedMessage,
+    ) {
+        let evidence = match self
+            .state
+            .check_equivocation(kind, validator, round, message)
+        {
+            Some(evidence) => evidence,
+            None => return,
+        };
+        warn!(
+            "Validator {} has sent conflicting {:?} messages for epoch {}, round {}",
+            validator,
+            kind,
+            self.state.epoch(),
+            round
+        );
+
+        self.state.queue_evidence(evidence);
+        self.save_equivocation_evidence();
+    }
+
+    /// Saves the queued evidence of equivocation to the blockchain storage. If the storage
+    /// cannot be written to, the evidence stays in the queue; saving is retried on
+    /// the next detected equivocation or after the next block is committed.
+    fn save_equivocation_evidence(&mut self) {
+        if self.state.queued_evidence().is_empty() {
+            return;
+        }
+
+        let fork = self.blockchain.fork();
+        {
+            let mut evidence_index = Schema::new(&fork).equivocation_evidence();
+            for evidence in self.state.queued_evidence() {
+                evidence_index.put(&evidence.object_hash(), evidence.clone());
+            }
+        }
+        match self.blockchain.merge(fork.into_patch()) {
+            Ok(()) => self.state.clear_queued_evidence(),
+            Err(e) => error!("Unable to save equivocation evidence: {}", e),
+        }
+    }
+
     /// Handles the `Prevote` message. For details see the message documentation.
     fn handle_prevote(&mut self, from: PublicKey, msg: &Verified<Prevote>) {
         trace!("Handle prevote");
@@ -508,6 +587,12 @@ impl NodeHandler {
             Some(from),
             self.state.consensus_public_key_of(msg.payload().validator)
         );
+        self.detect_equivocation(
+            EquivocationKind::Prevote,
+            msg.payload().validator,
+            msg.payload().round,
+            msg.as_raw(),
+        );
 
         // Add prevote and check if majority of validator nodes have voted for this propose.
         let has_consensus = self.state.add_prevote(msg.clone());
@@ -677,6 +762,12 @@ impl NodeHandler {
             Some(from),
             self.state.consensus_public_key_of(msg.payload().validator)
         );
+        self.detect_equivocation(
+            EquivocationKind::Precommit,
+            msg.payload().validator,
+            msg.payload().round,
+            msg.as_raw(),
+        );
 
         // Add precommit
         let has_consensus = self.state.add_precommit(msg.clone());
@@ -737,12 +828,13 @@ impl NodeHandler {
         self.blockchain
             .commit(block_state.patch(), precommits)
             .expect("Cannot commit block");
+        // Retry saving equivocation evidence which could not be saved earlier.
+        self.save_equivocation_evidence();
 
         match block_kind {
             BlockKind::Normal => {
                 // Update node state.
-                self.state
-                    .update_config(Schema::new(&self.blockchain.snapshot()).consensus_config());
+                self.update_consensus_config();
                 // Update state to new height.
                 self.state.new_height(
                     block_hash,
@@ -783,6 +875,9 @@ impl NodeHandler {
                 .merge(fork.into_patch())
                 .expect("Cannot save changes to transaction pool");
         }
+        if let Some(pruner) = &self.pruner {
+            pruner.notify();
+        }
 
         let schema = Schema::new(&snapshot);
         let pool_len = schema.transactions_pool_len();
@@ -836,10 +931,15 @@ impl NodeHandler {
             // `handle_tx` was called for it previously.
             return Err(HandleTxError::AlreadyProcessed);
         }
+        if self.state_sync.is_some() {
+            // The storage is inconsistent until the state is fully imported.
+            return Err(HandleTxError::StateSync);
+        }
 
         let snapshot = self.blockchain.snapshot();
         let schema = Schema::new(&snapshot);
-        if schema.transactions().contains(&hash) {
+        if schema.transactions().contains(&hash) || schema.transactions_locations().contains(&hash)
+        {
             // Transaction is either committed or is present in the persistent pool.
             return Err(HandleTxError::AlreadyProcessed);
         }
@@ -991,6 +1091,10 @@ impl NodeHandler {
         if epoch != self.state.epoch() || round != self.state.round() {
             return;
         }
+        if self.state_sync.is_some() {
+            // The round timeout will be added after the state synchronization is finished.
+            return;
+        }
         warn!("ROUND TIMEOUT epoch={}, round={}", epoch, round);
 
         // Update the node state to the new round.
@@ -1011,6 +1115,9 @@ impl NodeHandler {
         if self.state.locked_propose().is_some() || self.state.have_prevote(round) {
             return;
         }
+        if self.state_sync.is_some() {
+            return;
+        }
 
         let validator_id = if let Some(validator_id) = self.state.validator_id() {
             validator_id
@@ -1036,7 +1143,21 @@ impl NodeHandler {
                 self.state.last_hash(),
             ),
         };
-        let propose = self.sign_message(propose);
+        if let Some(propose) = self.sign_consensus_message(propose) {
+            self.handle_own_propose(propose);
+        }
+    }
+
+    /// Broadcasts our `Propose` and sends a prevote for it.
+    fn handle_own_propose(&mut self, propose: Verified<Propose>) {
+        // The state of the node may have changed if the propose was signed asynchronously.
+        let round = propose.payload().round;
+        if propose.payload().epoch != self.state.epoch() || round != self.state.round() {
+            return;
+        }
+        if self.state.locked_propose().is_some() || self.state.have_prevote(round) {
+            return;
+        }
 
         trace!("Broadcast propose: {:?}", propose);
         self.broadcast(propose.clone());
@@ -1052,6 +1173,47 @@ impl NodeHandler {
         }
     }
 
+    /// Handles a consensus message signed by a blocking signer. If the signer has failed,
+    /// `message` is `None`.
+    pub(crate) fn handle_signed_message(&mut self, payload: &[u8], message: Option<Message>) {
+        if let Ok(ExonumMessage::Prevote(prevote)) = ExonumMessage::from_bytes(payload.into()) {
+            if prevote.epoch == self.state.epoch() {
+                self.state.remove_pending_prevote(prevote.round);
+            }
+        }
+
+        match message {
+            Some(Message::Consensus(ConsensusMessage::Propose(propose))) => {
+                self.handle_own_propose(propose)
+            }
+            Some(Message::Consensus(ConsensusMessage::Prevote(prevote))) => {
+                if prevote.payload().epoch != self.state.epoch() {
+                    return;
+                }
+                let (round, propose_hash) =
+                    (prevote.payload().round, prevote.payload().propose_hash);
+                if self.add_own_prevote(prevote) {
+                    self.handle_majority_prevotes(round, propose_hash);
+                }
+            }
+            Some(Message::Consensus(ConsensusMessage::Precommit(precommit))) => {
+                if precommit.payload().epoch != self.state.epoch() {
+                    return;
+                }
+                let round = precommit.payload().round;
+                let propose_hash = precommit.payload().propose_hash;
+                let block_hash = precommit.payload().block_hash;
+                self.add_own_precommit(precommit);
+                if self.state.has_majority_precommits(round, block_hash) {
+                    self.handle_majority_precommits(round, &propose_hash, &block_hash);
+                }
+            }
+            Some(Message::Service(Service::Status(status))) => self.broadcast(status),
+            Some(other) => error!("Signer has produced an unexpected message: {:?}", other),
+            None => {}
+        }
+    }
+
     fn get_propose_template(&mut self) -> ProposeTemplate {
         let txs_cache_len = self.state.tx_cache_len() as u64;
         info!("LEADER: cache = {}", txs_cache_len);
@@ -1130,6 +1292,10 @@ impl NodeHandler {
                 } => self
                     .sign_message(BlockRequest::with_epoch(peer, block_height, epoch))
                     .into(),
+
+                RequestData::StateChunk(height, ref cursor) => self
+                    .sign_message(StateChunkRequest::new(peer, height, cursor))
+                    .into(),
             };
 
             trace!("Send request {:?} to peer {:?}", data, peer);
@@ -1213,6 +1379,10 @@ impl NodeHandler {
             trace!("Not sending a request for the next block because the node is paused.");
             return;
         }
+        if self.state_sync.is_some() {
+            trace!("Not sending a request for the next block during state synchronization.");
+            return;
+        }
 
         // TODO: Randomize next peer. (ECR-171)
         let peers = self.state.advanced_peers();
@@ -1222,7 +1392,7 @@ impl NodeHandler {
     }
 
     /// Removes the specified request from the pending request list.
-    fn remove_request(&mut self, data: &RequestData) -> HashSet<PublicKey> {
+    pub(crate) fn remove_request(&mut self, data: &RequestData) -> HashSet<PublicKey> {
         // TODO: Clear timeout. (ECR-171)
         self.state.remove_request(data)
     }
@@ -1234,13 +1404,31 @@ impl NodeHandler {
             .validator_id()
             .expect("called broadcast_prevote in Auditor node.");
         let locked_round = self.state.locked_round();
-        let prevote = self.sign_message(Prevote::new(
+        let prevote = Prevote::new(
             validator_id,
             self.state.epoch(),
             round,
             propose_hash,
             locked_round,
-        ));
+        );
+        let is_blocking = self.state.signer().is_blocking();
+        match self.sign_consensus_message(prevote) {
+            Some(prevote) => self.add_own_prevote(prevote),
+            None => {
+                if is_blocking {
+                    self.state.add_pending_prevote(round);
+                }
+                false
+            }
+        }
+    }
+
+    /// Adds our `Prevote` to the state and broadcasts it to all peers.
+    ///
+    /// Returns `true` if majority of prevotes is achieved, and returns `false` otherwise.
+    fn add_own_prevote(&mut self, prevote: Verified<Prevote>) -> bool {
+        let round = prevote.payload().round;
+        let propose_hash = prevote.payload().propose_hash;
         let has_majority_prevotes = self.state.add_prevote(prevote.clone());
 
         // Save the outgoing `Prevote` to the consensus messages cache before broadcast.
@@ -1262,14 +1450,25 @@ impl NodeHandler {
             .state
             .validator_id()
             .expect("called broadcast_precommit in Auditor node.");
-        let precommit = self.sign_message(Precommit::new(
+        let mut precommit = Precommit::new(
             validator_id,
             self.state.epoch(),
             round,
             propose_hash,
             block_hash,
             self.system_state.current_time().into(),
-        ));
+        );
+        if let Some(signature) = self.sign_commit_certificate(validator_id, round, &block_hash) {
+            precommit = precommit.with_bls_signature(signature);
+        }
+        if let Some(precommit) = self.sign_consensus_message(precommit) {
+            self.add_own_precommit(precommit);
+        }
+    }
+
+    /// Adds our `Precommit` to the state and broadcasts it to all peers.
+    fn add_own_precommit(&mut self, precommit: Verified<Precommit>) {
+        let round = precommit.payload().round;
         self.state.add_precommit(precommit.clone());
 
         // Put our Precommit to the consensus cache before broadcast.
@@ -1282,8 +1481,30 @@ impl NodeHandler {
         self.broadcast(precommit);
     }
 
+    /// Signs the block hash with the BLS key held by the node signer, provided that the key
+    /// is registered in the consensus configuration. The signature is used to build a commit
+    /// certificate.
+    fn sign_commit_certificate(
+        &self,
+        validator_id: ValidatorId,
+        round: Round,
+        block_hash: &Hash,
+    ) -> Option<BlsSignature> {
+        let bls_key = self.state.validators()[validator_id.0 as usize].bls_key?;
+        let signer = self.state.signer();
+        if signer.bls_public_key()? != bls_key.public_key {
+            warn!("BLS key of the node does not match the key in the consensus configuration");
+            return None;
+        }
+
+        signer
+            .sign_block_hash(self.state.epoch(), round, block_hash)
+            .map_err(|e| error!("Cannot sign block hash with the BLS key: {}", e))
+            .ok()
+    }
+
     /// Checks that pre-commits count is correct and calls `validate_precommit` for each of them.
-    fn validate_precommits(
+    pub(crate) fn validate_precommits(
         &self,
         precommits: &[Verified<Precommit>],
         epoch: Height,
@@ -18,8 +18,8 @@ use tokio::{task, time::delay_for};
 
 use std::time::{Duration, SystemTime};
 
-use super::{InternalEvent, InternalRequest, TimeoutRequest};
-use crate::messages::{ExonumMessage, Message};
+use super::{InternalEvent, InternalRequest, SignRequest, TimeoutRequest};
+use crate::messages::Message;
 
 /// Processor of `InternalRequest`s that emits `InternalEvent`s as a result.
 #[derive(Debug)]
@@ -39,9 +39,7 @@ impl InternalPart {
 
     async fn verify_message(raw: Vec<u8>, internal_tx: mpsc::Sender<InternalEvent>) {
         let task = task::spawn_blocking(|| {
-            SignedMessage::from_bytes(raw.into())
-                .and_then(SignedMessage::into_verified::<ExonumMessage>)
-                .map(Message::from)
+            SignedMessage::from_bytes(raw.into()).and_then(Message::from_signed)
         });
         if let Ok(Ok(msg)) = task.await {
             let event = InternalEvent::message_verified(msg);
@@ -49,6 +47,12 @@ impl InternalPart {
         }
     }
 
+    async fn sign_message(request: SignRequest, internal_tx: mpsc::Sender<InternalEvent>) {
+        if let Ok(event) = task::spawn_blocking(|| request.sign()).await {
+            Self::send_event(internal_tx, event).await;
+        }
+    }
+
     /// Represents a task that processes internal requests and produces internal events.
     /// `handle` is used to schedule additional tasks within this task.
     /// `verify_executor` is where transaction verification tasks are executed.
@@ -81,6 +85,10 @@ impl InternalPart {
                     let event = InternalEvent::jump_to_round(height, round);
                     tokio::spawn(Self::send_event(internal_tx, event));
                 }
+
+                InternalRequest::SignMessage(request) => {
+                    tokio::spawn(Self::sign_message(request, internal_tx));
+                }
             }
         }
     }
@@ -32,11 +32,16 @@ use futures::{channel::mpsc, prelude::*};
 use std::{
     cmp::Ordering,
     pin::Pin,
+    sync::Arc,
     task::{Context, Poll},
     time::SystemTime,
 };
 
-use crate::{messages::Message, ExternalMessage, NodeTimeout};
+use crate::{
+    messages::Message,
+    signer::{sign_payload, Signer},
+    ExternalMessage, NodeTimeout,
+};
 
 #[cfg(test)]
 mod tests;
@@ -128,6 +133,13 @@ impl InternalEvent {
     pub(crate) fn timeout(timeout: NodeTimeout) -> Self {
         Self(InternalEventInner::Timeout(timeout))
     }
+
+    pub(crate) fn message_signed(payload: Vec<u8>, message: Option<Message>) -> Self {
+        Self(InternalEventInner::MessageSigned {
+            payload,
+            message: message.map(Box::new),
+        })
+    }
 }
 
 #[derive(Debug, PartialEq)]
@@ -139,6 +151,12 @@ pub(crate) enum InternalEventInner {
     /// Message has been successfully verified.
     /// Message is boxed here so that enum variants have similar size.
     MessageVerified(Box<Message>),
+    /// Consensus message has been signed by a blocking signer. If the signer has failed,
+    /// `message` is `None`.
+    MessageSigned {
+        payload: Vec<u8>,
+        message: Option<Box<Message>>,
+    },
 }
 
 /// Asynchronous requests for internal actions.
@@ -150,6 +168,32 @@ pub enum InternalRequest {
     JumpToRound(Height, Round),
     /// Verify a message in the thread pool.
     VerifyMessage(Vec<u8>),
+    /// Sign a consensus message in the thread pool.
+    SignMessage(SignRequest),
+}
+
+/// Request to sign a consensus message with a blocking signer.
+#[derive(Debug)]
+pub struct SignRequest {
+    pub(crate) signer: Arc<dyn Signer>,
+    pub(crate) payload: Vec<u8>,
+}
+
+impl SignRequest {
+    pub(crate) fn new(signer: Arc<dyn Signer>, payload: Vec<u8>) -> Self {
+        Self { signer, payload }
+    }
+
+    /// Signs the message in the current thread. The signed message is `None` if the signer
+    /// has failed.
+    pub(crate) fn sign(self) -> InternalEvent {
+        let message = sign_payload(self.signer.as_ref(), self.payload.clone()).and_then(|raw| {
+            Message::from_signed(raw)
+                .map_err(|e| log::error!("Signer has produced an invalid message: {}", e))
+                .ok()
+        });
+        InternalEvent::message_signed(self.payload, message)
+    }
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -122,6 +122,7 @@ pub enum NetworkEvent {
 #[derive(Debug, Clone)]
 pub enum NetworkRequest {
     SendMessage(PublicKey, SignedMessage),
+    UpdateConnectMessage(Verified<Connect>),
     #[cfg(test)]
     DisconnectWithPeer(PublicKey),
 }
@@ -305,7 +306,7 @@ struct NetworkHandler {
     pool: SharedConnectionPool,
     network_config: NetworkConfiguration,
     network_tx: mpsc::Sender<NetworkEvent>,
-    handshake_params: HandshakeParams,
+    handshake_params: Arc<RwLock<HandshakeParams>>,
     connect_list: SharedConnectList,
 }
 
@@ -323,11 +324,19 @@ impl NetworkHandler {
             pool: connection_pool,
             network_config,
             network_tx,
-            handshake_params,
+            handshake_params: Arc::new(RwLock::new(handshake_params)),
             connect_list,
         }
     }
 
+    /// Returns the current handshake parameters.
+    fn handshake_params(&self) -> HandshakeParams {
+        self.handshake_params
+            .read()
+            .expect("HandshakeParams read lock")
+            .clone()
+    }
+
     async fn listener(self) -> anyhow::Result<()> {
         let mut listener = TcpListener::bind(&self.listen_address).await?;
         let mut incoming_connections = listener.incoming();
@@ -357,7 +366,7 @@ impl NetworkHandler {
             let pool = self.pool.clone();
             let connect_list = self.connect_list.clone();
             let network_tx = self.network_tx.clone();
-            let handshake = NoiseHandshake::responder(&self.handshake_params);
+            let handshake = NoiseHandshake::responder(&self.handshake_params());
 
             let task = async move {
                 let HandshakeData {
@@ -570,6 +579,14 @@ impl NetworkHandler {
                     });
                 }
 
+                NetworkRequest::UpdateConnectMessage(connect) => {
+                    let mut params = self
+                        .handshake_params
+                        .write()
+                        .expect("HandshakeParams write lock");
+                    params.connect = connect;
+                }
+
                 #[cfg(test)]
                 NetworkRequest::DisconnectWithPeer(peer) => {
                     let disconnected = self.pool.write().remove(&peer, None);
@@ -607,9 +624,9 @@ impl NetworkHandler {
         key: PublicKey,
         message: SignedMessage,
     ) -> anyhow::Result<()> {
-        self.connect(key, &self.handshake_params).await?;
-        let connect = &self.handshake_params.connect;
-        if message != *connect.as_raw() {
+        let handshake_params = self.handshake_params();
+        self.connect(key, &handshake_params).await?;
+        if message != *handshake_params.connect.as_raw() {
             self.pool.send_message(&key, message).await;
         }
         Ok(())
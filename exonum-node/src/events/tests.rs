@@ -87,6 +87,13 @@ impl TestHandler {
             .unwrap();
     }
 
+    pub async fn update_connect_message(&mut self, connect: Verified<Connect>) {
+        self.network_requests_tx
+            .send(NetworkRequest::UpdateConnectMessage(connect))
+            .await
+            .unwrap();
+    }
+
     pub async fn send_to(&mut self, key: PublicKey, raw: SignedMessage) {
         self.network_requests_tx
             .send(NetworkRequest::SendMessage(key, raw))
@@ -188,6 +195,7 @@ pub fn raw_message(payload_len: usize) -> SignedMessage {
 
 #[derive(Debug, Clone)]
 struct ConnectionParams {
+    keypair: KeyPair,
     connect: Verified<Connect>,
     connect_info: ConnectInfo,
     address: SocketAddr,
@@ -235,10 +243,11 @@ impl ConnectionParams {
         };
 
         Self {
+            keypair,
             connect,
+            connect_info,
             address,
             handshake_params,
-            connect_info,
         }
     }
 
@@ -277,6 +286,36 @@ async fn test_network_handshake() {
     assert_eq!(e2.wait_for_disconnect().await, first_key);
 }
 
+#[tokio::test]
+async fn test_network_handshake_with_updated_connect() {
+    let first = "127.0.0.1:17240".parse().unwrap();
+    let second = "127.0.0.1:17241".parse().unwrap();
+    let mut connect_list = ConnectList::default();
+
+    let mut t1 = ConnectionParams::from_address(first);
+    connect_list.add(t1.connect_info.clone());
+
+    let mut t2 = ConnectionParams::from_address(second);
+    let second_key = t2.connect_info.public_key;
+    connect_list.add(t2.connect_info.clone());
+
+    let connect_list = SharedConnectList::from_connect_list(connect_list);
+    let e1 = TestEvents::with_addr(first, &connect_list);
+    let e2 = TestEvents::with_addr(second, &connect_list);
+    let mut e1 = t1.spawn(e1, connect_list.clone());
+    let mut e2 = t2.spawn(e2, connect_list);
+
+    // The updated message announces a new consensus key of the node.
+    let connect = Connect::new(first.to_string(), SystemTime::now().into(), user_agent())
+        .with_consensus_key(KeyPair::random().public_key());
+    let connect = Verified::from_value(connect, t1.keypair.public_key(), t1.keypair.secret_key());
+    e1.update_connect_message(connect.clone()).await;
+
+    e1.connect_with(second_key, connect.clone()).await;
+    assert_eq!(e2.wait_for_connect().await, connect);
+    assert_eq!(e1.wait_for_connect().await, t2.connect);
+}
+
 #[tokio::test]
 async fn test_network_big_message() {
     let first = "127.0.0.1:17200".parse().unwrap();
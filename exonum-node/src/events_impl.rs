@@ -50,6 +50,9 @@ impl NodeHandler {
             InternalEventInner::Timeout(timeout) => self.handle_timeout(timeout),
             InternalEventInner::JumpToRound(height, round) => self.handle_new_round(height, round),
             InternalEventInner::MessageVerified(msg) => self.handle_message(*msg),
+            InternalEventInner::MessageSigned { payload, message } => {
+                self.handle_signed_message(&payload, message.map(|message| *message))
+            }
         }
     }
 
@@ -69,7 +69,7 @@ use exonum::{
     crypto::{self, Hash, PublicKey},
     helpers::{user_agent, Height, Milliseconds, Round, ValidateInput, ValidatorId},
     keys::Keys,
-    merkledb::{Database, ObjectHash},
+    merkledb::{BinaryValue, Database, ObjectHash, Snapshot},
     messages::{AnyTx, IntoMessage, SignedMessage, Verified},
     runtime::RuntimeInstance,
 };
@@ -99,11 +99,14 @@ use crate::{
     connect_list::ConnectList,
     events::{
         HandlerPart, HandshakeParams, InternalEvent, InternalPart, InternalRequest, NetworkEvent,
-        NetworkPart, NetworkRequest, SyncSender, TimeoutRequest,
+        NetworkPart, NetworkRequest, SignRequest, SyncSender, TimeoutRequest,
     },
     messages::Connect,
     pool::{ManagePool, StandardPoolManager},
+    pruning::{Pruner, PrunerHandle},
+    requests::StateSync,
     schema::NodeSchema,
+    signer::{sign_payload, LocalSigner, RemoteSigner, RemoteSignerConfig, Signer},
     state::{RequestData, State},
 };
 
@@ -117,10 +120,12 @@ mod messages;
 mod plugin;
 pub mod pool;
 mod proto;
+mod pruning;
 mod requests;
 #[cfg(test)]
 mod sandbox;
 mod schema;
+pub mod signer;
 mod state;
 
 // Logically private types re-exported for benchmarks.
@@ -206,6 +211,15 @@ pub(crate) struct NodeHandler {
     allow_expedited_propose: bool,
     /// Pool manager.
     pool_manager: Box<dyn ManagePool>,
+    /// Handle of the pruner of historical blockchain data, if the node prunes the data.
+    pruner: Option<PrunerHandle>,
+    /// Can the node synchronize the blockchain state with other nodes?
+    allow_state_sync: bool,
+    /// Ongoing synchronization of the blockchain state, if any.
+    state_sync: Option<StateSync>,
+    /// Snapshot of the state at the specified height, from which state chunks
+    /// are exported to other nodes.
+    state_chunk_source: Option<(Height, Box<dyn Snapshot>)>,
 }
 
 /// HTTP API configuration options.
@@ -388,6 +402,63 @@ impl Default for FlushPoolStrategy {
     }
 }
 
+/// Retention policy for the historical blockchain data stored by the node.
+///
+/// The policy determines for which blocks the node keeps transactions, call records
+/// and precommits. Block headers and locations of committed transactions are always
+/// retained. Pruning does not influence the blockchain state, but nodes pruning the data
+/// cannot serve the pruned blocks to other nodes (e.g., to the nodes catching up
+/// with the network), or provide the pruned data via the HTTP API.
+///
+/// The pruning is performed in a background thread after committing each block, so that it
+/// does not stall the consensus if a large number of blocks needs to be pruned
+/// (e.g., after the policy was changed for an existing node).
+///
+/// # Examples
+///
+/// ```
+/// # use exonum_node::RetentionPolicy;
+/// // Keep transactions and precommits for the last 10,000 blocks.
+/// let policy = RetentionPolicy::KeepLast { blocks: 10_000 };
+/// # drop(policy);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum RetentionPolicy {
+    /// Keep the full history of the blockchain. This is the default policy.
+    Archive,
+
+    /// Keep transactions, call records and precommits only for the specified number
+    /// of the latest blocks.
+    KeepLast {
+        /// Number of latest blocks to keep the data for. Must be positive.
+        blocks: u64,
+    },
+
+    /// Keep only block headers. Transactions, call records and precommits are kept only
+    /// for the latest block, which is required for the node operation.
+    HeadersOnly,
+}
+
+impl RetentionPolicy {
+    /// Returns the number of latest blocks for which the data should be retained,
+    /// or `None` if the data should be retained for all blocks.
+    fn retained_blocks(self) -> Option<u64> {
+        match self {
+            Self::Archive => None,
+            Self::KeepLast { blocks } => Some(blocks),
+            Self::HeadersOnly => Some(1),
+        }
+    }
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self::Archive
+    }
+}
+
 /// Configuration for the `Node`.
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct NodeConfig {
@@ -407,6 +478,30 @@ pub struct NodeConfig {
     pub connect_list: ConnectListConfig,
     /// Number of threads allocated for transaction verification.
     pub thread_pool_size: Option<u8>,
+    /// Retention policy for the historical blockchain data.
+    #[serde(default)]
+    pub retention: RetentionPolicy,
+    /// Allows a node, which has no blocks except for the genesis one, to fetch
+    /// the latest blockchain state from its peers instead of replaying all blocks.
+    /// The state is imported into a staging database and authenticated by the `state_hash`
+    /// in the latest block backed by precommits of the genesis validators.
+    ///
+    /// The validator set must not have changed since the genesis block; otherwise, the block
+    /// cannot be verified, and the node falls back to replaying blocks. Note that the genesis
+    /// validator keys remain trusted by syncing nodes even after they are no longer used,
+    /// so a leak of these keys allows to endorse a fake state. Transactions
+    /// and precommits are synchronized only for the latest block. If the blockchain advances
+    /// during the synchronization, peers need to keep the state history
+    /// (see `BlockchainBuilder::with_state_history()`) to provide the remaining state chunks.
+    #[serde(default)]
+    pub state_sync: bool,
+    /// Remote signer for consensus messages. If not specified, consensus messages
+    /// are signed with the consensus key of the node. See the [`signer`] module docs
+    /// for details.
+    ///
+    /// [`signer`]: signer/index.html
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remote_signer: Option<RemoteSignerConfig>,
 }
 
 impl ValidateInput for NodeConfig {
@@ -434,6 +529,10 @@ impl ValidateInput for NodeConfig {
             restart_policy.retry_timeout > 0,
             "`server_restart.retry_timeout` must be strictly larger than 0"
         );
+        ensure!(
+            self.retention.retained_blocks() != Some(0),
+            "`retention.blocks` must be strictly larger than 0"
+        );
 
         // Sanity checks for cases of accidental negative overflows.
         let sanity_max = 2_usize.pow(16);
@@ -469,6 +568,12 @@ pub(crate) struct Configuration {
     pub mempool: MemoryPoolConfig,
     /// Validator keys.
     pub keys: Keys,
+    /// Can the node synchronize the blockchain state with other nodes?
+    pub state_sync: bool,
+    /// Signer of consensus messages.
+    pub signer: Arc<dyn Signer>,
+    /// Keys the node switches to once they are activated by the consensus configuration.
+    pub pending_keys: Option<Keys>,
 }
 
 /// Channel for messages, timeouts and api requests. Consumed by the `NodeHandler` constructor.
@@ -523,7 +628,7 @@ impl NodeHandler {
     /// Creates `NodeHandler` using specified `Configuration`.
     #[allow(clippy::too_many_arguments)]
     pub fn new(
-        blockchain: BlockchainMut,
+        mut blockchain: BlockchainMut,
         external_address: &str,
         sender: NodeSender,
         system_state: Box<dyn SystemStateProvider>,
@@ -549,8 +654,9 @@ impl NodeHandler {
             .iter()
             .collect();
         let peer_discovery = config.peer_discovery.clone();
+        let allow_state_sync = config.state_sync;
 
-        let state = State::new(
+        let mut state = State::new(
             config,
             consensus_config,
             connect,
@@ -559,6 +665,11 @@ impl NodeHandler {
             last_block_skip.as_ref(),
             system_state.current_time(),
         );
+        // Pending keys may have been activated before the node was restarted.
+        if let Some(keys) = state.activate_pending_keys() {
+            blockchain.set_service_keypair(keys.service.clone());
+            state.update_our_connect_message(system_state.current_time().into());
+        }
 
         let validator_id = state.validator_id();
         let node_role = NodeRole::new(validator_id);
@@ -578,6 +689,10 @@ impl NodeHandler {
             config_manager,
             allow_expedited_propose: true,
             pool_manager,
+            pruner: None,
+            allow_state_sync,
+            state_sync: None,
+            state_chunk_source: None,
         }
     }
 
@@ -592,6 +707,49 @@ impl NodeHandler {
         )
     }
 
+    /// Updates the consensus configuration of the node from the blockchain state,
+    /// switching to the pending keys of the node if they are activated by the configuration.
+    fn update_consensus_config(&mut self) {
+        let config = Schema::new(&self.blockchain.snapshot()).consensus_config();
+        if let Some(keys) = self.state.update_config(config) {
+            self.blockchain.set_service_keypair(keys.service.clone());
+            self.announce_consensus_key();
+        }
+    }
+
+    /// Announces the current consensus key of the node to peers with a new `Connect` message.
+    /// The message is also used in handshakes with new peers.
+    fn announce_consensus_key(&mut self) {
+        let time = self.system_state.current_time().into();
+        let connect = self.state.update_our_connect_message(time).clone();
+        let request = NetworkRequest::UpdateConnectMessage(connect.clone());
+        self.channel.network_requests.send(request);
+        self.broadcast(connect);
+    }
+
+    /// Signs a consensus message with the node signer. Returns `None` and logs an error
+    /// if the signer has failed.
+    ///
+    /// If the signer may block (e.g., if it is a remote signer), the message is signed
+    /// in the thread pool, and `None` is returned as well. The signed message is then
+    /// processed by `handle_signed_message`.
+    fn sign_consensus_message<T>(&mut self, message: T) -> Option<Verified<T>>
+    where
+        T: TryFrom<SignedMessage> + IntoMessage,
+    {
+        let payload = T::Container::from(message).into_bytes();
+        if self.state.signer().is_blocking() {
+            let request = SignRequest::new(self.state.shared_signer(), payload);
+            self.execute_later(InternalRequest::SignMessage(request));
+            return None;
+        }
+
+        sign_payload(self.state.signer(), payload)?
+            .into_verified()
+            .map_err(|e| log::error!("Signer has produced an invalid message: {}", e))
+            .ok()
+    }
+
     /// Return internal `SharedNodeState`
     fn api_state(&self) -> &SharedNodeState {
         &self.api_state
@@ -754,7 +912,11 @@ impl NodeHandler {
     }
 
     /// Adds request timeout if it isn't already requested.
+    ///
+    /// The peer may be specified by its consensus key; the request is sent
+    /// to the network key of the peer.
     fn request(&mut self, data: RequestData, peer: PublicKey) {
+        let peer = self.state.network_key_of(&peer);
         let is_new = self.state.request(data.clone(), peer);
         if is_new {
             self.add_request_timeout(data, None);
@@ -986,6 +1148,8 @@ pub struct Node {
     max_message_len: u32,
     thread_pool_size: Option<u8>,
     disable_signals: bool,
+    /// Pruner of historical blockchain data, which is launched together with the node.
+    pruner: Option<Pruner>,
 }
 
 impl Default for NodeChannel {
@@ -1041,6 +1205,8 @@ pub struct NodeBuilder {
     config_manager: Option<Box<dyn ConfigManager>>,
     pool_manager: Box<dyn ManagePool>,
     plugins: Vec<Box<dyn NodePlugin>>,
+    signer: Option<Arc<dyn Signer>>,
+    pending_keys: Option<Keys>,
     disable_signals: bool,
 }
 
@@ -1051,6 +1217,7 @@ impl fmt::Debug for NodeBuilder {
             .field("channel", &self.channel)
             .field("blockchain_builder", &self.blockchain_builder)
             .field("node_config", &self.node_config)
+            .field("signer", &self.signer)
             .finish()
     }
 }
@@ -1078,6 +1245,8 @@ impl NodeBuilder {
             config_manager: None,
             plugins: vec![],
             pool_manager: Box::new(StandardPoolManager::default()),
+            signer: None,
+            pending_keys: None,
             disable_signals: false,
         }
     }
@@ -1135,6 +1304,29 @@ impl NodeBuilder {
         self
     }
 
+    /// Sets the signer of consensus messages, overriding the [`remote_signer`] option
+    /// of the node configuration. The public key of the signer is used to determine
+    /// whether the node is a validator.
+    ///
+    /// [`remote_signer`]: struct.NodeConfig.html#structfield.remote_signer
+    pub fn with_signer<T: Signer + 'static>(mut self, signer: T) -> Self {
+        self.signer = Some(Arc::new(signer));
+        self
+    }
+
+    /// Sets the pending keys of the node, which are used for key rotation.
+    ///
+    /// The node switches to the pending keys as soon as their consensus key appears
+    /// in the consensus configuration, i.e., at the height at which the configuration
+    /// with new keys of the validator is activated. After the switch, the node signs
+    /// consensus messages with the new consensus key (overriding the signer of the node)
+    /// and uses the new service key for transactions generated by services.
+    /// The keys from the constructor are still used to authenticate the node in the network.
+    pub fn with_pending_keys(mut self, keys: Keys) -> Self {
+        self.pending_keys = Some(keys);
+        self
+    }
+
     /// Switches off [default signal handling] for the node.
     /// This is useful to implement more complex signal handling, or one that differs
     /// from the default.
@@ -1160,6 +1352,8 @@ impl NodeBuilder {
             self.config_manager,
             self.plugins,
             self.pool_manager,
+            self.signer,
+            self.pending_keys,
         );
         node.disable_signals = self.disable_signals;
         node
@@ -1168,6 +1362,7 @@ impl NodeBuilder {
 
 impl Node {
     /// Creates a node for the given blockchain and node configuration.
+    #[allow(clippy::too_many_arguments)]
     fn with_blockchain(
         blockchain: BlockchainMut,
         channel: NodeChannel,
@@ -1176,9 +1371,18 @@ impl Node {
         config_manager: Option<Box<dyn ConfigManager>>,
         plugins: Vec<Box<dyn NodePlugin>>,
         pool_manager: Box<dyn ManagePool>,
+        signer: Option<Arc<dyn Signer>>,
+        pending_keys: Option<Keys>,
     ) -> Self {
         crypto::init();
 
+        let signer = signer.unwrap_or_else(|| match node_cfg.remote_signer.clone() {
+            Some(signer_config) => Arc::new(RemoteSigner::new(
+                signer_config,
+                node_keys.consensus.clone(),
+            )),
+            None => Arc::new(LocalSigner::new(node_keys.consensus.clone())),
+        });
         let peers = node_cfg.connect_list.addresses();
         let config = Configuration {
             connect_list: ConnectList::from_config(node_cfg.connect_list),
@@ -1186,6 +1390,9 @@ impl Node {
             network: node_cfg.network,
             peer_discovery: peers,
             keys: node_keys,
+            state_sync: node_cfg.state_sync,
+            signer,
+            pending_keys,
         };
 
         let api_state = SharedNodeState::new(node_cfg.api.state_update_timeout as u64);
@@ -1220,6 +1427,7 @@ impl Node {
         let api_runtime_config = ApiManagerConfig::new(servers, api_aggregator)
             .with_retries(restart_policy.retry_timeout, restart_policy.max_retries);
 
+        let pruner = Pruner::new(blockchain.immutable_view(), node_cfg.retention);
         let mut handler = NodeHandler::new(
             blockchain,
             &node_cfg.external_address,
@@ -1241,6 +1449,7 @@ impl Node {
             thread_pool_size: node_cfg.thread_pool_size,
             api_manager_config: api_runtime_config,
             disable_signals: false,
+            pruner,
         }
     }
 
@@ -1316,7 +1525,8 @@ struct Reactor {
 }
 
 impl Reactor {
-    fn new(node: Node) -> Self {
+    fn new(mut node: Node) -> Self {
+        node.handler.pruner = node.pruner.take().map(Pruner::spawn);
         let connect_message = node.state().our_connect_message().clone();
         let connect_list = node.state().connect_list();
         let shutdown_handle = node.shutdown_handle();
@@ -1494,6 +1704,9 @@ pub fn generate_testnet_config(count: u16, start_port: u16) -> Vec<(NodeConfig,
                 api: NodeApiConfig::default(),
                 mempool: MemoryPoolConfig::default(),
                 thread_pool_size: None,
+                retention: RetentionPolicy::default(),
+                state_sync: false,
+                remote_signer: None,
             };
             (config, keys)
         })
@@ -1587,4 +1800,47 @@ mod tests {
         let restored: MemoryPoolConfig = toml::from_str(config_without_strategy).unwrap();
         assert_eq!(restored, MemoryPoolConfig::default());
     }
+
+    #[test]
+    #[should_panic(expected = "`retention.blocks` must be strictly larger than 0")]
+    fn test_bad_retention_policy() {
+        let db = TemporaryDB::new();
+        let (mut node_cfg, node_keys) = generate_testnet_config(1, 16_500).pop().unwrap();
+        node_cfg.retention = RetentionPolicy::KeepLast { blocks: 0 };
+        NodeBuilder::new(db, node_cfg, node_keys);
+    }
+
+    #[test]
+    fn retention_policy_is_serializable() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Config {
+            #[serde(default)]
+            retention: RetentionPolicy,
+        }
+
+        let policies = vec![
+            RetentionPolicy::Archive,
+            RetentionPolicy::KeepLast { blocks: 1_000 },
+            RetentionPolicy::HeadersOnly,
+        ];
+        for retention in policies {
+            let config = Config { retention };
+            let s = toml::to_string(&config).unwrap();
+            let restored: Config = toml::from_str(&s).unwrap();
+            assert_eq!(restored, config);
+        }
+
+        let config = r#"
+            [retention]
+            type = "keep_last"
+            blocks = 100
+        "#;
+        let restored: Config = toml::from_str(config).unwrap();
+        assert_eq!(
+            restored.retention,
+            RetentionPolicy::KeepLast { blocks: 100 }
+        );
+        let restored: Config = toml::from_str("").unwrap();
+        assert_eq!(restored.retention, RetentionPolicy::Archive);
+    }
 }
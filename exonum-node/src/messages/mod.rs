@@ -14,8 +14,9 @@
 
 pub use self::types::*;
 
+use anyhow::ensure;
 use exonum::{
-    crypto::{Hash, PublicKey, PUBLIC_KEY_LENGTH},
+    crypto::{schemes::SignatureScheme, Hash, PublicKey, PUBLIC_KEY_LENGTH},
     helpers::{Height, Round, ValidatorId},
     merkledb::{BinaryValue, ObjectHash},
     messages::{AnyTx, Precommit, SignedMessage, Verified, SIGNED_MESSAGE_MIN_SIZE},
@@ -88,6 +89,8 @@ pub enum Responses {
     TransactionsResponse(Verified<TransactionsResponse>),
     /// Block response message.
     BlockResponse(Verified<BlockResponse>),
+    /// State chunk response message.
+    StateChunkResponse(Verified<StateChunkResponse>),
 }
 
 impl Responses {
@@ -95,6 +98,7 @@ impl Responses {
         match self {
             Self::TransactionsResponse(msg) => msg.as_raw(),
             Self::BlockResponse(msg) => msg.as_raw(),
+            Self::StateChunkResponse(msg) => msg.as_raw(),
         }
     }
 }
@@ -111,6 +115,12 @@ impl From<Verified<BlockResponse>> for Responses {
     }
 }
 
+impl From<Verified<StateChunkResponse>> for Responses {
+    fn from(msg: Verified<StateChunkResponse>) -> Self {
+        Self::StateChunkResponse(msg)
+    }
+}
+
 /// Request messages.
 #[derive(Debug, Clone, PartialEq)]
 pub enum Requests {
@@ -126,6 +136,8 @@ pub enum Requests {
     BlockRequest(Verified<BlockRequest>),
     /// Request of uncommitted transactions.
     PoolTransactionsRequest(Verified<PoolTransactionsRequest>),
+    /// Request of a chunk of the blockchain state.
+    StateChunkRequest(Verified<StateChunkRequest>),
 }
 
 impl Requests {
@@ -137,6 +149,7 @@ impl Requests {
             Self::PeersRequest(msg) => msg.as_raw(),
             Self::BlockRequest(msg) => msg.as_raw(),
             Self::PoolTransactionsRequest(msg) => msg.as_raw(),
+            Self::StateChunkRequest(msg) => msg.as_raw(),
         }
     }
 }
@@ -156,8 +169,17 @@ pub enum Message {
 
 impl Message {
     /// Deserialize message from signed message.
+    ///
+    /// Only transactions may be signed with non-Ed25519 keys; consensus and other
+    /// node messages signed with such keys are rejected.
     pub fn from_signed(signed: SignedMessage) -> anyhow::Result<Self> {
-        signed.into_verified::<ExonumMessage>().map(From::from)
+        let verified = signed.into_verified::<ExonumMessage>()?;
+        ensure!(
+            matches!(verified.payload(), ExonumMessage::AnyTx(_))
+                || verified.as_raw().signature_scheme() == SignatureScheme::Ed25519,
+            "Only transactions may be signed with non-Ed25519 keys."
+        );
+        Ok(verified.into())
     }
 
     /// Checks buffer and returns instance of `Message`.
@@ -235,12 +257,14 @@ impl_message_from_verified! {
     Propose: Consensus,
     BlockResponse: Responses,
     TransactionsResponse: Responses,
+    StateChunkResponse: Responses,
     BlockRequest: Requests,
     PeersRequest: Requests,
     PrevotesRequest: Requests,
     ProposeRequest: Requests,
     TransactionsRequest: Requests,
-    PoolTransactionsRequest: Requests
+    PoolTransactionsRequest: Requests,
+    StateChunkRequest: Requests
 }
 
 impl Requests {
@@ -253,6 +277,7 @@ impl Requests {
             Self::PeersRequest(msg) => msg.payload().to,
             Self::BlockRequest(msg) => msg.payload().to,
             Self::PoolTransactionsRequest(msg) => msg.payload().to,
+            Self::StateChunkRequest(msg) => msg.payload().to,
         }
     }
 
@@ -265,6 +290,7 @@ impl Requests {
             Self::PeersRequest(msg) => msg.author(),
             Self::BlockRequest(msg) => msg.author(),
             Self::PoolTransactionsRequest(msg) => msg.author(),
+            Self::StateChunkRequest(msg) => msg.author(),
         }
     }
 }
@@ -329,8 +355,13 @@ mod tests {
     use chrono::Utc;
     use exonum::{
         blockchain::{AdditionalHeaders, Block, BlockProof},
-        crypto::{self, KeyPair},
+        crypto::{
+            self,
+            schemes::{AnyKeyPair, SignatureScheme},
+            KeyPair,
+        },
         merkledb::ObjectHash,
+        runtime::CallInfo,
     };
     use pretty_assertions::assert_eq;
 
@@ -382,6 +413,29 @@ mod tests {
         assert_eq!(err.to_string(), "Failed to verify signature.");
     }
 
+    #[test]
+    fn test_message_with_ecdsa_signature() {
+        let keypair = AnyKeyPair::random(SignatureScheme::Secp256k1);
+
+        let tx = AnyTx::new(CallInfo::new(100, 0), vec![1, 2, 3]).sign_with_any_keypair(&keypair);
+        let msg = Message::from_signed(tx.clone().into_raw()).unwrap();
+        assert_eq!(msg, Message::from(tx));
+
+        // Other messages must be signed with Ed25519 keys.
+        let status = Status {
+            epoch: Height(0),
+            blockchain_height: Height(0),
+            last_hash: Hash::zero(),
+            pool_size: 0,
+        };
+        let signed = SignedMessage::sign_with(ExonumMessage::from(status), &keypair);
+        let err = Message::from_signed(signed).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Only transactions may be signed with non-Ed25519 keys."
+        );
+    }
+
     #[test]
     fn test_verified_status_binary_value() {
         let keypair = KeyPair::random();
@@ -19,7 +19,7 @@ use exonum::{
     crypto::{Hash, PublicKey},
     helpers::{Height, Round, ValidatorId},
     impl_exonum_msg_try_from_signed,
-    merkledb::{BinaryValue, HashTag},
+    merkledb::{dump::ChunkCursor, BinaryValue, HashTag},
     messages::{AnyTx, Precommit, SignedMessage},
 };
 use exonum_derive::{BinaryValue, ObjectHash};
@@ -44,7 +44,8 @@ use crate::proto::consensus;
 ///
 /// A node sends `Connect` message to all known addresses during
 /// initialization. Additionally, the node responds by its own `Connect`
-/// message after receiving `node::Event::Connected`.
+/// message after receiving `node::Event::Connected`. A new `Connect` message
+/// is broadcast once the node switches to a new consensus key.
 #[derive(Clone, PartialEq, Eq, Ord, PartialOrd, Debug, ProtobufConvert)]
 #[protobuf_convert(source = "consensus::Connect")]
 pub struct Connect {
@@ -54,6 +55,10 @@ pub struct Connect {
     pub time: DateTime<Utc>,
     /// String containing information about this node including Exonum, Rust and OS versions.
     pub user_agent: String,
+    /// Consensus key of the node if it differs from the key the message is signed with
+    /// (e.g., if consensus messages are signed by a remote signer).
+    #[protobuf_convert(with = "pb_consensus_key")]
+    pub consensus_key: Option<PublicKey>,
 }
 
 impl Connect {
@@ -67,9 +72,16 @@ impl Connect {
             host: host.into(),
             time,
             user_agent: user_agent.into(),
+            consensus_key: None,
         }
     }
 
+    /// Specifies the consensus key of the node.
+    pub fn with_consensus_key(mut self, consensus_key: PublicKey) -> Self {
+        self.consensus_key = Some(consensus_key);
+        self
+    }
+
     /// The node's address.
     pub fn pub_addr(&self) -> &str {
         &self.host
@@ -86,6 +98,29 @@ impl Connect {
     }
 }
 
+/// Consensus key is stored as raw bytes, so that `Connect` messages without it
+/// retain their binary representation.
+mod pb_consensus_key {
+    use super::PublicKey;
+
+    #[allow(clippy::needless_pass_by_value)] // required for work with `protobuf_convert(with)`
+    pub fn from_pb(pb: Vec<u8>) -> anyhow::Result<Option<PublicKey>> {
+        if pb.is_empty() {
+            Ok(None)
+        } else {
+            PublicKey::from_slice(&pb)
+                .map(Some)
+                .ok_or_else(|| anyhow::format_err!("Invalid consensus key"))
+        }
+    }
+
+    pub fn to_pb(value: &Option<PublicKey>) -> Vec<u8> {
+        value
+            .as_ref()
+            .map_or_else(Vec::new, |key| key.as_bytes().to_vec())
+    }
+}
+
 /// Current node status.
 ///
 /// ### Validation
@@ -556,6 +591,144 @@ impl BlockRequest {
     }
 }
 
+/// Request for a chunk of the blockchain state.
+///
+/// ### Validation
+///
+/// The message is ignored if the state at the specified `height` is not available
+/// to the node, or if the cursor in the message is invalid.
+///
+/// ### Processing
+///
+/// `StateChunkResponse` message with the chunk of the state right after committing the block
+/// at the specified `height` is sent as the response.
+///
+/// ### Generation
+///
+/// This message is sent by the node synchronizing its state with other nodes. The first chunk
+/// is requested during `Status` processing; further chunks are requested after processing
+/// `StateChunkResponse`.
+#[derive(Clone, PartialEq, Eq, Ord, PartialOrd, Debug, ProtobufConvert)]
+#[protobuf_convert(source = "consensus::StateChunkRequest")]
+pub struct StateChunkRequest {
+    /// Public key of the recipient.
+    pub to: PublicKey,
+    /// Height of the block, the state after which is requested.
+    pub height: Height,
+    /// Ordinal number of the view, from which the chunk starts.
+    pub view: u64,
+    /// Key in the view, from which the chunk starts.
+    pub key: Vec<u8>,
+}
+
+impl StateChunkRequest {
+    /// Creates a new `StateChunkRequest`.
+    pub fn new(to: PublicKey, height: Height, cursor: &ChunkCursor) -> Self {
+        Self {
+            to,
+            height,
+            view: cursor.view,
+            key: cursor.key.clone(),
+        }
+    }
+
+    /// Returns the position of the requested chunk.
+    pub fn cursor(&self) -> ChunkCursor {
+        ChunkCursor {
+            view: self.view,
+            key: self.key.clone(),
+        }
+    }
+}
+
+/// Chunk of the blockchain state.
+///
+/// ### Validation
+///
+/// The message is ignored if:
+///
+/// - its `to` field corresponds to a different node
+/// - the node does not synchronize its state, or has not requested the chunk
+/// - the block is not approved by the supermajority of validators
+///
+/// ### Processing
+///
+/// The chunk is imported into the storage of the node. If the chunk is the last one,
+/// the imported state is checked against the `state_hash` of the block, and the node
+/// moves to the height following the block.
+///
+/// ### Generation
+///
+/// The message is sent as response to `StateChunkRequest`.
+#[derive(Clone, PartialEq, Eq, Ord, PartialOrd, Debug, ProtobufConvert)]
+#[protobuf_convert(source = "consensus::StateChunkResponse")]
+pub struct StateChunkResponse {
+    /// Public key of the recipient.
+    pub to: PublicKey,
+    /// Block, the state after which is contained in the chunk.
+    pub block: Block,
+    /// List of pre-commits for the block.
+    pub precommits: Vec<Vec<u8>>,
+    /// Ordinal number of the view, from which the chunk starts.
+    pub view: u64,
+    /// Key in the view, from which the chunk starts.
+    pub key: Vec<u8>,
+    /// Chunk contents.
+    pub chunk: Vec<u8>,
+    /// Is this chunk the last one?
+    pub is_last: bool,
+    /// Ordinal number of the view, from which the next chunk starts.
+    pub next_view: u64,
+    /// Key in the view, from which the next chunk starts.
+    pub next_key: Vec<u8>,
+}
+
+impl StateChunkResponse {
+    /// Creates a new `StateChunkResponse`.
+    pub fn new(
+        to: PublicKey,
+        block: Block,
+        precommits: impl IntoIterator<Item = Vec<u8>>,
+        cursor: &ChunkCursor,
+        chunk: Vec<u8>,
+        next_cursor: Option<ChunkCursor>,
+    ) -> Self {
+        let is_last = next_cursor.is_none();
+        let next_cursor = next_cursor.unwrap_or_default();
+        Self {
+            to,
+            block,
+            precommits: precommits.into_iter().collect(),
+            view: cursor.view,
+            key: cursor.key.clone(),
+            chunk,
+            is_last,
+            next_view: next_cursor.view,
+            next_key: next_cursor.key,
+        }
+    }
+
+    /// Returns the position of the chunk.
+    pub fn cursor(&self) -> ChunkCursor {
+        ChunkCursor {
+            view: self.view,
+            key: self.key.clone(),
+        }
+    }
+
+    /// Returns the position of the next chunk, or `None` if this chunk is the last one.
+    pub fn next_cursor(&self) -> Option<ChunkCursor> {
+        if self.is_last {
+            None
+        } else {
+            Some(ChunkCursor {
+                view: self.next_view,
+                key: self.next_key.clone(),
+            })
+        }
+    }
+}
+
 /// Enumeration of all possible types of Exonum messages which are used in P2P communication
 /// between nodes.
 #[derive(Clone, PartialEq, Eq, Ord, PartialOrd, Debug)]
@@ -595,6 +768,10 @@ pub enum ExonumMessage {
     BlockRequest(BlockRequest),
     /// Request of uncommitted transactions.
     PoolTransactionsRequest(PoolTransactionsRequest),
+    /// Request of a chunk of the blockchain state.
+    StateChunkRequest(StateChunkRequest),
+    /// Chunk of the blockchain state, that sent as response to `StateChunkRequest`.
+    StateChunkResponse(StateChunkResponse),
 }
 
 impl TryFrom<SignedMessage> for ExonumMessage {
@@ -618,5 +795,6 @@ impl_exonum_msg_try_from_signed! {
     ExonumMessage => Connect, Status,
     Propose, Prevote, TransactionsResponse,
     BlockResponse, ProposeRequest, TransactionsRequest,
-    PrevotesRequest, PeersRequest, BlockRequest, PoolTransactionsRequest
+    PrevotesRequest, PeersRequest, BlockRequest, PoolTransactionsRequest,
+    StateChunkRequest, StateChunkResponse
 }
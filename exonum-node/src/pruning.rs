@@ -0,0 +1,109 @@
+// Copyright 2020 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Background pruning of historical blockchain data.
+
+use exonum::{blockchain::Blockchain, helpers::Height, runtime::SnapshotExt};
+use log::{error, info};
+
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+
+use crate::RetentionPolicy;
+
+/// Maximum number of blocks pruned within a single database merge.
+const MAX_PRUNED_BLOCKS_PER_BATCH: u64 = 16;
+
+/// Pruner of historical data for blocks not covered by the retention policy of the node.
+///
+/// The pruner runs in a separate thread, so that pruning a large number of blocks
+/// (e.g., after the policy was changed for an existing node) does not stall the consensus.
+/// The pruner is woken up by the node handler after each committed block.
+#[derive(Debug)]
+pub(crate) struct Pruner {
+    blockchain: Blockchain,
+    retained_blocks: u64,
+}
+
+/// Handle used to notify the pruner about committed blocks.
+#[derive(Debug, Clone)]
+pub(crate) struct PrunerHandle(SyncSender<()>);
+
+impl PrunerHandle {
+    /// Notifies the pruner that a new block has been committed.
+    pub(crate) fn notify(&self) {
+        match self.0.try_send(()) {
+            // If the channel is full, the pruner will be woken up anyway.
+            Ok(()) | Err(TrySendError::Full(())) => {}
+            Err(TrySendError::Disconnected(())) => {
+                error!("Pruner has stopped; historical blockchain data will not be pruned");
+            }
+        }
+    }
+}
+
+impl Pruner {
+    /// Creates a pruner for the specified retention policy, or returns `None` if the policy
+    /// does not require pruning.
+    pub(crate) fn new(blockchain: Blockchain, retention: RetentionPolicy) -> Option<Self> {
+        retention.retained_blocks().map(|retained_blocks| Self {
+            blockchain,
+            retained_blocks,
+        })
+    }
+
+    /// Prunes all blocks not covered by the retention policy. Blocks are pruned in batches
+    /// merged into the database separately, so that the pruner does not hold large patches
+    /// in memory.
+    pub(crate) fn prune(&self) -> anyhow::Result<()> {
+        let height = self.blockchain.snapshot().for_core().height().0;
+        if height < self.retained_blocks {
+            return Ok(());
+        }
+
+        // Blocks with heights `0..=last_height` should be pruned.
+        let last_height = Height(height - self.retained_blocks);
+        while let Some(heights) = self
+            .blockchain
+            .prune_blocks(last_height, MAX_PRUNED_BLOCKS_PER_BATCH)?
+        {
+            info!(
+                "Pruned data for blocks at heights {}..={}",
+                heights.start(),
+                heights.end()
+            );
+        }
+        Ok(())
+    }
+
+    /// Spawns the pruner in a separate thread. The thread terminates once the returned
+    /// handle is dropped.
+    pub(crate) fn spawn(self) -> PrunerHandle {
+        // A single notification is enough to make the pruner process all committed blocks.
+        let (tx, rx) = mpsc::sync_channel(1);
+        std::thread::spawn(move || self.run(&rx));
+        PrunerHandle(tx)
+    }
+
+    fn run(&self, notifications: &Receiver<()>) {
+        // Prune the blocks remaining from the previous node launch.
+        loop {
+            if let Err(e) = self.prune() {
+                error!("Error pruning historical blockchain data: {}", e);
+            }
+            if notifications.recv().is_err() {
+                break;
+            }
+        }
+    }
+}
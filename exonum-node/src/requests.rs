@@ -12,25 +12,77 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use anyhow::{bail, format_err};
 use exonum::{
-    blockchain::{PersistentPool, Schema, TransactionCache},
+    blockchain::{BlockProof, PersistentPool, Schema, TransactionCache},
     crypto::{Hash, PublicKey},
-    merkledb::BinaryValue,
+    helpers::{Height, Round},
+    merkledb::{
+        dump::{self, ChunkCursor},
+        BinaryValue, ObjectHash, Snapshot,
+    },
     messages::Verified,
 };
-use log::{error, trace};
+use log::{error, info, trace, warn};
 
 use std::mem;
 
 use crate::{
+    consensus::{into_verified, PersistChanges},
     messages::{
         BlockRequest, BlockResponse, PoolTransactionsRequest, PrevotesRequest, ProposeRequest,
-        Requests, TransactionsRequest, TransactionsResponse, TX_RES_EMPTY_SIZE,
-        TX_RES_PB_OVERHEAD_PAYLOAD,
+        Requests, StateChunkRequest, StateChunkResponse, TransactionsRequest, TransactionsResponse,
+        TX_RES_EMPTY_SIZE, TX_RES_PB_OVERHEAD_PAYLOAD,
     },
+    state::RequestData,
     NodeHandler,
 };
 
+/// Maximum number of blocks by which the height of a requested state chunk may lag behind
+/// the blockchain height of the node. The state for earlier blocks is not served,
+/// since a historical snapshot keeps all changes made after it in memory.
+pub const MAX_STATE_CHUNK_LAG: u64 = 1_000;
+
+/// State of the blockchain state synchronization with other nodes.
+///
+/// The node requests state chunks one by one, starting from the beginning of the state.
+/// Each received chunk is imported into a staging database right away. After the last chunk
+/// is imported, the state is verified against the block backed by the precommits, replaces
+/// the state in the node storage, and the node moves to the height following this block.
+///
+/// The height of the synchronized block is fixed once the first state chunk is imported;
+/// the chunks may be provided by any peer ahead of this height that keeps the state history.
+///
+/// The block is verified against the genesis validator keys. If this fails, or if the imported
+/// state shows that the validator set has changed, the synchronization is stopped for good,
+/// and the node requests blocks instead.
+#[derive(Debug)]
+pub(crate) struct StateSync {
+    /// Height of the block, the state after which is synchronized.
+    height: Height,
+    /// Position of the next state chunk.
+    cursor: ChunkCursor,
+    /// Hash of the block, if at least one state chunk was imported.
+    block_hash: Option<Hash>,
+    /// Consensus keys of validators, which are trusted to endorse the synchronized block.
+    validator_keys: Vec<PublicKey>,
+}
+
+impl StateSync {
+    fn new(height: Height, validator_keys: Vec<PublicKey>) -> Self {
+        Self {
+            height,
+            cursor: ChunkCursor::default(),
+            block_hash: None,
+            validator_keys,
+        }
+    }
+
+    fn request_data(&self) -> RequestData {
+        RequestData::StateChunk(self.height, self.cursor.clone())
+    }
+}
+
 // TODO: Height should be updated after any message, not only after status (if signature is correct). (ECR-171)
 // TODO: Request propose makes sense only if we know that node is on our height. (ECR-171)
 
@@ -58,6 +110,7 @@ impl NodeHandler {
             Requests::PeersRequest(ref msg) => self.handle_request_peers(msg),
             Requests::BlockRequest(ref msg) => self.handle_request_block(msg),
             Requests::PoolTransactionsRequest(ref msg) => self.handle_request_pool_txs(msg),
+            Requests::StateChunkRequest(ref msg) => self.handle_request_state_chunk(msg),
         }
     }
 
@@ -173,6 +226,11 @@ impl NodeHandler {
                     }
                 }
             }
+        } else if schema
+            .pruned_height()
+            .map_or(false, |pruned| height <= pruned)
+        {
+            trace!("Requested block at height {} is pruned", height);
         } else {
             let proof = schema.block_and_precommits(height).unwrap();
             let transactions = schema.block_transactions(height).iter().collect();
@@ -189,4 +247,267 @@ impl NodeHandler {
             self.send_to_peer(msg.author(), block_msg);
         }
     }
+
+    /// Handles `StateChunkRequest` message. For details see the message documentation.
+    pub(crate) fn handle_request_state_chunk(&mut self, msg: &Verified<StateChunkRequest>) {
+        let height = msg.payload().height;
+        trace!(
+            "Handling `StateChunkRequest` with height: {}, cursor: {:?}",
+            height,
+            msg.payload().cursor()
+        );
+
+        if height.0 + MAX_STATE_CHUNK_LAG < self.state.blockchain_height().0 {
+            trace!("State at height {} is too old to be served", height);
+            return;
+        }
+        let snapshot = self.blockchain.snapshot();
+        let schema = Schema::new(&snapshot);
+        if schema
+            .pruned_height()
+            .map_or(false, |pruned| height <= pruned)
+        {
+            trace!("Precommits for the block at height {} are pruned", height);
+            return;
+        }
+        let proof = match schema.block_and_precommits(height) {
+            Some(proof) => proof,
+            None => return,
+        };
+
+        // Reserve a half of the message for the block, precommits and encoding overhead.
+        let max_size = self.state.config().max_message_len as usize / 2;
+        let cursor = msg.payload().cursor();
+        let export_result = self.state_chunk_source(height).and_then(|snapshot| {
+            dump::export_chunk(snapshot, &cursor, max_size).map_err(From::from)
+        });
+        let (chunk, next_cursor) = match export_result {
+            Ok(res) => res,
+            Err(e) => {
+                trace!("Cannot export state chunk at height {}: {}", height, e);
+                return;
+            }
+        };
+
+        let chunk_msg = self.sign_message(StateChunkResponse::new(
+            msg.author(),
+            proof.block,
+            proof.precommits.iter().map(BinaryValue::to_bytes),
+            &cursor,
+            chunk,
+            next_cursor,
+        ));
+        self.send_to_peer(msg.author(), chunk_msg);
+    }
+
+    /// Returns the snapshot of the state at the specified height to export state chunks from.
+    /// The last used snapshot is cached, so that a historical snapshot is not reconstructed
+    /// for each requested chunk.
+    fn state_chunk_source(&mut self, height: Height) -> anyhow::Result<&dyn Snapshot> {
+        let is_cached = self
+            .state_chunk_source
+            .as_ref()
+            .map_or(false, |(cached_height, _)| *cached_height == height);
+        if !is_cached {
+            // Drop the previous snapshot before creating a new one to bound memory usage.
+            self.state_chunk_source = None;
+            let snapshot = self.blockchain.as_ref().state_snapshot(height)?;
+            self.state_chunk_source = Some((height, snapshot));
+        }
+        Ok(self.state_chunk_source.as_ref().unwrap().1.as_ref())
+    }
+
+    /// Updates the state synchronization based on the blockchain height of a peer.
+    /// The synchronization is started if the node has no blocks except for the genesis one,
+    /// and the peer is more than one block ahead. The node does not participate
+    /// in the consensus until the synchronization is finished.
+    ///
+    /// Returns `true` if the node synchronizes its state; in this case, the node
+    /// should not request blocks from peers.
+    pub(crate) fn sync_state_with_peer(&mut self, peer: PublicKey, peer_height: Height) -> bool {
+        if let Some(ref sync) = self.state_sync {
+            // Until a state chunk is imported, the synchronization can be moved to the latest
+            // block of the peer if no peers have responded to the chunk request.
+            let data = sync.request_data();
+            let can_restart = sync.block_hash.is_none()
+                && peer_height > sync.height.next()
+                && !self.state.is_requested(&data);
+            if !can_restart {
+                if peer_height > sync.height {
+                    // The peer can provide state chunks if it is at the synchronized height,
+                    // or if it keeps the state history.
+                    self.request(data, peer);
+                }
+                return true;
+            }
+        } else {
+            let is_allowed = self.allow_state_sync && self.state.blockchain_height() == Height(1);
+            if !is_allowed || peer_height <= self.state.blockchain_height().next() {
+                return false;
+            }
+        }
+
+        // The node has no blocks except for the genesis one, thus, its consensus configuration
+        // is the genesis configuration.
+        let validator_keys = self
+            .state
+            .config()
+            .validator_keys
+            .iter()
+            .map(|keys| keys.consensus_key)
+            .collect();
+        let sync = StateSync::new(peer_height.previous(), validator_keys);
+        info!(
+            "Starting state synchronization at height {} with peer {}",
+            sync.height, peer
+        );
+        self.request(sync.request_data(), peer);
+        self.state_sync = Some(sync);
+        true
+    }
+
+    /// Handles `StateChunkResponse` message. For details see the message documentation.
+    pub(crate) fn handle_state_chunk(&mut self, msg: Verified<StateChunkResponse>) {
+        let block_proof = match self.validate_state_chunk(&msg) {
+            Ok(block_proof) => block_proof,
+            Err(e) => {
+                error!("Received incorrect state chunk {:?}: {}", msg.payload(), e);
+                return;
+            }
+        };
+        // The block can be checked only against the genesis validator keys, which fails
+        // if the validator set has changed since then.
+        let validator_keys = &self.state_sync.as_ref().unwrap().validator_keys;
+        if let Err(e) = block_proof.verify(validator_keys) {
+            warn!(
+                "Block in the state chunk from {} is not endorsed by genesis validators: {}",
+                msg.author(),
+                e
+            );
+            self.abort_state_sync();
+            return;
+        }
+
+        let sender = msg.author();
+        let next_cursor = msg.payload().next_cursor();
+        let chunk = msg.into_payload().chunk;
+        let mut sync = self.state_sync.take().unwrap();
+        let known_nodes = self.remove_request(&sync.request_data());
+
+        if sync.block_hash.is_none() {
+            self.blockchain
+                .start_state_import()
+                .expect("Cannot create staging database for state synchronization");
+            sync.block_hash = Some(block_proof.block.object_hash());
+        }
+        if let Err(e) = self.blockchain.import_state_chunk(&chunk) {
+            // The synchronization will be restarted on the next `Status` message.
+            error!("Cannot import state chunk from {}: {}", sender, e);
+            return;
+        }
+
+        if let Some(cursor) = next_cursor {
+            sync.cursor = cursor;
+            let data = sync.request_data();
+            self.state_sync = Some(sync);
+            self.request(data.clone(), sender);
+            for node in known_nodes {
+                self.request(data.clone(), node);
+            }
+        } else {
+            self.finish_state_sync(block_proof);
+        }
+    }
+
+    /// Checks that the state chunk was requested and that it contains precommits
+    /// for the synchronized block.
+    fn validate_state_chunk(
+        &self,
+        msg: &Verified<StateChunkResponse>,
+    ) -> anyhow::Result<BlockProof> {
+        if msg.payload().to != self.state.keys().consensus_pk() {
+            bail!(
+                "Received state chunk intended for another peer, to={}, from={}",
+                msg.payload().to,
+                msg.author()
+            );
+        }
+
+        let sync = self
+            .state_sync
+            .as_ref()
+            .ok_or_else(|| format_err!("State is not synchronized"))?;
+        let block = &msg.payload().block;
+        if block.height != sync.height || msg.payload().cursor() != sync.cursor {
+            bail!("Received state chunk was not requested");
+        }
+
+        if let Some(expected_hash) = sync.block_hash {
+            if block.object_hash() != expected_hash {
+                bail!(
+                    "Block in the received state chunk differs from the block in previous chunks"
+                );
+            }
+        }
+        let precommits = into_verified(&msg.payload().precommits)?;
+        Ok(BlockProof::new(block.clone(), precommits))
+    }
+
+    /// Stops the state synchronization; the node synchronizes by requesting blocks instead.
+    /// State synchronization is not attempted again, since the synchronized state cannot be
+    /// verified if the validator set has changed since the genesis block.
+    fn abort_state_sync(&mut self) {
+        if let Some(sync) = self.state_sync.take() {
+            self.remove_request(&sync.request_data());
+        }
+        self.allow_state_sync = false;
+        warn!(
+            "State synchronization is supported only if the validator set has not changed \
+             since the genesis block; falling back to requesting blocks"
+        );
+    }
+
+    /// Finishes the state synchronization after the last state chunk is imported.
+    fn finish_state_sync(&mut self, block_proof: BlockProof) {
+        if let Err(e) = self.blockchain.finish_state_import(&block_proof) {
+            // The imported state cannot be verified; blocks will be requested
+            // on the next `Status` message.
+            error!("Cannot finish state synchronization: {}", e);
+            self.abort_state_sync();
+            return;
+        }
+
+        // Node-specific data is a part of the synchronized state as well; replace it
+        // with the data of this node.
+        let peers: Vec<_> = self.state.peers().values().cloned().collect();
+        self.blockchain.persist_changes(
+            |schema| {
+                schema.consensus_messages_cache().clear();
+                schema.set_consensus_round(Round::first());
+                schema.peers_cache().clear();
+                for peer in peers {
+                    schema.save_peer(&peer.author(), peer);
+                }
+            },
+            "Cannot reset node data after state synchronization",
+        );
+
+        self.update_consensus_config();
+        let snapshot = self.blockchain.snapshot();
+        self.state.tx_cache_mut().clear();
+        self.state
+            .jump_to_height(&block_proof.block, self.system_state.current_time());
+        for plugin in &self.plugins {
+            plugin.after_commit(&snapshot);
+        }
+        info!(
+            "Finished state synchronization at height {}, hash={}",
+            block_proof.block.height,
+            block_proof.block.object_hash().to_hex()
+        );
+
+        self.broadcast_status();
+        self.add_status_timeout();
+        self.add_round_timeout();
+    }
 }
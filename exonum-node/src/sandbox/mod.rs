@@ -26,7 +26,10 @@ use exonum::{
         BlockchainMut, ConsensusConfig, Epoch, PersistentPool, ProposerId, Schema, SkipFlag,
         TransactionCache, ValidatorKeys,
     },
-    crypto::{Hash, KeyPair, PublicKey, SecretKey, Seed, SEED_LENGTH},
+    crypto::{
+        bls::{BlsPublicKey, BlsSignature},
+        Hash, KeyPair, PublicKey, SecretKey, Seed, Signature, SEED_LENGTH,
+    },
     helpers::{user_agent, Height, Round, ValidatorId},
     keys::Keys,
     merkledb::{BinaryValue, HashTag, MapProof, ObjectHash, Snapshot, SystemSchema, TemporaryDB},
@@ -61,14 +64,17 @@ use crate::{
         SyncSender, TimeoutRequest,
     },
     messages::{
-        BlockRequest, BlockResponse, Connect, ExonumMessage, Message, PeersRequest,
-        PoolTransactionsRequest, Prevote, PrevotesRequest, Propose, ProposeRequest, Status,
-        TransactionsRequest, TransactionsResponse,
+        BlockRequest, BlockResponse, Connect, Message, PeersRequest, PoolTransactionsRequest,
+        Prevote, PrevotesRequest, Propose, ProposeRequest, Status, TransactionsRequest,
+        TransactionsResponse,
     },
     pool::{ManagePool, StandardPoolManager},
+    pruning::Pruner,
+    signer::{LocalSigner, Signer, SignerError},
     state::State,
     ApiSender, Configuration, ConnectInfo, ConnectListConfig, ExternalMessage, MemoryPoolConfig,
-    NetworkConfiguration, NodeHandler, NodeSender, SharedNodeState, SystemStateProvider,
+    NetworkConfiguration, NodeHandler, NodeSender, RetentionPolicy, SharedNodeState,
+    SystemStateProvider,
 };
 
 pub type SharedTime = Arc<Mutex<SystemTime>>;
@@ -129,7 +135,8 @@ impl SandboxInner {
                     let msg = Message::from_signed(msg).expect("Expected valid message.");
                     self.sent.push_back((peer, msg))
                 }
-                NetworkRequest::DisconnectWithPeer(_) => {}
+                NetworkRequest::UpdateConnectMessage(_) | NetworkRequest::DisconnectWithPeer(_) => {
+                }
             }
         }
     }
@@ -146,13 +153,16 @@ impl SandboxInner {
 
                 InternalRequest::VerifyMessage(raw) => {
                     let msg = SignedMessage::from_bytes(raw.into())
-                        .and_then(SignedMessage::into_verified::<ExonumMessage>)
-                        .map(Message::from)
+                        .and_then(Message::from_signed)
                         .unwrap();
 
                     self.handler
                         .handle_event(InternalEvent::message_verified(msg).into());
                 }
+
+                InternalRequest::SignMessage(request) => {
+                    self.handler.handle_event(request.sign().into());
+                }
             }
         }
     }
@@ -174,8 +184,11 @@ pub struct Sandbox {
     pub api_sender: ApiSender,
     inner: RefCell<SandboxInner>,
     addresses: Vec<ConnectInfo>,
-    /// Connect message used during initialization.
-    connect: Option<Verified<Connect>>,
+    /// Connect message used during initialization or announced by the node afterwards.
+    connect: RefCell<Option<Verified<Connect>>>,
+    /// Pruner of historical blockchain data. Unlike the node, the sandbox does not run
+    /// the pruner in background; pruning is performed with `prune_blocks()`.
+    pruner: Option<Pruner>,
 }
 
 impl Sandbox {
@@ -206,7 +219,7 @@ impl Sandbox {
         }
 
         self.check_unexpected_message();
-        self.connect = Some(connect);
+        self.connect.replace(Some(connect));
     }
 
     fn check_unexpected_message(&self) {
@@ -513,6 +526,13 @@ impl Sandbox {
         self.inner.borrow().handler.blockchain.as_ref().clone()
     }
 
+    /// Prunes historical blockchain data according to the retention policy of the sandbox.
+    pub fn prune_blocks(&self) {
+        if let Some(pruner) = &self.pruner {
+            pruner.prune().expect("Cannot prune blocks");
+        }
+    }
+
     pub fn blockchain_mut(&self) -> impl ops::DerefMut<Target = BlockchainMut> + '_ {
         RefMut::map(self.inner.borrow_mut(), |inner| {
             &mut inner.handler.blockchain
@@ -520,8 +540,8 @@ impl Sandbox {
     }
 
     /// Returns connect message used during initialization.
-    pub fn connect(&self) -> Option<&Verified<Connect>> {
-        self.connect.as_ref()
+    pub fn connect(&self) -> Option<Verified<Connect>> {
+        self.connect.borrow().clone()
     }
 
     pub fn recv<T: TryFrom<SignedMessage>>(&self, msg: &Verified<T>) {
@@ -647,12 +667,21 @@ impl Sandbox {
     }
 
     pub fn check_broadcast_status(&self, height: Height, block_hash: Hash) {
+        // The node announces its new consensus key with a `Connect` message.
+        let connect = self.node_state().our_connect_message().clone();
+        if self.connect().map_or(false, |initial| initial != connect) {
+            self.broadcast(&connect);
+            self.connect.replace(Some(connect));
+        }
+
+        // `Status` is signed by the node signer, which uses the rotated keys if any.
+        let keys = self.node_state().consensus_keys().consensus.clone();
         self.broadcast(&Self::create_status(
-            self.node_public_key(),
+            keys.public_key(),
             height,
             block_hash,
             0,
-            &self.node_secret_key(),
+            keys.secret_key(),
         ));
     }
 
@@ -731,6 +760,8 @@ impl Sandbox {
     /// **NB.** This method does not correctly process transactions that mutate the `Dispatcher`,
     /// e.g., starting new services.
     pub fn compute_block_hashes(&self, txs: &[Verified<AnyTx>]) -> (Hash, Hash) {
+        // The epoch affects the state hash if the block changes the consensus configuration.
+        let epoch = self.current_epoch();
         let mut blockchain = self.blockchain_mut();
 
         let mut hashes = vec![];
@@ -747,7 +778,7 @@ impl Sandbox {
         }
         blockchain.merge(fork.into_patch()).unwrap();
 
-        let block_data = BlockParams::new(ValidatorId(0), Height(0), &hashes);
+        let block_data = BlockParams::new(ValidatorId(0), epoch, &hashes);
         let patch = blockchain.create_patch(block_data, &());
 
         let fork = blockchain.fork();
@@ -957,7 +988,10 @@ impl Sandbox {
             network: NetworkConfiguration::default(),
             peer_discovery: Vec::new(),
             mempool: MemoryPoolConfig::default(),
+            signer: Arc::new(LocalSigner::new(keys.consensus.clone())),
             keys,
+            state_sync: inner.handler.allow_state_sync,
+            pending_keys: inner.handler.state().pending_keys().cloned(),
         };
 
         let shared_time = SharedTime::new(Mutex::new(time));
@@ -996,19 +1030,12 @@ impl Sandbox {
             services_map: self.services_map,
             api_sender: ApiSender::new(tx_channel.0),
             addresses: self.addresses,
-            connect: None,
+            connect: RefCell::new(None),
+            pruner: self.pruner,
         };
         sandbox.process_events();
         sandbox
     }
-
-    fn node_public_key(&self) -> PublicKey {
-        self.node_state().keys().consensus_pk()
-    }
-
-    fn node_secret_key(&self) -> SecretKey {
-        self.node_state().keys().consensus_sk().to_owned()
-    }
 }
 
 #[derive(Debug)]
@@ -1021,6 +1048,10 @@ pub struct SandboxBuilder {
     instances: Vec<InstanceInitParams>,
     artifacts: HashMap<ArtifactId, Vec<u8>>,
     pool_manager: Box<dyn ManagePool>,
+    retention: RetentionPolicy,
+    state_sync: bool,
+    pending_keys: Option<Keys>,
+    blocking_signer: bool,
 }
 
 impl Default for SandboxBuilder {
@@ -1048,6 +1079,10 @@ impl Default for SandboxBuilder {
             instances: Vec::new(),
             artifacts: HashMap::new(),
             pool_manager: Box::new(StandardPoolManager::default()),
+            retention: RetentionPolicy::default(),
+            state_sync: false,
+            pending_keys: None,
+            blocking_signer: false,
         }
     }
 }
@@ -1086,6 +1121,31 @@ impl SandboxBuilder {
         self
     }
 
+    /// Sets the retention policy for the historical blockchain data.
+    pub fn with_retention_policy(mut self, retention: RetentionPolicy) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    /// Allows the node to synchronize the blockchain state with other nodes.
+    pub fn with_state_sync(mut self) -> Self {
+        self.state_sync = true;
+        self
+    }
+
+    /// Sets the pending keys of the sandbox node.
+    pub fn with_pending_keys(mut self, keys: Keys) -> Self {
+        self.pending_keys = Some(keys);
+        self
+    }
+
+    /// Makes the node sign consensus messages with a signer reported as blocking,
+    /// so that the messages are signed asynchronously.
+    pub fn with_blocking_signer(mut self) -> Self {
+        self.blocking_signer = true;
+        self
+    }
+
     /// Adds instances descriptions to the testkit that will be used for specification of builtin
     /// services of testing blockchain.
     pub fn with_instance(mut self, instance: impl Into<InstanceInitParams>) -> Self {
@@ -1126,8 +1186,12 @@ impl SandboxBuilder {
             self.instances,
             self.consensus_config,
             self.validators_count,
+            self.pending_keys,
+            self.blocking_signer,
         );
         sandbox.inner.borrow_mut().handler.pool_manager = self.pool_manager;
+        sandbox.pruner = Pruner::new(sandbox.blockchain(), self.retention);
+        sandbox.inner.borrow_mut().handler.allow_state_sync = self.state_sync;
 
         sandbox.inner.borrow_mut().sent.clear(); // To clear initial connect messages.
         if self.initialize {
@@ -1138,6 +1202,42 @@ impl SandboxBuilder {
     }
 }
 
+/// Local signer reported as blocking, so that the node signs consensus messages
+/// asynchronously.
+#[derive(Debug)]
+struct BlockingSigner(LocalSigner);
+
+impl Signer for BlockingSigner {
+    fn public_key(&self) -> PublicKey {
+        self.0.public_key()
+    }
+
+    fn sign(&self, payload: &[u8]) -> Result<Signature, SignerError> {
+        self.0.sign(payload)
+    }
+
+    fn is_blocking(&self) -> bool {
+        true
+    }
+
+    fn rotate(&self, keys: &KeyPair) -> Option<Arc<dyn Signer>> {
+        Some(Arc::new(Self(LocalSigner::new(keys.clone()))))
+    }
+
+    fn bls_public_key(&self) -> Option<BlsPublicKey> {
+        self.0.bls_public_key()
+    }
+
+    fn sign_block_hash(
+        &self,
+        epoch: Height,
+        round: Round,
+        block_hash: &Hash,
+    ) -> Result<BlsSignature, SignerError> {
+        self.0.sign_block_hash(epoch, round, block_hash)
+    }
+}
+
 fn gen_primitive_socket_addr(idx: u8) -> SocketAddr {
     let addr = Ipv4Addr::new(idx, idx, idx, idx);
     SocketAddr::new(IpAddr::V4(addr), u16::from(idx))
@@ -1170,6 +1270,8 @@ fn sandbox_with_services_uninitialized(
     instances: Vec<InstanceInitParams>,
     consensus: ConsensusConfig,
     validators_count: u8,
+    pending_keys: Option<Keys>,
+    blocking_signer: bool,
 ) -> Sandbox {
     let keys = (0..validators_count)
         .map(|i| {
@@ -1234,6 +1336,13 @@ fn sandbox_with_services_uninitialized(
         peer_discovery: Vec::new(),
         mempool: MemoryPoolConfig::default(),
         keys: keys[0].clone(),
+        state_sync: false,
+        signer: if blocking_signer {
+            Arc::new(BlockingSigner(LocalSigner::new(keys[0].consensus.clone())))
+        } else {
+            Arc::new(LocalSigner::new(keys[0].consensus.clone()))
+        },
+        pending_keys,
     };
 
     let system_state = SandboxSystemStateProvider {
@@ -1284,7 +1393,8 @@ fn sandbox_with_services_uninitialized(
         validators_map: HashMap::from_iter(validators),
         services_map: HashMap::from_iter(service_keys),
         addresses: connect_infos,
-        connect: None,
+        connect: RefCell::new(None),
+        pruner: None,
     };
 
     // General assumption; necessary for correct work of consensus algorithm
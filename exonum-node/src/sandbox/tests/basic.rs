@@ -99,6 +99,21 @@ fn test_one_validator() {
     sandbox.assert_state(Height(2), Round(1));
 }
 
+/// The idea of the test is to reach several heights if consensus messages are signed
+/// asynchronously, as with a remote signer.
+#[test]
+fn test_reach_height_with_blocking_signer() {
+    let sandbox = timestamping_sandbox_builder()
+        .with_blocking_signer()
+        .build();
+    let sandbox_state = SandboxState::new();
+
+    for height in 2..=5 {
+        add_one_height(&sandbox, &sandbox_state);
+        sandbox.assert_state(Height(height), Round(1));
+    }
+}
+
 /// Validator2,3,4 starts in 5th round
 /// Validator1 starts with delay
 /// Validator1 receive consensus messages, and reach actual round
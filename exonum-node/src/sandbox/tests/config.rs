@@ -15,11 +15,16 @@
 //! Tests in this module are designed to test configuration change protocol.
 
 use exonum::{
+    blockchain::ValidatorKeys,
     helpers::{Height, ValidatorId},
+    keys::Keys,
     merkledb::BinaryValue,
 };
 
-use crate::sandbox::{sandbox_tests_helper::*, supervisor::TxConfig, timestamping_sandbox};
+use crate::sandbox::{
+    sandbox_tests_helper::*, supervisor::TxConfig, timestamping_sandbox,
+    timestamping_sandbox_builder,
+};
 
 /// - exclude validator from consensus
 /// - idea of test is to exclude sandbox validator from consensus
@@ -48,5 +53,56 @@ fn test_exclude_validator_from_consensus() {
     add_one_height_with_transactions_from_other_validator(&sandbox, &sandbox_state, &[]);
 }
 
+/// - rotate keys of the sandbox validator
+/// - node switches to the pending keys once the config with these keys is activated
+#[test]
+fn test_switch_to_pending_keys() {
+    let new_keys = Keys::random();
+    let sandbox = timestamping_sandbox_builder()
+        .with_pending_keys(new_keys.clone())
+        .build();
+    let sandbox_state = SandboxState::new();
+
+    add_one_height(&sandbox, &sandbox_state);
+    let old_consensus_key = sandbox.public_key(ValidatorId(0));
+    assert_eq!(
+        sandbox.node_state().signer().public_key(),
+        old_consensus_key
+    );
+
+    let tx_cfg = {
+        let mut consensus_cfg = sandbox.cfg();
+        consensus_cfg.validator_keys[0] =
+            ValidatorKeys::new(new_keys.consensus_pk(), new_keys.service_pk());
+
+        TxConfig::create_signed(
+            sandbox.public_key(ValidatorId(0)),
+            &consensus_cfg.into_bytes(),
+            Height(0),
+            sandbox.secret_key(ValidatorId(0)),
+        )
+    };
+    add_one_height_with_transactions(&sandbox, &sandbox_state, &[tx_cfg]);
+
+    let state = sandbox.node_state();
+    assert_eq!(state.signer().public_key(), new_keys.consensus_pk());
+    assert_eq!(state.validator_id(), Some(ValidatorId(0)));
+    assert!(state.pending_keys().is_none());
+    assert_eq!(state.consensus_keys().service_pk(), new_keys.service_pk());
+    // Network keys of the node are retained, and the new consensus key is announced to peers.
+    assert_eq!(state.keys().consensus_pk(), old_consensus_key);
+    let connect = state.our_connect_message();
+    assert_eq!(connect.author(), old_consensus_key);
+    assert_eq!(
+        connect.payload().consensus_key,
+        Some(new_keys.consensus_pk())
+    );
+    drop(state);
+    assert_eq!(
+        sandbox.blockchain().service_keypair().public_key(),
+        new_keys.service_pk()
+    );
+}
+
 // - include validator to consensus
 // TODO [ECR-3222]
@@ -0,0 +1,109 @@
+// Copyright 2020 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tests in this module are designed to test detection of validators sending
+//! conflicting consensus messages.
+
+use exonum::{
+    blockchain::{EquivocationEvidence, EquivocationKind, Schema},
+    crypto::{self, Hash},
+    helpers::{Height, Round, ValidatorId},
+};
+
+use crate::sandbox::{sandbox_tests_helper::*, timestamping_sandbox, Sandbox};
+
+fn equivocation_evidence(sandbox: &Sandbox) -> Vec<EquivocationEvidence> {
+    let snapshot = sandbox.blockchain().snapshot();
+    let evidence = Schema::new(&snapshot).equivocation_evidence();
+    evidence.values().collect()
+}
+
+#[test]
+fn conflicting_prevotes_are_recorded() {
+    let sandbox = timestamping_sandbox();
+    let first_prevote = sandbox.create_prevote(
+        ValidatorId(2),
+        Height(1),
+        Round(1),
+        crypto::hash(&[1]),
+        NOT_LOCKED,
+        sandbox.secret_key(ValidatorId(2)),
+    );
+    let second_prevote = sandbox.create_prevote(
+        ValidatorId(2),
+        Height(1),
+        Round(1),
+        crypto::hash(&[2]),
+        NOT_LOCKED,
+        sandbox.secret_key(ValidatorId(2)),
+    );
+
+    sandbox.recv(&first_prevote);
+    // Receiving the same message again is not an equivocation.
+    sandbox.recv(&first_prevote);
+    assert!(equivocation_evidence(&sandbox).is_empty());
+
+    sandbox.recv(&second_prevote);
+    let evidence = equivocation_evidence(&sandbox);
+    assert_eq!(evidence.len(), 1);
+    let evidence = &evidence[0];
+    assert_eq!(
+        *evidence,
+        EquivocationEvidence::new(first_prevote.into_raw(), second_prevote.into_raw())
+    );
+
+    let validator_keys = sandbox.cfg().validator_keys;
+    let equivocation = evidence.verify(&validator_keys).unwrap();
+    assert_eq!(equivocation.validator, ValidatorId(2));
+    assert_eq!(equivocation.kind, EquivocationKind::Prevote);
+    assert_eq!(equivocation.epoch, Height(1));
+    assert_eq!(equivocation.round, Round(1));
+}
+
+#[test]
+fn conflicting_precommits_are_recorded() {
+    let sandbox = timestamping_sandbox();
+    let precommit = |block_hash: Hash| {
+        sandbox.create_precommit(
+            ValidatorId(3),
+            Height(1),
+            Round(1),
+            crypto::hash(&[1]),
+            block_hash,
+            sandbox.time().into(),
+            sandbox.secret_key(ValidatorId(3)),
+        )
+    };
+
+    sandbox.recv(&precommit(crypto::hash(&[10])));
+    // Precommits in different rounds do not conflict.
+    sandbox.recv(&sandbox.create_precommit(
+        ValidatorId(3),
+        Height(1),
+        Round(2),
+        crypto::hash(&[2]),
+        crypto::hash(&[20]),
+        sandbox.time().into(),
+        sandbox.secret_key(ValidatorId(3)),
+    ));
+    assert!(equivocation_evidence(&sandbox).is_empty());
+
+    let second_precommit = precommit(crypto::hash(&[11]));
+    sandbox.recv(&second_precommit);
+    let evidence = equivocation_evidence(&sandbox);
+    assert_eq!(evidence.len(), 1);
+    let equivocation = evidence[0].verify(&sandbox.cfg().validator_keys).unwrap();
+    assert_eq!(equivocation.validator, ValidatorId(3));
+    assert_eq!(equivocation.kind, EquivocationKind::Precommit);
+}
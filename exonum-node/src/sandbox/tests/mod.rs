@@ -17,12 +17,15 @@
 mod basic;
 mod block_request;
 mod config;
+mod equivocation;
 mod invalid_message;
 mod old;
+mod pruning;
 mod recovery;
 mod requests;
 mod round_details;
 mod skips;
+mod state_sync;
 mod timeouts;
 mod transactions;
 mod unsynchronized_message;
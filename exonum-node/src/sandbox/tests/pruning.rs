@@ -0,0 +1,118 @@
+// Copyright 2020 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tests in this module are designed to test pruning of historical blockchain data.
+
+use exonum::{
+    helpers::{Height, ValidatorId},
+    merkledb::ObjectHash,
+    runtime::SnapshotExt,
+};
+
+use crate::{
+    sandbox::{sandbox_tests_helper::*, timestamping_sandbox_builder, Sandbox},
+    RetentionPolicy,
+};
+
+#[test]
+fn pruning_with_keep_last_policy() {
+    let sandbox = timestamping_sandbox_builder()
+        .with_retention_policy(RetentionPolicy::KeepLast { blocks: 2 })
+        .build();
+
+    let transactions: Vec<_> = (0..4).map(|_| gen_timestamping_tx()).collect();
+    for tx in &transactions {
+        add_one_height_with_transactions(&sandbox, &SandboxState::new(), vec![tx]);
+    }
+    // Pruning is not performed as a part of the block commit.
+    let snapshot = sandbox.blockchain().snapshot();
+    assert_eq!(snapshot.for_core().pruned_height(), None);
+    sandbox.prune_blocks();
+
+    let snapshot = sandbox.blockchain().snapshot();
+    let schema = snapshot.for_core();
+    assert_eq!(schema.height(), Height(4));
+    assert_eq!(schema.pruned_height(), Some(Height(2)));
+
+    for (i, tx) in transactions.iter().enumerate() {
+        let height = Height(i as u64 + 1);
+        let tx_hash = tx.object_hash();
+        assert!(schema.transactions_locations().contains(&tx_hash));
+        let is_pruned = height <= Height(2);
+        assert_eq!(schema.transactions().contains(&tx_hash), !is_pruned);
+        assert_eq!(schema.block_transactions(height).is_empty(), is_pruned);
+        let precommits = schema.block_and_precommits(height).unwrap().precommits;
+        assert_eq!(precommits.is_empty(), is_pruned);
+    }
+
+    // Committed transactions from the pruned blocks are not processed again.
+    sandbox.recv(&transactions[0]);
+    sandbox.assert_tx_cache_len(0);
+    sandbox.assert_pool_len(0);
+}
+
+#[test]
+fn pruned_blocks_are_not_served() {
+    let sandbox = timestamping_sandbox_builder()
+        .with_retention_policy(RetentionPolicy::HeadersOnly)
+        .build();
+    let tx = gen_timestamping_tx();
+    add_one_height(&sandbox, &SandboxState::new());
+    add_one_height_with_transactions(&sandbox, &SandboxState::new(), vec![&tx]);
+    sandbox.prune_blocks();
+    assert_eq!(
+        sandbox.blockchain().snapshot().for_core().pruned_height(),
+        Some(Height(1))
+    );
+
+    // The request for the pruned block is ignored.
+    sandbox.recv(&Sandbox::create_block_request(
+        sandbox.public_key(ValidatorId(1)),
+        sandbox.public_key(ValidatorId(0)),
+        Height(1),
+        sandbox.secret_key(ValidatorId(1)),
+    ));
+
+    // The latest block is retained and can be served.
+    sandbox.recv(&Sandbox::create_block_request(
+        sandbox.public_key(ValidatorId(1)),
+        sandbox.public_key(ValidatorId(0)),
+        Height(2),
+        sandbox.secret_key(ValidatorId(1)),
+    ));
+    let proof = sandbox.block_and_precommits(Height(2)).unwrap();
+    assert!(!proof.precommits.is_empty());
+    let response = Sandbox::create_block_response(
+        sandbox.public_key(ValidatorId(0)),
+        sandbox.public_key(ValidatorId(1)),
+        proof.block,
+        proof.precommits,
+        vec![tx.object_hash()],
+        sandbox.secret_key(ValidatorId(0)),
+    );
+    sandbox.send(sandbox.public_key(ValidatorId(1)), &response);
+}
+
+#[test]
+fn archive_policy_does_not_prune_blocks() {
+    let sandbox = timestamping_sandbox_builder().build();
+    for _ in 0..3 {
+        add_one_height(&sandbox, &SandboxState::new());
+    }
+    sandbox.prune_blocks();
+    let snapshot = sandbox.blockchain().snapshot();
+    let schema = snapshot.for_core();
+    assert_eq!(schema.pruned_height(), None);
+    assert!(!schema.block_transactions(Height(1)).is_empty());
+}
@@ -0,0 +1,478 @@
+// Copyright 2020 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tests in this module are designed to test synchronization of the blockchain state
+//! with other nodes.
+
+use exonum::{
+    blockchain::ValidatorKeys,
+    helpers::{Height, Round, ValidatorId},
+    keys::Keys,
+    merkledb::{dump::ChunkCursor, BinaryValue, ObjectHash, SystemSchema},
+    messages::Verified,
+    runtime::SnapshotExt,
+};
+
+use std::time::Duration;
+
+use crate::{
+    messages::{StateChunkRequest, StateChunkResponse},
+    sandbox::{
+        sandbox_tests_helper::*, supervisor::TxConfig, timestamping_sandbox,
+        timestamping_sandbox_builder, Sandbox,
+    },
+    state::{BLOCK_REQUEST_TIMEOUT, STATE_CHUNK_REQUEST_TIMEOUT},
+};
+
+/// Maximum size of state chunks in tests, which is small enough to split the state
+/// into several chunks.
+const CHUNK_SIZE: usize = 1_000;
+
+/// Creates a sandbox with the specified number of committed blocks.
+fn source_sandbox(blocks: usize) -> (Sandbox, Vec<Verified<exonum::messages::AnyTx>>) {
+    let sandbox = timestamping_sandbox();
+    let transactions: Vec<_> = (0..blocks).map(|_| gen_timestamping_tx()).collect();
+    for tx in &transactions {
+        add_one_height_with_transactions(&sandbox, &SandboxState::new(), vec![tx]);
+    }
+    (sandbox, transactions)
+}
+
+/// Creates a sandbox in which the keys of validator #1 are replaced at height 2.
+/// If `extra_block` is set, one more block is committed with the new validator keys.
+fn source_sandbox_with_changed_validators(extra_block: bool) -> Sandbox {
+    let (mut sandbox, _) = source_sandbox(1);
+    let new_keys = Keys::random();
+    let tx_cfg = {
+        let mut consensus_cfg = sandbox.cfg();
+        consensus_cfg.validator_keys[1] =
+            ValidatorKeys::new(new_keys.consensus_pk(), new_keys.service_pk());
+        TxConfig::create_signed(
+            sandbox.public_key(ValidatorId(0)),
+            &consensus_cfg.into_bytes(),
+            Height(0),
+            sandbox.secret_key(ValidatorId(0)),
+        )
+    };
+    add_one_height_with_transactions(&sandbox, &SandboxState::new(), &[tx_cfg]);
+    assert_eq!(sandbox.public_key(ValidatorId(1)), new_keys.consensus_pk());
+
+    if extra_block {
+        sandbox
+            .validators_map
+            .insert(new_keys.consensus_pk(), new_keys.consensus_sk().clone());
+        add_one_height(&sandbox, &SandboxState::new());
+    }
+    sandbox
+}
+
+/// Imports the state from `source` at the specified `height` into `sandbox`,
+/// expecting that the state synchronization was started with validator #3.
+fn import_state_chunks(sandbox: &Sandbox, source: &Sandbox, height: Height) {
+    let mut cursor = ChunkCursor::default();
+    loop {
+        let response = create_chunk_response(source, ValidatorId(3), height, &cursor, CHUNK_SIZE);
+        let next_cursor = response.payload().next_cursor();
+        sandbox.recv(&response);
+        if let Some(next_cursor) = next_cursor {
+            cursor = next_cursor;
+            sandbox.add_time(Duration::from_millis(STATE_CHUNK_REQUEST_TIMEOUT));
+            sandbox.send(
+                sandbox.public_key(ValidatorId(3)),
+                &create_chunk_request(sandbox, ValidatorId(0), ValidatorId(3), height, &cursor),
+            );
+        } else {
+            break;
+        }
+    }
+}
+
+fn create_chunk_request(
+    sandbox: &Sandbox,
+    from: ValidatorId,
+    to: ValidatorId,
+    height: Height,
+    cursor: &ChunkCursor,
+) -> Verified<StateChunkRequest> {
+    Verified::from_value(
+        StateChunkRequest::new(sandbox.public_key(to), height, cursor),
+        sandbox.public_key(from),
+        sandbox.secret_key(from),
+    )
+}
+
+/// Creates a response with the state chunk exported from the `source` sandbox.
+fn create_chunk_response(
+    source: &Sandbox,
+    from: ValidatorId,
+    height: Height,
+    cursor: &ChunkCursor,
+    max_size: usize,
+) -> Verified<StateChunkResponse> {
+    let proof = source.block_and_precommits(height).unwrap();
+    let (chunk, next_cursor) = source
+        .blockchain()
+        .export_state_chunk(height, cursor, max_size)
+        .unwrap();
+    Verified::from_value(
+        StateChunkResponse::new(
+            source.public_key(ValidatorId(0)),
+            proof.block,
+            proof.precommits.iter().map(BinaryValue::to_bytes),
+            cursor,
+            chunk,
+            next_cursor,
+        ),
+        source.public_key(from),
+        source.secret_key(from),
+    )
+}
+
+/// - get `Status` from other nodes with later height, send `StateChunkRequest`
+/// - receive state chunks one by one; if a peer does not respond, request the chunk
+///   from another peer
+/// - after the last chunk, the node moves to the latest height of peers
+#[test]
+fn state_sync_from_peers() {
+    let (source, transactions) = source_sandbox(3);
+    let sandbox = timestamping_sandbox_builder().with_state_sync().build();
+    assert_eq!(
+        sandbox
+            .blockchain()
+            .snapshot()
+            .for_core()
+            .block_hash_by_height(Height(0)),
+        source
+            .blockchain()
+            .snapshot()
+            .for_core()
+            .block_hash_by_height(Height(0))
+    );
+
+    sandbox.recv(&Sandbox::create_status(
+        sandbox.public_key(ValidatorId(3)),
+        Height(4),
+        source.last_hash(),
+        0,
+        sandbox.secret_key(ValidatorId(3)),
+    ));
+    let mut cursor = ChunkCursor::default();
+    sandbox.add_time(Duration::from_millis(STATE_CHUNK_REQUEST_TIMEOUT));
+    sandbox.send(
+        sandbox.public_key(ValidatorId(3)),
+        &create_chunk_request(&sandbox, ValidatorId(0), ValidatorId(3), Height(3), &cursor),
+    );
+
+    // The first request is not answered, so it is repeated to another peer.
+    sandbox.recv(&Sandbox::create_status(
+        sandbox.public_key(ValidatorId(2)),
+        Height(4),
+        source.last_hash(),
+        0,
+        sandbox.secret_key(ValidatorId(2)),
+    ));
+    sandbox.add_time(Duration::from_millis(STATE_CHUNK_REQUEST_TIMEOUT));
+    sandbox.send(
+        sandbox.public_key(ValidatorId(2)),
+        &create_chunk_request(&sandbox, ValidatorId(0), ValidatorId(2), Height(3), &cursor),
+    );
+
+    let mut chunks = 0;
+    loop {
+        let response =
+            create_chunk_response(&source, ValidatorId(2), Height(3), &cursor, CHUNK_SIZE);
+        let next_cursor = response.payload().next_cursor();
+        sandbox.recv(&response);
+        chunks += 1;
+
+        if let Some(next_cursor) = next_cursor {
+            cursor = next_cursor;
+            sandbox.add_time(Duration::from_millis(STATE_CHUNK_REQUEST_TIMEOUT));
+            sandbox.send(
+                sandbox.public_key(ValidatorId(2)),
+                &create_chunk_request(&sandbox, ValidatorId(0), ValidatorId(2), Height(3), &cursor),
+            );
+        } else {
+            break;
+        }
+    }
+    assert!(chunks > 1, "{}", chunks);
+
+    sandbox.broadcast(&Sandbox::create_status(
+        sandbox.public_key(ValidatorId(0)),
+        Height(4),
+        source.last_hash(),
+        0,
+        sandbox.secret_key(ValidatorId(0)),
+    ));
+    sandbox.assert_state(Height(4), Round(1));
+    assert_eq!(sandbox.last_block(), source.last_block());
+
+    let snapshot = sandbox.blockchain().snapshot();
+    assert_eq!(
+        SystemSchema::new(&snapshot).state_hash(),
+        source.last_state_hash()
+    );
+    let schema = snapshot.for_core();
+    for tx in &transactions {
+        assert!(schema.transactions_locations().contains(&tx.object_hash()));
+    }
+
+    // The node continues operating after the synchronization.
+    add_one_height(&sandbox, &SandboxState::new());
+    sandbox.assert_state(Height(5), Round(1));
+}
+
+/// - start the synchronization and import the first state chunk
+/// - get `Status` from another node with a later height; the synchronized height
+///   does not change, and the next chunk is requested from this node as well
+#[test]
+fn state_sync_height_is_pinned_after_first_chunk() {
+    let (source, _) = source_sandbox(3);
+    let sandbox = timestamping_sandbox_builder().with_state_sync().build();
+
+    sandbox.recv(&Sandbox::create_status(
+        sandbox.public_key(ValidatorId(3)),
+        Height(4),
+        source.last_hash(),
+        0,
+        sandbox.secret_key(ValidatorId(3)),
+    ));
+    let cursor = ChunkCursor::default();
+    sandbox.add_time(Duration::from_millis(STATE_CHUNK_REQUEST_TIMEOUT));
+    sandbox.send(
+        sandbox.public_key(ValidatorId(3)),
+        &create_chunk_request(&sandbox, ValidatorId(0), ValidatorId(3), Height(3), &cursor),
+    );
+
+    let response = create_chunk_response(&source, ValidatorId(3), Height(3), &cursor, CHUNK_SIZE);
+    let cursor = response.payload().next_cursor().unwrap();
+    sandbox.recv(&response);
+
+    sandbox.recv(&Sandbox::create_status(
+        sandbox.public_key(ValidatorId(2)),
+        Height(10),
+        source.last_hash(),
+        0,
+        sandbox.secret_key(ValidatorId(2)),
+    ));
+    sandbox.add_time(Duration::from_millis(STATE_CHUNK_REQUEST_TIMEOUT));
+    sandbox.send(
+        sandbox.public_key(ValidatorId(3)),
+        &create_chunk_request(&sandbox, ValidatorId(0), ValidatorId(3), Height(3), &cursor),
+    );
+    sandbox.add_time(Duration::from_millis(STATE_CHUNK_REQUEST_TIMEOUT));
+    sandbox.send(
+        sandbox.public_key(ValidatorId(2)),
+        &create_chunk_request(&sandbox, ValidatorId(0), ValidatorId(2), Height(3), &cursor),
+    );
+    sandbox.assert_state(Height(1), Round(1));
+}
+
+#[test]
+fn state_chunk_request_is_served() {
+    let (sandbox, _) = source_sandbox(2);
+    let cursor = ChunkCursor::default();
+    sandbox.recv(&create_chunk_request(
+        &sandbox,
+        ValidatorId(1),
+        ValidatorId(0),
+        Height(2),
+        &cursor,
+    ));
+
+    // The whole state fits into a single chunk.
+    let max_size = sandbox.cfg().max_message_len as usize / 2;
+    let response = create_chunk_response(&sandbox, ValidatorId(0), Height(2), &cursor, max_size);
+    assert!(response.payload().is_last);
+    let response = Verified::from_value(
+        StateChunkResponse {
+            to: sandbox.public_key(ValidatorId(1)),
+            ..response.into_payload()
+        },
+        sandbox.public_key(ValidatorId(0)),
+        sandbox.secret_key(ValidatorId(0)),
+    );
+    sandbox.send(sandbox.public_key(ValidatorId(1)), &response);
+
+    // The state for earlier blocks is not available.
+    sandbox.recv(&create_chunk_request(
+        &sandbox,
+        ValidatorId(1),
+        ValidatorId(0),
+        Height(1),
+        &cursor,
+    ));
+}
+
+#[test]
+fn state_chunk_without_precommits_is_ignored() {
+    let (source, _) = source_sandbox(2);
+    let sandbox = timestamping_sandbox_builder().with_state_sync().build();
+    let genesis_block = sandbox.last_block();
+
+    sandbox.recv(&Sandbox::create_status(
+        sandbox.public_key(ValidatorId(3)),
+        Height(3),
+        source.last_hash(),
+        0,
+        sandbox.secret_key(ValidatorId(3)),
+    ));
+    let cursor = ChunkCursor::default();
+    sandbox.add_time(Duration::from_millis(STATE_CHUNK_REQUEST_TIMEOUT));
+    sandbox.send(
+        sandbox.public_key(ValidatorId(3)),
+        &create_chunk_request(&sandbox, ValidatorId(0), ValidatorId(3), Height(2), &cursor),
+    );
+
+    let response = create_chunk_response(&source, ValidatorId(3), Height(2), &cursor, CHUNK_SIZE);
+    let response = Verified::from_value(
+        StateChunkResponse {
+            precommits: vec![],
+            ..response.into_payload()
+        },
+        sandbox.public_key(ValidatorId(3)),
+        sandbox.secret_key(ValidatorId(3)),
+    );
+    sandbox.recv(&response);
+    assert_eq!(sandbox.last_block(), genesis_block);
+    sandbox.assert_state(Height(1), Round(1));
+}
+
+/// Without the state synchronization enabled, the node requests blocks as usual.
+#[test]
+fn no_state_sync_by_default() {
+    let (source, _) = source_sandbox(2);
+    let sandbox = timestamping_sandbox();
+
+    sandbox.recv(&Sandbox::create_status(
+        sandbox.public_key(ValidatorId(3)),
+        Height(3),
+        source.last_hash(),
+        0,
+        sandbox.secret_key(ValidatorId(3)),
+    ));
+    sandbox.add_time(Duration::from_millis(BLOCK_REQUEST_TIMEOUT));
+    sandbox.send(
+        sandbox.public_key(ValidatorId(3)),
+        &Sandbox::create_block_request(
+            sandbox.public_key(ValidatorId(0)),
+            sandbox.public_key(ValidatorId(3)),
+            Height(1),
+            sandbox.secret_key(ValidatorId(0)),
+        ),
+    );
+}
+
+/// - the validator set changes before the synchronization
+/// - the synchronized block is endorsed by genesis validators, but the imported state
+///   shows that validators have changed; the state is discarded
+/// - the node falls back to requesting blocks
+#[test]
+fn state_sync_falls_back_to_blocks_if_validators_changed() {
+    let source = source_sandbox_with_changed_validators(false);
+    let sandbox = timestamping_sandbox_builder().with_state_sync().build();
+    let genesis_block = sandbox.last_block();
+
+    sandbox.recv(&Sandbox::create_status(
+        sandbox.public_key(ValidatorId(3)),
+        Height(3),
+        source.last_hash(),
+        0,
+        sandbox.secret_key(ValidatorId(3)),
+    ));
+    sandbox.add_time(Duration::from_millis(STATE_CHUNK_REQUEST_TIMEOUT));
+    sandbox.send(
+        sandbox.public_key(ValidatorId(3)),
+        &create_chunk_request(
+            &sandbox,
+            ValidatorId(0),
+            ValidatorId(3),
+            Height(2),
+            &ChunkCursor::default(),
+        ),
+    );
+    import_state_chunks(&sandbox, &source, Height(2));
+    assert_eq!(sandbox.last_block(), genesis_block);
+    sandbox.assert_state(Height(1), Round(1));
+
+    sandbox.recv(&Sandbox::create_status(
+        sandbox.public_key(ValidatorId(3)),
+        Height(3),
+        source.last_hash(),
+        0,
+        sandbox.secret_key(ValidatorId(3)),
+    ));
+    sandbox.add_time(Duration::from_millis(BLOCK_REQUEST_TIMEOUT));
+    sandbox.send(
+        sandbox.public_key(ValidatorId(3)),
+        &Sandbox::create_block_request(
+            sandbox.public_key(ValidatorId(0)),
+            sandbox.public_key(ValidatorId(3)),
+            Height(1),
+            sandbox.secret_key(ValidatorId(0)),
+        ),
+    );
+}
+
+/// - the validator set changes before the synchronized block
+/// - the block is not endorsed by genesis validators, so the synchronization is stopped
+///   after the first chunk, and the node falls back to requesting blocks
+#[test]
+fn state_sync_stops_if_block_is_not_endorsed_by_genesis_validators() {
+    let source = source_sandbox_with_changed_validators(true);
+    let sandbox = timestamping_sandbox_builder().with_state_sync().build();
+
+    sandbox.recv(&Sandbox::create_status(
+        sandbox.public_key(ValidatorId(3)),
+        Height(4),
+        source.last_hash(),
+        0,
+        sandbox.secret_key(ValidatorId(3)),
+    ));
+    let cursor = ChunkCursor::default();
+    sandbox.add_time(Duration::from_millis(STATE_CHUNK_REQUEST_TIMEOUT));
+    sandbox.send(
+        sandbox.public_key(ValidatorId(3)),
+        &create_chunk_request(&sandbox, ValidatorId(0), ValidatorId(3), Height(3), &cursor),
+    );
+    sandbox.recv(&create_chunk_response(
+        &source,
+        ValidatorId(3),
+        Height(3),
+        &cursor,
+        CHUNK_SIZE,
+    ));
+    // The next chunk is not requested.
+    sandbox.add_time(Duration::from_millis(STATE_CHUNK_REQUEST_TIMEOUT));
+    sandbox.assert_state(Height(1), Round(1));
+
+    sandbox.recv(&Sandbox::create_status(
+        sandbox.public_key(ValidatorId(3)),
+        Height(4),
+        source.last_hash(),
+        0,
+        sandbox.secret_key(ValidatorId(3)),
+    ));
+    sandbox.add_time(Duration::from_millis(BLOCK_REQUEST_TIMEOUT));
+    sandbox.send(
+        sandbox.public_key(ValidatorId(3)),
+        &Sandbox::create_block_request(
+            sandbox.public_key(ValidatorId(0)),
+            sandbox.public_key(ValidatorId(3)),
+            Height(1),
+            sandbox.secret_key(ValidatorId(0)),
+        ),
+    );
+}
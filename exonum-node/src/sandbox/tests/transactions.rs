@@ -18,7 +18,7 @@ use bit_vec::BitVec;
 use exonum::{
     blockchain::{Blockchain, ProposerId, TransactionCache},
     crypto::{Hash, KeyPair, PublicKey},
-    helpers::{Height, Round, ValidatorId},
+    helpers::{user_agent, Height, Round, ValidatorId},
     merkledb::{BinaryValue, ObjectHash, Snapshot},
     messages::{AnyTx, Verified},
 };
@@ -26,7 +26,7 @@ use exonum::{
 use std::{collections::BTreeSet, iter::FromIterator, time::Duration};
 
 use crate::{
-    messages::{TX_RES_EMPTY_SIZE, TX_RES_PB_OVERHEAD_PAYLOAD},
+    messages::{Connect, TX_RES_EMPTY_SIZE, TX_RES_PB_OVERHEAD_PAYLOAD},
     pool::{ManagePool, Pool, ProposeParams, ProposeTemplate, StandardPoolManager},
     sandbox::{
         sandbox_tests_helper::*,
@@ -37,6 +37,7 @@ use crate::{
         timestamping_sandbox, timestamping_sandbox_builder, Milliseconds, Sandbox,
     },
     state::TRANSACTIONS_REQUEST_TIMEOUT,
+    ConnectInfo,
 };
 
 const MAX_PROPOSE_TIMEOUT: Milliseconds = 200;
@@ -574,6 +575,77 @@ fn request_txs_when_get_propose_or_prevote() {
     sandbox.add_time(Duration::from_millis(0));
 }
 
+/// - request tx from a validator signing consensus messages with a key that differs
+///   from its network key (e.g., with a remote signer)
+/// - idea of the test:
+///   - validator 2 connects with a separate network key and announces its consensus key
+///     in the `Connect` message
+///   - receive Propose with unknown transaction from validator 2
+///   - request unknown transaction from validator 2 by its network key
+///   - receive the transaction and vote for the Propose
+#[test]
+fn request_txs_from_validator_with_separate_network_key() {
+    let mut sandbox = timestamping_sandbox_builder()
+        .do_not_initialize_connections()
+        .build();
+    let time = sandbox.time();
+    sandbox.initialize(time, 1, 2);
+    sandbox.initialize(time, 3, 4);
+
+    let network_keys = KeyPair::random();
+    let address = sandbox.address(ValidatorId(2));
+    sandbox
+        .inner
+        .borrow_mut()
+        .handler
+        .state_mut()
+        .add_peer_to_connect_list(ConnectInfo {
+            address: address.clone(),
+            public_key: network_keys.public_key(),
+        });
+    let connect = Connect::new(address, sandbox.time().into(), user_agent())
+        .with_consensus_key(sandbox.public_key(ValidatorId(2)));
+    sandbox.recv(&Verified::from_value(
+        connect,
+        network_keys.public_key(),
+        network_keys.secret_key(),
+    ));
+    sandbox.send(network_keys.public_key(), &sandbox.connect().unwrap());
+
+    let tx = gen_timestamping_tx();
+    let propose = ProposeBuilder::new(&sandbox)
+        .with_tx_hashes(&[tx.object_hash()])
+        .build();
+    sandbox.recv(&propose);
+    sandbox.add_time(Duration::from_millis(sandbox.current_round_timeout() - 1));
+
+    sandbox.send(
+        network_keys.public_key(),
+        &Sandbox::create_transactions_request(
+            sandbox.public_key(ValidatorId(0)),
+            network_keys.public_key(),
+            vec![tx.object_hash()],
+            sandbox.secret_key(ValidatorId(0)),
+        ),
+    );
+
+    sandbox.recv(&Sandbox::create_transactions_response(
+        network_keys.public_key(),
+        sandbox.public_key(ValidatorId(0)),
+        vec![tx],
+        network_keys.secret_key(),
+    ));
+    sandbox.broadcast_to_addrs(
+        &make_prevote_from_propose(&sandbox, &propose),
+        &[
+            sandbox.public_key(ValidatorId(1)),
+            network_keys.public_key(),
+            sandbox.public_key(ValidatorId(3)),
+        ],
+    );
+    sandbox.add_time(Duration::from_millis(0));
+}
+
 #[test]
 fn regular_propose_when_no_transaction_pressure() {
     let sandbox = timestamping_sandbox_with_threshold();
@@ -0,0 +1,285 @@
+// Copyright 2020 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use chrono::{TimeZone, Utc};
+use exonum::{
+    crypto::{self, Hash},
+    helpers::{Height, Round},
+    merkledb::BinaryValue,
+};
+use serde_derive::{Deserialize, Serialize};
+
+use std::{
+    borrow::Cow,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use super::SignerError;
+use crate::messages::ExonumMessage;
+
+/// Kind of a consensus message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum VoteKind {
+    Propose,
+    Prevote,
+    Precommit,
+}
+
+/// Consensus message signed at the latest epoch.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct SignedVote {
+    kind: VoteKind,
+    round: Round,
+    payload_hash: Hash,
+}
+
+/// State of the guard, which is persisted between restarts of the signer.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct SignState {
+    epoch: Height,
+    votes: Vec<SignedVote>,
+}
+
+impl Default for SignState {
+    fn default() -> Self {
+        Self {
+            epoch: Height::zero(),
+            votes: vec![],
+        }
+    }
+}
+
+/// Protection against signing conflicting consensus messages.
+///
+/// The guard allows to sign at most one `Propose`, `Prevote` and `Precommit` message
+/// for each consensus round; signing the same message again is allowed. Precommits
+/// are compared without their time, since it changes if the node re-signs a precommit
+/// after a restart. Messages
+/// for epochs preceding the latest signed epoch are refused as well. `Status` messages
+/// are always allowed, and all other messages are always refused.
+///
+/// The guard may persist its state in a file, so that the protection is retained
+/// after the signer restarts.
+#[derive(Debug, Default)]
+pub struct DoubleSignGuard {
+    state: SignState,
+    state_path: Option<PathBuf>,
+}
+
+impl DoubleSignGuard {
+    /// Creates a guard with the state kept in memory.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a guard with the state persisted in the specified file. If the file exists,
+    /// the state is loaded from it.
+    pub fn with_state_file(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let state = if path.exists() {
+            let contents = fs::read(path)?;
+            serde_json::from_slice(&contents)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+        } else {
+            SignState::default()
+        };
+
+        Ok(Self {
+            state,
+            state_path: Some(path.to_owned()),
+        })
+    }
+
+    /// Checks whether the message with the specified payload may be signed, and records
+    /// the message as signed if it can.
+    pub fn check(&mut self, payload: &[u8]) -> Result<(), SignerError> {
+        let message = ExonumMessage::from_bytes(Cow::Borrowed(payload))
+            .map_err(|e| SignerError::Rejected(format!("Cannot decode message: {}", e)))?;
+
+        let (kind, epoch, round, payload_hash) = match message {
+            ExonumMessage::Propose(propose) => (
+                VoteKind::Propose,
+                propose.epoch,
+                propose.round,
+                crypto::hash(payload),
+            ),
+            ExonumMessage::Prevote(prevote) => (
+                VoteKind::Prevote,
+                prevote.epoch,
+                prevote.round,
+                crypto::hash(payload),
+            ),
+            ExonumMessage::Precommit(mut precommit) => {
+                // The node sets the current time in precommits, so a precommit re-signed
+                // after the node restart differs from the original one only in time.
+                // Such precommits are not conflicting.
+                precommit.time = Utc.timestamp(0, 0);
+                let (epoch, round) = (precommit.epoch, precommit.round);
+                let payload_hash = crypto::hash(&ExonumMessage::from(precommit).into_bytes());
+                (VoteKind::Precommit, epoch, round, payload_hash)
+            }
+            ExonumMessage::Status(_) => return Ok(()),
+            _ => {
+                let msg = "Only consensus messages can be signed".to_owned();
+                return Err(SignerError::Rejected(msg));
+            }
+        };
+
+        let vote = SignedVote {
+            kind,
+            round,
+            payload_hash,
+        };
+        if epoch < self.state.epoch {
+            let msg = format!(
+                "{:?} for epoch {} precedes the latest signed epoch {}",
+                kind, epoch, self.state.epoch
+            );
+            return Err(SignerError::Rejected(msg));
+        }
+
+        let mut new_state = self.state.clone();
+        if epoch > new_state.epoch {
+            new_state = SignState {
+                epoch,
+                votes: vec![],
+            };
+        }
+
+        let existing_vote = new_state
+            .votes
+            .iter()
+            .find(|signed| signed.kind == kind && signed.round == round);
+        match existing_vote {
+            Some(signed) if signed.payload_hash == vote.payload_hash => return Ok(()),
+            Some(_) => {
+                let msg = format!(
+                    "Another {:?} has already been signed for epoch {}, round {}",
+                    kind, epoch, round
+                );
+                return Err(SignerError::Rejected(msg));
+            }
+            None => new_state.votes.push(vote),
+        }
+
+        // The state is persisted before the signature is released.
+        if let Some(path) = &self.state_path {
+            save_state(&new_state, path)?;
+        }
+        self.state = new_state;
+        Ok(())
+    }
+}
+
+fn save_state(state: &SignState, path: &Path) -> io::Result<()> {
+    let contents = serde_json::to_vec(state)?;
+    let tmp_path = path.with_extension("tmp");
+    fs::write(&tmp_path, contents)?;
+    fs::rename(tmp_path, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use exonum::{
+        crypto::Hash,
+        helpers::{Height, Round, ValidatorId},
+        merkledb::BinaryValue,
+        messages::Precommit,
+    };
+
+    use super::*;
+    use crate::messages::{Prevote, Status};
+
+    fn prevote(epoch: u64, round: u32, propose_hash: Hash) -> Vec<u8> {
+        let prevote = Prevote::new(
+            ValidatorId(0),
+            Height(epoch),
+            Round(round),
+            propose_hash,
+            Round::zero(),
+        );
+        ExonumMessage::from(prevote).into_bytes()
+    }
+
+    #[test]
+    fn guard_refuses_conflicting_votes() {
+        let mut guard = DoubleSignGuard::new();
+        let first_hash = crypto::hash(&[1]);
+        let second_hash = crypto::hash(&[2]);
+
+        guard.check(&prevote(1, 1, first_hash)).unwrap();
+        // The same message may be signed again.
+        guard.check(&prevote(1, 1, first_hash)).unwrap();
+        let err = guard.check(&prevote(1, 1, second_hash)).unwrap_err();
+        assert!(err.to_string().contains("has already been signed"));
+
+        // Votes in other rounds and epochs are fine.
+        guard.check(&prevote(1, 2, second_hash)).unwrap();
+        guard.check(&prevote(2, 1, second_hash)).unwrap();
+        let err = guard.check(&prevote(1, 3, first_hash)).unwrap_err();
+        assert!(err.to_string().contains("precedes the latest signed epoch"));
+
+        // `Status` messages are not restricted.
+        let status = Status::new(Height(1), Height(1), Hash::zero(), 0);
+        guard
+            .check(&ExonumMessage::from(status).into_bytes())
+            .unwrap();
+        let err = guard.check(b"garbage").unwrap_err();
+        assert!(err.to_string().contains("Cannot decode message"));
+    }
+
+    fn precommit(block_hash: Hash, time: i64) -> Vec<u8> {
+        let precommit = Precommit::new(
+            ValidatorId(0),
+            Height(1),
+            Round(1),
+            Hash::zero(),
+            block_hash,
+            Utc.timestamp(time, 0),
+        );
+        ExonumMessage::from(precommit).into_bytes()
+    }
+
+    #[test]
+    fn guard_ignores_time_of_precommits() {
+        let mut guard = DoubleSignGuard::new();
+        let block_hash = crypto::hash(&[1]);
+        guard.check(&precommit(block_hash, 1_000)).unwrap();
+        guard.check(&precommit(block_hash, 2_000)).unwrap();
+        let err = guard
+            .check(&precommit(crypto::hash(&[2]), 1_000))
+            .unwrap_err();
+        assert!(err.to_string().contains("has already been signed"));
+    }
+
+    #[test]
+    fn guard_state_is_persisted() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("signer_state.json");
+        let first_hash = crypto::hash(&[1]);
+
+        let mut guard = DoubleSignGuard::with_state_file(&path).unwrap();
+        guard.check(&prevote(5, 1, first_hash)).unwrap();
+        drop(guard);
+
+        let mut guard = DoubleSignGuard::with_state_file(&path).unwrap();
+        guard.check(&prevote(5, 1, first_hash)).unwrap();
+        let err = guard.check(&prevote(5, 1, crypto::hash(&[2]))).unwrap_err();
+        assert!(err.to_string().contains("has already been signed"));
+        let err = guard.check(&prevote(4, 1, first_hash)).unwrap_err();
+        assert!(err.to_string().contains("precedes the latest signed epoch"));
+    }
+}
@@ -0,0 +1,333 @@
+// Copyright 2020 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Signing of consensus messages.
+//!
+//! The node signs `Propose`, `Prevote`, `Precommit` and `Status` messages with a [`Signer`].
+//! The signer also holds the BLS key of the validator, if any, which is used to sign block
+//! hashes for commit certificates.
+//! By default, the [`LocalSigner`] is used, which holds the consensus secret key in the node
+//! memory. Alternatively, the consensus key may be kept by a separate process, possibly
+//! on another host, which is accessed by the [`RemoteSigner`]. The remote process is
+//! implemented by the [`SignerServer`]; it refuses to sign conflicting consensus messages
+//! with the help of the [`DoubleSignGuard`].
+//!
+//! A remote signer may be configured with the [`NodeConfig::remote_signer`] option
+//! or supplied directly to [`NodeBuilder::with_signer`].
+//!
+//! # Protocol
+//!
+//! The node and the signer exchange frames over a TCP connection or a Unix socket.
+//! Each frame is prefixed by its length encoded as a big-endian `u32`. Responses
+//! of the signer are frames, the first byte of which is a status:
+//!
+//! - `0` means success; the remaining bytes depend on the request
+//! - `1` means that the signer has refused the request; the remaining bytes
+//!   are a UTF-8 description of the reason
+//!
+//! Once a connection is accepted, the signer sends a frame with a random 32-byte nonce.
+//! The node answers with a frame containing its network public key (see below); the signer
+//! responds with a success status if the key is in the list of allowed keys, and closes
+//! the connection otherwise.
+//!
+//! Then, the node sends frames with payloads of messages to sign, i.e., the bytes which
+//! will be placed into the `payload` field of the `SignedMessage`. Each payload is prefixed
+//! by an Ed25519 signature made with the network key of the node; the signature covers
+//! the nonce, the zero-based number of the request within the connection encoded
+//! as a big-endian `u64`, and the payload. The signer closes the connection if the signature
+//! is invalid. Otherwise, the signer answers with the Ed25519 signature of the payload
+//! made with the consensus key; the node checks this signature against the expected key
+//! of the validator.
+//!
+//! # Network identity
+//!
+//! The remote signer does not sign block hashes with a BLS key, so that the BLS key
+//! of the validator is not kept on the node host either; validators using the remote
+//! signer do not add BLS signatures to their precommits.
+//!
+//! The signer is only used for consensus messages. Other messages, as well as
+//! the handshake in the P2P network, are authenticated with the consensus key
+//! from the node [`Keys`]. If the node uses a remote signer, this key is not required
+//! to coincide with the validator key from the consensus configuration; hence, the consensus
+//! secret key of the validator never leaves the signer. However, peers address the node
+//! by its network key, so it should be specified in connect lists of other nodes.
+//! The same key authenticates the node to the signer.
+//!
+//! [`Signer`]: trait.Signer.html
+//! [`LocalSigner`]: struct.LocalSigner.html
+//! [`RemoteSigner`]: struct.RemoteSigner.html
+//! [`SignerServer`]: struct.SignerServer.html
+//! [`DoubleSignGuard`]: struct.DoubleSignGuard.html
+//! [`NodeConfig::remote_signer`]: ../struct.NodeConfig.html#structfield.remote_signer
+//! [`NodeBuilder::with_signer`]: ../struct.NodeBuilder.html#method.with_signer
+//! [`Keys`]: https://docs.rs/exonum/latest/exonum/keys/struct.Keys.html
+
+pub use self::{
+    guard::DoubleSignGuard,
+    remote::{RemoteSigner, SignerServer},
+};
+
+use exonum::{
+    blockchain::CommitCertificate,
+    crypto::{
+        self,
+        bls::{BlsKeyPair, BlsPublicKey, BlsSignature},
+        Hash, KeyPair, PublicKey, Signature, SEED_LENGTH,
+    },
+    helpers::{Height, Milliseconds, Round},
+    messages::SignedMessage,
+};
+use serde::{de::Error as _, Deserializer, Serializer};
+use serde_derive::{Deserialize, Serialize};
+use thiserror::Error;
+
+use std::{fmt, io, net::SocketAddr, path::PathBuf, str::FromStr, sync::Arc};
+
+mod guard;
+mod remote;
+
+/// Signer of consensus messages.
+pub trait Signer: Send + Sync + fmt::Debug {
+    /// Returns the public key corresponding to the secret key of the signer.
+    /// This key must be the consensus key of the validator in the consensus configuration.
+    fn public_key(&self) -> PublicKey;
+
+    /// Signs the payload of a consensus message.
+    fn sign(&self, payload: &[u8]) -> Result<Signature, SignerError>;
+
+    /// Returns `true` if signing may block the current thread for a noticeable time,
+    /// e.g., because it involves network communication. The node signs messages with
+    /// such signers outside of its event loop. The default implementation returns `false`.
+    fn is_blocking(&self) -> bool {
+        false
+    }
+
+    /// Returns a signer for the new consensus key of the validator, to which the node switches
+    /// during key rotation. `keys` are the pending consensus keys of the node.
+    ///
+    /// The default implementation returns `None`, which means that the signer does not support
+    /// key rotation; in this case, the node keeps the pending keys and the current signer.
+    fn rotate(&self, _keys: &KeyPair) -> Option<Arc<dyn Signer>> {
+        None
+    }
+
+    /// Returns the public BLS key, with which the signer signs block hashes
+    /// for commit certificates. The default implementation returns `None`, which means
+    /// that the signer does not hold a BLS key; in this case, precommits of the node
+    /// are not signed with BLS.
+    fn bls_public_key(&self) -> Option<BlsPublicKey> {
+        None
+    }
+
+    /// Signs the hash of a block proposed at the specified epoch and round with the BLS key
+    /// to build a commit certificate for the block. The default implementation refuses
+    /// to sign the hash.
+    fn sign_block_hash(
+        &self,
+        _epoch: Height,
+        _round: Round,
+        _block_hash: &Hash,
+    ) -> Result<BlsSignature, SignerError> {
+        Err(SignerError::Rejected(
+            "Signer does not hold a BLS key".to_owned(),
+        ))
+    }
+}
+
+/// Signs the payload of a consensus message with the specified signer. Returns `None`
+/// and logs an error if the signer has failed.
+pub(crate) fn sign_payload(signer: &dyn Signer, payload: Vec<u8>) -> Option<SignedMessage> {
+    match signer.sign(&payload) {
+        Ok(signature) => Some(SignedMessage::from_parts(
+            payload,
+            signer.public_key(),
+            signature,
+        )),
+        Err(e) => {
+            log::error!("Cannot sign consensus message: {}", e);
+            None
+        }
+    }
+}
+
+/// Errors that can occur during signing consensus messages.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum SignerError {
+    /// I/O error during communication with a remote signer.
+    #[error("Cannot communicate with the signer: {}", _0)]
+    Io(#[from] io::Error),
+
+    /// The signer has refused to sign the message, e.g., because it conflicts
+    /// with a previously signed message.
+    #[error("Signer has refused to sign the message: {}", _0)]
+    Rejected(String),
+
+    /// The response of a remote signer is malformed.
+    #[error("Malformed response from the signer")]
+    MalformedResponse,
+}
+
+/// Signer holding the secret key in memory.
+///
+/// The signer also holds the BLS keypair derived from the consensus secret key
+/// in the same way as in `Keys::bls_keypair()`.
+#[derive(Debug, Clone)]
+pub struct LocalSigner {
+    keys: KeyPair,
+    bls_keys: BlsKeyPair,
+}
+
+impl LocalSigner {
+    /// Creates a signer with the specified keypair.
+    pub fn new(keys: KeyPair) -> Self {
+        let bls_keys = BlsKeyPair::from_seed(&keys.secret_key()[..SEED_LENGTH]);
+        Self { keys, bls_keys }
+    }
+}
+
+impl Signer for LocalSigner {
+    fn public_key(&self) -> PublicKey {
+        self.keys.public_key()
+    }
+
+    fn sign(&self, payload: &[u8]) -> Result<Signature, SignerError> {
+        Ok(crypto::sign(payload, self.keys.secret_key()))
+    }
+
+    fn rotate(&self, keys: &KeyPair) -> Option<Arc<dyn Signer>> {
+        Some(Arc::new(Self::new(keys.clone())))
+    }
+
+    fn bls_public_key(&self) -> Option<BlsPublicKey> {
+        Some(*self.bls_keys.public_key())
+    }
+
+    fn sign_block_hash(
+        &self,
+        _epoch: Height,
+        _round: Round,
+        block_hash: &Hash,
+    ) -> Result<BlsSignature, SignerError> {
+        let message = CommitCertificate::signed_message(block_hash);
+        Ok(self.bls_keys.sign(&message))
+    }
+}
+
+/// Address of a remote signer.
+///
+/// The address is represented as a string in the form `tcp://$host:$port`
+/// or `unix://$path`, e.g., `tcp://10.0.0.5:7100` or `unix:///run/exonum/signer.sock`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SignerAddress {
+    /// TCP socket address.
+    Tcp(SocketAddr),
+    /// Path to a Unix domain socket.
+    Unix(PathBuf),
+}
+
+impl fmt::Display for SignerAddress {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tcp(address) => write!(formatter, "tcp://{}", address),
+            Self::Unix(path) => write!(formatter, "unix://{}", path.display()),
+        }
+    }
+}
+
+impl FromStr for SignerAddress {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        const TCP_PREFIX: &str = "tcp://";
+        const UNIX_PREFIX: &str = "unix://";
+
+        if s.starts_with(TCP_PREFIX) {
+            Ok(Self::Tcp(s[TCP_PREFIX.len()..].parse()?))
+        } else if s.starts_with(UNIX_PREFIX) {
+            let path = &s[UNIX_PREFIX.len()..];
+            anyhow::ensure!(!path.is_empty(), "Path to the Unix socket is empty");
+            Ok(Self::Unix(path.into()))
+        } else {
+            anyhow::bail!(
+                "Invalid signer address `{}`; expected `tcp://$host:$port` or `unix://$path`",
+                s
+            )
+        }
+    }
+}
+
+impl serde::Serialize for SignerAddress {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for SignerAddress {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = <String as serde::Deserialize>::deserialize(deserializer)?;
+        s.parse().map_err(D::Error::custom)
+    }
+}
+
+/// Configuration of a remote signer.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct RemoteSignerConfig {
+    /// Address of the signer.
+    pub address: SignerAddress,
+    /// Consensus public key of the validator held by the signer.
+    pub public_key: PublicKey,
+    /// Timeout for connecting to the signer and waiting for its responses.
+    #[serde(default = "RemoteSignerConfig::default_timeout")]
+    pub timeout: Milliseconds,
+}
+
+impl RemoteSignerConfig {
+    /// Creates a configuration with the default timeout.
+    pub fn new(address: SignerAddress, public_key: PublicKey) -> Self {
+        Self {
+            address,
+            public_key,
+            timeout: Self::default_timeout(),
+        }
+    }
+
+    fn default_timeout() -> Milliseconds {
+        1_000
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use exonum::keys::Keys;
+
+    use super::*;
+
+    #[test]
+    fn local_signer_signs_block_hashes() {
+        let keys = Keys::random();
+        let signer = LocalSigner::new(keys.consensus.clone());
+        let bls_key = *keys.bls_keypair().public_key();
+        assert_eq!(signer.bls_public_key(), Some(bls_key));
+
+        let block_hash = crypto::hash(b"block");
+        let signature = signer
+            .sign_block_hash(Height(1), Round(1), &block_hash)
+            .unwrap();
+        let message = CommitCertificate::signed_message(&block_hash);
+        assert!(bls_key.verify(&message, &signature));
+    }
+}
@@ -0,0 +1,580 @@
+// Copyright 2020 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use exonum::crypto::{self, Hash, KeyPair, PublicKey, Signature, SIGNATURE_LENGTH};
+use rand::{thread_rng, RngCore};
+
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::{
+    collections::HashSet,
+    convert::TryFrom,
+    io::{self, Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use super::{DoubleSignGuard, RemoteSignerConfig, Signer, SignerAddress, SignerError};
+
+/// Maximum length of a frame in the signer protocol.
+const MAX_FRAME_LEN: usize = 16 * 1024 * 1024;
+/// Length of the session nonce sent by the signer.
+const NONCE_LEN: usize = 32;
+/// Domain separator for signatures of requests to the signer, which ensures that
+/// these signatures cannot be confused with signatures of network messages.
+const REQUEST_CONTEXT: &[u8] = b"exonum-signer-request";
+/// Response status for a successfully processed request.
+const STATUS_OK: u8 = 0;
+/// Response status for a request the signer has refused.
+const STATUS_REJECTED: u8 = 1;
+
+fn write_frame(writer: &mut impl Write, data: &[u8]) -> io::Result<()> {
+    let len = u32::try_from(data.len())
+        .ok()
+        .filter(|&len| len as usize <= MAX_FRAME_LEN)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Frame is too long"))?;
+    writer.write_all(&len.to_be_bytes())?;
+    writer.write_all(data)?;
+    writer.flush()
+}
+
+fn read_frame(reader: &mut impl Read) -> io::Result<Vec<u8>> {
+    let mut len_bytes = [0_u8; 4];
+    reader.read_exact(&mut len_bytes)?;
+    let len = u32::from_be_bytes(len_bytes) as usize;
+    if len > MAX_FRAME_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Frame is too long",
+        ));
+    }
+
+    let mut data = vec![0_u8; len];
+    reader.read_exact(&mut data)?;
+    Ok(data)
+}
+
+fn ok_response(data: &[u8]) -> Vec<u8> {
+    let mut response = Vec::with_capacity(1 + data.len());
+    response.push(STATUS_OK);
+    response.extend_from_slice(data);
+    response
+}
+
+fn rejected_response(reason: &str) -> Vec<u8> {
+    let mut response = vec![STATUS_REJECTED];
+    response.extend_from_slice(reason.as_bytes());
+    response
+}
+
+/// Computes the digest signed by the node for a request within a connection.
+fn request_digest(nonce: &[u8], request_index: u64, payload: &[u8]) -> Hash {
+    let mut data = Vec::with_capacity(REQUEST_CONTEXT.len() + nonce.len() + 8 + payload.len());
+    data.extend_from_slice(REQUEST_CONTEXT);
+    data.extend_from_slice(nonce);
+    data.extend_from_slice(&request_index.to_be_bytes());
+    data.extend_from_slice(payload);
+    crypto::hash(&data)
+}
+
+/// Stream connected to a remote signer.
+#[derive(Debug)]
+enum Stream {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl Stream {
+    fn open(address: &SignerAddress, timeout: Duration) -> io::Result<Self> {
+        match address {
+            SignerAddress::Tcp(address) => {
+                let stream = TcpStream::connect_timeout(address, timeout)?;
+                stream.set_nodelay(true)?;
+                stream.set_read_timeout(Some(timeout))?;
+                stream.set_write_timeout(Some(timeout))?;
+                Ok(Self::Tcp(stream))
+            }
+
+            #[cfg(unix)]
+            SignerAddress::Unix(path) => {
+                let stream = UnixStream::connect(path)?;
+                stream.set_read_timeout(Some(timeout))?;
+                stream.set_write_timeout(Some(timeout))?;
+                Ok(Self::Unix(stream))
+            }
+
+            #[cfg(not(unix))]
+            SignerAddress::Unix(_) => Err(io::Error::new(
+                io::ErrorKind::Other,
+                "Unix sockets are not supported on this platform",
+            )),
+        }
+    }
+
+    fn exchange(&mut self, request: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            Self::Tcp(stream) => {
+                write_frame(stream, request)?;
+                read_frame(stream)
+            }
+            #[cfg(unix)]
+            Self::Unix(stream) => {
+                write_frame(stream, request)?;
+                read_frame(stream)
+            }
+        }
+    }
+
+    fn receive(&mut self) -> io::Result<Vec<u8>> {
+        match self {
+            Self::Tcp(stream) => read_frame(stream),
+            #[cfg(unix)]
+            Self::Unix(stream) => read_frame(stream),
+        }
+    }
+}
+
+/// Authenticated connection to a remote signer.
+#[derive(Debug)]
+struct Connection {
+    stream: Stream,
+    nonce: Vec<u8>,
+    request_index: u64,
+}
+
+impl Connection {
+    fn open(address: &SignerAddress, timeout: Duration, keys: &KeyPair) -> io::Result<Self> {
+        let mut stream = Stream::open(address, timeout)?;
+        let nonce = stream.receive()?;
+        if nonce.len() != NONCE_LEN {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Malformed nonce received from the signer",
+            ));
+        }
+
+        let response = stream.exchange(keys.public_key().as_ref())?;
+        match response.split_first() {
+            Some((&STATUS_OK, _)) => Ok(Self {
+                stream,
+                nonce,
+                request_index: 0,
+            }),
+            Some((&STATUS_REJECTED, reason)) => Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!(
+                    "Signer has refused the connection: {}",
+                    String::from_utf8_lossy(reason)
+                ),
+            )),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Malformed response to the handshake",
+            )),
+        }
+    }
+
+    fn exchange(&mut self, payload: &[u8], keys: &KeyPair) -> io::Result<Vec<u8>> {
+        let digest = request_digest(&self.nonce, self.request_index, payload);
+        let signature = crypto::sign(digest.as_ref(), keys.secret_key());
+        let mut request = Vec::with_capacity(SIGNATURE_LENGTH + payload.len());
+        request.extend_from_slice(signature.as_ref());
+        request.extend_from_slice(payload);
+
+        self.request_index += 1;
+        self.stream.exchange(&request)
+    }
+}
+
+/// Signer accessed over the network.
+///
+/// The signer connects to the remote process lazily, and reconnects to it if the connection
+/// is broken. The node authenticates to the remote process with its network keypair, which
+/// should be allowed by the process. Signing is a blocking operation, which takes at most
+/// the double of the configured timeout per connection attempt; thus, the node signs
+/// messages with this signer outside of its event loop.
+#[derive(Debug)]
+pub struct RemoteSigner {
+    config: RemoteSignerConfig,
+    keys: KeyPair,
+    connection: Mutex<Option<Connection>>,
+}
+
+impl RemoteSigner {
+    /// Creates a signer with the specified configuration. `keys` is the network keypair
+    /// of the node used to authenticate to the remote process. No connection is established
+    /// until the first message is signed.
+    pub fn new(config: RemoteSignerConfig, keys: KeyPair) -> Self {
+        Self {
+            config,
+            keys,
+            connection: Mutex::new(None),
+        }
+    }
+
+    fn request_signature(&self, payload: &[u8]) -> io::Result<Vec<u8>> {
+        let timeout = Duration::from_millis(self.config.timeout);
+        let mut connection = self.connection.lock().unwrap();
+
+        // If the connection has been established before, the signer may have closed it
+        // in the meantime; in this case, the request is retried with a new connection.
+        if let Some(existing) = connection.as_mut() {
+            match existing.exchange(payload, &self.keys) {
+                Ok(response) => return Ok(response),
+                Err(e) => log::warn!("Reconnecting to the signer after error: {}", e),
+            }
+        }
+
+        *connection = None;
+        let mut new_connection = Connection::open(&self.config.address, timeout, &self.keys)?;
+        let response = new_connection.exchange(payload, &self.keys)?;
+        *connection = Some(new_connection);
+        Ok(response)
+    }
+}
+
+impl Signer for RemoteSigner {
+    fn public_key(&self) -> PublicKey {
+        self.config.public_key
+    }
+
+    fn sign(&self, payload: &[u8]) -> Result<Signature, SignerError> {
+        let response = self.request_signature(payload)?;
+        let (&status, data) = response
+            .split_first()
+            .ok_or(SignerError::MalformedResponse)?;
+
+        match status {
+            STATUS_OK => {
+                let signature =
+                    Signature::from_slice(data).ok_or(SignerError::MalformedResponse)?;
+                if crypto::verify(&signature, payload, &self.config.public_key) {
+                    Ok(signature)
+                } else {
+                    Err(SignerError::MalformedResponse)
+                }
+            }
+            STATUS_REJECTED => Err(SignerError::Rejected(
+                String::from_utf8_lossy(data).into_owned(),
+            )),
+            _ => Err(SignerError::MalformedResponse),
+        }
+    }
+
+    fn is_blocking(&self) -> bool {
+        true
+    }
+
+    /// Returns a signer which connects to the same remote process, but expects signatures
+    /// made with the new consensus key. The secret key from `keys` is not used; the remote
+    /// process should be switched to the new key by its operator.
+    fn rotate(&self, keys: &KeyPair) -> Option<Arc<dyn Signer>> {
+        let mut config = self.config.clone();
+        config.public_key = keys.public_key();
+        Some(Arc::new(Self::new(config, self.keys.clone())))
+    }
+}
+
+/// Server part of the remote signer.
+///
+/// The server holds the consensus keypair of the validator and signs messages received
+/// from the node, unless signing is refused by the [`DoubleSignGuard`]. Only nodes
+/// with allowed network keys may connect to the server, and each request must be signed
+/// by the node. Each connection is served in a separate thread.
+///
+/// [`DoubleSignGuard`]: struct.DoubleSignGuard.html
+#[derive(Debug)]
+pub struct SignerServer {
+    keys: KeyPair,
+    guard: Mutex<DoubleSignGuard>,
+    allowed_keys: HashSet<PublicKey>,
+}
+
+impl SignerServer {
+    /// Creates a server with the specified keypair and double-signing protection.
+    /// Connections are accepted only from nodes with network keys from `allowed_keys`.
+    pub fn new(
+        keys: KeyPair,
+        guard: DoubleSignGuard,
+        allowed_keys: impl IntoIterator<Item = PublicKey>,
+    ) -> Self {
+        Self {
+            keys,
+            guard: Mutex::new(guard),
+            allowed_keys: allowed_keys.into_iter().collect(),
+        }
+    }
+
+    /// Returns the public key of the signer.
+    pub fn public_key(&self) -> PublicKey {
+        self.keys.public_key()
+    }
+
+    fn respond(&self, payload: &[u8]) -> Vec<u8> {
+        let check_result = self.guard.lock().unwrap().check(payload);
+        match check_result {
+            Ok(()) => {
+                let signature = crypto::sign(payload, self.keys.secret_key());
+                ok_response(signature.as_ref())
+            }
+            Err(e) => {
+                log::warn!("Refused to sign a message: {}", e);
+                rejected_response(&e.to_string())
+            }
+        }
+    }
+
+    /// Authenticates the node on the other end of the connection. Returns the network key
+    /// of the node and the nonce of the connection.
+    fn accept<S: Read + Write>(&self, stream: &mut S) -> io::Result<(PublicKey, Vec<u8>)> {
+        let mut nonce = vec![0_u8; NONCE_LEN];
+        thread_rng().fill_bytes(&mut nonce);
+        write_frame(stream, &nonce)?;
+
+        let hello = read_frame(stream)?;
+        let node_key = PublicKey::from_slice(&hello).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, "Malformed node public key")
+        })?;
+        if !self.allowed_keys.contains(&node_key) {
+            write_frame(stream, &rejected_response("Node key is not allowed"))?;
+            let msg = format!("Connection from a node with unknown key {:?}", node_key);
+            return Err(io::Error::new(io::ErrorKind::PermissionDenied, msg));
+        }
+        write_frame(stream, &ok_response(&[]))?;
+        Ok((node_key, nonce))
+    }
+
+    /// Serves requests from a single connection until it is closed by the client.
+    /// The connection is closed with an error if the client cannot be authenticated.
+    pub fn handle_connection<S: Read + Write>(&self, mut stream: S) -> io::Result<()> {
+        let (node_key, nonce) = self.accept(&mut stream)?;
+        for request_index in 0_u64.. {
+            let request = match read_frame(&mut stream) {
+                Ok(request) => request,
+                Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+                Err(e) => return Err(e),
+            };
+
+            let is_authentic = request.len() >= SIGNATURE_LENGTH && {
+                let (signature, payload) = request.split_at(SIGNATURE_LENGTH);
+                let signature = Signature::from_slice(signature).unwrap();
+                let digest = request_digest(&nonce, request_index, payload);
+                crypto::verify(&signature, digest.as_ref(), &node_key)
+            };
+            if !is_authentic {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Invalid signature of the request",
+                ));
+            }
+            let response = self.respond(&request[SIGNATURE_LENGTH..]);
+            write_frame(&mut stream, &response)?;
+        }
+        Ok(())
+    }
+
+    /// Serves connections accepted by a TCP listener. This method blocks the current thread
+    /// and returns only if the listener fails.
+    pub fn serve_tcp(self, listener: &TcpListener) -> io::Result<()> {
+        let this = Arc::new(self);
+        for stream in listener.incoming() {
+            let stream = stream?;
+            stream.set_nodelay(true)?;
+            this.spawn_handler(stream);
+        }
+        Ok(())
+    }
+
+    /// Serves connections accepted by a Unix socket listener. This method blocks
+    /// the current thread and returns only if the listener fails.
+    #[cfg(unix)]
+    pub fn serve_unix(self, listener: &UnixListener) -> io::Result<()> {
+        let this = Arc::new(self);
+        for stream in listener.incoming() {
+            this.spawn_handler(stream?);
+        }
+        Ok(())
+    }
+
+    fn spawn_handler<S: Read + Write + Send + 'static>(self: &Arc<Self>, stream: S) {
+        let this = Arc::clone(self);
+        thread::spawn(move || {
+            if let Err(e) = this.handle_connection(stream) {
+                log::warn!("Connection to the node has failed: {}", e);
+            }
+        });
+    }
+
+    /// Listens to the specified address and serves incoming connections. This method blocks
+    /// the current thread.
+    pub fn run(self, address: &SignerAddress) -> io::Result<()> {
+        log::info!("Starting signer for {:?} on {}", self.public_key(), address);
+        match address {
+            SignerAddress::Tcp(address) => self.serve_tcp(&TcpListener::bind(address)?),
+            #[cfg(unix)]
+            SignerAddress::Unix(path) => self.serve_unix(&UnixListener::bind(path)?),
+            #[cfg(not(unix))]
+            SignerAddress::Unix(_) => Err(io::Error::new(
+                io::ErrorKind::Other,
+                "Unix sockets are not supported on this platform",
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use exonum::{
+        crypto::{Hash, KeyPair},
+        helpers::{Height, Round, ValidatorId},
+        merkledb::BinaryValue,
+        messages::Precommit,
+    };
+
+    use super::*;
+    use crate::messages::ExonumMessage;
+
+    fn precommit(propose_hash: Hash) -> Vec<u8> {
+        let precommit = Precommit::new(
+            ValidatorId(0),
+            Height(1),
+            Round(1),
+            propose_hash,
+            Hash::zero(),
+            Utc::now(),
+        );
+        ExonumMessage::from(precommit).into_bytes()
+    }
+
+    fn start_server(keys: KeyPair, allowed_keys: Vec<PublicKey>) -> SignerAddress {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = SignerAddress::Tcp(listener.local_addr().unwrap());
+        let server = SignerServer::new(keys, DoubleSignGuard::new(), allowed_keys);
+        thread::spawn(move || server.serve_tcp(&listener));
+        address
+    }
+
+    #[test]
+    fn remote_signer_over_tcp() {
+        let keys = KeyPair::random();
+        let node_keys = KeyPair::random();
+        let address = start_server(keys.clone(), vec![node_keys.public_key()]);
+
+        let config = RemoteSignerConfig::new(address, keys.public_key());
+        let signer = RemoteSigner::new(config, node_keys);
+        assert_eq!(signer.public_key(), keys.public_key());
+        assert!(signer.is_blocking());
+        // The remote signer does not hold a BLS key, and the node does not sign block hashes.
+        assert_eq!(signer.bls_public_key(), None);
+
+        let payload = precommit(crypto::hash(&[1]));
+        let signature = signer.sign(&payload).unwrap();
+        assert!(crypto::verify(&signature, &payload, &keys.public_key()));
+        // Requests within the same connection are authenticated as well.
+        let signature = signer.sign(&payload).unwrap();
+        assert!(crypto::verify(&signature, &payload, &keys.public_key()));
+
+        let err = signer.sign(&precommit(crypto::hash(&[2]))).unwrap_err();
+        match err {
+            SignerError::Rejected(reason) => assert!(reason.contains("already been signed")),
+            other => panic!("Unexpected error: {}", other),
+        }
+    }
+
+    #[test]
+    fn remote_signer_with_wrong_key() {
+        let node_keys = KeyPair::random();
+        let address = start_server(KeyPair::random(), vec![node_keys.public_key()]);
+
+        let public_key = KeyPair::random().public_key();
+        let signer = RemoteSigner::new(RemoteSignerConfig::new(address, public_key), node_keys);
+        let err = signer.sign(&precommit(Hash::zero())).unwrap_err();
+        assert!(matches!(err, SignerError::MalformedResponse));
+    }
+
+    #[test]
+    fn remote_signer_rotation() {
+        let node_keys = KeyPair::random();
+        let new_keys = KeyPair::random();
+        let address = start_server(new_keys.clone(), vec![node_keys.public_key()]);
+
+        let config = RemoteSignerConfig::new(address, KeyPair::random().public_key());
+        let signer = RemoteSigner::new(config, node_keys);
+        let signer = signer.rotate(&new_keys).unwrap();
+        assert_eq!(signer.public_key(), new_keys.public_key());
+        assert!(signer.is_blocking());
+
+        let payload = precommit(Hash::zero());
+        let signature = signer.sign(&payload).unwrap();
+        assert!(crypto::verify(&signature, &payload, &new_keys.public_key()));
+    }
+
+    #[test]
+    fn remote_signer_refuses_unknown_node() {
+        let keys = KeyPair::random();
+        let address = start_server(keys.clone(), vec![KeyPair::random().public_key()]);
+
+        let config = RemoteSignerConfig::new(address, keys.public_key());
+        let signer = RemoteSigner::new(config, KeyPair::random());
+        let err = signer.sign(&precommit(Hash::zero())).unwrap_err();
+        match err {
+            SignerError::Io(e) => {
+                assert_eq!(e.kind(), io::ErrorKind::PermissionDenied);
+                assert!(e.to_string().contains("Node key is not allowed"));
+            }
+            other => panic!("Unexpected error: {}", other),
+        }
+    }
+
+    #[test]
+    fn signer_server_refuses_unsigned_requests() {
+        let keys = KeyPair::random();
+        let node_keys = KeyPair::random();
+        let address = start_server(keys, vec![node_keys.public_key()]);
+        let mut stream = Stream::open(&address, Duration::from_secs(1)).unwrap();
+        assert_eq!(stream.receive().unwrap().len(), NONCE_LEN);
+        let response = stream.exchange(node_keys.public_key().as_ref()).unwrap();
+        assert_eq!(response, [STATUS_OK]);
+
+        // Request signed for another nonce.
+        let payload = precommit(Hash::zero());
+        let digest = request_digest(&[0; NONCE_LEN], 0, &payload);
+        let mut request = crypto::sign(digest.as_ref(), node_keys.secret_key())
+            .as_ref()
+            .to_vec();
+        request.extend_from_slice(&payload);
+        assert!(stream.exchange(&request).is_err());
+    }
+
+    #[test]
+    fn signer_address_parsing() {
+        let address: SignerAddress = "tcp://127.0.0.1:7100".parse().unwrap();
+        assert_eq!(
+            address,
+            SignerAddress::Tcp("127.0.0.1:7100".parse().unwrap())
+        );
+        assert_eq!(address.to_string(), "tcp://127.0.0.1:7100");
+
+        let address: SignerAddress = "unix:///run/signer.sock".parse().unwrap();
+        assert_eq!(address, SignerAddress::Unix("/run/signer.sock".into()));
+        assert_eq!(address.to_string(), "unix:///run/signer.sock");
+
+        assert!("127.0.0.1:7100".parse::<SignerAddress>().is_err());
+        assert!("unix://".parse::<SignerAddress>().is_err());
+    }
+}
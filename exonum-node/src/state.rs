@@ -16,18 +16,20 @@
 
 use anyhow::bail;
 use bit_vec::BitVec;
+use chrono::{DateTime, Utc};
 use exonum::{
     blockchain::{
-        Block, BlockKind, BlockPatch, BlockchainMut, ConsensusConfig, PersistentPool,
-        TransactionCache, TxCheckCache, ValidatorKeys,
+        Block, BlockKind, BlockPatch, BlockchainMut, ConsensusConfig, EquivocationEvidence,
+        EquivocationKind, PersistentPool, TransactionCache, TxCheckCache, TxLocation,
+        ValidatorKeys,
     },
     crypto::{Hash, PublicKey},
     helpers::{byzantine_quorum, Height, Milliseconds, Round, ValidatorId},
     keys::Keys,
-    merkledb::{access::RawAccess, KeySetIndex, MapIndex, ObjectHash, Snapshot},
-    messages::{AnyTx, Precommit, Verified},
+    merkledb::{access::RawAccess, dump::ChunkCursor, KeySetIndex, MapIndex, ObjectHash, Snapshot},
+    messages::{AnyTx, Precommit, SignedMessage, Verified},
 };
-use log::{error, trace};
+use log::{error, info, trace};
 
 use std::{
     collections::{hash_map::Entry, BTreeMap, HashMap, HashSet},
@@ -40,6 +42,7 @@ use crate::{
     consensus::{PersistChanges, RoundAction},
     events::ConnectedPeerAddr,
     messages::{Connect, Consensus as ConsensusMessage, Prevote, Propose, Status},
+    signer::Signer,
     Configuration, ConnectInfo, FlushPoolStrategy,
 };
 
@@ -53,6 +56,8 @@ pub const TRANSACTIONS_REQUEST_TIMEOUT: Milliseconds = 100;
 pub const PREVOTES_REQUEST_TIMEOUT: Milliseconds = 100;
 /// Timeout value for the `BlockRequest` message.
 pub const BLOCK_REQUEST_TIMEOUT: Milliseconds = 100;
+/// Timeout value for the `StateChunkRequest` message.
+pub const STATE_CHUNK_REQUEST_TIMEOUT: Milliseconds = 1_000;
 
 #[derive(Debug, Clone, Copy)]
 pub(crate) struct PeerState {
@@ -138,6 +143,11 @@ pub(crate) struct State {
     blocks: HashMap<Hash, BlockState>,
     prevotes: HashMap<(Round, Hash), Votes<Verified<Prevote>>>,
     precommits: HashMap<(Round, Hash), Votes<Verified<Precommit>>>,
+    // First consensus message of each kind received from a validator in a round;
+    // used to detect equivocation.
+    first_messages: HashMap<(EquivocationKind, ValidatorId, Round), SignedMessage>,
+    // Evidence of equivocation not yet saved to the blockchain storage.
+    queued_evidence: Vec<EquivocationEvidence>,
 
     queued: Vec<ConsensusMessage>,
 
@@ -170,6 +180,11 @@ pub(crate) struct State {
     invalid_txs: HashSet<Hash>,
 
     keys: Keys,
+    signer: Arc<dyn Signer>,
+    // Keys the node switches to once they are activated by the consensus configuration.
+    pending_keys: Option<Keys>,
+    // Pending keys after their activation.
+    active_keys: Option<Keys>,
 }
 
 /// State of a validator node.
@@ -178,6 +193,8 @@ pub(crate) struct ValidatorState {
     id: ValidatorId,
     our_prevotes: HashMap<Round, Verified<Prevote>>,
     our_precommits: HashMap<Round, Verified<Precommit>>,
+    // Rounds for which our pre-votes are being signed by a blocking signer.
+    pending_prevotes: HashSet<Round>,
 }
 
 /// `RequestData` represents a request for some data to other nodes. Each enum variant will be
@@ -199,6 +216,8 @@ pub(crate) enum RequestData {
     /// Represents `BlockRequest` message with `epoch` field set. This is used to request
     /// a block at the next height or a block skip with a successive epoch.
     BlockOrEpoch { block_height: Height, epoch: Height },
+    /// Represents `StateChunkRequest` message.
+    StateChunk(Height, ChunkCursor),
 }
 
 #[derive(Debug)]
@@ -277,6 +296,7 @@ impl ValidatorState {
             id,
             our_precommits: HashMap::new(),
             our_prevotes: HashMap::new(),
+            pending_prevotes: HashSet::new(),
         }
     }
 
@@ -290,15 +310,17 @@ impl ValidatorState {
         self.id = id;
     }
 
-    /// Checks if the node has pre-vote for the specified round.
+    /// Checks if the node has pre-vote for the specified round, or if the pre-vote
+    /// is being signed.
     pub fn have_prevote(&self, round: Round) -> bool {
-        self.our_prevotes.get(&round).is_some()
+        self.our_prevotes.get(&round).is_some() || self.pending_prevotes.contains(&round)
     }
 
     /// Clears pre-commits and pre-votes.
     pub fn clear(&mut self) {
         self.our_precommits.clear();
         self.our_prevotes.clear();
+        self.pending_prevotes.clear();
     }
 }
 
@@ -351,6 +373,7 @@ impl RequestData {
             }
             Self::Prevotes(..) => PREVOTES_REQUEST_TIMEOUT,
             Self::Block(..) | Self::BlockOrEpoch { .. } => BLOCK_REQUEST_TIMEOUT,
+            Self::StateChunk(..) => STATE_CHUNK_REQUEST_TIMEOUT,
         };
         Duration::from_millis(ms)
     }
@@ -548,13 +571,9 @@ impl State {
         let validator_id = consensus_config
             .validator_keys
             .iter()
-            .position(|pk| pk.consensus_key == config.keys.consensus_pk());
+            .position(|pk| pk.consensus_key == config.signer.public_key());
 
-        let our_connect_message = Verified::from_value(
-            connect,
-            config.keys.consensus_pk(),
-            config.keys.consensus_sk(),
-        );
+        let our_connect_message = sign_connect(connect, &config.keys, config.signer.public_key());
 
         let last_epoch = last_block_skip
             .map_or_else(|| last_block.epoch(), Block::epoch)
@@ -577,6 +596,8 @@ impl State {
             blocks: HashMap::new(),
             prevotes: HashMap::new(),
             precommits: HashMap::new(),
+            first_messages: HashMap::new(),
+            queued_evidence: Vec::new(),
 
             queued: Vec::new(),
 
@@ -597,6 +618,9 @@ impl State {
             invalid_txs: HashSet::default(),
 
             keys: config.keys,
+            signer: config.signer,
+            pending_keys: config.pending_keys,
+            active_keys: None,
         }
     }
 
@@ -656,25 +680,75 @@ impl State {
     }
 
     /// Replaces `ConsensusConfig` with a new one and updates validator ID of the current node
-    /// if the new config is different from the previous one.
-    pub fn update_config(&mut self, config: ConsensusConfig) {
+    /// if the new config is different from the previous one. Returns the pending keys
+    /// of the node if they were activated by the new config.
+    pub fn update_config(&mut self, config: ConsensusConfig) -> Option<&Keys> {
         if self.config == config {
-            return;
+            return None;
         }
 
         trace!("Updating node config={:#?}", config);
-        let validator_id = config
+        self.config = config;
+        // TODO: update connect list (ECR-1745)
+
+        self.update_validator_id();
+        self.activate_pending_keys()
+    }
+
+    /// Switches the node to the pending keys if the consensus key from these keys is present
+    /// in the current consensus config. After the switch, consensus messages are signed
+    /// with the new consensus key by the signer returned from `Signer::rotate()`.
+    /// Returns the activated keys.
+    ///
+    /// The keys used to authenticate the node in the network (i.e., the keys
+    /// returned by `keys()`) are not changed; the new consensus key should be announced
+    /// to peers with `update_our_connect_message()`.
+    pub(super) fn activate_pending_keys(&mut self) -> Option<&Keys> {
+        let consensus_key = self.pending_keys()?.consensus_pk();
+        let is_active = self
+            .config
             .validator_keys
             .iter()
-            .position(|pk| pk.consensus_key == self.keys().consensus_pk())
-            .map(|id| ValidatorId(id as u16));
+            .any(|keys| keys.consensus_key == consensus_key);
+        if !is_active {
+            return None;
+        }
 
-        // TODO: update connect list (ECR-1745)
+        let signer = match self.signer.rotate(&self.pending_keys()?.consensus) {
+            Some(signer) => signer,
+            None => {
+                error!(
+                    "Cannot switch to the new consensus key {}: the signer does not support \
+                     key rotation",
+                    consensus_key
+                );
+                return None;
+            }
+        };
+
+        let keys = self.pending_keys.take()?;
+        info!(
+            "Switching to the new consensus key {} and service key {}",
+            keys.consensus_pk(),
+            keys.service_pk()
+        );
+        self.signer = signer;
+        self.active_keys = Some(keys);
+        self.update_validator_id();
+        self.active_keys.as_ref()
+    }
+
+    /// Updates validator ID of the current node based on the public key of the signer.
+    fn update_validator_id(&mut self) {
+        let validator_id = self
+            .config
+            .validator_keys
+            .iter()
+            .position(|pk| pk.consensus_key == self.signer.public_key())
+            .map(|id| ValidatorId(id as u16));
 
         self.renew_validator_id(validator_id);
         trace!("Validator={:#?}", self.validator_state());
-
-        self.config = config;
     }
 
     /// Adds the public key, address, and `Connect` message of a validator.
@@ -694,12 +768,31 @@ impl State {
         self.peers.remove(key)
     }
 
-    /// Checks if this node considers a peer to be a validator.
+    /// Checks if this node considers a peer to be a validator. The peer is identified
+    /// by its network key; its consensus key is taken from its `Connect` message.
     pub(super) fn peer_is_validator(&self, pubkey: &PublicKey) -> bool {
+        let consensus_key = self
+            .peers
+            .get(pubkey)
+            .and_then(|connect| connect.payload().consensus_key)
+            .unwrap_or(*pubkey);
         self.config
             .validator_keys
             .iter()
-            .any(|x| &x.consensus_key == pubkey)
+            .any(|x| &x.consensus_key == pubkey || x.consensus_key == consensus_key)
+    }
+
+    /// Returns the key identifying a peer in the network by the key the peer uses
+    /// in consensus. If no connected peer has announced this consensus key in its
+    /// `Connect` message, the key is returned as is.
+    pub(super) fn network_key_of(&self, consensus_key: &PublicKey) -> PublicKey {
+        if self.peers.contains_key(consensus_key) {
+            return *consensus_key;
+        }
+        self.peers
+            .iter()
+            .find(|(_, connect)| connect.payload().consensus_key.as_ref() == Some(consensus_key))
+            .map_or(*consensus_key, |(network_key, _)| *network_key)
     }
 
     /// Checks if a peer is in this node's connection list.
@@ -728,6 +821,28 @@ impl State {
         &self.keys
     }
 
+    /// Returns the signer of consensus messages.
+    pub fn signer(&self) -> &dyn Signer {
+        self.signer.as_ref()
+    }
+
+    /// Returns a shared reference to the signer of consensus messages.
+    pub(crate) fn shared_signer(&self) -> Arc<dyn Signer> {
+        Arc::clone(&self.signer)
+    }
+
+    /// Returns the keys the node will switch to once they are activated
+    /// by the consensus config.
+    pub fn pending_keys(&self) -> Option<&Keys> {
+        self.pending_keys.as_ref()
+    }
+
+    /// Returns the keys used by the node in consensus, i.e., the activated pending keys
+    /// or, if there are none, the keys of the node.
+    pub(crate) fn consensus_keys(&self) -> &Keys {
+        self.active_keys.as_ref().unwrap_or(&self.keys)
+    }
+
     /// Returns the leader id for the specified round and current height.
     pub fn leader(&self, round: Round) -> ValidatorId {
         let epoch: u64 = self.epoch().into();
@@ -929,6 +1044,7 @@ impl State {
         self.proposes_confirmed_by_majority.clear();
         self.prevotes.clear();
         self.precommits.clear();
+        self.first_messages.clear();
         self.validators_rounds.clear();
         if let Some(ref mut validator_state) = self.validator_state {
             validator_state.clear();
@@ -951,6 +1067,18 @@ impl State {
         self.tx_check_cache = TxCheckCache::new();
     }
 
+    /// Moves the node to the height following the `block` together with entering a new epoch.
+    /// Unlike `new_height`, the block is not required to follow the current height;
+    /// this is used after synchronizing the blockchain state with other nodes.
+    pub(super) fn jump_to_height(&mut self, block: &Block, epoch_start_time: SystemTime) {
+        let epoch = block.epoch().expect("No `epoch` recorded in the block");
+        self.new_epoch(epoch.next(), epoch_start_time);
+        self.blockchain_height = block.height.next();
+        self.last_hash = block.object_hash();
+        self.invalid_txs.clear();
+        self.tx_check_cache = TxCheckCache::new();
+    }
+
     /// Returns a list of queued consensus messages.
     pub(super) fn queued(&mut self) -> Vec<ConsensusMessage> {
         let mut queued = Vec::new();
@@ -1127,6 +1255,7 @@ impl State {
         &mut self,
         msg: Verified<Propose>,
         transactions: &MapIndex<T, Hash, Verified<AnyTx>>,
+        transaction_locations: &MapIndex<T, Hash, TxLocation>,
         transaction_pool: &KeySetIndex<T, Hash>,
     ) -> anyhow::Result<&ProposeState> {
         let propose_hash = msg.object_hash();
@@ -1145,6 +1274,9 @@ impl State {
                         if !transaction_pool.contains(hash) {
                             bail!("Received propose with already committed transaction");
                         }
+                    } else if transaction_locations.contains(hash) {
+                        // The transaction is committed in a pruned block.
+                        bail!("Received propose with already committed transaction");
                     } else if self.invalid_txs.contains(hash) {
                         // If the propose contains an invalid transaction,
                         // we don't stop processing, since we expect this propose to
@@ -1224,6 +1356,61 @@ impl State {
         self.incomplete_block().unwrap()
     }
 
+    /// Checks whether a consensus message conflicts with the message of the same kind received
+    /// from the same validator in the same round of the current epoch. Returns the evidence
+    /// of equivocation if the messages conflict.
+    pub(super) fn check_equivocation(
+        &mut self,
+        kind: EquivocationKind,
+        validator: ValidatorId,
+        round: Round,
+        message: &SignedMessage,
+    ) -> Option<EquivocationEvidence> {
+        let first_message = match self.first_messages.entry((kind, validator, round)) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                entry.insert(message.clone());
+                return None;
+            }
+        };
+        if first_message == message {
+            return None;
+        }
+
+        let evidence = EquivocationEvidence::new(first_message.clone(), message.clone());
+        evidence.verify(&self.config.validator_keys).ok()?;
+        Some(evidence)
+    }
+
+    /// Adds the evidence of equivocation to the queue of evidence to be saved.
+    pub(super) fn queue_evidence(&mut self, evidence: EquivocationEvidence) {
+        self.queued_evidence.push(evidence);
+    }
+
+    /// Returns the evidence of equivocation which is not saved yet.
+    pub(super) fn queued_evidence(&self) -> &[EquivocationEvidence] {
+        &self.queued_evidence
+    }
+
+    /// Clears the queue of evidence after it has been saved.
+    pub(super) fn clear_queued_evidence(&mut self) {
+        self.queued_evidence.clear();
+    }
+
+    /// Marks our pre-vote for the specified round as being signed.
+    pub(super) fn add_pending_prevote(&mut self, round: Round) {
+        if let Some(ref mut validator_state) = self.validator_state {
+            validator_state.pending_prevotes.insert(round);
+        }
+    }
+
+    /// Removes the mark set by `add_pending_prevote`.
+    pub(super) fn remove_pending_prevote(&mut self, round: Round) {
+        if let Some(ref mut validator_state) = self.validator_state {
+            validator_state.pending_prevotes.remove(&round);
+        }
+    }
+
     /// Adds pre-vote. Returns `true` there are +2/3 pre-votes.
     ///
     /// # Panics
@@ -1364,6 +1551,11 @@ impl State {
         is_new
     }
 
+    /// Checks whether the specified request is pending.
+    pub(super) fn is_requested(&self, data: &RequestData) -> bool {
+        self.requests.contains_key(data)
+    }
+
     /// Returns public key of a peer that has required information. Returned key is removed from
     /// the corresponding validators list, so next time request will be sent to a different peer.
     pub(super) fn retry(
@@ -1400,6 +1592,15 @@ impl State {
         &self.our_connect_message
     }
 
+    /// Replaces the `Connect` message of the current node with a message created
+    /// at the specified time, which announces the current consensus key of the node.
+    pub(super) fn update_our_connect_message(&mut self, time: DateTime<Utc>) -> &Verified<Connect> {
+        let payload = self.our_connect_message.payload();
+        let connect = Connect::new(payload.host.clone(), time, payload.user_agent.clone());
+        self.our_connect_message = sign_connect(connect, &self.keys, self.signer.public_key());
+        &self.our_connect_message
+    }
+
     /// Add peer to node's `ConnectList`.
     pub fn add_peer_to_connect_list(&mut self, peer: ConnectInfo) {
         let mut list = self
@@ -1452,3 +1653,14 @@ impl State {
         &mut self.invalid_txs
     }
 }
+
+/// Signs the `Connect` message of the node with its network key. The message announces
+/// the consensus key of the node if it differs from the network key.
+fn sign_connect(connect: Connect, keys: &Keys, consensus_key: PublicKey) -> Verified<Connect> {
+    let connect = if consensus_key == keys.consensus_pk() {
+        connect
+    } else {
+        connect.with_consensus_key(consensus_key)
+    };
+    Verified::from_value(connect, keys.consensus_pk(), keys.consensus_sk())
+}
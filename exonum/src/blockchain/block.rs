@@ -13,10 +13,14 @@
 // limitations under the License.
 
 use anyhow::ensure;
-use exonum_crypto::{Hash, PublicKey};
+use exonum_crypto::{
+    bls::{aggregate_signatures, verify_aggregate, BlsSignature},
+    Hash, PublicKey,
+};
 use exonum_derive::{BinaryValue, ObjectHash};
 use exonum_merkledb::{
-    proof_map::MapProofError, BinaryValue, MapProof, ObjectHash, ValidationError,
+    compact::Compact, proof_list::ListProofError, proof_map::MapProofError, BinaryValue, MapProof,
+    ObjectHash, ValidationError,
 };
 use exonum_proto::ProtobufConvert;
 use thiserror::Error;
@@ -24,7 +28,7 @@ use thiserror::Error;
 use std::borrow::Cow;
 
 use crate::{
-    blockchain::CallInBlock,
+    blockchain::{CallInBlock, ValidatorKeys},
     helpers::{byzantine_quorum, Height, OrderedMap, ValidatorId},
     messages::{Precommit, Verified},
     proto::schema,
@@ -223,11 +227,137 @@ impl Block {
     }
 }
 
+/// Compact proof that a block was endorsed by the Byzantine majority of validators.
+///
+/// The certificate consists of the IDs of the validators that have endorsed the block
+/// and the aggregation of their BLS signatures of the block hash. Unlike `Precommit`
+/// messages, the size of the certificate practically does not depend on the number
+/// of validators. Certificates can only be created if validators have BLS keys
+/// in the consensus configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Serialize, Deserialize)]
+#[derive(ProtobufConvert, BinaryValue, ObjectHash)]
+#[protobuf_convert(source = "schema::blockchain::CommitCertificate")]
+#[non_exhaustive]
+pub struct CommitCertificate {
+    /// IDs of the validators that have signed the block, in the increasing order.
+    pub signers: Vec<ValidatorId>,
+    /// Aggregated BLS signature of the validators.
+    pub signature: BlsSignature,
+}
+
+impl CommitCertificate {
+    /// Domain separation prefix for the signed messages.
+    const MESSAGE_PREFIX: &'static [u8] = b"exonum-commit:";
+
+    /// Returns the message which validators sign with their BLS keys to endorse
+    /// the block with the specified hash.
+    pub fn signed_message(block_hash: &Hash) -> Vec<u8> {
+        let mut message = Self::MESSAGE_PREFIX.to_vec();
+        message.extend_from_slice(block_hash.as_ref());
+        message
+    }
+
+    /// Creates a certificate from the BLS signatures contained in `precommits`.
+    ///
+    /// Invalid signatures and signatures from validators without a BLS key are skipped.
+    /// Returns `None` if the remaining signatures are not sufficient to form
+    /// the Byzantine majority of validators.
+    pub fn from_precommits(
+        precommits: &[Verified<Precommit>],
+        validator_keys: &[ValidatorKeys],
+    ) -> Option<Self> {
+        let block_hash = precommits.first()?.payload().block_hash;
+        let message = Self::signed_message(&block_hash);
+
+        let mut signatures = precommits
+            .iter()
+            .map(Verified::payload)
+            .filter(|precommit| precommit.block_hash == block_hash)
+            .filter_map(|precommit| {
+                let signature = precommit.bls_signature?;
+                let keys = validator_keys.get(usize::from(precommit.validator))?;
+                let public_key = keys.bls_key?.public_key;
+                Some((precommit.validator, public_key, signature))
+            })
+            .collect::<Vec<_>>();
+        signatures.sort_by_key(|(validator, ..)| *validator);
+        signatures.dedup_by_key(|(validator, ..)| *validator);
+
+        let quorum = byzantine_quorum(validator_keys.len());
+        if signatures.len() < quorum {
+            return None;
+        }
+
+        // Most of the time all signatures are valid, so we verify them individually
+        // only if the aggregated signature turns out to be incorrect.
+        let mut aggregated =
+            aggregate_signatures(signatures.iter().map(|(.., signature)| signature));
+        let is_valid = verify_aggregate(
+            &message,
+            signatures.iter().map(|(_, public_key, _)| public_key),
+            &aggregated,
+        );
+        if !is_valid {
+            signatures.retain(|(_, public_key, signature)| public_key.verify(&message, signature));
+            if signatures.len() < quorum {
+                return None;
+            }
+            aggregated = aggregate_signatures(signatures.iter().map(|(.., signature)| signature));
+        }
+
+        Some(Self {
+            signers: signatures
+                .iter()
+                .map(|(validator, ..)| *validator)
+                .collect(),
+            signature: aggregated,
+        })
+    }
+
+    /// Verifies that the block with the specified hash is endorsed by the Byzantine majority
+    /// of provided validators.
+    pub fn verify(
+        &self,
+        block_hash: &Hash,
+        validator_keys: &[ValidatorKeys],
+    ) -> Result<(), ProofError> {
+        if self.signers.len() < byzantine_quorum(validator_keys.len()) {
+            return Err(ProofError::NoQuorum);
+        }
+        let signers_are_ordered = self.signers.windows(2).all(|pair| pair[0] < pair[1]);
+        if !signers_are_ordered {
+            return Err(ProofError::InvalidCertificate);
+        }
+
+        let public_keys = self
+            .signers
+            .iter()
+            .map(|&validator| {
+                let keys = validator_keys
+                    .get(usize::from(validator))
+                    .ok_or(ProofError::IncorrectValidatorId)?;
+                keys.bls_key
+                    .map(|bls_key| bls_key.public_key)
+                    .ok_or(ProofError::InvalidCertificate)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let message = Self::signed_message(block_hash);
+        if verify_aggregate(&message, &public_keys, &self.signature) {
+            Ok(())
+        } else {
+            Err(ProofError::InvalidCertificate)
+        }
+    }
+}
+
 /// Block with its `Precommit` messages.
 ///
 /// This structure contains enough information to prove the correctness of
 /// a block. It consists of the block itself and the `Precommit`
-/// messages related to this block.
+/// messages related to this block. If validators have BLS keys, the proof
+/// may also contain a [commit certificate](struct.CommitCertificate.html) for the block.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ProtobufConvert)]
 #[protobuf_convert(source = "schema::proofs::BlockProof")]
 #[non_exhaustive]
@@ -238,12 +368,62 @@ pub struct BlockProof {
     pub block: Block,
     /// List of `Precommit` messages for the block.
     pub precommits: Vec<Verified<Precommit>>,
+    /// Commit certificate for the block.
+    #[protobuf_convert(with = "pb_optional_certificate")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit_certificate: Option<CommitCertificate>,
+}
+
+/// Conversion of an optional certificate; the absent certificate is represented
+/// by the default Protobuf message.
+mod pb_optional_certificate {
+    use exonum_proto::ProtobufConvert;
+
+    use super::CommitCertificate;
+    use crate::proto::schema::blockchain::CommitCertificate as PbCommitCertificate;
+
+    pub fn from_pb(pb: PbCommitCertificate) -> anyhow::Result<Option<CommitCertificate>> {
+        if pb.get_signers().is_empty() && !pb.has_signature() {
+            Ok(None)
+        } else {
+            CommitCertificate::from_pb(pb).map(Some)
+        }
+    }
+
+    pub fn to_pb(value: &Option<CommitCertificate>) -> PbCommitCertificate {
+        value
+            .as_ref()
+            .map_or_else(PbCommitCertificate::new, CommitCertificate::to_pb)
+    }
 }
 
 impl BlockProof {
     /// Creates a new `BlockProof` object.
     pub fn new(block: Block, precommits: Vec<Verified<Precommit>>) -> Self {
-        Self { block, precommits }
+        Self {
+            block,
+            precommits,
+            commit_certificate: None,
+        }
+    }
+
+    /// Attaches a commit certificate to this proof.
+    pub fn with_commit_certificate(mut self, certificate: CommitCertificate) -> Self {
+        self.commit_certificate = Some(certificate);
+        self
+    }
+
+    /// Verifies that the block in this proof is endorsed by the Byzantine majority of provided
+    /// validators using the commit certificate. Unlike [`verify`], precommits in the proof
+    /// are not checked and may be absent.
+    ///
+    /// [`verify`]: #method.verify
+    pub fn verify_certificate(&self, validator_keys: &[ValidatorKeys]) -> Result<(), ProofError> {
+        let certificate = self
+            .commit_certificate
+            .as_ref()
+            .ok_or(ProofError::NoCertificate)?;
+        certificate.verify(&self.block.object_hash(), validator_keys)
     }
 
     /// Verifies that the block in this proof is endorsed by the Byzantine majority of provided
@@ -301,7 +481,8 @@ impl BlockProof {
     }
 }
 
-/// Errors that can occur during verification of `BlockProof`s, `IndexProof`s and `CallProof`s.
+/// Errors that can occur during verification of `BlockProof`s, `IndexProof`s, `CallProof`s
+/// and `StateProof`s.
 #[derive(Debug, Error)]
 #[non_exhaustive]
 pub enum ProofError {
@@ -334,6 +515,14 @@ pub enum ProofError {
     #[error("Multiple precommits from the same validator")]
     DoubleEndorsement,
 
+    /// The proof does not contain a commit certificate.
+    #[error("Proof does not contain a commit certificate")]
+    NoCertificate,
+
+    /// Commit certificate is malformed or its signature is incorrect.
+    #[error("Commit certificate is invalid")]
+    InvalidCertificate,
+
     /// The proof does not actually prove existence of any index.
     #[error("Proof does not actually prove existence of any entry")]
     NoEntry,
@@ -350,6 +539,31 @@ pub enum ProofError {
     /// properly functioning Exonum node.
     #[error("Call status embedded in the proof is malformed")]
     MalformedStatus,
+
+    /// The proof does not contain data for the index, or the data is not authenticated
+    /// by the state aggregator.
+    #[error("Proof does not contain authenticated data for the index")]
+    NoIndexData,
+
+    /// The proof contains data for an index of another type.
+    #[error("Proof contains data for an index of another type")]
+    IndexTypeMismatch,
+
+    /// Index data in the proof cannot be decoded with the specified types.
+    #[error("Index data in the proof cannot be decoded")]
+    MalformedIndexData,
+
+    /// Proof for entries of a map index is incorrect.
+    #[error("Map proof is incorrect: {}", _0)]
+    IncorrectMapProof(#[source] ValidationError<MapProofError>),
+
+    /// Proof for elements of a list index is incorrect.
+    #[error("List proof is incorrect: {}", _0)]
+    IncorrectListProof(#[source] ValidationError<ListProofError>),
+
+    /// Value of an entry does not match its hash in the state aggregator.
+    #[error("Entry value does not match its hash")]
+    IncorrectEntryValue,
 }
 
 /// Proof of authenticity for a single index within the database.
@@ -570,6 +784,55 @@ impl CallProof {
     }
 }
 
+/// Version of [`CallProof`] with the proof of the call status encoded in the compact
+/// binary format.
+///
+/// The JSON representation is the same as for `CallProof`, except for the `call_proof` field,
+/// which is a hex string with the compact encoding of the map proof. The compact proof
+/// should be converted to `CallProof` for verification.
+///
+/// [`CallProof`]: struct.CallProof.html
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct CompactCallProof {
+    /// Proof of authenticity for the block header.
+    #[serde(flatten)]
+    pub block_proof: BlockProof,
+
+    /// Proof of authenticity for the call status in the compact binary format.
+    pub call_proof: Compact<MapProof<CallInBlock, ExecutionError>>,
+
+    /// Human-readable description of an error if the call status is erroneous.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_description: Option<String>,
+
+    /// Error backtrace.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_backtrace: Option<Vec<CallSite>>,
+}
+
+impl From<CallProof> for CompactCallProof {
+    fn from(proof: CallProof) -> Self {
+        Self {
+            block_proof: proof.block_proof,
+            call_proof: Compact(proof.call_proof),
+            error_description: proof.error_description,
+            error_backtrace: proof.error_backtrace,
+        }
+    }
+}
+
+impl From<CompactCallProof> for CallProof {
+    fn from(proof: CompactCallProof) -> Self {
+        Self {
+            block_proof: proof.block_proof,
+            call_proof: proof.call_proof.0,
+            error_description: proof.error_description,
+            error_backtrace: proof.error_backtrace,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use assert_matches::assert_matches;
@@ -581,7 +844,12 @@ mod tests {
     use pretty_assertions::{assert_eq, assert_ne};
 
     use super::*;
-    use crate::{blockchain::Schema as CoreSchema, helpers::Round, runtime::InstanceId};
+    use crate::{
+        blockchain::{Schema as CoreSchema, ValidatorBlsKey},
+        crypto::bls::BlsKeyPair,
+        helpers::Round,
+        runtime::InstanceId,
+    };
 
     impl BlockHeaderKey for Hash {
         const NAME: &'static str = "HASH";
@@ -876,6 +1144,151 @@ mod tests {
         );
     }
 
+    fn create_validator_keys(keys: &[KeyPair], bls_keys: &[BlsKeyPair]) -> Vec<ValidatorKeys> {
+        keys.iter()
+            .zip(bls_keys)
+            .map(|(keypair, bls_keypair)| {
+                let service_key = KeyPair::random().public_key();
+                ValidatorKeys::new(keypair.public_key(), service_key)
+                    .with_bls_key(ValidatorBlsKey::from_keypair(bls_keypair))
+            })
+            .collect()
+    }
+
+    fn sign_with_bls(proof: &mut BlockProof, keys: &[KeyPair], bls_keys: &[BlsKeyPair]) {
+        let message = CommitCertificate::signed_message(&proof.block.object_hash());
+        for precommit in &mut proof.precommits {
+            let validator = usize::from(precommit.payload().validator);
+            let payload = precommit
+                .payload()
+                .clone()
+                .with_bls_signature(bls_keys[validator].sign(&message));
+            let keypair = &keys[validator];
+            *precommit = Verified::from_value(payload, keypair.public_key(), keypair.secret_key());
+        }
+    }
+
+    #[test]
+    fn commit_certificate() {
+        let keys: Vec<_> = (0..4).map(|_| KeyPair::random()).collect();
+        let bls_keys: Vec<_> = (0..4_u8).map(|i| BlsKeyPair::from_seed(&[i; 32])).collect();
+        let validator_keys = create_validator_keys(&keys, &bls_keys);
+
+        let mut proof = create_block_proof(&keys, Hash::zero(), Hash::zero());
+        assert!(CommitCertificate::from_precommits(&proof.precommits, &validator_keys).is_none());
+
+        sign_with_bls(&mut proof, &keys, &bls_keys);
+        let certificate =
+            CommitCertificate::from_precommits(&proof.precommits, &validator_keys).unwrap();
+        assert_eq!(certificate.signers.len(), 4);
+        let block_hash = proof.block.object_hash();
+        certificate.verify(&block_hash, &validator_keys).unwrap();
+
+        // The certificate can be used without precommits.
+        proof.precommits.clear();
+        let proof = proof.with_commit_certificate(certificate.clone());
+        proof.verify_certificate(&validator_keys).unwrap();
+        let restored = BlockProof::from_pb(proof.to_pb()).unwrap();
+        assert_eq!(restored, proof);
+        let restored = CommitCertificate::from_bytes(certificate.to_bytes().into()).unwrap();
+        assert_eq!(restored, certificate);
+    }
+
+    #[test]
+    fn commit_certificate_skips_invalid_signatures() {
+        let keys: Vec<_> = (0..4).map(|_| KeyPair::random()).collect();
+        let bls_keys: Vec<_> = (0..4_u8).map(|i| BlsKeyPair::from_seed(&[i; 32])).collect();
+        let validator_keys = create_validator_keys(&keys, &bls_keys);
+
+        let mut proof = create_block_proof(&keys, Hash::zero(), Hash::zero());
+        sign_with_bls(&mut proof, &keys, &bls_keys);
+
+        // Replace the signature of a validator with the signature of another message.
+        let mut payload = proof.precommits[2].payload().clone();
+        payload.bls_signature = Some(bls_keys[2].sign(b"other message"));
+        proof.precommits[2] =
+            Verified::from_value(payload, keys[2].public_key(), keys[2].secret_key());
+
+        let certificate =
+            CommitCertificate::from_precommits(&proof.precommits, &validator_keys).unwrap();
+        assert_eq!(
+            certificate.signers,
+            vec![ValidatorId(0), ValidatorId(1), ValidatorId(3)]
+        );
+        certificate
+            .verify(&proof.block.object_hash(), &validator_keys)
+            .unwrap();
+
+        // Two valid signatures do not constitute a quorum.
+        proof.precommits.truncate(3);
+        assert!(CommitCertificate::from_precommits(&proof.precommits, &validator_keys).is_none());
+    }
+
+    #[test]
+    fn incorrect_commit_certificates() {
+        let keys: Vec<_> = (0..4).map(|_| KeyPair::random()).collect();
+        let bls_keys: Vec<_> = (0..4_u8).map(|i| BlsKeyPair::from_seed(&[i; 32])).collect();
+        let validator_keys = create_validator_keys(&keys, &bls_keys);
+
+        let mut proof = create_block_proof(&keys, Hash::zero(), Hash::zero());
+        assert_matches!(
+            proof.verify_certificate(&validator_keys).unwrap_err(),
+            ProofError::NoCertificate
+        );
+        sign_with_bls(&mut proof, &keys, &bls_keys);
+        let certificate =
+            CommitCertificate::from_precommits(&proof.precommits, &validator_keys).unwrap();
+        let block_hash = proof.block.object_hash();
+
+        // Certificate for another block.
+        assert_matches!(
+            certificate
+                .verify(&Hash::zero(), &validator_keys)
+                .unwrap_err(),
+            ProofError::InvalidCertificate
+        );
+
+        // Too few signers.
+        let mut mauled = certificate.clone();
+        mauled.signers.truncate(2);
+        assert_matches!(
+            mauled.verify(&block_hash, &validator_keys).unwrap_err(),
+            ProofError::NoQuorum
+        );
+
+        // Duplicate signers.
+        let mut mauled = certificate.clone();
+        mauled.signers = vec![ValidatorId(0), ValidatorId(0), ValidatorId(1)];
+        assert_matches!(
+            mauled.verify(&block_hash, &validator_keys).unwrap_err(),
+            ProofError::InvalidCertificate
+        );
+
+        // Signer outside of the validator set.
+        let mut mauled = certificate.clone();
+        mauled.signers[3] = ValidatorId(4);
+        assert_matches!(
+            mauled.verify(&block_hash, &validator_keys).unwrap_err(),
+            ProofError::IncorrectValidatorId
+        );
+
+        // Signer without a BLS key.
+        let mut other_keys = validator_keys.clone();
+        other_keys[1].bls_key = None;
+        assert_matches!(
+            certificate.verify(&block_hash, &other_keys).unwrap_err(),
+            ProofError::InvalidCertificate
+        );
+
+        // Signature does not correspond to the signers.
+        let mut mauled = certificate;
+        mauled.signers.truncate(3);
+        assert_matches!(
+            mauled.verify(&block_hash, &validator_keys).unwrap_err(),
+            ProofError::InvalidCertificate
+        );
+    }
+
     fn create_index_proof() -> (Hash, MapProof<String, Hash>) {
         let db = TemporaryDB::new();
         let fork = db.fork();
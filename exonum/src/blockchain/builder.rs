@@ -31,6 +31,10 @@ pub struct BlockchainBuilder {
     runtimes: Vec<RuntimeInstance>,
     /// Blockchain configuration used to create the genesis block.
     genesis_config: Option<GenesisConfig>,
+    /// Should the blockchain record the history of states?
+    keeps_state_history: bool,
+    /// Number of latest blocks for which the history of states is retained.
+    state_history_depth: Option<u64>,
 }
 
 impl BlockchainBuilder {
@@ -40,6 +44,8 @@ impl BlockchainBuilder {
             blockchain,
             runtimes: vec![],
             genesis_config: None,
+            keeps_state_history: false,
+            state_history_depth: None,
         }
     }
 
@@ -56,6 +62,37 @@ impl BlockchainBuilder {
         self
     }
 
+    /// Enables recording the history of blockchain states. With the history enabled, the state
+    /// of the blockchain after any committed block can be accessed
+    /// via [`Blockchain::snapshot_at()`].
+    ///
+    /// The history is recorded with the help of `DatabaseExt::merge_with_history()`
+    /// for the patches of committed blocks. Keeping the history increases the database size
+    /// proportionally to the size of the changes in each block; use
+    /// [`with_state_history_depth()`] to bound the size of the history.
+    /// The history is only recorded for blocks committed while it is enabled.
+    ///
+    /// [`Blockchain::snapshot_at()`]: struct.Blockchain.html#method.snapshot_at
+    /// [`with_state_history_depth()`]: #method.with_state_history_depth
+    pub fn with_state_history(mut self) -> Self {
+        self.keeps_state_history = true;
+        self
+    }
+
+    /// Enables recording the history of blockchain states, retaining the states only
+    /// for the latest `depth` blocks. The history for older blocks is removed from the database
+    /// once new blocks are committed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `depth` is zero.
+    pub fn with_state_history_depth(mut self, depth: u64) -> Self {
+        assert!(depth > 0, "State history depth must be positive");
+        self.keeps_state_history = true;
+        self.state_history_depth = Some(depth);
+        self
+    }
+
     /// Returns blockchain instance, creates and commits the genesis block with the specified
     /// genesis configuration if the blockchain has not been initialized.
     /// Otherwise restores dispatcher state from database.
@@ -72,6 +109,9 @@ impl BlockchainBuilder {
         let mut blockchain = BlockchainMut {
             dispatcher: Dispatcher::new(&self.blockchain, self.runtimes),
             inner: self.blockchain,
+            keeps_state_history: self.keeps_state_history,
+            state_history_depth: self.state_history_depth,
+            staged_state: None,
         };
 
         // If genesis block had been already created just restores dispatcher state from database
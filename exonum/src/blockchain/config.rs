@@ -26,7 +26,10 @@ use log::warn;
 use std::collections::{HashMap, HashSet};
 
 use crate::{
-    crypto::PublicKey,
+    crypto::{
+        bls::{BlsKeyPair, BlsPublicKey, BlsSignature},
+        PublicKey,
+    },
     helpers::{Milliseconds, ValidateInput, ValidatorId},
     keys::Keys,
     merkledb::BinaryValue,
@@ -38,16 +41,20 @@ use crate::{
 /// Public keys of a validator. Each validator has two public keys: the
 /// `consensus_key` is used for internal operations in the consensus process,
 /// while the `service_key` is used in services.
+///
+/// Additionally, a validator may have a BLS key, which is used to create
+/// [commit certificates](struct.CommitCertificate.html) for blocks.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 #[derive(Serialize, Deserialize)]
-#[derive(ProtobufConvert)]
-#[protobuf_convert(source = "schema::blockchain::ValidatorKeys")]
 #[non_exhaustive]
 pub struct ValidatorKeys {
     /// Consensus key is used for messages related to the consensus algorithm.
     pub consensus_key: PublicKey,
     /// Service key is used to sign transactions broadcast by the services.
     pub service_key: PublicKey,
+    /// Optional BLS key used to sign commit certificates.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bls_key: Option<ValidatorBlsKey>,
 }
 
 impl ValidatorKeys {
@@ -61,8 +68,15 @@ impl ValidatorKeys {
         Self {
             consensus_key,
             service_key,
+            bls_key: None,
         }
     }
+
+    /// Sets the BLS key of the validator.
+    pub fn with_bls_key(mut self, bls_key: ValidatorBlsKey) -> Self {
+        self.bls_key = Some(bls_key);
+        self
+    }
 }
 
 impl ValidateInput for ValidatorKeys {
@@ -72,6 +86,76 @@ impl ValidateInput for ValidatorKeys {
         if self.consensus_key == self.service_key {
             bail!("Consensus and service keys must be different.");
         }
+        if let Some(bls_key) = &self.bls_key {
+            bls_key.validate()?;
+        }
+        Ok(())
+    }
+}
+
+// `ProtobufConvert` is implemented manually, so that keys without a BLS key retain
+// their binary representation (and thus, the hash of the consensus configuration).
+impl ProtobufConvert for ValidatorKeys {
+    type ProtoStruct = schema::blockchain::ValidatorKeys;
+
+    fn to_pb(&self) -> Self::ProtoStruct {
+        let mut pb = Self::ProtoStruct::new();
+        pb.set_consensus_key(self.consensus_key.to_pb());
+        pb.set_service_key(self.service_key.to_pb());
+        if let Some(bls_key) = &self.bls_key {
+            pb.set_bls_key(bls_key.to_pb());
+        }
+        pb
+    }
+
+    fn from_pb(mut pb: Self::ProtoStruct) -> anyhow::Result<Self> {
+        let bls_key = if pb.has_bls_key() {
+            Some(ValidatorBlsKey::from_pb(pb.take_bls_key())?)
+        } else {
+            None
+        };
+        Ok(Self {
+            consensus_key: PublicKey::from_pb(pb.take_consensus_key())?,
+            service_key: PublicKey::from_pb(pb.take_service_key())?,
+            bls_key,
+        })
+    }
+}
+
+/// BLS public key of a validator together with the proof of possession
+/// of the corresponding secret key. The proof prevents rogue key attacks
+/// on aggregated signatures.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(Serialize, Deserialize)]
+#[derive(ProtobufConvert)]
+#[protobuf_convert(source = "schema::blockchain::ValidatorBlsKey")]
+#[non_exhaustive]
+pub struct ValidatorBlsKey {
+    /// BLS public key.
+    pub public_key: BlsPublicKey,
+    /// Proof of possession of the secret key.
+    pub proof_of_possession: BlsSignature,
+}
+
+impl ValidatorBlsKey {
+    /// Creates a BLS key of a validator from the keypair.
+    pub fn from_keypair(keypair: &BlsKeyPair) -> Self {
+        Self {
+            public_key: *keypair.public_key(),
+            proof_of_possession: keypair.prove_possession(),
+        }
+    }
+}
+
+impl ValidateInput for ValidatorBlsKey {
+    type Error = anyhow::Error;
+
+    fn validate(&self) -> Result<(), Self::Error> {
+        ensure!(
+            self.public_key.verify_possession(&self.proof_of_possession),
+            "Invalid proof of possession for the BLS key {}",
+            self.public_key
+        );
         Ok(())
     }
 }
@@ -197,6 +281,7 @@ impl ConsensusConfig {
         );
 
         let mut exist_keys = HashSet::with_capacity(self.validator_keys.len() * 2);
+        let mut exist_bls_keys = HashSet::new();
         for validator_keys in &self.validator_keys {
             validator_keys.validate()?;
             if exist_keys.contains(&validator_keys.consensus_key)
@@ -207,6 +292,15 @@ impl ConsensusConfig {
 
             exist_keys.insert(validator_keys.consensus_key);
             exist_keys.insert(validator_keys.service_key);
+
+            // Since proofs of possession are public, a validator could copy the BLS key
+            // of another validator to double-count its signatures in commit certificates.
+            if let Some(bls_key) = &validator_keys.bls_key {
+                ensure!(
+                    exist_bls_keys.insert(bls_key.public_key),
+                    "Duplicated BLS keys are found: each BLS key must be unique"
+                );
+            }
         }
 
         Ok(())
@@ -637,6 +731,34 @@ mod tests {
         assert_err_contains(e, "Consensus and service keys must be different");
     }
 
+    #[test]
+    fn validate_validator_keys_with_bls_key() {
+        let bls_keypair = BlsKeyPair::from_seed(&[1; 32]);
+        let keys = gen_validator_keys(0).with_bls_key(ValidatorBlsKey::from_keypair(&bls_keypair));
+        keys.validate().expect("Expected valid validator keys");
+
+        let other_keypair = BlsKeyPair::from_seed(&[2; 32]);
+        let mut bls_key = ValidatorBlsKey::from_keypair(&bls_keypair);
+        bls_key.proof_of_possession = other_keypair.prove_possession();
+        let keys = gen_validator_keys(0).with_bls_key(bls_key);
+        let e = keys.validate().unwrap_err();
+        assert_err_contains(e, "Invalid proof of possession");
+    }
+
+    #[test]
+    fn validator_keys_without_bls_key_keep_binary_representation() {
+        let keys = gen_validator_keys(0);
+        let mut pb = schema::blockchain::ValidatorKeys::new();
+        pb.set_consensus_key(keys.consensus_key.to_pb());
+        pb.set_service_key(keys.service_key.to_pb());
+        assert_eq!(keys.to_pb(), pb);
+
+        let bls_key = ValidatorBlsKey::from_keypair(&BlsKeyPair::from_seed(&[1; 32]));
+        let keys = keys.with_bls_key(bls_key);
+        let restored = ValidatorKeys::from_pb(keys.to_pb()).unwrap();
+        assert_eq!(restored, keys);
+    }
+
     #[test]
     fn consensus_config_validate_ok() {
         let cfg = ConsensusConfig {
@@ -683,6 +805,19 @@ mod tests {
                 },
                 "Duplicated keys are found",
             ),
+            (
+                {
+                    let bls_key = ValidatorBlsKey::from_keypair(&BlsKeyPair::from_seed(&[1; 32]));
+                    ConsensusConfig {
+                        validator_keys: vec![
+                            ValidatorKeys::new(keys[0], keys[1]).with_bls_key(bls_key),
+                            ValidatorKeys::new(keys[2], keys[3]).with_bls_key(bls_key),
+                        ],
+                        ..ConsensusConfig::default()
+                    }
+                },
+                "Duplicated BLS keys are found",
+            ),
             (
                 ConsensusConfig {
                     min_propose_timeout: 10,
@@ -0,0 +1,342 @@
+// Copyright 2020 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use exonum_crypto::{self as crypto, schemes::SignatureScheme, Hash};
+use exonum_derive::{BinaryValue, ObjectHash};
+use exonum_merkledb::ObjectHash;
+use exonum_proto::ProtobufConvert;
+use protobuf::Message;
+use thiserror::Error;
+
+use crate::{
+    blockchain::ValidatorKeys,
+    helpers::{Height, Round, ValidatorId},
+    messages::SignedMessage,
+    proto::schema::messages as pb_messages,
+};
+
+/// Kind of consensus messages involved in an equivocation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum EquivocationKind {
+    /// Two different `Propose` messages.
+    Propose,
+    /// Two `Prevote` messages for different proposals.
+    Prevote,
+    /// Two `Precommit` messages for different proposals or blocks.
+    Precommit,
+}
+
+/// Information about an equivocation proven by `EquivocationEvidence`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Equivocation {
+    /// ID of the equivocating validator.
+    pub validator: ValidatorId,
+    /// Kind of the conflicting messages.
+    pub kind: EquivocationKind,
+    /// Consensus epoch of the conflicting messages.
+    pub epoch: Height,
+    /// Consensus round of the conflicting messages.
+    pub round: Round,
+}
+
+/// Evidence that a validator has signed two conflicting consensus messages.
+///
+/// Honest validators sign at most one `Propose`, one `Prevote` and one `Precommit`
+/// for each epoch and round of the consensus algorithm. A pair of signed messages of
+/// the same kind and for the same round which vote for different proposals or blocks
+/// proves that the validator is faulty. Such evidence is recorded by nodes in
+/// the [`equivocation_evidence`] index of the core schema, and may be recorded
+/// in the blockchain by services via [`ExecutionContext::report_equivocation`].
+///
+/// [`equivocation_evidence`]: struct.Schema.html#method.equivocation_evidence
+/// [`ExecutionContext::report_equivocation`]: ../runtime/struct.ExecutionContext.html#method.report_equivocation
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Serialize, Deserialize)]
+#[derive(ProtobufConvert, BinaryValue, ObjectHash)]
+#[protobuf_convert(source = "pb_messages::EquivocationEvidence")]
+#[non_exhaustive]
+pub struct EquivocationEvidence {
+    /// First of the conflicting messages.
+    pub first: SignedMessage,
+    /// Second of the conflicting messages.
+    pub second: SignedMessage,
+}
+
+impl EquivocationEvidence {
+    /// Creates evidence from two messages. The messages are ordered by their hashes,
+    /// so that the evidence (and its hash) does not depend on the order of arguments.
+    pub fn new(first: SignedMessage, second: SignedMessage) -> Self {
+        if first.object_hash() <= second.object_hash() {
+            Self { first, second }
+        } else {
+            Self {
+                first: second,
+                second: first,
+            }
+        }
+    }
+
+    /// Returns the consensus epoch of the conflicting messages. The configuration active
+    /// at this epoch should be used to verify the evidence.
+    pub fn epoch(&self) -> Result<Height, EvidenceError> {
+        MessageView::decode(&self.first).map(|view| view.equivocation.epoch)
+    }
+
+    /// Verifies the evidence against the keys of validators from the consensus configuration
+    /// which was active during the equivocation. Returns information about the equivocation
+    /// if the evidence is correct.
+    pub fn verify(&self, validator_keys: &[ValidatorKeys]) -> Result<Equivocation, EvidenceError> {
+        let first = MessageView::decode(&self.first)?;
+        let second = MessageView::decode(&self.second)?;
+        if first.equivocation != second.equivocation {
+            return Err(EvidenceError::DifferentRounds);
+        }
+        if first.choice == second.choice {
+            return Err(EvidenceError::NoConflict);
+        }
+
+        let validator = first.equivocation.validator;
+        let keys = validator_keys
+            .get(usize::from(validator))
+            .ok_or(EvidenceError::UnknownValidator)?;
+        for message in &[&self.first, &self.second] {
+            if message.signature_scheme() != SignatureScheme::Ed25519
+                || message.author != keys.consensus_key
+            {
+                return Err(EvidenceError::KeyMismatch);
+            }
+            if !message.verify_signature() {
+                return Err(EvidenceError::InvalidSignature);
+            }
+        }
+        Ok(first.equivocation)
+    }
+}
+
+/// Information about a consensus message necessary to check equivocation evidence.
+#[derive(Debug)]
+struct MessageView {
+    equivocation: Equivocation,
+    /// Hashes identifying the choice made by the validator in the message: the hash
+    /// of the payload for `Propose`s, the proposal hash for `Prevote`s, and the proposal
+    /// and block hashes for `Precommit`s.
+    choice: (Hash, Hash),
+}
+
+impl MessageView {
+    fn decode(message: &SignedMessage) -> Result<Self, EvidenceError> {
+        Self::decode_payload(&message.payload).map_err(|_| EvidenceError::NotConsensusMessage)
+    }
+
+    fn decode_payload(payload: &[u8]) -> anyhow::Result<Self> {
+        let mut view = pb_messages::ConsensusMessageView::parse_from_bytes(payload)?;
+        let (kind, validator, epoch, round, choice) = if view.has_precommit() {
+            let mut pb = view.take_precommit();
+            let choice = (
+                Hash::from_pb(pb.take_propose_hash())?,
+                Hash::from_pb(pb.take_block_hash())?,
+            );
+            let kind = EquivocationKind::Precommit;
+            (kind, pb.validator, pb.epoch, pb.round, choice)
+        } else if view.has_prevote() {
+            let mut pb = view.take_prevote();
+            let choice = (Hash::from_pb(pb.take_propose_hash())?, Hash::zero());
+            let kind = EquivocationKind::Prevote;
+            (kind, pb.validator, pb.epoch, pb.round, choice)
+        } else if view.has_propose() {
+            let pb = view.take_propose();
+            let choice = (crypto::hash(payload), Hash::zero());
+            let kind = EquivocationKind::Propose;
+            (kind, pb.validator, pb.epoch, pb.round, choice)
+        } else {
+            anyhow::bail!("Not a consensus message");
+        };
+
+        let equivocation = Equivocation {
+            validator: ValidatorId::from_pb(validator)?,
+            kind,
+            epoch: Height(epoch),
+            round: Round(round),
+        };
+        Ok(Self {
+            equivocation,
+            choice,
+        })
+    }
+}
+
+/// Errors that can occur during verification of `EquivocationEvidence`.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum EvidenceError {
+    /// One of the messages is not a `Propose`, `Prevote` or `Precommit`.
+    #[error("Evidence contains a message which is not a consensus message")]
+    NotConsensusMessage,
+
+    /// Messages have different kinds, validators, epochs or rounds.
+    #[error("Messages relate to different validators, kinds, epochs or rounds")]
+    DifferentRounds,
+
+    /// Messages do not conflict with each other.
+    #[error("Messages do not conflict with each other")]
+    NoConflict,
+
+    /// Validator ID mentioned in the messages is not present in the consensus configuration.
+    #[error("Unknown validator ID")]
+    UnknownValidator,
+
+    /// Author of a message differs from the consensus key of the validator.
+    #[error("Mismatch between message author and the consensus key of the validator")]
+    KeyMismatch,
+
+    /// Signature of a message is incorrect.
+    #[error("Invalid message signature")]
+    InvalidSignature,
+}
+
+#[cfg(test)]
+mod tests {
+    use exonum_crypto::KeyPair;
+    use exonum_merkledb::BinaryValue;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::messages::{CoreMessage, Precommit};
+
+    fn prevote(propose_hash: Hash, keys: &KeyPair) -> SignedMessage {
+        let mut pb = pb_messages::PrevoteView::new();
+        pb.set_validator(1);
+        pb.set_epoch(5);
+        pb.set_round(2);
+        pb.set_propose_hash(propose_hash.to_pb());
+        let mut view = pb_messages::ConsensusMessageView::new();
+        view.set_prevote(pb);
+        let payload = view.write_to_bytes().unwrap();
+        SignedMessage::new(payload, keys.public_key(), keys.secret_key())
+    }
+
+    fn precommit(block_hash: Hash, keys: &KeyPair) -> SignedMessage {
+        let precommit = Precommit::new(
+            ValidatorId(1),
+            Height(5),
+            Round(2),
+            Hash::zero(),
+            block_hash,
+            chrono::Utc::now(),
+        );
+        let payload = CoreMessage::from(precommit).into_bytes();
+        SignedMessage::new(payload, keys.public_key(), keys.secret_key())
+    }
+
+    fn validator_keys(keys: &KeyPair) -> Vec<ValidatorKeys> {
+        let other = KeyPair::random().public_key();
+        vec![
+            ValidatorKeys::new(other, other),
+            ValidatorKeys::new(keys.public_key(), other),
+        ]
+    }
+
+    #[test]
+    fn evidence_for_prevotes() {
+        let keys = KeyPair::random();
+        let validator_keys = validator_keys(&keys);
+        let first = prevote(crypto::hash(&[1]), &keys);
+        let second = prevote(crypto::hash(&[2]), &keys);
+
+        let evidence = EquivocationEvidence::new(first.clone(), second.clone());
+        let reversed = EquivocationEvidence::new(second, first.clone());
+        assert_eq!(evidence, reversed);
+        let equivocation = evidence.verify(&validator_keys).unwrap();
+        assert_eq!(
+            equivocation,
+            Equivocation {
+                validator: ValidatorId(1),
+                kind: EquivocationKind::Prevote,
+                epoch: Height(5),
+                round: Round(2),
+            }
+        );
+
+        let same = EquivocationEvidence::new(first.clone(), first.clone());
+        assert!(matches!(
+            same.verify(&validator_keys).unwrap_err(),
+            EvidenceError::NoConflict
+        ));
+
+        let other_keys = KeyPair::random();
+        let forged = EquivocationEvidence::new(first, prevote(crypto::hash(&[3]), &other_keys));
+        assert!(matches!(
+            forged.verify(&validator_keys).unwrap_err(),
+            EvidenceError::KeyMismatch
+        ));
+    }
+
+    #[test]
+    fn evidence_for_precommits() {
+        let keys = KeyPair::random();
+        let validator_keys = validator_keys(&keys);
+        let first = precommit(crypto::hash(&[1]), &keys);
+        let second = precommit(crypto::hash(&[2]), &keys);
+
+        let evidence = EquivocationEvidence::new(first.clone(), second);
+        let equivocation = evidence.verify(&validator_keys).unwrap();
+        assert_eq!(equivocation.kind, EquivocationKind::Precommit);
+
+        // Precommits for the same block differing only in time are not conflicting.
+        let same_block = precommit(crypto::hash(&[1]), &keys);
+        let evidence = EquivocationEvidence::new(first.clone(), same_block);
+        assert!(matches!(
+            evidence.verify(&validator_keys).unwrap_err(),
+            EvidenceError::NoConflict
+        ));
+
+        let prevote = prevote(crypto::hash(&[1]), &keys);
+        let evidence = EquivocationEvidence::new(first, prevote);
+        assert!(matches!(
+            evidence.verify(&validator_keys).unwrap_err(),
+            EvidenceError::DifferentRounds
+        ));
+    }
+
+    #[test]
+    fn evidence_with_invalid_messages() {
+        let keys = KeyPair::random();
+        let validator_keys = validator_keys(&keys);
+        let first = prevote(crypto::hash(&[1]), &keys);
+
+        let mut second = prevote(crypto::hash(&[2]), &keys);
+        second.signature = first.signature;
+        let evidence = EquivocationEvidence::new(first.clone(), second);
+        assert!(matches!(
+            evidence.verify(&validator_keys).unwrap_err(),
+            EvidenceError::InvalidSignature
+        ));
+
+        let garbage = SignedMessage::new(vec![1, 2, 3], keys.public_key(), keys.secret_key());
+        let evidence = EquivocationEvidence::new(first.clone(), garbage);
+        assert!(matches!(
+            evidence.verify(&validator_keys).unwrap_err(),
+            EvidenceError::NotConsensusMessage
+        ));
+
+        let evidence = EquivocationEvidence::new(first, prevote(crypto::hash(&[2]), &keys));
+        assert!(matches!(
+            evidence.verify(&validator_keys[..1]).unwrap_err(),
+            EvidenceError::UnknownValidator
+        ));
+    }
+}
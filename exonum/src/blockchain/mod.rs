@@ -17,12 +17,14 @@
 pub use self::{
     api_sender::{ApiSender, SendError},
     block::{
-        AdditionalHeaders, Block, BlockHeaderKey, BlockProof, CallProof, Epoch, IndexProof,
-        ProofError, ProposerId, SkipFlag,
+        AdditionalHeaders, Block, BlockHeaderKey, BlockProof, CallProof, CommitCertificate,
+        CompactCallProof, Epoch, IndexProof, ProofError, ProposerId, SkipFlag,
     },
     builder::BlockchainBuilder,
-    config::{ConsensusConfig, ConsensusConfigBuilder, ValidatorKeys},
+    config::{ConsensusConfig, ConsensusConfigBuilder, ValidatorBlsKey, ValidatorKeys},
+    evidence::{Equivocation, EquivocationEvidence, EquivocationKind, EvidenceError},
     schema::{CallErrorsIter, CallInBlock, CallRecords, Schema, TxLocation},
+    state_proof::{CheckedStateProof, IndexDataProof, StateProof, StateProofBuilder},
 };
 pub use crate::runtime::TxCheckCache;
 
@@ -30,14 +32,24 @@ pub mod config;
 
 pub(crate) use crate::runtime::ExecutionError;
 
+use anyhow::{ensure, format_err};
 use exonum_crypto::{Hash, KeyPair};
 use exonum_merkledb::{
     access::{Access, RawAccess},
-    Database, Fork, HashTag, KeySetIndex, MapIndex, ObjectHash, Patch, Result as StorageResult,
-    Snapshot, SystemSchema, TemporaryDB,
+    dump::{self, ChunkCursor, DumpInfo},
+    ColumnFamilyStats, Database, DatabaseExt, Fork, HashTag, IndexStats, KeySetIndex, MapIndex,
+    ObjectHash, Patch, Result as StorageResult, Snapshot, SystemSchema, TemporaryDB,
 };
 
-use std::{borrow::Cow, collections::BTreeMap, iter, sync::Arc};
+use std::{
+    borrow::Cow,
+    cmp,
+    collections::BTreeMap,
+    io::{Read, Write},
+    iter,
+    ops::RangeInclusive,
+    sync::{Arc, RwLock},
+};
 
 use crate::{
     blockchain::config::GenesisConfig,
@@ -49,7 +61,9 @@ use crate::{
 mod api_sender;
 mod block;
 mod builder;
+mod evidence;
 mod schema;
+mod state_proof;
 #[cfg(test)]
 pub mod tests;
 
@@ -178,7 +192,7 @@ where
 pub struct Blockchain {
     api_sender: ApiSender,
     db: Arc<dyn Database>,
-    service_keypair: KeyPair,
+    service_keypair: Arc<RwLock<KeyPair>>,
 }
 
 impl Blockchain {
@@ -190,7 +204,7 @@ impl Blockchain {
     ) -> Self {
         Self {
             db: database.into(),
-            service_keypair: service_keypair.into(),
+            service_keypair: Arc::new(RwLock::new(service_keypair.into())),
             api_sender,
         }
     }
@@ -213,6 +227,19 @@ impl Blockchain {
         self.db.snapshot()
     }
 
+    /// Returns statistics for all indexes in the blockchain database.
+    ///
+    /// The method iterates over all entries in the database, thus, it is linear w.r.t.
+    /// the database size.
+    pub fn index_stats(&self) -> Vec<IndexStats> {
+        self.db.index_stats()
+    }
+
+    /// Returns backend-specific statistics for column families of the blockchain database.
+    pub fn column_family_stats(&self) -> Vec<ColumnFamilyStats> {
+        self.db.column_family_stats()
+    }
+
     /// Returns the hash of the latest committed block.
     /// If genesis block was not committed returns `Hash::zero()`.
     pub fn last_hash(&self) -> Hash {
@@ -227,6 +254,145 @@ impl Blockchain {
         Schema::new(&self.snapshot()).last_block()
     }
 
+    /// Prunes historical data for the blocks up to and including `last_height`
+    /// (see [`Schema::prune_block()`]), starting from the block following the latest pruned one.
+    /// At most `max_blocks` blocks are pruned within a single call. Returns the range
+    /// of pruned heights, or `None` if there are no blocks to prune.
+    ///
+    /// Pruning only touches the data of blocks preceding the latest one, which is not
+    /// modified when new blocks are committed. Hence, unlike other changes to the blockchain,
+    /// pruning can be performed concurrently with block processing (e.g., in a background
+    /// thread). Changes made by pruning are not recorded in the state history.
+    ///
+    /// [`Schema::prune_block()`]: struct.Schema.html#method.prune_block
+    pub fn prune_blocks(
+        &self,
+        last_height: Height,
+        max_blocks: u64,
+    ) -> StorageResult<Option<RangeInclusive<Height>>> {
+        let fork = self.db.fork();
+        let mut schema = Schema::new(&fork);
+        let first_height = schema.pruned_height().map_or(0, |height| height.0 + 1);
+        // The latest block cannot be pruned.
+        let latest_height = schema.height().0;
+        if max_blocks == 0 || first_height >= latest_height || first_height > last_height.0 {
+            return Ok(None);
+        }
+        let last_height = cmp::min(last_height.0, latest_height - 1);
+        let last_height = cmp::min(last_height, first_height + max_blocks - 1);
+
+        for height in first_height..=last_height {
+            schema.prune_block(Height(height));
+        }
+        self.db.merge(fork.into_patch())?;
+        Ok(Some(Height(first_height)..=Height(last_height)))
+    }
+
+    /// Creates a read-only snapshot of the storage state right after committing the block
+    /// at the specified `height`. Returns `None` if the state at this height is not available,
+    /// e.g., if the blockchain does not keep the state history.
+    ///
+    /// The state hash of the returned snapshot (as per `SystemSchema::state_hash()`) is equal
+    /// to the `state_hash` recorded in the block, so the snapshot can be used to build proofs
+    /// for past blockchain states.
+    ///
+    /// Note that the snapshot may contain changes that are not related to the block execution
+    /// and were not recorded in the history, such as additions to the transaction pool.
+    /// Such changes do not influence the state hash.
+    pub fn snapshot_at(&self, height: Height) -> Option<Box<dyn Snapshot>> {
+        let version = Schema::new(&self.snapshot()).state_version(height)?;
+        self.db.snapshot_at(version).ok()
+    }
+
+    /// Exports the blockchain state right after committing the block at the specified `height`
+    /// into the `writer`. The exported state can be imported into another database with
+    /// [`import_state`], which allows to bootstrap a node without replaying all blocks.
+    ///
+    /// The state is available for the latest block, and for earlier blocks if the blockchain
+    /// keeps the state history (see [`snapshot_at`]).
+    ///
+    /// [`import_state`]: #method.import_state
+    /// [`snapshot_at`]: #method.snapshot_at
+    pub fn export_state<W: Write>(&self, height: Height, writer: W) -> anyhow::Result<DumpInfo> {
+        let snapshot = self.state_snapshot(height)?;
+        dump::export(snapshot.as_ref(), writer).map_err(From::from)
+    }
+
+    /// Exports a chunk of the blockchain state right after committing the block
+    /// at the specified `height`. The chunk starts from the `cursor` and has approximately
+    /// `max_size` bytes. Returns the chunk contents and the cursor of the next chunk, or `None`
+    /// if the chunk is the last one.
+    ///
+    /// Chunks are used to synchronize the state between nodes; they can be imported
+    /// with [`BlockchainMut::import_state_chunk()`]. The state availability is the same
+    /// as for [`export_state`].
+    ///
+    /// [`BlockchainMut::import_state_chunk()`]: struct.BlockchainMut.html#method.import_state_chunk
+    /// [`export_state`]: #method.export_state
+    pub fn export_state_chunk(
+        &self,
+        height: Height,
+        cursor: &ChunkCursor,
+        max_size: usize,
+    ) -> anyhow::Result<(Vec<u8>, Option<ChunkCursor>)> {
+        let snapshot = self.state_snapshot(height)?;
+        dump::export_chunk(snapshot.as_ref(), cursor, max_size).map_err(From::from)
+    }
+
+    /// Returns the snapshot of the blockchain state right after committing the block
+    /// at the specified `height`. Unlike [`snapshot_at`], the state after the latest block
+    /// is available even if the blockchain does not keep the state history.
+    ///
+    /// [`snapshot_at`]: #method.snapshot_at
+    pub fn state_snapshot(&self, height: Height) -> anyhow::Result<Box<dyn Snapshot>> {
+        let snapshot = self.snapshot();
+        let blocks_count = Schema::new(&snapshot).block_hashes_by_height().len();
+        if blocks_count == height.next().0 {
+            Ok(snapshot)
+        } else {
+            self.snapshot_at(height)
+                .ok_or_else(|| format_err!("State at height {} is not available", height))
+        }
+    }
+
+    /// Imports the blockchain state exported with [`export_state`] into an empty `database`.
+    ///
+    /// The hashes of Merkelized indexes are recomputed from the imported data, and the state
+    /// is checked against the `state_hash` of the latest block in this state. The proof
+    /// for this block is returned together with the dump information; it is the responsibility
+    /// of the caller to check the proof against the trusted set of validator keys
+    /// with [`BlockProof::verify()`], and to decide whether to trust the indexes not covered
+    /// by the state hash (see [`DumpInfo`]).
+    ///
+    /// If the import fails, the database may contain a part of the imported data
+    /// and should be discarded.
+    ///
+    /// [`export_state`]: #method.export_state
+    /// [`BlockProof::verify()`]: struct.BlockProof.html#method.verify
+    /// [`DumpInfo`]: ../../exonum_merkledb/dump/struct.DumpInfo.html
+    pub fn import_state<R: Read>(
+        database: &dyn Database,
+        reader: R,
+    ) -> anyhow::Result<(BlockProof, DumpInfo)> {
+        let info = dump::import(database, reader)?;
+
+        let snapshot = database.snapshot();
+        let schema = Schema::new(&snapshot);
+        let last_height = schema.block_hashes_by_height().len().checked_sub(1);
+        let block_proof = last_height
+            .and_then(|height| schema.block_and_precommits(Height(height)))
+            .ok_or_else(|| format_err!("Imported state does not contain blocks"))?;
+        ensure!(
+            block_proof.block.state_hash == info.state_hash,
+            "State hash of the imported state ({:?}) does not match the state hash \
+             in the block at height {} ({:?})",
+            info.state_hash,
+            block_proof.block.height,
+            block_proof.block.state_hash
+        );
+        Ok((block_proof, info))
+    }
+
     /// Returns the transactions pool size.
     #[doc(hidden)]
     pub fn pool_size(&self) -> u64 {
@@ -247,7 +413,7 @@ impl Blockchain {
         use self::config::GenesisConfigBuilder;
 
         let (mut config, _) = ConsensusConfig::for_tests(1);
-        config.validator_keys[0].service_key = self.service_keypair.public_key();
+        config.validator_keys[0].service_key = self.service_keypair().public_key();
         let genesis_config = GenesisConfigBuilder::with_consensus_config(config).build();
         self.into_mut(genesis_config)
     }
@@ -257,9 +423,15 @@ impl Blockchain {
         &self.api_sender
     }
 
-    /// Returns reference to the service key pair of the current node.
-    pub fn service_keypair(&self) -> &KeyPair {
-        &self.service_keypair
+    /// Returns the service key pair of the current node.
+    ///
+    /// The key pair may be changed by the node during its operation (e.g., if the node
+    /// rotates its keys), thus it should not be cached for a long time.
+    pub fn service_keypair(&self) -> KeyPair {
+        self.service_keypair
+            .read()
+            .expect("Cannot read service keypair")
+            .clone()
     }
 
     /// Performs several shallow checks that transaction is correct.
@@ -446,6 +618,12 @@ impl AsRef<Patch> for BlockPatch {
 pub struct BlockchainMut {
     inner: Blockchain,
     dispatcher: Dispatcher,
+    /// Does this blockchain record the history of states?
+    keeps_state_history: bool,
+    /// Number of latest blocks for which the history of states is retained.
+    state_history_depth: Option<u64>,
+    /// Staging database for the state being imported from other nodes.
+    staged_state: Option<TemporaryDB>,
 }
 
 impl AsRef<Blockchain> for BlockchainMut {
@@ -471,6 +649,17 @@ impl BlockchainMut {
         self.inner.snapshot()
     }
 
+    /// Replaces the service key pair of the node. The new key pair is used by all
+    /// `Blockchain` instances derived from this blockchain, e.g., to sign transactions
+    /// generated by services.
+    pub fn set_service_keypair(&mut self, keypair: impl Into<KeyPair>) {
+        *self
+            .inner
+            .service_keypair
+            .write()
+            .expect("Cannot write service keypair") = keypair.into();
+    }
+
     /// Creates a snapshot of the current storage state that can be later committed into the storage
     /// via the `merge` method.
     pub fn fork(&self) -> Fork {
@@ -478,10 +667,45 @@ impl BlockchainMut {
     }
 
     /// Commits changes from the `patch` to the blockchain storage.
+    ///
+    /// The changes are not recorded in the state history (if the blockchain keeps it);
+    /// only the patches of committed blocks are.
     pub fn merge(&mut self, patch: Patch) -> StorageResult<()> {
         self.inner.db.merge(patch)
     }
 
+    /// Returns the database version the next block patch will be recorded with, or `None`
+    /// if the blockchain does not keep the state history.
+    fn next_state_version(&self) -> Option<u64> {
+        if self.keeps_state_history {
+            Some(
+                self.inner
+                    .db
+                    .latest_version()
+                    .map_or(0, |version| version + 1),
+            )
+        } else {
+            None
+        }
+    }
+
+    /// Commits changes from the block `patch` to the blockchain storage. If the blockchain keeps
+    /// the state history, records the new state in the history and returns the corresponding
+    /// database version. The history for blocks beyond the history depth is pruned.
+    fn merge_block_patch(&mut self, patch: Patch) -> StorageResult<Option<u64>> {
+        if !self.keeps_state_history {
+            return self.inner.db.merge(patch).map(|()| None);
+        }
+
+        let version = self.inner.db.merge_with_history(patch)?;
+        if let Some(depth) = self.state_history_depth {
+            // Retain versions for the latest `depth` blocks.
+            let earliest_version = (version + 1).saturating_sub(depth);
+            self.inner.db.prune_history(earliest_version)?;
+        }
+        Ok(Some(version))
+    }
+
     /// Creates and commits the genesis block with the given genesis configuration.
     ///
     /// # Panics
@@ -545,7 +769,13 @@ impl BlockchainMut {
         // On the other hand, we need to notify runtimes *after* the block has been created.
         // Otherwise, benign operations (e.g., calling `height()` on the core schema) will panic.
         self.dispatcher.notify_runtimes_about_commit(&patch);
-        self.merge(patch).unwrap();
+        let fork = Fork::from(patch);
+        let expected_version = self.next_state_version();
+        if let Some(version) = expected_version {
+            Schema::new(&fork).set_state_version(Height(0), version);
+        }
+        let version = self.merge_block_patch(fork.into_patch()).unwrap();
+        debug_assert_eq!(version, expected_version);
 
         log::info!(
             "GENESIS_BLOCK ====== hash={}",
@@ -620,6 +850,8 @@ impl BlockchainMut {
         C: TransactionCache + ?Sized,
     {
         let height = Schema::new(&fork).next_height();
+        // Configuration at the beginning of the block; the genesis block has none.
+        let config_before = Schema::new(&fork).consensus_config_entry().get();
 
         // Skip execution for genesis block.
         if height > Height(0) {
@@ -644,6 +876,15 @@ impl BlockchainMut {
             }
         }
 
+        // Record the configuration change, so that consensus messages (e.g., equivocation
+        // evidence) can be checked against the configuration active at their epoch.
+        if let Some(config_before) = config_before {
+            let mut schema = Schema::new(&fork);
+            if schema.consensus_config() != config_before {
+                schema.record_config_change(config_before, block_data.epoch);
+            }
+        }
+
         let (patch, block) = self.create_block_header(fork, block_data, height, tx_hashes);
         log::trace!("Executing {:?}", block);
 
@@ -733,27 +974,139 @@ impl BlockchainMut {
     where
         I: IntoIterator<Item = Verified<Precommit>>,
     {
+        let precommits: Vec<_> = precommits.into_iter().collect();
+        // Precommits are authored by validators from the configuration preceding the block,
+        // hence the configuration is taken from the latest committed state.
+        let certificate = Schema::new(&self.snapshot())
+            .consensus_config_entry()
+            .get()
+            .and_then(|config| {
+                CommitCertificate::from_precommits(&precommits, &config.validator_keys)
+            });
+
         let fork: Fork = patch.inner.into();
         let schema = Schema::new(&fork);
         schema.precommits(&patch.block_hash).extend(precommits);
+        if let Some(certificate) = certificate {
+            schema
+                .commit_certificates()
+                .put(&patch.block_hash, certificate);
+        }
 
         match patch.kind {
             BlockKind::Skip => {
                 self.merge(fork.into_patch())?;
             }
             BlockKind::Normal => {
+                let height = schema.height();
                 let patch = self.dispatcher.commit_block_and_notify_runtimes(fork);
-                self.merge(patch)?;
 
-                // TODO: this makes `commit` non-atomic; can this be avoided? (ECR-4319)
-                let new_fork = self.fork();
-                Schema::new(&new_fork).update_transaction_count();
-                self.merge(new_fork.into_patch())?;
+                // Auxiliary block data is updated within the block patch, so that the block
+                // is committed with a single atomic merge.
+                let fork = Fork::from(patch);
+                let mut schema = Schema::new(&fork);
+                schema.update_transaction_count();
+                let expected_version = self.next_state_version();
+                if let Some(version) = expected_version {
+                    schema.set_state_version(height, version);
+                }
+                let version = self.merge_block_patch(fork.into_patch())?;
+                debug_assert_eq!(version, expected_version);
             }
         }
         Ok(())
     }
 
+    /// Starts importing the blockchain state synchronized from other nodes. The state is
+    /// imported with [`import_state_chunk()`] into a temporary staging database, and is copied
+    /// into the blockchain storage by [`finish_state_import()`] only after it is verified.
+    /// Thus, the blockchain storage remains intact if the import fails.
+    ///
+    /// Calling this method discards the previously imported state chunks, if any.
+    ///
+    /// [`import_state_chunk()`]: #method.import_state_chunk
+    /// [`finish_state_import()`]: #method.finish_state_import
+    pub fn start_state_import(&mut self) -> anyhow::Result<()> {
+        self.staged_state = Some(TemporaryDB::new());
+        Ok(())
+    }
+
+    /// Imports a chunk of the blockchain state exported with [`Blockchain::export_state_chunk()`]
+    /// into the staging database.
+    ///
+    /// [`Blockchain::export_state_chunk()`]: struct.Blockchain.html#method.export_state_chunk
+    pub fn import_state_chunk(&mut self, chunk: &[u8]) -> anyhow::Result<()> {
+        let staged_state = self
+            .staged_state
+            .as_ref()
+            .ok_or_else(|| format_err!("State import is not started"))?;
+        dump::import_chunk(staged_state, chunk)?;
+        Ok(())
+    }
+
+    /// Finishes importing the blockchain state. The hashes of Merkelized indexes are recomputed
+    /// from the imported data, and the state is checked against the `state_hash`
+    /// of the provided block, which must be the latest block in the imported state. It is
+    /// the responsibility of the caller to check the block proof against the trusted set
+    /// of validator keys.
+    ///
+    /// The block proof can only be checked against the validator keys known locally.
+    /// Thus, the import fails if the validator keys in the imported state, either
+    /// in the actual consensus configuration or in any of the previous configurations,
+    /// differ from the local ones. That is, the state cannot be imported if the validator
+    /// set has changed since the local consensus configuration.
+    ///
+    /// If the checks pass, the imported state replaces the state in the blockchain storage.
+    /// Local data of the node that has exported the state (such as the transaction pool)
+    /// is removed, and the services are restored from the imported state. The staging database
+    /// is discarded regardless of the outcome.
+    pub fn finish_state_import(&mut self, block_proof: &BlockProof) -> anyhow::Result<()> {
+        let staged_state = self
+            .staged_state
+            .take()
+            .ok_or_else(|| format_err!("State import is not started"))?;
+        let snapshot = staged_state.snapshot();
+        let state_hash = dump::verify_aggregation(snapshot.as_ref())?;
+        ensure!(
+            state_hash == block_proof.block.state_hash,
+            "State hash of the imported state ({:?}) does not match the state hash \
+             in the block at height {} ({:?})",
+            state_hash,
+            block_proof.block.height,
+            block_proof.block.state_hash
+        );
+        let schema = Schema::new(&snapshot);
+        ensure!(
+            schema.block_hashes_by_height().len() == block_proof.block.height.next().0
+                && schema.last_block().object_hash() == block_proof.block.object_hash(),
+            "Block at height {} is not the latest block in the imported state",
+            block_proof.block.height
+        );
+        let local_keys = Schema::new(&self.snapshot())
+            .consensus_config()
+            .validator_keys;
+        let keys_are_unchanged = iter::once(schema.consensus_config())
+            .chain(schema.consensus_config_history().values())
+            .all(|config| config.validator_keys == local_keys);
+        ensure!(
+            keys_are_unchanged,
+            "Validator keys in the imported state differ from the local validator keys"
+        );
+
+        let fork = staged_state.fork();
+        let mut schema = Schema::new(&fork);
+        let pool_transactions: Vec<_> = schema.transactions_pool().iter().collect();
+        for tx_hash in pool_transactions {
+            schema.reject_transaction(tx_hash);
+        }
+        schema.clear_state_versions();
+        staged_state.merge(fork.into_patch())?;
+
+        dump::replace_state(self.inner.db.as_ref(), staged_state.snapshot().as_ref())?;
+        self.dispatcher.restore_state(&self.snapshot());
+        Ok(())
+    }
+
     /// Adds a transaction into pool of uncommitted transactions.
     ///
     /// Unlike the corresponding method in the core schema, this method checks if the
@@ -780,7 +1133,11 @@ impl BlockchainMut {
         let fork = db.fork();
         let mut schema = Schema::new(&fork);
         for transaction in transactions {
-            if !schema.transactions().contains(&transaction.object_hash()) {
+            let tx_hash = transaction.object_hash();
+            // Transactions from pruned blocks are only recorded in `transactions_locations`.
+            let is_known = schema.transactions().contains(&tx_hash)
+                || schema.transactions_locations().contains(&tx_hash);
+            if !is_known {
                 schema.add_transaction_into_pool(transaction);
             }
         }
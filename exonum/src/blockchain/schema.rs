@@ -24,7 +24,9 @@ use exonum_proto::ProtobufConvert;
 
 use std::fmt;
 
-use super::{Block, BlockProof, CallProof, ConsensusConfig};
+use super::{
+    Block, BlockProof, CallProof, CommitCertificate, ConsensusConfig, EquivocationEvidence,
+};
 use crate::{
     crypto::{Hash, PublicKey},
     helpers::{Height, ValidatorId},
@@ -57,7 +59,13 @@ define_names!(
     BLOCK_TRANSACTIONS => "block_transactions";
     BLOCK_SKIP => "block_skip";
     PRECOMMITS => "precommits";
+    COMMIT_CERTIFICATES => "commit_certificates";
+    EQUIVOCATION_EVIDENCE => "equivocation_evidence";
+    REPORTED_EQUIVOCATIONS => "reported_equivocations";
     CONSENSUS_CONFIG => "consensus_config";
+    CONSENSUS_CONFIG_HISTORY => "consensus_config_history";
+    STATE_VERSIONS => "state_versions";
+    PRUNED_HEIGHT => "pruned_height";
 );
 
 /// Transaction location in a block. Defines the block where the transaction was
@@ -231,8 +239,9 @@ impl<T: Access> Schema<T> {
     /// [block skip]: enum.BlockContents.html#variant.Skip
     pub fn block_skip_and_precommits(&self) -> Option<BlockProof> {
         let block = self.block_skip_entry().get()?;
-        let precommits = self.precommits(&block.object_hash()).iter().collect();
-        Some(BlockProof::new(block, precommits))
+        let block_hash = block.object_hash();
+        let precommits = self.precommits(&block_hash).iter().collect();
+        Some(self.attach_certificate(BlockProof::new(block, precommits), &block_hash))
     }
 
     /// Returns a table that keeps a list of precommits for the block with the given hash.
@@ -240,12 +249,81 @@ impl<T: Access> Schema<T> {
         self.access.get_list((PRECOMMITS, hash))
     }
 
+    /// Returns a table that maps block hashes to commit certificates of the corresponding
+    /// blocks. Certificates are only created if validators have BLS keys in the consensus
+    /// configuration. Unlike precommits, certificates are retained when blocks are pruned.
+    pub fn commit_certificates(&self) -> MapIndex<T::Base, Hash, CommitCertificate> {
+        self.access.get_map(COMMIT_CERTIFICATES)
+    }
+
+    /// Returns a table of equivocation evidence detected by this node, keyed by the hash
+    /// of the evidence.
+    ///
+    /// The table is filled by the node as it processes consensus messages. It is not a part
+    /// of the blockchain state and may differ among nodes; hence, it must not be accessed
+    /// during transaction execution. Services may use the table, e.g., in the `after_commit`
+    /// hook to submit the evidence to the blockchain in transactions.
+    pub fn equivocation_evidence(&self) -> MapIndex<T::Base, Hash, EquivocationEvidence> {
+        self.access.get_map(EQUIVOCATION_EVIDENCE)
+    }
+
+    /// Returns a table of equivocation evidence recorded in the blockchain by services,
+    /// keyed by the hash of the evidence.
+    ///
+    /// See [`ExecutionContext::report_equivocation`] for details.
+    ///
+    /// The table is not aggregated into the state hash.
+    ///
+    /// [`ExecutionContext::report_equivocation`]: ../runtime/struct.ExecutionContext.html#method.report_equivocation
+    pub fn reported_equivocations(&self) -> MapIndex<T::Base, Hash, EquivocationEvidence> {
+        self.access.get_map(REPORTED_EQUIVOCATIONS)
+    }
+
+    fn attach_certificate(&self, proof: BlockProof, block_hash: &Hash) -> BlockProof {
+        if let Some(certificate) = self.commit_certificates().get(block_hash) {
+            proof.with_commit_certificate(certificate)
+        } else {
+            proof
+        }
+    }
+
     /// Returns an actual consensus configuration entry.
     #[doc(hidden)]
     pub fn consensus_config_entry(&self) -> ProofEntry<T::Base, ConsensusConfig> {
         self.access.get_proof_entry(CONSENSUS_CONFIG)
     }
 
+    /// Returns a table that maps block heights to the database versions created when
+    /// committing the corresponding blocks. The table is only filled if the blockchain
+    /// keeps the state history.
+    fn state_versions(&self) -> MapIndex<T::Base, u64, u64> {
+        self.access.get_map(STATE_VERSIONS)
+    }
+
+    /// Returns the database version with the state right after committing the block
+    /// at the given height, or `None` if the version was not recorded.
+    ///
+    /// Versions are recorded only if the state history is enabled
+    /// via [`BlockchainBuilder::with_state_history()`].
+    ///
+    /// [`BlockchainBuilder::with_state_history()`]: struct.BlockchainBuilder.html#method.with_state_history
+    pub fn state_version(&self, height: Height) -> Option<u64> {
+        self.state_versions().get(&height.0)
+    }
+
+    /// Returns an entry storing the height of the latest pruned block.
+    fn pruned_height_entry(&self) -> Entry<T::Base, u64> {
+        self.access.get_entry(PRUNED_HEIGHT)
+    }
+
+    /// Returns the height of the latest block pruned with [`prune_block()`], or `None`
+    /// if no blocks were pruned. All blocks up to and including this height are pruned.
+    ///
+    /// [`prune_block()`]: #method.prune_block
+    pub fn pruned_height(&self) -> Option<Height> {
+        self.pruned_height_entry().get().map(Height)
+    }
+
     /// Returns the block hash for the given height.
     pub fn block_hash_by_height(&self, height: Height) -> Option<Hash> {
         self.block_hashes_by_height().get(height.into())
@@ -256,7 +334,7 @@ impl<T: Access> Schema<T> {
         let block_hash = self.block_hash_by_height(height)?;
         let block = self.blocks().get(&block_hash).unwrap();
         let precommits = self.precommits(&block_hash).iter().collect();
-        Some(BlockProof::new(block, precommits))
+        Some(self.attach_certificate(BlockProof::new(block, precommits), &block_hash))
     }
 
     /// Returns the latest committed block.
@@ -306,6 +384,32 @@ impl<T: Access> Schema<T> {
             .expect("Consensus configuration is absent")
     }
 
+    /// Returns a table of consensus configurations keyed by the epoch starting from which
+    /// the configuration is active. The table is empty until the configuration is changed
+    /// for the first time; then, the genesis configuration is recorded for epoch 0.
+    ///
+    /// Keys are stored as `u64::MAX - epoch`, so that the table is iterated from
+    /// the latest configuration to the earliest one. The table is not aggregated
+    /// into the state hash, since it is fully determined by the configuration changes.
+    pub(crate) fn consensus_config_history(&self) -> MapIndex<T::Base, u64, ConsensusConfig> {
+        self.access.get_map(CONSENSUS_CONFIG_HISTORY)
+    }
+
+    /// Returns the consensus configuration which was active at the specified consensus epoch.
+    /// For future epochs, returns the actual configuration.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the genesis block was not created.
+    pub fn consensus_config_at(&self, epoch: Height) -> ConsensusConfig {
+        // The first entry starting from the inverted epoch is the latest configuration
+        // which became active no later than `epoch`.
+        self.consensus_config_history()
+            .values_from(&(u64::MAX - epoch.0))
+            .next()
+            .unwrap_or_else(|| self.consensus_config())
+    }
+
     /// Attempts to find a `ValidatorId` by the provided service public key.
     pub fn validator_id(&self, service_public_key: PublicKey) -> Option<ValidatorId> {
         self.consensus_config()
@@ -370,6 +474,17 @@ where
         self.block_transactions(height).push(*hash);
     }
 
+    /// Records the change of the consensus configuration in the block with the specified epoch.
+    /// The new configuration becomes active starting from the next epoch.
+    pub(crate) fn record_config_change(&mut self, previous_config: ConsensusConfig, epoch: Height) {
+        let config = self.consensus_config();
+        let mut history = self.consensus_config_history();
+        if history.keys().next().is_none() {
+            history.put(&u64::MAX, previous_config);
+        }
+        history.put(&(u64::MAX - epoch.next().0), config);
+    }
+
     /// Updates transaction count of the blockchain.
     pub(crate) fn update_transaction_count(&mut self) {
         let block_transactions = self.block_transactions(self.height());
@@ -400,6 +515,59 @@ where
         pool_len_index.set(new_pool_len);
     }
 
+    /// Removes transactions, call records and precommits of the block at the specified height.
+    /// Block headers (i.e., `blocks`, `block_hashes_by_height` and `block_skip`) are retained.
+    ///
+    /// Transaction locations are retained as well, so that the committed transactions
+    /// can still be recognized (e.g., in order to prevent replaying them). After pruning,
+    /// `transaction_result()` returns `None` for transactions in the pruned block.
+    ///
+    /// Pruning does not influence the blockchain state hash.
+    ///
+    /// # Panics
+    ///
+    /// - If the blocks are not pruned sequentially starting from the genesis block,
+    ///   i.e., if `height` is not the height following the [`pruned_height()`].
+    /// - If `height` is not less than the current blockchain height. The latest block
+    ///   cannot be pruned since it is required for the node operation.
+    ///
+    /// [`pruned_height()`]: #method.pruned_height
+    pub fn prune_block(&mut self, height: Height) {
+        let expected_height = self.pruned_height().map_or(Height(0), Height::next);
+        assert_eq!(
+            height, expected_height,
+            "Blocks must be pruned sequentially starting from the genesis block"
+        );
+        assert!(
+            height < self.height(),
+            "Attempt to prune the latest block at height {}",
+            height
+        );
+
+        let mut transactions = self.transactions();
+        let mut block_transactions = self.block_transactions(height);
+        for tx_hash in &block_transactions {
+            transactions.remove(&tx_hash);
+        }
+        block_transactions.clear();
+        self.call_errors_map(height).clear();
+        self.call_errors_aux(height).clear();
+
+        let block_hash = self.block_hash_by_height(height).unwrap();
+        self.precommits(&block_hash).clear();
+        self.pruned_height_entry().set(height.0);
+    }
+
+    /// Records the database version for the block at the given height.
+    pub(super) fn set_state_version(&mut self, height: Height, version: u64) {
+        self.state_versions().put(&height.0, version);
+    }
+
+    /// Removes database versions recorded for all blocks.
+    pub(super) fn clear_state_versions(&mut self) {
+        self.state_versions().clear();
+    }
+
     /// Saves an error to the blockchain.
     pub(crate) fn save_error(
         &mut self,
@@ -416,6 +584,7 @@ where
         if let Some(block_skip) = self.block_skip_entry().take() {
             let block_hash = block_skip.object_hash();
             self.precommits(&block_hash).clear();
+            self.commit_certificates().remove(&block_hash);
         }
     }
 
@@ -0,0 +1,615 @@
+// Copyright 2020 The Exonum Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//   http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Aggregated proofs for data in several Merkelized indexes.
+
+use anyhow::{bail, ensure};
+use exonum_crypto::Hash;
+use exonum_merkledb::{
+    access::CopyAccessExt,
+    proof_map::{Hashed, Raw, ToProofPath},
+    BinaryKey, BinaryValue, HashTag, ListProof, MapProof, ObjectHash, Snapshot, SystemSchema,
+};
+use exonum_proto::ProtobufConvert;
+use protobuf::well_known_types::Empty;
+
+use std::{borrow::Cow, collections::BTreeMap, ops::RangeBounds};
+
+use crate::{
+    blockchain::{BlockProof, ProofError},
+    proto::schema::proofs,
+};
+
+/// Proof of authenticity for data in several Merkelized indexes, such as entries
+/// of `ProofMapIndex`es (including proofs of absence), elements of `ProofListIndex`es
+/// and values of `ProofEntry`s.
+///
+/// The proof consists of two parts:
+///
+/// - `aggregator_proof`: proof from the state aggregator for the hashes of all indexes
+///   covered by the proof. Indexes missing from the aggregator are treated as empty.
+/// - `index_proofs`: proofs for data within each index, keyed by the full index name.
+///
+/// A proof is created with [`StateProofBuilder`] and verified against a block
+/// with [`check_against_block()`]. Data for each index is then extracted from the returned
+/// [`CheckedStateProof`]; the caller specifies the index type at this stage.
+///
+/// [`StateProofBuilder`]: struct.StateProofBuilder.html
+/// [`check_against_block()`]: #method.check_against_block
+/// [`CheckedStateProof`]: struct.CheckedStateProof.html
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct StateProof {
+    /// Proof from the state aggregator for all indexes covered by the proof.
+    /// The root hash of the proof must be equal to the `state_hash` of the block.
+    pub aggregator_proof: MapProof<String, Hash>,
+    /// Proofs for data within individual indexes.
+    pub index_proofs: BTreeMap<String, IndexDataProof>,
+}
+
+/// Proof for data within a single Merkelized index.
+///
+/// Keys and values are stored in the serialized form, so that proofs for indexes
+/// of different types can be stored together. They are decoded when the data
+/// is extracted from a [`CheckedStateProof`].
+///
+/// [`CheckedStateProof`]: struct.CheckedStateProof.html
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum IndexDataProof {
+    /// Proof for entries of a `ProofMapIndex`.
+    Map(MapProof<Vec<u8>, Vec<u8>>),
+    /// Proof for elements of a `ProofListIndex`.
+    List(ListProof<Vec<u8>>),
+    /// Value of a `ProofEntry`.
+    Entry(Option<Vec<u8>>),
+}
+
+/// Converts a typed map proof into the serialized form. The proof is not valid
+/// in this form; it becomes valid once converted back with the original types.
+fn erase_map_proof<K, V, KeyMode>(proof: &MapProof<K, V, KeyMode>) -> MapProof<Vec<u8>, Vec<u8>>
+where
+    K: BinaryValue,
+    V: BinaryValue,
+{
+    MapProof::from_pb(proof.to_pb()).expect("Cannot convert map proof")
+}
+
+fn erase_list_proof<V: BinaryValue>(proof: &ListProof<V>) -> ListProof<Vec<u8>> {
+    ListProof::from_pb(proof.to_pb()).expect("Cannot convert list proof")
+}
+
+impl StateProof {
+    /// Verifies the aggregator part of this proof against the `state_hash` of the block.
+    ///
+    /// This method does not check precommits in `block_proof`; the block proof should
+    /// be verified separately with [`BlockProof::verify()`].
+    ///
+    /// [`BlockProof::verify()`]: struct.BlockProof.html#method.verify
+    pub fn check_against_block(
+        &self,
+        block_proof: &BlockProof,
+    ) -> Result<CheckedStateProof<'_>, ProofError> {
+        let checked = self
+            .aggregator_proof
+            .check_against_hash(block_proof.block.state_hash)
+            .map_err(ProofError::IncorrectEntryProof)?;
+        let index_hashes: BTreeMap<_, _> = checked
+            .all_entries()
+            .map(|(name, hash)| (name.as_str(), hash.copied()))
+            .collect();
+
+        // Data for each index should be authenticated by the aggregator.
+        if self
+            .index_proofs
+            .keys()
+            .any(|name| !index_hashes.contains_key(name.as_str()))
+        {
+            return Err(ProofError::NoIndexData);
+        }
+
+        Ok(CheckedStateProof {
+            index_proofs: &self.index_proofs,
+            index_hashes,
+        })
+    }
+}
+
+/// Version of `StateProof` obtained after verification against a block.
+///
+/// Data for each index is verified and decoded when it is accessed. Each data access
+/// method fails if the index is not covered by the proof, or if it is covered
+/// by the proof for an index of another type.
+#[derive(Debug)]
+pub struct CheckedStateProof<'a> {
+    index_proofs: &'a BTreeMap<String, IndexDataProof>,
+    /// Index hashes from the state aggregator; `None` for indexes missing from the aggregator.
+    index_hashes: BTreeMap<&'a str, Option<Hash>>,
+}
+
+impl<'a> CheckedStateProof<'a> {
+    /// Returns names of all indexes covered by the proof.
+    pub fn index_names(&self) -> impl Iterator<Item = &'a str> + '_ {
+        self.index_proofs.keys().map(String::as_str)
+    }
+
+    fn index_proof(
+        &self,
+        index_name: &str,
+        empty_hash: Hash,
+    ) -> Result<(&'a IndexDataProof, Hash), ProofError> {
+        let proof = self
+            .index_proofs
+            .get(index_name)
+            .ok_or(ProofError::NoIndexData)?;
+        let index_hash = self.index_hashes[index_name].unwrap_or(empty_hash);
+        Ok((proof, index_hash))
+    }
+
+    /// Returns entries of a `ProofMapIndex` with hashed keys, which are authenticated
+    /// by the proof. Keys missing from the map are returned with `None` values.
+    pub fn map_entries<K, V>(&self, index_name: &str) -> Result<Vec<(K, Option<V>)>, ProofError>
+    where
+        K: BinaryValue + ObjectHash + Clone,
+        V: BinaryValue + Clone,
+    {
+        self.generic_map_entries::<K, V, Hashed>(index_name)
+    }
+
+    /// Returns entries of a `ProofMapIndex` with raw keys, which are authenticated
+    /// by the proof. Keys missing from the map are returned with `None` values.
+    pub fn raw_map_entries<K, V>(&self, index_name: &str) -> Result<Vec<(K, Option<V>)>, ProofError>
+    where
+        K: BinaryValue + Clone,
+        V: BinaryValue + Clone,
+        Raw: ToProofPath<K>,
+    {
+        self.generic_map_entries::<K, V, Raw>(index_name)
+    }
+
+    fn generic_map_entries<K, V, KeyMode>(
+        &self,
+        index_name: &str,
+    ) -> Result<Vec<(K, Option<V>)>, ProofError>
+    where
+        K: BinaryValue + Clone,
+        V: BinaryValue + Clone,
+        KeyMode: ToProofPath<K>,
+    {
+        let (proof, index_hash) = self.index_proof(index_name, HashTag::empty_map_hash())?;
+        let proof = match proof {
+            IndexDataProof::Map(proof) => proof,
+            _ => return Err(ProofError::IndexTypeMismatch),
+        };
+        let proof = MapProof::<K, V, KeyMode>::from_pb(proof.to_pb())
+            .map_err(|_| ProofError::MalformedIndexData)?;
+        let checked = proof
+            .check_against_hash(index_hash)
+            .map_err(ProofError::IncorrectMapProof)?;
+        Ok(checked
+            .all_entries()
+            .map(|(key, value)| (key.clone(), value.cloned()))
+            .collect())
+    }
+
+    /// Returns elements of a `ProofListIndex` together with their indexes, which are
+    /// authenticated by the proof. Requested elements not present in the returned
+    /// vector are missing from the list.
+    pub fn list_entries<V>(&self, index_name: &str) -> Result<Vec<(u64, V)>, ProofError>
+    where
+        V: BinaryValue + Clone,
+    {
+        let (proof, index_hash) = self.index_proof(index_name, HashTag::empty_list_hash())?;
+        let proof = match proof {
+            IndexDataProof::List(proof) => proof,
+            _ => return Err(ProofError::IndexTypeMismatch),
+        };
+        let proof =
+            ListProof::<V>::from_pb(proof.to_pb()).map_err(|_| ProofError::MalformedIndexData)?;
+        let checked = proof
+            .check_against_hash(index_hash)
+            .map_err(ProofError::IncorrectListProof)?;
+        Ok(checked.entries().to_vec())
+    }
+
+    /// Returns the value of a `ProofEntry` authenticated by the proof.
+    pub fn entry_value<V>(&self, index_name: &str) -> Result<Option<V>, ProofError>
+    where
+        V: BinaryValue + ObjectHash,
+    {
+        let (proof, index_hash) = self.index_proof(index_name, Hash::zero())?;
+        let value = match proof {
+            IndexDataProof::Entry(value) => value.as_ref(),
+            _ => return Err(ProofError::IndexTypeMismatch),
+        };
+        let value = value
+            .map(|bytes| V::from_bytes(Cow::Borrowed(bytes)))
+            .transpose()
+            .map_err(|_| ProofError::MalformedIndexData)?;
+
+        // Hash of an empty `ProofEntry` is `Hash::zero()`.
+        let value_hash = value
+            .as_ref()
+            .map_or_else(Hash::zero, ObjectHash::object_hash);
+        if value_hash == index_hash {
+            Ok(value)
+        } else {
+            Err(ProofError::IncorrectEntryValue)
+        }
+    }
+}
+
+/// Builder for `StateProof`s.
+///
+/// Indexes are specified by their full names, e.g., `cryptocurrency.wallets`.
+/// Indexes not present in the snapshot are treated as empty.
+///
+/// # Panics
+///
+/// Builder methods panic if an index exists, but has a type different
+/// from the requested one.
+///
+/// # Examples
+///
+/// ```
+/// # use exonum::blockchain::{StateProof, StateProofBuilder};
+/// # use exonum::merkledb::{access::CopyAccessExt, Database, TemporaryDB};
+/// # use exonum::crypto::PublicKey;
+/// let db = TemporaryDB::new();
+/// let fork = db.fork();
+/// fork.get_raw_proof_map("token.wallets").put(&PublicKey::new([1; 32]), 100_u64);
+/// fork.get_proof_list("token.history").push("transfer".to_owned());
+/// db.merge(fork.into_patch()).unwrap();
+///
+/// let snapshot = db.snapshot();
+/// let proof: StateProof = StateProofBuilder::new(&snapshot)
+///     .raw_map::<PublicKey, u64>("token.wallets", vec![PublicKey::new([1; 32])])
+///     .list::<String>("token.history", 0..1)
+///     .entry::<u64>("token.total_supply")
+///     .build();
+/// assert_eq!(proof.index_proofs.len(), 3);
+/// ```
+#[derive(Debug)]
+pub struct StateProofBuilder<'a> {
+    snapshot: &'a dyn Snapshot,
+    index_proofs: BTreeMap<String, IndexDataProof>,
+}
+
+impl<'a> StateProofBuilder<'a> {
+    /// Creates a builder of a proof for the specified snapshot.
+    pub fn new(snapshot: &'a dyn Snapshot) -> Self {
+        Self {
+            snapshot,
+            index_proofs: BTreeMap::new(),
+        }
+    }
+
+    /// Adds a proof for the specified keys of a `ProofMapIndex` with hashed keys.
+    pub fn map<K, V>(self, index_name: &str, keys: impl IntoIterator<Item = K::Owned>) -> Self
+    where
+        K: BinaryKey + ObjectHash + ?Sized,
+        K::Owned: BinaryValue,
+        V: BinaryValue,
+    {
+        self.generic_map::<K, V, Hashed>(index_name, keys)
+    }
+
+    /// Adds a proof for the specified keys of a `ProofMapIndex` with raw keys.
+    pub fn raw_map<K, V>(self, index_name: &str, keys: impl IntoIterator<Item = K::Owned>) -> Self
+    where
+        K: BinaryKey + ?Sized,
+        K::Owned: BinaryValue,
+        V: BinaryValue,
+        Raw: ToProofPath<K>,
+    {
+        self.generic_map::<K, V, Raw>(index_name, keys)
+    }
+
+    fn generic_map<K, V, KeyMode>(
+        mut self,
+        index_name: &str,
+        keys: impl IntoIterator<Item = K::Owned>,
+    ) -> Self
+    where
+        K: BinaryKey + ?Sized,
+        K::Owned: BinaryValue,
+        V: BinaryValue,
+        KeyMode: ToProofPath<K>,
+    {
+        let index = self
+            .snapshot
+            .get_generic_proof_map::<_, K, V, KeyMode>(index_name);
+        let proof = erase_map_proof(&index.get_multiproof(keys));
+        self.index_proofs
+            .insert(index_name.to_owned(), IndexDataProof::Map(proof));
+        self
+    }
+
+    /// Adds a proof for the specified range of elements of a `ProofListIndex`.
+    pub fn list<V>(mut self, index_name: &str, range: impl RangeBounds<u64>) -> Self
+    where
+        V: BinaryValue,
+    {
+        let index = self.snapshot.get_proof_list::<_, V>(index_name);
+        let proof = erase_list_proof(&index.get_range_proof(range));
+        self.index_proofs
+            .insert(index_name.to_owned(), IndexDataProof::List(proof));
+        self
+    }
+
+    /// Adds the value of a `ProofEntry`.
+    pub fn entry<V>(mut self, index_name: &str) -> Self
+    where
+        V: BinaryValue + ObjectHash,
+    {
+        let value = self
+            .snapshot
+            .get_proof_entry::<_, V>(index_name)
+            .get()
+            .map(BinaryValue::into_bytes);
+        self.index_proofs
+            .insert(index_name.to_owned(), IndexDataProof::Entry(value));
+        self
+    }
+
+    /// Builds the proof.
+    pub fn build(self) -> StateProof {
+        let aggregator = SystemSchema::new(self.snapshot).state_aggregator();
+        let aggregator_proof = aggregator.get_multiproof(self.index_proofs.keys().cloned());
+        StateProof {
+            aggregator_proof,
+            index_proofs: self.index_proofs,
+        }
+    }
+}
+
+impl ProtobufConvert for StateProof {
+    type ProtoStruct = proofs::StateProof;
+
+    fn to_pb(&self) -> Self::ProtoStruct {
+        let mut inner = Self::ProtoStruct::default();
+        inner.set_aggregator_proof(self.aggregator_proof.to_pb());
+        let index_proofs: Vec<_> = self
+            .index_proofs
+            .iter()
+            .map(|(name, proof)| index_data_to_pb(name, proof))
+            .collect();
+        inner.set_index_proofs(index_proofs.into());
+        inner
+    }
+
+    fn from_pb(mut pb: Self::ProtoStruct) -> anyhow::Result<Self> {
+        let aggregator_proof = MapProof::from_pb(pb.take_aggregator_proof())?;
+        let mut index_proofs = BTreeMap::new();
+        for index_pb in pb.take_index_proofs() {
+            let (name, proof) = index_data_from_pb(index_pb)?;
+            ensure!(
+                !index_proofs.contains_key(&name),
+                "Duplicate proof for index `{}`",
+                name
+            );
+            index_proofs.insert(name, proof);
+        }
+
+        Ok(Self {
+            aggregator_proof,
+            index_proofs,
+        })
+    }
+}
+
+fn index_data_to_pb(name: &str, proof: &IndexDataProof) -> proofs::IndexDataProof {
+    let mut inner = proofs::IndexDataProof::new();
+    inner.set_name(name.to_owned());
+    match proof {
+        IndexDataProof::Map(proof) => inner.set_map(proof.to_pb()),
+        IndexDataProof::List(proof) => inner.set_list(proof.to_pb()),
+        IndexDataProof::Entry(value) => {
+            let mut entry = proofs::EntryValue::new();
+            match value {
+                Some(value) => entry.set_value(value.clone()),
+                None => entry.set_no_value(Empty::new()),
+            }
+            inner.set_entry(entry);
+        }
+    }
+    inner
+}
+
+fn index_data_from_pb(mut pb: proofs::IndexDataProof) -> anyhow::Result<(String, IndexDataProof)> {
+    let proof = if pb.has_map() {
+        IndexDataProof::Map(MapProof::from_pb(pb.take_map())?)
+    } else if pb.has_list() {
+        IndexDataProof::List(ListProof::from_pb(pb.take_list())?)
+    } else if pb.has_entry() {
+        let mut entry = pb.take_entry();
+        if entry.has_value() {
+            IndexDataProof::Entry(Some(entry.take_value()))
+        } else if entry.has_no_value() {
+            IndexDataProof::Entry(None)
+        } else {
+            bail!("Entry value is not specified");
+        }
+    } else {
+        bail!("Index data is not specified");
+    };
+    Ok((pb.take_name(), proof))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use assert_matches::assert_matches;
+    use exonum_crypto::PublicKey;
+    use exonum_merkledb::{Database, TemporaryDB};
+
+    use crate::{
+        blockchain::{AdditionalHeaders, Block},
+        helpers::Height,
+    };
+
+    fn create_snapshot() -> Box<dyn Snapshot> {
+        let db = TemporaryDB::new();
+        let fork = db.fork();
+        {
+            let mut wallets = fork.get_raw_proof_map("token.wallets");
+            wallets.put(&PublicKey::new([1; 32]), 100_u64);
+            wallets.put(&PublicKey::new([2; 32]), 200_u64);
+            let mut names = fork.get_proof_map("token.names");
+            names.put(&"Alice".to_owned(), PublicKey::new([1; 32]));
+            let mut history = fork.get_proof_list("token.history");
+            history.extend(vec![1_u32, 2, 3]);
+            fork.get_proof_entry("token.total_supply").set(300_u64);
+        }
+        db.merge(fork.into_patch()).unwrap();
+        db.snapshot()
+    }
+
+    fn block_proof(snapshot: &dyn Snapshot) -> BlockProof {
+        let block = Block {
+            height: Height(1),
+            tx_count: 0,
+            prev_hash: Hash::zero(),
+            tx_hash: Hash::zero(),
+            state_hash: SystemSchema::new(snapshot).state_hash(),
+            error_hash: Hash::zero(),
+            additional_headers: AdditionalHeaders::new(),
+        };
+        BlockProof::new(block, vec![])
+    }
+
+    fn create_proof(snapshot: &dyn Snapshot) -> StateProof {
+        StateProofBuilder::new(snapshot)
+            .raw_map::<PublicKey, u64>(
+                "token.wallets",
+                vec![PublicKey::new([1; 32]), PublicKey::new([3; 32])],
+            )
+            .map::<String, PublicKey>("token.names", vec!["Alice".to_owned(), "Bob".to_owned()])
+            .list::<u32>("token.history", 1..5)
+            .entry::<u64>("token.total_supply")
+            .map::<String, u64>("token.missing", vec!["key".to_owned()])
+            .entry::<u64>("token.missing_entry")
+            .build()
+    }
+
+    fn check_proof(proof: &StateProof, block_proof: &BlockProof) {
+        let checked = proof.check_against_block(block_proof).unwrap();
+        assert_eq!(checked.index_names().count(), 6);
+
+        let wallets = checked
+            .raw_map_entries::<PublicKey, u64>("token.wallets")
+            .unwrap();
+        assert_eq!(
+            wallets,
+            vec![
+                (PublicKey::new([1; 32]), Some(100)),
+                (PublicKey::new([3; 32]), None)
+            ]
+        );
+        let mut names = checked
+            .map_entries::<String, PublicKey>("token.names")
+            .unwrap();
+        names.sort();
+        assert_eq!(
+            names,
+            vec![
+                ("Alice".to_owned(), Some(PublicKey::new([1; 32]))),
+                ("Bob".to_owned(), None),
+            ]
+        );
+        let history = checked.list_entries::<u32>("token.history").unwrap();
+        assert_eq!(history, vec![(1, 2), (2, 3)]);
+        let total_supply = checked.entry_value::<u64>("token.total_supply").unwrap();
+        assert_eq!(total_supply, Some(300));
+
+        // Missing indexes are treated as empty.
+        let missing = checked.map_entries::<String, u64>("token.missing").unwrap();
+        assert_eq!(missing, vec![("key".to_owned(), None)]);
+        let missing = checked.entry_value::<u64>("token.missing_entry").unwrap();
+        assert_eq!(missing, None);
+    }
+
+    #[test]
+    fn state_proof_for_several_indexes() {
+        let snapshot = create_snapshot();
+        let proof = create_proof(&snapshot);
+        check_proof(&proof, &block_proof(&snapshot));
+    }
+
+    #[test]
+    fn state_proof_serialization() {
+        let snapshot = create_snapshot();
+        let proof = create_proof(&snapshot);
+        let block_proof = block_proof(&snapshot);
+
+        let restored = StateProof::from_pb(proof.to_pb()).unwrap();
+        assert_eq!(restored, proof);
+        check_proof(&restored, &block_proof);
+
+        let json = serde_json::to_string(&proof).unwrap();
+        let restored: StateProof = serde_json::from_str(&json).unwrap();
+        check_proof(&restored, &block_proof);
+    }
+
+    #[test]
+    fn incorrect_state_proofs() {
+        let snapshot = create_snapshot();
+        let proof = create_proof(&snapshot);
+        let mut block_proof = block_proof(&snapshot);
+
+        let checked = proof.check_against_block(&block_proof).unwrap();
+        let err = checked.entry_value::<u64>("token.other").unwrap_err();
+        assert_matches!(err, ProofError::NoIndexData);
+        let err = checked.list_entries::<u64>("token.wallets").unwrap_err();
+        assert_matches!(err, ProofError::IndexTypeMismatch);
+        let err = checked
+            .map_entries::<PublicKey, u64>("token.wallets")
+            .unwrap_err();
+        assert_matches!(err, ProofError::IncorrectMapProof(_));
+        let err = checked
+            .entry_value::<PublicKey>("token.total_supply")
+            .unwrap_err();
+        assert_matches!(err, ProofError::MalformedIndexData);
+
+        // Data for an index not covered by the aggregator proof.
+        let mut other_proof = create_proof(&snapshot);
+        let entry_proof = other_proof
+            .index_proofs
+            .remove("token.total_supply")
+            .unwrap();
+        other_proof
+            .index_proofs
+            .insert("token.other".to_owned(), entry_proof);
+        let err = other_proof.check_against_block(&block_proof).unwrap_err();
+        assert_matches!(err, ProofError::NoIndexData);
+
+        // Tampered entry value.
+        let mut other_proof = create_proof(&snapshot);
+        other_proof.index_proofs.insert(
+            "token.total_supply".to_owned(),
+            IndexDataProof::Entry(Some(301_u64.into_bytes())),
+        );
+        let checked = other_proof.check_against_block(&block_proof).unwrap();
+        let err = checked
+            .entry_value::<u64>("token.total_supply")
+            .unwrap_err();
+        assert_matches!(err, ProofError::IncorrectEntryValue);
+
+        block_proof.block.state_hash = Hash::zero();
+        let err = proof.check_against_block(&block_proof).unwrap_err();
+        assert_matches!(err, ProofError::IncorrectEntryProof(_));
+    }
+}
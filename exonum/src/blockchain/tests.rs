@@ -17,7 +17,9 @@ use exonum_crypto::{Hash, KeyPair};
 use exonum_derive::{BinaryValue, FromAccess};
 use exonum_merkledb::{
     access::{Access, FromAccess},
-    BinaryValue, Error as MerkledbError, ObjectHash, ProofListIndex, Snapshot, SystemSchema,
+    dump::{self, ChunkCursor},
+    BinaryValue, Database, DatabaseExt, Error as MerkledbError, ObjectHash, ProofListIndex,
+    Snapshot, SystemSchema, TemporaryDB,
 };
 use pretty_assertions::assert_eq;
 use semver::Version;
@@ -25,16 +27,17 @@ use semver::Version;
 use std::{
     cell::RefCell,
     collections::{BTreeMap, VecDeque},
-    panic,
+    iter, panic,
 };
 
 use crate::{
     blockchain::{
         config::{ConsensusConfig, GenesisConfig, GenesisConfigBuilder, InstanceInitParams},
-        BlockParams, Blockchain, BlockchainMut, PersistentPool, Schema, TransactionCache,
+        BlockParams, Blockchain, BlockchainBuilder, BlockchainMut, EquivocationEvidence,
+        PersistentPool, Schema, TransactionCache,
     },
     helpers::{Height, Round, ValidatorId},
-    messages::{Precommit, Verified},
+    messages::{CoreMessage, Precommit, SignedMessage, Verified},
     runtime::{
         catch_panic,
         migrations::{InitMigrationError, MigrationScript},
@@ -153,6 +156,8 @@ enum Transaction {
     AddService(InstanceSpec, InitAction),
     /// Stop service with the specified ID.
     StopService(InstanceId),
+    /// Report equivocation of a validator.
+    ReportEquivocation(EquivocationEvidence),
 }
 
 impl Transaction {
@@ -200,6 +205,8 @@ impl Execute for Transaction {
             Self::StopService(instance_id) => {
                 Dispatcher::initiate_stopping_service(context.fork, instance_id)
             }
+
+            Self::ReportEquivocation(evidence) => context.report_equivocation(evidence).map(drop),
         }
     }
 }
@@ -367,7 +374,22 @@ fn create_blockchain(
     runtime: RuntimeInspector,
     instances: Vec<InstanceInitParams>,
 ) -> BlockchainMut {
+    blockchain_builder(runtime, instances).build()
+}
+
+fn blockchain_builder(
+    runtime: RuntimeInspector,
+    instances: Vec<InstanceInitParams>,
+) -> BlockchainBuilder {
     let (config, _) = ConsensusConfig::for_tests(1);
+    blockchain_builder_with_config(runtime, instances, config)
+}
+
+fn blockchain_builder_with_config(
+    runtime: RuntimeInspector,
+    instances: Vec<InstanceInitParams>,
+    config: ConsensusConfig,
+) -> BlockchainBuilder {
     let genesis_config = instances
         .into_iter()
         .fold(
@@ -383,7 +405,6 @@ fn create_blockchain(
     Blockchain::build_for_tests()
         .into_mut(genesis_config)
         .with_runtime(runtime)
-        .build()
 }
 
 /// Checks that `after_transactions` is invoked for services added
@@ -457,6 +478,401 @@ fn handling_tx_panic_error() {
     assert_eq!(InspectorSchema::new(&snapshot).values.get(0), Some(10));
 }
 
+#[test]
+fn snapshots_at_past_heights() {
+    let keys = KeyPair::random();
+    let mut blockchain = blockchain_builder(
+        RuntimeInspector::default(),
+        vec![InitAction::Noop.into_default_instance()],
+    )
+    .with_state_history()
+    .build();
+
+    for value in 1..=3 {
+        execute_transaction(
+            &mut blockchain,
+            Transaction::AddValue(value).sign(TEST_SERVICE_ID, &keys),
+        )
+        .expect("Transaction must success");
+    }
+
+    let blockchain = blockchain.immutable_view();
+    assert!(blockchain.snapshot_at(Height(4)).is_none());
+    for height in 0..=3 {
+        // Only block patches are versioned, so versions correspond to block heights.
+        let state_version = Schema::new(&blockchain.snapshot()).state_version(Height(height));
+        assert_eq!(state_version, Some(height));
+        let snapshot = blockchain.snapshot_at(Height(height)).unwrap();
+        let values: Vec<_> = InspectorSchema::new(&snapshot).values.iter().collect();
+        assert_eq!(values, (1..=height).collect::<Vec<_>>());
+
+        let block = Schema::new(&blockchain.snapshot())
+            .block_and_precommits(Height(height))
+            .unwrap()
+            .block;
+        assert_eq!(Schema::new(&snapshot).height(), Height(height));
+        assert_eq!(SystemSchema::new(&snapshot).state_hash(), block.state_hash);
+        if height == 0 {
+            // The index is not yet created at the genesis block.
+            continue;
+        }
+
+        let aggregator = SystemSchema::new(&snapshot).state_aggregator();
+        let proof = aggregator.get_proof("values".to_owned());
+        let checked_proof = proof.check_against_hash(block.state_hash).unwrap();
+        let expected_hash = InspectorSchema::new(&snapshot).values.object_hash();
+        assert_eq!(
+            checked_proof.entries().collect::<Vec<_>>(),
+            vec![(&"values".to_owned(), &expected_hash)]
+        );
+    }
+}
+
+#[test]
+fn state_history_with_limited_depth() {
+    let keys = KeyPair::random();
+    let mut blockchain = blockchain_builder(
+        RuntimeInspector::default(),
+        vec![InitAction::Noop.into_default_instance()],
+    )
+    .with_state_history_depth(2)
+    .build();
+
+    for value in 1..=4 {
+        execute_transaction(
+            &mut blockchain,
+            Transaction::AddValue(value).sign(TEST_SERVICE_ID, &keys),
+        )
+        .expect("Transaction must success");
+    }
+
+    let blockchain = blockchain.immutable_view();
+    for height in 0..=2 {
+        assert!(blockchain.snapshot_at(Height(height)).is_none());
+    }
+    let snapshot = blockchain.snapshot_at(Height(3)).unwrap();
+    let values: Vec<_> = InspectorSchema::new(&snapshot).values.iter().collect();
+    assert_eq!(values, vec![1, 2, 3]);
+    assert!(blockchain.snapshot_at(Height(4)).is_some());
+}
+
+#[test]
+fn state_history_is_disabled_by_default() {
+    let blockchain = create_blockchain(
+        RuntimeInspector::default(),
+        vec![InitAction::Noop.into_default_instance()],
+    );
+    let blockchain = blockchain.immutable_view();
+    assert!(blockchain.snapshot_at(Height(0)).is_none());
+}
+
+#[test]
+fn exporting_and_importing_state() {
+    let keys = KeyPair::random();
+    let mut blockchain = blockchain_builder(
+        RuntimeInspector::default(),
+        vec![InitAction::Noop.into_default_instance()],
+    )
+    .with_state_history()
+    .build();
+
+    for value in 1..=3 {
+        execute_transaction(
+            &mut blockchain,
+            Transaction::AddValue(value).sign(TEST_SERVICE_ID, &keys),
+        )
+        .expect("Transaction must success");
+    }
+
+    let blockchain = blockchain.immutable_view();
+    for height in 1..=3 {
+        let mut buffer = vec![];
+        blockchain
+            .export_state(Height(height), &mut buffer)
+            .unwrap();
+
+        let db = TemporaryDB::new();
+        let (block_proof, info) = Blockchain::import_state(&db, buffer.as_slice()).unwrap();
+        // Core indexes with block data are not covered by the state hash.
+        assert!(info
+            .unauthenticated_indexes
+            .contains(&"core.blocks".to_owned()));
+        assert_eq!(block_proof.block.height, Height(height));
+        let expected_block = Schema::new(&blockchain.snapshot())
+            .block_and_precommits(Height(height))
+            .unwrap()
+            .block;
+        assert_eq!(block_proof.block, expected_block);
+
+        let snapshot = db.snapshot();
+        let values: Vec<_> = InspectorSchema::new(&snapshot).values.iter().collect();
+        assert_eq!(values, (1..=height).collect::<Vec<_>>());
+        assert_eq!(
+            SystemSchema::new(&snapshot).state_hash(),
+            expected_block.state_hash
+        );
+    }
+
+    let err = blockchain.export_state(Height(4), &mut vec![]).unwrap_err();
+    assert_eq!(err.to_string(), "State at height 4 is not available");
+}
+
+#[test]
+fn importing_state_without_blocks() {
+    let mut buffer = vec![];
+    dump::export(TemporaryDB::new().snapshot().as_ref(), &mut buffer).unwrap();
+    let err = Blockchain::import_state(&TemporaryDB::new(), buffer.as_slice()).unwrap_err();
+    assert_eq!(err.to_string(), "Imported state does not contain blocks");
+}
+
+#[test]
+fn importing_state_in_chunks() {
+    let keys = KeyPair::random();
+    let (config, _) = ConsensusConfig::for_tests(1);
+    let create_blockchain = || {
+        blockchain_builder_with_config(
+            RuntimeInspector::default(),
+            vec![InitAction::Noop.into_default_instance()],
+            config.clone(),
+        )
+        .build()
+    };
+    let mut blockchain = create_blockchain();
+    for value in 1..=3 {
+        let tx = Transaction::AddValue(value).sign(TEST_SERVICE_ID, &keys);
+        blockchain.add_transactions_into_pool(iter::once(tx));
+        execute_transaction(
+            &mut blockchain,
+            Transaction::AddValue(value + 10).sign(TEST_SERVICE_ID, &keys),
+        )
+        .expect("Transaction must success");
+    }
+    let source = blockchain.immutable_view();
+    let snapshot = source.snapshot();
+    let block_proof = Schema::new(&snapshot)
+        .block_and_precommits(Height(3))
+        .unwrap();
+
+    let mut other_blockchain = create_blockchain();
+    let import_state = |blockchain: &mut BlockchainMut| {
+        blockchain.start_state_import().unwrap();
+        let mut cursor = Some(ChunkCursor::default());
+        while let Some(current) = cursor {
+            let (chunk, next) = source.export_state_chunk(Height(3), &current, 256).unwrap();
+            blockchain.import_state_chunk(&chunk).unwrap();
+            cursor = next;
+        }
+    };
+    import_state(&mut other_blockchain);
+
+    // The state should correspond to the latest block.
+    let err = other_blockchain
+        .finish_state_import(
+            &Schema::new(&snapshot)
+                .block_and_precommits(Height(2))
+                .unwrap(),
+        )
+        .unwrap_err();
+    assert!(err.to_string().contains("does not match"), "{}", err);
+    // The storage is not affected by the failed import, and the imported chunks are discarded.
+    assert_eq!(other_blockchain.as_ref().last_block().height, Height(0));
+    let err = other_blockchain
+        .finish_state_import(&block_proof)
+        .unwrap_err();
+    assert_eq!(err.to_string(), "State import is not started");
+
+    import_state(&mut other_blockchain);
+    other_blockchain.finish_state_import(&block_proof).unwrap();
+
+    let snapshot = other_blockchain.snapshot();
+    let schema = Schema::new(&snapshot);
+    assert_eq!(schema.last_block(), block_proof.block);
+    assert_eq!(schema.transactions_pool_len(), 0);
+    assert_eq!(schema.state_version(Height(3)), None);
+    let values: Vec<_> = InspectorSchema::new(&snapshot).values.iter().collect();
+    assert_eq!(values, vec![11, 12, 13]);
+
+    // The imported blockchain should be able to process new blocks.
+    execute_transaction(
+        &mut other_blockchain,
+        Transaction::AddValue(14).sign(TEST_SERVICE_ID, &keys),
+    )
+    .expect("Transaction must success");
+    let snapshot = other_blockchain.snapshot();
+    assert_eq!(Schema::new(&snapshot).height(), Height(4));
+}
+
+/// The imported state cannot be trusted if validators have changed since the local
+/// consensus configuration.
+#[test]
+fn importing_state_with_changed_validators() {
+    let keys = KeyPair::random();
+    let (config, _) = ConsensusConfig::for_tests(1);
+    let mut blockchain = blockchain_builder_with_config(
+        RuntimeInspector::default(),
+        vec![InitAction::Noop.into_default_instance()],
+        config.clone(),
+    )
+    .build();
+    execute_transaction(
+        &mut blockchain,
+        Transaction::AddValue(1).sign(TEST_SERVICE_ID, &keys),
+    )
+    .expect("Transaction must success");
+
+    let import_state = |blockchain: &BlockchainMut, other_blockchain: &mut BlockchainMut| {
+        let snapshot = blockchain.snapshot();
+        let block_proof = Schema::new(&snapshot)
+            .block_and_precommits(Height(1))
+            .unwrap();
+        let cursor = ChunkCursor::default();
+        let (chunk, next) = blockchain
+            .immutable_view()
+            .export_state_chunk(Height(1), &cursor, usize::MAX)
+            .unwrap();
+        assert!(next.is_none());
+        other_blockchain.start_state_import().unwrap();
+        other_blockchain.import_state_chunk(&chunk).unwrap();
+        other_blockchain.finish_state_import(&block_proof)
+    };
+    let expected_msg = "Validator keys in the imported state differ from the local validator keys";
+
+    // The actual configuration has other validators.
+    let mut other_blockchain = create_blockchain(
+        RuntimeInspector::default(),
+        vec![InitAction::Noop.into_default_instance()],
+    );
+    let err = import_state(&blockchain, &mut other_blockchain).unwrap_err();
+    assert_eq!(err.to_string(), expected_msg);
+
+    // The actual configuration is the same, but validators have changed in the past.
+    let fork = blockchain.fork();
+    let mut schema = Schema::new(&fork);
+    let (previous_config, _) = ConsensusConfig::for_tests(1);
+    schema.consensus_config_entry().set(previous_config);
+    schema.record_config_change(config.clone(), Height(1));
+    schema.consensus_config_entry().set(config.clone());
+    blockchain.merge(fork.into_patch()).unwrap();
+
+    let mut other_blockchain = blockchain_builder_with_config(
+        RuntimeInspector::default(),
+        vec![InitAction::Noop.into_default_instance()],
+        config,
+    )
+    .build();
+    let err = import_state(&blockchain, &mut other_blockchain).unwrap_err();
+    assert_eq!(err.to_string(), expected_msg);
+    assert_eq!(other_blockchain.as_ref().last_block().height, Height(0));
+}
+
+#[test]
+fn pruning_blocks() {
+    let keys = KeyPair::random();
+    let mut blockchain = create_blockchain(
+        RuntimeInspector::default(),
+        vec![InitAction::Noop.into_default_instance()],
+    );
+
+    let transactions: Vec<_> = (1..=3)
+        .map(|value| Transaction::AddValue(value).sign(TEST_SERVICE_ID, &keys))
+        .collect();
+    for tx in transactions.clone() {
+        execute_transaction(&mut blockchain, tx).expect("Transaction must success");
+    }
+    let state_hash = blockchain.as_ref().last_block().state_hash;
+
+    let fork = blockchain.fork();
+    let mut schema = Schema::new(&fork);
+    assert_eq!(schema.pruned_height(), None);
+    schema.prune_block(Height(0));
+    schema.prune_block(Height(1));
+    schema.prune_block(Height(2));
+    assert_eq!(schema.pruned_height(), Some(Height(2)));
+    blockchain.merge(fork.into_patch()).unwrap();
+
+    let snapshot = blockchain.snapshot();
+    let schema = Schema::new(&snapshot);
+    assert_eq!(SystemSchema::new(&snapshot).state_hash(), state_hash);
+    for (i, tx) in transactions.iter().enumerate() {
+        let height = Height(i as u64 + 1);
+        let tx_hash = tx.object_hash();
+        let location = schema.transactions_locations().get(&tx_hash).unwrap();
+        assert_eq!(location.block_height(), height);
+
+        let block_proof = schema.block_and_precommits(height).unwrap();
+        assert_eq!(block_proof.block.height, height);
+        if height <= Height(2) {
+            assert!(!schema.transactions().contains(&tx_hash));
+            assert!(schema.block_transactions(height).is_empty());
+            assert!(schema.transaction_result(location).is_none());
+        } else {
+            assert!(schema.transactions().contains(&tx_hash));
+            assert_eq!(schema.block_transactions(height).len(), 1);
+            assert_eq!(schema.transaction_result(location), Some(Ok(())));
+        }
+    }
+
+    // Pruned transactions are not added to the pool again.
+    blockchain.add_transactions_into_pool(transactions);
+    assert_eq!(blockchain.as_ref().pool_size(), 0);
+}
+
+#[test]
+fn pruning_blocks_in_batches() {
+    let keys = KeyPair::random();
+    let mut blockchain = blockchain_builder(
+        RuntimeInspector::default(),
+        vec![InitAction::Noop.into_default_instance()],
+    )
+    .with_state_history()
+    .build();
+    for value in 1..=4 {
+        let tx = Transaction::AddValue(value).sign(TEST_SERVICE_ID, &keys);
+        execute_transaction(&mut blockchain, tx).expect("Transaction must success");
+    }
+
+    let blockchain = blockchain.immutable_view();
+    let latest_version = blockchain.database().latest_version();
+    let pruned = blockchain.prune_blocks(Height(10), 2).unwrap();
+    assert_eq!(pruned, Some(Height(0)..=Height(1)));
+    // The latest block is never pruned.
+    let pruned = blockchain.prune_blocks(Height(10), 5).unwrap();
+    assert_eq!(pruned, Some(Height(2)..=Height(3)));
+    assert_eq!(blockchain.prune_blocks(Height(10), 5).unwrap(), None);
+
+    let snapshot = blockchain.snapshot();
+    let schema = Schema::new(&snapshot);
+    assert_eq!(schema.pruned_height(), Some(Height(3)));
+    assert!(!schema.block_transactions(Height(4)).is_empty());
+    // Pruning does not create new versions in the state history.
+    assert_eq!(blockchain.database().latest_version(), latest_version);
+}
+
+#[test]
+#[should_panic(expected = "Attempt to prune the latest block")]
+fn pruning_latest_block() {
+    let blockchain = create_blockchain(RuntimeInspector::default(), vec![]);
+    let fork = blockchain.fork();
+    Schema::new(&fork).prune_block(Height(0));
+}
+
+#[test]
+#[should_panic(expected = "Blocks must be pruned sequentially")]
+fn pruning_blocks_out_of_order() {
+    let keys = KeyPair::random();
+    let mut blockchain = create_blockchain(
+        RuntimeInspector::default(),
+        vec![InitAction::Noop.into_default_instance()],
+    );
+    for value in 1..=2 {
+        let tx = Transaction::AddValue(value).sign(TEST_SERVICE_ID, &keys);
+        execute_transaction(&mut blockchain, tx).expect("Transaction must success");
+    }
+
+    let fork = blockchain.fork();
+    Schema::new(&fork).prune_block(Height(1));
+}
+
 #[test]
 #[should_panic]
 fn handling_tx_merkledb_error() {
@@ -841,6 +1257,7 @@ fn executing_block_skip() {
         propose_hash: Hash::zero(),
         block_hash: patch.block_hash(),
         time: Utc::now(),
+        bls_signature: None,
     };
     let precommit = Verified::from_value(
         precommit,
@@ -882,6 +1299,7 @@ fn clearing_block_skip() {
         propose_hash: Hash::zero(),
         block_hash,
         time: Utc::now(),
+        bls_signature: None,
     };
     let precommit = Verified::from_value(
         precommit_payload.clone(),
@@ -930,3 +1348,94 @@ fn clearing_block_skip() {
     assert!(schema.block_skip().is_none());
     assert!(schema.precommits(&new_block_hash).is_empty());
 }
+
+#[test]
+fn reporting_equivocation() {
+    let (config, node_keys) = ConsensusConfig::for_tests(1);
+    let instance = InitAction::Noop.into_default_instance();
+    let genesis_config = GenesisConfigBuilder::with_consensus_config(config)
+        .with_artifact(instance.instance_spec.artifact.clone())
+        .with_instance(instance)
+        .build();
+    let mut blockchain = Blockchain::build_for_tests()
+        .into_mut(genesis_config)
+        .with_runtime(RuntimeInspector::default())
+        .build();
+
+    let precommit = |block_hash: Hash| {
+        let precommit = Precommit::new(
+            ValidatorId(0),
+            Height(1),
+            Round(1),
+            Hash::zero(),
+            block_hash,
+            Utc::now(),
+        );
+        let payload = CoreMessage::from(precommit).into_bytes();
+        SignedMessage::new(payload, node_keys.consensus_pk(), node_keys.consensus_sk())
+    };
+    let evidence = EquivocationEvidence::new(
+        precommit(exonum_crypto::hash(&[1])),
+        precommit(exonum_crypto::hash(&[2])),
+    );
+
+    let keys = KeyPair::random();
+    let tx = Transaction::ReportEquivocation(evidence.clone());
+    execute_transaction(&mut blockchain, tx.clone().sign(TEST_SERVICE_ID, &keys)).unwrap();
+    let snapshot = blockchain.snapshot();
+    let reported = Schema::new(&snapshot).reported_equivocations();
+    assert_eq!(reported.get(&evidence.object_hash()), Some(evidence));
+    // Reported evidence does not influence the state hash.
+    let aggregator = SystemSchema::new(&snapshot).state_aggregator();
+    assert!(!aggregator.contains("core.reported_equivocations"));
+
+    // The same evidence cannot be reported twice.
+    let err = execute_transaction(&mut blockchain, tx.sign(TEST_SERVICE_ID, &keys)).unwrap_err();
+    assert_eq!(
+        err,
+        ErrorMatch::from_fail(&CoreError::EvidenceAlreadyReported)
+    );
+
+    // Messages which do not conflict are not an evidence.
+    let message = precommit(exonum_crypto::hash(&[3]));
+    let evidence = EquivocationEvidence::new(message.clone(), message);
+    let tx = Transaction::ReportEquivocation(evidence);
+    let err = execute_transaction(&mut blockchain, tx.sign(TEST_SERVICE_ID, &keys)).unwrap_err();
+    assert_eq!(
+        err,
+        ErrorMatch::from_fail(&CoreError::InvalidEvidence).with_any_description()
+    );
+}
+
+#[test]
+fn consensus_config_history() {
+    let fork = create_blockchain(RuntimeInspector::default(), vec![]).fork();
+    let mut schema = Schema::new(&fork);
+    let config = schema.consensus_config();
+    // Without configuration changes, the actual configuration is returned for all epochs.
+    assert_eq!(schema.consensus_config_at(Height(0)), config);
+    assert_eq!(schema.consensus_config_at(Height(5)), config);
+
+    let mut changes = vec![config];
+    for &epoch in &[2, 5] {
+        let previous_config = schema.consensus_config();
+        let mut new_config = previous_config.clone();
+        new_config.max_message_len += 1;
+        schema.consensus_config_entry().set(new_config.clone());
+        schema.record_config_change(previous_config, Height(epoch));
+        changes.push(new_config);
+    }
+
+    let expected = [0, 0, 0, 1, 1, 1, 2, 2];
+    for (epoch, &index) in expected.iter().enumerate() {
+        assert_eq!(
+            schema.consensus_config_at(Height(epoch as u64)),
+            changes[index]
+        );
+    }
+    assert_eq!(schema.consensus_config_at(Height(u64::MAX)), changes[2]);
+
+    // The history does not influence the state hash.
+    let aggregator = SystemSchema::new(&fork).state_aggregator();
+    assert!(!aggregator.contains("core.consensus_config_history"));
+}
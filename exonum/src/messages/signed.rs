@@ -27,7 +27,7 @@ use std::{
 };
 
 use crate::{
-    crypto::{self, Hash, PublicKey, SecretKey},
+    crypto::{schemes::AnyKeyPair, Hash, PublicKey, SecretKey},
     messages::types::SignedMessage,
     proto::schema,
 };
@@ -39,10 +39,7 @@ impl SignedMessage {
         T: TryFrom<Self>,
     {
         // Verifies message signature
-        ensure!(
-            crypto::verify(&self.signature, &self.payload, &self.author),
-            "Failed to verify signature."
-        );
+        ensure!(self.verify_signature(), "Failed to verify signature.");
         // Deserializes message.
         let inner = T::try_from(self.clone())
             .map_err(|_| anyhow::format_err!("Failed to decode message from payload."))?;
@@ -97,6 +94,13 @@ impl<T> Verified<T> {
     }
 
     /// Returns the public key of the message author.
+    ///
+    /// If the message is signed with a non-Ed25519 key, the returned value is
+    /// the key identifier (see [`AnyPublicKey::key_id`]) rather than a verification key.
+    /// Use [`SignedMessage::any_author()`] to access the original key.
+    ///
+    /// [`AnyPublicKey::key_id`]: ../crypto/schemes/struct.AnyPublicKey.html#method.key_id
+    /// [`SignedMessage::any_author()`]: struct.SignedMessage.html#method.any_author
     pub fn author(&self) -> PublicKey {
         self.raw.author
     }
@@ -155,6 +159,19 @@ where
         };
         Self { raw, inner }
     }
+
+    /// Signs the specified value with a keypair of any supported signature scheme
+    /// and creates a new verified message from it.
+    pub fn sign_with(inner: T, keypair: &AnyKeyPair) -> Self {
+        let container: T::Container = inner.into();
+        let raw = SignedMessage::sign_with(container.to_bytes(), keypair);
+        let inner: T = if let Ok(inner) = container.try_into() {
+            inner
+        } else {
+            unreachable!("We can safely convert `ExonumMessage` back to the inner type.")
+        };
+        Self { raw, inner }
+    }
 }
 
 impl<'de, T> Deserialize<'de> for Verified<T>
@@ -250,7 +267,11 @@ impl<T: Debug> Debug for Verified<T> {
 #[cfg(test)]
 mod tests {
     use chrono::Utc;
-    use exonum_crypto::{self as crypto, Signature};
+    use exonum_crypto::{
+        self as crypto,
+        schemes::{AnyKeyPair, SignatureScheme},
+        Signature,
+    };
     use pretty_assertions::assert_eq;
 
     use super::*;
@@ -272,6 +293,55 @@ mod tests {
         assert_eq!(msg, msg2);
     }
 
+    #[test]
+    fn test_verified_any_tx_with_ecdsa_keys() {
+        let schemes = [
+            SignatureScheme::Secp256k1,
+            SignatureScheme::P256,
+            SignatureScheme::Ethereum,
+        ];
+        for &scheme in &schemes {
+            let keypair = AnyKeyPair::random(scheme);
+            let msg =
+                AnyTx::new(CallInfo::new(5, 2), vec![1, 2, 3, 4]).sign_with_any_keypair(&keypair);
+            let raw = msg.as_raw();
+            assert_eq!(raw.signature_scheme(), scheme);
+            assert_eq!(raw.any_author(), *keypair.public_key());
+            assert_eq!(raw.author, keypair.public_key().key_id());
+            assert_eq!(msg.author(), keypair.public_key().key_id());
+
+            let bytes = msg.to_bytes();
+            let msg2 = Verified::<AnyTx>::from_bytes(bytes.into()).unwrap();
+            assert_eq!(msg, msg2);
+
+            // Signature with another key of the same scheme is rejected.
+            let raw = msg.into_raw();
+            let other_key = AnyKeyPair::random(scheme).public_key().clone();
+            let raw =
+                SignedMessage::from_parts(raw.payload.clone(), other_key, raw.any_signature());
+            let err = raw.into_verified::<AnyTx>().unwrap_err();
+            assert!(err.to_string().contains("Failed to verify signature"));
+        }
+    }
+
+    #[test]
+    fn test_ed25519_message_parts() {
+        let keypair = crypto::KeyPair::random();
+        let msg = AnyTx::new(CallInfo::new(5, 2), vec![1, 2, 3]).sign_with_keypair(&keypair);
+        let raw = msg.as_raw();
+        assert_eq!(raw.signature_scheme(), SignatureScheme::Ed25519);
+        assert_eq!(raw.any_author(), keypair.public_key().into());
+        assert_eq!(raw.any_signature(), raw.signature.into());
+
+        let restored =
+            SignedMessage::from_parts(raw.payload.clone(), raw.any_author(), raw.any_signature());
+        assert_eq!(restored, *raw);
+        // The Protobuf representation is the same for both kinds of keys.
+        let any_keypair = AnyKeyPair::from(keypair);
+        let any_msg = SignedMessage::sign_with(raw.payload.clone(), &any_keypair);
+        assert_eq!(any_msg, *raw);
+    }
+
     #[test]
     fn test_verified_protobuf_convert() {
         let keypair = crypto::KeyPair::random();
@@ -22,28 +22,49 @@ use exonum_proto::ProtobufConvert;
 use std::convert::TryFrom;
 
 use crate::{
-    crypto::{self, Hash, PublicKey, SecretKey, Signature},
+    crypto::{
+        self,
+        bls::BlsSignature,
+        schemes::{AnyKeyPair, AnyPublicKey, AnySignature, SignatureScheme},
+        Hash, PublicKey, SecretKey, Signature,
+    },
     helpers::{Height, Round, ValidatorId},
     proto::schema::messages,
 };
 
 /// Protobuf-based container for an arbitrary signed message.
 ///
+/// Messages are usually signed with Ed25519 keys. Transactions may also be signed with keys
+/// of other [signature schemes]; the key and the signature of such a message are returned
+/// by [`any_author()`] and [`any_signature()`] respectively.
+///
 /// See module [documentation](index.html#examples) for examples.
+///
+/// [signature schemes]: ../crypto/schemes/index.html
+/// [`any_author()`]: #method.any_author
+/// [`any_signature()`]: #method.any_signature
 #[derive(Clone, PartialEq, Eq, Ord, PartialOrd, Debug)]
-#[derive(ProtobufConvert, BinaryValue, ObjectHash)]
-#[protobuf_convert(source = "messages::SignedMessage")]
+#[derive(BinaryValue, ObjectHash)]
 pub struct SignedMessage {
     /// Payload of the message.
     pub payload: Vec<u8>,
     /// `PublicKey` of the author of the message.
+    ///
+    /// If the message is signed with a non-Ed25519 key, this is the key identifier
+    /// (see [`AnyPublicKey::key_id()`]) rather than a verification key.
+    ///
+    /// [`AnyPublicKey::key_id()`]: ../crypto/schemes/struct.AnyPublicKey.html#method.key_id
     pub author: PublicKey,
     /// Digital signature over `payload` created with the secret key of the author of the message.
+    ///
+    /// If the message is signed with a non-Ed25519 key, the signature is zeroed.
     pub signature: Signature,
+    /// Key and signature of the author if the message is signed with a non-Ed25519 key.
+    other_scheme: Option<Box<(AnyPublicKey, AnySignature)>>,
 }
 
 impl SignedMessage {
-    /// Creates a new signed message from the given binary value.
+    /// Creates a new signed message from the given binary value using Ed25519 keys.
     pub fn new(payload: impl BinaryValue, author: PublicKey, secret_key: &SecretKey) -> Self {
         let payload = payload.into_bytes();
         let signature = crypto::sign(payload.as_ref(), secret_key);
@@ -51,10 +72,97 @@ impl SignedMessage {
             payload,
             author,
             signature,
+            other_scheme: None,
+        }
+    }
+
+    /// Creates a new signed message from the given binary value using a keypair
+    /// of any supported signature scheme.
+    pub fn sign_with(payload: impl BinaryValue, keypair: &AnyKeyPair) -> Self {
+        let payload = payload.into_bytes();
+        let signature = keypair.sign(payload.as_ref());
+        Self::from_parts(payload, keypair.public_key().clone(), signature)
+    }
+
+    /// Creates a signed message from the payload, the key of its author and the signature,
+    /// which may belong to any supported signature scheme. The signature is not verified.
+    pub fn from_parts(
+        payload: Vec<u8>,
+        author: impl Into<AnyPublicKey>,
+        signature: impl Into<AnySignature>,
+    ) -> Self {
+        let author = author.into();
+        let signature = signature.into();
+        if let (Some(ed25519_author), Some(ed25519_signature)) =
+            (author.as_ed25519(), signature.as_ed25519())
+        {
+            Self {
+                payload,
+                author: ed25519_author,
+                signature: ed25519_signature,
+                other_scheme: None,
+            }
+        } else {
+            Self {
+                payload,
+                author: author.key_id(),
+                signature: Signature::zero(),
+                other_scheme: Some(Box::new((author, signature))),
+            }
+        }
+    }
+
+    /// Returns the signature scheme of the message.
+    pub fn signature_scheme(&self) -> SignatureScheme {
+        self.other_scheme
+            .as_ref()
+            .map_or(SignatureScheme::Ed25519, |other| other.0.scheme())
+    }
+
+    /// Returns the public key of the message author, which may belong to any supported
+    /// signature scheme.
+    pub fn any_author(&self) -> AnyPublicKey {
+        self.other_scheme
+            .as_ref()
+            .map_or_else(|| self.author.into(), |other| other.0.clone())
+    }
+
+    /// Returns the signature of the message, which may belong to any supported
+    /// signature scheme.
+    pub fn any_signature(&self) -> AnySignature {
+        self.other_scheme
+            .as_ref()
+            .map_or_else(|| self.signature.into(), |other| other.1.clone())
+    }
+
+    /// Checks whether the message is correctly signed by its author.
+    pub(crate) fn verify_signature(&self) -> bool {
+        if let Some(other) = &self.other_scheme {
+            other.0.verify(&self.payload, &other.1)
+        } else {
+            crypto::verify(&self.signature, &self.payload, &self.author)
         }
     }
 }
 
+impl ProtobufConvert for SignedMessage {
+    type ProtoStruct = messages::SignedMessage;
+
+    fn to_pb(&self) -> Self::ProtoStruct {
+        let mut pb = messages::SignedMessage::new();
+        pb.set_payload(self.payload.clone());
+        pb.set_author(self.any_author().to_pb());
+        pb.set_signature(self.any_signature().to_pb());
+        pb
+    }
+
+    fn from_pb(mut pb: Self::ProtoStruct) -> anyhow::Result<Self> {
+        let author = AnyPublicKey::from_pb(pb.take_author())?;
+        let signature = AnySignature::from_pb(pb.take_signature())?;
+        Ok(Self::from_parts(pb.take_payload(), author, signature))
+    }
+}
+
 /// Pre-commit for a block, essentially meaning that a validator node endorses the block.
 /// The consensus algorithm ensures that once a Byzantine majority of validators has
 /// endorsed a block, no other block at the same height may be endorsed at any point in the future.
@@ -77,6 +185,13 @@ pub struct Precommit {
     pub block_hash: Hash,
     /// Local time of the validator node when the `Precommit` was created.
     pub time: DateTime<Utc>,
+    /// Optional BLS signature of the block hash, which is used to build
+    /// a [commit certificate] for the block.
+    ///
+    /// [commit certificate]: ../blockchain/struct.CommitCertificate.html
+    #[protobuf_convert(with = "pb_bls_signature")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bls_signature: Option<BlsSignature>,
 }
 
 impl Precommit {
@@ -96,8 +211,38 @@ impl Precommit {
             propose_hash,
             block_hash,
             time,
+            bls_signature: None,
         }
     }
+
+    /// Attaches a BLS signature of the block hash to this precommit.
+    pub fn with_bls_signature(mut self, signature: BlsSignature) -> Self {
+        self.bls_signature = Some(signature);
+        self
+    }
+}
+
+/// BLS signature is stored as raw bytes, so that precommits without it
+/// retain their binary representation.
+mod pb_bls_signature {
+    use super::BlsSignature;
+
+    #[allow(clippy::needless_pass_by_value)] // required for work with `protobuf_convert(with)`
+    pub fn from_pb(pb: Vec<u8>) -> anyhow::Result<Option<BlsSignature>> {
+        if pb.is_empty() {
+            Ok(None)
+        } else {
+            BlsSignature::from_slice(&pb)
+                .map(Some)
+                .ok_or_else(|| anyhow::format_err!("Invalid BLS signature"))
+        }
+    }
+
+    pub fn to_pb(value: &Option<BlsSignature>) -> Vec<u8> {
+        value
+            .as_ref()
+            .map_or_else(Vec::new, |signature| signature.as_bytes().to_vec())
+    }
 }
 
 /// Subset of Exonum messages defined in the Exonum core.
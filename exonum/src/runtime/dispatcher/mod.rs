@@ -378,6 +378,8 @@ impl Dispatcher {
     }
 
     /// Restore the dispatcher from the state which was saved in the specified snapshot.
+    /// Artifacts that are already deployed (e.g., if the state is restored after
+    /// synchronizing it with other nodes) are not redeployed.
     ///
     /// # Panics
     ///
@@ -392,6 +394,9 @@ impl Dispatcher {
                 ArtifactStatus::Active,
                 "BUG: Artifact should not be in pending state."
             );
+            if self.is_artifact_deployed(&artifact) {
+                continue;
+            }
 
             self.deploy_artifact(artifact.clone(), state.deploy_spec)
                 .unwrap_or_else(|err| {
@@ -61,6 +61,10 @@ pub enum CoreError {
     IncorrectCall = 14,
     /// Cannot unload artifact.
     CannotUnloadArtifact = 15,
+    /// Equivocation evidence is incorrect.
+    InvalidEvidence = 16,
+    /// Equivocation evidence has already been reported.
+    EvidenceAlreadyReported = 17,
 }
 
 impl CoreError {
@@ -13,10 +13,10 @@
 // limitations under the License.
 
 use crate::{
-    blockchain::Schema as CoreSchema,
+    blockchain::{Equivocation, EquivocationEvidence, Schema as CoreSchema},
     crypto::{Hash, PublicKey},
     helpers::{Height, ValidateInput},
-    merkledb::{access::Prefixed, BinaryValue, Fork},
+    merkledb::{access::Prefixed, BinaryValue, Fork, ObjectHash},
     runtime::{
         migrations::MigrationType, ArtifactId, BlockchainData, CallSite, CallType, Caller,
         CoreError, Dispatcher, DispatcherSchema, ExecutionError, ExecutionFail, InstanceDescriptor,
@@ -169,6 +169,38 @@ impl<'a> ExecutionContext<'a> {
         SupervisorExtensions(self.reborrow(self.instance.clone()))
     }
 
+    /// Records the evidence of equivocation by a validator in the blockchain. The recorded
+    /// evidence is available via the [`reported_equivocations`] index of the core schema.
+    ///
+    /// The evidence is verified against the consensus configuration active at the epoch
+    /// of the conflicting messages, so it can be reported after the equivocating validator
+    /// is removed from the validator set or changes its consensus key. Returns information
+    /// about the equivocation if the evidence is correct and was not reported before.
+    ///
+    /// [`reported_equivocations`]: ../blockchain/struct.Schema.html#method.reported_equivocations
+    pub fn report_equivocation(
+        &mut self,
+        evidence: EquivocationEvidence,
+    ) -> Result<Equivocation, ExecutionError> {
+        if self.call_error_flag.is_set() {
+            panic!(ACCESS_ERROR_STR);
+        }
+
+        let schema = CoreSchema::new(&*self.fork);
+        let equivocation = evidence
+            .epoch()
+            .and_then(|epoch| evidence.verify(&schema.consensus_config_at(epoch).validator_keys))
+            .map_err(|e| CoreError::InvalidEvidence.with_description(e))?;
+
+        let mut reported = schema.reported_equivocations();
+        let evidence_hash = evidence.object_hash();
+        if reported.contains(&evidence_hash) {
+            return Err(CoreError::EvidenceAlreadyReported.into());
+        }
+        reported.put(&evidence_hash, evidence);
+        Ok(equivocation)
+    }
+
     /// Initiates adding a new service instance to the blockchain. The created service is not active
     /// (i.e., does not process transactions or the `after_transactions` hook)
     /// until the block built on top of the provided `fork` is committed.
@@ -13,7 +13,7 @@
 // limitations under the License.
 
 use anyhow::{bail, ensure, format_err};
-use exonum_crypto::{Hash, KeyPair, PublicKey, SecretKey, HASH_SIZE};
+use exonum_crypto::{schemes::AnyKeyPair, Hash, KeyPair, PublicKey, SecretKey, HASH_SIZE};
 use exonum_derive::{BinaryValue, ObjectHash};
 use exonum_merkledb::{
     impl_binary_key_for_binary_value,
@@ -128,6 +128,12 @@ impl AnyTx {
         Verified::from_value(self, keypair.public_key(), keypair.secret_key())
     }
 
+    /// Signs a transaction with the specified keypair, which may belong to any
+    /// supported signature scheme (e.g., secp256k1).
+    pub fn sign_with_any_keypair(self, keypair: &AnyKeyPair) -> Verified<Self> {
+        Verified::sign_with(self, keypair)
+    }
+
     /// Parse transaction arguments as a specific type.
     pub fn parse<T: BinaryValue>(&self) -> anyhow::Result<T> {
         T::from_bytes(Cow::Borrowed(&self.arguments))
@@ -126,7 +126,7 @@ impl ServiceApiState {
         Ok(Self {
             broadcaster: Broadcaster::new(
                 instance,
-                blockchain.service_keypair().clone(),
+                blockchain.service_keypair(),
                 blockchain.sender().clone(),
             ),
             snapshot,
@@ -857,13 +857,14 @@ impl Runtime for RustRuntime {
         }
 
         let blockchain = self.blockchain();
-        let validator_id = core_schema.validator_id(blockchain.service_keypair().public_key());
+        let service_keypair = blockchain.service_keypair();
+        let validator_id = core_schema.validator_id(service_keypair.public_key());
         for service in self.started_services.values() {
             service.as_ref().after_commit(AfterCommitContext::new(
                 mailbox,
                 service.descriptor(),
                 snapshot,
-                blockchain.service_keypair(),
+                &service_keypair,
                 blockchain.sender(),
                 validator_id,
             ));
@@ -18,7 +18,7 @@
 //! and the `explanation` module below for an explanation how stubs work.
 
 use exonum::{
-    crypto::{KeyPair, PublicKey, SecretKey},
+    crypto::{schemes::AnyKeyPair, KeyPair, PublicKey, SecretKey},
     messages::Verified,
     runtime::{
         AnyTx, CallInfo, ExecutionContext, ExecutionContextUnstable, ExecutionError, InstanceId,
@@ -166,11 +166,28 @@ impl GenericCall<InstanceId> for KeyPair {
     }
 }
 
+impl GenericCall<InstanceId> for AnyKeyPair {
+    type Output = Verified<AnyTx>;
+
+    fn generic_call(
+        &self,
+        instance_id: InstanceId,
+        method: MethodDescriptor<'_>,
+        args: Vec<u8>,
+    ) -> Self::Output {
+        let tx = TxStub.generic_call(instance_id, method, args);
+        Verified::sign_with(tx, self)
+    }
+}
+
 #[cfg(test)]
 mod explanation {
     use super::*;
 
-    use exonum::{crypto::KeyPair, merkledb::BinaryValue};
+    use exonum::{
+        crypto::{schemes::SignatureScheme, KeyPair},
+        merkledb::BinaryValue,
+    };
     use pretty_assertions::assert_eq;
 
     // Suppose we have the following trait describing user service.
@@ -245,6 +262,12 @@ mod explanation {
         assert_eq!(tx.payload().call_info.method_id, 0);
         let other_tx = keypair.transfer(SERVICE_ID, Transfer::default());
         assert_eq!(other_tx.payload().call_info.method_id, 1);
+
+        // Transactions can be signed with keys of other signature schemes as well.
+        let keypair = AnyKeyPair::random(SignatureScheme::Secp256k1);
+        let tx = keypair.transfer(SERVICE_ID, Transfer::default());
+        assert_eq!(tx.payload().call_info.method_id, 1);
+        assert_eq!(tx.author(), keypair.public_key().key_id());
     }
 
     // It's also possible to define new stubs (not necessarily in this crate). For example,
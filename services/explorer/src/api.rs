@@ -223,8 +223,15 @@
 //!
 //! Returns call status of committed transaction.
 //!
+//! If `with_proof` is set, the status is returned together with a `CallProof`. Additionally
+//! specifying `proof_format=compact` encodes the Merkle proof of the call status
+//! in the compact binary format (see [`ProofFormat`]), which is recommended for light clients.
+//! The same query parameters are supported by the endpoints for `before_transactions`
+//! and `after_transactions` hooks.
+//!
 //! [`TransactionStatusQuery`]: struct.TransactionStatusQuery.html
 //! [`CallStatusResponse`]: enum.CallStatusResponse.html
+//! [`ProofFormat`]: enum.ProofFormat.html
 //!
 //! ```
 //! # use exonum::{
@@ -427,7 +434,7 @@ pub use exonum_explorer::{
     },
     api::{
         BlockInfo, BlockQuery, BlocksQuery, BlocksRange, CallStatusQuery, CallStatusResponse,
-        TransactionHex, TransactionQuery, TransactionResponse, TransactionStatusQuery,
+        ProofFormat, TransactionHex, TransactionQuery, TransactionResponse, TransactionStatusQuery,
         MAX_BLOCKS_PER_REQUEST,
     },
     TransactionInfo,
@@ -568,6 +575,7 @@ impl ExplorerApi {
         block_height: Height,
         call_in_block: CallInBlock,
         with_proof: bool,
+        proof_format: ProofFormat,
     ) -> api::Result<CallStatusResponse> {
         let records = schema.call_records(block_height).ok_or_else(|| {
             api::Error::not_found()
@@ -580,7 +588,10 @@ impl ExplorerApi {
 
         Ok(if with_proof {
             let proof = records.get_proof(call_in_block);
-            CallStatusResponse::Proof(proof)
+            match proof_format {
+                ProofFormat::Json => CallStatusResponse::Proof(proof),
+                ProofFormat::Compact => CallStatusResponse::CompactProof(proof.into()),
+            }
         } else {
             let status = ExecutionStatus(records.get(call_in_block));
             CallStatusResponse::Simple(status)
@@ -602,7 +613,13 @@ impl ExplorerApi {
 
         let call_in_block = CallInBlock::transaction(tx_location.position_in_block());
         let block_height = tx_location.block_height();
-        Self::get_status(schema, block_height, call_in_block, query.with_proof)
+        Self::get_status(
+            schema,
+            block_height,
+            call_in_block,
+            query.with_proof,
+            query.proof_format,
+        )
     }
 
     /// Returns call status of `before_transactions` hook.
@@ -611,7 +628,13 @@ impl ExplorerApi {
         query: &CallStatusQuery,
     ) -> api::Result<CallStatusResponse> {
         let call_in_block = CallInBlock::before_transactions(query.service_id);
-        Self::get_status(schema, query.height, call_in_block, query.with_proof)
+        Self::get_status(
+            schema,
+            query.height,
+            call_in_block,
+            query.with_proof,
+            query.proof_format,
+        )
     }
 
     /// Returns call status of `after_transactions` hook.
@@ -620,7 +643,13 @@ impl ExplorerApi {
         query: &CallStatusQuery,
     ) -> api::Result<CallStatusResponse> {
         let call_in_block = CallInBlock::after_transactions(query.service_id);
-        Self::get_status(schema, query.height, call_in_block, query.with_proof)
+        Self::get_status(
+            schema,
+            query.height,
+            call_in_block,
+            query.with_proof,
+            query.proof_format,
+        )
     }
 
     fn add_transaction(
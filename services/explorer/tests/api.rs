@@ -14,7 +14,7 @@
 
 use assert_matches::assert_matches;
 use exonum::{
-    blockchain::{AdditionalHeaders, CallInBlock, Epoch, ProposerId},
+    blockchain::{AdditionalHeaders, CallInBlock, CallProof, Epoch, ProposerId},
     crypto::{Hash, KeyPair},
     helpers::{Height, ValidatorId},
     merkledb::{BinaryValue, HashTag, ObjectHash},
@@ -689,3 +689,57 @@ async fn test_explorer_api_with_after_transactions_error() {
         .description()
         .contains("What's the question?"));
 }
+
+#[tokio::test]
+async fn test_explorer_api_with_compact_proof() {
+    let (mut testkit, api) = init_testkit();
+    let tx = KeyPair::random().increment(SERVICE_ID, 0);
+    testkit.create_block_with_transaction(tx.clone());
+
+    let response: Value = api
+        .public(ApiKind::Explorer)
+        .query(&TransactionStatusQuery::new(tx.object_hash()).with_compact_proof())
+        .get("v1/call_status/transaction")
+        .await
+        .expect("Explorer Api unexpectedly failed");
+    // The map proof is encoded as a hex string.
+    assert!(response["call_proof"].is_string());
+
+    let response: CallStatusResponse = serde_json::from_value(response).unwrap();
+    let proof = match response {
+        CallStatusResponse::CompactProof(proof) => proof,
+        other => panic!("Unexpected proof format: {:?}", other),
+    };
+    let validator_keys = [testkit.us().public_keys().consensus_key];
+    let (call, res) = CallProof::from(proof).verify(&validator_keys).unwrap();
+    assert_eq!(call, CallInBlock::transaction(0));
+    let execution_error = res.unwrap_err();
+    assert!(execution_error
+        .description()
+        .contains("Adding zero does nothing!"));
+
+    // Compact format can be requested for hooks as well.
+    let response: CallStatusResponse = api
+        .public(ApiKind::Explorer)
+        .query(&CallStatusQuery::new(Height(1), SERVICE_ID).with_compact_proof())
+        .get("v1/call_status/after_transactions")
+        .await
+        .expect("Explorer Api unexpectedly failed");
+    let proof = match response {
+        CallStatusResponse::CompactProof(proof) => proof,
+        other => panic!("Unexpected proof format: {:?}", other),
+    };
+    let (_, res) = CallProof::from(proof).verify(&validator_keys).unwrap();
+    assert!(res.is_ok());
+
+    // `proof_format` is ignored if the proof is not requested.
+    let response: ExecutionStatus = api
+        .public(ApiKind::Explorer)
+        .get(&format!(
+            "v1/call_status/transaction?hash={}&proof_format=compact",
+            tx.object_hash().to_hex()
+        ))
+        .await
+        .expect("Explorer Api unexpectedly failed");
+    assert!(response.0.is_err());
+}
@@ -13,6 +13,7 @@
 // limitations under the License.
 
 use exonum::{
+    blockchain::EquivocationEvidence,
     crypto::{Hash, PublicKey},
     helpers::{Height, ValidateInput},
     runtime::{
@@ -98,6 +99,15 @@ pub trait SupervisorInterface<Ctx> {
     /// completes.
     #[interface_method(id = 5)]
     fn report_migration_result(&self, context: Ctx, result: MigrationResult) -> Self::Output;
+
+    /// Records the evidence of equivocation by a validator in the blockchain.
+    ///
+    /// The transaction can be sent by anyone. The evidence is verified against
+    /// the consensus configuration active at the epoch of the conflicting messages;
+    /// the recorded evidence is available via the `reported_equivocations` index
+    /// of the core schema.
+    #[interface_method(id = 6)]
+    fn report_equivocation(&self, context: Ctx, evidence: EquivocationEvidence) -> Self::Output;
 }
 
 impl ConfigChange {
@@ -682,6 +692,14 @@ impl SupervisorInterface<ExecutionContext<'_>> for Supervisor {
             }
         }
     }
+
+    fn report_equivocation(
+        &self,
+        mut context: ExecutionContext<'_>,
+        evidence: EquivocationEvidence,
+    ) -> Self::Output {
+        context.report_equivocation(evidence).map(drop)
+    }
 }
 
 impl Supervisor {
@@ -12,11 +12,17 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use exonum::{helpers::ValidatorId, merkledb::ObjectHash};
+use exonum::{
+    blockchain::EquivocationEvidence,
+    crypto,
+    helpers::{Height, ValidatorId},
+    merkledb::ObjectHash,
+    runtime::{CoreError, ErrorMatch, SnapshotExt, SUPERVISOR_INSTANCE_ID},
+};
 use exonum_testkit::TestKitBuilder;
 
 use crate::utils::*;
-use exonum_supervisor::Supervisor;
+use exonum_supervisor::{Supervisor, SupervisorInterface};
 
 #[test]
 fn test_add_nodes_to_validators() {
@@ -152,3 +158,65 @@ fn test_change_our_validator_id() {
     assert_eq!(&testkit.network().validators()[1], testkit.network().us());
     assert_eq!(testkit.consensus_config(), new_consensus_config);
 }
+
+#[test]
+fn test_report_equivocation_of_excluded_validator() {
+    let mut testkit = testkit_with_supervisor(4);
+    let initiator_id = testkit.network().us().validator_id().unwrap();
+
+    let equivocating_node = testkit.validator(ValidatorId(1));
+    let evidence = EquivocationEvidence::new(
+        equivocating_node
+            .create_precommit(Height(1), crypto::hash(&[1]))
+            .into_raw(),
+        equivocating_node
+            .create_precommit(Height(1), crypto::hash(&[2]))
+            .into_raw(),
+    );
+
+    // Exclude the equivocating validator from the validator set.
+    let new_consensus_config = {
+        let mut cfg = testkit.consensus_config();
+        cfg.validator_keys.remove(1);
+        cfg
+    };
+    let config_proposal = ConfigProposeBuilder::new(CFG_CHANGE_HEIGHT)
+        .extend_consensus_config_propose(new_consensus_config.clone())
+        .build();
+    let proposal_hash = config_proposal.object_hash();
+    testkit.create_block_with_transaction(sign_config_propose_transaction(
+        &testkit,
+        config_proposal,
+        initiator_id,
+    ));
+    let signed_txs = build_confirmation_transactions(&testkit, proposal_hash, initiator_id);
+    testkit.create_block_with_transactions(signed_txs);
+    testkit.create_blocks_until(CFG_CHANGE_HEIGHT);
+    assert_eq!(testkit.consensus_config(), new_consensus_config);
+
+    // The evidence is still valid, since it is checked against the configuration
+    // active at the epoch of the conflicting messages.
+    let keys = testkit.us().service_keypair();
+    let tx = keys.report_equivocation(SUPERVISOR_INSTANCE_ID, evidence.clone());
+    let block = testkit.create_block_with_transaction(tx);
+    block.transactions[0]
+        .status()
+        .expect("Evidence should be accepted");
+
+    let snapshot = testkit.snapshot();
+    let reported = snapshot.for_core().reported_equivocations();
+    assert_eq!(
+        reported.get(&evidence.object_hash()),
+        Some(evidence.clone())
+    );
+
+    // The same evidence cannot be reported twice.
+    let keys = testkit.validator(ValidatorId(2)).service_keypair();
+    let tx = keys.report_equivocation(SUPERVISOR_INSTANCE_ID, evidence);
+    let block = testkit.create_block_with_transaction(tx);
+    let err = block.transactions[0].status().unwrap_err();
+    assert_eq!(
+        *err,
+        ErrorMatch::from_fail(&CoreError::EvidenceAlreadyReported)
+    );
+}
@@ -64,6 +64,7 @@ pub struct TestKitBuilder {
     our_validator_id: Option<ValidatorId>,
     test_network: Option<TestNetwork>,
     logger: bool,
+    bls_keys: bool,
     rust_runtime: RustRuntimeBuilder,
     api_notifier_channel: ApiNotifierChannel,
     additional_runtimes: Vec<RuntimeInstance>,
@@ -111,6 +112,14 @@ impl TestKitBuilder {
         self
     }
 
+    /// Enables BLS keys for the validators in the test network, so that created blocks
+    /// have commit certificates. BLS cryptography is relatively slow, especially
+    /// in debug builds; thus, the keys are disabled by default.
+    pub fn with_bls_keys(mut self) -> Self {
+        self.bls_keys = true;
+        self
+    }
+
     /// Adds a deploy spec to this builder. The spec may contain artifacts and service instances
     /// to deploy at the blockchain start.
     pub fn with(mut self, spec: impl Deploy) -> Self {
@@ -162,9 +171,12 @@ impl TestKitBuilder {
         crypto::init();
 
         let our_validator_id = self.our_validator_id;
-        let network = self
+        let mut network = self
             .test_network
             .unwrap_or_else(|| TestNetwork::with_our_role(our_validator_id, 1));
+        if self.bls_keys {
+            network = network.with_bls_keys();
+        }
 
         let rust_runtime = self.rust_runtime.build(self.api_notifier_channel.0.clone());
         self.additional_runtimes.push(rust_runtime.into());
@@ -216,6 +228,7 @@ impl TestKitBuilder {
             test_network: None,
             our_validator_id: validator_id,
             logger: false,
+            bls_keys: false,
             rust_runtime: RustRuntimeBuilder::new(),
             api_notifier_channel,
             additional_runtimes: vec![],
@@ -12,7 +12,9 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use exonum_merkledb::{Database, DatabaseExt, Patch, Result as StorageResult, Snapshot};
+use exonum_merkledb::{
+    ColumnFamilyStats, Database, DatabaseExt, Patch, Result as StorageResult, Snapshot,
+};
 
 use std::{
     fmt,
@@ -80,6 +82,14 @@ impl<T: Database> Database for CheckpointDb<T> {
     fn merge_sync(&self, patch: Patch) -> StorageResult<()> {
         self.merge(patch)
     }
+
+    fn column_family_stats(&self) -> Vec<ColumnFamilyStats> {
+        self.inner
+            .read()
+            .expect("Cannot lock CheckpointDb for statistics")
+            .db
+            .column_family_stats()
+    }
 }
 
 #[allow(clippy::use_self)] // false positive
@@ -15,7 +15,7 @@
 use serde::{Deserialize, Serialize};
 
 use exonum::{
-    blockchain::{ConsensusConfig, ValidatorKeys},
+    blockchain::{CommitCertificate, ConsensusConfig, ValidatorBlsKey, ValidatorKeys},
     crypto::{self, Hash, KeyPair, PublicKey},
     helpers::{Height, Round, ValidatorId},
     keys::Keys,
@@ -54,6 +54,7 @@ impl TestNetwork {
             .map(|(n, keys)| TestNode {
                 keys,
                 validator_id: Some(ValidatorId(n as u16)),
+                uses_bls: false,
             })
             .collect::<Vec<_>>();
 
@@ -73,6 +74,17 @@ impl TestNetwork {
         Self { nodes, us }
     }
 
+    /// Enables BLS keys for all nodes in the network. Validators will include their BLS keys
+    /// into the consensus configuration and sign precommits with them, so that commit
+    /// certificates are created for blocks.
+    pub fn with_bls_keys(mut self) -> Self {
+        for node in &mut self.nodes {
+            node.uses_bls = true;
+        }
+        self.us.uses_bls = true;
+        self
+    }
+
     /// Adds a new auditor node to this network.
     pub fn add_node(&mut self) -> &TestNode {
         self.nodes.push(TestNode::new_auditor());
@@ -173,6 +185,8 @@ pub struct TestNode {
     keys: Keys,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     validator_id: Option<ValidatorId>,
+    #[serde(default)]
+    uses_bls: bool,
 }
 
 impl TestNode {
@@ -181,6 +195,7 @@ impl TestNode {
         Self {
             keys: Keys::random(),
             validator_id: None,
+            uses_bls: false,
         }
     }
 
@@ -189,6 +204,7 @@ impl TestNode {
         Self {
             keys: Keys::random(),
             validator_id: Some(validator_id),
+            uses_bls: false,
         }
     }
 
@@ -201,9 +217,16 @@ impl TestNode {
         Self {
             keys: Keys::from_keys(consensus_keys, service_keys),
             validator_id,
+            uses_bls: false,
         }
     }
 
+    /// Enables the BLS key for this node. The key is derived from the consensus secret key.
+    pub fn with_bls_key(mut self) -> Self {
+        self.uses_bls = true;
+        self
+    }
+
     /// Creates a `Precommit` message signed by this validator.
     pub fn create_precommit(
         &self,
@@ -212,16 +235,21 @@ impl TestNode {
     ) -> Verified<Precommit> {
         use std::time::SystemTime;
 
+        let mut precommit = Precommit::new(
+            self.validator_id
+                .expect("An attempt to create propose from a non-validator node."),
+            height,
+            Round::first(),
+            Hash::zero(),
+            block_hash,
+            SystemTime::now().into(),
+        );
+        if self.uses_bls {
+            let message = CommitCertificate::signed_message(&block_hash);
+            precommit = precommit.with_bls_signature(self.keys.bls_keypair().sign(&message));
+        }
         Verified::from_value(
-            Precommit::new(
-                self.validator_id
-                    .expect("An attempt to create propose from a non-validator node."),
-                height,
-                Round::first(),
-                Hash::zero(),
-                block_hash,
-                SystemTime::now().into(),
-            ),
+            precommit,
             self.keys.consensus_pk(),
             self.keys.consensus_sk(),
         )
@@ -229,7 +257,12 @@ impl TestNode {
 
     /// Returns public keys of the node.
     pub fn public_keys(&self) -> ValidatorKeys {
-        ValidatorKeys::new(self.keys.consensus_pk(), self.keys.service_pk())
+        let keys = ValidatorKeys::new(self.keys.consensus_pk(), self.keys.service_pk());
+        if self.uses_bls {
+            keys.with_bls_key(ValidatorBlsKey::from_keypair(&self.keys.bls_keypair()))
+        } else {
+            keys
+        }
     }
 
     /// Returns the current validator id of node if it is validator of the test network.
@@ -13,7 +13,7 @@
 // limitations under the License.
 
 use exonum::{
-    blockchain::CallInBlock,
+    blockchain::{CallInBlock, Schema},
     crypto::{Hash, KeyPair, PublicKey},
     helpers::Height,
     messages::{AnyTx, Verified},
@@ -400,6 +400,42 @@ async fn test_explorer_single_block() {
     assert!(validators.len() >= testkit.majority_count());
 }
 
+#[tokio::test]
+async fn test_explorer_commit_certificate() {
+    let mut testkit = TestKitBuilder::validator()
+        .with_validators(4)
+        .with_bls_keys()
+        .with(Spec::new(CounterService).with_default_instance())
+        .build();
+
+    let tx = KeyPair::random().increment(SERVICE_ID, 5);
+    testkit.api().send(tx).await;
+    testkit.create_block();
+
+    let snapshot = testkit.snapshot();
+    let explorer = BlockchainExplorer::new(snapshot.as_ref());
+    // The genesis block is not endorsed by precommits, so it has no certificate.
+    assert!(explorer
+        .block(Height(0))
+        .unwrap()
+        .commit_certificate()
+        .is_none());
+
+    let block = explorer.block(Height(1)).unwrap();
+    let certificate = block.commit_certificate().unwrap();
+    assert_eq!(certificate.signers.len(), 4);
+
+    let validator_keys = testkit.consensus_config().validator_keys;
+    let block_proof = Schema::new(snapshot.as_ref())
+        .block_and_precommits(Height(1))
+        .unwrap();
+    assert_eq!(block_proof.commit_certificate, Some(certificate));
+    block_proof.verify_certificate(&validator_keys).unwrap();
+
+    let block_info = serde_json::to_value(&block).unwrap();
+    assert!(block_info.get("commit_certificate").is_some());
+}
+
 #[tokio::test]
 async fn submitting_incorrect_tx_via_sender() {
     exonum::helpers::init_logger().ok();